@@ -510,7 +510,7 @@ impl PluginObject for DarkIrc {
         let event_graph_ = Arc::clone(&self.event_graph);
         let registry = self.p2p.protocol_registry();
         registry
-            .register(SESSION_DEFAULT, move |channel, _| {
+            .register(SESSION_DEFAULT, "ProtocolEventGraph", 0, move |channel, _| {
                 let event_graph_ = event_graph_.clone();
                 async move { ProtocolEventGraph::init(event_graph_, channel).await.unwrap() }
             })
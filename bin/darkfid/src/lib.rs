@@ -127,6 +127,7 @@ impl Darkfid {
         net_settings: &Settings,
         minerd_endpoint: &Option<Url>,
         txs_batch_size: &Option<usize>,
+        dandelion: bool,
         ex: &ExecutorPtr,
     ) -> Result<DarkfidPtr> {
         info!(target: "darkfid::Darkfid::init", "Initializing a Darkfi daemon...");
@@ -134,7 +135,7 @@ impl Darkfid {
         let validator = Validator::new(sled_db, config).await?;
 
         // Initialize P2P network
-        let p2p_handler = DarkfidP2pHandler::init(net_settings, ex).await?;
+        let p2p_handler = DarkfidP2pHandler::init(net_settings, ex, dandelion).await?;
 
         // Grab blockchain network configured transactions batch size for garbage collection
         let txs_batch_size = match txs_batch_size {
@@ -28,7 +28,7 @@ use darkfi::{
     blockchain::BlockInfo,
     cli_desc,
     net::settings::SettingsOpt,
-    rpc::settings::RpcSettingsOpt,
+    rpc::{metrics::start_exporter, settings::RpcSettingsOpt},
     util::{
         encoding::base64,
         path::{expand_path, get_config_path},
@@ -139,6 +139,14 @@ pub struct BlockchainNetwork {
     /// Garbage collection task transactions batch size
     txs_batch_size: Option<usize>,
 
+    #[structopt(long)]
+    /// Propagate transactions using Dandelion++
+    dandelion: bool,
+
+    #[structopt(long)]
+    /// Optional Prometheus metrics listen URL
+    metrics_listen: Option<Url>,
+
     #[structopt(flatten)]
     /// P2P network settings
     net: SettingsOpt,
@@ -215,6 +223,7 @@ async fn realmain(args: Args, ex: Arc<smol::Executor<'static>>) -> Result<()> {
         &blockchain_config.net.into(),
         &blockchain_config.minerd_endpoint,
         &blockchain_config.txs_batch_size,
+        blockchain_config.dandelion,
         &ex,
     )
     .await?;
@@ -239,11 +248,22 @@ async fn realmain(args: Args, ex: Arc<smol::Executor<'static>>) -> Result<()> {
         )
         .await?;
 
+    // Start the metrics exporter, if enabled
+    let metrics_task = blockchain_config.metrics_listen.map(|listen| {
+        info!(target: "darkfid", "Starting metrics exporter");
+        start_exporter(listen, ex.clone())
+    });
+
     // Signal handling for graceful termination.
     let (signals_handler, signals_task) = SignalHandler::new(ex)?;
     signals_handler.wait_termination(signals_task).await?;
     info!(target: "darkfid", "Caught termination signal, cleaning up and exiting...");
 
+    if let Some(metrics_task) = metrics_task {
+        info!(target: "darkfid", "Stopping metrics exporter");
+        metrics_task.stop().await;
+    }
+
     daemon.stop().await?;
 
     info!(target: "darkfid", "Shut down successfully");
@@ -22,6 +22,7 @@ use darkfi::{
     net::{P2p, P2pPtr, Settings},
     rpc::jsonrpc::JsonSubscriber,
     system::ExecutorPtr,
+    tx::Transaction,
     validator::ValidatorPtr,
     Result,
 };
@@ -42,7 +43,7 @@ pub use protocol_sync::{
     SyncRequest, SyncResponse, TipRequest, TipResponse, BATCH,
 };
 
-/// Transaction broadcast protocol
+/// Transaction broadcast protocol, with optional Dandelion++ stem phase
 mod protocol_tx;
 pub use protocol_tx::{ProtocolTxHandler, ProtocolTxHandlerPtr};
 
@@ -65,8 +66,13 @@ impl DarkfidP2pHandler {
     /// Initialize a Darkfid P2P protocols handler.
     ///
     /// A new P2P instance is generated using provided settings and all
-    /// corresponding protocols are registered.
-    pub async fn init(settings: &Settings, executor: &ExecutorPtr) -> Result<DarkfidP2pHandlerPtr> {
+    /// corresponding protocols are registered. If `dandelion` is set,
+    /// transactions are propagated using Dandelion++.
+    pub async fn init(
+        settings: &Settings,
+        executor: &ExecutorPtr,
+        dandelion: bool,
+    ) -> Result<DarkfidP2pHandlerPtr> {
         info!(
             target: "darkfid::proto::mod::DarkfidP2pHandler::init",
             "Initializing a new Darkfid P2P handler..."
@@ -82,7 +88,7 @@ impl DarkfidP2pHandler {
         let sync = ProtocolSyncHandler::init(&p2p).await;
 
         // Generate a new `ProtocolTx` messages handler
-        let txs = ProtocolTxHandler::init(&p2p).await;
+        let txs = ProtocolTxHandler::init(&p2p, dandelion).await;
 
         info!(
             target: "darkfid::proto::mod::DarkfidP2pHandler::init",
@@ -127,6 +133,11 @@ impl DarkfidP2pHandler {
         Ok(())
     }
 
+    /// Propagate a transaction created by this node to the P2P network.
+    pub async fn broadcast_tx(&self, tx: &Transaction) {
+        self.txs.broadcast(&self.p2p, tx).await;
+    }
+
     /// Stop the Darkfid P2P protocols handler.
     pub async fn stop(&self) {
         info!(target: "darkfid::proto::mod::DarkfidP2pHandler::stop", "Terminating Darkfid P2P handler...");
@@ -16,27 +16,70 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
-use log::{debug, error};
+use log::{debug, error, info};
+use rand::{seq::SliceRandom, Rng};
+use smol::lock::Mutex;
 use tinyjson::JsonValue;
+use url::Url;
 
 use darkfi::{
+    error::TxVerifyFailed,
+    impl_p2p_message,
     net::{
         protocol::protocol_generic::{
             ProtocolGenericAction, ProtocolGenericHandler, ProtocolGenericHandlerPtr,
         },
-        session::SESSION_DEFAULT,
-        P2pPtr,
+        session::{SESSION_DEFAULT, SESSION_MANUAL, SESSION_OUTBOUND},
+        Message, P2pPtr,
     },
     rpc::jsonrpc::JsonSubscriber,
-    system::ExecutorPtr,
-    tx::Transaction,
-    util::encoding::base64,
+    system::{sleep, ExecutorPtr, StoppableTask, StoppableTaskPtr},
+    tx::{Transaction, TransactionHash},
+    util::{encoding::base64, time::Timestamp},
     validator::ValidatorPtr,
     Error, Result,
 };
-use darkfi_serial::serialize_async;
+use darkfi_serial::{serialize_async, SerialDecodable, SerialEncodable};
+
+/// Name of the Dandelion++ stem phase protocol
+const STEM_PROTOCOL: &str = "ProtocolStemTx";
+
+/// Version of the Dandelion++ stem phase protocol
+const STEM_PROTOCOL_VERSION: u32 = 1;
+
+/// Duration of a Dandelion++ epoch, in seconds. Each epoch the node
+/// picks its stem relays and whether it diffuses stem transactions.
+const DANDELION_EPOCH: u64 = 600;
+
+/// Probability, in percent, of the node diffusing (fluffing) the stem
+/// transactions it receives during an epoch.
+const DANDELION_FLUFF_PROBABILITY: u32 = 10;
+
+/// Number of outbound peers used as stem relays during an epoch
+const DANDELION_RELAYS: usize = 2;
+
+/// Minimum time, in seconds, a stem transaction is embargoed for,
+/// before we fluff it ourselves.
+const DANDELION_EMBARGO: u64 = 30;
+
+/// Maximum random time, in seconds, added to the embargo
+const DANDELION_EMBARGO_JITTER: u64 = 30;
+
+/// Maximum number of embargoed stem transactions. When full, the oldest
+/// ones are dropped, leaving their fluff phase to the rest of their path.
+const DANDELION_STEMPOOL_SIZE: usize = 1000;
+
+/// Structure representing a [`Transaction`] in the Dandelion++ stem
+/// phase, which gets relayed to a single peer instead of broadcasted.
+#[derive(Clone, Debug, SerialEncodable, SerialDecodable)]
+pub struct StemTx {
+    /// The stem phase transaction
+    pub tx: Transaction,
+}
+
+impl_p2p_message!(StemTx, "stemtx");
 
 /// Atomic pointer to the `ProtocolTx` handler.
 pub type ProtocolTxHandlerPtr = Arc<ProtocolTxHandler>;
@@ -45,12 +88,15 @@ pub type ProtocolTxHandlerPtr = Arc<ProtocolTxHandler>;
 pub struct ProtocolTxHandler {
     /// The generic handler for [`Transaction`] messages.
     handler: ProtocolGenericHandlerPtr<Transaction, Transaction>,
+    /// Dandelion++ stem phase handler, if enabled.
+    dandelion: Option<DandelionPtr>,
 }
 
 impl ProtocolTxHandler {
     /// Initialize a generic prototocol handler for [`Transaction`] messages
     /// and registers it to the provided P2P network, using the default session flag.
-    pub async fn init(p2p: &P2pPtr) -> ProtocolTxHandlerPtr {
+    /// If `dandelion` is set, the Dandelion++ stem phase protocol is registered too.
+    pub async fn init(p2p: &P2pPtr, dandelion: bool) -> ProtocolTxHandlerPtr {
         debug!(
             target: "darkfid::proto::protocol_tx::init",
             "Adding ProtocolTx to the protocol registry"
@@ -58,7 +104,9 @@ impl ProtocolTxHandler {
 
         let handler = ProtocolGenericHandler::new(p2p, "ProtocolTx", SESSION_DEFAULT).await;
 
-        Arc::new(Self { handler })
+        let dandelion = if dandelion { Some(Dandelion::init(p2p).await) } else { None };
+
+        Arc::new(Self { handler, dandelion })
     }
 
     /// Start the `ProtocolTx` background task.
@@ -73,8 +121,10 @@ impl ProtocolTxHandler {
             "Starting ProtocolTx handler task..."
         );
 
+        let dandelion = self.dandelion.clone();
+        let subscriber_ = subscriber.clone();
         self.handler.task.clone().start(
-            handle_receive_tx(self.handler.clone(), validator.clone(), subscriber),
+            handle_receive_tx(self.handler.clone(), dandelion, validator.clone(), subscriber_),
             |res| async move {
                 match res {
                     Ok(()) | Err(Error::DetachedTaskStopped) => { /* Do nothing */ }
@@ -85,6 +135,10 @@ impl ProtocolTxHandler {
             executor.clone(),
         );
 
+        if let Some(dandelion) = &self.dandelion {
+            dandelion.start(executor, validator, subscriber).await;
+        }
+
         debug!(
             target: "darkfid::proto::protocol_tx::start",
             "ProtocolTx handler task started!"
@@ -96,14 +150,31 @@ impl ProtocolTxHandler {
     /// Stop the `ProtocolTx` background task.
     pub async fn stop(&self) {
         debug!(target: "darkfid::proto::protocol_tx::stop", "Terminating ProtocolTx handler task...");
+        if let Some(dandelion) = &self.dandelion {
+            dandelion.stop().await;
+        }
         self.handler.task.stop().await;
         debug!(target: "darkfid::proto::protocol_tx::stop", "ProtocolTx handler task terminated!");
     }
+
+    /// Propagate a transaction created by this node. With Dandelion++
+    /// enabled, it always starts in the stem phase, otherwise it gets
+    /// broadcasted to all peers.
+    pub async fn broadcast(&self, p2p: &P2pPtr, tx: &Transaction) {
+        if let Some(dandelion) = &self.dandelion {
+            if dandelion.stem(None, tx).await {
+                return
+            }
+        }
+
+        p2p.broadcast(tx).await;
+    }
 }
 
 /// Background handler function for ProtocolTx.
 async fn handle_receive_tx(
     handler: ProtocolGenericHandlerPtr<Transaction, Transaction>,
+    dandelion: Option<DandelionPtr>,
     validator: ValidatorPtr,
     subscriber: JsonSubscriber,
 ) -> Result<()> {
@@ -121,6 +192,11 @@ async fn handle_receive_tx(
             }
         };
 
+        // The transaction reached its fluff phase, so lift its embargo
+        if let Some(dandelion) = &dandelion {
+            dandelion.state.lock().await.stempool.remove(&tx.hash());
+        }
+
         // Check if node has finished syncing its blockchain
         if !*validator.synced.read().await {
             debug!(
@@ -145,7 +221,504 @@ async fn handle_receive_tx(
         handler.send_action(channel, ProtocolGenericAction::Broadcast).await;
 
         // Notify subscriber
-        let encoded_tx = JsonValue::String(base64::encode(&serialize_async(&tx).await));
-        subscriber.notify(vec![encoded_tx].into()).await;
+        notify_tx(&subscriber, &tx).await;
+    }
+}
+
+/// Notify provided subscriber about a new transaction.
+async fn notify_tx(subscriber: &JsonSubscriber, tx: &Transaction) {
+    let encoded_tx = JsonValue::String(base64::encode(&serialize_async(tx).await));
+    subscriber.notify(vec![encoded_tx].into()).await;
+}
+
+/// Atomic pointer to the Dandelion++ stem phase handler.
+type DandelionPtr = Arc<Dandelion>;
+
+/// Dandelion++ stem phase handler.
+///
+/// Instead of being broadcasted right away, new transactions are first
+/// relayed along a random path of outbound peers (stem phase), until a
+/// node diffuses them to the whole network (fluff phase), which makes it
+/// hard to link a transaction to the node that created it. Each stem
+/// transaction is embargoed, so if it doesn't reach its fluff phase in
+/// time, e.g. due to a misbehaving relay, we fluff it ourselves.
+struct Dandelion {
+    /// P2P network pointer
+    p2p: P2pPtr,
+    /// The generic handler for [`StemTx`] messages
+    handler: ProtocolGenericHandlerPtr<StemTx, StemTx>,
+    /// Current epoch state and stem transactions
+    state: Mutex<DandelionState>,
+    /// Background task fluffing the embargoed transactions
+    embargo_task: StoppableTaskPtr,
+}
+
+impl Dandelion {
+    /// Initialize the Dandelion++ handler and register its stem phase
+    /// protocol to the provided P2P network.
+    async fn init(p2p: &P2pPtr) -> DandelionPtr {
+        debug!(
+            target: "darkfid::proto::protocol_tx::Dandelion::init",
+            "Adding {STEM_PROTOCOL} to the protocol registry"
+        );
+
+        let handler = ProtocolGenericHandler::new_with_version(
+            p2p,
+            STEM_PROTOCOL,
+            STEM_PROTOCOL_VERSION,
+            SESSION_DEFAULT,
+        )
+        .await;
+
+        Arc::new(Self {
+            p2p: p2p.clone(),
+            handler,
+            state: Mutex::new(DandelionState::default()),
+            embargo_task: StoppableTask::new(),
+        })
+    }
+
+    /// Start the stem phase and embargo background tasks.
+    async fn start(
+        self: &Arc<Self>,
+        executor: &ExecutorPtr,
+        validator: &ValidatorPtr,
+        subscriber: JsonSubscriber,
+    ) {
+        self.handler.task.clone().start(
+            handle_receive_stem_tx(self.clone(), validator.clone(), subscriber.clone()),
+            |res| async move {
+                match res {
+                    Ok(()) | Err(Error::DetachedTaskStopped) => { /* Do nothing */ }
+                    Err(e) => error!(target: "darkfid::proto::protocol_tx::Dandelion::start", "Failed starting {STEM_PROTOCOL} handler task: {e}"),
+                }
+            },
+            Error::DetachedTaskStopped,
+            executor.clone(),
+        );
+
+        self.embargo_task.clone().start(
+            embargo_task(self.clone(), validator.clone(), subscriber),
+            |res| async move {
+                match res {
+                    Ok(()) | Err(Error::DetachedTaskStopped) => { /* Do nothing */ }
+                    Err(e) => error!(target: "darkfid::proto::protocol_tx::Dandelion::start", "Failed starting embargo task: {e}"),
+                }
+            },
+            Error::DetachedTaskStopped,
+            executor.clone(),
+        );
+    }
+
+    /// Stop the stem phase and embargo background tasks.
+    async fn stop(&self) {
+        self.embargo_task.stop().await;
+        self.handler.task.stop().await;
+    }
+
+    /// Relay provided transaction to the stem relay of its source channel,
+    /// or to our own one for locally created transactions (`source` is `None`).
+    /// Returns `false` if the transaction must be fluffed instead, either
+    /// because we are a diffuser for this epoch or we have no stem relays.
+    async fn stem(&self, source: Option<u32>, tx: &Transaction) -> bool {
+        let now = Timestamp::current_time().inner();
+        let mut state = self.state.lock().await;
+
+        let relay = {
+            let mut rng = rand::thread_rng();
+            state.refresh(now, stem_peers(&self.p2p), &mut rng);
+
+            // Locally created transactions always start in the stem phase
+            if source.is_some() && state.fluff {
+                return false
+            }
+
+            let Some(relay) = state.relay_for(source, &mut rng) else { return false };
+            relay
+        };
+        let Some(channel) = self.p2p.hosts().get_channel(relay) else { return false };
+
+        state.embargo(tx, now, &mut rand::thread_rng());
+        drop(state);
+
+        debug!(
+            target: "darkfid::proto::protocol_tx::Dandelion::stem",
+            "Relaying stem tx {} to {}", tx.hash(), channel.address(),
+        );
+
+        if let Err(e) = channel.send(&StemTx { tx: tx.clone() }).await {
+            debug!(
+                target: "darkfid::proto::protocol_tx::Dandelion::stem",
+                "Failed relaying stem tx to {}: {e}", channel.address(),
+            );
+            self.state.lock().await.stempool.remove(&tx.hash());
+            return false
+        }
+
+        true
+    }
+}
+
+/// Dandelion++ epoch state.
+#[derive(Default)]
+struct DandelionState {
+    /// Start of the current epoch, as a UNIX timestamp
+    epoch: u64,
+    /// Flag indicating we fluff received stem transactions this epoch
+    fluff: bool,
+    /// Channel IDs of the stem relays of the current epoch
+    relays: Vec<u32>,
+    /// Stem relay channel ID, by the source channel ID it serves
+    routes: HashMap<u32, u32>,
+    /// Stem transactions with their embargo expiry timestamp and
+    /// insertion sequence number
+    stempool: HashMap<TransactionHash, (Transaction, u64, u64)>,
+    /// Sequence number of the next stem transaction
+    stem_seq: u64,
+}
+
+impl DandelionState {
+    /// Start a new epoch if the current one is over, and replace the
+    /// stem relays that are no longer connected using provided peers.
+    fn refresh<R: Rng>(&mut self, now: u64, peers: Vec<u32>, rng: &mut R) {
+        if now >= self.epoch + DANDELION_EPOCH {
+            self.epoch = now;
+            self.fluff = rng.gen_range(0..100) < DANDELION_FLUFF_PROBABILITY;
+            self.relays.clear();
+            self.routes.clear();
+            debug!(
+                target: "darkfid::proto::protocol_tx::DandelionState::refresh",
+                "New Dandelion++ epoch, fluff: {}", self.fluff,
+            );
+        }
+
+        self.relays.retain(|id| peers.contains(id));
+
+        let mut candidates: Vec<u32> =
+            peers.into_iter().filter(|id| !self.relays.contains(id)).collect();
+        candidates.shuffle(rng);
+        while self.relays.len() < DANDELION_RELAYS {
+            let Some(id) = candidates.pop() else { break };
+            self.relays.push(id);
+        }
+    }
+
+    /// Return the stem relay for provided source channel, assigning it a
+    /// random one if it doesn't have one yet. Locally created transactions
+    /// always use the first relay.
+    fn relay_for<R: Rng>(&mut self, source: Option<u32>, rng: &mut R) -> Option<u32> {
+        if self.relays.is_empty() {
+            return None
+        }
+
+        let Some(source) = source else { return Some(self.relays[0]) };
+
+        if let Some(relay) = self.routes.get(&source) {
+            if self.relays.contains(relay) {
+                return Some(*relay)
+            }
+        }
+
+        let relay = *self.relays.choose(rng).unwrap();
+        self.routes.insert(source, relay);
+        Some(relay)
+    }
+
+    /// Embargo provided stem transaction for a random duration, dropping
+    /// the oldest embargoed transactions if the stempool is full.
+    fn embargo<R: Rng>(&mut self, tx: &Transaction, now: u64, rng: &mut R) {
+        let hash = tx.hash();
+        while !self.stempool.contains_key(&hash) && self.stempool.len() >= DANDELION_STEMPOOL_SIZE {
+            let oldest = *self.stempool.iter().min_by_key(|(_, (_, _, seq))| *seq).unwrap().0;
+            debug!(
+                target: "darkfid::proto::protocol_tx::DandelionState::embargo",
+                "Stempool is full, dropping stem tx {oldest}",
+            );
+            self.stempool.remove(&oldest);
+        }
+
+        let embargo = DANDELION_EMBARGO + rng.gen_range(0..=DANDELION_EMBARGO_JITTER);
+        self.stempool.insert(hash, (tx.clone(), now + embargo, self.stem_seq));
+        self.stem_seq += 1;
+    }
+
+    /// Remove and return the stem transactions whose embargo expired.
+    fn expired(&mut self, now: u64) -> Vec<Transaction> {
+        let expired: Vec<TransactionHash> = self
+            .stempool
+            .iter()
+            .filter(|(_, (_, expiry, _))| *expiry <= now)
+            .map(|(hash, _)| *hash)
+            .collect();
+
+        expired.iter().filter_map(|hash| self.stempool.remove(hash)).map(|(tx, _, _)| tx).collect()
+    }
+}
+
+/// Return the channel IDs of the outbound peers supporting the stem phase protocol.
+fn stem_peers(p2p: &P2pPtr) -> Vec<u32> {
+    p2p.hosts()
+        .peers()
+        .iter()
+        .filter(|c| c.session_type_id() & (SESSION_OUTBOUND | SESSION_MANUAL) != 0)
+        .filter(|c| c.supports_feature(STEM_PROTOCOL, STEM_PROTOCOL_VERSION))
+        .map(|c| c.info.id)
+        .collect()
+}
+
+/// Add provided transaction to our pending txs store, broadcast it to all
+/// peers except the excluded ones and notify the subscriber.
+/// Transactions we have already seen are only broadcasted.
+async fn fluff(
+    dandelion: &Dandelion,
+    validator: &ValidatorPtr,
+    subscriber: &JsonSubscriber,
+    tx: &Transaction,
+    exclude_list: &[Url],
+) {
+    match validator.append_tx(tx, true).await {
+        Ok(()) => notify_tx(subscriber, tx).await,
+        // Our own transactions are already in the pending txs store
+        Err(Error::TxVerifyFailed(TxVerifyFailed::AlreadySeenTx(_))) => {}
+        Err(e) => {
+            debug!(
+                target: "darkfid::proto::protocol_tx::fluff",
+                "append_tx fail: {e}"
+            );
+            return
+        }
+    }
+
+    debug!(target: "darkfid::proto::protocol_tx::fluff", "Fluffing tx {}", tx.hash());
+    dandelion.p2p.broadcast_with_exclude(tx, exclude_list).await;
+}
+
+/// Background handler function for the Dandelion++ stem phase protocol.
+async fn handle_receive_stem_tx(
+    dandelion: DandelionPtr,
+    validator: ValidatorPtr,
+    subscriber: JsonSubscriber,
+) -> Result<()> {
+    debug!(target: "darkfid::proto::protocol_tx::handle_receive_stem_tx", "START");
+    loop {
+        // Wait for a new stem transaction message
+        let (channel, msg) = match dandelion.handler.receiver.recv().await {
+            Ok(r) => r,
+            Err(e) => {
+                debug!(
+                    target: "darkfid::proto::protocol_tx::handle_receive_stem_tx",
+                    "recv fail: {e}"
+                );
+                continue
+            }
+        };
+
+        // Stem transactions are never broadcasted by the generic handler
+        dandelion.handler.send_action(channel, ProtocolGenericAction::Skip).await;
+
+        // Check if node has finished syncing its blockchain
+        if !*validator.synced.read().await {
+            debug!(
+                target: "darkfid::proto::protocol_tx::handle_receive_stem_tx",
+                "Node still syncing blockchain, skipping..."
+            );
+            continue
+        }
+
+        // Skip transactions we are already relaying, as the path looped
+        if dandelion.state.lock().await.stempool.contains_key(&msg.tx.hash()) {
+            continue
+        }
+
+        // Only verify the transaction, as it gets appended to our pending
+        // txs store when we see it in its fluff phase.
+        if let Err(e) = validator.append_tx(&msg.tx, false).await {
+            debug!(
+                target: "darkfid::proto::protocol_tx::handle_receive_stem_tx",
+                "append_tx fail: {e}"
+            );
+            continue
+        }
+
+        if dandelion.stem(Some(channel), &msg.tx).await {
+            continue
+        }
+
+        // We are a diffuser for this epoch, so start the fluff phase
+        let exclude_list = match dandelion.p2p.hosts().get_channel(channel) {
+            Some(c) => vec![c.address().clone()],
+            None => vec![],
+        };
+        fluff(&dandelion, &validator, &subscriber, &msg.tx, &exclude_list).await;
+    }
+}
+
+/// Background task fluffing stem transactions whose embargo expired,
+/// as they didn't reach their fluff phase in time.
+async fn embargo_task(
+    dandelion: DandelionPtr,
+    validator: ValidatorPtr,
+    subscriber: JsonSubscriber,
+) -> Result<()> {
+    debug!(target: "darkfid::proto::protocol_tx::embargo_task", "START");
+    loop {
+        sleep(1).await;
+
+        let now = Timestamp::current_time().inner();
+        let expired = dandelion.state.lock().await.expired(now);
+
+        for tx in expired {
+            info!(
+                target: "darkfid::proto::protocol_tx::embargo_task",
+                "Embargo expired for stem tx {}, fluffing it", tx.hash(),
+            );
+            fluff(&dandelion, &validator, &subscriber, &tx, &[]).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    /// Create a distinct transaction for provided index.
+    fn test_tx(i: u32) -> Transaction {
+        Transaction { expiry: Some(i), ..Default::default() }
+    }
+
+    #[test]
+    fn dandelion_epoch_rotation() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut state = DandelionState::default();
+
+        let now = DANDELION_EPOCH;
+        state.refresh(now, vec![1, 2, 3, 4], &mut rng);
+        assert_eq!(state.epoch, now);
+        assert_eq!(state.relays.len(), DANDELION_RELAYS);
+        let relay = state.relay_for(Some(7), &mut rng).unwrap();
+        assert!(state.relays.contains(&relay));
+        assert_eq!(state.relay_for(None, &mut rng), Some(state.relays[0]));
+
+        // Within the epoch, relays and routes are kept
+        let relays = state.relays.clone();
+        state.refresh(now + DANDELION_EPOCH - 1, vec![1, 2, 3, 4], &mut rng);
+        assert_eq!(state.epoch, now);
+        assert_eq!(state.relays, relays);
+        assert_eq!(state.relay_for(Some(7), &mut rng), Some(relay));
+
+        // Disconnected relays get replaced
+        let gone = relays[0];
+        let peers: Vec<u32> = [1, 2, 3, 4].into_iter().filter(|id| *id != gone).collect();
+        state.refresh(now + DANDELION_EPOCH - 1, peers.clone(), &mut rng);
+        assert_eq!(state.relays.len(), DANDELION_RELAYS);
+        assert!(!state.relays.contains(&gone));
+        assert!(state.relays.iter().all(|id| peers.contains(id)));
+
+        // A new epoch starts once the current one is over
+        state.routes.insert(8, state.relays[0]);
+        state.refresh(now + DANDELION_EPOCH, vec![5, 6], &mut rng);
+        assert_eq!(state.epoch, now + DANDELION_EPOCH);
+        assert!(state.routes.is_empty());
+        state.relays.sort();
+        assert_eq!(state.relays, vec![5, 6]);
+
+        // Without peers we have no relays
+        state.refresh(now + 2 * DANDELION_EPOCH, vec![], &mut rng);
+        assert!(state.relays.is_empty());
+        assert_eq!(state.relay_for(None, &mut rng), None);
+    }
+
+    #[test]
+    fn dandelion_fluff_probability() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut state = DandelionState::default();
+
+        let epochs = 10000;
+        let mut fluffs: u64 = 0;
+        for i in 1..=epochs {
+            state.refresh(i * DANDELION_EPOCH, vec![1, 2], &mut rng);
+            if state.fluff {
+                fluffs += 1;
+            }
+        }
+
+        // Expect DANDELION_FLUFF_PROBABILITY percent of epochs to fluff,
+        // within a margin far wider than the deviation over 10000 epochs.
+        let expected = epochs * DANDELION_FLUFF_PROBABILITY as u64 / 100;
+        assert!(fluffs.abs_diff(expected) < expected / 5, "{fluffs} fluffs, expected {expected}");
+
+        // The same seed yields the same epochs
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut replay = DandelionState::default();
+        let mut replay_fluffs = 0;
+        for i in 1..=epochs {
+            replay.refresh(i * DANDELION_EPOCH, vec![1, 2], &mut rng);
+            if replay.fluff {
+                replay_fluffs += 1;
+            }
+        }
+        assert_eq!(fluffs, replay_fluffs);
+    }
+
+    #[test]
+    fn dandelion_embargo_expiry() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut state = DandelionState::default();
+
+        let now = 1000;
+        let tx = test_tx(0);
+        state.embargo(&tx, now, &mut rng);
+        let expiry = state.stempool[&tx.hash()].1;
+        assert!(expiry >= now + DANDELION_EMBARGO);
+        assert!(expiry <= now + DANDELION_EMBARGO + DANDELION_EMBARGO_JITTER);
+
+        // Nothing is fluffed before the embargo expires
+        assert!(state.expired(now).is_empty());
+        assert!(state.expired(expiry - 1).is_empty());
+        assert!(state.stempool.contains_key(&tx.hash()));
+
+        // Once it expires, the stem transaction is returned to be fluffed
+        // and removed from the stempool, so it is fluffed only once.
+        assert_eq!(state.expired(expiry), vec![tx.clone()]);
+        assert!(state.stempool.is_empty());
+        assert!(state.expired(expiry + DANDELION_EPOCH).is_empty());
+
+        // Only the expired transactions are returned
+        let late = test_tx(1);
+        state.embargo(&tx, now, &mut rng);
+        state.embargo(&late, now + DANDELION_EMBARGO + DANDELION_EMBARGO_JITTER + 1, &mut rng);
+        let expired = state.expired(now + DANDELION_EMBARGO + DANDELION_EMBARGO_JITTER);
+        assert_eq!(expired, vec![tx]);
+        assert!(state.stempool.contains_key(&late.hash()));
+    }
+
+    #[test]
+    fn dandelion_stempool_size() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut state = DandelionState::default();
+
+        let size = DANDELION_STEMPOOL_SIZE as u32;
+        for i in 0..size {
+            state.embargo(&test_tx(i), 0, &mut rng);
+        }
+        assert_eq!(state.stempool.len(), DANDELION_STEMPOOL_SIZE);
+
+        // Re-embargoing a known transaction doesn't drop anything
+        state.embargo(&test_tx(1), 0, &mut rng);
+        assert_eq!(state.stempool.len(), DANDELION_STEMPOOL_SIZE);
+        assert!(state.stempool.contains_key(&test_tx(0).hash()));
+
+        // New transactions drop the oldest ones
+        state.embargo(&test_tx(size), 0, &mut rng);
+        state.embargo(&test_tx(size + 1), 0, &mut rng);
+        assert_eq!(state.stempool.len(), DANDELION_STEMPOOL_SIZE);
+        assert!(!state.stempool.contains_key(&test_tx(0).hash()));
+        assert!(!state.stempool.contains_key(&test_tx(2).hash()));
+        assert!(state.stempool.contains_key(&test_tx(1).hash()));
+        assert!(state.stempool.contains_key(&test_tx(3).hash()));
+        assert!(state.stempool.contains_key(&test_tx(size).hash()));
+        assert!(state.stempool.contains_key(&test_tx(size + 1).hash()));
     }
 }
@@ -29,7 +29,8 @@ use darkfi::{
     rpc::{
         client::RpcChadClient,
         jsonrpc::{ErrorCode, JsonError, JsonRequest, JsonResponse, JsonResult},
-        p2p_method::HandlerP2p,
+        openrpc::{RpcMethod, RpcParam, PING_METHOD},
+        p2p_method::{HandlerP2p, P2P_GET_INFO_METHOD},
         server::RequestHandler,
     },
     system::{sleep, ExecutorPtr, StoppableTaskPtr},
@@ -67,6 +68,131 @@ impl MinerRpcClient {
     }
 }
 
+/// Methods served over JSON-RPC, as advertised by `rpc.discover`
+const RPC_METHODS: &[RpcMethod] = &[
+    PING_METHOD,
+    RpcMethod {
+        name: "clock",
+        summary: "Returns current system clock as `u64` (String) timestamp",
+        params: &[],
+        result: "string",
+    },
+    RpcMethod {
+        name: "ping_miner",
+        summary: "Pings configured miner daemon for liveness",
+        params: &[],
+        result: "boolean",
+    },
+    RpcMethod {
+        name: "dnet.switch",
+        summary: "Activate or deactivate dnet in the P2P stack",
+        params: &[RpcParam { name: "enable", schema: "boolean" }],
+        result: "boolean",
+    },
+    RpcMethod {
+        name: "dnet.subscribe_events",
+        summary: "Subscribe to P2P dnet events",
+        params: &[],
+        result: "object",
+    },
+    P2P_GET_INFO_METHOD,
+    RpcMethod {
+        name: "blockchain.get_block",
+        summary: "Returns the base64-encoded block in the given height",
+        params: &[RpcParam { name: "height", schema: "string" }],
+        result: "string",
+    },
+    RpcMethod {
+        name: "blockchain.get_tx",
+        summary: "Returns the base64-encoded transaction with the given hash",
+        params: &[RpcParam { name: "tx_hash", schema: "string" }],
+        result: "string",
+    },
+    RpcMethod {
+        name: "blockchain.last_confirmed_block",
+        summary: "Returns the height and header hash of the last confirmed block",
+        params: &[],
+        result: "array",
+    },
+    RpcMethod {
+        name: "blockchain.best_fork_next_block_height",
+        summary: "Returns the current best fork next block height",
+        params: &[],
+        result: "number",
+    },
+    RpcMethod {
+        name: "blockchain.block_target",
+        summary: "Returns the currently configured block target time",
+        params: &[],
+        result: "number",
+    },
+    RpcMethod {
+        name: "blockchain.lookup_zkas",
+        summary: "Returns the zkas bincodes of the given contract, with their namespace",
+        params: &[RpcParam { name: "contract_id", schema: "string" }],
+        result: "array",
+    },
+    RpcMethod {
+        name: "blockchain.subscribe_blocks",
+        summary: "Subscribe to new incoming blocks",
+        params: &[],
+        result: "object",
+    },
+    RpcMethod {
+        name: "blockchain.subscribe_txs",
+        summary: "Subscribe to new incoming transactions",
+        params: &[],
+        result: "object",
+    },
+    RpcMethod {
+        name: "blockchain.subscribe_proposals",
+        summary: "Subscribe to new incoming proposals",
+        params: &[],
+        result: "object",
+    },
+    RpcMethod {
+        name: "tx.simulate",
+        summary: "Simulate a network state transition with the given transaction",
+        params: &[RpcParam { name: "tx", schema: "string" }],
+        result: "boolean",
+    },
+    RpcMethod {
+        name: "tx.broadcast",
+        summary: "Broadcast the given transaction to the P2P network",
+        params: &[RpcParam { name: "tx", schema: "string" }],
+        result: "string",
+    },
+    RpcMethod {
+        name: "tx.pending",
+        summary: "Returns the hashes of all pending transactions",
+        params: &[],
+        result: "array",
+    },
+    RpcMethod {
+        name: "tx.clean_pending",
+        summary: "Removes all pending transactions, returning their hashes",
+        params: &[],
+        result: "array",
+    },
+    RpcMethod {
+        name: "tx.calculate_fee",
+        summary: "Compute the total gas of the given transaction against the best fork",
+        params: &[
+            RpcParam { name: "tx", schema: "string" },
+            RpcParam { name: "include_fee", schema: "boolean" },
+        ],
+        result: "number",
+    },
+];
+
+/// Methods served over the merge mining JSON-RPC, as advertised by `rpc.discover`
+const MM_RPC_METHODS: &[RpcMethod] = &[RpcMethod {
+    name: "merge_mining_get_chain_id",
+    summary: "Returns the unique ID identifying this merge mined chain",
+    params: &[],
+    result: "object",
+}];
+
 #[async_trait]
 #[rustfmt::skip]
 impl RequestHandler<DefaultRpcHandler> for DarkfiNode {
@@ -116,6 +242,10 @@ impl RequestHandler<DefaultRpcHandler> for DarkfiNode {
     async fn connections_mut(&self) -> MutexGuard<'life0, HashSet<StoppableTaskPtr>> {
         self.rpc_connections.lock().await
     }
+
+    fn methods(&self) -> Vec<RpcMethod> {
+        RPC_METHODS.to_vec()
+    }
 }
 
 #[async_trait]
@@ -140,6 +270,10 @@ impl RequestHandler<MmRpcHandler> for DarkfiNode {
     async fn connections_mut(&self) -> MutexGuard<'life0, HashSet<StoppableTaskPtr>> {
         self.mm_rpc_connections.lock().await
     }
+
+    fn methods(&self) -> Vec<RpcMethod> {
+        MM_RPC_METHODS.to_vec()
+    }
 }
 
 impl DarkfiNode {
@@ -16,14 +16,17 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
-use darkfi_serial::deserialize_async;
+use darkfi_serial::deserialize_async_limited;
 use log::{error, warn};
 use tinyjson::JsonValue;
 
 use darkfi::{
-    rpc::jsonrpc::{
-        ErrorCode::{InternalError, InvalidParams},
-        JsonError, JsonResponse, JsonResult,
+    rpc::{
+        jsonrpc::{
+            ErrorCode::{InternalError, InvalidParams},
+            JsonError, JsonResponse, JsonResult,
+        },
+        util::RPC_DECODE_LIMITS,
     },
     tx::Transaction,
     util::encoding::base64,
@@ -61,7 +64,7 @@ impl DarkfiNode {
             }
         };
 
-        let tx: Transaction = match deserialize_async(&tx_bytes).await {
+        let tx: Transaction = match deserialize_async_limited(&tx_bytes, &RPC_DECODE_LIMITS).await {
             Ok(v) => v,
             Err(e) => {
                 error!(target: "darkfid::rpc::tx_simulate", "Failed deserializing bytes into Transaction: {}", e);
@@ -111,7 +114,7 @@ impl DarkfiNode {
             }
         };
 
-        let tx: Transaction = match deserialize_async(&tx_bytes).await {
+        let tx: Transaction = match deserialize_async_limited(&tx_bytes, &RPC_DECODE_LIMITS).await {
             Ok(v) => v,
             Err(e) => {
                 error!(target: "darkfid::rpc::tx_broadcast", "Failed deserializing bytes into Transaction: {}", e);
@@ -133,7 +136,7 @@ impl DarkfiNode {
             return server_error(RpcError::TxSimulationFail, id, None)
         };
 
-        self.p2p_handler.p2p.broadcast(&tx).await;
+        self.p2p_handler.broadcast_tx(&tx).await;
         if !self.p2p_handler.p2p.is_connected() {
             warn!(target: "darkfid::rpc::tx_broadcast", "No connected channels to broadcast tx");
         }
@@ -237,7 +240,7 @@ impl DarkfiNode {
             }
         };
 
-        let tx: Transaction = match deserialize_async(&tx_bytes).await {
+        let tx: Transaction = match deserialize_async_limited(&tx_bytes, &RPC_DECODE_LIMITS).await {
             Ok(v) => v,
             Err(e) => {
                 error!(target: "darkfid::rpc::tx_calculate_fee", "Failed deserializing bytes into Transaction: {}", e);
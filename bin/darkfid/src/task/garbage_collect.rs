@@ -22,7 +22,8 @@ use log::{debug, error, info};
 
 use crate::DarkfiNodePtr;
 
-/// Async task used for purging erroneous pending transactions from the nodes mempool.
+/// Async task used for purging erroneous and expired pending transactions
+/// from the nodes mempool.
 pub async fn garbage_collect_task(node: DarkfiNodePtr) -> Result<()> {
     info!(target: "darkfid::task::garbage_collect_task", "Starting garbage collection task...");
 
@@ -40,9 +41,41 @@ pub async fn garbage_collect_task(node: DarkfiNodePtr) -> Result<()> {
             }
         };
 
+    // Grab canonical next block height. Forks always extend canonical,
+    // so a transaction expired for this height is expired for all of them.
+    let next_block_height = match node.validator.blockchain.last() {
+        Ok((height, _)) => height + 1,
+        Err(e) => {
+            error!(
+                target: "darkfid::task::garbage_collect_task",
+                "Last canonical block retrieval failed: {e}"
+            );
+            return Ok(())
+        }
+    };
+
     while !txs.is_empty() {
-        // Verify each one against current forks
-        for tx in txs {
+        // Remove expired transactions, without verifying them
+        let (expired, unexpired): (Vec<_>, Vec<_>) =
+            txs.into_iter().partition(|tx| tx.is_expired(next_block_height));
+        if !expired.is_empty() {
+            let expired: Vec<_> = expired.iter().map(|tx| tx.hash()).collect();
+            for tx_hash in &expired {
+                debug!(target: "darkfid::task::garbage_collect_task", "Removing expired transaction: {tx_hash}");
+            }
+            for fork in node.validator.consensus.forks.write().await.iter_mut() {
+                fork.mempool.retain(|tx| !expired.contains(tx));
+            }
+            if let Err(e) = node.validator.blockchain.remove_pending_txs_hashes(&expired) {
+                error!(
+                    target: "darkfid::task::garbage_collect_task",
+                    "Removing expired transactions failed: {e}"
+                );
+            };
+        }
+
+        // Verify each remaining one against current forks
+        for tx in unexpired {
             let tx_hash = tx.hash();
             let tx_vec = [tx.clone()];
             let mut valid = false;
@@ -153,6 +186,8 @@ pub async fn garbage_collect_task(node: DarkfiNodePtr) -> Result<()> {
         };
     }
 
+    node.validator.update_metrics().await;
+
     info!(target: "darkfid::task::garbage_collect_task", "Garbage collection finished successfully!");
     Ok(())
 }
@@ -243,7 +243,7 @@ pub async fn generate_node(
     subscribers.insert("proposals", JsonSubscriber::new("blockchain.subscribe_proposals"));
     subscribers.insert("dnet", JsonSubscriber::new("dnet.subscribe_events"));
 
-    let p2p_handler = DarkfidP2pHandler::init(settings, ex).await?;
+    let p2p_handler = DarkfidP2pHandler::init(settings, ex, false).await?;
     let node =
         DarkfiNode::new(p2p_handler.clone(), validator.clone(), 50, subscribers.clone(), None)
             .await;
@@ -19,10 +19,12 @@
 use std::sync::Arc;
 
 use darkfi::{
-    net::Settings, rpc::settings::RpcSettings, validator::utils::best_fork_index, Result,
+    blockchain::BlockInfo, net::Settings, rpc::settings::RpcSettings, util::encoding::base64,
+    validator::utils::best_fork_index, Result,
 };
 use darkfi_contract_test_harness::init_logger;
 use darkfi_sdk::num_traits::One;
+use darkfi_serial::{deserialize, serialize};
 use num_bigint::BigUint;
 use smol::Executor;
 use url::Url;
@@ -268,6 +270,7 @@ fn darkfid_programmatic_control() -> Result<()> {
                     &darkfi::net::Settings::default(),
                     &None,
                     &None,
+                    false,
                     &ex,
                 )
                 .await
@@ -293,3 +296,18 @@ fn darkfid_programmatic_control() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn genesis_blocks_roundtrip() {
+    // The shipped genesis blocks must decode, and encode back to the
+    // exact same bytes so their hashes stay the same.
+    for genesis_block in [
+        include_str!("../../genesis_block_localnet"),
+        include_str!("../../genesis_block_testnet"),
+        include_str!("../../genesis_block_mainnet"),
+    ] {
+        let bytes = base64::decode(genesis_block.trim()).unwrap();
+        let block: BlockInfo = deserialize(&bytes).unwrap();
+        assert_eq!(serialize(&block), bytes);
+    }
+}
@@ -37,7 +37,7 @@ use darkfi_sdk::{
     crypto::{pasta_prelude::PrimeField, poseidon_hash, MerkleTree},
     pasta::pallas,
 };
-use darkfi_serial::{deserialize_async, serialize_async};
+use darkfi_serial::{deserialize_async, deserialize_async_partial, serialize_async};
 use futures::FutureExt;
 use log::{debug, error, info, warn};
 use sled_overlay::sled;
@@ -50,7 +50,7 @@ use smol::{
 
 use super::{
     server::{IrcServer, MAX_MSG_LEN},
-    Msg, NickServ, OldPrivmsg, SERVER_NAME,
+    NickServ, Privmsg, SERVER_NAME,
 };
 use crate::crypto::rln::{
     closest_epoch, hash_event, RlnIdentity, RLN2_SIGNAL_ZKBIN, RLN_APP_IDENTIFIER,
@@ -305,9 +305,8 @@ impl Client {
                     }
 
                     // Try to deserialize the `Event`'s content into a `Privmsg`
-                    let mut privmsg = match Msg::deserialize(r.content()).await {
-                        Ok(Msg::V1(old_msg)) => old_msg.into_new(),
-                        Ok(Msg::V2(new_msg)) => new_msg,
+                    let mut privmsg: Privmsg = match deserialize_async_partial(r.content()).await {
+                        Ok((privmsg, _)) => privmsg,
                         Err(e) => {
                             error!("[IRC CLIENT] Failed deserializing incoming Privmsg event: {}", e);
                             continue
@@ -521,14 +520,8 @@ impl Client {
         // Truncate messages longer than MAX_MSG_LEN
         let msg = if msg.len() > MAX_MSG_LEN { msg.split_at(MAX_MSG_LEN).0 } else { msg };
 
-        // TODO: This is kept as old version of privmsg, since now we
-        // can deserialize both old and new versions, after some time
-        // this will be replaced with Privmsg (new version)
-        let mut privmsg = OldPrivmsg {
-            channel,
-            nick: self.nickname.read().await.to_string(),
-            msg: msg.to_string(),
-        };
+        let mut privmsg =
+            Privmsg { channel, nick: self.nickname.read().await.to_string(), msg: msg.to_string() };
 
         // Encrypt the Privmsg if an encryption method is available.
         self.server.try_encrypt(&mut privmsg).await;
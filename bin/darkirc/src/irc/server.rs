@@ -49,7 +49,7 @@ use smol::{
 };
 use url::Url;
 
-use super::{client::Client, ChaChaBox, IrcChannel, IrcContact, Priv, Privmsg};
+use super::{client::Client, ChaChaBox, IrcChannel, IrcContact, Privmsg};
 use crate::{
     crypto::{
         rln::{RlnIdentity, RLN2_SIGNAL_ZKBIN, RLN2_SLASH_ZKBIN},
@@ -377,33 +377,33 @@ impl IrcServer {
     }
 
     /// Try encrypting a given `Privmsg` if there is such a channel/contact.
-    pub async fn try_encrypt<T: Priv>(&self, privmsg: &mut T) {
-        if let Some((name, channel)) = self.channels.read().await.get_key_value(privmsg.channel()) {
+    pub async fn try_encrypt(&self, privmsg: &mut Privmsg) {
+        if let Some((name, channel)) = self.channels.read().await.get_key_value(&privmsg.channel) {
             if let Some(saltbox) = &channel.saltbox {
                 // We will use a dummy channel value of MAX_NICK_LEN,
                 // since its not used, so all encrypted messages look the same.
-                *privmsg.channel() = saltbox::encrypt(saltbox, &[0x00; MAX_NICK_LEN]);
+                privmsg.channel = saltbox::encrypt(saltbox, &[0x00; MAX_NICK_LEN]);
                 // We will pad the name to MAX_NICK_LEN so they all look the same
-                *privmsg.nick() = saltbox::encrypt(saltbox, &Self::pad(privmsg.nick()));
-                *privmsg.msg() = saltbox::encrypt(saltbox, privmsg.msg().as_bytes());
+                privmsg.nick = saltbox::encrypt(saltbox, &Self::pad(&privmsg.nick));
+                privmsg.msg = saltbox::encrypt(saltbox, privmsg.msg.as_bytes());
                 debug!("Successfully encrypted message for {}", name);
                 return
             }
         };
 
-        if let Some((name, contact)) = self.contacts.read().await.get_key_value(privmsg.channel()) {
+        if let Some((name, contact)) = self.contacts.read().await.get_key_value(&privmsg.channel) {
             if let Some(saltbox) = &contact.saltbox {
                 // We will use dummy channel and nick values of MAX_NICK_LEN,
                 // since they are not used, so all encrypted messages look the same.
-                *privmsg.channel() = saltbox::encrypt(saltbox, &[0x00; MAX_NICK_LEN]);
+                privmsg.channel = saltbox::encrypt(saltbox, &[0x00; MAX_NICK_LEN]);
                 // We will encrypt the dummy nick value using our own self saltbox,
                 // so we can identify our messages. We can safely unwrap here since
                 // we know that if contacts exist, our self saltbox does as well.
-                *privmsg.nick() = saltbox::encrypt(
+                privmsg.nick = saltbox::encrypt(
                     self.saltbox.read().await.as_ref().unwrap(),
                     &[0x00; MAX_NICK_LEN],
                 );
-                *privmsg.msg() = saltbox::encrypt(saltbox, privmsg.msg().as_bytes());
+                privmsg.msg = saltbox::encrypt(saltbox, privmsg.msg.as_bytes());
                 debug!("Successfully encrypted message for {}", name);
             }
         };
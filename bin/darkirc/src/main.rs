@@ -24,6 +24,7 @@ use darkfi::{
     net::{session::SESSION_DEFAULT, settings::SettingsOpt, P2p, P2pPtr},
     rpc::{
         jsonrpc::JsonSubscriber,
+        metrics::start_exporter,
         server::{listen_and_serve, RequestHandler},
         settings::{RpcSettings, RpcSettingsOpt},
     },
@@ -134,6 +135,10 @@ struct Args {
     /// List configured contacts.
     list_contacts: bool,
 
+    #[structopt(long)]
+    /// Optional Prometheus metrics listen URL
+    metrics_listen: Option<Url>,
+
     #[structopt(flatten)]
     /// P2P network settings
     net: SettingsOpt,
@@ -308,7 +313,7 @@ async fn realmain(args: Args, ex: Arc<Executor<'static>>) -> Result<()> {
     let event_graph_ = Arc::clone(&event_graph);
     let registry = p2p.protocol_registry();
     registry
-        .register(SESSION_DEFAULT, move |channel, _| {
+        .register(SESSION_DEFAULT, "ProtocolEventGraph", 0, move |channel, _| {
             let event_graph_ = event_graph_.clone();
             async move { ProtocolEventGraph::init(event_graph_, channel).await.unwrap() }
         })
@@ -386,6 +391,11 @@ async fn realmain(args: Args, ex: Arc<Executor<'static>>) -> Result<()> {
         ex.clone(),
     );
 
+    let metrics_task = args.metrics_listen.map(|listen| {
+        info!("Starting metrics exporter");
+        start_exporter(listen, ex.clone())
+    });
+
     info!("Starting IRC server");
     let password = args.password.unwrap_or_default();
     let config_path = get_config_path(args.config, CONFIG_FILE)?;
@@ -446,6 +456,11 @@ async fn realmain(args: Args, ex: Arc<Executor<'static>>) -> Result<()> {
     dnet_task.stop().await;
     deg_task.stop().await;
 
+    if let Some(metrics_task) = metrics_task {
+        info!("Stopping metrics exporter");
+        metrics_task.stop().await;
+    }
+
     info!("Stopping IRC server");
     irc_task.stop().await;
     prune_task.stop().await;
@@ -23,7 +23,8 @@ use darkfi::{
     net::P2pPtr,
     rpc::{
         jsonrpc::{ErrorCode, JsonError, JsonRequest, JsonResponse, JsonResult},
-        p2p_method::HandlerP2p,
+        openrpc::{RpcMethod, RpcParam, PING_METHOD},
+        p2p_method::{HandlerP2p, P2P_GET_INFO_METHOD},
         server::RequestHandler,
         util::JsonValue,
     },
@@ -34,6 +35,48 @@ use smol::lock::MutexGuard;
 
 use super::DarkIrc;
 
+/// Methods served over JSON-RPC, as advertised by `rpc.discover`
+const RPC_METHODS: &[RpcMethod] = &[
+    PING_METHOD,
+    RpcMethod {
+        name: "dnet.switch",
+        summary: "Activate or deactivate dnet in the P2P stack",
+        params: &[RpcParam { name: "enable", schema: "boolean" }],
+        result: "boolean",
+    },
+    RpcMethod {
+        name: "dnet.subscribe_events",
+        summary: "Subscribe to P2P dnet events",
+        params: &[],
+        result: "object",
+    },
+    P2P_GET_INFO_METHOD,
+    RpcMethod {
+        name: "deg.switch",
+        summary: "Activate or deactivate deg in the event graph",
+        params: &[RpcParam { name: "enable", schema: "boolean" }],
+        result: "boolean",
+    },
+    RpcMethod {
+        name: "deg.subscribe_events",
+        summary: "Subscribe to event graph deg events",
+        params: &[],
+        result: "object",
+    },
+    RpcMethod {
+        name: "eventgraph.get_info",
+        summary: "Get event graph info",
+        params: &[],
+        result: "object",
+    },
+    RpcMethod {
+        name: "eventgraph.replay",
+        summary: "Get replayed event graph info",
+        params: &[],
+        result: "object",
+    },
+];
+
 #[async_trait]
 impl RequestHandler<()> for DarkIrc {
     async fn handle_request(&self, req: JsonRequest) -> JsonResult {
@@ -57,6 +100,10 @@ impl RequestHandler<()> for DarkIrc {
     async fn connections_mut(&self) -> MutexGuard<'life0, HashSet<StoppableTaskPtr>> {
         self.rpc_connections.lock().await
     }
+
+    fn methods(&self) -> Vec<RpcMethod> {
+        RPC_METHODS.to_vec()
+    }
 }
 
 impl DarkIrc {
@@ -0,0 +1,113 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2025 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::io::Cursor;
+
+use log::info;
+
+use darkfi::{
+    blockchain::BlockInfo,
+    tx::{ContractCallLeaf, TransactionBuilder},
+    zk::{empty_witnesses, ProvingKey, ZkCircuit},
+    zkas::ZkBinary,
+    Error, Result,
+};
+use darkfi_contract_test_harness::vks::Pks;
+use darkfi_money_contract::{
+    client::genesis_mint_v1::GenesisMintCallBuilder, MoneyFunction, MONEY_CONTRACT_ZKAS_MINT_NS_V1,
+};
+use darkfi_sdk::{
+    crypto::{Keypair, MONEY_CONTRACT_ID},
+    ContractCall,
+};
+use darkfi_serial::AsyncEncodable;
+
+/// A wallet pre-funded in the devnet genesis block
+#[derive(Clone, Debug)]
+pub struct DevnetWallet {
+    /// Name used to refer to the wallet
+    pub name: String,
+    /// Wallet keypair, owning the genesis coins
+    pub keypair: Keypair,
+    /// Values of the coins minted in the genesis block
+    pub amounts: Vec<u64>,
+}
+
+/// Generate a genesis block containing a genesis mint transaction
+/// for each of the provided wallets.
+pub async fn generate_genesis_block(wallets: &[DevnetWallet], pks: &Pks) -> Result<BlockInfo> {
+    // Grab mint proving key and zkbin
+    let mut mint = None;
+    for (bincode, namespace, pk) in pks {
+        if namespace.as_str() != MONEY_CONTRACT_ZKAS_MINT_NS_V1 {
+            continue
+        }
+        let mut reader = Cursor::new(pk);
+        let zkbin = ZkBinary::decode(bincode)?;
+        let circuit = ZkCircuit::new(empty_witnesses(&zkbin)?, &zkbin);
+        let proving_key = ProvingKey::read(&mut reader, circuit)?;
+        mint = Some((proving_key, zkbin));
+    }
+    let Some((mint_pk, mint_zkbin)) = mint else {
+        return Err(Error::Custom("Mint proving key not found".to_string()))
+    };
+
+    let mut genesis_txs = Vec::with_capacity(wallets.len());
+    for wallet in wallets {
+        if wallet.amounts.is_empty() {
+            continue
+        }
+        info!(target: "devnet::genesis", "Minting {:?} to wallet {}", wallet.amounts, wallet.name);
+
+        let builder = GenesisMintCallBuilder {
+            signature_public: wallet.keypair.public,
+            amounts: wallet.amounts.clone(),
+            recipient: None,
+            spend_hook: None,
+            user_data: None,
+            mint_zkbin: mint_zkbin.clone(),
+            mint_pk: mint_pk.clone(),
+        };
+        let debris = builder.build()?;
+
+        // Encode and build the transaction
+        let mut data = vec![MoneyFunction::GenesisMintV1 as u8];
+        debris.params.encode_async(&mut data).await?;
+        let call = ContractCall { contract_id: *MONEY_CONTRACT_ID, data };
+        let mut tx_builder =
+            TransactionBuilder::new(ContractCallLeaf { call, proofs: debris.proofs }, vec![])?;
+        let mut tx = tx_builder.build()?;
+        let sigs = tx.create_sigs(&[wallet.keypair.secret])?;
+        tx.signatures = vec![sigs];
+        genesis_txs.push(tx);
+    }
+
+    // Generate the genesis block
+    let mut genesis_block = BlockInfo::default();
+
+    // Retrieve genesis producer transaction
+    let producer_tx = genesis_block.txs.pop().unwrap();
+
+    // Append genesis transactions
+    if !genesis_txs.is_empty() {
+        genesis_block.append_txs(genesis_txs);
+    }
+    genesis_block.append_txs(vec![producer_tx]);
+
+    Ok(genesis_block)
+}
@@ -0,0 +1,594 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2025 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{
+    collections::HashSet,
+    net::{Ipv4Addr, SocketAddr, TcpListener},
+    path::PathBuf,
+    sync::Arc,
+};
+
+use log::{error, info};
+use rand::rngs::OsRng;
+use smol::{
+    channel::{Receiver, Sender},
+    lock::Mutex,
+};
+use url::Url;
+
+use darkfi::{
+    blockchain::HeaderHash,
+    net::settings::Settings,
+    rpc::{
+        server::{listen_and_serve, RequestHandler},
+        settings::RpcSettings,
+    },
+    system::{ExecutorPtr, StoppableTask, StoppableTaskPtr},
+    validator::ValidatorConfig,
+    Error, Result,
+};
+use darkfi_contract_test_harness::vks;
+use darkfi_sdk::crypto::Keypair;
+use darkfid::{task::consensus::ConsensusInitTaskConfig, Darkfid, DarkfidPtr};
+use num_bigint::BigUint;
+use sled_overlay::sled;
+
+/// Daemon error codes
+mod error;
+
+/// Genesis block generation
+pub mod genesis;
+pub use genesis::DevnetWallet;
+
+/// Mining gates of the nodes
+mod miner;
+use miner::{MiningGate, MiningGatePtr};
+
+/// Links proxying the P2P connections between nodes
+mod proxy;
+use proxy::{Link, LinkPtr};
+
+/// Child processes of the auxiliary daemons
+mod process;
+use process::ChildProcesses;
+
+/// JSON-RPC server methods
+mod rpc;
+
+#[cfg(test)]
+mod tests;
+
+/// Configuration of a devnet
+#[derive(Clone, Debug)]
+pub struct DevnetConfig {
+    /// Number of darkfid nodes to run
+    pub nodes: usize,
+    /// Directory holding the nodes and wallets data.
+    /// `None` keeps everything in temporary databases.
+    pub datadir: Option<PathBuf>,
+    /// First port of the range used by the devnet.
+    /// `0` picks free ports for every address instead.
+    pub base_port: u16,
+    /// Confirmation threshold, denominated by number of blocks
+    pub threshold: usize,
+    /// PoW block production target, in seconds
+    pub pow_target: u32,
+    /// Optional fixed PoW difficulty
+    pub pow_fixed_difficulty: Option<BigUint>,
+    /// PoW miner number of threads to use
+    pub miner_threads: usize,
+    /// Wallets to fund in the genesis block, as names and coin values
+    pub wallets: Vec<(String, Vec<u64>)>,
+    /// Optional path to the `explorerd` binary, to run one on node 0
+    pub explorerd: Option<PathBuf>,
+    /// Optional path to the `drk` binary, to set up a wallet for
+    /// each funded keypair
+    pub drk: Option<PathBuf>,
+}
+
+impl Default for DevnetConfig {
+    fn default() -> Self {
+        Self {
+            nodes: 3,
+            datadir: None,
+            base_port: 28000,
+            threshold: 3,
+            pow_target: 10,
+            pow_fixed_difficulty: Some(BigUint::from(1u8)),
+            miner_threads: 4,
+            wallets: vec![],
+            explorerd: None,
+            drk: None,
+        }
+    }
+}
+
+/// Addresses used by a devnet.
+///
+/// With a fixed base port, every address has a fixed offset in the port
+/// range starting at it. With base port `0`, free ports are picked instead.
+struct DevnetPorts {
+    /// P2P inbound, JSON-RPC and mining gate ports of every node
+    nodes: Vec<[u16; 3]>,
+    /// `explorerd` JSON-RPC port
+    explorerd: u16,
+    /// Listen ports of the links
+    links: Vec<u16>,
+}
+
+impl DevnetPorts {
+    fn new(base_port: u16, n_nodes: usize, n_links: usize) -> Result<Self> {
+        if base_port != 0 {
+            let port = |offset: usize| base_port + offset as u16;
+            return Ok(Self {
+                nodes: (0..n_nodes)
+                    .map(|i| [port(i * 10), port(i * 10 + 1), port(i * 10 + 2)])
+                    .collect(),
+                explorerd: port(900),
+                links: (0..n_links).map(|i| port(1000 + i)).collect(),
+            })
+        }
+
+        // Keep the listeners open until all the ports are picked, so the
+        // OS can't hand out the same port twice.
+        let mut listeners = vec![];
+        let mut free_port = || -> Result<u16> {
+            let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+            let port = listener.local_addr()?.port();
+            listeners.push(listener);
+            Ok(port)
+        };
+
+        let mut nodes = Vec::with_capacity(n_nodes);
+        for _ in 0..n_nodes {
+            nodes.push([free_port()?, free_port()?, free_port()?]);
+        }
+        let explorerd = free_port()?;
+        let mut links = Vec::with_capacity(n_links);
+        for _ in 0..n_links {
+            links.push(free_port()?);
+        }
+
+        Ok(Self { nodes, explorerd, links })
+    }
+
+    /// P2P inbound address of given node
+    fn p2p_addr(&self, node: usize) -> SocketAddr {
+        addr(self.nodes[node][0])
+    }
+
+    /// JSON-RPC endpoint of given node
+    fn rpc_endpoint(&self, node: usize) -> Url {
+        Url::parse(&format!("tcp://{}", addr(self.nodes[node][1]))).unwrap()
+    }
+
+    /// Mining gate endpoint of given node
+    fn gate_endpoint(&self, node: usize) -> Url {
+        Url::parse(&format!("tcp://{}", addr(self.nodes[node][2]))).unwrap()
+    }
+
+    /// JSON-RPC endpoint of the `explorerd` instance
+    fn explorerd_endpoint(&self) -> Url {
+        Url::parse(&format!("tcp://{}", addr(self.explorerd))).unwrap()
+    }
+
+    /// Listen address of the link with given index
+    fn link_addr(&self, index: usize) -> SocketAddr {
+        addr(self.links[index])
+    }
+}
+
+fn addr(port: u16) -> SocketAddr {
+    SocketAddr::from((Ipv4Addr::LOCALHOST, port))
+}
+
+/// A devnet node
+struct DevnetNode {
+    /// The node daemon
+    daemon: DarkfidPtr,
+    /// Miner daemon endpoint of the node
+    gate: MiningGatePtr,
+    /// Mining gate JSON-RPC background task
+    gate_task: StoppableTaskPtr,
+}
+
+/// Atomic pointer to a devnet
+pub type DevnetPtr = Arc<Devnet>;
+
+/// A local network of darkfid nodes, running in-process.
+///
+/// Nodes connect to each other through [`Link`] proxies, so the network
+/// can be partitioned, and only mine blocks when requested to.
+pub struct Devnet {
+    /// Devnet configuration
+    pub config: DevnetConfig,
+    /// Wallets funded in the genesis block
+    pub wallets: Vec<DevnetWallet>,
+    /// Addresses used by the devnet
+    ports: DevnetPorts,
+    /// Running nodes
+    nodes: Vec<DevnetNode>,
+    /// Links between every pair of nodes
+    links: Vec<LinkPtr>,
+    /// Child processes of the auxiliary daemons
+    processes: Mutex<ChildProcesses>,
+    /// Shutdown request sender
+    shutdown_tx: Sender<()>,
+    /// Shutdown request receiver
+    shutdown_rx: Receiver<()>,
+    /// JSON-RPC background task
+    rpc_task: StoppableTaskPtr,
+    /// JSON-RPC connection tracker
+    rpc_connections: Mutex<HashSet<StoppableTaskPtr>>,
+}
+
+impl Devnet {
+    /// Generate the genesis block and start all the devnet nodes,
+    /// links and auxiliary daemons. If any of them fails to start, the
+    /// ones already started get stopped.
+    pub async fn start(config: DevnetConfig, ex: &ExecutorPtr) -> Result<DevnetPtr> {
+        info!(target: "devnet::Devnet::start", "Starting devnet with {} nodes...", config.nodes);
+        if config.nodes == 0 {
+            return Err(Error::Custom("Devnet needs at least one node".to_string()))
+        }
+        if config.datadir.is_none() && (config.explorerd.is_some() || config.drk.is_some()) {
+            return Err(Error::Custom("explorerd and drk require a data directory".to_string()))
+        }
+
+        // Generate the wallets and the genesis block funding them
+        let wallets: Vec<DevnetWallet> = config
+            .wallets
+            .iter()
+            .map(|(name, amounts)| DevnetWallet {
+                name: name.clone(),
+                keypair: Keypair::random(&mut OsRng),
+                amounts: amounts.clone(),
+            })
+            .collect();
+        let (pks, vks) = vks::get_cached_pks_and_vks()?;
+        let genesis_block = genesis::generate_genesis_block(&wallets, &pks).await?;
+        let bootstrap = genesis_block.header.timestamp.inner();
+
+        let validator_config = ValidatorConfig {
+            confirmation_threshold: config.threshold,
+            pow_target: config.pow_target,
+            pow_fixed_difficulty: config.pow_fixed_difficulty.clone(),
+            genesis_block,
+            verify_fees: false,
+        };
+
+        // Mining rewards go to the first wallet, if any. All nodes start
+        // from the same genesis block, so there is nothing to sync.
+        let recipient = match wallets.first() {
+            Some(wallet) => wallet.keypair.public,
+            None => Keypair::random(&mut OsRng).public,
+        };
+        let consensus_config = ConsensusInitTaskConfig {
+            skip_sync: true,
+            checkpoint_height: None,
+            checkpoint: None,
+            miner: true,
+            recipient: Some(recipient.to_string()),
+            spend_hook: None,
+            user_data: None,
+            bootstrap,
+        };
+
+        let n_links = config.nodes * (config.nodes - 1) / 2;
+        let ports = DevnetPorts::new(config.base_port, config.nodes, n_links)?;
+
+        let mut links = vec![];
+        let mut nodes = Vec::with_capacity(config.nodes);
+        let mut processes = ChildProcesses::default();
+        let res = async {
+            // Start a link for every pair of nodes. The higher node
+            // connects to the lower one through it.
+            for b in 0..config.nodes {
+                for a in b + 1..config.nodes {
+                    let link = Link::new(a, b, ports.link_addr(links.len()), ports.p2p_addr(b));
+                    link.clone().start(ex).await?;
+                    links.push(link);
+                }
+            }
+
+            let miner_lock = Arc::new(Mutex::new(()));
+            for i in 0..config.nodes {
+                let node = Self::start_node(
+                    i,
+                    &config,
+                    &ports,
+                    &links,
+                    &validator_config,
+                    &consensus_config,
+                    &vks,
+                    &miner_lock,
+                    ex,
+                )
+                .await?;
+                nodes.push(node);
+            }
+
+            // Start the auxiliary daemons, talking to node 0
+            if let Some(datadir) = &config.datadir {
+                if let Some(explorerd) = &config.explorerd {
+                    let listen = ports.explorerd_endpoint();
+                    processes
+                        .start_explorerd(explorerd, datadir, &listen, &ports.rpc_endpoint(0))
+                        .await?;
+                }
+                if let Some(drk) = &config.drk {
+                    for wallet in &wallets {
+                        processes.setup_drk(drk, datadir, &ports.rpc_endpoint(0), wallet).await?;
+                    }
+                }
+            }
+
+            Ok::<(), Error>(())
+        }
+        .await;
+
+        if let Err(e) = res {
+            error!(target: "devnet::Devnet::start", "Failed starting devnet: {}", e);
+            let _ = stop_components(&mut processes, &nodes, &links).await;
+            return Err(e)
+        }
+
+        let (shutdown_tx, shutdown_rx) = smol::channel::bounded(1);
+        info!(target: "devnet::Devnet::start", "Devnet started successfully!");
+        Ok(Arc::new(Self {
+            config,
+            wallets,
+            ports,
+            nodes,
+            links,
+            processes: Mutex::new(processes),
+            shutdown_tx,
+            shutdown_rx,
+            rpc_task: StoppableTask::new(),
+            rpc_connections: Mutex::new(HashSet::new()),
+        }))
+    }
+
+    /// Start the mining gate and the darkfid daemon of given node.
+    /// The gate gets stopped if the daemon fails to start.
+    #[allow(clippy::too_many_arguments)]
+    async fn start_node(
+        i: usize,
+        config: &DevnetConfig,
+        ports: &DevnetPorts,
+        links: &[LinkPtr],
+        validator_config: &ValidatorConfig,
+        consensus_config: &ConsensusInitTaskConfig,
+        vks: &vks::Vks,
+        miner_lock: &Arc<Mutex<()>>,
+        ex: &ExecutorPtr,
+    ) -> Result<DevnetNode> {
+        info!(target: "devnet::Devnet::start", "Starting node {}...", i);
+
+        // Start the mining gate first, since darkfid connects to it on init
+        let gate = MiningGate::new(i, config.miner_threads, miner_lock.clone());
+        let gate_task = StoppableTask::new();
+        let gate_settings =
+            RpcSettings { listen: ports.gate_endpoint(i), ..RpcSettings::default() };
+        let gate_ = gate.clone();
+        gate_task.clone().start(
+            listen_and_serve(gate_settings, gate.clone(), None, ex.clone()),
+            |res| async move {
+                match res {
+                    Ok(()) | Err(Error::RpcServerStopped) => {
+                        <MiningGate as RequestHandler<()>>::stop_connections(&gate_).await
+                    }
+                    Err(e) => error!(target: "devnet::Devnet::start", "Failed starting mining gate: {}", e),
+                }
+            },
+            Error::RpcServerStopped,
+            ex.clone(),
+        );
+
+        let daemon = async {
+            // Initialize the node database
+            let sled_db = match &config.datadir {
+                Some(datadir) => sled::open(datadir.join(format!("node{}", i)))?,
+                None => sled::Config::new().temporary(true).open()?,
+            };
+            vks::inject(&sled_db, vks)?;
+
+            // Every node only has manual connections through the links, so
+            // cutting them is enough to partition the network.
+            let settings = Settings {
+                localnet: true,
+                inbound_addrs: vec![Url::parse(&format!("tcp://{}", ports.p2p_addr(i)))?],
+                peers: links
+                    .iter()
+                    .filter(|link| link.a == i)
+                    .map(|link| Url::parse(&format!("tcp://{}", link.listen)).unwrap())
+                    .collect(),
+                outbound_connections: 0,
+                inbound_connections: config.nodes,
+                outbound_connect_timeout: 1,
+                allowed_transports: vec!["tcp".to_string()],
+                ..Default::default()
+            };
+
+            let daemon = Darkfid::init(
+                &sled_db,
+                validator_config,
+                &settings,
+                &Some(ports.gate_endpoint(i)),
+                &None,
+                false,
+                ex,
+            )
+            .await?;
+
+            let rpc_settings =
+                RpcSettings { listen: ports.rpc_endpoint(i), ..RpcSettings::default() };
+            daemon.start(ex, &rpc_settings, &None, consensus_config).await?;
+
+            Ok::<DarkfidPtr, Error>(daemon)
+        }
+        .await;
+
+        match daemon {
+            Ok(daemon) => Ok(DevnetNode { daemon, gate, gate_task }),
+            Err(e) => {
+                gate_task.stop().await;
+                Err(e)
+            }
+        }
+    }
+
+    /// Start the devnet control JSON-RPC server.
+    pub fn start_rpc(self: &Arc<Self>, rpc_settings: &RpcSettings, ex: &ExecutorPtr) {
+        let self_ = self.clone();
+        self.rpc_task.clone().start(
+            listen_and_serve(rpc_settings.clone(), self.clone(), None, ex.clone()),
+            |res| async move {
+                match res {
+                    Ok(()) | Err(Error::RpcServerStopped) => {
+                        <Devnet as RequestHandler<()>>::stop_connections(&self_).await
+                    }
+                    Err(e) => error!(target: "devnet::Devnet::start_rpc", "Failed starting JSON-RPC server: {}", e),
+                }
+            },
+            Error::RpcServerStopped,
+            ex.clone(),
+        );
+    }
+
+    /// JSON-RPC endpoint of given node
+    pub fn rpc_endpoint(&self, node: usize) -> Url {
+        self.ports.rpc_endpoint(node)
+    }
+
+    /// JSON-RPC endpoint of the `explorerd` instance, if it's running
+    pub fn explorerd_endpoint(&self) -> Option<Url> {
+        match (&self.config.datadir, &self.config.explorerd) {
+            (Some(_), Some(_)) => Some(self.ports.explorerd_endpoint()),
+            _ => None,
+        }
+    }
+
+    /// Number of nodes in the devnet
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns true if the devnet has no nodes
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Mine `count` blocks on given node, and return their hashes.
+    pub async fn mine(&self, node: usize, count: usize) -> Result<Vec<HeaderHash>> {
+        let Some(node) = self.nodes.get(node) else {
+            return Err(Error::Custom(format!("Unknown node: {}", node)))
+        };
+        node.gate.mine(count).await
+    }
+
+    /// Split the nodes into the given groups, cutting all the links between
+    /// nodes of different groups. Nodes not in any group are isolated.
+    pub async fn partition(&self, groups: &[Vec<usize>]) -> Result<()> {
+        let group_of = |node: usize| groups.iter().position(|group| group.contains(&node));
+        for group in groups {
+            if let Some(node) = group.iter().find(|node| **node >= self.nodes.len()) {
+                return Err(Error::Custom(format!("Unknown node: {}", node)))
+            }
+        }
+
+        info!(target: "devnet::Devnet::partition", "Partitioning network into {:?}", groups);
+        for link in &self.links {
+            let connected = match (group_of(link.a), group_of(link.b)) {
+                (Some(a), Some(b)) => a == b,
+                _ => false,
+            };
+            if link.is_blocked() == connected {
+                link.set_blocked(!connected).await;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Restore all the links between the nodes.
+    pub async fn heal(&self) {
+        info!(target: "devnet::Devnet::heal", "Healing network partitions");
+        for link in &self.links {
+            if link.is_blocked() {
+                link.set_blocked(false).await;
+            }
+        }
+    }
+
+    /// Request the devnet to shut down. [`Devnet::wait_shutdown`] returns
+    /// once this gets called.
+    pub fn request_shutdown(&self) {
+        let _ = self.shutdown_tx.try_send(());
+    }
+
+    /// Wait until a shutdown gets requested.
+    pub async fn wait_shutdown(&self) {
+        let _ = self.shutdown_rx.recv().await;
+    }
+
+    /// Tear down the devnet: stop the auxiliary daemons, the nodes and
+    /// the links between them.
+    pub async fn stop(&self) -> Result<()> {
+        info!(target: "devnet::Devnet::stop", "Terminating devnet...");
+
+        info!(target: "devnet::Devnet::stop", "Stopping JSON-RPC server...");
+        self.rpc_task.stop().await;
+
+        stop_components(&mut *self.processes.lock().await, &self.nodes, &self.links).await?;
+
+        info!(target: "devnet::Devnet::stop", "Devnet terminated successfully!");
+        Ok(())
+    }
+}
+
+/// Stop the auxiliary daemons, then the nodes, then the links between
+/// them. Everything gets stopped even if a node fails to, and the first
+/// such error is returned.
+async fn stop_components(
+    processes: &mut ChildProcesses,
+    nodes: &[DevnetNode],
+    links: &[LinkPtr],
+) -> Result<()> {
+    info!(target: "devnet::Devnet::stop", "Stopping auxiliary daemons...");
+    processes.stop().await;
+
+    let mut result = Ok(());
+    for (i, node) in nodes.iter().enumerate() {
+        info!(target: "devnet::Devnet::stop", "Stopping node {}...", i);
+        if let Err(e) = node.daemon.stop().await {
+            error!(target: "devnet::Devnet::stop", "Failed stopping node {}: {}", i, e);
+            if result.is_ok() {
+                result = Err(e);
+            }
+        }
+        node.gate_task.stop().await;
+    }
+
+    info!(target: "devnet::Devnet::stop", "Stopping links...");
+    for link in links {
+        link.stop().await;
+    }
+
+    result
+}
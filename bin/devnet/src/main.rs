@@ -0,0 +1,154 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2025 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::sync::Arc;
+
+use log::info;
+use num_bigint::BigUint;
+use smol::{stream::StreamExt, Executor};
+use structopt_toml::{serde::Deserialize, structopt::StructOpt, StructOptToml};
+
+use darkfi::{
+    async_daemonize, cli_desc, rpc::settings::RpcSettingsOpt, util::path::expand_path, Result,
+};
+
+use devnet::{Devnet, DevnetConfig};
+
+const CONFIG_FILE: &str = "devnet.toml";
+const CONFIG_FILE_CONTENTS: &str = include_str!("../devnet.toml");
+
+#[derive(Clone, Debug, Deserialize, StructOpt, StructOptToml)]
+#[serde(default)]
+#[structopt(name = "devnet", about = cli_desc!())]
+struct Args {
+    #[structopt(short, long)]
+    /// Configuration file to use
+    config: Option<String>,
+
+    #[structopt(flatten)]
+    /// Devnet control JSON-RPC settings
+    rpc: RpcSettingsOpt,
+
+    #[structopt(short, long, default_value = "3")]
+    /// Number of darkfid nodes to run
+    nodes: usize,
+
+    #[structopt(short, long)]
+    /// Directory to keep the nodes and wallets data in.
+    /// Temporary databases are used if omitted.
+    datadir: Option<String>,
+
+    #[structopt(long, default_value = "28000")]
+    /// First port of the range used by the devnet nodes (0 picks free ports)
+    base_port: u16,
+
+    #[structopt(long, default_value = "3")]
+    /// Confirmation threshold, denominated by number of blocks
+    threshold: usize,
+
+    #[structopt(long, default_value = "10")]
+    /// PoW block production target, in seconds
+    pow_target: u32,
+
+    #[structopt(long, default_value = "1")]
+    /// Fixed PoW difficulty of the devnet blocks
+    pow_fixed_difficulty: usize,
+
+    #[structopt(short, long, default_value = "4")]
+    /// PoW miner number of threads to use
+    threads: usize,
+
+    #[structopt(long)]
+    /// Path to an `explorerd` binary to run against node 0
+    explorerd: Option<String>,
+
+    #[structopt(long)]
+    /// Path to a `drk` binary to set up the wallets with
+    drk: Option<String>,
+
+    #[structopt(skip)]
+    /// Wallets to fund in the genesis block
+    wallet: Vec<WalletOpt>,
+
+    #[structopt(short, long)]
+    /// Set log file to ouput into
+    log: Option<String>,
+
+    #[structopt(short, parse(from_occurrences))]
+    /// Increase verbosity (-vvv supported)
+    verbose: u8,
+}
+
+/// Genesis wallet configuration
+#[derive(Clone, Debug, Deserialize)]
+struct WalletOpt {
+    /// Wallet name
+    name: String,
+    /// Values of the native token coins minted to the wallet
+    amounts: Vec<u64>,
+}
+
+async_daemonize!(realmain);
+async fn realmain(args: Args, ex: Arc<Executor<'static>>) -> Result<()> {
+    info!(target: "devnet", "Starting DarkFi devnet...");
+
+    let datadir = match args.datadir {
+        Some(datadir) => Some(expand_path(&datadir)?),
+        None => None,
+    };
+    let config = DevnetConfig {
+        nodes: args.nodes,
+        datadir,
+        base_port: args.base_port,
+        threshold: args.threshold,
+        pow_target: args.pow_target,
+        pow_fixed_difficulty: Some(BigUint::from(args.pow_fixed_difficulty)),
+        miner_threads: args.threads,
+        wallets: args.wallet.into_iter().map(|w| (w.name, w.amounts)).collect(),
+        explorerd: args.explorerd.map(Into::into),
+        drk: args.drk.map(Into::into),
+    };
+
+    let devnet = Devnet::start(config, &ex).await?;
+    devnet.start_rpc(&args.rpc.into(), &ex);
+    for wallet in &devnet.wallets {
+        info!(target: "devnet", "Wallet {}: {}", wallet.name, wallet.keypair.public);
+    }
+
+    // Run until we catch a termination signal, or shutdown is requested
+    // over JSON-RPC.
+    let (signals_handler, signals_task) = SignalHandler::new(ex)?;
+    smol::future::or(
+        async {
+            if let Err(e) = signals_handler.wait_termination(signals_task).await {
+                info!(target: "devnet", "Signal handler failed: {}", e);
+            }
+            info!(target: "devnet", "Caught termination signal, cleaning up and exiting");
+        },
+        async {
+            devnet.wait_shutdown().await;
+            info!(target: "devnet", "Shutdown requested, cleaning up and exiting");
+        },
+    )
+    .await;
+
+    devnet.stop().await?;
+
+    info!(target: "devnet", "Shut down successfully");
+    Ok(())
+}
@@ -0,0 +1,273 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2025 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{collections::HashSet, sync::Arc, time::Duration};
+
+use log::{debug, error, info};
+use num_bigint::BigUint;
+use smol::{
+    channel::{Receiver, Sender},
+    lock::{Mutex, MutexGuard},
+};
+
+use darkfi::{
+    blockchain::{BlockInfo, HeaderHash},
+    rpc::{
+        jsonrpc::{ErrorCode, JsonError, JsonRequest, JsonResponse, JsonResult},
+        openrpc::{RpcMethod, RpcParam, PING_METHOD},
+        server::RequestHandler,
+        util::JsonValue,
+    },
+    system::{timeout::timeout, StoppableTaskPtr},
+    util::encoding::base64,
+    validator::pow::mine_block,
+    Error, Result,
+};
+use darkfi_sdk::num_traits::Num;
+use darkfi_serial::{async_trait, deserialize_async};
+
+use crate::error::{server_error, RpcError};
+
+/// Time to wait for the node to mine a single granted block, in seconds
+const MINE_TIMEOUT: u64 = 120;
+
+/// Atomic pointer to a mining gate
+pub type MiningGatePtr = Arc<MiningGate>;
+
+/// Miner daemon endpoint of a devnet node.
+///
+/// The node sends its `mine` requests here like it would to `minerd`,
+/// but they are held until the devnet grants the node blocks to mine.
+/// Mining itself happens in-process, one node at a time.
+pub struct MiningGate {
+    /// Index of the node using this gate
+    node: usize,
+    /// PoW miner number of threads to use
+    threads: usize,
+    /// Lock shared by all gates, so only one node mines at a time
+    miner_lock: Arc<Mutex<()>>,
+    /// Sender of blocks the node is allowed to mine
+    credits_tx: Sender<()>,
+    /// Receiver of blocks the node is allowed to mine
+    credits_rx: Receiver<()>,
+    /// Sender of the node abort requests
+    abort_tx: Sender<()>,
+    /// Receiver of the node abort requests
+    abort_rx: Receiver<()>,
+    /// Sender of the mined block hashes
+    mined_tx: Sender<HeaderHash>,
+    /// Receiver of the mined block hashes
+    mined_rx: Receiver<HeaderHash>,
+    /// JSON-RPC connection tracker
+    rpc_connections: Mutex<HashSet<StoppableTaskPtr>>,
+}
+
+impl MiningGate {
+    pub fn new(node: usize, threads: usize, miner_lock: Arc<Mutex<()>>) -> MiningGatePtr {
+        let (credits_tx, credits_rx) = smol::channel::unbounded();
+        let (abort_tx, abort_rx) = smol::channel::bounded(1);
+        let (mined_tx, mined_rx) = smol::channel::unbounded();
+        Arc::new(Self {
+            node,
+            threads,
+            miner_lock,
+            credits_tx,
+            credits_rx,
+            abort_tx,
+            abort_rx,
+            mined_tx,
+            mined_rx,
+            rpc_connections: Mutex::new(HashSet::new()),
+        })
+    }
+
+    /// Allow the node to mine `count` blocks, and wait until it did.
+    /// Returns the hashes of the mined blocks, or an error if the node
+    /// didn't mine one of them in time.
+    pub async fn mine(&self, count: usize) -> Result<Vec<HeaderHash>> {
+        info!(target: "devnet::miner", "Node {} is allowed to mine {} blocks", self.node, count);
+
+        // Blocks mined after a previous request timed out don't concern this one
+        while self.mined_rx.try_recv().is_ok() {}
+
+        for _ in 0..count {
+            // Channel is unbounded and we hold the receiver, so this can't fail
+            self.credits_tx.send(()).await.unwrap();
+        }
+
+        let mut hashes = Vec::with_capacity(count);
+        for _ in 0..count {
+            match timeout(Duration::from_secs(MINE_TIMEOUT), self.mined_rx.recv()).await {
+                Ok(Ok(hash)) => hashes.push(hash),
+                _ => {
+                    // Take back the blocks the node didn't mine yet
+                    while self.credits_rx.try_recv().is_ok() {}
+                    return Err(Error::Custom(format!(
+                        "Node {} didn't mine block {}/{} in time",
+                        self.node,
+                        hashes.len() + 1,
+                        count
+                    )))
+                }
+            }
+        }
+
+        Ok(hashes)
+    }
+}
+
+/// Methods served over JSON-RPC, as advertised by `rpc.discover`.
+/// These are the ones of minerd, which darkfid expects.
+const RPC_METHODS: &[RpcMethod] = &[
+    PING_METHOD,
+    RpcMethod {
+        name: "abort",
+        summary: "Abort mining the pending request",
+        params: &[],
+        result: "boolean",
+    },
+    RpcMethod {
+        name: "mine",
+        summary: "Mine the given block for the given target, returning its nonce",
+        params: &[
+            RpcParam { name: "target", schema: "string" },
+            RpcParam { name: "block", schema: "string" },
+        ],
+        result: "number",
+    },
+];
+
+#[async_trait]
+impl RequestHandler<()> for MiningGate {
+    async fn handle_request(&self, req: JsonRequest) -> JsonResult {
+        debug!(target: "devnet::miner::rpc", "--> {}", req.stringify().unwrap());
+
+        match req.method.as_str() {
+            "ping" => self.pong(req.id, req.params).await,
+            "abort" => self.abort(req.id, req.params).await,
+            "mine" => self.mine_request(req.id, req.params).await,
+            _ => JsonError::new(ErrorCode::MethodNotFound, None, req.id).into(),
+        }
+    }
+
+    async fn connections_mut(&self) -> MutexGuard<'life0, HashSet<StoppableTaskPtr>> {
+        self.rpc_connections.lock().await
+    }
+
+    fn methods(&self) -> Vec<RpcMethod> {
+        RPC_METHODS.to_vec()
+    }
+}
+
+impl MiningGate {
+    // RPCAPI:
+    // Signals the gate to abort the pending mining request of the node.
+    // Returns `true` on success.
+    //
+    // --> {"jsonrpc": "2.0", "method": "abort", "params": [], "id": 42}
+    // <-- {"jsonrpc": "2.0", "result": "true", "id": 42}
+    async fn abort(&self, id: u16, _params: JsonValue) -> JsonResult {
+        // A full channel means an abort is already pending
+        let _ = self.abort_tx.try_send(());
+        JsonResponse::new(JsonValue::Boolean(true), id).into()
+    }
+
+    // RPCAPI:
+    // Wait until the devnet allows the node to mine, then mine provided
+    // block for requested mine target and return the corresponding nonce.
+    //
+    // --> {"jsonrpc": "2.0", "method": "mine", "params": ["target", "block"], "id": 42}
+    // <-- {"jsonrpc": "2.0", "result": "nonce", "id": 42}
+    async fn mine_request(&self, id: u16, params: JsonValue) -> JsonResult {
+        // Verify parameters
+        if !params.is_array() {
+            return JsonError::new(ErrorCode::InvalidParams, None, id).into()
+        }
+        let params = params.get::<Vec<JsonValue>>().unwrap();
+        if params.len() != 2 || !params[0].is_string() || !params[1].is_string() {
+            return JsonError::new(ErrorCode::InvalidParams, None, id).into()
+        }
+
+        // Parse parameters
+        let Ok(target) = BigUint::from_str_radix(params[0].get::<String>().unwrap(), 10) else {
+            error!(target: "devnet::miner::rpc", "Failed to parse target");
+            return server_error(RpcError::TargetParseError, id, None)
+        };
+        let Some(block_bytes) = base64::decode(params[1].get::<String>().unwrap()) else {
+            error!(target: "devnet::miner::rpc", "Failed to parse block bytes");
+            return server_error(RpcError::BlockParseError, id, None)
+        };
+        let Ok(mut block) = deserialize_async::<BlockInfo>(&block_bytes).await else {
+            error!(target: "devnet::miner::rpc", "Failed to parse block");
+            return server_error(RpcError::BlockParseError, id, None)
+        };
+
+        // Aborts sent before this request don't concern it
+        while self.abort_rx.try_recv().is_ok() {}
+
+        // Wait until we are allowed to mine a block
+        let granted = smol::future::or(async { self.credits_rx.recv().await.is_ok() }, async {
+            let _ = self.abort_rx.recv().await;
+            false
+        })
+        .await;
+        if !granted {
+            debug!(target: "devnet::miner::rpc", "Node {} aborted its pending request", self.node);
+            return server_error(RpcError::MiningAborted, id, None)
+        }
+
+        // Mine provided block
+        let _miner_lock = self.miner_lock.lock().await;
+        info!(target: "devnet::miner::rpc", "Node {} mining block {} for target: {}", self.node, block.hash(), target);
+        let (stop_tx, stop_rx) = smol::channel::bounded(1);
+        let threads = self.threads;
+        let mining = smol::unblock(move || {
+            let result = mine_block(&target, &mut block, threads, &stop_rx);
+            (result, block)
+        });
+        let mined = smol::future::or(async { Some(mining.await) }, async {
+            let _ = self.abort_rx.recv().await;
+            None
+        })
+        .await;
+
+        let block = match mined {
+            Some((Ok(()), block)) => block,
+            Some((Err(e), _)) => {
+                error!(target: "devnet::miner::rpc", "Node {} failed mining block: {}", self.node, e);
+                self.credits_tx.send(()).await.unwrap();
+                return server_error(RpcError::MiningFailed, id, None)
+            }
+            None => {
+                // Stop the miner threads and give the block back, since
+                // nothing was mined for it
+                let _ = stop_tx.send(()).await;
+                self.credits_tx.send(()).await.unwrap();
+                debug!(target: "devnet::miner::rpc", "Node {} aborted mining", self.node);
+                return server_error(RpcError::MiningAborted, id, None)
+            }
+        };
+
+        let block_hash = block.hash();
+        info!(target: "devnet::miner::rpc", "Node {} mined block {} with nonce: {}", self.node, block_hash, block.header.nonce);
+        self.mined_tx.send(block_hash).await.unwrap();
+
+        // Return block nonce
+        JsonResponse::new(JsonValue::Number(block.header.nonce as f64), id).into()
+    }
+}
@@ -0,0 +1,136 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2025 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Stdio,
+};
+
+use log::info;
+use smol::{
+    io::AsyncWriteExt,
+    process::{Child, Command},
+};
+use url::Url;
+
+use darkfi::{Error, Result};
+
+use crate::genesis::DevnetWallet;
+
+/// Auxiliary daemons running as child processes of the devnet.
+/// They get killed when dropped.
+#[derive(Default)]
+pub struct ChildProcesses {
+    children: Vec<Child>,
+}
+
+impl ChildProcesses {
+    /// Start an `explorerd` instance syncing from given darkfid endpoint.
+    pub async fn start_explorerd(
+        &mut self,
+        binary: &Path,
+        datadir: &Path,
+        listen: &Url,
+        endpoint: &Url,
+    ) -> Result<()> {
+        info!(target: "devnet::process", "Starting explorerd on {}", listen);
+        let db_path = datadir.join("explorerd");
+        let child = Command::new(binary)
+            .arg("--rpc-listen")
+            .arg(listen.as_str())
+            .arg("--db-path")
+            .arg(&db_path)
+            .arg("--endpoint")
+            .arg(endpoint.as_str())
+            .stdout(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()?;
+        self.children.push(child);
+        Ok(())
+    }
+
+    /// Create a `drk` wallet holding the secret key of given devnet wallet,
+    /// using given darkfid endpoint, and scan the blockchain into it.
+    /// Returns the path of the generated `drk` configuration file.
+    pub async fn setup_drk(
+        &mut self,
+        binary: &Path,
+        datadir: &Path,
+        endpoint: &Url,
+        wallet: &DevnetWallet,
+    ) -> Result<PathBuf> {
+        info!(target: "devnet::process", "Setting up drk wallet for {}", wallet.name);
+        let dir = datadir.join("drk").join(&wallet.name);
+        fs::create_dir_all(&dir)?;
+        let config_path = dir.join("drk_config.toml");
+        let config = format!(
+            "network = \"localnet\"\n\n\
+             [network_config.\"localnet\"]\n\
+             wallet_path = \"{}\"\n\
+             wallet_pass = \"changeme\"\n\
+             endpoint = \"{}\"\n",
+            dir.join("wallet.db").display(),
+            endpoint,
+        );
+        fs::write(&config_path, config)?;
+
+        run_drk(binary, &config_path, &["wallet", "--initialize"], None).await?;
+        let secret = format!("{}\n", wallet.keypair.secret);
+        run_drk(binary, &config_path, &["wallet", "--import-secrets"], Some(&secret)).await?;
+        run_drk(binary, &config_path, &["wallet", "--default-address", "1"], None).await?;
+        run_drk(binary, &config_path, &["scan"], None).await?;
+
+        Ok(config_path)
+    }
+
+    /// Kill all the child processes.
+    pub async fn stop(&mut self) {
+        for mut child in self.children.drain(..) {
+            let _ = child.kill();
+            let _ = child.status().await;
+        }
+    }
+}
+
+/// Run a `drk` command to completion, optionally writing `stdin` to it.
+async fn run_drk(binary: &Path, config: &Path, args: &[&str], stdin: Option<&str>) -> Result<()> {
+    let mut child = Command::new(binary)
+        .arg("--config")
+        .arg(config)
+        .arg("--network")
+        .arg("localnet")
+        .args(args)
+        .stdin(if stdin.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    if let Some(input) = stdin {
+        // Dropping the handle closes the child stdin
+        let mut handle = child.stdin.take().unwrap();
+        handle.write_all(input.as_bytes()).await?;
+    }
+
+    let status = child.status().await?;
+    if !status.success() {
+        return Err(Error::Custom(format!("drk {} failed: {}", args.join(" "), status)))
+    }
+
+    Ok(())
+}
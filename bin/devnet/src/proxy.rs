@@ -0,0 +1,152 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2025 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use log::{debug, error, info};
+use smol::{
+    lock::Mutex,
+    net::{TcpListener, TcpStream},
+    Task,
+};
+
+use darkfi::{
+    system::{ExecutorPtr, StoppableTask, StoppableTaskPtr},
+    Error, Result,
+};
+
+/// Atomic pointer to a devnet link
+pub type LinkPtr = Arc<Link>;
+
+/// TCP proxy carrying the P2P connections between two devnet nodes.
+///
+/// Node `a` connects to node `b` through the link, so the devnet can
+/// cut their connections at any time to partition the network.
+pub struct Link {
+    /// Node connecting through the link
+    pub a: usize,
+    /// Node the link forwards connections to
+    pub b: usize,
+    /// Address the link listens on
+    pub listen: SocketAddr,
+    /// P2P inbound address of node `b`
+    target: SocketAddr,
+    /// Flag marking the link as cut
+    blocked: AtomicBool,
+    /// Forwarding tasks of the active connections
+    conns: Mutex<Vec<Task<()>>>,
+    /// Accept loop background task
+    task: StoppableTaskPtr,
+}
+
+impl Link {
+    pub fn new(a: usize, b: usize, listen: SocketAddr, target: SocketAddr) -> LinkPtr {
+        Arc::new(Self {
+            a,
+            b,
+            listen,
+            target,
+            blocked: AtomicBool::new(false),
+            conns: Mutex::new(vec![]),
+            task: StoppableTask::new(),
+        })
+    }
+
+    /// Bind the link listener and start accepting connections.
+    pub async fn start(self: Arc<Self>, ex: &ExecutorPtr) -> Result<()> {
+        let listener = TcpListener::bind(self.listen).await?;
+        info!(target: "devnet::proxy", "Link {} -> {} listening on {}", self.a, self.b, self.listen);
+
+        let self_ = self.clone();
+        let ex_ = ex.clone();
+        self.task.clone().start(
+            async move { self_.accept_loop(listener, ex_).await },
+            |res| async {
+                match res {
+                    Ok(()) | Err(Error::DetachedTaskStopped) => { /* Do nothing */ }
+                    Err(e) => error!(target: "devnet::proxy", "Link accept loop failed: {}", e),
+                }
+            },
+            Error::DetachedTaskStopped,
+            ex.clone(),
+        );
+
+        Ok(())
+    }
+
+    async fn accept_loop(&self, listener: TcpListener, ex: ExecutorPtr) -> Result<()> {
+        loop {
+            let (stream, _) = listener.accept().await?;
+
+            if self.is_blocked() {
+                debug!(target: "devnet::proxy", "Link {} -> {} is cut, dropping connection", self.a, self.b);
+                continue
+            }
+
+            let target = match TcpStream::connect(self.target).await {
+                Ok(v) => v,
+                Err(e) => {
+                    debug!(target: "devnet::proxy", "Link {} -> {} failed connecting: {}", self.a, self.b, e);
+                    continue
+                }
+            };
+
+            // Forward data both ways until either side closes. Dropping the
+            // task closes both streams.
+            let task = ex.spawn(async move {
+                let _ = smol::future::or(
+                    smol::io::copy(stream.clone(), target.clone()),
+                    smol::io::copy(target, stream),
+                )
+                .await;
+            });
+
+            let mut conns = self.conns.lock().await;
+            conns.retain(|conn| !conn.is_finished());
+            conns.push(task);
+        }
+    }
+
+    pub fn is_blocked(&self) -> bool {
+        self.blocked.load(Ordering::SeqCst)
+    }
+
+    /// Cut or restore the link. Cutting it closes all of its active
+    /// connections, and refuses new ones until it is restored.
+    pub async fn set_blocked(&self, blocked: bool) {
+        self.blocked.store(blocked, Ordering::SeqCst);
+        if blocked {
+            info!(target: "devnet::proxy", "Cutting link {} -> {}", self.a, self.b);
+            self.conns.lock().await.clear();
+        } else {
+            info!(target: "devnet::proxy", "Restoring link {} -> {}", self.a, self.b);
+        }
+    }
+
+    /// Stop the link, closing all of its connections.
+    pub async fn stop(&self) {
+        self.task.stop().await;
+        self.conns.lock().await.clear();
+    }
+}
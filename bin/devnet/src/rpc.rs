@@ -0,0 +1,278 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2025 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::collections::{HashMap, HashSet};
+
+use log::{debug, error};
+use smol::lock::MutexGuard;
+
+use darkfi::{
+    rpc::{
+        jsonrpc::{ErrorCode, JsonError, JsonRequest, JsonResponse, JsonResult},
+        openrpc::{RpcMethod, RpcParam, PING_METHOD},
+        server::RequestHandler,
+        util::JsonValue,
+    },
+    system::StoppableTaskPtr,
+};
+use darkfi_serial::async_trait;
+
+use crate::{
+    error::{server_error, RpcError},
+    Devnet,
+};
+
+/// Methods served over JSON-RPC, as advertised by `rpc.discover`
+const RPC_METHODS: &[RpcMethod] = &[
+    PING_METHOD,
+    RpcMethod {
+        name: "devnet.nodes",
+        summary: "Get the JSON-RPC endpoints of the nodes and explorerd",
+        params: &[],
+        result: "object",
+    },
+    RpcMethod {
+        name: "devnet.wallets",
+        summary: "Get the wallets funded in the genesis block",
+        params: &[],
+        result: "array",
+    },
+    RpcMethod {
+        name: "devnet.mine",
+        summary: "Mine the given number of blocks on the given node, returning their hashes",
+        params: &[
+            RpcParam { name: "node", schema: "integer" },
+            RpcParam { name: "count", schema: "integer" },
+        ],
+        result: "array",
+    },
+    RpcMethod {
+        name: "devnet.partition",
+        summary: "Partition the network into groups of nodes, one array of node indexes per group",
+        params: &[RpcParam { name: "group", schema: "array" }],
+        result: "boolean",
+    },
+    RpcMethod {
+        name: "devnet.heal",
+        summary: "Reconnect all the network partitions",
+        params: &[],
+        result: "boolean",
+    },
+    RpcMethod {
+        name: "devnet.shutdown",
+        summary: "Tear the devnet down and terminate the daemon",
+        params: &[],
+        result: "boolean",
+    },
+];
+
+#[async_trait]
+impl RequestHandler<()> for Devnet {
+    async fn handle_request(&self, req: JsonRequest) -> JsonResult {
+        debug!(target: "devnet::rpc", "--> {}", req.stringify().unwrap());
+
+        match req.method.as_str() {
+            "ping" => self.pong(req.id, req.params).await,
+            "devnet.nodes" => self.devnet_nodes(req.id, req.params).await,
+            "devnet.wallets" => self.devnet_wallets(req.id, req.params).await,
+            "devnet.mine" => self.devnet_mine(req.id, req.params).await,
+            "devnet.partition" => self.devnet_partition(req.id, req.params).await,
+            "devnet.heal" => self.devnet_heal(req.id, req.params).await,
+            "devnet.shutdown" => self.devnet_shutdown(req.id, req.params).await,
+            _ => JsonError::new(ErrorCode::MethodNotFound, None, req.id).into(),
+        }
+    }
+
+    async fn connections_mut(&self) -> MutexGuard<'life0, HashSet<StoppableTaskPtr>> {
+        self.rpc_connections.lock().await
+    }
+
+    fn methods(&self) -> Vec<RpcMethod> {
+        RPC_METHODS.to_vec()
+    }
+}
+
+/// Parse a node index out of given JSON value
+fn parse_index(value: &JsonValue) -> Option<usize> {
+    let index = *value.get::<f64>()?;
+    if index < 0.0 || index.fract() != 0.0 {
+        return None
+    }
+    Some(index as usize)
+}
+
+impl Devnet {
+    // RPCAPI:
+    // Returns the JSON-RPC endpoints of the devnet nodes, along with the
+    // `explorerd` one, if it's running.
+    //
+    // --> {"jsonrpc": "2.0", "method": "devnet.nodes", "params": [], "id": 1}
+    // <-- {"jsonrpc": "2.0", "result": {"nodes": ["tcp://127.0.0.1:28001", ...], "explorerd": null}, "id": 1}
+    async fn devnet_nodes(&self, id: u16, params: JsonValue) -> JsonResult {
+        if !params.is_array() || !params.get::<Vec<JsonValue>>().unwrap().is_empty() {
+            return JsonError::new(ErrorCode::InvalidParams, None, id).into()
+        }
+
+        let nodes =
+            (0..self.len()).map(|i| JsonValue::String(self.rpc_endpoint(i).to_string())).collect();
+        let explorerd = match self.explorerd_endpoint() {
+            Some(endpoint) => JsonValue::String(endpoint.to_string()),
+            None => JsonValue::Null,
+        };
+
+        let result = HashMap::from([
+            ("nodes".to_string(), JsonValue::Array(nodes)),
+            ("explorerd".to_string(), explorerd),
+        ]);
+        JsonResponse::new(JsonValue::Object(result), id).into()
+    }
+
+    // RPCAPI:
+    // Returns the wallets funded in the devnet genesis block, with their
+    // address, secret key and minted coin values. Block rewards go to
+    // the first wallet.
+    //
+    // --> {"jsonrpc": "2.0", "method": "devnet.wallets", "params": [], "id": 1}
+    // <-- {"jsonrpc": "2.0", "result": [{"name": "alice", "address": "...", "secret": "...", "amounts": [...]}], "id": 1}
+    async fn devnet_wallets(&self, id: u16, params: JsonValue) -> JsonResult {
+        if !params.is_array() || !params.get::<Vec<JsonValue>>().unwrap().is_empty() {
+            return JsonError::new(ErrorCode::InvalidParams, None, id).into()
+        }
+
+        let wallets = self
+            .wallets
+            .iter()
+            .map(|wallet| {
+                let amounts = wallet
+                    .amounts
+                    .iter()
+                    .map(|amount| JsonValue::String(amount.to_string()))
+                    .collect();
+                JsonValue::Object(HashMap::from([
+                    ("name".to_string(), JsonValue::String(wallet.name.clone())),
+                    ("address".to_string(), JsonValue::String(wallet.keypair.public.to_string())),
+                    ("secret".to_string(), JsonValue::String(wallet.keypair.secret.to_string())),
+                    ("amounts".to_string(), JsonValue::Array(amounts)),
+                ]))
+            })
+            .collect();
+
+        JsonResponse::new(JsonValue::Array(wallets), id).into()
+    }
+
+    // RPCAPI:
+    // Mines given number of blocks on given node, and returns their hashes
+    // once the node produced them all.
+    //
+    // --> {"jsonrpc": "2.0", "method": "devnet.mine", "params": [0, 2], "id": 1}
+    // <-- {"jsonrpc": "2.0", "result": ["hash", "hash"], "id": 1}
+    async fn devnet_mine(&self, id: u16, params: JsonValue) -> JsonResult {
+        if !params.is_array() {
+            return JsonError::new(ErrorCode::InvalidParams, None, id).into()
+        }
+        let params = params.get::<Vec<JsonValue>>().unwrap();
+        if params.len() != 2 || !params[0].is_number() || !params[1].is_number() {
+            return JsonError::new(ErrorCode::InvalidParams, None, id).into()
+        }
+        let (Some(node), Some(count)) = (parse_index(&params[0]), parse_index(&params[1])) else {
+            return JsonError::new(ErrorCode::InvalidParams, None, id).into()
+        };
+        if node >= self.len() {
+            return server_error(RpcError::UnknownNode, id, None)
+        }
+
+        let hashes = match self.mine(node, count).await {
+            Ok(hashes) => hashes,
+            Err(e) => {
+                error!(target: "devnet::rpc::devnet_mine", "Failed mining blocks: {}", e);
+                return server_error(RpcError::DevnetFailed, id, None)
+            }
+        };
+
+        let hashes = hashes.iter().map(|hash| JsonValue::String(hash.to_string())).collect();
+        JsonResponse::new(JsonValue::Array(hashes), id).into()
+    }
+
+    // RPCAPI:
+    // Partitions the network into given groups of nodes. Nodes of different
+    // groups get disconnected, and nodes not in any group get isolated.
+    // Returns `true` on success.
+    //
+    // --> {"jsonrpc": "2.0", "method": "devnet.partition", "params": [[0, 1], [2]], "id": 1}
+    // <-- {"jsonrpc": "2.0", "result": true, "id": 1}
+    async fn devnet_partition(&self, id: u16, params: JsonValue) -> JsonResult {
+        if !params.is_array() {
+            return JsonError::new(ErrorCode::InvalidParams, None, id).into()
+        }
+
+        let mut groups = vec![];
+        for group in params.get::<Vec<JsonValue>>().unwrap() {
+            let Some(group) = group.get::<Vec<JsonValue>>() else {
+                return JsonError::new(ErrorCode::InvalidParams, None, id).into()
+            };
+            let mut nodes = vec![];
+            for node in group {
+                let Some(node) = parse_index(node) else {
+                    return JsonError::new(ErrorCode::InvalidParams, None, id).into()
+                };
+                if node >= self.len() {
+                    return server_error(RpcError::UnknownNode, id, None)
+                }
+                nodes.push(node);
+            }
+            groups.push(nodes);
+        }
+
+        if let Err(e) = self.partition(&groups).await {
+            error!(target: "devnet::rpc::devnet_partition", "Failed partitioning network: {}", e);
+            return server_error(RpcError::DevnetFailed, id, None)
+        }
+
+        JsonResponse::new(JsonValue::Boolean(true), id).into()
+    }
+
+    // RPCAPI:
+    // Reconnects all the network partitions.
+    // Returns `true` on success.
+    //
+    // --> {"jsonrpc": "2.0", "method": "devnet.heal", "params": [], "id": 1}
+    // <-- {"jsonrpc": "2.0", "result": true, "id": 1}
+    async fn devnet_heal(&self, id: u16, params: JsonValue) -> JsonResult {
+        if !params.is_array() || !params.get::<Vec<JsonValue>>().unwrap().is_empty() {
+            return JsonError::new(ErrorCode::InvalidParams, None, id).into()
+        }
+
+        self.heal().await;
+        JsonResponse::new(JsonValue::Boolean(true), id).into()
+    }
+
+    // RPCAPI:
+    // Tears the devnet down and terminates the daemon.
+    // Returns `true` on success.
+    //
+    // --> {"jsonrpc": "2.0", "method": "devnet.shutdown", "params": [], "id": 1}
+    // <-- {"jsonrpc": "2.0", "result": true, "id": 1}
+    async fn devnet_shutdown(&self, id: u16, params: JsonValue) -> JsonResult {
+        if !params.is_array() || !params.get::<Vec<JsonValue>>().unwrap().is_empty() {
+            return JsonError::new(ErrorCode::InvalidParams, None, id).into()
+        }
+
+        self.request_shutdown();
+        JsonResponse::new(JsonValue::Boolean(true), id).into()
+    }
+}
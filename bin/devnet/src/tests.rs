@@ -0,0 +1,151 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2025 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{collections::HashMap, sync::Arc};
+
+use darkfi::{
+    rpc::{client::RpcClient, jsonrpc::JsonRequest, util::JsonValue},
+    system::msleep,
+    Result,
+};
+use darkfi_contract_test_harness::init_logger;
+use smol::Executor;
+
+use crate::{Devnet, DevnetConfig};
+
+/// Query every node with given JSON-RPC method, and map the results
+/// with `f`. Nodes which can't be reached yet map to `None`.
+async fn query_nodes<T>(
+    devnet: &Devnet,
+    method: &str,
+    f: impl Fn(&JsonValue) -> T,
+    ex: &Arc<Executor<'static>>,
+) -> Vec<Option<T>> {
+    let mut results = vec![];
+    for i in 0..devnet.len() {
+        let Ok(client) = RpcClient::new(devnet.rpc_endpoint(i), ex.clone()).await else {
+            results.push(None);
+            continue
+        };
+        let req = JsonRequest::new(method, JsonValue::Array(vec![]));
+        results.push(client.request(req).await.ok().map(|rep| f(&rep)));
+        client.stop().await;
+    }
+    results
+}
+
+/// Poll the nodes with given JSON-RPC method until the mapped results
+/// match `expected` or the retries run out, and return the last ones.
+async fn wait_nodes<T: PartialEq + Clone>(
+    devnet: &Devnet,
+    method: &str,
+    f: impl Fn(&JsonValue) -> T,
+    expected: &[T],
+    ex: &Arc<Executor<'static>>,
+) -> Vec<Option<T>> {
+    let expected: Vec<Option<T>> = expected.iter().cloned().map(Some).collect();
+    let mut results = vec![];
+    for _ in 0..60 {
+        results = query_nodes(devnet, method, &f, ex).await;
+        if results == expected {
+            break
+        }
+        msleep(500).await;
+    }
+    results
+}
+
+/// Wait until the best fork next block height of every node matches
+/// `expected`, and return the last polled heights.
+async fn wait_heights(
+    devnet: &Devnet,
+    expected: &[u32],
+    ex: &Arc<Executor<'static>>,
+) -> Vec<Option<u32>> {
+    let f = |rep: &JsonValue| *rep.get::<f64>().unwrap() as u32;
+    wait_nodes(devnet, "blockchain.best_fork_next_block_height", f, expected, ex).await
+}
+
+/// Wait until every node has a P2P channel to every other node.
+async fn wait_connected(devnet: &Devnet, ex: &Arc<Executor<'static>>) -> bool {
+    let f = |rep: &JsonValue| {
+        let info = rep.get::<HashMap<String, JsonValue>>().unwrap();
+        info["channels"].get::<Vec<JsonValue>>().unwrap().len()
+    };
+    let expected = vec![devnet.len() - 1; devnet.len()];
+    let connected = wait_nodes(devnet, "p2p.get_info", f, &expected, ex).await;
+    connected.iter().all(|c| *c == Some(devnet.len() - 1))
+}
+
+async fn devnet_partition_real(ex: Arc<Executor<'static>>) -> Result<()> {
+    init_logger();
+
+    let config = DevnetConfig {
+        base_port: 0,
+        threshold: 6,
+        miner_threads: 2,
+        wallets: vec![("alice".to_string(), vec![42])],
+        ..Default::default()
+    };
+    let devnet = Devnet::start(config, &ex).await?;
+    assert_eq!(devnet.len(), 3);
+    assert!(wait_connected(&devnet, &ex).await);
+
+    // Blocks mined by one node reach all the others
+    let hashes = devnet.mine(0, 2).await?;
+    assert_eq!(hashes.len(), 2);
+    assert_eq!(wait_heights(&devnet, &[3, 3, 3], &ex).await, vec![Some(3), Some(3), Some(3)]);
+
+    // Each side of a partition extends its own fork
+    devnet.partition(&[vec![0, 1], vec![2]]).await?;
+    devnet.mine(0, 1).await?;
+    devnet.mine(2, 2).await?;
+    assert_eq!(wait_heights(&devnet, &[4, 4, 5], &ex).await, vec![Some(4), Some(4), Some(5)]);
+
+    // Once healed, the nodes converge to the longest fork
+    devnet.heal().await;
+    assert!(wait_connected(&devnet, &ex).await);
+    devnet.mine(2, 1).await?;
+    assert_eq!(wait_heights(&devnet, &[6, 6, 6], &ex).await, vec![Some(6), Some(6), Some(6)]);
+
+    // Unknown nodes are rejected
+    assert!(devnet.mine(3, 1).await.is_err());
+    assert!(devnet.partition(&[vec![0, 3]]).await.is_err());
+
+    devnet.stop().await?;
+
+    // Thanks for reading
+    Ok(())
+}
+
+#[test]
+fn devnet_partition() -> Result<()> {
+    let ex = Arc::new(Executor::new());
+    let (signal, shutdown) = smol::channel::unbounded::<()>();
+
+    easy_parallel::Parallel::new().each(0..4, |_| smol::block_on(ex.run(shutdown.recv()))).finish(
+        || {
+            smol::block_on(async {
+                devnet_partition_real(ex.clone()).await.unwrap();
+                drop(signal);
+            })
+        },
+    );
+
+    Ok(())
+}
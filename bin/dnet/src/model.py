@@ -155,6 +155,14 @@ class Model:
                 key = (f'{name}', 'outbound')
                 event[key] = f'peer discovery: {state} (attempt {attempt})'
                 logging.debug(f'{current_time}  peer_discovery: {state} (attempt {attempt})')
+            case 'resource_throttled' | 'resource_disconnected' | 'resource_banned':
+                addr = info['chan']['addr']
+                protocol = info['protocol']
+                resource = info['resource']
+                delay = info['delay']
+                action = event.removeprefix('resource_')
+                logging.debug(f'{current_time}  {action}: addr={addr} '
+                              f'{protocol} {resource} (delay {delay}ms)')
 
 
     def add_lilith(self, lilith):
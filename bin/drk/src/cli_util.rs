@@ -196,6 +196,11 @@ pub fn generate_completions(shell: &str) -> Result<()> {
         .long("half-split")
         .help("Split the output coin into two equal halves");
 
+    let expires_in = Arg::with_name("expires-in")
+        .long("expires-in")
+        .takes_value(true)
+        .help("Number of blocks after which the transaction expires");
+
     let transfer =
         SubCommand::with_name("transfer").about("Create a payment transaction").args(&vec![
             amount.clone(),
@@ -204,6 +209,7 @@ pub fn generate_completions(shell: &str) -> Result<()> {
             spend_hook.clone(),
             user_data.clone(),
             half_split,
+            expires_in.clone(),
         ]);
 
     // Otc
@@ -223,8 +229,9 @@ pub fn generate_completions(shell: &str) -> Result<()> {
         .about("Initialize the first half of the atomic swap")
         .args(&vec![value_pair, token_pair]);
 
-    let join =
-        SubCommand::with_name("join").about("Build entire swap tx given the first half from stdin");
+    let join = SubCommand::with_name("join")
+        .about("Build entire swap tx given the first half from stdin")
+        .args(&vec![expires_in.clone()]);
 
     let inspect = SubCommand::with_name("inspect")
         .about("Inspect a swap half or the full swap tx from stdin");
@@ -299,7 +306,7 @@ pub fn generate_completions(shell: &str) -> Result<()> {
 
     let mint = SubCommand::with_name("mint")
         .about("Mint an imported DAO on-chain")
-        .args(&vec![name.clone()]);
+        .args(&vec![name.clone(), expires_in.clone()]);
 
     let duration = Arg::with_name("duration").help("Duration of the proposal, in block windows");
 
@@ -332,6 +339,7 @@ pub fn generate_completions(shell: &str) -> Result<()> {
         bulla.clone(),
         export,
         mint_proposal,
+        expires_in.clone(),
     ]);
 
     let proposal_import = SubCommand::with_name("proposal-import")
@@ -346,12 +354,16 @@ pub fn generate_completions(shell: &str) -> Result<()> {
         bulla.clone(),
         vote,
         vote_weight,
+        expires_in.clone(),
     ]);
 
     let early = Arg::with_name("early").long("early").help("Execute the proposal early");
 
-    let exec =
-        SubCommand::with_name("exec").about("Execute a DAO proposal").args(&vec![bulla, early]);
+    let exec = SubCommand::with_name("exec").about("Execute a DAO proposal").args(&vec![
+        bulla,
+        early,
+        expires_in.clone(),
+    ]);
 
     let spend_hook_cmd = SubCommand::with_name("spend-hook")
         .about("Print the DAO contract base58-encoded spend hook");
@@ -475,13 +487,19 @@ pub fn generate_completions(shell: &str) -> Result<()> {
 
     let recipient = Arg::with_name("recipient").help("Recipient of the minted tokens");
 
-    let mint = SubCommand::with_name("mint")
-        .about("Mint tokens")
-        .args(&vec![token, amount, recipient, spend_hook, user_data]);
+    let mint = SubCommand::with_name("mint").about("Mint tokens").args(&vec![
+        token,
+        amount,
+        recipient,
+        spend_hook,
+        user_data,
+        expires_in.clone(),
+    ]);
 
     let token = Arg::with_name("token").help("Token ID to freeze");
 
-    let freeze = SubCommand::with_name("freeze").about("Freeze a token mint").arg(token);
+    let freeze =
+        SubCommand::with_name("freeze").about("Freeze a token mint").args(&vec![token, expires_in]);
 
     let token = SubCommand::with_name("token").about("Token functionalities").subcommands(vec![
         import,
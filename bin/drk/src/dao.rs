@@ -2046,7 +2046,7 @@ impl Drk {
     }
 
     /// Mint a DAO on-chain.
-    pub async fn dao_mint(&self, name: &str) -> Result<Transaction> {
+    pub async fn dao_mint(&self, name: &str, expiry: Option<u32>) -> Result<Transaction> {
         // Retrieve the dao record
         let dao = self.get_dao_by_name(name).await?;
 
@@ -2121,6 +2121,7 @@ impl Drk {
 
         // Create the TransactionBuilder containing above call
         let mut tx_builder = TransactionBuilder::new(ContractCallLeaf { call, proofs }, vec![])?;
+        tx_builder.set_expiry(expiry);
 
         // We first have to execute the fee-less tx to gather its used gas, and then we feed
         // it into the fee-creating function.
@@ -2317,7 +2318,11 @@ impl Drk {
     }
 
     /// Create a DAO transfer proposal transaction.
-    pub async fn dao_transfer_proposal_tx(&self, proposal: &ProposalRecord) -> Result<Transaction> {
+    pub async fn dao_transfer_proposal_tx(
+        &self,
+        proposal: &ProposalRecord,
+        expiry: Option<u32>,
+    ) -> Result<Transaction> {
         // Check we know the plaintext data
         if proposal.data.is_none() {
             return Err(Error::Custom(
@@ -2517,6 +2522,7 @@ impl Drk {
 
         // Create the TransactionBuilder containing above call
         let mut tx_builder = TransactionBuilder::new(ContractCallLeaf { call, proofs }, vec![])?;
+        tx_builder.set_expiry(expiry);
 
         // We first have to execute the fee-less tx to gather its used gas, and then we feed
         // it into the fee-creating function.
@@ -2542,7 +2548,11 @@ impl Drk {
     }
 
     /// Create a DAO generic proposal transaction.
-    pub async fn dao_generic_proposal_tx(&self, proposal: &ProposalRecord) -> Result<Transaction> {
+    pub async fn dao_generic_proposal_tx(
+        &self,
+        proposal: &ProposalRecord,
+        expiry: Option<u32>,
+    ) -> Result<Transaction> {
         // Fetch DAO and check its deployed
         let Ok(dao) = self.get_dao_by_bulla(&proposal.proposal.dao_bulla).await else {
             return Err(Error::Custom(format!(
@@ -2705,6 +2715,7 @@ impl Drk {
 
         // Create the TransactionBuilder containing above call
         let mut tx_builder = TransactionBuilder::new(ContractCallLeaf { call, proofs }, vec![])?;
+        tx_builder.set_expiry(expiry);
 
         // We first have to execute the fee-less tx to gather its used gas, and then we feed
         // it into the fee-creating function.
@@ -2735,6 +2746,7 @@ impl Drk {
         proposal_bulla: &DaoProposalBulla,
         vote_option: bool,
         weight: Option<u64>,
+        expiry: Option<u32>,
     ) -> Result<Transaction> {
         // Feth the proposal and check its deployed
         let Ok(proposal) = self.get_dao_proposal_by_bulla(proposal_bulla).await else {
@@ -2908,6 +2920,7 @@ impl Drk {
 
         // Create the TransactionBuilder containing above call
         let mut tx_builder = TransactionBuilder::new(ContractCallLeaf { call, proofs }, vec![])?;
+        tx_builder.set_expiry(expiry);
 
         // We first have to execute the fee-less tx to gather its used gas, and then we feed
         // it into the fee-creating function.
@@ -2937,6 +2950,7 @@ impl Drk {
         &self,
         proposal: &ProposalRecord,
         early: bool,
+        expiry: Option<u32>,
     ) -> Result<Transaction> {
         if proposal.leaf_position.is_none() ||
             proposal.money_snapshot_tree.is_none() ||
@@ -3249,6 +3263,7 @@ impl Drk {
                 ),
             ],
         )?;
+        tx_builder.set_expiry(expiry);
 
         // We first have to execute the fee-less tx to gather its used gas, and then we feed
         // it into the fee-creating function.
@@ -3283,6 +3298,7 @@ impl Drk {
         &self,
         proposal: &ProposalRecord,
         early: bool,
+        expiry: Option<u32>,
     ) -> Result<Transaction> {
         if proposal.leaf_position.is_none() ||
             proposal.money_snapshot_tree.is_none() ||
@@ -3424,6 +3440,7 @@ impl Drk {
             ContractCallLeaf { call: exec_call, proofs: exec_proofs },
             vec![],
         )?;
+        tx_builder.set_expiry(expiry);
 
         // We first have to execute the fee-less tx to gather its used gas, and then we feed
         // it into the fee-creating function.
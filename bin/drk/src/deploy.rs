@@ -138,6 +138,7 @@ impl Drk {
         deploy_auth: u64,
         wasm_bincode: Vec<u8>,
         deploy_ix: Vec<u8>,
+        expiry: Option<u32>,
     ) -> Result<Transaction> {
         // Fetch the keypair
         let deploy_keypair = self.get_deploy_auth(deploy_auth).await?;
@@ -152,6 +153,7 @@ impl Drk {
         let call = ContractCall { contract_id: *DEPLOYOOOR_CONTRACT_ID, data };
         let mut tx_builder =
             TransactionBuilder::new(ContractCallLeaf { call, proofs: vec![] }, vec![])?;
+        tx_builder.set_expiry(expiry);
 
         let mut tx = tx_builder.build()?;
         let sigs = tx.create_sigs(&[deploy_keypair.secret])?;
@@ -161,7 +163,11 @@ impl Drk {
     }
 
     /// Create a feeless contract redeployment lock transaction.
-    pub async fn lock_contract(&self, deploy_auth: u64) -> Result<Transaction> {
+    pub async fn lock_contract(
+        &self,
+        deploy_auth: u64,
+        expiry: Option<u32>,
+    ) -> Result<Transaction> {
         // Fetch the keypair
         let deploy_keypair = self.get_deploy_auth(deploy_auth).await?;
 
@@ -175,6 +181,7 @@ impl Drk {
         let call = ContractCall { contract_id: *DEPLOYOOOR_CONTRACT_ID, data };
         let mut tx_builder =
             TransactionBuilder::new(ContractCallLeaf { call, proofs: vec![] }, vec![])?;
+        tx_builder.set_expiry(expiry);
 
         let mut tx = tx_builder.build()?;
         let sigs = tx.create_sigs(&[deploy_keypair.secret])?;
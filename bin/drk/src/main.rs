@@ -183,6 +183,10 @@ enum Subcmd {
         #[structopt(long)]
         /// Split the output coin into two equal halves
         half_split: bool,
+
+        #[structopt(long)]
+        /// Number of blocks after which the transaction expires
+        expires_in: Option<u32>,
     },
 
     /// OTC atomic swap
@@ -264,7 +268,11 @@ enum OtcSubcmd {
     },
 
     /// Build entire swap tx given the first half from stdin
-    Join,
+    Join {
+        #[structopt(long)]
+        /// Number of blocks after which the transaction expires
+        expires_in: Option<u32>,
+    },
 
     /// Inspect a swap half or the full swap tx from stdin
     Inspect,
@@ -319,6 +327,10 @@ enum DaoSubcmd {
     Mint {
         /// Name identifier for the DAO
         name: String,
+
+        #[structopt(long)]
+        /// Number of blocks after which the transaction expires
+        expires_in: Option<u32>,
     },
 
     /// Create a transfer proposal for a DAO
@@ -375,6 +387,10 @@ enum DaoSubcmd {
         #[structopt(long)]
         /// Create the proposal transaction
         mint_proposal: bool,
+
+        #[structopt(long)]
+        /// Number of blocks after which the transaction expires
+        expires_in: Option<u32>,
     },
 
     /// Import a base64 encoded and encrypted proposal from stdin
@@ -390,6 +406,10 @@ enum DaoSubcmd {
 
         /// Optional vote weight (amount of governance tokens)
         vote_weight: Option<String>,
+
+        #[structopt(long)]
+        /// Number of blocks after which the transaction expires
+        expires_in: Option<u32>,
     },
 
     /// Execute a DAO proposal
@@ -400,6 +420,10 @@ enum DaoSubcmd {
         #[structopt(long)]
         /// Execute the proposal early
         early: bool,
+
+        #[structopt(long)]
+        /// Number of blocks after which the transaction expires
+        expires_in: Option<u32>,
     },
 
     /// Print the DAO contract base58-encoded spend hook
@@ -508,12 +532,20 @@ enum TokenSubcmd {
 
         /// Optional user data to use
         user_data: Option<String>,
+
+        #[structopt(long)]
+        /// Number of blocks after which the transaction expires
+        expires_in: Option<u32>,
     },
 
     /// Freeze a token mint
     Freeze {
         /// Token ID to freeze
         token: String,
+
+        #[structopt(long)]
+        /// Number of blocks after which the transaction expires
+        expires_in: Option<u32>,
     },
 }
 
@@ -535,12 +567,20 @@ enum ContractSubcmd {
 
         /// Path to serialized deploy instruction
         deploy_ix: String,
+
+        #[structopt(long)]
+        /// Number of blocks after which the transaction expires
+        expires_in: Option<u32>,
     },
 
     /// Lock a smart contract
     Lock {
         /// Contract ID (deploy authority)
         deploy_auth: u64,
+
+        #[structopt(long)]
+        /// Number of blocks after which the transaction expires
+        expires_in: Option<u32>,
     },
 }
 
@@ -976,7 +1016,15 @@ async fn realmain(args: Args, ex: Arc<smol::Executor<'static>>) -> Result<()> {
             Ok(())
         }
 
-        Subcmd::Transfer { amount, token, recipient, spend_hook, user_data, half_split } => {
+        Subcmd::Transfer {
+            amount,
+            token,
+            recipient,
+            spend_hook,
+            user_data,
+            half_split,
+            expires_in,
+        } => {
             let drk = new_wallet(
                 blockchain_config.wallet_path,
                 blockchain_config.wallet_pass,
@@ -1039,8 +1087,9 @@ async fn realmain(args: Args, ex: Arc<smol::Executor<'static>>) -> Result<()> {
                 None => None,
             };
 
+            let expiry = drk.expiry_height(expires_in).await?;
             let tx = match drk
-                .transfer(&amount, token_id, rcpt, spend_hook, user_data, half_split)
+                .transfer(&amount, token_id, rcpt, spend_hook, user_data, half_split, expiry)
                 .await
             {
                 Ok(t) => t,
@@ -1080,7 +1129,7 @@ async fn realmain(args: Args, ex: Arc<smol::Executor<'static>>) -> Result<()> {
                 drk.stop_rpc_client().await
             }
 
-            OtcSubcmd::Join => {
+            OtcSubcmd::Join { expires_in } => {
                 let mut buf = String::new();
                 stdin().read_to_string(&mut buf)?;
                 let Some(bytes) = base64::decode(buf.trim()) else {
@@ -1098,7 +1147,8 @@ async fn realmain(args: Args, ex: Arc<smol::Executor<'static>>) -> Result<()> {
                     args.fun,
                 )
                 .await;
-                let tx = match drk.join_swap(partial, None, None, None).await {
+                let expiry = drk.expiry_height(expires_in).await?;
+                let tx = match drk.join_swap(partial, None, None, None, expiry).await {
                     Ok(tx) => tx,
                     Err(e) => {
                         eprintln!("Failed to create a join swap transaction: {e:?}");
@@ -1359,7 +1409,7 @@ async fn realmain(args: Args, ex: Arc<smol::Executor<'static>>) -> Result<()> {
                 Ok(())
             }
 
-            DaoSubcmd::Mint { name } => {
+            DaoSubcmd::Mint { name, expires_in } => {
                 let drk = new_wallet(
                     blockchain_config.wallet_path,
                     blockchain_config.wallet_pass,
@@ -1368,7 +1418,8 @@ async fn realmain(args: Args, ex: Arc<smol::Executor<'static>>) -> Result<()> {
                     args.fun,
                 )
                 .await;
-                let tx = match drk.dao_mint(&name).await {
+                let expiry = drk.expiry_height(expires_in).await?;
+                let tx = match drk.dao_mint(&name, expiry).await {
                     Ok(tx) => tx,
                     Err(e) => {
                         eprintln!("Failed to mint DAO: {e:?}");
@@ -1531,7 +1582,7 @@ async fn realmain(args: Args, ex: Arc<smol::Executor<'static>>) -> Result<()> {
                 Ok(())
             }
 
-            DaoSubcmd::Proposal { bulla, export, mint_proposal } => {
+            DaoSubcmd::Proposal { bulla, export, mint_proposal, expires_in } => {
                 let bulla = match DaoProposalBulla::from_str(&bulla) {
                     Ok(b) => b,
                     Err(e) => {
@@ -1568,11 +1619,13 @@ async fn realmain(args: Args, ex: Arc<smol::Executor<'static>>) -> Result<()> {
                 }
 
                 if mint_proposal {
+                    let expiry = drk.expiry_height(expires_in).await?;
+
                     // Identify proposal type by its auth calls
                     for call in &proposal.proposal.auth_calls {
                         // We only support transfer right now
                         if call.function_code == DaoFunction::AuthMoneyTransfer as u8 {
-                            let tx = match drk.dao_transfer_proposal_tx(&proposal).await {
+                            let tx = match drk.dao_transfer_proposal_tx(&proposal, expiry).await {
                                 Ok(tx) => tx,
                                 Err(e) => {
                                     eprintln!("Failed to create DAO transfer proposal: {e:?}");
@@ -1587,7 +1640,7 @@ async fn realmain(args: Args, ex: Arc<smol::Executor<'static>>) -> Result<()> {
 
                     // If proposal has no auth calls, we consider it a generic one
                     if proposal.proposal.auth_calls.is_empty() {
-                        let tx = match drk.dao_generic_proposal_tx(&proposal).await {
+                        let tx = match drk.dao_generic_proposal_tx(&proposal, expiry).await {
                             Ok(tx) => tx,
                             Err(e) => {
                                 eprintln!("Failed to create DAO generic proposal: {e:?}");
@@ -1787,7 +1840,7 @@ async fn realmain(args: Args, ex: Arc<smol::Executor<'static>>) -> Result<()> {
                 exit(2);
             }
 
-            DaoSubcmd::Vote { bulla, vote, vote_weight } => {
+            DaoSubcmd::Vote { bulla, vote, vote_weight, expires_in } => {
                 let bulla = match DaoProposalBulla::from_str(&bulla) {
                     Ok(b) => b,
                     Err(e) => {
@@ -1821,7 +1874,8 @@ async fn realmain(args: Args, ex: Arc<smol::Executor<'static>>) -> Result<()> {
                     args.fun,
                 )
                 .await;
-                let tx = match drk.dao_vote(&bulla, vote, weight).await {
+                let expiry = drk.expiry_height(expires_in).await?;
+                let tx = match drk.dao_vote(&bulla, vote, weight, expiry).await {
                     Ok(tx) => tx,
                     Err(e) => {
                         eprintln!("Failed to create DAO Vote transaction: {e:?}");
@@ -1833,7 +1887,7 @@ async fn realmain(args: Args, ex: Arc<smol::Executor<'static>>) -> Result<()> {
                 drk.stop_rpc_client().await
             }
 
-            DaoSubcmd::Exec { bulla, early } => {
+            DaoSubcmd::Exec { bulla, early, expires_in } => {
                 let bulla = match DaoProposalBulla::from_str(&bulla) {
                     Ok(b) => b,
                     Err(e) => {
@@ -1851,12 +1905,13 @@ async fn realmain(args: Args, ex: Arc<smol::Executor<'static>>) -> Result<()> {
                 )
                 .await;
                 let proposal = drk.get_dao_proposal_by_bulla(&bulla).await?;
+                let expiry = drk.expiry_height(expires_in).await?;
 
                 // Identify proposal type by its auth calls
                 for call in &proposal.proposal.auth_calls {
                     // We only support transfer right now
                     if call.function_code == DaoFunction::AuthMoneyTransfer as u8 {
-                        let tx = match drk.dao_exec_transfer(&proposal, early).await {
+                        let tx = match drk.dao_exec_transfer(&proposal, early, expiry).await {
                             Ok(tx) => tx,
                             Err(e) => {
                                 eprintln!("Failed to execute DAO transfer proposal: {e:?}");
@@ -1871,7 +1926,7 @@ async fn realmain(args: Args, ex: Arc<smol::Executor<'static>>) -> Result<()> {
 
                 // If proposal has no auth calls, we consider it a generic one
                 if proposal.proposal.auth_calls.is_empty() {
-                    let tx = match drk.dao_exec_generic(&proposal, early).await {
+                    let tx = match drk.dao_exec_generic(&proposal, early, expiry).await {
                         Ok(tx) => tx,
                         Err(e) => {
                             eprintln!("Failed to execute DAO generic proposal: {e:?}");
@@ -2376,7 +2431,7 @@ async fn realmain(args: Args, ex: Arc<smol::Executor<'static>>) -> Result<()> {
                 Ok(())
             }
 
-            TokenSubcmd::Mint { token, amount, recipient, spend_hook, user_data } => {
+            TokenSubcmd::Mint { token, amount, recipient, spend_hook, user_data, expires_in } => {
                 let drk = new_wallet(
                     blockchain_config.wallet_path,
                     blockchain_config.wallet_pass,
@@ -2439,7 +2494,10 @@ async fn realmain(args: Args, ex: Arc<smol::Executor<'static>>) -> Result<()> {
                     None => None,
                 };
 
-                let tx = match drk.mint_token(&amount, rcpt, token_id, spend_hook, user_data).await
+                let expiry = drk.expiry_height(expires_in).await?;
+                let tx = match drk
+                    .mint_token(&amount, rcpt, token_id, spend_hook, user_data, expiry)
+                    .await
                 {
                     Ok(tx) => tx,
                     Err(e) => {
@@ -2453,7 +2511,7 @@ async fn realmain(args: Args, ex: Arc<smol::Executor<'static>>) -> Result<()> {
                 drk.stop_rpc_client().await
             }
 
-            TokenSubcmd::Freeze { token } => {
+            TokenSubcmd::Freeze { token, expires_in } => {
                 let drk = new_wallet(
                     blockchain_config.wallet_path,
                     blockchain_config.wallet_pass,
@@ -2470,7 +2528,8 @@ async fn realmain(args: Args, ex: Arc<smol::Executor<'static>>) -> Result<()> {
                     }
                 };
 
-                let tx = match drk.freeze_token(token_id).await {
+                let expiry = drk.expiry_height(expires_in).await?;
+                let tx = match drk.freeze_token(token_id, expiry).await {
                     Ok(tx) => tx,
                     Err(e) => {
                         eprintln!("Failed to create token freeze transaction: {e:?}");
@@ -2531,7 +2590,7 @@ async fn realmain(args: Args, ex: Arc<smol::Executor<'static>>) -> Result<()> {
                 Ok(())
             }
 
-            ContractSubcmd::Deploy { deploy_auth, wasm_path, deploy_ix } => {
+            ContractSubcmd::Deploy { deploy_auth, wasm_path, deploy_ix, expires_in } => {
                 // Read the wasm bincode and deploy instruction
                 let wasm_bin = smol::fs::read(expand_path(&wasm_path)?).await?;
                 let deploy_ix = smol::fs::read(expand_path(&deploy_ix)?).await?;
@@ -2545,13 +2604,15 @@ async fn realmain(args: Args, ex: Arc<smol::Executor<'static>>) -> Result<()> {
                 )
                 .await;
 
-                let mut tx = match drk.deploy_contract(deploy_auth, wasm_bin, deploy_ix).await {
-                    Ok(v) => v,
-                    Err(e) => {
-                        eprintln!("Error creating contract deployment tx: {}", e);
-                        exit(2);
-                    }
-                };
+                let expiry = drk.expiry_height(expires_in).await?;
+                let mut tx =
+                    match drk.deploy_contract(deploy_auth, wasm_bin, deploy_ix, expiry).await {
+                        Ok(v) => v,
+                        Err(e) => {
+                            eprintln!("Error creating contract deployment tx: {}", e);
+                            exit(2);
+                        }
+                    };
 
                 if let Err(e) = drk.attach_fee(&mut tx).await {
                     eprintln!("Failed to attach the fee call to the transaction: {e:?}");
@@ -2563,7 +2624,7 @@ async fn realmain(args: Args, ex: Arc<smol::Executor<'static>>) -> Result<()> {
                 drk.stop_rpc_client().await
             }
 
-            ContractSubcmd::Lock { deploy_auth } => {
+            ContractSubcmd::Lock { deploy_auth, expires_in } => {
                 let drk = new_wallet(
                     blockchain_config.wallet_path,
                     blockchain_config.wallet_pass,
@@ -2573,7 +2634,8 @@ async fn realmain(args: Args, ex: Arc<smol::Executor<'static>>) -> Result<()> {
                 )
                 .await;
 
-                let mut tx = match drk.lock_contract(deploy_auth).await {
+                let expiry = drk.expiry_height(expires_in).await?;
+                let mut tx = match drk.lock_contract(deploy_auth, expiry).await {
                     Ok(v) => v,
                     Err(e) => {
                         eprintln!("Error creating contract lock tx: {}", e);
@@ -502,6 +502,14 @@ impl Drk {
         Ok(next_height)
     }
 
+    /// Auxiliary function to compute a transaction expiry height, using
+    /// provided number of blocks after current best fork next height.
+    pub async fn expiry_height(&self, expires_in: Option<u32>) -> Result<Option<u32>> {
+        let Some(expires_in) = expires_in else { return Ok(None) };
+        let next_height = self.get_next_block_height().await?;
+        Ok(Some(next_height.saturating_add(expires_in)))
+    }
+
     /// Queries darkfid for currently configured block target time.
     pub async fn get_block_target(&self) -> Result<u32> {
         let rep = self
@@ -181,6 +181,7 @@ impl Drk {
         user_data_blind_send: Option<BaseBlind>,
         spend_hook_recv: Option<FuncId>,
         user_data_recv: Option<pallas::Base>,
+        expiry: Option<u32>,
     ) -> Result<Transaction> {
         // Our side of the tx in the pairs is the second half, so we try to find
         // an unspent coin like that in our wallet.
@@ -277,6 +278,7 @@ impl Drk {
         let call = ContractCall { contract_id: *MONEY_CONTRACT_ID, data };
         let mut tx_builder =
             TransactionBuilder::new(ContractCallLeaf { call, proofs: full_proofs }, vec![])?;
+        tx_builder.set_expiry(expiry);
         let mut tx = tx_builder.build()?;
 
         // Sign the transaction and return it
@@ -220,6 +220,7 @@ impl Drk {
         token_id: TokenId,
         spend_hook: Option<FuncId>,
         user_data: Option<pallas::Base>,
+        expiry: Option<u32>,
     ) -> Result<Transaction> {
         // Decode provided amount
         let amount = decode_base10(amount, BALANCE_BASE10_DECIMALS, false)?;
@@ -309,6 +310,7 @@ impl Drk {
                 None,
             )],
         )?;
+        tx_builder.set_expiry(expiry);
 
         // We first have to execute the fee-less tx to gather its used gas, and then we feed
         // it into the fee-creating function.
@@ -337,7 +339,11 @@ impl Drk {
     }
 
     /// Create a token freeze transaction. Returns the transaction object on success.
-    pub async fn freeze_token(&self, token_id: TokenId) -> Result<Transaction> {
+    pub async fn freeze_token(
+        &self,
+        token_id: TokenId,
+        expiry: Option<u32>,
+    ) -> Result<Transaction> {
         // Grab token ID mint authority and attributes
         let token_mint_authority = self.get_token_mint_authority(&token_id).await?;
         let token_attrs =
@@ -391,6 +397,7 @@ impl Drk {
             ContractCallLeaf { call: freeze_call, proofs: freeze_debris.proofs },
             vec![],
         )?;
+        tx_builder.set_expiry(expiry);
 
         // We first have to execute the fee-less tx to gather its used gas, and then we feed
         // it into the fee-creating function.
@@ -46,6 +46,7 @@ impl Drk {
         spend_hook: Option<FuncId>,
         user_data: Option<pallas::Base>,
         half_split: bool,
+        expiry: Option<u32>,
     ) -> Result<Transaction> {
         // First get all unspent OwnCoins to see what our balance is
         let owncoins = self.get_token_coins(&token_id).await?;
@@ -133,6 +134,7 @@ impl Drk {
         // Create the TransactionBuilder containing the `Transfer` call
         let mut tx_builder =
             TransactionBuilder::new(ContractCallLeaf { call, proofs: secrets.proofs }, vec![])?;
+        tx_builder.set_expiry(expiry);
 
         // We first have to execute the fee-less tx to gather its used gas, and then we feed
         // it into the fee-creating function.
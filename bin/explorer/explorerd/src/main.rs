@@ -36,6 +36,7 @@ use darkfi::{
     cli_desc,
     rpc::{
         client::RpcClient,
+        metrics::start_exporter,
         server::{listen_and_serve, RequestHandler},
         settings::RpcSettingsOpt,
     },
@@ -130,6 +131,10 @@ struct Args {
     /// darkfid JSON-RPC endpoint
     endpoint: Url,
 
+    #[structopt(long)]
+    /// Optional Prometheus metrics listen URL
+    metrics_listen: Option<Url>,
+
     #[structopt(short, long)]
     /// Set log file to output into
     log: Option<String>,
@@ -346,6 +351,12 @@ async fn realmain(args: Args, ex: Arc<smol::Executor<'static>>) -> Result<()> {
         ex.clone(),
     );
 
+    // Metrics exporter
+    let metrics_task = args.metrics_listen.map(|listen| {
+        info!(target: "explorerd", "Starting metrics exporter");
+        start_exporter(listen, ex.clone())
+    });
+
     // Sync blocks
     info!(target: "explorerd", "Syncing blocks from darkfid...");
     if let Err(e) = explorer.sync_blocks(args.reset).await {
@@ -374,6 +385,11 @@ async fn realmain(args: Args, ex: Arc<smol::Executor<'static>>) -> Result<()> {
     info!(target: "explorerd", "Stopping JSON-RPC server...");
     rpc_task.stop().await;
 
+    if let Some(metrics_task) = metrics_task {
+        info!(target: "explorerd", "Stopping metrics exporter...");
+        metrics_task.stop().await;
+    }
+
     info!(target: "explorerd", "Stopping darkfid listener...");
     listener_task.stop().await;
 
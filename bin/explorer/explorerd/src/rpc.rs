@@ -26,6 +26,7 @@ use tinyjson::JsonValue;
 use darkfi::{
     rpc::{
         jsonrpc::{ErrorCode, JsonError, JsonRequest, JsonResponse, JsonResult},
+        openrpc::{RpcMethod, RpcParam, PING_METHOD},
         server::RequestHandler,
     },
     system::StoppableTaskPtr,
@@ -37,6 +38,83 @@ use crate::{
     Explorerd,
 };
 
+/// Methods served over JSON-RPC, as advertised by `rpc.discover`
+const RPC_METHODS: &[RpcMethod] = &[
+    PING_METHOD,
+    RpcMethod {
+        name: "ping_darkfid",
+        summary: "Ping the configured darkfid daemon for liveness",
+        params: &[],
+        result: "boolean",
+    },
+    RpcMethod {
+        name: "blocks.get_last_n_blocks",
+        summary: "Get the last N blocks",
+        params: &[RpcParam { name: "n", schema: "string" }],
+        result: "array",
+    },
+    RpcMethod {
+        name: "blocks.get_blocks_in_heights_range",
+        summary: "Get the blocks in the given heights range",
+        params: &[
+            RpcParam { name: "start", schema: "string" },
+            RpcParam { name: "end", schema: "string" },
+        ],
+        result: "array",
+    },
+    RpcMethod {
+        name: "blocks.get_block_by_hash",
+        summary: "Get the block with the given header hash",
+        params: &[RpcParam { name: "header_hash", schema: "string" }],
+        result: "array",
+    },
+    RpcMethod {
+        name: "contracts.get_native_contracts",
+        summary: "Get the native contracts along with their metadata",
+        params: &[],
+        result: "array",
+    },
+    RpcMethod {
+        name: "contracts.get_contract_source_code_paths",
+        summary: "Get the source code paths of the given contract",
+        params: &[RpcParam { name: "contract_id", schema: "string" }],
+        result: "array",
+    },
+    RpcMethod {
+        name: "contracts.get_contract_source",
+        summary: "Get the content of a source file of the given contract",
+        params: &[
+            RpcParam { name: "contract_id", schema: "string" },
+            RpcParam { name: "source_path", schema: "string" },
+        ],
+        result: "string",
+    },
+    RpcMethod {
+        name: "transactions.get_transactions_by_header_hash",
+        summary: "Get the transactions of the block with the given header hash",
+        params: &[RpcParam { name: "header_hash", schema: "string" }],
+        result: "array",
+    },
+    RpcMethod {
+        name: "transactions.get_transaction_by_hash",
+        summary: "Get the transaction with the given hash",
+        params: &[RpcParam { name: "tx_hash", schema: "string" }],
+        result: "array",
+    },
+    RpcMethod {
+        name: "statistics.get_basic_statistics",
+        summary: "Get the current basic statistics",
+        params: &[],
+        result: "array",
+    },
+    RpcMethod {
+        name: "statistics.get_metric_statistics",
+        summary: "Get all metric statistics",
+        params: &[],
+        result: "array",
+    },
+];
+
 #[async_trait]
 impl RequestHandler<()> for Explorerd {
     async fn handle_request(&self, req: JsonRequest) -> JsonResult {
@@ -103,6 +181,10 @@ impl RequestHandler<()> for Explorerd {
     async fn connections_mut(&self) -> MutexGuard<'_, HashSet<StoppableTaskPtr>> {
         self.rpc_connections.lock().await
     }
+
+    fn methods(&self) -> Vec<RpcMethod> {
+        RPC_METHODS.to_vec()
+    }
 }
 
 impl Explorerd {
@@ -16,33 +16,26 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
-use std::{
-    collections::{HashMap, HashSet},
-    sync::Arc,
-};
+use std::{collections::HashSet, sync::Arc};
 
 use async_trait::async_trait;
 use log::{debug, error, info, warn};
 use smol::{
-    channel,
     fs::File,
-    lock::{Mutex, MutexGuard, RwLock},
-    stream::StreamExt,
+    lock::{Mutex, MutexGuard},
     Executor,
 };
 use structopt_toml::{structopt::StructOpt, StructOptToml};
 use tinyjson::JsonValue;
-use url::Url;
 
 use darkfi::{
     async_daemonize, cli_desc,
-    geode::Geode,
-    net::{
-        self, connector::Connector, protocol::ProtocolVersion, session::Session,
-        settings::SettingsOpt, P2p, P2pPtr,
-    },
+    dht::{Dht, DhtNode, DhtPtr, DhtSettings, ProtocolDht},
+    geode::{ChunkedFile, Geode},
+    net::{self, settings::SettingsOpt, Message, P2p, P2pPtr},
     rpc::{
         jsonrpc::{ErrorCode, JsonError, JsonRequest, JsonResponse, JsonResult},
+        openrpc::{RpcMethod, RpcParam, PING_METHOD},
         server::{listen_and_serve, RequestHandler},
         settings::{RpcSettings, RpcSettingsOpt},
     },
@@ -53,10 +46,7 @@ use darkfi::{
 
 /// P2P protocols
 mod proto;
-use proto::{
-    FudChunkPut, FudChunkReply, FudChunkRequest, FudFilePut, FudFileReply, FudFileRequest,
-    ProtocolFud,
-};
+use proto::{FudChunkReply, FudChunkRequest, FudFileReply, FudFileRequest, ProtocolFud};
 
 const CONFIG_FILE: &str = "fud_config.toml";
 const CONFIG_FILE_CONTENTS: &str = include_str!("../fud_config.toml");
@@ -81,6 +71,10 @@ struct Args {
     /// Base directory for filesystem storage
     base_dir: String,
 
+    #[structopt(long, default_value = "10")]
+    /// Seconds to wait for a peer to reply to a fetch request
+    fetch_timeout: u64,
+
     #[structopt(flatten)]
     /// Network settings
     net: SettingsOpt,
@@ -91,23 +85,41 @@ struct Args {
 }
 
 pub struct Fud {
-    /// Routing table for file metadata
-    metadata_router: Arc<RwLock<HashMap<blake3::Hash, HashSet<Url>>>>,
-    /// Routing table for file chunks
-    chunks_router: Arc<RwLock<HashMap<blake3::Hash, HashSet<Url>>>>,
     /// Pointer to the P2P network instance
     p2p: P2pPtr,
+    /// DHT used to find the providers of files and chunks
+    dht: DhtPtr,
     /// The Geode instance
     geode: Geode,
-
-    file_fetch_tx: channel::Sender<(blake3::Hash, Result<()>)>,
-    file_fetch_rx: channel::Receiver<(blake3::Hash, Result<()>)>,
-    chunk_fetch_tx: channel::Sender<(blake3::Hash, Result<()>)>,
-    chunk_fetch_rx: channel::Receiver<(blake3::Hash, Result<()>)>,
+    /// Seconds to wait for a peer to reply to a fetch request
+    fetch_timeout: u64,
 
     rpc_connections: Mutex<HashSet<StoppableTaskPtr>>,
 }
 
+/// Methods served over JSON-RPC, as advertised by `rpc.discover`
+const RPC_METHODS: &[RpcMethod] = &[
+    PING_METHOD,
+    RpcMethod {
+        name: "put",
+        summary: "Put a local file onto the network, returning its hash",
+        params: &[RpcParam { name: "path", schema: "string" }],
+        result: "string",
+    },
+    RpcMethod {
+        name: "get",
+        summary: "Fetch a file from the network, returning the paths of its chunks",
+        params: &[RpcParam { name: "file_hash", schema: "string" }],
+        result: "array",
+    },
+    RpcMethod {
+        name: "dnet_switch",
+        summary: "Activate or deactivate dnet in the P2P stack",
+        params: &[RpcParam { name: "enable", schema: "boolean" }],
+        result: "boolean",
+    },
+];
+
 #[async_trait]
 impl RequestHandler<()> for Fud {
     async fn handle_request(&self, req: JsonRequest) -> JsonResult {
@@ -125,6 +137,10 @@ impl RequestHandler<()> for Fud {
     async fn connections_mut(&self) -> MutexGuard<'_, HashSet<StoppableTaskPtr>> {
         self.rpc_connections.lock().await
     }
+
+    fn methods(&self) -> Vec<RpcMethod> {
+        RPC_METHODS.to_vec()
+    }
 }
 
 impl Fud {
@@ -156,7 +172,7 @@ impl Fud {
             }
         };
 
-        let (file_hash, chunk_hashes) = match self.geode.insert(fd).await {
+        let (file_hash, _) = match self.geode.insert(fd).await {
             Ok(v) => v,
             Err(e) => {
                 error!("Failed inserting file {:?} to geode: {}", path, e);
@@ -164,8 +180,7 @@ impl Fud {
             }
         };
 
-        let fud_file = FudFilePut { file_hash, chunk_hashes };
-        self.p2p.broadcast(&fud_file).await;
+        self.announce(&file_hash).await;
 
         JsonResponse::new(JsonValue::String(file_hash.to_hex().to_string()), id).into()
     }
@@ -187,87 +202,14 @@ impl Fud {
             Err(_) => return JsonError::new(ErrorCode::InvalidParams, None, id).into(),
         };
 
-        let chunked_file = match self.geode.get(&file_hash).await {
+        let chunked_file = match self.fetch(&file_hash).await {
             Ok(v) => v,
-            Err(Error::GeodeNeedsGc) => todo!(),
-            Err(Error::GeodeFileNotFound) => {
-                info!("Requested file {} not found in Geode, triggering fetch", file_hash);
-                self.file_fetch_tx.send((file_hash, Ok(()))).await.unwrap();
-                info!("Waiting for background file fetch task...");
-                let (i_file_hash, status) = self.file_fetch_rx.recv().await.unwrap();
-                match status {
-                    Ok(()) => {
-                        let ch_file = self.geode.get(&file_hash).await.unwrap();
-
-                        let m = FudFilePut {
-                            file_hash: i_file_hash,
-                            chunk_hashes: ch_file.iter().map(|(h, _)| *h).collect(),
-                        };
-
-                        self.p2p.broadcast(&m).await;
-
-                        ch_file
-                    }
-
-                    Err(Error::GeodeFileRouteNotFound) => {
-                        // TODO: Return FileNotFound error
-                        return JsonError::new(ErrorCode::InternalError, None, id).into()
-                    }
-
-                    Err(e) => panic!("{}", e),
-                }
+            Err(e) => {
+                error!("Failed fetching file {}: {}", file_hash, e);
+                return JsonError::new(ErrorCode::InternalError, Some(e.to_string()), id).into()
             }
-
-            Err(e) => panic!("{}", e),
         };
 
-        if chunked_file.is_complete() {
-            let chunks: Vec<JsonValue> = chunked_file
-                .iter()
-                .map(|(_, path)| {
-                    JsonValue::String(
-                        path.as_ref().unwrap().clone().into_os_string().into_string().unwrap(),
-                    )
-                })
-                .collect();
-
-            return JsonResponse::new(JsonValue::Array(chunks), id).into()
-        }
-
-        // Fetch any missing chunks
-        let mut missing_chunks = vec![];
-        for (chunk, path) in chunked_file.iter() {
-            if path.is_none() {
-                missing_chunks.push(*chunk);
-            }
-        }
-
-        for chunk in missing_chunks {
-            self.chunk_fetch_tx.send((chunk, Ok(()))).await.unwrap();
-            let (i_chunk_hash, status) = self.chunk_fetch_rx.recv().await.unwrap();
-
-            match status {
-                Ok(()) => {
-                    let m = FudChunkPut { chunk_hash: i_chunk_hash };
-                    self.p2p.broadcast(&m).await;
-                    break
-                }
-                Err(Error::GeodeChunkRouteNotFound) => continue,
-
-                Err(e) => panic!("{}", e),
-            }
-        }
-
-        let chunked_file = match self.geode.get(&file_hash).await {
-            Ok(v) => v,
-            Err(e) => panic!("{}", e),
-        };
-
-        if !chunked_file.is_complete() {
-            todo!();
-            // Return JsonError missing chunks
-        }
-
         let chunks: Vec<JsonValue> = chunked_file
             .iter()
             .map(|(_, path)| {
@@ -303,228 +245,163 @@ impl Fud {
 
         JsonResponse::new(JsonValue::Boolean(true), id).into()
     }
-}
 
-/// Background task that receives file fetch requests and tries to
-/// fetch objects from the network using the routing table.
-/// TODO: This can be optimised a lot for connection reuse, etc.
-async fn fetch_file_task(fud: Arc<Fud>, executor: Arc<Executor<'_>>) -> Result<()> {
-    info!("Started background file fetch task");
-    loop {
-        let (file_hash, _) = fud.file_fetch_rx.recv().await.unwrap();
-        info!("fetch_file_task: Received {}", file_hash);
-
-        let mut metadata_router = fud.metadata_router.write().await;
-        let peers = metadata_router.get_mut(&file_hash);
-
-        if peers.is_none() {
-            warn!("File {} not in routing table, cannot fetch", file_hash);
-            fud.file_fetch_tx.send((file_hash, Err(Error::GeodeFileRouteNotFound))).await.unwrap();
-            continue
+    /// Announce ourselves in the DHT as a provider of a file. Chunks are
+    /// requested by their index in a file, so they are not announced.
+    async fn announce(&self, file_hash: &blake3::Hash) {
+        if let Err(e) = self.dht.announce(file_hash).await {
+            warn!("Failed announcing {} in the DHT: {}", file_hash, e);
         }
+    }
 
-        let mut found = false;
-        let peers = peers.unwrap();
-        let mut invalid_file_routes = vec![];
-
-        for peer in peers.iter() {
-            let session_out = fud.p2p.session_outbound();
-            let session_weak = Arc::downgrade(&fud.p2p.session_outbound());
-
-            info!("Connecting to {} to fetch {}", peer, file_hash);
-            let connector = Connector::new(fud.p2p.settings(), session_weak);
-            match connector.connect(peer).await {
-                Ok((url, channel)) => {
-                    let proto_ver = ProtocolVersion::new(
-                        channel.clone(),
-                        fud.p2p.settings().clone(),
-                        fud.p2p.hosts().clone(),
-                    )
-                    .await;
-
-                    let handshake_task = session_out.perform_handshake_protocols(
-                        proto_ver,
-                        channel.clone(),
-                        executor.clone(),
-                    );
-
-                    channel.clone().start(executor.clone());
-
-                    if let Err(e) = handshake_task.await {
-                        error!("Handshake with {} failed: {}", url, e);
-                        // Delete peer from router
-                        invalid_file_routes.push(peer.clone());
-                        continue
-                    }
-
-                    let msg_subscriber = channel.subscribe_msg::<FudFileReply>().await.unwrap();
-                    let request = FudFileRequest { file_hash };
-
-                    if let Err(e) = channel.send(&request).await {
-                        error!("Failed sending FudFileRequest({}) to {}: {}", file_hash, url, e);
-                        continue
-                    }
-
-                    // TODO: With timeout!
-                    let reply = match msg_subscriber.receive().await {
-                        Ok(v) => v,
-                        Err(e) => {
-                            error!("Error receiving FudFileReply from subscriber: {}", e);
-                            continue
-                        }
-                    };
-
-                    msg_subscriber.unsubscribe().await;
-                    channel.stop().await;
-
-                    if let Err(e) = fud.geode.insert_file(&file_hash, &reply.chunk_hashes).await {
-                        error!("Failed inserting file {} to Geode: {}", file_hash, e);
-                        continue
-                    }
-
-                    found = true;
-                    break
-                }
+    /// Return the indexes of the chunks of a file we still need to fetch.
+    async fn missing_chunks(&self, file_hash: &blake3::Hash) -> Result<Vec<u64>> {
+        match self.geode.get(file_hash).await {
+            Ok(chunked_file) => Ok(chunked_file.missing()),
+            Err(Error::GeodeFileNotFound) => match self.geode.get_download(file_hash).await? {
+                Some(download) => Ok(download.missing()),
+                // The proof of the first chunk tells us the chunk count
+                None => Ok(vec![0]),
+            },
+            Err(e) => Err(e),
+        }
+    }
 
-                Err(e) => {
-                    error!("Failed to connect to {}: {}", peer, e);
-                    continue
-                }
-            }
+    /// Retrieve a file from Geode, fetching any missing chunks from the
+    /// providers found in the DHT. Interrupted fetches resume from the
+    /// chunks already stored in Geode.
+    async fn fetch(&self, file_hash: &blake3::Hash) -> Result<ChunkedFile> {
+        let known = match self.geode.get(file_hash).await {
+            Ok(chunked_file) if chunked_file.is_complete() => return Ok(chunked_file),
+            Ok(_) => true,
+            Err(Error::GeodeFileNotFound) => self.geode.get_download(file_hash).await?.is_some(),
+            Err(e) => return Err(e),
+        };
+
+        let providers = self.dht.find_providers(file_hash).await;
+        if providers.is_empty() {
+            warn!("No providers found for file {}", file_hash);
+            return Err(Error::GeodeFileRouteNotFound)
         }
 
-        for peer in invalid_file_routes {
-            debug!("Removing peer {} from {} file router", peer, file_hash);
-            peers.remove(&peer);
+        // Try fetching the metadata first. If that fails, e.g. because it
+        // is too large, the chunks are fetched one by one with their proofs.
+        if !known {
+            info!("Requested file {} not found in Geode, fetching metadata", file_hash);
+            if let Err(e) = self.fetch_file(file_hash, &providers).await {
+                warn!("Failed fetching metadata of {}: {}", file_hash, e);
+            }
         }
 
-        if !found {
-            warn!("Did not manage to fetch {} file metadata", file_hash);
-            fud.file_fetch_tx.send((file_hash, Err(Error::GeodeFileRouteNotFound))).await.unwrap();
-            continue
+        loop {
+            let missing = self.missing_chunks(file_hash).await?;
+            if missing.is_empty() {
+                break
+            }
+
+            let mut fetched = false;
+            for index in missing {
+                match self.fetch_chunk(file_hash, index, &providers).await {
+                    Ok(()) => fetched = true,
+                    Err(e) => warn!("Failed fetching chunk {} of {}: {}", index, file_hash, e),
+                }
+            }
+
+            if !fetched {
+                return Err(Error::GeodeChunkRouteNotFound)
+            }
         }
 
-        info!("Successfully fetched {} file metadata", file_hash);
-        fud.file_fetch_tx.send((file_hash, Ok(()))).await.unwrap();
+        let chunked_file = self.geode.get(file_hash).await?;
+        self.announce(file_hash).await;
+
+        Ok(chunked_file)
     }
-}
 
-/// Background task that receives chunk fetch requests and tries to
-/// fetch objects from the network using the routing table.
-/// TODO: This can be optimised a lot for connection reuse, etc.
-async fn fetch_chunk_task(fud: Arc<Fud>, executor: Arc<Executor<'_>>) -> Result<()> {
-    info!("Started background chunk fetch task");
-    loop {
-        let (chunk_hash, _) = fud.chunk_fetch_rx.recv().await.unwrap();
-        info!("fetch_chunk_task: Received {}", chunk_hash);
-
-        let mut chunk_router = fud.chunks_router.write().await;
-        let peers = chunk_router.get_mut(&chunk_hash);
-
-        if peers.is_none() {
-            warn!("Chunk {} not in routing table, cannot fetch", chunk_hash);
-            fud.chunk_fetch_tx
-                .send((chunk_hash, Err(Error::GeodeChunkRouteNotFound)))
-                .await
-                .unwrap();
-            continue
+    /// Fetch file metadata from its providers and insert it into Geode.
+    /// Geode verifies the chunk hashes against the file hash.
+    async fn fetch_file(&self, file_hash: &blake3::Hash, providers: &[DhtNode]) -> Result<()> {
+        let request = FudFileRequest { file_hash: *file_hash };
+        for provider in providers {
+            let reply: Arc<FudFileReply> = match self.request(provider, &request).await {
+                Ok(v) => v,
+                Err(e) => {
+                    warn!("Failed fetching file {} from {}: {}", file_hash, provider.id, e);
+                    continue
+                }
+            };
+
+            if let Err(e) = self.geode.insert_file(file_hash, &reply.chunk_hashes).await {
+                error!("Failed inserting file {} to Geode: {}", file_hash, e);
+                continue
+            }
+
+            info!("Successfully fetched {} file metadata", file_hash);
+            return Ok(())
         }
 
-        let mut found = false;
-        let peers = peers.unwrap();
-        let mut invalid_chunk_routes = vec![];
-
-        for peer in peers.iter() {
-            let session_out = fud.p2p.session_outbound();
-            let session_weak = Arc::downgrade(&fud.p2p.session_outbound());
-
-            info!("Connecting to {} to fetch {}", peer, chunk_hash);
-            let connector = Connector::new(fud.p2p.settings(), session_weak);
-            match connector.connect(peer).await {
-                Ok((url, channel)) => {
-                    let proto_ver = ProtocolVersion::new(
-                        channel.clone(),
-                        fud.p2p.settings().clone(),
-                        fud.p2p.hosts().clone(),
-                    )
-                    .await;
-
-                    let handshake_task = session_out.perform_handshake_protocols(
-                        proto_ver,
-                        channel.clone(),
-                        executor.clone(),
-                    );
-
-                    channel.clone().start(executor.clone());
-
-                    if let Err(e) = handshake_task.await {
-                        error!("Handshake with {} failed: {}", url, e);
-                        // Delete peer from router
-                        invalid_chunk_routes.push(peer.clone());
-                        continue
-                    }
-
-                    let msg_subscriber = channel.subscribe_msg::<FudChunkReply>().await.unwrap();
-                    let request = FudChunkRequest { chunk_hash };
-
-                    if let Err(e) = channel.send(&request).await {
-                        error!("Failed sending FudChunkRequest({}) to {}: {}", chunk_hash, url, e);
-                        continue
-                    }
-
-                    // TODO: With timeout!
-                    let reply = match msg_subscriber.receive().await {
-                        Ok(v) => v,
-                        Err(e) => {
-                            error!("Error receiving FudChunkReply from subscriber: {}", e);
-                            continue
-                        }
-                    };
-
-                    msg_subscriber.unsubscribe().await;
-                    channel.stop().await;
-
-                    match fud.geode.insert_chunk(&reply.chunk).await {
-                        Ok(inserted_hash) => {
-                            if inserted_hash != chunk_hash {
-                                warn!("Received chunk does not match requested chunk");
-                                invalid_chunk_routes.push(peer.clone());
-                                continue
-                            }
-                        }
-                        Err(e) => {
-                            error!("Failed inserting chunk {} to Geode: {}", chunk_hash, e);
-                            continue
-                        }
-                    }
-
-                    found = true;
-                    break
-                }
+        Err(Error::GeodeFileRouteNotFound)
+    }
 
+    /// Fetch the chunk at `index` of a file from its providers and insert
+    /// it into Geode, which verifies it against the file hash.
+    async fn fetch_chunk(
+        &self,
+        file_hash: &blake3::Hash,
+        index: u64,
+        providers: &[DhtNode],
+    ) -> Result<()> {
+        let request = FudChunkRequest { file_hash: *file_hash, index };
+        for provider in providers {
+            let reply: Arc<FudChunkReply> = match self.request(provider, &request).await {
+                Ok(v) => v,
                 Err(e) => {
-                    error!("Failed to connect to {}: {}", peer, e);
+                    warn!("Failed fetching chunk {} from {}: {}", index, provider.id, e);
                     continue
                 }
+            };
+
+            if reply.proof.index != index {
+                warn!("Received chunk from {} is not the requested chunk", provider.id);
+                continue
+            }
+
+            if let Err(e) =
+                self.geode.insert_chunk_verified(file_hash, &reply.proof, &reply.chunk).await
+            {
+                error!("Failed inserting chunk {} of {} to Geode: {}", index, file_hash, e);
+                continue
             }
-        }
 
-        for peer in invalid_chunk_routes {
-            debug!("Removing peer {} from {} chunk router", peer, chunk_hash);
-            peers.remove(&peer);
+            info!("Successfully fetched chunk {} of {}", index, file_hash);
+            return Ok(())
         }
 
-        if !found {
-            warn!("Did not manage to fetch {} chunk", chunk_hash);
-            fud.chunk_fetch_tx
-                .send((chunk_hash, Err(Error::GeodeChunkRouteNotFound)))
-                .await
-                .unwrap();
-            continue
+        Err(Error::GeodeChunkRouteNotFound)
+    }
+
+    /// Send `request` to `provider` and wait for its reply.
+    async fn request<Req: Message, Rep: Message>(
+        &self,
+        provider: &DhtNode,
+        request: &Req,
+    ) -> Result<Arc<Rep>> {
+        let channel = self.dht.get_channel(provider).await?;
+        debug!("Sending {} to {}", Req::NAME, channel.address());
+
+        let res = async {
+            let sub = channel.subscribe_msg::<Rep>().await?;
+            let res = match channel.send(request).await {
+                Ok(()) => sub.receive_with_timeout(self.fetch_timeout).await,
+                Err(e) => Err(e),
+            };
+            sub.unsubscribe().await;
+            res
         }
+        .await;
 
-        info!("Successfully fetched {} chunk", chunk_hash);
-        fud.chunk_fetch_tx.send((chunk_hash, Ok(()))).await.unwrap();
+        self.dht.release_channel(&channel).await;
+        res
     }
 }
 
@@ -533,59 +410,24 @@ async fn realmain(args: Args, ex: Arc<Executor<'static>>) -> Result<()> {
     // The working directory for this daemon and geode.
     let basedir = expand_path(&args.base_dir)?;
 
-    // Hashmaps used for routing
-    let metadata_router = Arc::new(RwLock::new(HashMap::new()));
-    let chunks_router = Arc::new(RwLock::new(HashMap::new()));
-
     info!("Instantiating Geode instance");
     let geode = Geode::new(&basedir).await?;
 
     info!("Instantiating P2P network");
-    let p2p = P2p::new(args.net.into(), ex.clone()).await;
+    let p2p = P2p::new(args.net.into(), ex.clone()).await?;
+
+    info!("Instantiating DHT");
+    let dht = Dht::new(p2p.clone(), DhtSettings::default());
 
     // Daemon instantiation
-    let (file_fetch_tx, file_fetch_rx) = smol::channel::unbounded();
-    let (chunk_fetch_tx, chunk_fetch_rx) = smol::channel::unbounded();
     let fud = Arc::new(Fud {
-        metadata_router,
-        chunks_router,
         p2p: p2p.clone(),
+        dht: dht.clone(),
         geode,
-        file_fetch_tx,
-        file_fetch_rx,
-        chunk_fetch_tx,
-        chunk_fetch_rx,
+        fetch_timeout: args.fetch_timeout,
         rpc_connections: Mutex::new(HashSet::new()),
     });
 
-    info!(target: "fud", "Starting fetch file task");
-    let file_task = StoppableTask::new();
-    file_task.clone().start(
-        fetch_file_task(fud.clone(), ex.clone()),
-        |res| async {
-            match res {
-                Ok(()) | Err(Error::DetachedTaskStopped) => { /* Do nothing */ }
-                Err(e) => error!(target: "fud", "Failed starting fetch file task: {}", e),
-            }
-        },
-        Error::DetachedTaskStopped,
-        ex.clone(),
-    );
-
-    info!(target: "fud", "Starting fetch chunk task");
-    let chunk_task = StoppableTask::new();
-    chunk_task.clone().start(
-        fetch_chunk_task(fud.clone(), ex.clone()),
-        |res| async {
-            match res {
-                Ok(()) | Err(Error::DetachedTaskStopped) => { /* Do nothing */ }
-                Err(e) => error!(target: "fud", "Failed starting fetch chunk task: {}", e),
-            }
-        },
-        Error::DetachedTaskStopped,
-        ex.clone(),
-    );
-
     let rpc_settings: RpcSettings = args.rpc.into();
     info!(target: "fud", "Starting JSON-RPC server on {}", rpc_settings.listen);
     let rpc_task = StoppableTask::new();
@@ -604,25 +446,44 @@ async fn realmain(args: Args, ex: Arc<Executor<'static>>) -> Result<()> {
 
     info!("Starting P2P protocols");
     let registry = p2p.protocol_registry();
+    let dht_ = dht.clone();
+    registry
+        .register(net::session::SESSION_DEFAULT, "ProtocolDht", 1, move |channel, _| {
+            let dht_ = dht_.clone();
+            async move { ProtocolDht::init(dht_, channel).await.unwrap() }
+        })
+        .await;
     let fud_ = fud.clone();
     registry
-        .register(net::SESSION_NET, move |channel, p2p| {
+        .register(net::session::SESSION_DEFAULT, "ProtocolFud", 0, move |channel, _| {
             let fud_ = fud_.clone();
-            async move { ProtocolFud::init(fud_, channel, p2p).await.unwrap() }
+            async move { ProtocolFud::init(fud_, channel).await.unwrap() }
         })
         .await;
     p2p.clone().start().await?;
 
+    info!(target: "fud", "Starting DHT refresh task");
+    dht.bootstrap().await;
+    let dht_task = StoppableTask::new();
+    dht_task.clone().start(
+        dht.clone().refresh_task(),
+        |res| async {
+            match res {
+                Ok(()) | Err(Error::DetachedTaskStopped) => { /* Do nothing */ }
+                Err(e) => error!(target: "fud", "Failed starting DHT refresh task: {}", e),
+            }
+        },
+        Error::DetachedTaskStopped,
+        ex.clone(),
+    );
+
     // Signal handling for graceful termination.
     let (signals_handler, signals_task) = SignalHandler::new(ex)?;
     signals_handler.wait_termination(signals_task).await?;
     info!("Caught termination signal, cleaning up and exiting...");
 
-    info!(target: "fud", "Stopping fetch file task...");
-    file_task.stop().await;
-
-    info!(target: "fud", "Stopping fetch chunk task...");
-    chunk_task.stop().await;
+    info!(target: "fud", "Stopping DHT refresh task...");
+    dht_task.stop().await;
 
     info!(target: "fud", "Stopping JSON-RPC server...");
     rpc_task.stop().await;
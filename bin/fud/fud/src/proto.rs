@@ -16,14 +16,14 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
-use std::{collections::HashSet, sync::Arc};
+use std::sync::Arc;
 
 use async_trait::async_trait;
 use darkfi::{
-    geode::MAX_CHUNK_SIZE,
+    geode::{ChunkProof, MAX_CHUNK_SIZE},
     impl_p2p_message,
     net::{
-        ChannelPtr, Message, MessageSubscription, P2pPtr, ProtocolBase, ProtocolBasePtr,
+        ChannelPtr, Message, MessageSubscription, ProtocolBase, ProtocolBasePtr,
         ProtocolJobsManager, ProtocolJobsManagerPtr,
     },
     Error, Result,
@@ -31,42 +31,9 @@ use darkfi::{
 use darkfi_serial::{SerialDecodable, SerialEncodable};
 use log::{debug, error};
 use smol::{fs::File, io::AsyncReadExt, Executor};
-use url::Url;
 
 use super::Fud;
 
-/// Message representing a new file on the network
-#[derive(Debug, Clone, SerialEncodable, SerialDecodable)]
-pub struct FudFilePut {
-    pub file_hash: blake3::Hash,
-    pub chunk_hashes: Vec<blake3::Hash>,
-}
-impl_p2p_message!(FudFilePut, "FudFilePut");
-
-/// Message representing a new chunk on the network
-#[derive(Debug, Clone, SerialEncodable, SerialDecodable)]
-pub struct FudChunkPut {
-    pub chunk_hash: blake3::Hash,
-}
-impl_p2p_message!(FudChunkPut, "FudChunkPut");
-
-/// Message representing a new route for a file on the network
-#[derive(Debug, Clone, SerialEncodable, SerialDecodable)]
-pub struct FudFileRoute {
-    pub file_hash: blake3::Hash,
-    pub chunk_hashes: Vec<blake3::Hash>,
-    pub peer: Url,
-}
-impl_p2p_message!(FudFileRoute, "FudFileRoute");
-
-/// Message representing a new route for a chunk on the network
-#[derive(Debug, Clone, SerialEncodable, SerialDecodable)]
-pub struct FudChunkRoute {
-    pub chunk_hash: blake3::Hash,
-    pub peer: Url,
-}
-impl_p2p_message!(FudChunkRoute, "FudChunkRoute");
-
 /// Message representing a file request from the network
 #[derive(Debug, Clone, SerialEncodable, SerialDecodable)]
 pub struct FudFileRequest {
@@ -84,7 +51,8 @@ impl_p2p_message!(FudFileReply, "FudFileReply");
 /// Message representing a chunk request from the network
 #[derive(Debug, Clone, SerialEncodable, SerialDecodable)]
 pub struct FudChunkRequest {
-    pub chunk_hash: blake3::Hash,
+    pub file_hash: blake3::Hash,
+    pub index: u64,
 }
 impl_p2p_message!(FudChunkRequest, "FudChunkRequest");
 
@@ -93,6 +61,8 @@ impl_p2p_message!(FudChunkRequest, "FudChunkRequest");
 pub struct FudChunkReply {
     // TODO: This sould be a chunk-sized array, but then we need padding?
     pub chunk: Vec<u8>,
+    /// Inclusion proof of the chunk in the requested file
+    pub proof: ChunkProof,
 }
 impl_p2p_message!(FudChunkReply, "FudChunkReply");
 
@@ -109,255 +79,41 @@ impl_p2p_message!(FudChunkNotFound, "FudChunkNotFound");
 /// P2P protocol implementation for fud.
 pub struct ProtocolFud {
     channel: ChannelPtr,
-    file_put_sub: MessageSubscription<FudFilePut>,
-    chunk_put_sub: MessageSubscription<FudChunkPut>,
-    file_route_sub: MessageSubscription<FudFileRoute>,
-    chunk_route_sub: MessageSubscription<FudChunkRoute>,
     file_request_sub: MessageSubscription<FudFileRequest>,
     chunk_request_sub: MessageSubscription<FudChunkRequest>,
     fud: Arc<Fud>,
-    p2p: P2pPtr,
     jobsman: ProtocolJobsManagerPtr,
 }
 
 impl ProtocolFud {
-    pub async fn init(fud: Arc<Fud>, channel: ChannelPtr, p2p: P2pPtr) -> Result<ProtocolBasePtr> {
+    pub async fn init(fud: Arc<Fud>, channel: ChannelPtr) -> Result<ProtocolBasePtr> {
         debug!(
             target: "fud::proto::ProtocolFud::init()",
             "Adding ProtocolFud to the protocol registry"
         );
 
+        // Replies are dispatched too, so fetches can subscribe to them
+        // on any channel running this protocol.
         let msg_subsystem = channel.message_subsystem();
-        msg_subsystem.add_dispatch::<FudFilePut>().await;
-        msg_subsystem.add_dispatch::<FudChunkPut>().await;
-        msg_subsystem.add_dispatch::<FudFileRoute>().await;
-        msg_subsystem.add_dispatch::<FudChunkRoute>().await;
         msg_subsystem.add_dispatch::<FudFileRequest>().await;
+        msg_subsystem.add_dispatch::<FudFileReply>().await;
+        msg_subsystem.add_dispatch::<FudFileNotFound>().await;
         msg_subsystem.add_dispatch::<FudChunkRequest>().await;
+        msg_subsystem.add_dispatch::<FudChunkReply>().await;
+        msg_subsystem.add_dispatch::<FudChunkNotFound>().await;
 
-        let file_put_sub = channel.subscribe_msg::<FudFilePut>().await?;
-        let chunk_put_sub = channel.subscribe_msg::<FudChunkPut>().await?;
-        let file_route_sub = channel.subscribe_msg::<FudFileRoute>().await?;
-        let chunk_route_sub = channel.subscribe_msg::<FudChunkRoute>().await?;
         let file_request_sub = channel.subscribe_msg::<FudFileRequest>().await?;
         let chunk_request_sub = channel.subscribe_msg::<FudChunkRequest>().await?;
 
         Ok(Arc::new(Self {
             channel: channel.clone(),
-            file_put_sub,
-            chunk_put_sub,
-            file_route_sub,
-            chunk_route_sub,
             file_request_sub,
             chunk_request_sub,
             fud,
-            p2p,
             jobsman: ProtocolJobsManager::new("ProtocolFud", channel.clone()),
         }))
     }
 
-    async fn handle_fud_file_put(self: Arc<Self>) -> Result<()> {
-        debug!(target: "fud::ProtocolFud::handle_fud_file_put()", "START");
-
-        loop {
-            let fud_file = match self.file_put_sub.receive().await {
-                Ok(v) => v,
-                Err(e) => {
-                    error!(
-                        target: "fud::ProtocolFud::handle_fud_file_put()",
-                        "recv fail: {}", e,
-                    );
-                    continue
-                }
-            };
-
-            // TODO: This approach is naive and optimistic. Needs to be fixed.
-            let mut metadata_lock = self.fud.metadata_router.write().await;
-            let file_route = metadata_lock.get_mut(&fud_file.file_hash);
-            match file_route {
-                Some(peers) => {
-                    peers.insert(self.channel.address().clone());
-                }
-                None => {
-                    let mut peers = HashSet::new();
-                    peers.insert(self.channel.address().clone());
-                    metadata_lock.insert(fud_file.file_hash, peers);
-                }
-            }
-            drop(metadata_lock);
-
-            let mut chunks_lock = self.fud.chunks_router.write().await;
-            for chunk in &fud_file.chunk_hashes {
-                let chunk_route = chunks_lock.get_mut(chunk);
-                match chunk_route {
-                    Some(peers) => {
-                        peers.insert(self.channel.address().clone());
-                    }
-                    None => {
-                        let mut peers = HashSet::new();
-                        peers.insert(self.channel.address().clone());
-                        chunks_lock.insert(*chunk, peers);
-                    }
-                }
-            }
-            drop(chunks_lock);
-
-            // Relay this knowledge of the new route
-            let route = FudFileRoute {
-                file_hash: fud_file.file_hash,
-                chunk_hashes: fud_file.chunk_hashes.clone(),
-                peer: self.channel.address().clone(),
-            };
-
-            self.p2p.broadcast_with_exclude(&route, &[self.channel.address().clone()]).await;
-        }
-    }
-
-    async fn handle_fud_chunk_put(self: Arc<Self>) -> Result<()> {
-        debug!(target: "fud::ProtocolFud::handle_fud_chunk_put()", "START");
-
-        loop {
-            let fud_chunk = match self.chunk_put_sub.receive().await {
-                Ok(v) => v,
-                Err(e) => {
-                    error!(
-                        target: "fud::ProtocolFud::handle_fud_chunk_put()",
-                        "recv fail: {}", e,
-                    );
-                    continue
-                }
-            };
-
-            // TODO: This approach is naive and optimistic. Needs to be fixed.
-            let mut chunks_lock = self.fud.chunks_router.write().await;
-            let chunk_route = chunks_lock.get_mut(&fud_chunk.chunk_hash);
-            match chunk_route {
-                Some(peers) => {
-                    peers.insert(self.channel.address().clone());
-                }
-                None => {
-                    let mut peers = HashSet::new();
-                    peers.insert(self.channel.address().clone());
-                    chunks_lock.insert(fud_chunk.chunk_hash, peers);
-                }
-            }
-            drop(chunks_lock);
-
-            // Relay this knowledge of the new route
-            let route = FudChunkRoute {
-                chunk_hash: fud_chunk.chunk_hash,
-                peer: self.channel.address().clone(),
-            };
-
-            self.p2p.broadcast_with_exclude(&route, &[self.channel.address().clone()]).await;
-        }
-    }
-
-    async fn handle_fud_file_route(self: Arc<Self>) -> Result<()> {
-        debug!(target: "fud::ProtocolFud::handle_fud_file_route()", "START");
-
-        loop {
-            let fud_file = match self.file_route_sub.receive().await {
-                Ok(v) => v,
-                Err(e) => {
-                    error!(
-                        target: "fud::ProtocolFud::handle_fud_file_route()",
-                        "recv fail: {}", e,
-                    );
-                    continue
-                }
-            };
-
-            // TODO: This approach is naive and optimistic. Needs to be fixed.
-            let mut metadata_lock = self.fud.metadata_router.write().await;
-            let file_route = metadata_lock.get_mut(&fud_file.file_hash);
-            match file_route {
-                Some(peers) => {
-                    peers.insert(fud_file.peer.clone());
-                }
-                None => {
-                    let mut peers = HashSet::new();
-                    peers.insert(fud_file.peer.clone());
-                    metadata_lock.insert(fud_file.file_hash, peers);
-                }
-            }
-            drop(metadata_lock);
-
-            let mut chunks_lock = self.fud.chunks_router.write().await;
-            for chunk in &fud_file.chunk_hashes {
-                let chunk_route = chunks_lock.get_mut(chunk);
-                match chunk_route {
-                    Some(peers) => {
-                        peers.insert(fud_file.peer.clone());
-                    }
-                    None => {
-                        let mut peers = HashSet::new();
-                        peers.insert(fud_file.peer.clone());
-                        chunks_lock.insert(*chunk, peers);
-                    }
-                }
-            }
-            drop(chunks_lock);
-
-            // Relay this knowledge of the new route
-            let route = FudFileRoute {
-                file_hash: fud_file.file_hash,
-                chunk_hashes: fud_file.chunk_hashes.clone(),
-                peer: fud_file.peer.clone(),
-            };
-
-            self.p2p
-                .broadcast_with_exclude(
-                    &route,
-                    &[self.channel.address().clone(), fud_file.peer.clone()],
-                )
-                .await;
-        }
-    }
-
-    async fn handle_fud_chunk_route(self: Arc<Self>) -> Result<()> {
-        debug!(target: "fud::ProtocolFud::handle_fud_chunk_route()", "START");
-
-        loop {
-            let fud_chunk = match self.chunk_route_sub.receive().await {
-                Ok(v) => v,
-                Err(e) => {
-                    error!(
-                        target: "fud::ProtocolFud::handle_fud_chunk_put()",
-                        "recv fail: {}", e,
-                    );
-                    continue
-                }
-            };
-
-            // TODO: This approach is naive and optimistic. Needs to be fixed.
-            let mut chunks_lock = self.fud.chunks_router.write().await;
-            let chunk_route = chunks_lock.get_mut(&fud_chunk.chunk_hash);
-            match chunk_route {
-                Some(peers) => {
-                    peers.insert(fud_chunk.peer.clone());
-                }
-                None => {
-                    let mut peers = HashSet::new();
-                    peers.insert(fud_chunk.peer.clone());
-                    chunks_lock.insert(fud_chunk.chunk_hash, peers);
-                }
-            }
-            drop(chunks_lock);
-
-            // Relay this knowledge of the new route
-            let route =
-                FudChunkRoute { chunk_hash: fud_chunk.chunk_hash, peer: fud_chunk.peer.clone() };
-
-            self.p2p
-                .broadcast_with_exclude(
-                    &route,
-                    &[self.channel.address().clone(), fud_chunk.peer.clone()],
-                )
-                .await;
-        }
-    }
-
     async fn handle_fud_file_request(self: Arc<Self>) -> Result<()> {
         debug!(target: "fud::ProtocolFud::handle_fud_file_request()", "START");
 
@@ -414,14 +170,20 @@ impl ProtocolFud {
                 }
             };
 
-            let chunk_path = match self.fud.geode.get_chunk(&chunk_request.chunk_hash).await {
+            let res = self
+                .fud
+                .geode
+                .get_chunk_with_proof(&chunk_request.file_hash, chunk_request.index)
+                .await;
+
+            let (chunk_path, proof) = match res {
                 Ok(v) => v,
                 Err(Error::GeodeNeedsGc) => {
                     // TODO: Run GC
                     continue
                 }
 
-                Err(Error::GeodeChunkNotFound) => {
+                Err(Error::GeodeFileNotFound) | Err(Error::GeodeChunkNotFound) => {
                     match self.channel.send(&FudChunkNotFound).await {
                         Ok(()) => continue,
                         Err(_e) => continue,
@@ -438,7 +200,7 @@ impl ProtocolFud {
             let bytes_read = chunk_fd.read(&mut buf).await.unwrap();
             let chunk_slice = &buf[..bytes_read];
 
-            let reply = FudChunkReply { chunk: chunk_slice.to_vec() };
+            let reply = FudChunkReply { chunk: chunk_slice.to_vec(), proof };
             match self.channel.send(&reply).await {
                 Ok(()) => continue,
                 Err(_e) => continue,
@@ -452,10 +214,6 @@ impl ProtocolBase for ProtocolFud {
     async fn start(self: Arc<Self>, executor: Arc<Executor<'_>>) -> Result<()> {
         debug!(target: "fud::ProtocolFud::start()", "START");
         self.jobsman.clone().start(executor.clone());
-        self.jobsman.clone().spawn(self.clone().handle_fud_file_put(), executor.clone()).await;
-        self.jobsman.clone().spawn(self.clone().handle_fud_chunk_put(), executor.clone()).await;
-        self.jobsman.clone().spawn(self.clone().handle_fud_file_route(), executor.clone()).await;
-        self.jobsman.clone().spawn(self.clone().handle_fud_chunk_route(), executor.clone()).await;
         self.jobsman.clone().spawn(self.clone().handle_fud_file_request(), executor.clone()).await;
         self.jobsman.clone().spawn(self.clone().handle_fud_chunk_request(), executor.clone()).await;
         debug!(target: "fud::ProtocolFud::start()", "END");
@@ -28,7 +28,8 @@ use darkfi::{
     net,
     rpc::{
         jsonrpc::{ErrorCode, JsonError, JsonRequest, JsonResponse, JsonResult, JsonSubscriber},
-        p2p_method::HandlerP2p,
+        openrpc::{RpcMethod, RpcParam, PING_METHOD},
+        p2p_method::{HandlerP2p, P2P_GET_INFO_METHOD},
         server::RequestHandler,
     },
     system::StoppableTaskPtr,
@@ -47,6 +48,54 @@ pub struct JsonRpcInterface {
     deg_sub: JsonSubscriber,
 }
 
+/// Methods served over JSON-RPC, as advertised by `rpc.discover`
+const RPC_METHODS: &[RpcMethod] = &[
+    RpcMethod {
+        name: "add",
+        summary: "Add a new base64 encoded event to the event graph",
+        params: &[RpcParam { name: "event", schema: "string" }],
+        result: "boolean",
+    },
+    RpcMethod {
+        name: "list",
+        summary: "List the events of the event graph, base64 encoded",
+        params: &[],
+        result: "string",
+    },
+    PING_METHOD,
+    RpcMethod {
+        name: "dnet.subscribe_events",
+        summary: "Subscribe to P2P dnet events",
+        params: &[],
+        result: "object",
+    },
+    RpcMethod {
+        name: "dnet.switch",
+        summary: "Activate or deactivate dnet in the P2P stack",
+        params: &[RpcParam { name: "enable", schema: "boolean" }],
+        result: "boolean",
+    },
+    P2P_GET_INFO_METHOD,
+    RpcMethod {
+        name: "deg.switch",
+        summary: "Activate or deactivate deg in the event graph",
+        params: &[RpcParam { name: "enable", schema: "boolean" }],
+        result: "boolean",
+    },
+    RpcMethod {
+        name: "deg.subscribe_events",
+        summary: "Subscribe to event graph deg events",
+        params: &[],
+        result: "object",
+    },
+    RpcMethod {
+        name: "eventgraph.get_info",
+        summary: "Get event graph info",
+        params: &[],
+        result: "object",
+    },
+];
+
 #[async_trait]
 impl RequestHandler<()> for JsonRpcInterface {
     async fn handle_request(&self, req: JsonRequest) -> JsonResult {
@@ -71,6 +120,10 @@ impl RequestHandler<()> for JsonRpcInterface {
     async fn connections_mut(&self) -> MutexGuard<'life0, HashSet<StoppableTaskPtr>> {
         self.rpc_connections.lock().await
     }
+
+    fn methods(&self) -> Vec<RpcMethod> {
+        RPC_METHODS.to_vec()
+    }
 }
 
 impl HandlerP2p for JsonRpcInterface {
@@ -42,6 +42,8 @@ use darkfi::{
     net::{self, hosts::HostColor, settings::BanPolicy, P2p, P2pPtr},
     rpc::{
         jsonrpc::*,
+        metrics::start_exporter,
+        openrpc::{RpcMethod, PING_METHOD},
         server::{listen_and_serve, RequestHandler},
         settings::{RpcSettings, RpcSettingsOpt},
     },
@@ -76,6 +78,10 @@ struct Args {
     #[structopt(long, default_value = "120")]
     /// Interval after which to check whitelist peers
     whitelist_refinery_interval: u64,
+
+    #[structopt(long)]
+    /// Optional Prometheus metrics listen URL
+    metrics_listen: Option<Url>,
 }
 
 /// Struct representing a spawned P2P network
@@ -241,6 +247,17 @@ impl Lilith {
     }
 }
 
+/// Methods served over JSON-RPC, as advertised by `rpc.discover`
+const RPC_METHODS: &[RpcMethod] = &[
+    PING_METHOD,
+    RpcMethod {
+        name: "spawns",
+        summary: "Returns all spawned networks names with their node addresses",
+        params: &[],
+        result: "object",
+    },
+];
+
 #[async_trait]
 impl RequestHandler<()> for Lilith {
     async fn handle_request(&self, req: JsonRequest) -> JsonResult {
@@ -254,6 +271,10 @@ impl RequestHandler<()> for Lilith {
     async fn connections_mut(&self) -> MutexGuard<'life0, HashSet<StoppableTaskPtr>> {
         self.rpc_connections.lock().await
     }
+
+    fn methods(&self) -> Vec<RpcMethod> {
+        RPC_METHODS.to_vec()
+    }
 }
 
 /// Parse a TOML string for any configured network and return a map containing
@@ -354,6 +375,7 @@ async fn spawn_net(name: String, info: &NetInfo, ex: Arc<Executor<'static>>) ->
         inbound_connections: 512,
         app_version: info.version.clone(),
         localnet: info.localnet,
+        network_name: name.clone(),
         p2p_datastore: Some(info.datastore.clone()),
         hostlist: Some(info.hostlist.clone()),
         allowed_transports: vec![
@@ -440,6 +462,12 @@ async fn realmain(args: Args, ex: Arc<Executor<'static>>) -> Result<()> {
         ex.clone(),
     );
 
+    // Metrics exporter
+    let metrics_task = args.metrics_listen.map(|listen| {
+        info!(target: "lilith", "Starting metrics exporter on {}", listen);
+        start_exporter(listen, ex.clone())
+    });
+
     // Signal handling for graceful termination.
     let (signals_handler, signals_task) = SignalHandler::new(ex)?;
     signals_handler.wait_termination(signals_task).await?;
@@ -448,6 +476,11 @@ async fn realmain(args: Args, ex: Arc<Executor<'static>>) -> Result<()> {
     info!(target: "lilith", "Stopping JSON-RPC server...");
     rpc_task.stop().await;
 
+    if let Some(metrics_task) = metrics_task {
+        info!(target: "lilith", "Stopping metrics exporter...");
+        metrics_task.stop().await;
+    }
+
     // Cleanly stop p2p networks
     for spawn in &lilith.networks {
         info!(target: "lilith", "Stopping \"{}\" task", spawn.name);
@@ -26,6 +26,7 @@ use darkfi::{
     blockchain::BlockInfo,
     rpc::{
         jsonrpc::{ErrorCode, JsonError, JsonRequest, JsonResponse, JsonResult},
+        openrpc::{RpcMethod, RpcParam, PING_METHOD},
         server::RequestHandler,
         util::JsonValue,
     },
@@ -41,6 +42,26 @@ use crate::{
     MinerNode,
 };
 
+/// Methods served over JSON-RPC, as advertised by `rpc.discover`
+const RPC_METHODS: &[RpcMethod] = &[
+    PING_METHOD,
+    RpcMethod {
+        name: "abort",
+        summary: "Abort mining the pending request",
+        params: &[],
+        result: "boolean",
+    },
+    RpcMethod {
+        name: "mine",
+        summary: "Mine the given block for the given target, returning its nonce",
+        params: &[
+            RpcParam { name: "target", schema: "string" },
+            RpcParam { name: "block", schema: "string" },
+        ],
+        result: "number",
+    },
+];
+
 #[async_trait]
 impl RequestHandler<()> for MinerNode {
     async fn handle_request(&self, req: JsonRequest) -> JsonResult {
@@ -57,6 +78,10 @@ impl RequestHandler<()> for MinerNode {
     async fn connections_mut(&self) -> MutexGuard<'life0, HashSet<StoppableTaskPtr>> {
         self.rpc_connections.lock().await
     }
+
+    fn methods(&self) -> Vec<RpcMethod> {
+        RPC_METHODS.to_vec()
+    }
 }
 
 impl MinerNode {
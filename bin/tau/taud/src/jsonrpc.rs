@@ -33,7 +33,8 @@ use darkfi::{
     net,
     rpc::{
         jsonrpc::{ErrorCode, JsonError, JsonRequest, JsonResult, JsonSubscriber},
-        p2p_method::HandlerP2p,
+        openrpc::{RpcMethod, RpcParam, PING_METHOD},
+        p2p_method::{HandlerP2p, P2P_GET_INFO_METHOD},
         server::RequestHandler,
     },
     system::StoppableTaskPtr,
@@ -52,6 +53,132 @@ use crate::Workspace;
 
 const DEFAULT_WORKSPACE: &str = "darkfi-dev";
 
+/// Methods served over JSON-RPC, as advertised by `rpc.discover`
+const RPC_METHODS: &[RpcMethod] = &[
+    RpcMethod {
+        name: "add",
+        summary: "Add a new task",
+        params: &[RpcParam { name: "task", schema: "object" }],
+        result: "boolean",
+    },
+    RpcMethod {
+        name: "get_ref_ids",
+        summary: "List the ref IDs of the active tasks",
+        params: &[],
+        result: "array",
+    },
+    RpcMethod {
+        name: "get_archive_ref_ids",
+        summary: "List the ref IDs of the tasks archived in the given month",
+        params: &[RpcParam { name: "month", schema: "string" }],
+        result: "array",
+    },
+    RpcMethod {
+        name: "modify",
+        summary: "Modify the given task",
+        params: &[
+            RpcParam { name: "ref_id", schema: "string" },
+            RpcParam { name: "changes", schema: "object" },
+        ],
+        result: "boolean",
+    },
+    RpcMethod {
+        name: "set_state",
+        summary: "Set the state of the given task",
+        params: &[
+            RpcParam { name: "ref_id", schema: "string" },
+            RpcParam { name: "state", schema: "string" },
+        ],
+        result: "boolean",
+    },
+    RpcMethod {
+        name: "set_comment",
+        summary: "Add a comment to the given task",
+        params: &[
+            RpcParam { name: "ref_id", schema: "string" },
+            RpcParam { name: "comment", schema: "string" },
+        ],
+        result: "boolean",
+    },
+    RpcMethod {
+        name: "get_task_by_ref_id",
+        summary: "Get an active task by its ref ID",
+        params: &[RpcParam { name: "ref_id", schema: "string" }],
+        result: "object",
+    },
+    RpcMethod {
+        name: "switch_ws",
+        summary: "Switch the tasks workspace",
+        params: &[RpcParam { name: "workspace", schema: "string" }],
+        result: "boolean",
+    },
+    RpcMethod {
+        name: "get_ws",
+        summary: "Get the current workspace",
+        params: &[],
+        result: "string",
+    },
+    RpcMethod {
+        name: "export",
+        summary: "Export the tasks to the given path",
+        params: &[RpcParam { name: "path", schema: "string" }],
+        result: "boolean",
+    },
+    RpcMethod {
+        name: "import",
+        summary: "Import the tasks from the given path",
+        params: &[RpcParam { name: "path", schema: "string" }],
+        result: "boolean",
+    },
+    RpcMethod {
+        name: "fetch_deactive_tasks",
+        summary: "Get the tasks archived in the given month",
+        params: &[RpcParam { name: "month", schema: "string" }],
+        result: "array",
+    },
+    RpcMethod {
+        name: "fetch_archive_task",
+        summary: "Get a task archived in the given month by its ref ID",
+        params: &[
+            RpcParam { name: "ref_id", schema: "string" },
+            RpcParam { name: "month", schema: "string" },
+        ],
+        result: "object",
+    },
+    PING_METHOD,
+    RpcMethod {
+        name: "dnet.subscribe_events",
+        summary: "Subscribe to P2P dnet events",
+        params: &[],
+        result: "object",
+    },
+    RpcMethod {
+        name: "dnet.switch",
+        summary: "Activate or deactivate dnet in the P2P stack",
+        params: &[RpcParam { name: "enable", schema: "boolean" }],
+        result: "boolean",
+    },
+    RpcMethod {
+        name: "deg.switch",
+        summary: "Activate or deactivate deg in the event graph",
+        params: &[RpcParam { name: "enable", schema: "boolean" }],
+        result: "boolean",
+    },
+    RpcMethod {
+        name: "deg.subscribe_events",
+        summary: "Subscribe to event graph deg events",
+        params: &[],
+        result: "object",
+    },
+    RpcMethod {
+        name: "eventgraph.get_info",
+        summary: "Get event graph info",
+        params: &[],
+        result: "object",
+    },
+    P2P_GET_INFO_METHOD,
+];
+
 pub struct JsonRpcInterface {
     dataset_path: PathBuf,
     notify_queue_sender: smol::channel::Sender<TaskInfo>,
@@ -101,6 +228,10 @@ impl RequestHandler<()> for JsonRpcInterface {
     async fn connections_mut(&self) -> MutexGuard<'life0, HashSet<StoppableTaskPtr>> {
         self.rpc_connections.lock().await
     }
+
+    fn methods(&self) -> Vec<RpcMethod> {
+        RPC_METHODS.to_vec()
+    }
 }
 
 impl HandlerP2p for JsonRpcInterface {
@@ -52,6 +52,7 @@ use darkfi::{
     net::{session::SESSION_DEFAULT, P2p, P2pPtr},
     rpc::{
         jsonrpc::JsonSubscriber,
+        metrics::start_exporter,
         server::{listen_and_serve, RequestHandler},
     },
     system::{sleep, StoppableTask},
@@ -534,7 +535,7 @@ async fn realmain(settings: Args, executor: Arc<smol::Executor<'static>>) -> Res
     let event_graph_ = Arc::clone(&event_graph);
     let registry = p2p.protocol_registry();
     registry
-        .register(SESSION_DEFAULT, move |channel, _| {
+        .register(SESSION_DEFAULT, "ProtocolEventGraph", 0, move |channel, _| {
             let event_graph_ = event_graph_.clone();
             async move { ProtocolEventGraph::init(event_graph_, channel).await.unwrap() }
         })
@@ -700,6 +701,11 @@ async fn realmain(settings: Args, executor: Arc<smol::Executor<'static>>) -> Res
         executor.clone(),
     );
 
+    let metrics_task = settings.metrics_listen.map(|listen| {
+        info!(target: "taud", "Starting metrics exporter");
+        start_exporter(listen, executor.clone())
+    });
+
     // Signal handling for graceful termination.
     let (signals_handler, signals_task) = SignalHandler::new(executor)?;
     signals_handler.wait_termination(signals_task).await?;
@@ -716,6 +722,11 @@ async fn realmain(settings: Args, executor: Arc<smol::Executor<'static>>) -> Res
     dnet_task.stop().await;
     deg_task.stop().await;
 
+    if let Some(metrics_task) = metrics_task {
+        info!(target: "taud", "Stopping metrics exporter...");
+        metrics_task.stop().await;
+    }
+
     info!(target: "taud", "Flushing sled database...");
     let flushed_bytes = sled_db.flush_async().await?;
     info!(target: "taud", "Flushed {} bytes", flushed_bytes);
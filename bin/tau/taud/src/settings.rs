@@ -18,6 +18,7 @@
 
 use structopt::StructOpt;
 use structopt_toml::{serde::Deserialize, StructOptToml};
+use url::Url;
 
 use darkfi::{net::settings::SettingsOpt, rpc::settings::RpcSettingsOpt};
 
@@ -49,6 +50,10 @@ pub struct Args {
     /// JSON-RPC settings
     pub rpc: RpcSettingsOpt,
 
+    #[structopt(long)]
+    /// Optional Prometheus metrics listen URL
+    pub metrics_listen: Option<Url>,
+
     #[structopt(flatten)]
     /// P2P network settings
     pub net: SettingsOpt,
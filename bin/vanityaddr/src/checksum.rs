@@ -0,0 +1,135 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2025 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Bech32m encoding (BIP-350), used to print the candidates in a
+//! checksummed form next to their plain base58 encoding, so that a
+//! mistyped character can be detected when they are copied around.
+
+/// Bech32 data alphabet
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+/// Checksum constant distinguishing bech32m from bech32
+const BECH32M_CONST: u32 = 0x2bc830a3;
+
+fn polymod(values: &[u8]) -> u32 {
+    const GEN: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+
+    let mut chk: u32 = 1;
+    for v in values {
+        let b = chk >> 25;
+        chk = ((chk & 0x1ffffff) << 5) ^ *v as u32;
+        for (i, g) in GEN.iter().enumerate() {
+            if (b >> i) & 1 == 1 {
+                chk ^= g;
+            }
+        }
+    }
+    chk
+}
+
+fn hrp_expand(hrp: &str) -> Vec<u8> {
+    let mut ret: Vec<u8> = hrp.bytes().map(|b| b >> 5).collect();
+    ret.push(0);
+    ret.extend(hrp.bytes().map(|b| b & 31));
+    ret
+}
+
+/// Regroup 8-bit bytes into 5-bit values, padding the last one with zeros
+fn to_base32(data: &[u8]) -> Vec<u8> {
+    let mut ret = Vec::with_capacity((data.len() * 8).div_ceil(5));
+    let mut acc: u32 = 0;
+    let mut bits = 0;
+    for b in data {
+        acc = (acc << 8) | *b as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            ret.push(((acc >> bits) & 31) as u8);
+        }
+    }
+    if bits > 0 {
+        ret.push(((acc << (5 - bits)) & 31) as u8);
+    }
+    ret
+}
+
+/// Encode 5-bit `values` with the given human-readable part
+fn encode_base32(hrp: &str, values: &[u8]) -> String {
+    let mut checked = hrp_expand(hrp);
+    checked.extend_from_slice(values);
+    checked.extend_from_slice(&[0; 6]);
+    let chk = polymod(&checked) ^ BECH32M_CONST;
+
+    let mut ret = format!("{}1", hrp);
+    for v in values {
+        ret.push(CHARSET[*v as usize] as char);
+    }
+    for i in 0..6 {
+        ret.push(CHARSET[((chk >> (5 * (5 - i))) & 31) as usize] as char);
+    }
+    ret
+}
+
+/// Encode `data` as bech32m with the given lowercase human-readable part
+pub fn encode(hrp: &str, data: &[u8]) -> String {
+    encode_base32(hrp, &to_base32(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Check the checksum of a bech32m string
+    fn verify(string: &str) -> bool {
+        let string = string.to_lowercase();
+        let Some((hrp, data)) = string.rsplit_once('1') else { return false };
+        if hrp.is_empty() || data.len() < 6 {
+            return false
+        }
+
+        let mut values = hrp_expand(hrp);
+        for c in data.bytes() {
+            let Some(v) = CHARSET.iter().position(|x| *x == c) else { return false };
+            values.push(v as u8);
+        }
+        polymod(&values) == BECH32M_CONST
+    }
+
+    #[test]
+    fn bech32m_encoding() {
+        // Test vectors from BIP-350
+        assert_eq!(encode_base32("a", &[]), "a1lqfn3a");
+        let values: Vec<u8> = (0..32).rev().collect();
+        assert_eq!(
+            encode_base32("abcdef", &values),
+            "abcdef1l7aum6echk45nj3s0wdvt2fg8x9yrzpqzd3ryx"
+        );
+        assert!(verify("A1LQFN3A"));
+        assert!(verify("?1v759aa"));
+
+        let encoded = encode("drk", &[0xff; 32]);
+        assert!(verify(&encoded));
+
+        // A single changed character has to be detected
+        let mut typo = encoded.into_bytes();
+        typo[10] = if typo[10] == b'q' { b'p' } else { b'q' };
+        assert!(!verify(&String::from_utf8(typo).unwrap()));
+
+        assert_eq!(to_base32(&[0xff]), vec![31, 28]);
+    }
+}
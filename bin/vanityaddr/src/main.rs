@@ -17,7 +17,10 @@
  */
 
 use std::{
+    fs,
+    path::PathBuf,
     process::{exit, ExitCode},
+    str::FromStr,
     sync::{mpsc::channel, Arc},
     thread::available_parallelism,
 };
@@ -25,21 +28,36 @@ use std::{
 use arg::Args;
 use darkfi::{util::cli::ProgressInc, ANSI_LOGO};
 use darkfi_money_contract::{model::TokenId, MoneyFunction};
-use darkfi_sdk::crypto::{
-    contract_id::MONEY_CONTRACT_ID, poseidon_hash, BaseBlind, ContractId, FuncRef, PublicKey,
-    SecretKey,
+use darkfi_sdk::{
+    crypto::{
+        contract_id::MONEY_CONTRACT_ID, pasta_prelude::*, poseidon_hash, BaseBlind, Blind,
+        ContractId, FuncRef, PublicKey, SecretKey,
+    },
+    pasta::pallas,
 };
 use rand::rngs::OsRng;
-use rayon::iter::ParallelIterator;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use tinyjson::JsonValue;
+
+/// Bech32m checksummed encoding
+mod checksum;
+
+/// Search patterns
+mod pattern;
+use pattern::{Mode, Pattern};
+
+/// Resumable search progress
+mod state;
+use state::State;
 
 const ABOUT: &str =
     concat!("vanityaddr ", env!("CARGO_PKG_VERSION"), '\n', env!("CARGO_PKG_DESCRIPTION"));
 
 const USAGE: &str = r#"
-Usage: vanityaddr [OPTIONS] <PREFIX> <PREFIX> ...
+Usage: vanityaddr [OPTIONS] <PATTERN> <PATTERN> ...
 
 Arguments:
-  <PREFIX>    Prefixes to search
+  <PATTERN>   Patterns to search
 
 Options:
   -c    Make the search case-sensitive
@@ -47,12 +65,74 @@ Options:
   -A    Search for an address
   -C    Search for a Contract ID
   -T    Search for a Token ID
+  -m    Match mode: prefix, suffix, contains or regex (default: prefix)
+  -n    Number of matches to find for each pattern (default: 1)
+  -r    Save the search progress to this file, and resume from it if it exists
+  -s    Derive the secrets from the seed in this file instead of randomly
+  -d    Derive and print the candidate at this index of the seed, then exit
+  -S    Print only the secret keys, one base58 key per line, as read by
+        `drk wallet --import-secrets` (requires -A)
+
+The seed derivation is not BIP32. The secret at path m/<kind>/<index>
+is derived with the BLAKE3 derive_key function over the seed, kind and
+index, so the seed can only be used to recover results with this tool.
 "#;
 
+/// Number of candidates tried between progress checkpoints
+const BATCH_SIZE: u64 = 1 << 16;
+
+/// Domain separation context of the seed derivation
+const SEED_CONTEXT: &str = "DarkFi vanityaddr seed derivation v1";
+
 fn usage() {
     print!("{}{}\n{}", ANSI_LOGO, ABOUT, USAGE);
 }
 
+/// Where the secrets of the candidates come from
+enum Source {
+    /// Fresh randomness for every candidate
+    Random,
+    /// Deterministic derivation from a seed, so any result can be
+    /// recovered from the seed and its index
+    Seed(Vec<u8>),
+}
+
+impl Source {
+    /// Get the field element used for the given candidate kind and index.
+    /// `branch` separates multiple secrets belonging to the same candidate.
+    fn derive(&self, kind: u32, index: u64, branch: u32) -> pallas::Base {
+        let Self::Seed(seed) = self else { return pallas::Base::random(&mut OsRng) };
+
+        let mut hasher = blake3::Hasher::new_derive_key(SEED_CONTEXT);
+        hasher.update(seed);
+        hasher.update(&kind.to_le_bytes());
+        hasher.update(&index.to_le_bytes());
+        hasher.update(&branch.to_le_bytes());
+
+        let mut bytes = [0u8; 64];
+        hasher.finalize_xof().fill(&mut bytes);
+        pallas::Base::from_uniform_bytes(&bytes)
+    }
+
+    /// Short identifier of the source, which doesn't leak the seed
+    fn id(&self) -> String {
+        match self {
+            Self::Random => "random".to_string(),
+            Self::Seed(seed) => blake3::hash(seed).to_hex().to_string(),
+        }
+    }
+}
+
+/// Parameters of a running search
+struct Search {
+    patterns: Vec<Pattern>,
+    count: u64,
+    source: Source,
+    progress_file: Option<PathBuf>,
+    /// Print only the secret keys instead of JSON
+    secrets_only: bool,
+}
+
 struct DrkAddr {
     pub public: PublicKey,
     pub secret: SecretKey,
@@ -69,27 +149,50 @@ struct DrkContract {
     pub secret: SecretKey,
 }
 
-trait Prefixable {
-    fn new() -> Self;
-    fn to_string(&self) -> String;
-    fn _get_secret(&self) -> SecretKey;
+trait Candidate: Send {
+    /// Candidate kind, used in the seed derivation path
+    const KIND: u32;
+    /// Name of the JSON field holding the candidate
+    const NAME: &'static str;
+    /// Human-readable part of the checksummed encoding
+    const HRP: &'static str;
 
-    fn starts_with(&self, prefix: &str, case_sensitive: bool) -> bool {
-        if case_sensitive {
-            self.to_string().starts_with(prefix)
-        } else {
-            self.to_string().to_lowercase().starts_with(prefix.to_lowercase().as_str())
-        }
+    fn derive(source: &Source, index: u64) -> Self;
+    fn to_string(&self) -> String;
+    /// Secret key the candidate is derived from
+    fn secret(&self) -> &SecretKey;
+    /// Extra JSON fields with the secrets of the candidate
+    fn secrets_json(&self) -> String;
+
+    /// Bech32m encoding of the candidate, carrying a checksum
+    fn to_checksummed(&self) -> String {
+        let bytes = bs58::decode(self.to_string()).into_vec().unwrap();
+        checksum::encode(Self::HRP, &bytes)
     }
 
-    fn starts_with_any(&self, prefixes: &[String], case_sensitive: bool) -> bool {
-        prefixes.iter().any(|prefix| self.starts_with(prefix, case_sensitive))
+    fn to_json(&self, pattern: Option<&str>, index: u64, source: &Source) -> String {
+        let mut json = format!("{{\"{}\":\"{}\"", Self::NAME, self.to_string());
+        json.push_str(&format!(",\"checksummed\":\"{}\"", self.to_checksummed()));
+        if let Some(pattern) = pattern {
+            let pattern = JsonValue::String(pattern.to_string()).stringify().unwrap();
+            json.push_str(&format!(",\"pattern\":{},\"attempts\":{}", pattern, index + 1));
+        }
+        json.push_str(&self.secrets_json());
+        if let Source::Seed(_) = source {
+            json.push_str(&format!(",\"path\":\"m/{}/{}\"", Self::KIND, index));
+        }
+        json.push('}');
+        json
     }
 }
 
-impl Prefixable for DrkAddr {
-    fn new() -> Self {
-        let secret = SecretKey::random(&mut OsRng);
+impl Candidate for DrkAddr {
+    const KIND: u32 = 0;
+    const NAME: &'static str = "address";
+    const HRP: &'static str = "drk";
+
+    fn derive(source: &Source, index: u64) -> Self {
+        let secret = SecretKey::from(source.derive(Self::KIND, index, 0));
         let public = PublicKey::from_secret(secret);
         Self { public, secret }
     }
@@ -98,16 +201,24 @@ impl Prefixable for DrkAddr {
         self.public.to_string()
     }
 
-    fn _get_secret(&self) -> SecretKey {
-        self.secret
+    fn secret(&self) -> &SecretKey {
+        &self.secret
+    }
+
+    fn secrets_json(&self) -> String {
+        format!(",\"secret\":\"{}\"", self.secret)
     }
 }
 
-impl Prefixable for DrkToken {
-    fn new() -> Self {
+impl Candidate for DrkToken {
+    const KIND: u32 = 1;
+    const NAME: &'static str = "token_id";
+    const HRP: &'static str = "drktoken";
+
+    fn derive(source: &Source, index: u64) -> Self {
         // Generate the mint authority secret key and blind
-        let secret = SecretKey::random(&mut OsRng);
-        let blind = BaseBlind::random(&mut OsRng);
+        let secret = SecretKey::from(source.derive(Self::KIND, index, 0));
+        let blind = Blind(source.derive(Self::KIND, index, 1));
 
         // Create the Auth FuncID
         let func_id = FuncRef {
@@ -130,14 +241,22 @@ impl Prefixable for DrkToken {
         self.token_id.to_string()
     }
 
-    fn _get_secret(&self) -> SecretKey {
-        self.secret
+    fn secret(&self) -> &SecretKey {
+        &self.secret
+    }
+
+    fn secrets_json(&self) -> String {
+        format!(",\"secret\":\"{}\",\"blind\":\"{}\"", self.secret, self.blind)
     }
 }
 
-impl Prefixable for DrkContract {
-    fn new() -> Self {
-        let secret = SecretKey::random(&mut OsRng);
+impl Candidate for DrkContract {
+    const KIND: u32 = 2;
+    const NAME: &'static str = "contract_id";
+    const HRP: &'static str = "drkcontract";
+
+    fn derive(source: &Source, index: u64) -> Self {
+        let secret = SecretKey::from(source.derive(Self::KIND, index, 0));
         let contract_id = ContractId::derive(secret);
         Self { contract_id, secret }
     }
@@ -146,8 +265,69 @@ impl Prefixable for DrkContract {
         self.contract_id.to_string()
     }
 
-    fn _get_secret(&self) -> SecretKey {
-        self.secret
+    fn secret(&self) -> &SecretKey {
+        &self.secret
+    }
+
+    fn secrets_json(&self) -> String {
+        format!(",\"secret\":\"{}\"", self.secret)
+    }
+}
+
+/// Search in batches until every pattern has `search.count` matches,
+/// printing each match as a JSON line and saving the progress after
+/// every batch.
+fn search<T: Candidate>(search: &Search, state: &mut State, progress: &ProgressInc) {
+    while state.found.iter().any(|found| *found < search.count) {
+        let start = state.next_index;
+        let mut hits: Vec<(u64, T, Vec<usize>)> = (start..start + BATCH_SIZE)
+            .into_par_iter()
+            .inspect(|_| progress.inc(1))
+            .filter_map(|index| {
+                let candidate = T::derive(&search.source, index);
+                let string = candidate.to_string();
+                let lowercase = string.to_lowercase();
+
+                let matched: Vec<usize> = search
+                    .patterns
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, pattern)| pattern.matches(&string, &lowercase))
+                    .map(|(i, _)| i)
+                    .collect();
+
+                if matched.is_empty() {
+                    return None
+                }
+                Some((index, candidate, matched))
+            })
+            .collect();
+
+        // Keep the output deterministic when deriving from a seed
+        hits.sort_by_key(|(index, _, _)| *index);
+
+        for (index, candidate, matched) in hits {
+            // A candidate counts for the first matched pattern still missing results
+            let Some(i) = matched.into_iter().find(|i| state.found[*i] < search.count) else {
+                continue
+            };
+            state.found[i] += 1;
+
+            eprint!("\r\x1b[2K");
+            if search.secrets_only {
+                println!("{}", candidate.secret());
+            } else {
+                let pattern = &search.patterns[i].source;
+                println!("{}", candidate.to_json(Some(pattern), index, &search.source));
+            }
+        }
+
+        state.next_index = start + BATCH_SIZE;
+        if let Some(path) = &search.progress_file {
+            if let Err(e) = state.save(path) {
+                eprintln!("\r\x1b[2KError: Failed saving progress to {}: {}", path.display(), e);
+            }
+        }
     }
 }
 
@@ -158,6 +338,12 @@ fn main() -> ExitCode {
     let mut addrflag = false;
     let mut toknflag = false;
     let mut ctrcflag = false;
+    let mut secretsflag = false;
+    let mut mode = String::from("prefix");
+    let mut count = String::from("1");
+    let mut progress_file = None;
+    let mut seed_file = None;
+    let mut derive_index = None;
 
     let mut n_threads = available_parallelism().unwrap().get();
 
@@ -167,14 +353,20 @@ fn main() -> ExitCode {
             'A' => addrflag = true,
             'T' => toknflag = true,
             'C' => ctrcflag = true,
+            'S' => secretsflag = true,
             't' => n_threads = args.eargf().parse::<usize>().unwrap(),
+            'm' => mode = args.eargf().to_string(),
+            'n' => count = args.eargf().to_string(),
+            'r' => progress_file = Some(PathBuf::from(args.eargf())),
+            's' => seed_file = Some(args.eargf().to_string()),
+            'd' => derive_index = Some(args.eargf().to_string()),
             _ => hflag = true,
         });
 
         argv = args.parse();
     }
 
-    if hflag || argv.is_empty() {
+    if hflag || (argv.is_empty() && derive_index.is_none()) {
         usage();
         return ExitCode::FAILURE
     }
@@ -184,17 +376,116 @@ fn main() -> ExitCode {
         return ExitCode::FAILURE
     }
 
-    // Validate search prefixes
-    for (idx, prefix) in argv.iter().enumerate() {
-        match bs58::decode(prefix).into_vec() {
-            Ok(_) => {}
+    if secretsflag && !addrflag {
+        eprintln!("Error: Only address secrets can be imported into drk. Use -S with -A.");
+        return ExitCode::FAILURE
+    }
+
+    let source = match seed_file {
+        Some(path) => match fs::read(&path) {
+            Ok(seed) if !seed.trim_ascii().is_empty() => Source::Seed(seed.trim_ascii().to_vec()),
+            Ok(_) => {
+                eprintln!("Error: Seed file {} is empty", path);
+                return ExitCode::FAILURE
+            }
+            Err(e) => {
+                eprintln!("Error: Failed reading seed file {}: {}", path, e);
+                return ExitCode::FAILURE
+            }
+        },
+        None => Source::Random,
+    };
+
+    // Recover a single candidate from the seed
+    if let Some(index) = derive_index {
+        let Source::Seed(_) = source else {
+            eprintln!("Error: Deriving a candidate requires a seed file (-s)");
+            return ExitCode::FAILURE
+        };
+
+        let Ok(index) = index.parse::<u64>() else {
+            eprintln!("Error: Invalid index: {}", index);
+            return ExitCode::FAILURE
+        };
+
+        let json = if secretsflag {
+            DrkAddr::derive(&source, index).secret.to_string()
+        } else if addrflag {
+            DrkAddr::derive(&source, index).to_json(None, index, &source)
+        } else if toknflag {
+            DrkToken::derive(&source, index).to_json(None, index, &source)
+        } else {
+            DrkContract::derive(&source, index).to_json(None, index, &source)
+        };
+        println!("{}", json);
+        return ExitCode::SUCCESS
+    }
+
+    let mode = match Mode::from_str(&mode) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return ExitCode::FAILURE
+        }
+    };
+
+    let count = match count.parse::<u64>() {
+        Ok(v) if v > 0 => v,
+        _ => {
+            eprintln!("Error: Invalid number of matches: {}", count);
+            return ExitCode::FAILURE
+        }
+    };
+
+    // Validate search patterns
+    let mut patterns = Vec::with_capacity(argv.len());
+    for (idx, pattern) in argv.iter().enumerate() {
+        match Pattern::new(pattern, mode, cflag) {
+            Ok(v) => patterns.push(v),
             Err(e) => {
-                eprintln!("Error: Invalid base58 for prefix #{}: {}", idx, e);
+                eprintln!("Error: Invalid pattern #{}: {}", idx, e);
                 return ExitCode::FAILURE
             }
         }
     }
 
+    // Resume a previous search if its progress file exists
+    let kind = if addrflag {
+        DrkAddr::KIND
+    } else if toknflag {
+        DrkToken::KIND
+    } else {
+        DrkContract::KIND
+    };
+    let description = format!("{}:{}:{}:{}:{}:{:?}", kind, mode, cflag, count, source.id(), argv);
+    let mut state = State::new(description.clone(), patterns.len());
+    if let Some(path) = &progress_file {
+        if path.exists() {
+            state = match State::load(path) {
+                Ok(v) => v,
+                Err(e) => {
+                    eprintln!("Error: Failed loading progress from {}: {}", path.display(), e);
+                    return ExitCode::FAILURE
+                }
+            };
+
+            if state.search != description || state.found.len() != patterns.len() {
+                eprintln!("Error: Progress file {} belongs to a different search", path.display());
+                return ExitCode::FAILURE
+            }
+
+            eprintln!("Resuming search at attempt {}", state.next_index);
+        }
+    }
+
+    let search_ = Search {
+        patterns,
+        count,
+        source,
+        progress_file: progress_file.clone(),
+        secrets_only: secretsflag,
+    };
+
     // Handle SIGINT
     let (tx, rx) = channel();
     ctrlc::set_handler(move || tx.send(()).expect("Could not send signal on channel"))
@@ -208,56 +499,21 @@ fn main() -> ExitCode {
     let rayon_pool = rayon::ThreadPoolBuilder::new().num_threads(n_threads).build().unwrap();
     rayon_pool.spawn(move || {
         if addrflag {
-            let addr = rayon::iter::repeat(DrkAddr::new)
-                .inspect(|_| progress_.inc(1))
-                .map(|create| create())
-                .find_any(|address| address.starts_with_any(&argv, cflag))
-                .expect("Failed to find an address match");
-
-            // The above will keep running until it finds a match or until
-            // the program terminates. Only if a match is found shall the
-            // following code be executed and the program exit successfully:
-            let attempts = progress_.position();
-            progress_.finish_and_clear();
-
-            println!(
-                "{{\"address\":\"{}\",\"attempts\":{},\"secret\":\"{}\"}}",
-                addr.public, attempts, addr.secret,
-            );
+            search::<DrkAddr>(&search_, &mut state, &progress_);
         }
 
         if toknflag {
-            let tid = rayon::iter::repeat(DrkToken::new)
-                .inspect(|_| progress_.inc(1))
-                .map(|create| create())
-                .find_any(|token_id| token_id.starts_with_any(&argv, cflag))
-                .expect("Failed to find a token ID match");
-
-            let attempts = progress_.position();
-            progress_.finish_and_clear();
-
-            println!(
-                "{{\"token_id\":\"{}\",\"attempts\":{},\"secret\":\"{}\",\"blind\":\"{}\"}}",
-                tid.token_id, attempts, tid.secret, tid.blind
-            );
+            search::<DrkToken>(&search_, &mut state, &progress_);
         }
 
         if ctrcflag {
-            let cid = rayon::iter::repeat(DrkContract::new)
-                .inspect(|_| progress_.inc(1))
-                .map(|create| create())
-                .find_any(|contract_id| contract_id.starts_with_any(&argv, cflag))
-                .expect("Failed to find a contract ID match");
-
-            let attempts = progress_.position();
-            progress_.finish_and_clear();
-
-            println!(
-                "{{\"contract_id\":\"{}\",\"attempts\":{},\"secret\":\"{}\"}}",
-                cid.contract_id, attempts, cid.secret,
-            );
+            search::<DrkContract>(&search_, &mut state, &progress_);
         }
 
+        // The above will keep running until all the matches are found or
+        // until the program terminates. Only if they are all found shall
+        // the program exit successfully.
+        progress_.finish_and_clear();
         exit(0);
     });
 
@@ -265,5 +521,8 @@ fn main() -> ExitCode {
     rx.recv().expect("Could not receive from channel");
     progress.finish_and_clear();
     eprintln!("\r\x1b[2KCaught SIGINT, exiting...");
+    if let Some(path) = progress_file {
+        eprintln!("Progress is saved in {}, run the same command to resume.", path.display());
+    }
     ExitCode::FAILURE
 }
@@ -0,0 +1,146 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2025 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{fmt, str::FromStr};
+
+use regex::{Regex, RegexBuilder};
+
+/// Characters of the base58 alphabet used by DarkFi
+const BASE58_ALPHABET: &str = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// Where in the encoded string a pattern has to be found
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Mode {
+    Prefix,
+    Suffix,
+    Contains,
+    Regex,
+}
+
+impl FromStr for Mode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "prefix" => Ok(Self::Prefix),
+            "suffix" => Ok(Self::Suffix),
+            "contains" => Ok(Self::Contains),
+            "regex" => Ok(Self::Regex),
+            _ => Err(format!("Unknown match mode: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Self::Prefix => "prefix",
+            Self::Suffix => "suffix",
+            Self::Contains => "contains",
+            Self::Regex => "regex",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+enum Matcher {
+    Text(Mode, String),
+    Regex(Regex),
+}
+
+/// A single search pattern given on the command line
+pub struct Pattern {
+    /// The pattern as given by the user
+    pub source: String,
+    matcher: Matcher,
+    case_sensitive: bool,
+}
+
+impl Pattern {
+    pub fn new(source: &str, mode: Mode, case_sensitive: bool) -> Result<Self, String> {
+        let matcher = match mode {
+            Mode::Regex => {
+                match RegexBuilder::new(source).case_insensitive(!case_sensitive).build() {
+                    Ok(re) => Matcher::Regex(re),
+                    Err(e) => return Err(format!("Invalid regex: {}", e)),
+                }
+            }
+            _ => {
+                // In case-insensitive mode a character only has to exist in
+                // the base58 alphabet in one of its cases, e.g. `l` for `L`.
+                let valid = |c: char| {
+                    BASE58_ALPHABET.contains(c) ||
+                        (!case_sensitive &&
+                            (BASE58_ALPHABET.contains(c.to_ascii_uppercase()) ||
+                                BASE58_ALPHABET.contains(c.to_ascii_lowercase())))
+                };
+                if let Some(c) = source.chars().find(|c| !valid(*c)) {
+                    return Err(format!("Invalid base58 character: {}", c))
+                }
+
+                let text = if case_sensitive { source.to_string() } else { source.to_lowercase() };
+                Matcher::Text(mode, text)
+            }
+        };
+
+        Ok(Self { source: source.to_string(), matcher, case_sensitive })
+    }
+
+    /// Check if the pattern matches the given string. `lowercase` must be
+    /// the lowercased `string`, so it is only computed once per candidate.
+    pub fn matches(&self, string: &str, lowercase: &str) -> bool {
+        match &self.matcher {
+            Matcher::Regex(re) => re.is_match(string),
+            Matcher::Text(mode, text) => {
+                let haystack = if self.case_sensitive { string } else { lowercase };
+                match mode {
+                    Mode::Prefix => haystack.starts_with(text.as_str()),
+                    Mode::Suffix => haystack.ends_with(text.as_str()),
+                    _ => haystack.contains(text.as_str()),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pattern_modes() {
+        let s = "DRKN9N83iNs34YHu1RuW5nELvBSrV34JSztE64FR8DpX";
+        let l = s.to_lowercase();
+
+        assert!(Pattern::new("DRK", Mode::Prefix, true).unwrap().matches(s, &l));
+        assert!(!Pattern::new("drk", Mode::Prefix, true).unwrap().matches(s, &l));
+        assert!(Pattern::new("drk", Mode::Prefix, false).unwrap().matches(s, &l));
+        assert!(Pattern::new("DpX", Mode::Suffix, true).unwrap().matches(s, &l));
+        assert!(!Pattern::new("DRK", Mode::Suffix, true).unwrap().matches(s, &l));
+        assert!(Pattern::new("YHu", Mode::Contains, true).unwrap().matches(s, &l));
+        assert!(Pattern::new("^drk.*dpx$", Mode::Regex, false).unwrap().matches(s, &l));
+        assert!(!Pattern::new("^drk.*dpx$", Mode::Regex, true).unwrap().matches(s, &l));
+
+        assert!(Pattern::new("0OIl", Mode::Prefix, true).is_err());
+        assert!(Pattern::new("0", Mode::Prefix, false).is_err());
+        assert!(Pattern::new("l", Mode::Prefix, true).is_err());
+        assert!(Pattern::new("nel", Mode::Contains, false).unwrap().matches(s, &l));
+        assert!(Pattern::new("Oi", Mode::Prefix, false).is_ok());
+        assert!(Pattern::new("(", Mode::Regex, true).is_err());
+    }
+}
@@ -0,0 +1,111 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2025 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{
+    collections::HashMap,
+    fs,
+    io::{Error, ErrorKind, Result},
+    path::Path,
+};
+
+use tinyjson::JsonValue;
+
+/// Progress of a search, saved to disk so long searches can be resumed
+#[derive(Debug, PartialEq)]
+pub struct State {
+    /// Description of the search parameters, used to refuse resuming
+    /// a different search from the same file
+    pub search: String,
+    /// Index of the next candidate to try
+    pub next_index: u64,
+    /// Number of matches found so far for each pattern
+    pub found: Vec<u64>,
+}
+
+impl State {
+    pub fn new(search: String, n_patterns: usize) -> Self {
+        Self { search, next_index: 0, found: vec![0; n_patterns] }
+    }
+
+    /// Load the state from the given file
+    pub fn load(path: &Path) -> Result<Self> {
+        let data = fs::read_to_string(path)?;
+        let invalid = || Error::new(ErrorKind::InvalidData, "Invalid progress file");
+
+        let json: JsonValue = data.parse().map_err(|_| invalid())?;
+        let Some(map) = json.get::<HashMap<String, JsonValue>>() else { return Err(invalid()) };
+
+        let Some(search) = map.get("search").and_then(|v| v.get::<String>()) else {
+            return Err(invalid())
+        };
+        let Some(next_index) = map.get("next_index").and_then(|v| v.get::<f64>()) else {
+            return Err(invalid())
+        };
+        let Some(found) = map.get("found").and_then(|v| v.get::<Vec<JsonValue>>()) else {
+            return Err(invalid())
+        };
+
+        let mut counts = Vec::with_capacity(found.len());
+        for count in found {
+            let Some(count) = count.get::<f64>() else { return Err(invalid()) };
+            counts.push(*count as u64);
+        }
+
+        Ok(Self { search: search.clone(), next_index: *next_index as u64, found: counts })
+    }
+
+    /// Write the state to the given file. The file is replaced atomically,
+    /// so an interrupted write can't corrupt the saved progress.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let found = self.found.iter().map(|n| JsonValue::Number(*n as f64)).collect();
+        let json = JsonValue::Object(HashMap::from([
+            ("search".to_string(), JsonValue::String(self.search.clone())),
+            ("next_index".to_string(), JsonValue::Number(self.next_index as f64)),
+            ("found".to_string(), JsonValue::Array(found)),
+        ]));
+
+        let Ok(data) = json.stringify() else {
+            return Err(Error::new(ErrorKind::InvalidData, "Failed to encode progress"))
+        };
+
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, data)?;
+        fs::rename(&tmp, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn state_save_load() {
+        let path = std::env::temp_dir().join("vanityaddr_state_test.json");
+
+        let mut state = State::new("address:prefix:drk\n\"x\"".to_string(), 2);
+        state.next_index = 1 << 40;
+        state.found[1] = 3;
+
+        state.save(&path).unwrap();
+        assert_eq!(State::load(&path).unwrap(), state);
+
+        fs::write(&path, "{\"search\":1}").unwrap();
+        assert!(State::load(&path).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,304 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2025 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use darkfi::zkas::{
+    ast::{Constant, Variable, Witness},
+    lexer::{Token, TokenType},
+    Analyzer, Diagnostic, Lexer, Opcode, Parser,
+};
+use lsp_types::{
+    CompletionItem, CompletionItemKind, DiagnosticSeverity, Documentation, MarkupContent,
+    MarkupKind, Position, Range,
+};
+
+/// Format the signature of an opcode, e.g.
+/// `ec_add(EcPoint, EcPoint) -> EcPoint`
+pub fn signature(opcode: &Opcode) -> String {
+    let (return_types, arg_types) = opcode.arg_types();
+    let args: Vec<&str> = arg_types.iter().map(|t| t.name()).collect();
+    let mut sig = format!("{}({})", opcode.name(), args.join(", "));
+    if !return_types.is_empty() {
+        let rets: Vec<&str> = return_types.iter().map(|t| t.name()).collect();
+        sig.push_str(&format!(" -> {}", rets.join(", ")));
+    }
+    sig
+}
+
+/// The result of running the zkas toolchain over a document.
+/// Analysis stops at the first error, and keeps whatever was
+/// built up to that point.
+#[derive(Default)]
+pub struct Analysis {
+    tokens: Vec<Token>,
+    constants: Vec<Constant>,
+    witnesses: Vec<Witness>,
+    heap: Vec<Variable>,
+    pub diagnostics: Vec<lsp_types::Diagnostic>,
+}
+
+impl Analysis {
+    pub fn new(filename: &str, source: &str) -> Self {
+        let mut analysis = Self::default();
+
+        let lexer = Lexer::new(filename, source.chars());
+        analysis.tokens = match lexer.lex() {
+            Ok(v) => v,
+            Err(e) => {
+                analysis.push_error(&e);
+                return analysis
+            }
+        };
+
+        let parser = Parser::new(filename, source.chars(), analysis.tokens.clone());
+        let parsed = parser.parse();
+        for warning in parser.warnings() {
+            analysis.push(&warning, DiagnosticSeverity::WARNING);
+        }
+
+        let (_, _, constants, witnesses, statements) = match parsed {
+            Ok(v) => v,
+            Err(e) => {
+                analysis.push_error(&e);
+                return analysis
+            }
+        };
+
+        analysis.constants.clone_from(&constants);
+        analysis.witnesses.clone_from(&witnesses);
+
+        let mut analyzer =
+            Analyzer::new(filename, source.chars(), constants, witnesses, statements);
+        if let Err(e) = analyzer.analyze_types() {
+            analysis.push_error(&e);
+        }
+
+        // On errors, the heap holds the variables analyzed so far
+        analysis.heap = analyzer.heap;
+        analysis
+    }
+
+    fn push_error(&mut self, err: &std::io::Error) {
+        match Diagnostic::from_io(err) {
+            Some(d) => self.push(d, DiagnosticSeverity::ERROR),
+            None => self.diagnostics.push(lsp_types::Diagnostic {
+                severity: Some(DiagnosticSeverity::ERROR),
+                message: err.to_string(),
+                source: Some("zkas".to_string()),
+                ..Default::default()
+            }),
+        }
+    }
+
+    fn push(&mut self, diagnostic: &Diagnostic, severity: DiagnosticSeverity) {
+        self.diagnostics.push(lsp_types::Diagnostic {
+            range: self.range(diagnostic.line, diagnostic.column),
+            severity: Some(severity),
+            source: Some(format!("zkas {}", diagnostic.namespace.to_lowercase())),
+            message: diagnostic.message.clone(),
+            ..Default::default()
+        });
+    }
+
+    /// Convert a zkas position to an LSP range. The range spans the
+    /// token found at the position, or a single character otherwise.
+    fn range(&self, line: usize, column: usize) -> Range {
+        if line == 0 {
+            return Range::default()
+        }
+
+        let len = match self.tokens.iter().find(|t| t.line == line && t.column == column) {
+            Some(token) => token.token.len(),
+            None => 1,
+        };
+
+        let start = Position::new((line - 1) as u32, column.saturating_sub(1) as u32);
+        let end = Position::new(start.line, start.character + len as u32);
+        Range::new(start, end)
+    }
+
+    /// Find the symbol token at an LSP position.
+    fn symbol_at(&self, position: Position) -> Option<&Token> {
+        let line = position.line as usize + 1;
+        let column = position.character as usize + 1;
+        self.tokens.iter().find(|t| {
+            t.token_type == TokenType::Symbol &&
+                t.line == line &&
+                t.column <= column &&
+                column < t.column + t.token.len()
+        })
+    }
+
+    /// Return the hover text of the symbol at an LSP position.
+    pub fn hover(&self, position: Position) -> Option<(String, Range)> {
+        let token = self.symbol_at(position)?;
+        let name = token.token.as_str();
+
+        let text = if let Some(opcode) = Opcode::from_name(name) {
+            signature(&opcode)
+        } else if let Some(c) = self.constants.iter().find(|c| c.name == name) {
+            format!("constant {}: {}", c.name, c.typ.name())
+        } else if let Some(w) = self.witnesses.iter().find(|w| w.name == name) {
+            format!("witness {}: {}", w.name, w.typ.name())
+        } else {
+            let v = self.heap.iter().find(|v| v.name == name)?;
+            format!("{}: {}", v.name, v.typ.name())
+        };
+
+        Some((format!("```zkas\n{}\n```", text), self.range(token.line, token.column)))
+    }
+
+    /// Return the range where the symbol at an LSP position is declared.
+    /// Constants, witnesses, and assigned variables are supported.
+    pub fn definition(&self, position: Position) -> Option<Range> {
+        let name = self.symbol_at(position)?.token.as_str();
+
+        let line = if let Some(c) = self.constants.iter().find(|c| c.name == name) {
+            c.line
+        } else if let Some(w) = self.witnesses.iter().find(|w| w.name == name) {
+            w.line
+        } else {
+            let v = self.heap.iter().find(|v| v.name == name)?;
+            v.line
+        };
+
+        // Declarations store the line, but not always the column of the name
+        let token = self.tokens.iter().find(|t| t.line == line && t.token == name)?;
+        Some(self.range(token.line, token.column))
+    }
+
+    /// Return completion items for opcodes and declared symbols.
+    pub fn completions(&self) -> Vec<CompletionItem> {
+        let mut items = vec![];
+
+        for opcode in Opcode::ALL {
+            items.push(CompletionItem {
+                label: opcode.name().to_string(),
+                kind: Some(CompletionItemKind::FUNCTION),
+                detail: Some(signature(&opcode)),
+                documentation: Some(Documentation::MarkupContent(MarkupContent {
+                    kind: MarkupKind::Markdown,
+                    value: format!("```zkas\n{}\n```", signature(&opcode)),
+                })),
+                ..Default::default()
+            });
+        }
+
+        let constants =
+            self.constants.iter().map(|c| (&c.name, c.typ, CompletionItemKind::CONSTANT));
+        let witnesses =
+            self.witnesses.iter().map(|w| (&w.name, w.typ, CompletionItemKind::VARIABLE));
+        let variables = self.heap.iter().map(|v| (&v.name, v.typ, CompletionItemKind::VARIABLE));
+
+        for (name, typ, kind) in constants.chain(witnesses).chain(variables) {
+            items.push(CompletionItem {
+                label: name.clone(),
+                kind: Some(kind),
+                detail: Some(typ.name().to_string()),
+                ..Default::default()
+            });
+        }
+
+        items
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Analysis, DiagnosticSeverity, Position, Range};
+
+    const SOURCE: &str = r#"k = 11;
+field = "pallas";
+
+constant "Arith" {}
+
+witness "Arith" {
+    Base a,
+    Base b,
+}
+
+circuit "Arith" {
+    sum = base_add(a, b);
+    constrain_instance(sum);
+}
+"#;
+
+    fn range(line: u32, character: u32, len: u32) -> Range {
+        Range::new(Position::new(line, character), Position::new(line, character + len))
+    }
+
+    #[test]
+    fn analysis_diagnostics() {
+        std::env::set_var("ZKAS_SILENT", "1");
+
+        // The empty constant section is only a warning
+        let analysis = Analysis::new("arith.zk", SOURCE);
+        assert_eq!(analysis.diagnostics.len(), 1);
+        assert_eq!(analysis.diagnostics[0].severity, Some(DiagnosticSeverity::WARNING));
+
+        // Use an undeclared variable
+        let source = SOURCE.replace("constrain_instance(sum)", "constrain_instance(foo)");
+        let analysis = Analysis::new("arith.zk", &source);
+        assert_eq!(analysis.diagnostics.len(), 2);
+
+        let diagnostic = &analysis.diagnostics[1];
+        assert_eq!(diagnostic.severity, Some(DiagnosticSeverity::ERROR));
+        assert_eq!(diagnostic.range.start.line, 12);
+        assert!(diagnostic.message.contains("foo"));
+
+        // Lexer errors are reported too
+        let analysis = Analysis::new("arith.zk", "k = 11;\nfield = \"pallas;\n");
+        assert_eq!(analysis.diagnostics.len(), 1);
+        assert_eq!(analysis.diagnostics[0].severity, Some(DiagnosticSeverity::ERROR));
+    }
+
+    #[test]
+    fn analysis_hover() {
+        std::env::set_var("ZKAS_SILENT", "1");
+        let analysis = Analysis::new("arith.zk", SOURCE);
+
+        let (text, r) = analysis.hover(Position::new(11, 12)).unwrap();
+        assert_eq!(text, "```zkas\nbase_add(Base, Base) -> Base\n```");
+        assert_eq!(r, range(11, 10, 8));
+
+        let (text, r) = analysis.hover(Position::new(11, 19)).unwrap();
+        assert_eq!(text, "```zkas\nwitness a: Base\n```");
+        assert_eq!(r, range(11, 19, 1));
+
+        let (text, _) = analysis.hover(Position::new(12, 24)).unwrap();
+        assert_eq!(text, "```zkas\nsum: Base\n```");
+
+        // Keywords and whitespace have no hover
+        assert!(analysis.hover(Position::new(5, 0)).is_none());
+        assert!(analysis.hover(Position::new(2, 0)).is_none());
+    }
+
+    #[test]
+    fn analysis_definition() {
+        std::env::set_var("ZKAS_SILENT", "1");
+        let analysis = Analysis::new("arith.zk", SOURCE);
+
+        // Variable `sum` is assigned in the circuit
+        assert_eq!(analysis.definition(Position::new(12, 24)), Some(range(11, 4, 3)));
+
+        // Witness `b` is declared in the witness section
+        assert_eq!(analysis.definition(Position::new(11, 22)), Some(range(7, 9, 1)));
+
+        // Opcodes have no definition
+        assert!(analysis.definition(Position::new(11, 12)).is_none());
+    }
+}
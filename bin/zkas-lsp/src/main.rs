@@ -0,0 +1,218 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2025 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{collections::HashMap, error::Error, process::ExitCode};
+
+use lsp_server::{Connection, Message, Notification, Request, RequestId, Response};
+use lsp_types::{
+    notification::{
+        DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+        Notification as LspNotification, PublishDiagnostics,
+    },
+    request::{Completion, GotoDefinition, HoverRequest, Request as LspRequest},
+    CompletionOptions, CompletionParams, CompletionResponse, DidChangeTextDocumentParams,
+    DidCloseTextDocumentParams, DidOpenTextDocumentParams, GotoDefinitionParams,
+    GotoDefinitionResponse, Hover, HoverContents, HoverParams, HoverProviderCapability, Location,
+    MarkupContent, MarkupKind, OneOf, PublishDiagnosticsParams, ServerCapabilities,
+    TextDocumentSyncCapability, TextDocumentSyncKind, Url,
+};
+
+/// Document analysis
+mod analysis;
+use analysis::Analysis;
+
+type LspResult<T> = Result<T, Box<dyn Error + Send + Sync>>;
+
+/// Language server state
+struct Server {
+    connection: Connection,
+    /// Analysis of the open documents
+    documents: HashMap<Url, Analysis>,
+}
+
+impl Server {
+    /// Main loop handling client messages until shutdown.
+    fn run(&mut self) -> LspResult<()> {
+        while let Ok(msg) = self.connection.receiver.recv() {
+            match msg {
+                Message::Request(req) => {
+                    if self.connection.handle_shutdown(&req)? {
+                        return Ok(())
+                    }
+                    self.handle_request(req)?;
+                }
+                Message::Notification(not) => self.handle_notification(not)?,
+                Message::Response(_) => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn handle_request(&mut self, req: Request) -> LspResult<()> {
+        let result = match req.method.as_str() {
+            HoverRequest::METHOD => {
+                let params: HoverParams = serde_json::from_value(req.params)?;
+                let doc = params.text_document_position_params;
+                let hover = self.documents.get(&doc.text_document.uri).and_then(|analysis| {
+                    let (value, range) = analysis.hover(doc.position)?;
+                    let contents = MarkupContent { kind: MarkupKind::Markdown, value };
+                    Some(Hover { contents: HoverContents::Markup(contents), range: Some(range) })
+                });
+                serde_json::to_value(hover)?
+            }
+
+            GotoDefinition::METHOD => {
+                let params: GotoDefinitionParams = serde_json::from_value(req.params)?;
+                let doc = params.text_document_position_params;
+                let uri = doc.text_document.uri;
+                let location = self.documents.get(&uri).and_then(|analysis| {
+                    let range = analysis.definition(doc.position)?;
+                    Some(GotoDefinitionResponse::Scalar(Location { uri: uri.clone(), range }))
+                });
+                serde_json::to_value(location)?
+            }
+
+            Completion::METHOD => {
+                let params: CompletionParams = serde_json::from_value(req.params)?;
+                let uri = params.text_document_position.text_document.uri;
+                let items = match self.documents.get(&uri) {
+                    Some(analysis) => analysis.completions(),
+                    None => Analysis::default().completions(),
+                };
+                serde_json::to_value(CompletionResponse::Array(items))?
+            }
+
+            _ => {
+                let resp = Response::new_err(
+                    req.id,
+                    lsp_server::ErrorCode::MethodNotFound as i32,
+                    format!("Unhandled method {}", req.method),
+                );
+                self.connection.sender.send(Message::Response(resp))?;
+                return Ok(())
+            }
+        };
+
+        self.respond(req.id, result)
+    }
+
+    fn respond(&self, id: RequestId, result: serde_json::Value) -> LspResult<()> {
+        let resp = Response { id, result: Some(result), error: None };
+        self.connection.sender.send(Message::Response(resp))?;
+        Ok(())
+    }
+
+    fn handle_notification(&mut self, not: Notification) -> LspResult<()> {
+        match not.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: DidOpenTextDocumentParams = serde_json::from_value(not.params)?;
+                let doc = params.text_document;
+                self.update(doc.uri, &doc.text, Some(doc.version))?;
+            }
+
+            DidChangeTextDocument::METHOD => {
+                let params: DidChangeTextDocumentParams = serde_json::from_value(not.params)?;
+                // We use full document sync, so the last change holds the text
+                if let Some(change) = params.content_changes.last() {
+                    let doc = params.text_document;
+                    self.update(doc.uri, &change.text, Some(doc.version))?;
+                }
+            }
+
+            DidCloseTextDocument::METHOD => {
+                let params: DidCloseTextDocumentParams = serde_json::from_value(not.params)?;
+                let uri = params.text_document.uri;
+                self.documents.remove(&uri);
+                self.publish_diagnostics(uri, vec![], None)?;
+            }
+
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Analyze a document and publish its diagnostics.
+    fn update(&mut self, uri: Url, text: &str, version: Option<i32>) -> LspResult<()> {
+        let filename = match uri.to_file_path() {
+            Ok(path) => path.display().to_string(),
+            Err(()) => uri.to_string(),
+        };
+
+        let analysis = Analysis::new(&filename, text);
+        let diagnostics = analysis.diagnostics.clone();
+        self.documents.insert(uri.clone(), analysis);
+        self.publish_diagnostics(uri, diagnostics, version)
+    }
+
+    fn publish_diagnostics(
+        &self,
+        uri: Url,
+        diagnostics: Vec<lsp_types::Diagnostic>,
+        version: Option<i32>,
+    ) -> LspResult<()> {
+        let params = PublishDiagnosticsParams { uri, diagnostics, version };
+        let not = Notification::new(PublishDiagnostics::METHOD.to_string(), params);
+        self.connection.sender.send(Message::Notification(not))?;
+        Ok(())
+    }
+}
+
+fn main() -> ExitCode {
+    // Diagnostics are sent to the client instead of stderr
+    std::env::set_var("ZKAS_SILENT", "1");
+
+    let (connection, io_threads) = Connection::stdio();
+
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        definition_provider: Some(OneOf::Left(true)),
+        completion_provider: Some(CompletionOptions::default()),
+        ..Default::default()
+    };
+
+    let capabilities = match serde_json::to_value(capabilities) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("Error: Failed serializing server capabilities. {}", e);
+            return ExitCode::FAILURE
+        }
+    };
+
+    if let Err(e) = connection.initialize(capabilities) {
+        eprintln!("Error: Failed initializing language server. {}", e);
+        return ExitCode::FAILURE
+    }
+
+    let mut server = Server { connection, documents: HashMap::new() };
+    if let Err(e) = server.run() {
+        eprintln!("Error: {}", e);
+        return ExitCode::FAILURE
+    }
+
+    // Drop the connection so the writer thread can finish
+    drop(server);
+    if let Err(e) = io_threads.join() {
+        eprintln!("Error: {}", e);
+        return ExitCode::FAILURE
+    }
+
+    ExitCode::SUCCESS
+}
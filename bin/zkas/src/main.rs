@@ -17,13 +17,14 @@
  */
 
 use std::{
+    env,
     fs::{read_to_string, File},
     io::Write,
     process::ExitCode,
 };
 
-use arg::Args;
-
+#[cfg(feature = "profile")]
+use darkfi::zk::{CircuitProfile, ProvingCost};
 use darkfi::{
     zkas::{Analyzer, Compiler, Lexer, Parser, ZkBinary},
     ANSI_LOGO,
@@ -45,32 +46,123 @@ Options:
   -i         Interactive semantic analysis
   -e         Examine decoded bytecode
   -h         Print this help
+
+  --profile  Profile circuit cost and check the declared k
+             (requires building with the "profile" feature)
 "#;
 
 fn usage() {
     print!("{}{}\n{}", ANSI_LOGO, ABOUT, USAGE);
 }
 
+/// Lay out the circuit and check that it fits in the declared `k`.
+/// A circuit that doesn't fit in 2^k rows would only fail once proofs
+/// are created, so we catch it here instead.
+#[cfg(feature = "profile")]
+fn profile_circuit(zkbin: &ZkBinary) -> Option<CircuitProfile> {
+    let profile = match CircuitProfile::new(zkbin) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("Error: Failed synthesizing circuit. {}", e);
+            return None
+        }
+    };
+
+    if let Err(e) = profile.check_k(zkbin.k) {
+        eprintln!("Error: Circuit \"{}\" doesn't fit. {}", zkbin.namespace, e);
+        return None
+    }
+
+    Some(profile)
+}
+
+/// Print the layout cost of every statement, and the proving cost of
+/// the circuit at the smallest `k` it fits in.
+#[cfg(feature = "profile")]
+fn print_profile(zkbin: &ZkBinary, profile: &CircuitProfile) -> ExitCode {
+    println!("{:>5}  {:<24}{:>8}{:>8}{:>9}", "#", "Opcode", "Regions", "Rows", "Columns");
+    for (index, statement) in profile.statements.iter().enumerate() {
+        println!(
+            "{:>5}  {:<24}{:>8}{:>8}{:>9}",
+            index,
+            statement.opcode.name(),
+            statement.regions,
+            statement.rows,
+            statement.columns
+        );
+    }
+
+    println!();
+    println!("Setup rows:    {}", profile.setup_rows);
+    println!("Circuit rows:  {} (+{} blinding)", profile.rows, profile.blinding_rows);
+    println!("Smallest k:    {} (declared k = {})", profile.k, zkbin.k);
+    println!("Gas:           {}", profile.gas);
+
+    println!("\nProving with random witnesses at k = {}...", profile.k);
+    let cost = match ProvingCost::measure(zkbin, profile.k) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("Error: Failed measuring proving cost. {}", e);
+            return ExitCode::FAILURE
+        }
+    };
+
+    println!("Keygen time:   {:?}", cost.keygen);
+    println!("Proving time:  {:?}", cost.proving);
+    println!("Proof size:    {} bytes", cost.proof_size);
+    if let Some(peak_memory) = cost.peak_memory {
+        println!("Peak memory:   {} MiB", peak_memory / (1024 * 1024));
+    }
+
+    ExitCode::SUCCESS
+}
+
 fn main() -> ExitCode {
-    let argv;
+    let mut argv = vec![];
     let mut pflag = false;
     let mut iflag = false;
     let mut eflag = false;
     let mut sflag = false;
+    let mut profile_flag = false;
     let mut hflag = false;
     let mut output = String::new();
 
-    {
-        let mut args = Args::new().with_cb(|args, flag| match flag {
-            'p' => pflag = true,
-            'i' => iflag = true,
-            'e' => eflag = true,
-            's' => sflag = true,
-            'o' => output = args.eargf().to_string(),
-            _ => hflag = true,
-        });
-
-        argv = args.parse();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--profile" {
+            profile_flag = true;
+            continue
+        }
+
+        // Everything after "--" is an input
+        if arg == "--" {
+            argv.extend(args.by_ref());
+            break
+        }
+
+        let Some(flags) = arg.strip_prefix('-').filter(|f| !f.is_empty()) else {
+            argv.push(arg);
+            continue
+        };
+
+        for (i, flag) in flags.char_indices() {
+            match flag {
+                'p' => pflag = true,
+                'i' => iflag = true,
+                'e' => eflag = true,
+                's' => sflag = true,
+                // The output file is either the rest of the flag, or the next argument
+                'o' => {
+                    let rest = &flags[i + 1..];
+                    match if rest.is_empty() { args.next() } else { Some(rest.to_string()) } {
+                        Some(v) => output = v,
+                        None => hflag = true,
+                    }
+                    break
+                }
+                _ => hflag = true,
+            }
+        }
     }
 
     if hflag || argv.is_empty() {
@@ -78,6 +170,11 @@ fn main() -> ExitCode {
         return ExitCode::FAILURE
     }
 
+    if profile_flag && !cfg!(feature = "profile") {
+        eprintln!("Error: zkas was built without the \"profile\" feature");
+        return ExitCode::FAILURE
+    }
+
     let filename = argv[0].as_str();
     let source = match read_to_string(filename) {
         Ok(v) => v,
@@ -146,6 +243,28 @@ fn main() -> ExitCode {
     };
     // ANCHOR_END: zkas
 
+    // The bytecode only needs to be decoded for examining or profiling it
+    let zkbin = if eflag || profile_flag {
+        match ZkBinary::decode(&bincode) {
+            Ok(v) => Some(v),
+            Err(e) => {
+                eprintln!("Error: Failed decoding compiled bincode. {}", e);
+                return ExitCode::FAILURE
+            }
+        }
+    } else {
+        None
+    };
+
+    #[cfg(feature = "profile")]
+    let profile = match zkbin.as_ref() {
+        Some(zkbin) if profile_flag => match profile_circuit(zkbin) {
+            Some(v) => Some(v),
+            None => return ExitCode::FAILURE,
+        },
+        _ => None,
+    };
+
     let output = if output.is_empty() { format!("{}.bin", filename) } else { output };
 
     let mut file = match File::create(&output) {
@@ -163,10 +282,14 @@ fn main() -> ExitCode {
 
     println!("Wrote output to {}", &output);
 
-    if eflag {
-        let zkbin = ZkBinary::decode(&bincode).unwrap();
+    if let Some(zkbin) = zkbin.as_ref().filter(|_| eflag) {
         println!("{:#?}", zkbin);
     }
 
+    #[cfg(feature = "profile")]
+    if let (Some(zkbin), Some(profile)) = (&zkbin, &profile) {
+        return print_profile(zkbin, profile)
+    }
+
     ExitCode::SUCCESS
 }
@@ -185,7 +185,7 @@ impl MockP2p {
 
         let registry = p2p.protocol_registry();
         registry
-            .register(net::SESSION_ALL, move |channel, p2p| {
+            .register(net::SESSION_ALL, "ProtocolDebugmsg", 0, move |channel, p2p| {
                 let sender = sender_clone.clone();
                 let seen_debugmsg_ids = seen_debugmsg_ids_clone.clone();
                 async move { ProtocolDebugmsg::init(channel, sender, seen_debugmsg_ids, p2p).await }
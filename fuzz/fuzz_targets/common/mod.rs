@@ -0,0 +1,219 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2025 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Shared harness for fuzzing native contracts through the wasm runtime.
+//!
+//! Every input is turned into a transaction's calls, laid out either as
+//! well-formed [`DarkTree`]s or as leaves with arbitrary indexes. One of
+//! the calls is pointed to the fuzzed contract, and run through its
+//! `metadata`, `exec` and `apply` sections against a [`BlockchainOverlay`]
+//! holding the deployed native contracts. The following must hold:
+//!
+//! * Nothing panics on the host side
+//! * Every section that runs is charged gas
+//! * `metadata` and `exec` never change state
+//! * State is unchanged after reverting a failed call, like the validator does
+
+use std::sync::OnceLock;
+
+use darkfi::{
+    blockchain::{Blockchain, BlockchainOverlay, BlockchainOverlayPtr},
+    runtime::vm_runtime::Runtime,
+    validator::utils::deploy_native_contracts,
+};
+use darkfi_sdk::{
+    crypto::{
+        contract_id::{
+            DAO_CONTRACT_ID, DEPLOYOOOR_CONTRACT_ID, EXCHANGE_CONTRACT_ID, MONEY_CONTRACT_ID,
+        },
+        ContractId,
+    },
+    dark_tree::{DarkForest, DarkLeaf, DarkTree},
+    tx::TransactionHash,
+    ContractCall,
+};
+use darkfi_serial::serialize;
+use libfuzzer_sys::arbitrary::{self, Arbitrary};
+use sled_overlay::sled;
+
+/// Block target used by the runtimes
+const BLOCK_TARGET: u32 = 120;
+
+/// Maximum number of calls in a transaction, as they're indexed by `u8`
+const MAX_CALLS: usize = 255;
+
+/// A single contract call
+#[derive(Debug, Arbitrary)]
+pub struct FuzzCall {
+    /// Native contract the call is made to, for calls other than the fuzzed one
+    contract: u8,
+    /// Function byte, mostly within the contract's functions
+    function: u8,
+    /// Call parameters following the function byte
+    params: Vec<u8>,
+}
+
+impl FuzzCall {
+    fn build(&self, functions: u8) -> ContractCall {
+        let natives =
+            [*MONEY_CONTRACT_ID, *DAO_CONTRACT_ID, *DEPLOYOOOR_CONTRACT_ID, *EXCHANGE_CONTRACT_ID];
+        let contract_id = natives[self.contract as usize % natives.len()];
+
+        // One past the last function covers the unknown function path
+        let mut data = vec![self.function % (functions + 1)];
+        data.extend_from_slice(&self.params);
+
+        ContractCall { contract_id, data }
+    }
+}
+
+/// A call along with the calls it invokes
+#[derive(Debug, Arbitrary)]
+pub struct CallTree {
+    call: FuzzCall,
+    children: Vec<CallTree>,
+}
+
+impl CallTree {
+    fn build(&self, functions: u8) -> DarkTree<ContractCall> {
+        let children = self.children.iter().map(|c| c.build(functions)).collect();
+        DarkTree::new(self.call.build(functions), children, None, None)
+    }
+}
+
+/// Layout of the transaction calls
+#[derive(Debug, Arbitrary)]
+pub enum Layout {
+    /// Well-formed forest built through [`DarkTree`]
+    Trees(Vec<CallTree>),
+    /// Leaves with arbitrary parent and children indexes
+    Raw(Vec<(FuzzCall, Option<u8>, Vec<u8>)>),
+}
+
+impl Layout {
+    fn build(&self, functions: u8) -> Vec<DarkLeaf<ContractCall>> {
+        match self {
+            Self::Trees(trees) => {
+                let mut forest = DarkForest::new(None, None);
+                for tree in trees {
+                    if forest.append(tree.build(functions)).is_err() {
+                        return vec![]
+                    }
+                }
+                forest.build_vec().unwrap_or_default()
+            }
+
+            Self::Raw(leaves) => leaves
+                .iter()
+                .map(|(call, parent, children)| DarkLeaf {
+                    data: call.build(functions),
+                    parent_index: parent.map(|p| p as usize),
+                    children_indexes: children.iter().map(|c| *c as usize).collect(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Fuzzer input for a native contract
+#[derive(Debug, Arbitrary)]
+pub struct ContractInput {
+    layout: Layout,
+    /// Index of the call made to the fuzzed contract
+    call_idx: u8,
+    /// Block height the call is verified at
+    block_height: u32,
+}
+
+/// Blockchain with the native contracts deployed, shared by all runs
+fn blockchain() -> &'static Blockchain {
+    static BLOCKCHAIN: OnceLock<Blockchain> = OnceLock::new();
+    BLOCKCHAIN.get_or_init(|| {
+        let sled_db = sled::Config::new().temporary(true).open().unwrap();
+        let blockchain = Blockchain::new(&sled_db).unwrap();
+
+        let overlay = BlockchainOverlay::new(&blockchain).unwrap();
+        smol::block_on(deploy_native_contracts(&overlay, BLOCK_TARGET)).unwrap();
+        overlay.lock().unwrap().overlay.lock().unwrap().apply().unwrap();
+
+        blockchain
+    })
+}
+
+/// Serialized changes of the overlay over the database
+fn state(overlay: &BlockchainOverlayPtr) -> Vec<u8> {
+    serialize(&overlay.lock().unwrap().overlay.lock().unwrap().diff(&[]).unwrap())
+}
+
+/// Assert the last runtime section was charged gas, and return the gas used so far
+fn charged(runtime: &mut Runtime, section: &str, gas: u64) -> u64 {
+    let used = runtime.gas_used();
+    assert!(used > gas, "{}() was not charged gas", section);
+    used
+}
+
+/// Run a fuzzer input against the given native contract, which has
+/// `functions` function bytes starting from `0x00`.
+pub fn fuzz_contract(contract_id: ContractId, functions: u8, input: ContractInput) {
+    let mut calls = input.layout.build(functions);
+    calls.truncate(MAX_CALLS);
+    if calls.is_empty() {
+        return
+    }
+
+    let call_idx = input.call_idx as usize % calls.len();
+    calls[call_idx].data.contract_id = contract_id;
+    let payload = serialize(&calls);
+
+    let overlay = BlockchainOverlay::new(blockchain()).unwrap();
+    let wasm = overlay.lock().unwrap().contracts.get(contract_id).unwrap();
+    let mut runtime = Runtime::new(
+        &wasm,
+        overlay.clone(),
+        contract_id,
+        input.block_height,
+        BLOCK_TARGET,
+        TransactionHash::none(),
+        call_idx as u8,
+    )
+    .unwrap();
+
+    let initial_state = state(&overlay);
+    let mut gas = runtime.gas_used();
+
+    // Read-only sections must never touch state
+    let metadata = runtime.metadata(&payload);
+    gas = charged(&mut runtime, "metadata", gas);
+    assert_eq!(state(&overlay), initial_state, "metadata() changed state");
+    if metadata.is_err() {
+        return
+    }
+
+    let update = runtime.exec(&payload);
+    gas = charged(&mut runtime, "exec", gas);
+    assert_eq!(state(&overlay), initial_state, "exec() changed state");
+    let Ok(update) = update else { return };
+
+    // A failed update must not have committed any partial writes,
+    // so the state is checked before the validator reverts the overlay.
+    let applied = runtime.apply(&update);
+    charged(&mut runtime, "apply", gas);
+    if applied.is_err() {
+        assert_eq!(state(&overlay), initial_state, "failed apply() changed state");
+    }
+}
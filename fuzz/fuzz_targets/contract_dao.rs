@@ -0,0 +1,28 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2025 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+#![no_main]
+use darkfi_sdk::crypto::contract_id::DAO_CONTRACT_ID;
+use libfuzzer_sys::fuzz_target;
+
+mod common;
+use common::{fuzz_contract, ContractInput};
+
+fuzz_target!(|input: ContractInput| {
+    fuzz_contract(*DAO_CONTRACT_ID, 5, input);
+});
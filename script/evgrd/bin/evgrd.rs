@@ -38,7 +38,7 @@ use darkfi::{
     Error, Result,
 };
 use darkfi_serial::{AsyncDecodable, AsyncEncodable};
-use futures::{FutureExt, AsyncWriteExt};
+use futures::{AsyncWriteExt, FutureExt};
 use log::{debug, error, info};
 use sled_overlay::sled;
 use smol::{fs, lock::Mutex, stream::StreamExt, Executor};
@@ -301,7 +301,7 @@ async fn realmain(args: Args, ex: Arc<Executor<'static>>) -> Result<()> {
     let event_graph_ = Arc::clone(&event_graph);
     let registry = p2p.protocol_registry();
     registry
-        .register(SESSION_DEFAULT, move |channel, _| {
+        .register(SESSION_DEFAULT, "ProtocolEventGraph", 0, move |channel, _| {
             let event_graph_ = event_graph_.clone();
             async move { ProtocolEventGraph::init(event_graph_, channel).await.unwrap() }
         })
@@ -384,7 +384,7 @@ async fn realmain(args: Args, ex: Arc<Executor<'static>>) -> Result<()> {
     let mut rpc_tasks = vec![];
     for listen_url in args.daemon_listen {
         let listener = Listener::new(listen_url, None).await?;
-        let ptlistener = listener.listen().await?;
+        let ptlistener = listener.listen(ex.clone()).await?;
 
         let rpc_task = StoppableTask::new();
         rpc_task.clone().start(
@@ -0,0 +1,122 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2025 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use darkfi::{
+    tx::{ContractCallLeaf, TransactionBuilder},
+    Result,
+};
+use darkfi_contract_test_harness::{init_logger, Holder, TestHarness};
+use darkfi_money_contract::{
+    client::transfer_v1::make_transfer_call, MoneyFunction, MONEY_CONTRACT_ZKAS_BURN_NS_V1,
+    MONEY_CONTRACT_ZKAS_MINT_NS_V1,
+};
+use darkfi_sdk::{crypto::contract_id::MONEY_CONTRACT_ID, ContractCall};
+use darkfi_serial::AsyncEncodable;
+use log::info;
+
+#[test]
+fn expired_tx() -> Result<()> {
+    smol::block_on(async {
+        init_logger();
+
+        // Holders this test will use
+        const HOLDERS: [Holder; 2] = [Holder::Alice, Holder::Bob];
+
+        // Initialize harness
+        let mut th = TestHarness::new(&HOLDERS, false).await?;
+
+        // Generate one new block mined by Alice
+        th.generate_block(&Holder::Alice, &HOLDERS).await?;
+
+        // Block height the transaction expires at
+        let expiry_block_height = 3;
+
+        // Manually create an Alice to Bob transfer call
+        let wallet = th.holders.get(&Holder::Alice).unwrap();
+        let alice_coins = &wallet.unspent_money_coins;
+        let rcpt = th.holders.get(&Holder::Bob).unwrap().keypair.public;
+
+        let (mint_pk, mint_zkbin) = th.proving_keys.get(MONEY_CONTRACT_ZKAS_MINT_NS_V1).unwrap();
+        let (burn_pk, burn_zkbin) = th.proving_keys.get(MONEY_CONTRACT_ZKAS_BURN_NS_V1).unwrap();
+
+        let (xfer_params, secrets, _) = make_transfer_call(
+            wallet.keypair,
+            rcpt,
+            alice_coins[0].note.value,
+            alice_coins[0].note.token_id,
+            alice_coins.to_owned(),
+            wallet.money_merkle_tree.clone(),
+            None,
+            None,
+            mint_zkbin.clone(),
+            mint_pk.clone(),
+            burn_zkbin.clone(),
+            burn_pk.clone(),
+            false,
+        )?;
+
+        // Encode the call
+        let mut data = vec![MoneyFunction::TransferV1 as u8];
+        xfer_params.encode_async(&mut data).await?;
+        let call = ContractCall { contract_id: *MONEY_CONTRACT_ID, data };
+
+        // Create the TransactionBuilder containing the `Transfer` call,
+        // and set its expiry before building and signing it
+        let mut tx_builder =
+            TransactionBuilder::new(ContractCallLeaf { call, proofs: secrets.proofs }, vec![])?;
+        tx_builder.set_expiry(Some(expiry_block_height));
+
+        let mut tx = tx_builder.build()?;
+        let sigs = tx.create_sigs(&secrets.signature_secrets)?;
+        tx.signatures = vec![sigs];
+        assert!(!tx.is_expired(expiry_block_height));
+        assert!(tx.is_expired(expiry_block_height + 1));
+
+        let block_target = wallet.validator.consensus.module.read().await.target;
+
+        info!("[Alice] Verifying transfer tx at its expiry height");
+        wallet
+            .validator
+            .add_test_transactions(&[tx.clone()], expiry_block_height, block_target, false, false)
+            .await?;
+
+        info!("[Alice] Verifying transfer tx after its expiry height");
+        assert!(wallet
+            .validator
+            .add_test_transactions(
+                &[tx.clone()],
+                expiry_block_height + 1,
+                block_target,
+                false,
+                false
+            )
+            .await
+            .is_err());
+
+        info!("[Alice] Verifying transfer tx with a stripped expiry");
+        tx.expiry = None;
+        assert!(wallet
+            .validator
+            .add_test_transactions(&[tx], expiry_block_height, block_target, false, false)
+            .await
+            .is_err());
+
+        // Thanks for reading
+        Ok(())
+    })
+}
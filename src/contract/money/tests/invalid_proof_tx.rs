@@ -0,0 +1,147 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2025 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use darkfi::{
+    error::TxVerifyFailed,
+    tx::{ContractCallLeaf, Transaction, TransactionBuilder},
+    zk::Proof,
+    Error, Result,
+};
+use darkfi_contract_test_harness::{init_logger, Holder, TestHarness};
+use darkfi_money_contract::{
+    client::transfer_v1::{make_transfer_call, TransferCallSecrets},
+    model::MoneyTransferParamsV1,
+    MoneyFunction, MONEY_CONTRACT_ZKAS_BURN_NS_V1, MONEY_CONTRACT_ZKAS_MINT_NS_V1,
+};
+use darkfi_sdk::{crypto::contract_id::MONEY_CONTRACT_ID, ContractCall};
+use darkfi_serial::AsyncEncodable;
+use log::info;
+
+/// Build and sign a transfer transaction from given params, attaching the provided proofs.
+async fn build_transfer_tx(
+    params: &MoneyTransferParamsV1,
+    secrets: &TransferCallSecrets,
+    proofs: Vec<Proof>,
+) -> Result<Transaction> {
+    let mut data = vec![MoneyFunction::TransferV1 as u8];
+    params.encode_async(&mut data).await?;
+    let call = ContractCall { contract_id: *MONEY_CONTRACT_ID, data };
+
+    let mut tx_builder = TransactionBuilder::new(ContractCallLeaf { call, proofs }, vec![])?;
+    let mut tx = tx_builder.build()?;
+    let sigs = tx.create_sigs(&secrets.signature_secrets)?;
+    tx.signatures = vec![sigs];
+
+    Ok(tx)
+}
+
+#[test]
+fn invalid_proof_tx() -> Result<()> {
+    smol::block_on(async {
+        init_logger();
+
+        // Holders this test will use
+        const HOLDERS: [Holder; 2] = [Holder::Alice, Holder::Bob];
+
+        // Initialize harness
+        let mut th = TestHarness::new(&HOLDERS, false).await?;
+
+        // Generate one new block mined by Alice
+        th.generate_block(&Holder::Alice, &HOLDERS).await?;
+
+        let wallet = th.holders.get(&Holder::Alice).unwrap();
+        let alice_coins = &wallet.unspent_money_coins;
+        let rcpt = th.holders.get(&Holder::Bob).unwrap().keypair.public;
+
+        let (mint_pk, mint_zkbin) = th.proving_keys.get(MONEY_CONTRACT_ZKAS_MINT_NS_V1).unwrap();
+        let (burn_pk, burn_zkbin) = th.proving_keys.get(MONEY_CONTRACT_ZKAS_BURN_NS_V1).unwrap();
+
+        // Create two transfer calls spending the same coin
+        let mut calls = vec![];
+        for _ in 0..2 {
+            calls.push(make_transfer_call(
+                wallet.keypair,
+                rcpt,
+                alice_coins[0].note.value,
+                alice_coins[0].note.token_id,
+                alice_coins.to_owned(),
+                wallet.money_merkle_tree.clone(),
+                None,
+                None,
+                mint_zkbin.clone(),
+                mint_pk.clone(),
+                burn_zkbin.clone(),
+                burn_pk.clone(),
+                false,
+            )?);
+        }
+        let (params_a, secrets_a, _) = &calls[0];
+        let (params_b, secrets_b, _) = &calls[1];
+
+        // The first transaction carries the proofs of the second call, so its
+        // state transition and signatures are valid, but its ZK proofs are not.
+        let bad_tx = build_transfer_tx(params_a, secrets_a, secrets_b.proofs.clone()).await?;
+        let good_tx = build_transfer_tx(params_b, secrets_b, secrets_b.proofs.clone()).await?;
+
+        let block_target = wallet.validator.consensus.module.read().await.target;
+        let verifying_block_height = 2;
+
+        info!("[Alice] Verifying the transaction with invalid proofs");
+        let result = wallet
+            .validator
+            .add_test_transactions(
+                &[bad_tx.clone()],
+                verifying_block_height,
+                block_target,
+                false,
+                false,
+            )
+            .await;
+        let Err(Error::TxVerifyFailed(TxVerifyFailed::ErroneousTxs(erroneous_txs))) = result else {
+            panic!("Transaction with invalid proofs was not rejected")
+        };
+        assert_eq!(erroneous_txs, vec![bad_tx.clone()]);
+
+        // Both transactions spend the same coin. The state of the invalid one must be
+        // reverted, so the valid one is not rejected as a double spend.
+        info!("[Alice] Verifying both transactions");
+        let result = wallet
+            .validator
+            .add_test_transactions(
+                &[bad_tx.clone(), good_tx.clone()],
+                verifying_block_height,
+                block_target,
+                false,
+                false,
+            )
+            .await;
+        let Err(Error::TxVerifyFailed(TxVerifyFailed::ErroneousTxs(erroneous_txs))) = result else {
+            panic!("Transaction with invalid proofs was not rejected")
+        };
+        assert_eq!(erroneous_txs, vec![bad_tx.clone()]);
+
+        info!("[Alice] Verifying the valid transaction");
+        wallet
+            .validator
+            .add_test_transactions(&[good_tx], verifying_block_height, block_target, false, false)
+            .await?;
+
+        // Thanks for reading
+        Ok(())
+    })
+}
@@ -0,0 +1,128 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2025 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use darkfi::{
+    blockchain::{BlockchainOverlay, BlockchainOverlayPtr},
+    validator::verification::{verify_transactions, verify_transactions_parallel},
+    Result,
+};
+use darkfi_contract_test_harness::{init_logger, Holder, TestHarness};
+use darkfi_money_contract::{
+    MONEY_CONTRACT_COIN_MERKLE_TREE, MONEY_CONTRACT_INFO_TREE, MONEY_CONTRACT_LATEST_COIN_ROOT,
+    MONEY_CONTRACT_LATEST_NULLIFIER_ROOT,
+};
+use darkfi_sdk::crypto::{contract_id::MONEY_CONTRACT_ID, MerkleTree};
+use log::info;
+
+/// Grab the Money contract state roots and Merkle tree from the given overlay
+fn money_state(overlay: &BlockchainOverlayPtr) -> Result<Vec<Option<Vec<u8>>>> {
+    let lock = overlay.lock().unwrap();
+    let info_tree = lock.contracts.lookup(&MONEY_CONTRACT_ID, MONEY_CONTRACT_INFO_TREE)?;
+    let overlay = lock.overlay.lock().unwrap();
+
+    let mut state = vec![];
+    for key in [
+        MONEY_CONTRACT_COIN_MERKLE_TREE,
+        MONEY_CONTRACT_LATEST_COIN_ROOT,
+        MONEY_CONTRACT_LATEST_NULLIFIER_ROOT,
+    ] {
+        state.push(overlay.get(&info_tree, key)?.map(|v| v.to_vec()));
+    }
+
+    Ok(state)
+}
+
+#[test]
+fn parallel_verification() -> Result<()> {
+    smol::block_on(async {
+        init_logger();
+
+        // Holders this test will use
+        const HOLDERS: [Holder; 2] = [Holder::Alice, Holder::Bob];
+
+        // Initialize harness, with fees enabled
+        let mut th = TestHarness::new(&HOLDERS, true).await?;
+
+        // Generate two new blocks mined by Alice
+        th.generate_block(&Holder::Alice, &HOLDERS).await?;
+        th.generate_block(&Holder::Alice, &HOLDERS).await?;
+
+        // Generate two new blocks mined by Bob
+        th.generate_block(&Holder::Bob, &HOLDERS).await?;
+        th.generate_block(&Holder::Bob, &HOLDERS).await?;
+
+        let current_block_height = 5;
+
+        // Alice and Bob transfer some tokens to each other, paying fees
+        let mut txs = vec![];
+        for (holder, recipient) in [(Holder::Alice, Holder::Bob), (Holder::Bob, Holder::Alice)] {
+            let coins = th.holders.get(&holder).unwrap().unspent_money_coins.clone();
+            let (tx, _, _) = th
+                .transfer(
+                    coins[0].note.value,
+                    &holder,
+                    &recipient,
+                    &[coins[0].clone()],
+                    coins[0].note.token_id,
+                    current_block_height,
+                    false,
+                )
+                .await?;
+            txs.push(tx);
+        }
+
+        let validator = &th.holders.get(&Holder::Alice).unwrap().validator;
+        let block_target = validator.consensus.module.read().await.target;
+
+        info!("[Alice] Verifying transactions serially");
+        let serial_overlay = BlockchainOverlay::new(&validator.blockchain)?;
+        let mut serial_tree = MerkleTree::new(1);
+        let serial_gas = verify_transactions(
+            &serial_overlay,
+            current_block_height,
+            block_target,
+            &txs,
+            &mut serial_tree,
+            true,
+        )
+        .await?;
+
+        info!("[Alice] Verifying transactions in parallel");
+        let parallel_overlay = BlockchainOverlay::new(&validator.blockchain)?;
+        let mut parallel_tree = MerkleTree::new(1);
+        let parallel_gas = verify_transactions_parallel(
+            &parallel_overlay,
+            current_block_height,
+            block_target,
+            &txs,
+            &mut parallel_tree,
+            true,
+        )
+        .await?;
+
+        // Both paths must produce the same outcome
+        assert_eq!(serial_gas, parallel_gas);
+        assert_eq!(serial_tree.root(0), parallel_tree.root(0));
+        let serial_state = money_state(&serial_overlay)?;
+        assert!(serial_state.iter().all(|value| value.is_some()));
+        assert_eq!(serial_state, money_state(&parallel_overlay)?);
+
+        // Thanks for reading
+        Ok(())
+    })
+}
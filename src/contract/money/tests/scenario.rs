@@ -0,0 +1,40 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2025 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Scenario-driven test of `Money::GenesisMint` and `Money::Transfer`.
+//!
+//! The steps and expected state are described in `scenarios/transfer.toml`.
+
+use darkfi::Result;
+use darkfi_contract_test_harness::{init_logger, MoneyModule, Scenario, ScenarioHarness};
+
+#[test]
+fn money_scenario() -> Result<()> {
+    smol::block_on(async {
+        init_logger();
+
+        let scenario = Scenario::from_toml(include_str!("scenarios/transfer.toml"))?;
+
+        let mut th = ScenarioHarness::new(&scenario.wallets).await?;
+        th.add_module(Box::new(MoneyModule)).await?;
+        th.run(&scenario).await?;
+
+        // Thanks for reading
+        Ok(())
+    })
+}
@@ -85,6 +85,14 @@ mod dao_exec;
 /// `Exchange::OrderMatch` functionality
 mod order_match;
 
+/// Scenario-driven tests with pluggable contract modules
+pub mod scenario;
+pub use scenario::{ContractModule, Scenario, ScenarioEnv, ScenarioHarness};
+
+/// `Money` contract module for scenarios
+mod scenario_money;
+pub use scenario_money::MoneyModule;
+
 /// Initialize the logging mechanism
 pub fn init_logger() {
     let mut cfg = simplelog::ConfigBuilder::new();
@@ -235,6 +243,29 @@ impl Wallet {
     }
 }
 
+/// Create the genesis block used by the harness
+fn genesis_block() -> BlockInfo {
+    let mut genesis_block = BlockInfo::default();
+    genesis_block.header.timestamp = Timestamp::from_u64(1689772567);
+    let producer_tx = genesis_block.txs.pop().unwrap();
+    genesis_block.append_txs(vec![producer_tx]);
+    genesis_block
+}
+
+/// Deserialize the cached [`ProvingKey`]s, keyed by their circuit namespace
+fn read_proving_keys(pks: vks::Pks) -> Result<HashMap<String, (ProvingKey, ZkBinary)>> {
+    let mut proving_keys = HashMap::new();
+    for (bincode, namespace, pk) in pks {
+        let mut reader = Cursor::new(pk);
+        let zkbin = ZkBinary::decode(&bincode)?;
+        let circuit = ZkCircuit::new(empty_witnesses(&zkbin)?, &zkbin);
+        let proving_key = ProvingKey::read(&mut reader, circuit)?;
+        proving_keys.insert(namespace, (proving_key, zkbin));
+    }
+
+    Ok(proving_keys)
+}
+
 /// Native contract test harness instance
 pub struct TestHarness {
     /// Initialized [`Holder`]s for this instance
@@ -251,25 +282,14 @@ impl TestHarness {
     /// Instantiate a new [`TestHarness`] given a slice of [`Holder`]s.
     /// Additionally, a `verify_fees` boolean will enforce tx fee verification.
     pub async fn new(holders: &[Holder], verify_fees: bool) -> Result<Self> {
-        // Create a genesis block
-        let mut genesis_block = BlockInfo::default();
-        genesis_block.header.timestamp = Timestamp::from_u64(1689772567);
-        let producer_tx = genesis_block.txs.pop().unwrap();
-        genesis_block.append_txs(vec![producer_tx]);
+        let genesis_block = genesis_block();
 
         // Deterministic PRNG
         let mut rng = Pcg32::new(42);
 
         // Build or read cached ZK PKs and VKs
         let (pks, vks) = vks::get_cached_pks_and_vks()?;
-        let mut proving_keys = HashMap::new();
-        for (bincode, namespace, pk) in pks {
-            let mut reader = Cursor::new(pk);
-            let zkbin = ZkBinary::decode(&bincode)?;
-            let circuit = ZkCircuit::new(empty_witnesses(&zkbin)?, &zkbin);
-            let proving_key = ProvingKey::read(&mut reader, circuit)?;
-            proving_keys.insert(namespace, (proving_key, zkbin));
-        }
+        let proving_keys = read_proving_keys(pks)?;
 
         // Create `Wallet` instances
         let mut holders_map = HashMap::new();
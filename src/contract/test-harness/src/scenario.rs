@@ -0,0 +1,365 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2025 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Scenario-driven contract tests.
+//!
+//! A [`Scenario`] names the wallets taking part in it and lists the steps
+//! to run. Every step is a contract call built by one wallet through a
+//! [`ContractModule`], and then executed by all wallets against their own
+//! [`Validator`](darkfi::validator::Validator). A step can expect the call
+//! to fail, and check the state of wallets after it ran.
+//!
+//! Contracts plug into the harness by implementing [`ContractModule`], so
+//! testing a new contract doesn't require changes to the harness itself.
+//!
+//! Scenarios are written in TOML:
+//!
+//! ```toml
+//! wallets = ["alice", "bob"]
+//!
+//! [[step]]
+//! module = "money"
+//! call = "genesis_mint"
+//! wallet = "alice"
+//! args = { amounts = [100] }
+//!
+//! [[step]]
+//! module = "money"
+//! call = "transfer"
+//! wallet = "alice"
+//! block_height = 1
+//! args = { to = "bob", amount = 30 }
+//! expect = [
+//!     { module = "money", wallet = "alice", state = "balance", args = { value = 70 } },
+//!     { module = "money", wallet = "bob", state = "balance", args = { value = 30 } },
+//! ]
+//!
+//! [[step]]
+//! module = "money"
+//! call = "transfer"
+//! wallet = "bob"
+//! block_height = 2
+//! args = { to = "alice", amount = 50 }
+//! expect_error = "Not enough value"
+//! ```
+
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use darkfi::{
+    blockchain::BlockInfo, tx::Transaction, util::pcg::Pcg32, zk::ProvingKey, zkas::ZkBinary,
+    Error, Result,
+};
+use darkfi_sdk::crypto::Keypair;
+use log::info;
+use serde::Deserialize;
+
+use super::{genesis_block, read_proving_keys, vks, Wallet};
+
+/// A declarative test scenario
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario {
+    /// Names of the wallets taking part in the scenario
+    pub wallets: Vec<String>,
+    /// Steps to run, in order
+    #[serde(default, rename = "step")]
+    pub steps: Vec<Step>,
+}
+
+impl Scenario {
+    /// Parse a scenario from its TOML representation
+    pub fn from_toml(scenario: &str) -> Result<Self> {
+        Ok(toml::from_str(scenario)?)
+    }
+}
+
+/// A single contract call in a [`Scenario`]
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Step {
+    /// Name of the [`ContractModule`] building the call
+    pub module: String,
+    /// Call to build, interpreted by the module
+    pub call: String,
+    /// Wallet building the call
+    pub wallet: String,
+    /// Block height to execute the call at
+    #[serde(default)]
+    pub block_height: u32,
+    /// Call arguments, interpreted by the module
+    #[serde(default)]
+    pub args: toml::Table,
+    /// If set, the call must fail for every wallet, either when it's
+    /// built or executed, with an error containing this message
+    pub expect_error: Option<String>,
+    /// State checks to run after the call
+    #[serde(default)]
+    pub expect: Vec<Expectation>,
+}
+
+/// Expected wallet state after a [`Step`]
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Expectation {
+    /// Name of the [`ContractModule`] checking the state
+    pub module: String,
+    /// Wallet to check
+    pub wallet: String,
+    /// State to check, interpreted by the module
+    pub state: String,
+    /// Expected values, interpreted by the module
+    #[serde(default)]
+    pub args: toml::Table,
+}
+
+/// Wallets and keys shared by all contract modules of a [`ScenarioHarness`]
+pub struct ScenarioEnv {
+    /// Initialized wallets, by name
+    pub wallets: HashMap<String, Wallet>,
+    /// Cached [`ProvingKey`]s for native contract ZK proving
+    pub proving_keys: HashMap<String, (ProvingKey, ZkBinary)>,
+    /// The genesis block for this harness
+    pub genesis_block: BlockInfo,
+}
+
+impl ScenarioEnv {
+    /// Fetch a wallet by its name
+    pub fn wallet(&self, name: &str) -> Result<&Wallet> {
+        self.wallets.get(name).ok_or_else(|| Error::Custom(format!("Unknown wallet: {}", name)))
+    }
+
+    /// Fetch a mutable wallet by its name
+    pub fn wallet_mut(&mut self, name: &str) -> Result<&mut Wallet> {
+        self.wallets.get_mut(name).ok_or_else(|| Error::Custom(format!("Unknown wallet: {}", name)))
+    }
+
+    /// Fetch a cached proving key and its zkas binary by circuit namespace
+    pub fn proving_key(&self, namespace: &str) -> Result<&(ProvingKey, ZkBinary)> {
+        self.proving_keys
+            .get(namespace)
+            .ok_or_else(|| Error::Custom(format!("Unknown circuit: {}", namespace)))
+    }
+}
+
+/// A contract's calls and state, pluggable into a [`ScenarioHarness`].
+///
+/// Modules keep any per-wallet state they need themselves, and update it
+/// from the transactions that executed successfully.
+#[async_trait(?Send)]
+pub trait ContractModule {
+    /// Name the module is referred to by in scenarios
+    fn name(&self) -> &str;
+
+    /// Called once when the module is added to the harness, e.g. to
+    /// deploy the contract or build its proving keys.
+    async fn init(&mut self, _env: &mut ScenarioEnv) -> Result<()> {
+        Ok(())
+    }
+
+    /// Build the transaction for `call`, made by `wallet`
+    async fn build_tx(
+        &mut self,
+        env: &ScenarioEnv,
+        wallet: &str,
+        call: &str,
+        args: &toml::Table,
+    ) -> Result<Transaction>;
+
+    /// Update the state of `wallet` after it executed `tx` successfully
+    async fn apply_tx(
+        &mut self,
+        env: &mut ScenarioEnv,
+        wallet: &str,
+        call: &str,
+        tx: &Transaction,
+    ) -> Result<()>;
+
+    /// Check that `state` of `wallet` matches the expected `args`.
+    /// Returns an error describing the mismatch otherwise.
+    fn check(&self, env: &ScenarioEnv, wallet: &str, state: &str, args: &toml::Table)
+        -> Result<()>;
+}
+
+/// Contract test harness running [`Scenario`]s
+pub struct ScenarioHarness {
+    /// Wallets and keys available to the modules
+    pub env: ScenarioEnv,
+    /// Registered contract modules
+    modules: Vec<Box<dyn ContractModule>>,
+}
+
+impl ScenarioHarness {
+    /// Instantiate a new [`ScenarioHarness`] with the given wallet names.
+    /// Every wallet runs its own [`Validator`](darkfi::validator::Validator)
+    /// using the cached VKs. Transaction fees are not verified.
+    pub async fn new(wallets: &[String]) -> Result<Self> {
+        let genesis_block = genesis_block();
+
+        // Deterministic PRNG
+        let mut rng = Pcg32::new(42);
+
+        // Build or read cached ZK PKs and VKs
+        let (pks, vks) = vks::get_cached_pks_and_vks()?;
+        let proving_keys = read_proving_keys(pks)?;
+
+        let mut wallets_map = HashMap::new();
+        for name in wallets {
+            let wallet = Wallet::new(
+                Keypair::random(&mut rng),
+                Keypair::random(&mut rng),
+                Keypair::random(&mut rng),
+                genesis_block.clone(),
+                &vks,
+                false,
+            )
+            .await?;
+
+            if wallets_map.insert(name.clone(), wallet).is_some() {
+                return Err(Error::Custom(format!("Duplicate wallet: {}", name)))
+            }
+        }
+
+        let env = ScenarioEnv { wallets: wallets_map, proving_keys, genesis_block };
+        Ok(Self { env, modules: vec![] })
+    }
+
+    /// Register a contract module, and initialize it
+    pub async fn add_module(&mut self, mut module: Box<dyn ContractModule>) -> Result<()> {
+        if self.modules.iter().any(|m| m.name() == module.name()) {
+            return Err(Error::Custom(format!("Duplicate module: {}", module.name())))
+        }
+
+        module.init(&mut self.env).await?;
+        self.modules.push(module);
+        Ok(())
+    }
+
+    fn module_index(&self, name: &str) -> Result<usize> {
+        self.modules
+            .iter()
+            .position(|m| m.name() == name)
+            .ok_or_else(|| Error::Custom(format!("Unknown module: {}", name)))
+    }
+
+    /// Run all steps of a scenario. Returns an error describing the first
+    /// step that didn't behave as expected.
+    pub async fn run(&mut self, scenario: &Scenario) -> Result<()> {
+        // Execute transactions in the same wallet order every time
+        let mut names: Vec<String> = self.env.wallets.keys().cloned().collect();
+        names.sort();
+
+        for (index, step) in scenario.steps.iter().enumerate() {
+            info!(
+                target: "test_harness::scenario",
+                "[{}] Step {}: {}::{}", step.wallet, index, step.module, step.call,
+            );
+            let step_error = |msg: String| {
+                Error::Custom(format!("Step {} ({}::{}): {}", index, step.module, step.call, msg))
+            };
+
+            let module = self.module_index(&step.module)?;
+            self.env.wallet(&step.wallet)?;
+
+            let built = self.modules[module]
+                .build_tx(&self.env, &step.wallet, &step.call, &step.args)
+                .await;
+
+            let tx = match (built, &step.expect_error) {
+                (Ok(tx), _) => tx,
+                (Err(e), Some(expected)) if e.to_string().contains(expected) => continue,
+                (Err(e), _) => return Err(step_error(format!("Failed building call: {}", e))),
+            };
+
+            let callname = format!("{}::{}", step.module, step.call);
+            for name in &names {
+                let wallet = self.env.wallet_mut(name)?;
+                let result = wallet.add_transaction(&callname, tx.clone(), step.block_height).await;
+
+                match (result, &step.expect_error) {
+                    (Ok(()), None) => {
+                        self.modules[module].apply_tx(&mut self.env, name, &step.call, &tx).await?
+                    }
+                    (Ok(()), Some(expected)) => {
+                        return Err(step_error(format!(
+                            "Call succeeded for {}, but was expected to fail with \"{}\"",
+                            name, expected
+                        )))
+                    }
+                    (Err(e), None) => {
+                        return Err(step_error(format!("Call failed for {}: {}", name, e)))
+                    }
+                    (Err(e), Some(expected)) if !e.to_string().contains(expected) => {
+                        return Err(step_error(format!(
+                            "Call failed for {} with \"{}\", but was expected to fail with \"{}\"",
+                            name, e, expected
+                        )))
+                    }
+                    (Err(_), Some(_)) => {}
+                }
+            }
+
+            for expectation in &step.expect {
+                let module = self.module_index(&expectation.module)?;
+                self.env.wallet(&expectation.wallet)?;
+                self.modules[module]
+                    .check(&self.env, &expectation.wallet, &expectation.state, &expectation.args)
+                    .map_err(|e| step_error(e.to_string()))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Read an unsigned integer argument
+pub fn arg_u64(args: &toml::Table, key: &str) -> Result<u64> {
+    match args.get(key) {
+        Some(toml::Value::Integer(v)) => {
+            u64::try_from(*v).map_err(|_| Error::Custom(format!("Invalid argument: {}", key)))
+        }
+        Some(_) => Err(Error::Custom(format!("Invalid argument: {}", key))),
+        None => Err(Error::Custom(format!("Missing argument: {}", key))),
+    }
+}
+
+/// Read an array of unsigned integers argument
+pub fn arg_u64_array(args: &toml::Table, key: &str) -> Result<Vec<u64>> {
+    let Some(toml::Value::Array(values)) = args.get(key) else {
+        return Err(Error::Custom(format!("Missing argument: {}", key)))
+    };
+
+    let mut ret = Vec::with_capacity(values.len());
+    for value in values {
+        let Some(v) = value.as_integer().and_then(|v| u64::try_from(v).ok()) else {
+            return Err(Error::Custom(format!("Invalid argument: {}", key)))
+        };
+        ret.push(v);
+    }
+
+    Ok(ret)
+}
+
+/// Read a string argument
+pub fn arg_str<'a>(args: &'a toml::Table, key: &str) -> Result<&'a str> {
+    match args.get(key) {
+        Some(toml::Value::String(v)) => Ok(v),
+        Some(_) => Err(Error::Custom(format!("Invalid argument: {}", key))),
+        None => Err(Error::Custom(format!("Missing argument: {}", key))),
+    }
+}
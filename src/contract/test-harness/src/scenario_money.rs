@@ -0,0 +1,302 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2025 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use darkfi::{
+    tx::{ContractCallLeaf, Transaction, TransactionBuilder},
+    zk::halo2::Field,
+    Error, Result,
+};
+use darkfi_money_contract::{
+    client::{
+        genesis_mint_v1::GenesisMintCallBuilder, transfer_v1::make_transfer_call, MoneyNote,
+        OwnCoin,
+    },
+    model::{MoneyGenesisMintParamsV1, MoneyTransferParamsV1, Nullifier, Output, DARK_TOKEN_ID},
+    MoneyFunction, MONEY_CONTRACT_ZKAS_BURN_NS_V1, MONEY_CONTRACT_ZKAS_MINT_NS_V1,
+};
+use darkfi_sdk::{
+    crypto::{
+        contract_id::MONEY_CONTRACT_ID,
+        smt::{MemoryStorageFp, PoseidonFp, SmtMemoryFp, EMPTY_NODES_FP},
+        MerkleNode, MerkleTree, SecretKey,
+    },
+    pasta::pallas,
+    ContractCall,
+};
+use darkfi_serial::{deserialize, AsyncEncodable};
+use log::debug;
+
+use super::{
+    scenario::{arg_str, arg_u64, arg_u64_array},
+    ContractModule, ScenarioEnv,
+};
+
+/// `Money` contract state of a single scenario wallet
+struct MoneyWallet {
+    /// Wallet's instance of the Merkle tree for the `Money` contract
+    merkle_tree: MerkleTree,
+    /// Wallet's instance of the SMT tree for the `Money` contract
+    null_smt: SmtMemoryFp,
+    /// Wallet's set of unspent [`OwnCoin`]s
+    unspent_coins: Vec<OwnCoin>,
+}
+
+impl MoneyWallet {
+    fn new() -> Self {
+        // The Merkle tree for the `Money` contract is initialized with a "null"
+        // leaf at position 0.
+        let mut merkle_tree = MerkleTree::new(1);
+        merkle_tree.append(MerkleNode::from(pallas::Base::ZERO));
+        merkle_tree.mark().unwrap();
+
+        let hasher = PoseidonFp::new();
+        let store = MemoryStorageFp::new();
+        let null_smt = SmtMemoryFp::new(store, hasher, &EMPTY_NODES_FP);
+
+        Self { merkle_tree, null_smt, unspent_coins: vec![] }
+    }
+
+    /// Mark the coins spent by the given nullifiers
+    fn spend(&mut self, nullifiers: &[Nullifier]) {
+        let smt_leaves = nullifiers.iter().map(|n| (n.inner(), n.inner())).collect();
+        self.null_smt.insert_batch(smt_leaves).expect("smt.insert_batch()");
+
+        self.unspent_coins.retain(|coin| {
+            let spent = nullifiers.contains(&coin.nullifier());
+            if spent {
+                debug!(target: "test_harness::scenario", "Found spent OwnCoin({})", coin.coin);
+            }
+            !spent
+        });
+    }
+
+    /// Append the outputs to the Merkle tree, and keep the coins
+    /// the wallet can decrypt
+    fn receive(&mut self, secret: &SecretKey, outputs: &[Output]) {
+        for output in outputs {
+            self.merkle_tree.append(MerkleNode::from(output.coin.inner()));
+
+            // Attempt to decrypt the output note to see if this is a coin for the wallet.
+            let Ok(note) = output.note.decrypt::<MoneyNote>(secret) else { continue };
+
+            let owncoin = OwnCoin {
+                coin: output.coin,
+                note,
+                secret: *secret,
+                leaf_position: self.merkle_tree.mark().unwrap(),
+            };
+
+            debug!(target: "test_harness::scenario", "Found new OwnCoin({})", owncoin.coin);
+            self.unspent_coins.push(owncoin);
+        }
+    }
+}
+
+/// `Money` contract module for scenarios, dealing in the native token.
+///
+/// Calls:
+/// * `genesis_mint`, with `amounts`: values of the coins minted to the wallet
+/// * `transfer`, with `to`: recipient wallet, and `amount`: value sent
+///
+/// States:
+/// * `balance`, with `value`: sum of the wallet's unspent coins
+/// * `coins`, with `value`: number of the wallet's unspent coins
+#[derive(Default)]
+pub struct MoneyModule {
+    /// `Money` contract state of every wallet, by name
+    wallets: HashMap<String, MoneyWallet>,
+}
+
+impl MoneyModule {
+    /// Fetch the `Money` state of a wallet by its name
+    fn wallet(&self, name: &str) -> Result<&MoneyWallet> {
+        self.wallets.get(name).ok_or_else(|| Error::Custom(format!("Unknown wallet: {}", name)))
+    }
+
+    /// Fetch the mutable `Money` state of a wallet by its name
+    fn wallet_mut(&mut self, name: &str) -> Result<&mut MoneyWallet> {
+        self.wallets.get_mut(name).ok_or_else(|| Error::Custom(format!("Unknown wallet: {}", name)))
+    }
+
+    async fn genesis_mint(
+        &self,
+        env: &ScenarioEnv,
+        wallet: &str,
+        args: &toml::Table,
+    ) -> Result<Transaction> {
+        let wallet = env.wallet(wallet)?;
+        let (mint_pk, mint_zkbin) = env.proving_key(MONEY_CONTRACT_ZKAS_MINT_NS_V1)?;
+
+        let builder = GenesisMintCallBuilder {
+            signature_public: wallet.keypair.public,
+            amounts: arg_u64_array(args, "amounts")?,
+            recipient: None,
+            spend_hook: None,
+            user_data: None,
+            mint_zkbin: mint_zkbin.clone(),
+            mint_pk: mint_pk.clone(),
+        };
+
+        let debris = builder.build()?;
+
+        let mut data = vec![MoneyFunction::GenesisMintV1 as u8];
+        debris.params.encode_async(&mut data).await?;
+        let call = ContractCall { contract_id: *MONEY_CONTRACT_ID, data };
+        let mut tx_builder =
+            TransactionBuilder::new(ContractCallLeaf { call, proofs: debris.proofs }, vec![])?;
+        let mut tx = tx_builder.build()?;
+        let sigs = tx.create_sigs(&[wallet.keypair.secret])?;
+        tx.signatures = vec![sigs];
+
+        Ok(tx)
+    }
+
+    async fn transfer(
+        &self,
+        env: &ScenarioEnv,
+        wallet: &str,
+        args: &toml::Table,
+    ) -> Result<Transaction> {
+        let money = self.wallet(wallet)?;
+        let wallet = env.wallet(wallet)?;
+        let recipient = env.wallet(arg_str(args, "to")?)?.keypair.public;
+
+        let (mint_pk, mint_zkbin) = env.proving_key(MONEY_CONTRACT_ZKAS_MINT_NS_V1)?;
+        let (burn_pk, burn_zkbin) = env.proving_key(MONEY_CONTRACT_ZKAS_BURN_NS_V1)?;
+
+        let (params, secrets, _) = make_transfer_call(
+            wallet.keypair,
+            recipient,
+            arg_u64(args, "amount")?,
+            *DARK_TOKEN_ID,
+            money.unspent_coins.clone(),
+            money.merkle_tree.clone(),
+            None,
+            None,
+            mint_zkbin.clone(),
+            mint_pk.clone(),
+            burn_zkbin.clone(),
+            burn_pk.clone(),
+            false,
+        )?;
+
+        let mut data = vec![MoneyFunction::TransferV1 as u8];
+        params.encode_async(&mut data).await?;
+        let call = ContractCall { contract_id: *MONEY_CONTRACT_ID, data };
+        let mut tx_builder =
+            TransactionBuilder::new(ContractCallLeaf { call, proofs: secrets.proofs }, vec![])?;
+        let mut tx = tx_builder.build()?;
+        let sigs = tx.create_sigs(&secrets.signature_secrets)?;
+        tx.signatures = vec![sigs];
+
+        Ok(tx)
+    }
+}
+
+#[async_trait(?Send)]
+impl ContractModule for MoneyModule {
+    fn name(&self) -> &str {
+        "money"
+    }
+
+    async fn init(&mut self, env: &mut ScenarioEnv) -> Result<()> {
+        for name in env.wallets.keys() {
+            self.wallets.insert(name.clone(), MoneyWallet::new());
+        }
+
+        Ok(())
+    }
+
+    async fn build_tx(
+        &mut self,
+        env: &ScenarioEnv,
+        wallet: &str,
+        call: &str,
+        args: &toml::Table,
+    ) -> Result<Transaction> {
+        match call {
+            "genesis_mint" => self.genesis_mint(env, wallet, args).await,
+            "transfer" => self.transfer(env, wallet, args).await,
+            _ => Err(Error::Custom(format!("Unknown money call: {}", call))),
+        }
+    }
+
+    async fn apply_tx(
+        &mut self,
+        env: &mut ScenarioEnv,
+        wallet: &str,
+        _call: &str,
+        tx: &Transaction,
+    ) -> Result<()> {
+        let secret = env.wallet(wallet)?.keypair.secret;
+        let money = self.wallet_mut(wallet)?;
+
+        for call in &tx.calls {
+            let call = &call.data;
+            if call.contract_id != *MONEY_CONTRACT_ID || call.data.is_empty() {
+                continue
+            }
+
+            match MoneyFunction::try_from(call.data[0])? {
+                MoneyFunction::GenesisMintV1 => {
+                    let params: MoneyGenesisMintParamsV1 = deserialize(&call.data[1..])?;
+                    money.receive(&secret, &params.outputs);
+                }
+                MoneyFunction::TransferV1 => {
+                    let params: MoneyTransferParamsV1 = deserialize(&call.data[1..])?;
+                    let nullifiers: Vec<Nullifier> =
+                        params.inputs.iter().map(|input| input.nullifier).collect();
+                    money.spend(&nullifiers);
+                    money.receive(&secret, &params.outputs);
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check(
+        &self,
+        _env: &ScenarioEnv,
+        wallet: &str,
+        state: &str,
+        args: &toml::Table,
+    ) -> Result<()> {
+        let coins = &self.wallet(wallet)?.unspent_coins;
+        let expected = arg_u64(args, "value")?;
+
+        let actual = match state {
+            "balance" => coins.iter().map(|coin| coin.note.value).sum(),
+            "coins" => coins.len() as u64,
+            _ => return Err(Error::Custom(format!("Unknown money state: {}", state))),
+        };
+
+        if actual != expected {
+            return Err(Error::Custom(format!(
+                "Expected {} {} of {}, found {}",
+                state, expected, wallet, actual
+            )))
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,588 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2025 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Kademlia-style distributed hash table on top of [`P2p`].
+//!
+//! Every node has a random 256-bit ID. Keys, like geode file and chunk
+//! hashes, live in the same keyspace, and the distance between two IDs
+//! is their XOR. Nodes are kept in k-buckets by [`RoutingTable`].
+//!
+//! Lookups are iterative: we ask the `alpha` closest nodes we know of
+//! for the nodes they know closest to the key (`FIND_NODE`), or for the
+//! providers of the key (`FIND_VALUE`), and repeat with the closer nodes
+//! they return until the `k` closest nodes have all been queried.
+//!
+//! Nodes holding some data announce themselves as its providers to the
+//! `k` nodes closest to its key. Provider records expire after
+//! [`DhtSettings::provider_ttl`], so [`Dht::refresh`] announces them
+//! again periodically.
+//!
+//! Applications create a [`Dht`] for their [`P2p`] instance, register
+//! [`ProtocolDht`] with the P2P protocol registry and spawn
+//! [`Dht::refresh_task`].
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::{Duration, UNIX_EPOCH},
+};
+
+use futures::stream::{FuturesUnordered, StreamExt};
+use log::{debug, info, warn};
+use rand::{rngs::OsRng, RngCore};
+use smol::lock::{Mutex, RwLock};
+use url::Url;
+
+use crate::{
+    net::{
+        connector::Connector,
+        session::{OutboundSessionPtr, Session},
+        ChannelPtr, Message, P2pPtr,
+    },
+    system::{sleep, timeout::timeout},
+    Error, Result,
+};
+
+/// Kademlia routing table
+pub mod routing;
+pub use routing::{BucketUpdate, DhtNode, RoutingTable};
+
+/// Provider records store
+pub mod providers;
+pub use providers::ProviderStore;
+
+/// P2P protocol and messages
+pub mod proto;
+pub use proto::ProtocolDht;
+use proto::{
+    DhtAnnounce, DhtFindNodeReply, DhtFindNodeRequest, DhtFindProvidersReply,
+    DhtFindProvidersRequest, DhtPing, DhtPong,
+};
+
+#[cfg(all(test, feature = "p2p-memory"))]
+mod tests;
+
+/// Atomic pointer to a [`Dht`] instance
+pub type DhtPtr = Arc<Dht>;
+
+/// DHT configuration
+#[derive(Debug, Clone)]
+pub struct DhtSettings {
+    /// Bucket size, and amount of nodes returned by lookups
+    pub k: usize,
+    /// Amount of concurrent requests during lookups
+    pub alpha: usize,
+    /// Seconds to wait for a reply to a request
+    pub timeout: u64,
+    /// Seconds a provider record stays valid
+    pub provider_ttl: u64,
+    /// Maximum amount of providers stored per key
+    pub max_providers: usize,
+    /// Seconds between routing table refreshes and provider announcements
+    pub refresh_interval: u64,
+    /// Consecutive failed requests after which a node is evicted
+    pub max_failures: usize,
+}
+
+impl Default for DhtSettings {
+    fn default() -> Self {
+        Self {
+            k: 20,
+            alpha: 3,
+            timeout: 10,
+            provider_ttl: 86400,
+            max_providers: 64,
+            refresh_interval: 3600,
+            max_failures: 3,
+        }
+    }
+}
+
+/// Kademlia DHT instance
+pub struct Dht {
+    /// Our node ID
+    pub node_id: blake3::Hash,
+    /// DHT configuration
+    pub settings: DhtSettings,
+    /// Routing table of known nodes
+    table: RwLock<RoutingTable>,
+    /// Provider records we store for other nodes
+    providers: RwLock<ProviderStore>,
+    /// Keys we provide, announced again on every refresh
+    provided: RwLock<HashSet<blake3::Hash>>,
+    /// Channels we opened to nodes, and the amount of requests using them
+    owned_channels: Arc<Mutex<HashMap<Url, (ChannelPtr, usize)>>>,
+    /// Per-address locks, so concurrent requests to a node we're not
+    /// connected to share a single new channel
+    connect_locks: Mutex<HashMap<Url, Arc<Mutex<()>>>>,
+    /// Pointer to the P2P network instance
+    p2p: P2pPtr,
+}
+
+/// Current UNIX timestamp in seconds
+fn now() -> u64 {
+    UNIX_EPOCH.elapsed().unwrap().as_secs()
+}
+
+impl Dht {
+    /// Create a new DHT instance with a random node ID.
+    pub fn new(p2p: P2pPtr, settings: DhtSettings) -> DhtPtr {
+        let mut seed = [0u8; 32];
+        OsRng.fill_bytes(&mut seed);
+        let node_id = blake3::hash(&seed);
+        info!(target: "dht::new()", "[DHT] Node ID: {}", node_id);
+
+        Arc::new(Self {
+            node_id,
+            table: RwLock::new(RoutingTable::new(node_id, settings.k, settings.max_failures)),
+            providers: RwLock::new(ProviderStore::new(
+                settings.provider_ttl,
+                settings.max_providers,
+            )),
+            provided: RwLock::new(HashSet::new()),
+            owned_channels: Arc::new(Mutex::new(HashMap::new())),
+            connect_locks: Mutex::new(HashMap::new()),
+            settings,
+            p2p,
+        })
+    }
+
+    /// Our own node, reachable at our P2P external addresses.
+    pub async fn node(&self) -> DhtNode {
+        DhtNode { id: self.node_id, addresses: self.p2p.hosts().external_addrs().await }
+    }
+
+    /// Return the `k` known nodes closest to `key`.
+    pub async fn closest(&self, key: &blake3::Hash) -> Vec<DhtNode> {
+        self.table.read().await.closest(key, self.settings.k)
+    }
+
+    /// Return the amount of nodes in the routing table.
+    pub async fn table_size(&self) -> usize {
+        self.table.read().await.len()
+    }
+
+    /// Record that we have seen `node`. If its bucket is full, the least
+    /// recently seen node is pinged in the background, and replaced by
+    /// `node` if it does not reply.
+    pub async fn add_node(self: &Arc<Self>, node: DhtNode) {
+        let BucketUpdate::Full(oldest) = self.table.write().await.update(node.clone()) else {
+            return
+        };
+
+        let self_ = self.clone();
+        self.p2p
+            .executor()
+            .spawn(async move {
+                if self_.ping(&oldest).await {
+                    self_.table.write().await.update(oldest);
+                    return
+                }
+
+                debug!(
+                    target: "dht::add_node()",
+                    "[DHT] Evicting unresponsive node {} for {}", oldest.id, node.id,
+                );
+                let mut table = self_.table.write().await;
+                table.remove(&oldest.id);
+                table.update(node);
+            })
+            .detach();
+    }
+
+    /// Record `node` as a provider of `key`.
+    pub async fn add_provider(&self, key: blake3::Hash, node: DhtNode) {
+        if node.addresses.is_empty() {
+            return
+        }
+
+        if !self.providers.write().await.insert(key, node, now()) {
+            debug!(target: "dht::add_provider()", "[DHT] Too many providers for {}", key);
+        }
+    }
+
+    /// Return the providers of `key` we store locally.
+    pub async fn local_providers(&self, key: &blake3::Hash) -> Vec<DhtNode> {
+        self.providers.read().await.get(key, now())
+    }
+
+    /// Iteratively look up the `k` nodes closest to `key` in the network.
+    pub async fn find_node(self: &Arc<Self>, key: &blake3::Hash) -> Vec<DhtNode> {
+        self.lookup(key, false).await.0
+    }
+
+    /// Look up the providers of `key`, both locally and in the network.
+    pub async fn find_providers(self: &Arc<Self>, key: &blake3::Hash) -> Vec<DhtNode> {
+        let mut providers = self.local_providers(key).await;
+
+        for provider in self.lookup(key, true).await.1 {
+            if !providers.iter().any(|p| p.id == provider.id) {
+                providers.push(provider);
+            }
+        }
+
+        providers
+    }
+
+    /// Announce ourselves as a provider of `key` to the `k` nodes closest
+    /// to it. The announcement is repeated on every [`Dht::refresh`].
+    pub async fn announce(self: &Arc<Self>, key: &blake3::Hash) -> Result<()> {
+        self.provided.write().await.insert(*key);
+
+        let node = self.node().await;
+        if node.addresses.is_empty() {
+            warn!(
+                target: "dht::announce()",
+                "[DHT] No external addresses configured, peers won't be able to reach us",
+            );
+            return Ok(())
+        }
+
+        let announce = DhtAnnounce { node, key: *key };
+        let mut futures = FuturesUnordered::new();
+        for peer in self.find_node(key).await {
+            let announce = &announce;
+            futures.push(async move { (peer.id, self.send(&peer, announce).await) });
+        }
+
+        while let Some((id, res)) = futures.next().await {
+            if let Err(e) = res {
+                debug!(
+                    target: "dht::announce()",
+                    "[DHT] Failed announcing {} to {}: {}", key, id, e,
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Stop announcing ourselves as a provider of `key`.
+    /// Existing records expire on their own.
+    pub async fn unannounce(&self, key: &blake3::Hash) {
+        self.provided.write().await.remove(key);
+    }
+
+    /// Populate the routing table by looking up our own ID.
+    pub async fn bootstrap(self: &Arc<Self>) {
+        let nodes = self.find_node(&self.node_id).await;
+        info!(
+            target: "dht::bootstrap()",
+            "[DHT] Bootstrapped with {} close nodes, {} nodes known",
+            nodes.len(), self.table_size().await,
+        );
+    }
+
+    /// Prune expired provider records, refresh the routing table and
+    /// announce the keys we provide again.
+    pub async fn refresh(self: &Arc<Self>) {
+        let pruned = self.providers.write().await.prune(now());
+        debug!(target: "dht::refresh()", "[DHT] Pruned {} expired provider records", pruned);
+
+        self.bootstrap().await;
+
+        let provided: Vec<blake3::Hash> = self.provided.read().await.iter().copied().collect();
+        for key in provided {
+            if let Err(e) = self.announce(&key).await {
+                warn!(target: "dht::refresh()", "[DHT] Failed announcing {}: {}", key, e);
+            }
+        }
+    }
+
+    /// Background task running [`Dht::refresh`] every
+    /// [`DhtSettings::refresh_interval`] seconds.
+    pub async fn refresh_task(self: Arc<Self>) -> Result<()> {
+        loop {
+            sleep(self.settings.refresh_interval).await;
+            self.refresh().await;
+        }
+    }
+
+    /// Iterative lookup of `key`. Returns the `k` closest nodes that
+    /// replied, and the providers found if `find_providers` is set,
+    /// in which case the lookup stops as soon as some are found.
+    async fn lookup(
+        self: &Arc<Self>,
+        key: &blake3::Hash,
+        find_providers: bool,
+    ) -> (Vec<DhtNode>, Vec<DhtNode>) {
+        let k = self.settings.k;
+        let mut shortlist = self.closest(key).await;
+        let mut queried = HashSet::new();
+        let mut providers = HashMap::new();
+
+        loop {
+            let pending: Vec<DhtNode> = shortlist
+                .iter()
+                .filter(|n| !queried.contains(&n.id))
+                .take(self.settings.alpha)
+                .cloned()
+                .collect();
+
+            if pending.is_empty() {
+                break
+            }
+
+            let mut futures = FuturesUnordered::new();
+            for node in pending {
+                queried.insert(node.id);
+                futures.push(async move {
+                    let res = self.query(&node, key, find_providers).await;
+                    (node, res)
+                });
+            }
+
+            while let Some((node, res)) = futures.next().await {
+                let (nodes, found) = match res {
+                    Ok(v) => v,
+                    Err(e) => {
+                        debug!(
+                            target: "dht::lookup()",
+                            "[DHT] Node {} failed to reply: {}", node.id, e,
+                        );
+                        shortlist.retain(|n| n.id != node.id);
+                        self.table.write().await.fail(&node.id);
+                        continue
+                    }
+                };
+
+                self.table.write().await.update(node);
+
+                for node in nodes {
+                    if node.id == self.node_id || node.addresses.is_empty() {
+                        continue
+                    }
+
+                    if !shortlist.iter().any(|n| n.id == node.id) {
+                        shortlist.push(node);
+                    }
+                }
+
+                for provider in found {
+                    if !provider.addresses.is_empty() {
+                        providers.insert(provider.id, provider);
+                    }
+                }
+            }
+
+            shortlist.sort_by(|a, b| routing::cmp_distance(key, &a.id, &b.id));
+            shortlist.truncate(k);
+
+            if find_providers && !providers.is_empty() {
+                break
+            }
+        }
+
+        // Only return nodes that actually replied
+        shortlist.retain(|n| queried.contains(&n.id));
+        (shortlist, providers.into_values().collect())
+    }
+
+    /// Send a `FIND_NODE` or `FIND_VALUE` request to `node`, returning
+    /// the closer nodes and the providers it replied with.
+    async fn query(
+        &self,
+        node: &DhtNode,
+        key: &blake3::Hash,
+        find_providers: bool,
+    ) -> Result<(Vec<DhtNode>, Vec<DhtNode>)> {
+        let sender = self.node().await;
+
+        if find_providers {
+            let req = DhtFindProvidersRequest { node: sender, key: *key };
+            let rep: Arc<DhtFindProvidersReply> =
+                self.request(node, &req, |rep: &DhtFindProvidersReply| &rep.key == key).await?;
+            return Ok((rep.nodes.clone(), rep.providers.clone()))
+        }
+
+        let req = DhtFindNodeRequest { node: sender, key: *key };
+        let rep: Arc<DhtFindNodeReply> =
+            self.request(node, &req, |rep: &DhtFindNodeReply| &rep.key == key).await?;
+        Ok((rep.nodes.clone(), vec![]))
+    }
+
+    /// Check if `node` is alive.
+    async fn ping(&self, node: &DhtNode) -> bool {
+        let req = DhtPing { node: self.node().await };
+        self.request(node, &req, |rep: &DhtPong| rep.node.id == node.id).await.is_ok()
+    }
+
+    /// Send `msg` to `node`, without waiting for a reply.
+    async fn send<M: Message>(&self, node: &DhtNode, msg: &M) -> Result<()> {
+        let channel = self.get_channel(node).await?;
+        let res = channel.send(msg).await;
+        self.release_channel(&channel).await;
+        res
+    }
+
+    /// Send `req` to `node` and wait for the first reply accepted by `check`.
+    async fn request<Req: Message, Rep: Message>(
+        &self,
+        node: &DhtNode,
+        req: &Req,
+        check: impl Fn(&Rep) -> bool,
+    ) -> Result<Arc<Rep>> {
+        let channel = self.get_channel(node).await?;
+
+        let res = async {
+            let sub = channel.subscribe_msg::<Rep>().await?;
+            let res = async {
+                channel.send(req).await?;
+                let dur = Duration::from_secs(self.settings.timeout);
+                let Ok(rep) = timeout(dur, async {
+                    loop {
+                        let rep = sub.receive().await?;
+                        if check(&rep) {
+                            return Ok(rep)
+                        }
+                    }
+                })
+                .await
+                else {
+                    return Err(Error::ConnectTimeout)
+                };
+                rep
+            }
+            .await;
+            sub.unsubscribe().await;
+            res
+        }
+        .await;
+
+        self.release_channel(&channel).await;
+        res
+    }
+
+    /// Find a channel to `node`. Existing channels are reused, otherwise
+    /// we connect to the node and register the channel with the outbound
+    /// session, which attaches [`ProtocolDht`] to it. Every channel must be
+    /// handed back with [`Dht::release_channel`] once done with it.
+    pub async fn get_channel(&self, node: &DhtNode) -> Result<ChannelPtr> {
+        for addr in &node.addresses {
+            if let Some(channel) = self.find_channel(addr).await {
+                return Ok(channel)
+            }
+        }
+
+        let session = self.p2p.session_outbound();
+        for addr in &node.addresses {
+            let lock = self.connect_locks.lock().await.entry(addr.clone()).or_default().clone();
+            let guard = lock.lock().await;
+
+            // Somebody else might have connected while we were waiting
+            let res = match self.find_channel(addr).await {
+                Some(channel) => Ok(channel),
+                None => self.connect(&session, addr).await,
+            };
+
+            drop(guard);
+            let mut locks = self.connect_locks.lock().await;
+            if Arc::strong_count(&lock) == 2 {
+                locks.remove(addr);
+            }
+            drop(locks);
+
+            match res {
+                Ok(channel) => return Ok(channel),
+                Err(e) => {
+                    debug!(
+                        target: "dht::get_channel()",
+                        "[DHT] Failed connecting to {}: {}", addr, e,
+                    );
+                }
+            }
+        }
+
+        Err(Error::DhtNodeUnreachable)
+    }
+
+    /// Connect to `addr` and register the channel with the outbound session.
+    async fn connect(&self, session: &OutboundSessionPtr, addr: &Url) -> Result<ChannelPtr> {
+        let session_ = Arc::downgrade(session);
+        let connector = Connector::new(self.p2p.settings(), session_);
+        let (_, channel) = connector.connect(addr).await?;
+
+        if let Err(e) = session.register_channel(channel.clone(), self.p2p.executor()).await {
+            channel.stop().await;
+            return Err(e)
+        }
+
+        let mut owned = self.owned_channels.lock().await;
+        owned.insert(addr.clone(), (channel.clone(), 1));
+        Ok(channel)
+    }
+
+    /// Hand back a channel returned by [`Dht::get_channel`]. Channels we
+    /// opened ourselves are stopped once no request used them for
+    /// [`DhtSettings::timeout`] seconds, which leaves the peer time to
+    /// handle the messages we sent.
+    pub async fn release_channel(&self, channel: &ChannelPtr) {
+        let mut owned = self.owned_channels.lock().await;
+        let Some((owned_channel, users)) = owned.get_mut(channel.address()) else { return };
+        if owned_channel.info.id != channel.info.id {
+            return
+        }
+
+        *users -= 1;
+        if *users > 0 {
+            return
+        }
+        drop(owned);
+
+        let owned_channels = self.owned_channels.clone();
+        let channel = channel.clone();
+        let idle = self.settings.timeout;
+        self.p2p
+            .executor()
+            .spawn(async move {
+                sleep(idle).await;
+
+                // The channel might have been picked up again meanwhile
+                let mut owned = owned_channels.lock().await;
+                match owned.get(channel.address()) {
+                    Some((c, 0)) if c.info.id == channel.info.id => {
+                        owned.remove(channel.address());
+                    }
+                    _ => return,
+                }
+                drop(owned);
+
+                channel.stop().await;
+            })
+            .detach();
+    }
+
+    /// Find a live channel to `addr`, counting ourselves as a user of it
+    /// if it is one we opened.
+    async fn find_channel(&self, addr: &Url) -> Option<ChannelPtr> {
+        let mut owned = self.owned_channels.lock().await;
+        if let Some((channel, users)) = owned.get_mut(addr) {
+            if !channel.is_stopped() {
+                *users += 1;
+                return Some(channel.clone())
+            }
+        }
+        drop(owned);
+
+        self.p2p
+            .hosts()
+            .channels()
+            .into_iter()
+            .find(|channel| !channel.is_stopped() && channel.address() == addr)
+    }
+}
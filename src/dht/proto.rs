@@ -0,0 +1,247 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2025 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::sync::Arc;
+
+use darkfi_serial::{async_trait, SerialDecodable, SerialEncodable};
+use log::{debug, warn};
+use smol::Executor;
+
+use super::{DhtNode, DhtPtr};
+use crate::{
+    impl_p2p_message,
+    net::{
+        ChannelPtr, Message, MessageSubscription, ProtocolBase, ProtocolBasePtr,
+        ProtocolJobsManager, ProtocolJobsManagerPtr,
+    },
+    Result,
+};
+
+/// Request checking that a node is alive, carrying the sender node
+#[derive(Debug, Clone, SerialEncodable, SerialDecodable)]
+pub struct DhtPing {
+    pub node: DhtNode,
+}
+impl_p2p_message!(DhtPing, "DhtPing");
+
+/// Reply to [`DhtPing`], carrying the replying node
+#[derive(Debug, Clone, SerialEncodable, SerialDecodable)]
+pub struct DhtPong {
+    pub node: DhtNode,
+}
+impl_p2p_message!(DhtPong, "DhtPong");
+
+/// `FIND_NODE` request for the nodes closest to `key`
+#[derive(Debug, Clone, SerialEncodable, SerialDecodable)]
+pub struct DhtFindNodeRequest {
+    pub node: DhtNode,
+    pub key: blake3::Hash,
+}
+impl_p2p_message!(DhtFindNodeRequest, "DhtFindNodeRequest");
+
+/// Reply to [`DhtFindNodeRequest`] with the closest nodes we know of
+#[derive(Debug, Clone, SerialEncodable, SerialDecodable)]
+pub struct DhtFindNodeReply {
+    pub key: blake3::Hash,
+    pub nodes: Vec<DhtNode>,
+}
+impl_p2p_message!(DhtFindNodeReply, "DhtFindNodeReply");
+
+/// `FIND_VALUE` request for the providers of `key`
+#[derive(Debug, Clone, SerialEncodable, SerialDecodable)]
+pub struct DhtFindProvidersRequest {
+    pub node: DhtNode,
+    pub key: blake3::Hash,
+}
+impl_p2p_message!(DhtFindProvidersRequest, "DhtFindProvidersRequest");
+
+/// Reply to [`DhtFindProvidersRequest`] with the providers we know of,
+/// and the closest nodes to continue the lookup with.
+#[derive(Debug, Clone, SerialEncodable, SerialDecodable)]
+pub struct DhtFindProvidersReply {
+    pub key: blake3::Hash,
+    pub providers: Vec<DhtNode>,
+    pub nodes: Vec<DhtNode>,
+}
+impl_p2p_message!(DhtFindProvidersReply, "DhtFindProvidersReply");
+
+/// Announcement that `node` provides `key`
+#[derive(Debug, Clone, SerialEncodable, SerialDecodable)]
+pub struct DhtAnnounce {
+    pub node: DhtNode,
+    pub key: blake3::Hash,
+}
+impl_p2p_message!(DhtAnnounce, "DhtAnnounce");
+
+/// P2P protocol serving DHT requests on a channel
+pub struct ProtocolDht {
+    channel: ChannelPtr,
+    dht: DhtPtr,
+    ping_sub: MessageSubscription<DhtPing>,
+    pong_sub: MessageSubscription<DhtPong>,
+    find_node_sub: MessageSubscription<DhtFindNodeRequest>,
+    find_providers_sub: MessageSubscription<DhtFindProvidersRequest>,
+    announce_sub: MessageSubscription<DhtAnnounce>,
+    jobsman: ProtocolJobsManagerPtr,
+}
+
+#[async_trait]
+impl ProtocolBase for ProtocolDht {
+    async fn start(self: Arc<Self>, ex: Arc<Executor<'_>>) -> Result<()> {
+        debug!(target: "dht::proto::start()", "START => address={}", self.channel.address());
+        self.jobsman.clone().start(ex.clone());
+        self.jobsman.clone().spawn(self.clone().handle_ping(), ex.clone()).await;
+        self.jobsman.clone().spawn(self.clone().handle_pong(), ex.clone()).await;
+        self.jobsman.clone().spawn(self.clone().handle_find_node(), ex.clone()).await;
+        self.jobsman.clone().spawn(self.clone().handle_find_providers(), ex.clone()).await;
+        self.jobsman.clone().spawn(self.clone().handle_announce(), ex.clone()).await;
+
+        // Introduce ourselves, so the peer can add us to its routing table
+        // and reply with its own node, which we add to ours.
+        let ping = DhtPing { node: self.dht.node().await };
+        self.channel.send(&ping).await?;
+
+        debug!(target: "dht::proto::start()", "END => address={}", self.channel.address());
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "ProtocolDht"
+    }
+}
+
+impl ProtocolDht {
+    pub async fn init(dht: DhtPtr, channel: ChannelPtr) -> Result<ProtocolBasePtr> {
+        let msg_subsystem = channel.message_subsystem();
+        msg_subsystem.add_dispatch::<DhtPing>().await;
+        msg_subsystem.add_dispatch::<DhtPong>().await;
+        msg_subsystem.add_dispatch::<DhtFindNodeRequest>().await;
+        msg_subsystem.add_dispatch::<DhtFindNodeReply>().await;
+        msg_subsystem.add_dispatch::<DhtFindProvidersRequest>().await;
+        msg_subsystem.add_dispatch::<DhtFindProvidersReply>().await;
+        msg_subsystem.add_dispatch::<DhtAnnounce>().await;
+
+        let ping_sub = channel.subscribe_msg::<DhtPing>().await?;
+        let pong_sub = channel.subscribe_msg::<DhtPong>().await?;
+        let find_node_sub = channel.subscribe_msg::<DhtFindNodeRequest>().await?;
+        let find_providers_sub = channel.subscribe_msg::<DhtFindProvidersRequest>().await?;
+        let announce_sub = channel.subscribe_msg::<DhtAnnounce>().await?;
+
+        Ok(Arc::new(Self {
+            channel: channel.clone(),
+            dht,
+            ping_sub,
+            pong_sub,
+            find_node_sub,
+            find_providers_sub,
+            announce_sub,
+            jobsman: ProtocolJobsManager::new("ProtocolDht", channel),
+        }))
+    }
+
+    /// Protocol function handling `DhtPing`.
+    /// Replies with our node and records the sender.
+    async fn handle_ping(self: Arc<Self>) -> Result<()> {
+        loop {
+            let Ok(ping) = self.ping_sub.receive().await else { continue };
+
+            let pong = DhtPong { node: self.dht.node().await };
+            if let Err(e) = self.channel.send(&pong).await {
+                warn!(
+                    target: "dht::proto::handle_ping()",
+                    "Failed sending DhtPong to {}: {}", self.channel.address(), e,
+                );
+            }
+
+            self.dht.add_node(ping.node.clone()).await;
+        }
+    }
+
+    /// Protocol function handling `DhtPong`.
+    /// Records the replying node.
+    async fn handle_pong(self: Arc<Self>) -> Result<()> {
+        loop {
+            let Ok(pong) = self.pong_sub.receive().await else { continue };
+            self.dht.add_node(pong.node.clone()).await;
+        }
+    }
+
+    /// Protocol function handling `DhtFindNodeRequest`.
+    /// Replies with the closest nodes to the requested key we know of.
+    async fn handle_find_node(self: Arc<Self>) -> Result<()> {
+        loop {
+            let Ok(req) = self.find_node_sub.receive().await else { continue };
+            debug!(
+                target: "dht::proto::handle_find_node()",
+                "Got FIND_NODE({}) from {}", req.key, self.channel.address(),
+            );
+
+            let reply = DhtFindNodeReply { key: req.key, nodes: self.dht.closest(&req.key).await };
+            if let Err(e) = self.channel.send(&reply).await {
+                warn!(
+                    target: "dht::proto::handle_find_node()",
+                    "Failed sending DhtFindNodeReply to {}: {}", self.channel.address(), e,
+                );
+            }
+
+            self.dht.add_node(req.node.clone()).await;
+        }
+    }
+
+    /// Protocol function handling `DhtFindProvidersRequest`.
+    /// Replies with the providers of the requested key we know of,
+    /// along with the closest nodes to it.
+    async fn handle_find_providers(self: Arc<Self>) -> Result<()> {
+        loop {
+            let Ok(req) = self.find_providers_sub.receive().await else { continue };
+            debug!(
+                target: "dht::proto::handle_find_providers()",
+                "Got FIND_VALUE({}) from {}", req.key, self.channel.address(),
+            );
+
+            let reply = DhtFindProvidersReply {
+                key: req.key,
+                providers: self.dht.local_providers(&req.key).await,
+                nodes: self.dht.closest(&req.key).await,
+            };
+            if let Err(e) = self.channel.send(&reply).await {
+                warn!(
+                    target: "dht::proto::handle_find_providers()",
+                    "Failed sending DhtFindProvidersReply to {}: {}", self.channel.address(), e,
+                );
+            }
+
+            self.dht.add_node(req.node.clone()).await;
+        }
+    }
+
+    /// Protocol function handling `DhtAnnounce`.
+    /// Stores the provider record for the announced key.
+    async fn handle_announce(self: Arc<Self>) -> Result<()> {
+        loop {
+            let Ok(announce) = self.announce_sub.receive().await else { continue };
+            debug!(
+                target: "dht::proto::handle_announce()",
+                "Got announce of {} by {}", announce.key, announce.node.id,
+            );
+
+            self.dht.add_provider(announce.key, announce.node.clone()).await;
+            self.dht.add_node(announce.node.clone()).await;
+        }
+    }
+}
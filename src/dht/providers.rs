@@ -0,0 +1,130 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2025 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Provider records.
+//!
+//! A provider record maps a key, e.g. a file or chunk hash, to a node
+//! holding the data for it. Records expire after a TTL, so providers
+//! have to announce themselves again periodically.
+
+use std::collections::HashMap;
+
+use super::DhtNode;
+
+/// A node providing a key, along with the UNIX timestamp it was last announced at
+#[derive(Debug, Clone)]
+struct ProviderRecord {
+    node: DhtNode,
+    timestamp: u64,
+}
+
+/// Store of provider records with expiry
+#[derive(Debug, Clone)]
+pub struct ProviderStore {
+    /// Seconds a record stays valid after being announced
+    ttl: u64,
+    /// Maximum amount of providers kept per key
+    max_providers: usize,
+    /// Records grouped by key, then by provider node ID
+    records: HashMap<blake3::Hash, HashMap<blake3::Hash, ProviderRecord>>,
+}
+
+impl ProviderStore {
+    /// Create a new empty provider store.
+    pub fn new(ttl: u64, max_providers: usize) -> Self {
+        Self { ttl, max_providers, records: HashMap::new() }
+    }
+
+    /// Record `node` as a provider of `key` at UNIX timestamp `now`.
+    /// Returns `false` if the key already has too many providers.
+    pub fn insert(&mut self, key: blake3::Hash, node: DhtNode, now: u64) -> bool {
+        let providers = self.records.entry(key).or_default();
+
+        if !providers.contains_key(&node.id) && providers.len() >= self.max_providers {
+            // Make room if any record has expired
+            providers.retain(|_, r| r.timestamp + self.ttl > now);
+            if providers.len() >= self.max_providers {
+                return false
+            }
+        }
+
+        providers.insert(node.id, ProviderRecord { node, timestamp: now });
+        true
+    }
+
+    /// Return the non-expired providers of `key` at UNIX timestamp `now`.
+    pub fn get(&self, key: &blake3::Hash, now: u64) -> Vec<DhtNode> {
+        let Some(providers) = self.records.get(key) else { return vec![] };
+        providers
+            .values()
+            .filter(|r| r.timestamp + self.ttl > now)
+            .map(|r| r.node.clone())
+            .collect()
+    }
+
+    /// Remove the records that expired at UNIX timestamp `now`.
+    /// Returns the amount of removed records.
+    pub fn prune(&mut self, now: u64) -> usize {
+        let mut removed = 0;
+        for providers in self.records.values_mut() {
+            let len = providers.len();
+            providers.retain(|_, r| r.timestamp + self.ttl > now);
+            removed += len - providers.len();
+        }
+        self.records.retain(|_, providers| !providers.is_empty());
+        removed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use url::Url;
+
+    fn node(i: u8) -> DhtNode {
+        DhtNode {
+            id: blake3::hash(&[i]),
+            addresses: vec![Url::parse(&format!("tcp://127.0.0.1:{}", 13000 + i as u16)).unwrap()],
+        }
+    }
+
+    #[test]
+    fn provider_store_expiry() {
+        let key = blake3::hash(b"file");
+        let mut store = ProviderStore::new(10, 2);
+
+        assert!(store.insert(key, node(0), 100));
+        assert!(store.insert(key, node(1), 105));
+        assert_eq!(store.get(&key, 105).len(), 2);
+
+        // The key is full until a record expires
+        assert!(!store.insert(key, node(2), 106));
+        assert!(store.insert(key, node(2), 110));
+        let providers = store.get(&key, 110);
+        assert_eq!(providers.len(), 2);
+        assert!(!providers.contains(&node(0)));
+
+        // Announcing again refreshes a record
+        assert!(store.insert(key, node(1), 114));
+        assert_eq!(store.get(&key, 119).len(), 2);
+        assert_eq!(store.prune(120), 1);
+        assert_eq!(store.get(&key, 120), vec![node(1)]);
+        assert_eq!(store.prune(124), 1);
+        assert!(store.get(&key, 124).is_empty());
+    }
+}
@@ -0,0 +1,315 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2025 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Kademlia routing table.
+//!
+//! Nodes are sorted into 256 k-buckets by the XOR distance between their
+//! ID and ours. Bucket `i` holds nodes whose distance has its highest set
+//! bit at position `i`. Each bucket keeps at most `k` nodes, ordered from
+//! least to most recently seen. Nodes failing to reply are only evicted
+//! after several consecutive failures, or earlier when a new node needs
+//! their slot.
+
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, VecDeque},
+};
+
+use darkfi_serial::{async_trait, SerialDecodable, SerialEncodable};
+use url::Url;
+
+/// Number of bits in a node ID, and therefore the number of k-buckets
+pub const ID_BITS: usize = 256;
+
+/// A node participating in the DHT
+#[derive(Debug, Clone, PartialEq, Eq, SerialEncodable, SerialDecodable)]
+pub struct DhtNode {
+    /// Node ID in the DHT keyspace
+    pub id: blake3::Hash,
+    /// Addresses the node can be reached at
+    pub addresses: Vec<Url>,
+}
+
+/// Compute the XOR distance between two IDs.
+pub fn distance(a: &blake3::Hash, b: &blake3::Hash) -> [u8; 32] {
+    let mut ret = [0u8; 32];
+    for (i, (x, y)) in a.as_bytes().iter().zip(b.as_bytes()).enumerate() {
+        ret[i] = x ^ y;
+    }
+    ret
+}
+
+/// Compare the distances of `a` and `b` to `key`.
+pub fn cmp_distance(key: &blake3::Hash, a: &blake3::Hash, b: &blake3::Hash) -> Ordering {
+    distance(key, a).cmp(&distance(key, b))
+}
+
+/// Outcome of [`RoutingTable::update`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BucketUpdate {
+    /// The node was added to its bucket
+    Inserted,
+    /// The node was already known and is now the most recently seen
+    Updated,
+    /// The bucket is full. Contains its least recently seen node, which
+    /// should be pinged and evicted if it does not respond.
+    Full(DhtNode),
+    /// The node is ourselves or has no addresses
+    Ignored,
+}
+
+/// Kademlia routing table made of k-buckets
+#[derive(Debug, Clone)]
+pub struct RoutingTable {
+    /// Our own node ID
+    local_id: blake3::Hash,
+    /// Maximum amount of nodes per bucket
+    k: usize,
+    /// The k-buckets, each ordered from least to most recently seen
+    buckets: Vec<VecDeque<DhtNode>>,
+    /// Consecutive failed requests of known nodes
+    failures: HashMap<blake3::Hash, usize>,
+    /// Consecutive failed requests after which a node is evicted
+    max_failures: usize,
+}
+
+impl RoutingTable {
+    /// Create a new empty routing table for the given local ID.
+    pub fn new(local_id: blake3::Hash, k: usize, max_failures: usize) -> Self {
+        Self {
+            local_id,
+            k,
+            buckets: vec![VecDeque::new(); ID_BITS],
+            failures: HashMap::new(),
+            max_failures,
+        }
+    }
+
+    /// Find the bucket index for `id`. Returns `None` for our own ID.
+    pub fn bucket_index(&self, id: &blake3::Hash) -> Option<usize> {
+        let dist = distance(&self.local_id, id);
+        let mut leading_zeros = 0;
+        for byte in dist {
+            if byte != 0 {
+                leading_zeros += byte.leading_zeros() as usize;
+                return Some(ID_BITS - 1 - leading_zeros)
+            }
+            leading_zeros += 8;
+        }
+
+        None
+    }
+
+    /// Record that we have seen `node`. If its bucket is full, the node
+    /// with the most failed requests is replaced by it. If none failed,
+    /// the least recently seen node is returned in [`BucketUpdate::Full`].
+    pub fn update(&mut self, node: DhtNode) -> BucketUpdate {
+        if node.addresses.is_empty() {
+            return BucketUpdate::Ignored
+        }
+
+        let Some(index) = self.bucket_index(&node.id) else { return BucketUpdate::Ignored };
+        let bucket = &mut self.buckets[index];
+
+        if let Some(pos) = bucket.iter().position(|n| n.id == node.id) {
+            self.failures.remove(&node.id);
+            bucket.remove(pos);
+            bucket.push_back(node);
+            return BucketUpdate::Updated
+        }
+
+        if bucket.len() < self.k {
+            bucket.push_back(node);
+            return BucketUpdate::Inserted
+        }
+
+        // Replace the node that failed the most, if any did
+        let failures = &self.failures;
+        let failed = bucket
+            .iter()
+            .enumerate()
+            .map(|(pos, n)| (pos, failures.get(&n.id).copied().unwrap_or(0)))
+            .filter(|(_, failures)| *failures > 0)
+            .max_by(|(a_pos, a), (b_pos, b)| a.cmp(b).then(b_pos.cmp(a_pos)));
+        if let Some((pos, _)) = failed {
+            let evicted = bucket.remove(pos).unwrap();
+            self.failures.remove(&evicted.id);
+            bucket.push_back(node);
+            return BucketUpdate::Inserted
+        }
+
+        BucketUpdate::Full(bucket.front().unwrap().clone())
+    }
+
+    /// Record a failed request to the node with the given ID. Once it
+    /// failed `max_failures` consecutive times, it is removed and returned.
+    pub fn fail(&mut self, id: &blake3::Hash) -> Option<DhtNode> {
+        self.get(id)?;
+
+        let failures = self.failures.entry(*id).or_default();
+        *failures += 1;
+        if *failures < self.max_failures {
+            return None
+        }
+
+        self.remove(id)
+    }
+
+    /// Remove the node with the given ID, returning it if it was known.
+    pub fn remove(&mut self, id: &blake3::Hash) -> Option<DhtNode> {
+        let index = self.bucket_index(id)?;
+        let bucket = &mut self.buckets[index];
+        let pos = bucket.iter().position(|n| &n.id == id)?;
+        self.failures.remove(id);
+        bucket.remove(pos)
+    }
+
+    /// Retrieve the node with the given ID, if known.
+    pub fn get(&self, id: &blake3::Hash) -> Option<&DhtNode> {
+        let index = self.bucket_index(id)?;
+        self.buckets[index].iter().find(|n| &n.id == id)
+    }
+
+    /// Return up to `n` known nodes closest to `key`.
+    pub fn closest(&self, key: &blake3::Hash, n: usize) -> Vec<DhtNode> {
+        let mut nodes: Vec<DhtNode> = self.buckets.iter().flatten().cloned().collect();
+        nodes.sort_by(|a, b| cmp_distance(key, &a.id, &b.id));
+        nodes.truncate(n);
+        nodes
+    }
+
+    /// Return the amount of known nodes.
+    pub fn len(&self) -> usize {
+        self.buckets.iter().map(|b| b.len()).sum()
+    }
+
+    /// Check if the routing table is empty.
+    pub fn is_empty(&self) -> bool {
+        self.buckets.iter().all(|b| b.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(id: [u8; 32]) -> DhtNode {
+        DhtNode {
+            id: blake3::Hash::from_bytes(id),
+            addresses: vec![Url::parse("tcp://127.0.0.1:13337").unwrap()],
+        }
+    }
+
+    #[test]
+    fn routing_table_buckets() {
+        let table = RoutingTable::new(blake3::Hash::from_bytes([0u8; 32]), 2, 3);
+        assert_eq!(table.bucket_index(&blake3::Hash::from_bytes([0u8; 32])), None);
+
+        let mut id = [0u8; 32];
+        id[31] = 1;
+        assert_eq!(table.bucket_index(&blake3::Hash::from_bytes(id)), Some(0));
+        id[31] = 0xff;
+        assert_eq!(table.bucket_index(&blake3::Hash::from_bytes(id)), Some(7));
+        id[0] = 0x80;
+        assert_eq!(table.bucket_index(&blake3::Hash::from_bytes(id)), Some(255));
+    }
+
+    #[test]
+    fn routing_table_update() {
+        let mut table = RoutingTable::new(blake3::Hash::from_bytes([0u8; 32]), 2, 3);
+
+        // Ourselves and unreachable nodes are ignored
+        assert_eq!(table.update(node([0u8; 32])), BucketUpdate::Ignored);
+        let mut unreachable = node([1u8; 32]);
+        unreachable.addresses.clear();
+        assert_eq!(table.update(unreachable), BucketUpdate::Ignored);
+
+        // Fill the top bucket
+        let mut a = [0u8; 32];
+        a[0] = 0x80;
+        let mut b = a;
+        b[31] = 1;
+        let mut c = a;
+        c[31] = 2;
+        assert_eq!(table.update(node(a)), BucketUpdate::Inserted);
+        assert_eq!(table.update(node(b)), BucketUpdate::Inserted);
+
+        // A full bucket returns its least recently seen node
+        assert_eq!(table.update(node(c)), BucketUpdate::Full(node(a)));
+
+        // Seeing a node again moves it to the tail
+        assert_eq!(table.update(node(a)), BucketUpdate::Updated);
+        assert_eq!(table.update(node(c)), BucketUpdate::Full(node(b)));
+
+        // Evicting makes room
+        assert_eq!(table.remove(&node(b).id), Some(node(b)));
+        assert_eq!(table.update(node(c)), BucketUpdate::Inserted);
+        assert_eq!(table.len(), 2);
+    }
+
+    #[test]
+    fn routing_table_failures() {
+        let mut table = RoutingTable::new(blake3::Hash::from_bytes([0u8; 32]), 2, 3);
+
+        let mut a = [0u8; 32];
+        a[0] = 0x80;
+        let mut b = a;
+        b[31] = 1;
+        let mut c = a;
+        c[31] = 2;
+        assert_eq!(table.update(node(a)), BucketUpdate::Inserted);
+        assert_eq!(table.update(node(b)), BucketUpdate::Inserted);
+
+        // A single failure doesn't evict the node, and replying resets the count
+        assert_eq!(table.fail(&node(a).id), None);
+        assert_eq!(table.fail(&node(a).id), None);
+        assert_eq!(table.update(node(a)), BucketUpdate::Updated);
+        assert_eq!(table.fail(&node(a).id), None);
+        assert_eq!(table.fail(&node(a).id), None);
+        assert_eq!(table.len(), 2);
+
+        // Consecutive failures evict it
+        assert_eq!(table.fail(&node(a).id), Some(node(a)));
+        assert_eq!(table.fail(&node(a).id), None);
+        assert_eq!(table.len(), 1);
+
+        // A new node takes the slot of a failing one in a full bucket
+        assert_eq!(table.update(node(a)), BucketUpdate::Inserted);
+        assert_eq!(table.update(node(c)), BucketUpdate::Full(node(b)));
+        assert_eq!(table.fail(&node(a).id), None);
+        assert_eq!(table.update(node(c)), BucketUpdate::Inserted);
+        assert_eq!(table.get(&node(a).id), None);
+        assert_eq!(table.len(), 2);
+    }
+
+    #[test]
+    fn routing_table_closest() {
+        let mut table = RoutingTable::new(blake3::Hash::from_bytes([0u8; 32]), 20, 3);
+        for i in 1..=16u8 {
+            let mut id = [0u8; 32];
+            id[31] = i;
+            table.update(node(id));
+        }
+
+        let mut key = [0u8; 32];
+        key[31] = 5;
+        let closest = table.closest(&blake3::Hash::from_bytes(key), 3);
+        let ids: Vec<u8> = closest.iter().map(|n| n.id.as_bytes()[31]).collect();
+        assert_eq!(ids, vec![5, 4, 7]);
+    }
+}
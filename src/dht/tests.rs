@@ -0,0 +1,181 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2025 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+// cargo +nightly test --release --features=dht,p2p-memory --lib dht_simulated -- --include-ignored
+
+use std::{sync::Arc, time::Duration};
+
+use log::{info, warn};
+use smol::{channel, future, Executor};
+
+use super::{routing::cmp_distance, Dht, DhtPtr, DhtSettings, ProtocolDht};
+use crate::{
+    net::{
+        session::SESSION_DEFAULT, simulator::Simulator, transport::memory::LinkConditions, Settings,
+    },
+    system::sleep,
+};
+
+// Seed of the simulation, change it to explore other scenarios
+const SEED: u64 = 42;
+
+// Number of nodes in the simulation, including the seed node
+const N_NODES: usize = 17;
+
+// Bucket size, kept below N_NODES so lookups need several hops
+const K: usize = 4;
+
+fn init_logger() {
+    let mut cfg = simplelog::ConfigBuilder::new();
+    cfg.add_filter_ignore("sled".to_string());
+    cfg.add_filter_ignore("net".to_string());
+
+    // We check this error so we can execute same file tests in parallel,
+    // otherwise second one fails to init logger here.
+    if simplelog::TermLogger::init(
+        simplelog::LevelFilter::Info,
+        //simplelog::LevelFilter::Debug,
+        cfg.build(),
+        simplelog::TerminalMode::Mixed,
+        simplelog::ColorChoice::Auto,
+    )
+    .is_err()
+    {
+        warn!(target: "test_harness", "Logger already initialized");
+    }
+}
+
+/// Spawn a simulation with node 0 acting as the seed of all others,
+/// and a [`Dht`] running on top of every other node. The seed only
+/// serves peer discovery, so it takes no part in the DHT.
+async fn spawn_simulation(ex: Arc<Executor<'static>>) -> (Simulator, Vec<DhtPtr>) {
+    let mut sim = Simulator::new(SEED, ex);
+    sim.set_default_link(LinkConditions {
+        latency: Duration::from_millis(20),
+        jitter: Duration::from_millis(10),
+        loss: 0.0,
+    });
+
+    let settings = Settings {
+        outbound_connections: 4,
+        inbound_connections: usize::MAX,
+        outbound_connect_timeout: 2,
+        channel_heartbeat_interval: 1,
+        outbound_peer_discovery_cooloff_time: 1,
+        outbound_peer_discovery_attempt_time: 1,
+        ..Default::default()
+    };
+    sim.add_node(Settings { outbound_connections: 0, ..settings.clone() }).await.unwrap();
+    sim.add_nodes(N_NODES - 1, Settings { seeds: vec![sim.addr(0)], ..settings }).await.unwrap();
+
+    let dht_settings = DhtSettings { k: K, alpha: 2, timeout: 2, ..Default::default() };
+    let mut dhts = Vec::with_capacity(N_NODES - 1);
+    for p2p in &sim.nodes()[1..] {
+        let dht = Dht::new(p2p.clone(), dht_settings.clone());
+        let dht_ = dht.clone();
+        p2p.protocol_registry()
+            .register(SESSION_DEFAULT, "ProtocolDht", 1, move |channel, _| {
+                let dht_ = dht_.clone();
+                async move { ProtocolDht::init(dht_, channel).await.unwrap() }
+            })
+            .await;
+        dhts.push(dht);
+    }
+
+    sim.start().await.unwrap();
+    (sim, dhts)
+}
+
+macro_rules! test_body {
+    ($real_call:ident) => {
+        init_logger();
+
+        let ex = Arc::new(Executor::new());
+        let ex_ = ex.clone();
+        let (signal, shutdown) = channel::unbounded::<()>();
+
+        easy_parallel::Parallel::new()
+            .each(0..4, |_| future::block_on(ex.run(shutdown.recv())))
+            .finish(|| {
+                future::block_on(async {
+                    $real_call(ex_).await;
+                    drop(signal);
+                })
+            });
+    };
+}
+
+#[test]
+fn dht_simulated_lookup() {
+    test_body!(dht_simulated_lookup_real);
+}
+
+async fn dht_simulated_lookup_real(ex: Arc<Executor<'static>>) {
+    let (sim, dhts) = spawn_simulation(ex).await;
+
+    info!("Waiting for the simulated network to settle");
+    for _ in 0..30 {
+        if (1..N_NODES).all(|i| !sim.node(i).hosts().peers().is_empty()) {
+            break
+        }
+        sleep(1).await;
+    }
+
+    for dht in &dhts {
+        dht.bootstrap().await;
+    }
+
+    // Every node learned about some others through its channels
+    for (i, dht) in dhts.iter().enumerate() {
+        let size = dht.table_size().await;
+        assert!(size > 0, "node{} has an empty routing table", i + 1);
+    }
+
+    // Every node finds the node closest to some key among all of them
+    let key = blake3::hash(b"dht_simulated_lookup");
+    let closest =
+        dhts.iter().map(|dht| dht.node_id).min_by(|a, b| cmp_distance(&key, a, b)).unwrap();
+    for (i, dht) in dhts.iter().enumerate() {
+        if dht.node_id == closest {
+            continue
+        }
+        let nodes = dht.find_node(&key).await;
+        assert!(nodes.len() <= K);
+        assert!(nodes.iter().any(|n| n.id == closest), "node{} missed the closest node", i + 1);
+    }
+
+    // A key announced by one node is found by all the others
+    let key = blake3::hash(b"dht_simulated_announce");
+    let provider = dhts.len() - 1;
+    dhts[provider].announce(&key).await.unwrap();
+    for (i, dht) in dhts.iter().enumerate() {
+        if i == provider {
+            continue
+        }
+        let providers = dht.find_providers(&key).await;
+        assert_eq!(providers.len(), 1, "node{} found {} providers", i + 1, providers.len());
+        assert_eq!(providers[0].id, dhts[provider].node_id);
+        assert_eq!(providers[0].addresses, vec![sim.addr(provider + 1)]);
+    }
+
+    // Unknown keys have no providers
+    let key = blake3::hash(b"dht_simulated_unknown");
+    assert!(dhts[0].find_providers(&key).await.is_empty());
+
+    sim.stop().await;
+}
@@ -165,6 +165,9 @@ pub enum Error {
     #[error("Error decoding packet: {0}")]
     DecodePacket(String),
 
+    #[error("Decoding limit exceeded: {0}")]
+    DecodeLimitExceeded(String),
+
     #[error("Socks proxy error: {0}")]
     SocksError(String),
 
@@ -208,6 +211,9 @@ pub enum Error {
     #[error("Wrong public inputs count")]
     WrongPublicInputsCount,
 
+    #[error("Circuit declares k = {0}, but needs at least k = {1}")]
+    CircuitKTooSmall(u32, u32),
+
     #[error("Unable to decrypt mint note: {0}")]
     NoteDecryptionFailed(String),
 
@@ -531,6 +537,18 @@ pub enum Error {
     #[error("Geode chunk route not found")]
     GeodeChunkRouteNotFound,
 
+    #[error("Geode file hash does not match its chunks")]
+    GeodeFileHashMismatch,
+
+    #[error("Geode chunk proof is invalid")]
+    GeodeInvalidProof,
+
+    #[error("Geode erasure coding parameters are invalid")]
+    GeodeInvalidErasureConfig,
+
+    #[error("DHT node unreachable")]
+    DhtNodeUnreachable,
+
     // ==================
     // Event Graph errors
     // ==================
@@ -589,6 +607,9 @@ pub enum TxVerifyFailed {
     #[error("Insufficient fee paid")]
     InsufficientFee,
 
+    #[error("Transaction expired at block height {0}")]
+    Expired(u32),
+
     #[error("Erroneous transactions found")]
     ErroneousTxs(Vec<crate::tx::Transaction>),
 }
@@ -659,6 +680,11 @@ impl From<std::io::Error> for ClientFailed {
 
 impl From<std::io::Error> for Error {
     fn from(err: std::io::Error) -> Self {
+        #[cfg(feature = "darkfi-serial")]
+        if let Some(limit) = darkfi_serial::LimitExceeded::from_io(&err) {
+            return Self::DecodeLimitExceeded(limit.to_string())
+        }
+
         Self::Io(err.kind())
     }
 }
@@ -0,0 +1,46 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2025 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::sync::{Arc, LazyLock};
+
+use crate::util::metrics::{registry, Gauge, Histogram, DEFAULT_BUCKETS};
+
+/// Event graph metrics, registered in the global registry on first use
+pub(super) static EVENT_GRAPH_METRICS: LazyLock<EventGraphMetrics> =
+    LazyLock::new(EventGraphMetrics::new);
+
+pub(super) struct EventGraphMetrics {
+    /// Events in the DAG
+    pub dag_size: Arc<Gauge>,
+    /// Time spent on successful DAG syncs
+    pub sync_time: Arc<Histogram>,
+}
+
+impl EventGraphMetrics {
+    fn new() -> Self {
+        let r = registry();
+        Self {
+            dag_size: r.gauge("darkfi_event_graph_dag_size", "Events in the DAG"),
+            sync_time: r.histogram(
+                "darkfi_event_graph_sync_duration_seconds",
+                "Time spent on successful DAG syncs",
+                DEFAULT_BUCKETS,
+            ),
+        }
+    }
+}
@@ -20,9 +20,10 @@ use std::{
     collections::{BTreeMap, HashMap, HashSet, VecDeque},
     path::PathBuf,
     sync::Arc,
+    time::Instant,
 };
 
-use darkfi_serial::{deserialize_async, serialize_async};
+use darkfi_serial::{deserialize_async, serialize_async, DecodeLimits};
 use log::{debug, error, info, warn};
 use num_bigint::BigUint;
 use sled_overlay::{sled, SledTreeOverlay};
@@ -59,6 +60,10 @@ use util::{generate_genesis, millis_until_next_rotation, next_rotation_timestamp
 pub mod deg;
 use deg::DegEvent;
 
+/// Event graph metrics
+mod metrics;
+use metrics::EVENT_GRAPH_METRICS;
+
 #[cfg(test)]
 mod tests;
 
@@ -72,6 +77,9 @@ pub const GENESIS_CONTENTS: &[u8] = &[0x47, 0x45, 0x4e, 0x45, 0x53, 0x49, 0x53];
 pub const N_EVENT_PARENTS: usize = 5;
 /// Allowed timestamp drift in milliseconds
 const EVENT_TIME_DRIFT: u64 = 60_000;
+/// Limits applied when decoding event graph messages from peers:
+/// 1M bytes of event content, 8MB per message and 8 nesting levels.
+pub const EVENT_DECODE_LIMITS: DecodeLimits = DecodeLimits::new(1024 * 1024, 8 * 1024 * 1024, 8);
 /// Null event ID
 pub const NULL_ID: blake3::Hash = blake3::Hash::from_bytes([0x00; blake3::OUT_LEN]);
 
@@ -172,6 +180,7 @@ impl EventGraph {
 
         // Find the unreferenced tips in the current DAG state.
         *self_.unreferenced_tips.write().await = self_.find_unreferenced_tips().await;
+        EVENT_GRAPH_METRICS.dag_size.set(dag.len() as i64);
 
         // Spawn the DAG pruning task
         if days_rotation > 0 {
@@ -200,6 +209,8 @@ impl EventGraph {
 
     /// Sync the DAG from connected peers
     pub async fn dag_sync(&self) -> Result<()> {
+        let start = Instant::now();
+
         // We do an optimistic sync where we ask all our connected peers for
         // the latest layer DAG tips (unreferenced events) and then we accept
         // the ones we see the most times.
@@ -438,6 +449,7 @@ impl EventGraph {
         self.dag_insert(&events).await?;
 
         *self.synced.write().await = true;
+        EVENT_GRAPH_METRICS.sync_time.observe_duration(start.elapsed());
 
         info!(target: "event_graph::dag_sync()", "[EVENTGRAPH] DAG synced successfully!");
         Ok(())
@@ -473,6 +485,7 @@ impl EventGraph {
         unreferenced_tips.insert(0, HashSet::from([genesis_event.id()]));
         *current_genesis = genesis_event;
         *broadcasted_ids = HashSet::new();
+        EVENT_GRAPH_METRICS.dag_size.set(1);
         drop(unreferenced_tips);
         drop(broadcasted_ids);
         drop(current_genesis);
@@ -536,6 +549,9 @@ impl EventGraph {
         // Here we keep the IDs to return
         let mut ids = Vec::with_capacity(events.len());
 
+        // Count of the events that were not in the DAG already
+        let mut new_events = 0;
+
         // Create an overlay over the DAG tree
         let mut overlay = SledTreeOverlay::new(&self.dag);
 
@@ -562,6 +578,9 @@ impl EventGraph {
             let event_se = serialize_async(event).await;
 
             // Add the event to the overlay
+            if overlay.get(event_id.as_bytes())?.is_none() {
+                new_events += 1;
+            }
             overlay.insert(event_id.as_bytes(), &event_se)?;
 
             if self.replay_mode {
@@ -579,6 +598,7 @@ impl EventGraph {
         if let Err(e) = self.dag.apply_batch(batch) {
             panic!("Failed applying dag_insert batch to sled: {}", e);
         }
+        EVENT_GRAPH_METRICS.dag_size.add(new_events);
 
         // Iterate over given events to update references and
         // send out notifications about them
@@ -28,7 +28,7 @@ use darkfi_serial::{async_trait, SerialDecodable, SerialEncodable};
 use log::{debug, error, trace, warn};
 use smol::Executor;
 
-use super::{Event, EventGraphPtr, NULL_ID};
+use super::{Event, EventGraphPtr, EVENT_DECODE_LIMITS, NULL_ID};
 use crate::{impl_p2p_message, net::*, system::msleep, util::time::NanoTimestamp, Error, Result};
 
 /// Malicious behaviour threshold. If the threshold is reached, we will
@@ -116,27 +116,27 @@ pub struct ProtocolEventGraph {
 /// A P2P message representing publishing an event on the network
 #[derive(Clone, SerialEncodable, SerialDecodable)]
 pub struct EventPut(pub Event);
-impl_p2p_message!(EventPut, "EventGraph::EventPut");
+impl_p2p_message!(EventPut, "EventGraph::EventPut", EVENT_DECODE_LIMITS);
 
 /// A P2P message representing an event request
 #[derive(Clone, SerialEncodable, SerialDecodable)]
 pub struct EventReq(pub Vec<blake3::Hash>);
-impl_p2p_message!(EventReq, "EventGraph::EventReq");
+impl_p2p_message!(EventReq, "EventGraph::EventReq", EVENT_DECODE_LIMITS);
 
 /// A P2P message representing an event reply
 #[derive(Clone, SerialEncodable, SerialDecodable)]
 pub struct EventRep(pub Vec<Event>);
-impl_p2p_message!(EventRep, "EventGraph::EventRep");
+impl_p2p_message!(EventRep, "EventGraph::EventRep", EVENT_DECODE_LIMITS);
 
 /// A P2P message representing a request for a peer's DAG tips
 #[derive(Clone, SerialEncodable, SerialDecodable)]
 pub struct TipReq {}
-impl_p2p_message!(TipReq, "EventGraph::TipReq");
+impl_p2p_message!(TipReq, "EventGraph::TipReq", EVENT_DECODE_LIMITS);
 
 /// A P2P message representing a reply for the peer's DAG tips
 #[derive(Clone, SerialEncodable, SerialDecodable)]
 pub struct TipRep(pub BTreeMap<u64, HashSet<blake3::Hash>>);
-impl_p2p_message!(TipRep, "EventGraph::TipRep");
+impl_p2p_message!(TipRep, "EventGraph::TipRep", EVENT_DECODE_LIMITS);
 
 #[async_trait]
 impl ProtocolBase for ProtocolEventGraph {
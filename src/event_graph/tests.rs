@@ -102,7 +102,7 @@ async fn spawn_node(
     // Register the P2P protocols
     let registry = p2p.protocol_registry();
     registry
-        .register(SESSION_DEFAULT, move |channel, _| {
+        .register(SESSION_DEFAULT, "ProtocolEventGraph", 0, move |channel, _| {
             let event_graph_ = event_graph_.clone();
             async move { ProtocolEventGraph::init(event_graph_, channel).await.unwrap() }
         })
@@ -0,0 +1,257 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2025 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Systematic Reed-Solomon erasure coding over GF(2^8).
+//!
+//! `k` data shards are extended with `m` parity shards, and any `k` of
+//! the `k + m` shards are enough to rebuild the others. The encoding
+//! matrix is the identity stacked over a `m x k` Cauchy matrix, so every
+//! `k x k` submatrix of it is invertible.
+
+use crate::{Error, Result};
+
+/// Field generator polynomial, x^8 + x^4 + x^3 + x^2 + 1
+const POLY: u16 = 0x11d;
+
+/// Maximum number of shards, bounded by the field size
+pub const MAX_SHARDS: usize = 256;
+
+const fn gf_tables() -> ([u8; 256], [u8; 512]) {
+    let mut log = [0u8; 256];
+    let mut exp = [0u8; 512];
+    let mut x: u16 = 1;
+    let mut i = 0;
+    while i < 255 {
+        exp[i] = x as u8;
+        exp[i + 255] = x as u8;
+        log[x as usize] = i as u8;
+        x <<= 1;
+        if x & 0x100 != 0 {
+            x ^= POLY;
+        }
+        i += 1;
+    }
+    (log, exp)
+}
+
+const TABLES: ([u8; 256], [u8; 512]) = gf_tables();
+const LOG: [u8; 256] = TABLES.0;
+const EXP: [u8; 512] = TABLES.1;
+
+fn gf_mul(a: u8, b: u8) -> u8 {
+    if a == 0 || b == 0 {
+        return 0
+    }
+    EXP[LOG[a as usize] as usize + LOG[b as usize] as usize]
+}
+
+fn gf_inv(a: u8) -> u8 {
+    assert!(a != 0);
+    EXP[255 - LOG[a as usize] as usize]
+}
+
+/// `dst ^= c * src`
+fn mul_add(dst: &mut [u8], src: &[u8], c: u8) {
+    if c == 0 {
+        return
+    }
+    for (d, s) in dst.iter_mut().zip(src) {
+        *d ^= gf_mul(c, *s);
+    }
+}
+
+/// Invert a square matrix with Gauss-Jordan elimination.
+fn invert(mut matrix: Vec<Vec<u8>>) -> Option<Vec<Vec<u8>>> {
+    let n = matrix.len();
+    let mut inverse: Vec<Vec<u8>> =
+        (0..n).map(|i| (0..n).map(|j| (i == j) as u8).collect()).collect();
+
+    for col in 0..n {
+        let pivot = (col..n).find(|&row| matrix[row][col] != 0)?;
+        matrix.swap(col, pivot);
+        inverse.swap(col, pivot);
+
+        let scale = gf_inv(matrix[col][col]);
+        for j in 0..n {
+            matrix[col][j] = gf_mul(matrix[col][j], scale);
+            inverse[col][j] = gf_mul(inverse[col][j], scale);
+        }
+
+        for row in 0..n {
+            let factor = matrix[row][col];
+            if row == col || factor == 0 {
+                continue
+            }
+            for j in 0..n {
+                matrix[row][j] ^= gf_mul(factor, matrix[col][j]);
+                inverse[row][j] ^= gf_mul(factor, inverse[col][j]);
+            }
+        }
+    }
+
+    Some(inverse)
+}
+
+/// Reed-Solomon codec for a fixed number of data and parity shards.
+pub struct ReedSolomon {
+    data: usize,
+    parity: usize,
+    /// Cauchy matrix generating the parity shards
+    matrix: Vec<Vec<u8>>,
+}
+
+impl ReedSolomon {
+    /// Create a codec for `data` data shards and `parity` parity shards.
+    pub fn new(data: usize, parity: usize) -> Result<Self> {
+        if data == 0 || data + parity > MAX_SHARDS {
+            return Err(Error::GeodeInvalidErasureConfig)
+        }
+
+        // Elements 1 / (x_i + y_j) with x_i = data + i and y_j = j,
+        // which are all distinct so the sum is never zero.
+        let matrix = (0..parity)
+            .map(|i| (0..data).map(|j| gf_inv(((data + i) ^ j) as u8)).collect())
+            .collect();
+
+        Ok(Self { data, parity, matrix })
+    }
+
+    /// Row `index` of the full encoding matrix.
+    fn row(&self, index: usize) -> Vec<u8> {
+        if index < self.data {
+            return (0..self.data).map(|j| (j == index) as u8).collect()
+        }
+        self.matrix[index - self.data].clone()
+    }
+
+    /// Compute the parity shards of the given data shards, which
+    /// must all have the same length.
+    pub fn encode(&self, data: &[&[u8]]) -> Result<Vec<Vec<u8>>> {
+        if data.len() != self.data {
+            return Err(Error::GeodeInvalidErasureConfig)
+        }
+
+        let len = data[0].len();
+        if data.iter().any(|shard| shard.len() != len) {
+            return Err(Error::GeodeInvalidErasureConfig)
+        }
+
+        let mut parity = vec![vec![0u8; len]; self.parity];
+        for (shard, row) in parity.iter_mut().zip(&self.matrix) {
+            for (src, coeff) in data.iter().zip(row) {
+                mul_add(shard, src, *coeff);
+            }
+        }
+
+        Ok(parity)
+    }
+
+    /// Rebuild the missing shards, given as `None`, in place. The data
+    /// shards come first, followed by the parity shards. At least
+    /// `data` shards must be present, and all of the same length.
+    pub fn reconstruct(&self, shards: &mut [Option<Vec<u8>>]) -> Result<()> {
+        if shards.len() != self.data + self.parity {
+            return Err(Error::GeodeInvalidErasureConfig)
+        }
+
+        if shards.iter().all(|shard| shard.is_some()) {
+            return Ok(())
+        }
+
+        let present: Vec<usize> =
+            (0..shards.len()).filter(|&i| shards[i].is_some()).take(self.data).collect();
+        if present.len() < self.data {
+            return Err(Error::GeodeChunkNotFound)
+        }
+
+        let len = shards[present[0]].as_ref().unwrap().len();
+        if present.iter().any(|&i| shards[i].as_ref().unwrap().len() != len) {
+            return Err(Error::GeodeInvalidErasureConfig)
+        }
+
+        // Rebuild the missing data shards by inverting the rows of
+        // the encoding matrix belonging to the present shards.
+        let Some(decode) = invert(present.iter().map(|&i| self.row(i)).collect()) else {
+            return Err(Error::GeodeInvalidErasureConfig)
+        };
+
+        for index in 0..self.data {
+            if shards[index].is_some() {
+                continue
+            }
+
+            let mut shard = vec![0u8; len];
+            for (src, coeff) in present.iter().zip(&decode[index]) {
+                mul_add(&mut shard, shards[*src].as_ref().unwrap(), *coeff);
+            }
+            shards[index] = Some(shard);
+        }
+
+        // Then encode the missing parity shards again
+        if shards[self.data..].iter().any(|shard| shard.is_none()) {
+            let data: Vec<&[u8]> =
+                shards[..self.data].iter().map(|s| s.as_ref().unwrap().as_slice()).collect();
+            let parity = self.encode(&data)?;
+            for (shard, rebuilt) in shards[self.data..].iter_mut().zip(parity) {
+                if shard.is_none() {
+                    *shard = Some(rebuilt);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reed_solomon_reconstruct() {
+        let (k, m) = (4, 3);
+        let rs = ReedSolomon::new(k, m).unwrap();
+
+        let data: Vec<Vec<u8>> =
+            (0..k).map(|i| (0..64).map(|j| (i * 64 + j * 7) as u8).collect()).collect();
+        let refs: Vec<&[u8]> = data.iter().map(|d| d.as_slice()).collect();
+        let parity = rs.encode(&refs).unwrap();
+        let all: Vec<Vec<u8>> = data.iter().chain(parity.iter()).cloned().collect();
+
+        // Every combination of up to `m` lost shards can be rebuilt
+        for lost in 0u32..(1 << (k + m)) {
+            let mut shards: Vec<Option<Vec<u8>>> = all
+                .iter()
+                .enumerate()
+                .map(|(i, s)| if lost & (1 << i) != 0 { None } else { Some(s.clone()) })
+                .collect();
+
+            if lost.count_ones() as usize > m {
+                assert!(rs.reconstruct(&mut shards).is_err());
+                continue
+            }
+
+            rs.reconstruct(&mut shards).unwrap();
+            let shards: Vec<Vec<u8>> = shards.into_iter().map(|s| s.unwrap()).collect();
+            assert_eq!(shards, all);
+        }
+
+        assert!(ReedSolomon::new(0, 1).is_err());
+        assert!(ReedSolomon::new(200, 57).is_err());
+    }
+}
@@ -0,0 +1,206 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2025 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Merkle tree over a file's chunk hashes.
+//!
+//! Leaves are `BLAKE3(0x00 || chunk_hash)` and inner nodes are
+//! `BLAKE3(0x01 || left || right)`. When a level has an odd number
+//! of nodes, the last one is promoted to the next level unchanged.
+//! The file hash is `BLAKE3(0x02 || chunks || root)`, committing to the
+//! number of chunks as a little-endian `u64`, so a proof can't claim a
+//! different file length. An empty file has the root `BLAKE3("")`.
+//! Erasure coded files append the commitment to their erasure coding
+//! layout and parity chunks, `BLAKE3(0x02 || chunks || root || erasure)`,
+//! so their parity chunks are covered by the file hash as well.
+
+use darkfi_serial::{async_trait, SerialDecodable, SerialEncodable};
+
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+const FILE_PREFIX: u8 = 0x02;
+
+fn leaf(chunk_hash: &blake3::Hash) -> blake3::Hash {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&[LEAF_PREFIX]);
+    hasher.update(chunk_hash.as_bytes());
+    hasher.finalize()
+}
+
+fn node(left: &blake3::Hash, right: &blake3::Hash) -> blake3::Hash {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&[NODE_PREFIX]);
+    hasher.update(left.as_bytes());
+    hasher.update(right.as_bytes());
+    hasher.finalize()
+}
+
+fn file(chunks: u64, root: &blake3::Hash, erasure: Option<&blake3::Hash>) -> blake3::Hash {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&[FILE_PREFIX]);
+    hasher.update(&chunks.to_le_bytes());
+    hasher.update(root.as_bytes());
+    if let Some(erasure) = erasure {
+        hasher.update(erasure.as_bytes());
+    }
+    hasher.finalize()
+}
+
+/// Hash a level of the tree into the next one.
+fn next_level(level: &[blake3::Hash]) -> Vec<blake3::Hash> {
+    level
+        .chunks(2)
+        .map(|pair| match pair {
+            [left, right] => node(left, right),
+            [single] => *single,
+            _ => unreachable!(),
+        })
+        .collect()
+}
+
+/// Compute the file hash of the given ordered chunk hashes.
+pub fn merkle_root(chunk_hashes: &[blake3::Hash]) -> blake3::Hash {
+    file_root(chunk_hashes, None)
+}
+
+/// Compute the file hash of the given ordered data chunk hashes of an
+/// erasure coded file, along with the commitment to its erasure coding.
+pub fn erasure_merkle_root(chunk_hashes: &[blake3::Hash], erasure: &blake3::Hash) -> blake3::Hash {
+    file_root(chunk_hashes, Some(erasure))
+}
+
+fn file_root(chunk_hashes: &[blake3::Hash], erasure: Option<&blake3::Hash>) -> blake3::Hash {
+    if chunk_hashes.is_empty() {
+        return file(0, &blake3::hash(&[]), erasure)
+    }
+
+    let mut level: Vec<blake3::Hash> = chunk_hashes.iter().map(leaf).collect();
+    while level.len() > 1 {
+        level = next_level(&level);
+    }
+
+    file(chunk_hashes.len() as u64, &level[0], erasure)
+}
+
+/// Inclusion proof of a chunk in a file.
+#[derive(Debug, Clone, PartialEq, Eq, SerialEncodable, SerialDecodable)]
+pub struct ChunkProof {
+    /// Index of the chunk in the file
+    pub index: u64,
+    /// Number of chunks in the file
+    pub chunks: u64,
+    /// Sibling hashes from the leaf up to the root.
+    /// Levels where the node was promoted have no sibling.
+    pub siblings: Vec<blake3::Hash>,
+    /// Commitment to the erasure coding of the file, if it has one
+    pub erasure: Option<blake3::Hash>,
+}
+
+impl ChunkProof {
+    /// Build the inclusion proof of the chunk at `index`.
+    /// Returns `None` if `index` is out of bounds.
+    pub fn new(chunk_hashes: &[blake3::Hash], index: usize) -> Option<Self> {
+        if index >= chunk_hashes.len() {
+            return None
+        }
+
+        let mut siblings = vec![];
+        let mut level: Vec<blake3::Hash> = chunk_hashes.iter().map(leaf).collect();
+        let mut idx = index;
+        while level.len() > 1 {
+            let sibling = idx ^ 1;
+            if sibling < level.len() {
+                siblings.push(level[sibling]);
+            }
+            level = next_level(&level);
+            idx /= 2;
+        }
+
+        Some(Self {
+            index: index as u64,
+            chunks: chunk_hashes.len() as u64,
+            siblings,
+            erasure: None,
+        })
+    }
+
+    /// Verify that `chunk_hash` is the chunk at [`ChunkProof::index`]
+    /// of the file named `file_hash`.
+    pub fn verify(&self, chunk_hash: &blake3::Hash, file_hash: &blake3::Hash) -> bool {
+        if self.index >= self.chunks {
+            return false
+        }
+
+        let mut siblings = self.siblings.iter();
+        let mut hash = leaf(chunk_hash);
+        let mut idx = self.index;
+        let mut width = self.chunks;
+        while width > 1 {
+            if idx % 2 == 1 {
+                let Some(sibling) = siblings.next() else { return false };
+                hash = node(sibling, &hash);
+            } else if idx + 1 < width {
+                let Some(sibling) = siblings.next() else { return false };
+                hash = node(&hash, sibling);
+            }
+            idx /= 2;
+            width = width.div_ceil(2);
+        }
+
+        siblings.next().is_none() && &file(self.chunks, &hash, self.erasure.as_ref()) == file_hash
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merkle_proofs() {
+        let chunks: Vec<blake3::Hash> = (0u8..13).map(|i| blake3::hash(&[i; 32])).collect();
+
+        for n in 1..=chunks.len() {
+            let root = merkle_root(&chunks[..n]);
+            for i in 0..n {
+                let proof = ChunkProof::new(&chunks[..n], i).unwrap();
+                assert!(proof.verify(&chunks[i], &root));
+                // Wrong chunk
+                assert!(!proof.verify(&chunks[(i + 1) % chunks.len()], &root));
+                // Wrong chunk count
+                let mut bad = proof.clone();
+                bad.chunks += 1;
+                assert!(!bad.verify(&chunks[i], &root));
+            }
+            assert!(ChunkProof::new(&chunks[..n], n).is_none());
+        }
+
+        // Erasure coded files commit to their erasure coding
+        let erasure = blake3::hash(b"erasure");
+        let root = erasure_merkle_root(&chunks, &erasure);
+        assert_ne!(root, merkle_root(&chunks));
+        assert_ne!(root, erasure_merkle_root(&chunks, &blake3::hash(b"forged")));
+        let mut proof = ChunkProof::new(&chunks, 3).unwrap();
+        assert!(!proof.verify(&chunks[3], &root));
+        proof.erasure = Some(erasure);
+        assert!(proof.verify(&chunks[3], &root));
+        assert!(!proof.verify(&chunks[3], &merkle_root(&chunks)));
+
+        // Appending a chunk changes the root
+        assert_ne!(merkle_root(&chunks[..3]), merkle_root(&chunks[..4]));
+        assert_ne!(merkle_root(&chunks[..1]), chunks[0]);
+    }
+}
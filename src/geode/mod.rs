@@ -23,12 +23,25 @@
 //! `remove` support. File removal should be handled externally, and then it
 //! is only required to run `garbage_collect()` to clean things up.
 //!
-//! The filesystem hierarchy stores two directories: `files` and `chunks`.
+//! The filesystem hierarchy stores three directories: `files`, `chunks`
+//! and `downloads`.
 //! `chunks` store [`MAX_CHUNK_SIZE`] files, where the filename is a BLAKE3
 //! hash of the chunk's contents.
 //! `files` store metadata about a full file, which can be retrieved by
 //! concatenating the chunks in order. The filename of a file in `files`
-//! is the BLAKE3 hash of hashed chunks in the correct order.
+//! is derived from a Merkle tree over the file's chunk hashes in order,
+//! see [`merkle`]. This allows verifying any single chunk against the
+//! file hash with a [`ChunkProof`], without knowing the other chunks.
+//! Metadata written by older versions, named by the BLAKE3 hash of the
+//! file contents, is rewritten under the Merkle root once its chunks
+//! confirm the old hash.
+//! Every chunk except the last one is exactly [`MAX_CHUNK_SIZE`] long,
+//! so byte ranges of a file map directly to the chunks holding them.
+//!
+//! Files that are being downloaded chunk by chunk without their metadata
+//! have their progress tracked in `downloads`, so interrupted downloads
+//! can be resumed. Once all chunks are verified, the file metadata is
+//! written to `files` and the download state is removed.
 //!
 //! It might look like the following:
 //! ```
@@ -51,12 +64,23 @@
 //! hashes found above. The contents of the files in `/chunks` are arbitrary
 //! data, and by concatenating them we can retrieve the original file.
 //!
+//! Files can optionally be stored with a Reed-Solomon [`ErasureLayout`],
+//! where every stripe of `k` consecutive chunks is extended with `m` parity
+//! chunks, stored in `chunks` like any other chunk. The metadata of such
+//! files starts with an `rs <k> <m> <file_size>` header, and the parity
+//! chunk hashes follow the data chunk hashes. Missing data chunks are
+//! rebuilt on [`Geode::get`] from any `k` chunks of their stripe. The
+//! file hash of such files also commits to the erasure coding header and
+//! the parity chunk hashes, see [`erasure_merkle_root`]. When they are
+//! downloaded chunk by chunk, only the data chunks are fetched, and the
+//! metadata keeps that commitment in an `rsc <commitment>` header.
+//!
 //! It is important to note that multiple files can use the same chunks.
 //! This is some kind of naive deduplication, so we actually don't consider
 //! chunks to be specific to a single file and therefore when we do garbage
 //! collection, we keep chunks and files independent of each other.
 
-use std::{collections::HashSet, path::PathBuf};
+use std::{collections::HashSet, ops::Range, path::PathBuf};
 
 use futures::AsyncRead;
 use log::{debug, info, warn};
@@ -69,6 +93,14 @@ use smol::{
 
 use crate::{Error, Result};
 
+/// Merkle tree over file chunks
+pub mod merkle;
+pub use merkle::{erasure_merkle_root, merkle_root, ChunkProof};
+
+/// Reed-Solomon erasure coding
+pub mod erasure;
+use erasure::ReedSolomon;
+
 /// Defined maximum size of a stored chunk (256 KiB)
 pub const MAX_CHUNK_SIZE: usize = 262_144;
 
@@ -76,6 +108,74 @@ pub const MAX_CHUNK_SIZE: usize = 262_144;
 const FILES_PATH: &str = "files";
 /// Path prefix where file chunks are stored
 const CHUNKS_PATH: &str = "chunks";
+/// Path prefix where download states are stored
+const DOWNLOADS_PATH: &str = "downloads";
+/// Header prefix of erasure coded file metadata
+const ERASURE_HEADER: &str = "rs ";
+/// Header prefix of the metadata of erasure coded files stored without
+/// their parity chunks, holding the commitment to their erasure coding
+const ERASURE_COMMITMENT_HEADER: &str = "rsc ";
+
+/// Reed-Solomon layout of an erasure coded file. Every stripe of
+/// `data_chunks` consecutive chunks of the file is extended with
+/// `parity_chunks` parity chunks, and any `data_chunks` chunks of
+/// a stripe are enough to rebuild it. The last stripe may hold
+/// fewer data chunks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ErasureLayout {
+    /// Number of data chunks in a stripe
+    pub data_chunks: usize,
+    /// Number of parity chunks in a stripe
+    pub parity_chunks: usize,
+}
+
+/// Erasure coding metadata of a file.
+struct ErasureMetadata {
+    layout: ErasureLayout,
+    /// Size of the file in bytes, needed to strip the padding of
+    /// rebuilt chunks
+    size: u64,
+    /// Parity chunk hashes, `parity_chunks` per stripe
+    parity_hashes: Vec<blake3::Hash>,
+}
+
+impl ErasureMetadata {
+    /// Commitment to the erasure coding layout, the file size, and the
+    /// parity chunk hashes, which the file hash covers.
+    fn commitment(&self) -> blake3::Hash {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(&(self.layout.data_chunks as u64).to_le_bytes());
+        hasher.update(&(self.layout.parity_chunks as u64).to_le_bytes());
+        hasher.update(&self.size.to_le_bytes());
+        hasher.update(merkle_root(&self.parity_hashes).as_bytes());
+        hasher.finalize()
+    }
+}
+
+/// Contents of a file's metadata.
+struct FileMetadata {
+    chunk_hashes: Vec<blake3::Hash>,
+    erasure: Option<ErasureMetadata>,
+    /// Erasure coding commitment of an erasure coded file downloaded
+    /// without its parity chunks
+    erasure_commitment: Option<blake3::Hash>,
+}
+
+impl FileMetadata {
+    /// Commitment to the erasure coding of the file, if it has one.
+    fn erasure_commitment(&self) -> Option<blake3::Hash> {
+        self.erasure.as_ref().map(|erasure| erasure.commitment()).or(self.erasure_commitment)
+    }
+
+    /// Compute the file hash. For erasure coded files, it also covers
+    /// the parity chunks.
+    fn file_hash(&self) -> blake3::Hash {
+        match self.erasure_commitment() {
+            Some(commitment) => erasure_merkle_root(&self.chunk_hashes, &commitment),
+            None => merkle_root(&self.chunk_hashes),
+        }
+    }
+}
 
 /// `ChunkedFile` is a representation of a file we're trying to
 /// retrieve from `Geode`.
@@ -101,6 +201,71 @@ impl ChunkedFile {
     pub fn iter(&self) -> core::slice::Iter<'_, (blake3::Hash, Option<PathBuf>)> {
         self.0.iter()
     }
+
+    /// Return the number of chunks in the file.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Check whether the file has no chunks.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Return the indexes of the chunks not available locally.
+    pub fn missing(&self) -> Vec<u64> {
+        let missing = self.0.iter().enumerate().filter(|(_, (_, p))| p.is_none());
+        missing.map(|(i, _)| i as u64).collect()
+    }
+
+    /// Return the indexes of the chunks holding `len` bytes starting
+    /// at `offset`, clamped to the file's chunks.
+    pub fn chunk_range(&self, offset: u64, len: u64) -> Range<usize> {
+        chunk_range(offset, len, self.0.len())
+    }
+}
+
+/// Return the indexes of the chunks holding `len` bytes starting at
+/// `offset`, in a file of `chunks` chunks.
+fn chunk_range(offset: u64, len: u64, chunks: usize) -> Range<usize> {
+    if len == 0 {
+        return 0..0
+    }
+
+    let chunk_size = MAX_CHUNK_SIZE as u64;
+    let start = (offset / chunk_size).min(chunks as u64) as usize;
+    let end = (offset.saturating_add(len - 1) / chunk_size + 1).min(chunks as u64) as usize;
+    start..end
+}
+
+/// Progress of a file being downloaded chunk by chunk.
+///
+/// The chunks are verified against the file hash with their
+/// [`ChunkProof`] as they are inserted, see [`Geode::insert_chunk_verified`].
+#[derive(Clone, Debug)]
+pub struct Download(Vec<Option<blake3::Hash>>);
+
+impl Download {
+    /// Check whether all the chunks have been downloaded.
+    pub fn is_complete(&self) -> bool {
+        self.0.iter().all(|c| c.is_some())
+    }
+
+    /// Return the number of chunks in the file.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Check whether the file has no chunks.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Return the indexes of the chunks still to be downloaded.
+    pub fn missing(&self) -> Vec<u64> {
+        let missing = self.0.iter().enumerate().filter(|(_, c)| c.is_none());
+        missing.map(|(i, _)| i as u64).collect()
+    }
 }
 
 /// Chunk-based file storage interface.
@@ -109,6 +274,8 @@ pub struct Geode {
     files_path: PathBuf,
     /// Path to the filesystem directory where file chunks are stored
     chunks_path: PathBuf,
+    /// Path to the filesystem directory where download states are stored
+    downloads_path: PathBuf,
 }
 
 impl Geode {
@@ -118,30 +285,400 @@ impl Geode {
     pub async fn new(base_path: &PathBuf) -> Result<Self> {
         let mut files_path: PathBuf = base_path.into();
         let mut chunks_path: PathBuf = base_path.into();
+        let mut downloads_path: PathBuf = base_path.into();
         files_path.push(FILES_PATH);
         chunks_path.push(CHUNKS_PATH);
+        downloads_path.push(DOWNLOADS_PATH);
 
         // Create necessary directory structure if needed
         fs::create_dir_all(&files_path).await?;
         fs::create_dir_all(&chunks_path).await?;
+        fs::create_dir_all(&downloads_path).await?;
+
+        let geode = Self { files_path, chunks_path, downloads_path };
+        geode.migrate_legacy_files().await?;
+
+        Ok(geode)
+    }
+
+    /// Check if `metadata`, stored under `file_hash`, was written before files
+    /// were identified by the Merkle root of their chunks, when their hash was
+    /// the BLAKE3 hash of the file contents. Returns `None` if this can't be
+    /// verified, because some of the chunks are not available locally.
+    async fn is_legacy_metadata(
+        &self,
+        file_hash: &blake3::Hash,
+        metadata: &FileMetadata,
+    ) -> Option<bool> {
+        // Erasure coded files were only ever identified by the Merkle root
+        if metadata.erasure_commitment().is_some() {
+            return Some(false)
+        }
+
+        let mut hasher = blake3::Hasher::new();
+        let mut buf = vec![0u8; MAX_CHUNK_SIZE];
+        for chunk_hash in &metadata.chunk_hashes {
+            let mut chunk_path = self.chunks_path.clone();
+            chunk_path.push(chunk_hash.to_hex().as_str());
+
+            let mut chunk_fd = File::open(&chunk_path).await.ok()?;
+            let bytes_read = Self::read_chunk(&mut chunk_fd, &mut buf).await.ok()?;
+            let chunk_slice = &buf[..bytes_read];
+            if &blake3::hash(chunk_slice) != chunk_hash {
+                return None
+            }
 
-        Ok(Self { files_path, chunks_path })
+            hasher.update(chunk_slice);
+        }
+
+        Some(&hasher.finalize() == file_hash)
     }
 
-    /// Attempt to read chunk hashes from a given file path and return
-    /// a `Vec` containing the hashes in order.
-    async fn read_metadata(path: &PathBuf) -> Result<Vec<blake3::Hash>> {
+    /// Rewrite the legacy metadata found at `path` under the Merkle root
+    /// of its chunks. Returns the new file hash.
+    async fn migrate_metadata(path: &PathBuf, metadata: &FileMetadata) -> Result<blake3::Hash> {
+        let file_hash = metadata.file_hash();
+        let mut file_path = path.clone();
+        file_path.set_file_name(file_hash.to_hex().as_str());
+
+        Self::write_metadata(&file_path, metadata).await?;
+        fs::remove_file(path).await?;
+
+        Ok(file_hash)
+    }
+
+    /// Rewrite the metadata of all the files stored under their legacy
+    /// hash, see [`Geode::is_legacy_metadata`], under the Merkle root of
+    /// their chunks. Files whose chunks are not all available locally
+    /// can't be verified, so they are left untouched.
+    async fn migrate_legacy_files(&self) -> Result<()> {
+        let mut file_paths = fs::read_dir(&self.files_path).await?;
+        while let Some(file) = file_paths.next().await {
+            let Ok(entry) = file else { continue };
+            let path = entry.path();
+
+            let Some(file_name) = path.file_name().and_then(|n| n.to_str()) else { continue };
+            let Ok(file_hash) = blake3::Hash::from_hex(file_name) else { continue };
+            let Ok(metadata) = Self::read_metadata(&path).await else { continue };
+
+            if metadata.file_hash() == file_hash ||
+                self.is_legacy_metadata(&file_hash, &metadata).await != Some(true)
+            {
+                continue
+            }
+
+            match Self::migrate_metadata(&path, &metadata).await {
+                Ok(new_hash) => info!(
+                    target: "geode::migrate_legacy_files()",
+                    "[Geode] Migrated file {} to {}", file_hash, new_hash,
+                ),
+                Err(e) => warn!(
+                    target: "geode::migrate_legacy_files()",
+                    "[Geode] Failed migrating file {}: {}", file_hash, e,
+                ),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Attempt to read file metadata from a given file path. Plain files
+    /// hold the chunk hashes in order, and erasure coded files start with
+    /// a header followed by the data and then the parity chunk hashes.
+    async fn read_metadata(path: &PathBuf) -> Result<FileMetadata> {
         debug!(target: "geode::read_metadata()", "Reading chunks from {:?}", path);
         let fd = File::open(path).await?;
-        let mut read_chunks = vec![];
         let mut lines = BufReader::new(fd).lines();
+
+        let mut header = None;
+        let mut erasure_commitment = None;
+        let mut read_chunks = vec![];
         while let Some(line) = lines.next().await {
             let line = line?;
+            if read_chunks.is_empty() && header.is_none() && erasure_commitment.is_none() {
+                if let Some(h) = line.strip_prefix(ERASURE_HEADER) {
+                    header = Some(Self::parse_erasure_header(h)?);
+                    continue
+                }
+
+                if let Some(h) = line.strip_prefix(ERASURE_COMMITMENT_HEADER) {
+                    erasure_commitment = Some(blake3::Hash::from_hex(h)?);
+                    continue
+                }
+            }
+
             let chunk_hash = blake3::Hash::from_hex(line)?;
             read_chunks.push(chunk_hash);
         }
 
-        Ok(read_chunks)
+        let Some((layout, size)) = header else {
+            return Ok(FileMetadata { chunk_hashes: read_chunks, erasure: None, erasure_commitment })
+        };
+
+        // The file size gives the number of data chunks and stripes
+        let data_chunks = size.div_ceil(MAX_CHUNK_SIZE as u64) as usize;
+        let parity_chunks = data_chunks.div_ceil(layout.data_chunks) * layout.parity_chunks;
+        if read_chunks.len() != data_chunks + parity_chunks {
+            return Err(Error::ParseFailed("Invalid erasure coded file metadata"))
+        }
+
+        let parity_hashes = read_chunks.split_off(data_chunks);
+        let erasure = ErasureMetadata { layout, size, parity_hashes };
+        Ok(FileMetadata {
+            chunk_hashes: read_chunks,
+            erasure: Some(erasure),
+            erasure_commitment: None,
+        })
+    }
+
+    /// Parse the `<k> <m> <file_size>` erasure coded file metadata header.
+    fn parse_erasure_header(header: &str) -> Result<(ErasureLayout, u64)> {
+        let fields: Vec<&str> = header.split(' ').collect();
+        let [data_chunks, parity_chunks, size] = fields.as_slice() else {
+            return Err(Error::ParseFailed("Invalid erasure coded file metadata header"))
+        };
+
+        let layout = ErasureLayout {
+            data_chunks: data_chunks.parse()?,
+            parity_chunks: parity_chunks.parse()?,
+        };
+        ReedSolomon::new(layout.data_chunks, layout.parity_chunks)?;
+
+        Ok((layout, size.parse()?))
+    }
+
+    /// Write file metadata to a given file path, overwriting it.
+    async fn write_metadata(path: &PathBuf, metadata: &FileMetadata) -> Result<()> {
+        let mut file_fd = File::create(path).await?;
+
+        let mut parity_hashes: &[blake3::Hash] = &[];
+        if let Some(erasure) = &metadata.erasure {
+            let layout = &erasure.layout;
+            let header = format!(
+                "{}{} {} {}\n",
+                ERASURE_HEADER, layout.data_chunks, layout.parity_chunks, erasure.size,
+            );
+            file_fd.write_all(header.as_bytes()).await?;
+            parity_hashes = &erasure.parity_hashes;
+        } else if let Some(commitment) = &metadata.erasure_commitment {
+            let header = format!("{}{}\n", ERASURE_COMMITMENT_HEADER, commitment.to_hex().as_str());
+            file_fd.write_all(header.as_bytes()).await?;
+        }
+
+        for ch in metadata.chunk_hashes.iter().chain(parity_hashes) {
+            file_fd.write_all(format!("{}\n", ch.to_hex().as_str()).as_bytes()).await?;
+        }
+
+        // Writes are buffered and lost if not flushed before dropping
+        file_fd.flush().await?;
+
+        Ok(())
+    }
+
+    /// Attempt to read a download state from a given file path.
+    /// The first line holds the number of chunks in the file, and every
+    /// following line a `<index> <chunk_hash>` pair of a verified chunk.
+    /// A truncated last line, left by an interrupted write, is ignored.
+    async fn read_download(path: &PathBuf) -> Result<Download> {
+        debug!(target: "geode::read_download()", "Reading download state from {:?}", path);
+        let fd = File::open(path).await?;
+        let mut lines = BufReader::new(fd).lines();
+
+        let Some(header) = lines.next().await else {
+            return Err(Error::ParseFailed("Empty download state"))
+        };
+        let Ok(n_chunks) = header?.parse::<usize>() else {
+            return Err(Error::ParseFailed("Invalid download state header"))
+        };
+
+        let mut chunks = vec![None; n_chunks];
+        while let Some(line) = lines.next().await {
+            let line = line?;
+            let Some((index, chunk_hash)) = line.split_once(' ') else { continue };
+            let (Ok(index), Ok(chunk_hash)) =
+                (index.parse::<usize>(), blake3::Hash::from_hex(chunk_hash))
+            else {
+                continue
+            };
+            if index < n_chunks {
+                chunks[index] = Some(chunk_hash);
+            }
+        }
+
+        Ok(Download(chunks))
+    }
+
+    /// Read a chunk from `stream` into `buf`, filling it unless the
+    /// stream ends. Returns the number of bytes read.
+    async fn read_chunk(stream: &mut (impl AsyncRead + Unpin), buf: &mut [u8]) -> Result<usize> {
+        let mut total = 0;
+        while total < buf.len() {
+            let bytes_read = stream.read(&mut buf[total..]).await?;
+            if bytes_read == 0 {
+                break
+            }
+            total += bytes_read;
+        }
+
+        Ok(total)
+    }
+
+    /// Write a chunk to the filesystem. The chunk is written to a temporary
+    /// file first and then renamed, so an interrupted write never leaves a
+    /// truncated chunk behind.
+    async fn write_chunk(&self, chunk_hash: &blake3::Hash, chunk: &[u8]) -> Result<PathBuf> {
+        let mut chunk_path = self.chunks_path.clone();
+        chunk_path.push(chunk_hash.to_hex().as_str());
+        let tmp_path = chunk_path.with_extension("part");
+
+        let mut chunk_fd = File::create(&tmp_path).await?;
+        chunk_fd.write_all(chunk).await?;
+        chunk_fd.sync_all().await?;
+        fs::rename(&tmp_path, &chunk_path).await?;
+
+        Ok(chunk_path)
+    }
+
+    /// Read a chunk from the filesystem, returning `None` if it is
+    /// unavailable or corrupted.
+    async fn read_local_chunk(&self, chunk_hash: &blake3::Hash) -> Option<Vec<u8>> {
+        let mut chunk_path = self.chunks_path.clone();
+        chunk_path.push(chunk_hash.to_hex().as_str());
+
+        let mut buf = vec![0u8; MAX_CHUNK_SIZE];
+        let mut chunk_fd = File::open(&chunk_path).await.ok()?;
+        let bytes_read = Self::read_chunk(&mut chunk_fd, &mut buf).await.ok()?;
+        buf.truncate(bytes_read);
+
+        if &blake3::hash(&buf) != chunk_hash {
+            return None
+        }
+
+        Some(buf)
+    }
+
+    /// Compute and write the parity chunks of a stripe of data chunks.
+    /// Shorter chunks are zero-padded to the length of the first one.
+    /// Returns the parity chunk hashes.
+    async fn write_parity(
+        &self,
+        stripe: &[Vec<u8>],
+        parity_chunks: usize,
+    ) -> Result<Vec<blake3::Hash>> {
+        let rs = ReedSolomon::new(stripe.len(), parity_chunks)?;
+
+        let shard_len = stripe[0].len();
+        let padded: Vec<Vec<u8>> = stripe
+            .iter()
+            .map(|chunk| {
+                let mut chunk = chunk.clone();
+                chunk.resize(shard_len, 0);
+                chunk
+            })
+            .collect();
+        let data: Vec<&[u8]> = padded.iter().map(|chunk| chunk.as_slice()).collect();
+
+        let mut parity_hashes = vec![];
+        for parity in rs.encode(&data)? {
+            let parity_hash = blake3::hash(&parity);
+            self.write_chunk(&parity_hash, &parity).await?;
+            parity_hashes.push(parity_hash);
+        }
+
+        Ok(parity_hashes)
+    }
+
+    /// Rebuild the missing chunks of an erasure coded file from the
+    /// available chunks of their stripes. Rebuilt data chunks are
+    /// verified against their hash and written to the filesystem,
+    /// and missing parity chunks are restored along the way.
+    async fn repair(&self, chunked_file: &mut ChunkedFile, erasure: &ErasureMetadata) {
+        let data_chunks = erasure.layout.data_chunks;
+        let parity_chunks = erasure.layout.parity_chunks;
+        let chunk_len = |index: usize| {
+            let offset = (index * MAX_CHUNK_SIZE) as u64;
+            erasure.size.saturating_sub(offset).min(MAX_CHUNK_SIZE as u64) as usize
+        };
+
+        for (stripe, start) in (0..chunked_file.len()).step_by(data_chunks).enumerate() {
+            let end = (start + data_chunks).min(chunked_file.len());
+            if chunked_file.0[start..end].iter().all(|(_, path)| path.is_some()) {
+                continue
+            }
+
+            let parity_hashes =
+                &erasure.parity_hashes[stripe * parity_chunks..(stripe + 1) * parity_chunks];
+            let stripe_hashes: Vec<blake3::Hash> = chunked_file.0[start..end]
+                .iter()
+                .map(|(hash, _)| *hash)
+                .chain(parity_hashes.iter().copied())
+                .collect();
+
+            // Data chunks are padded to the length of the stripe's
+            // first chunk, as they were when encoding.
+            let shard_len = chunk_len(start);
+            let mut shards = vec![];
+            for hash in &stripe_hashes {
+                let shard = self.read_local_chunk(hash).await.and_then(|mut chunk| {
+                    if chunk.len() > shard_len {
+                        return None
+                    }
+                    chunk.resize(shard_len, 0);
+                    Some(chunk)
+                });
+                shards.push(shard);
+            }
+            let missing: Vec<bool> = shards.iter().map(|shard| shard.is_none()).collect();
+
+            let rs = match ReedSolomon::new(end - start, parity_chunks) {
+                Ok(v) => v,
+                Err(_) => continue,
+            };
+
+            if let Err(e) = rs.reconstruct(&mut shards) {
+                warn!(
+                    target: "geode::repair()",
+                    "[Geode] Failed rebuilding stripe {}: {}", stripe, e,
+                );
+                continue
+            }
+
+            for (i, shard) in shards.into_iter().enumerate() {
+                if !missing[i] {
+                    continue
+                }
+
+                let mut chunk = shard.unwrap();
+                if i < end - start {
+                    chunk.truncate(chunk_len(start + i));
+                }
+
+                let hash = &stripe_hashes[i];
+                if &blake3::hash(&chunk) != hash {
+                    warn!(
+                        target: "geode::repair()",
+                        "[Geode] Rebuilt chunk {} does not match its hash", hash,
+                    );
+                    continue
+                }
+
+                let chunk_path = match self.write_chunk(hash, &chunk).await {
+                    Ok(v) => v,
+                    Err(e) => {
+                        warn!(
+                            target: "geode::repair()",
+                            "[Geode] Failed writing rebuilt chunk {}: {}", hash, e,
+                        );
+                        continue
+                    }
+                };
+
+                debug!(target: "geode::repair()", "Rebuilt chunk {}", hash);
+                if i < end - start {
+                    chunked_file.0[start + i].1 = Some(chunk_path);
+                }
+            }
+        }
     }
 
     /// Perform garbage collection over the filesystem hierarchy.
@@ -165,6 +702,12 @@ impl Geode {
                 continue
             }
 
+            // Remove leftovers of interrupted chunk writes
+            if chunk_path.extension().is_some_and(|ext| ext == "part") {
+                deleted_chunk_paths.insert(chunk_path);
+                continue
+            }
+
             // Make sure that the filename is a BLAKE3 hash
             let file_name = match chunk_path.file_name().and_then(|n| n.to_str()) {
                 Some(v) => v,
@@ -183,7 +726,7 @@ impl Geode {
             };
 
             // Perform consistency check
-            let Ok(bytes_read) = chunk_fd.read(&mut buf).await else {
+            let Ok(bytes_read) = Self::read_chunk(&mut chunk_fd, &mut buf).await else {
                 deleted_chunk_paths.insert(chunk_path);
                 deleted_chunks.insert(chunk_hash);
                 buf = [0u8; MAX_CHUNK_SIZE];
@@ -214,8 +757,8 @@ impl Geode {
             }
         }
 
-        // Perform health check over file metadata. For now we just ensure they
-        // have the correct format.
+        // Perform health check over file metadata. We ensure they have the
+        // correct format and that the chunks match the file hash.
         let mut file_paths = fs::read_dir(&self.files_path).await?;
         while let Some(file) = file_paths.next().await {
             let Ok(entry) = file else { continue };
@@ -237,9 +780,29 @@ impl Geode {
             };
 
             // The filename is a BLAKE3 hash. It should contain a newline-separated
-            // list of chunks which represent the full file. If that is not the case
-            // we will consider it a corrupted file and delete it.
-            if Self::read_metadata(&path).await.is_err() {
+            // list of chunks which represent the full file, optionally preceded by
+            // an erasure coding header and followed by the parity chunks. If that
+            // is not the case we will consider it a corrupted file and delete it.
+            // Legacy metadata is kept, unless its chunks prove it corrupted.
+            let valid = match Self::read_metadata(&path).await {
+                Ok(metadata) if metadata.file_hash() == file_hash => true,
+                Ok(metadata) => match self.is_legacy_metadata(&file_hash, &metadata).await {
+                    Some(true) => {
+                        if let Err(e) = Self::migrate_metadata(&path, &metadata).await {
+                            warn!(
+                               target: "geode::garbage_collect()",
+                               "[Geode] Garbage collect failed to migrate file: {}", e,
+                            );
+                        }
+                        continue
+                    }
+                    Some(false) => false,
+                    None => true,
+                },
+                Err(_) => false,
+            };
+
+            if !valid {
                 if let Err(e) = fs::remove_file(path).await {
                     warn!(
                        target: "geode::garbage_collect()",
@@ -252,6 +815,36 @@ impl Geode {
             }
         }
 
+        // Remove download states that are corrupted, or whose file
+        // metadata has already been written.
+        let mut download_paths = fs::read_dir(&self.downloads_path).await?;
+        while let Some(download) = download_paths.next().await {
+            let Ok(entry) = download else { continue };
+            let path = entry.path();
+
+            // Skip if we're not a plain file
+            if !path.is_file() {
+                continue
+            }
+
+            let Some(file_name) = path.file_name().and_then(|n| n.to_str()) else { continue };
+            if blake3::Hash::from_hex(file_name).is_err() {
+                continue
+            }
+
+            let mut file_path = self.files_path.clone();
+            file_path.push(file_name);
+
+            if Self::read_download(&path).await.is_err() || file_path.is_file() {
+                if let Err(e) = fs::remove_file(path).await {
+                    warn!(
+                       target: "geode::garbage_collect()",
+                       "[Geode] Garbage collect failed to remove download state: {}", e,
+                    );
+                }
+            }
+        }
+
         info!(target: "geode::garbage_collect()", "[Geode] Garbage collection finished");
         Ok((deleted_files, deleted_chunks))
     }
@@ -262,21 +855,51 @@ impl Geode {
     /// file name, and the file's chunks, respectively.
     pub async fn insert(
         &self,
-        mut stream: impl AsyncRead + Unpin,
+        stream: impl AsyncRead + Unpin,
     ) -> Result<(blake3::Hash, Vec<blake3::Hash>)> {
         info!(target: "geode::insert()", "[Geode] Inserting file...");
-        let mut file_hasher = blake3::Hasher::new();
+        self.insert_with_layout(stream, None).await
+    }
+
+    /// Insert a file into Geode with the given Reed-Solomon [`ErasureLayout`].
+    /// The file hash and data chunks are the same as with [`Geode::insert`],
+    /// and the parity chunks are stored alongside them.
+    pub async fn insert_erasure_coded(
+        &self,
+        stream: impl AsyncRead + Unpin,
+        layout: ErasureLayout,
+    ) -> Result<(blake3::Hash, Vec<blake3::Hash>)> {
+        info!(
+            target: "geode::insert_erasure_coded()",
+            "[Geode] Inserting file with {} data and {} parity chunks per stripe...",
+            layout.data_chunks, layout.parity_chunks,
+        );
+        ReedSolomon::new(layout.data_chunks, layout.parity_chunks)?;
+        self.insert_with_layout(stream, Some(layout)).await
+    }
+
+    async fn insert_with_layout(
+        &self,
+        mut stream: impl AsyncRead + Unpin,
+        layout: Option<ErasureLayout>,
+    ) -> Result<(blake3::Hash, Vec<blake3::Hash>)> {
         let mut chunk_hashes = vec![];
         let mut buf = [0u8; MAX_CHUNK_SIZE];
 
-        while let Ok(bytes_read) = stream.read(&mut buf).await {
+        // Erasure coding state
+        let mut size = 0;
+        let mut stripe = vec![];
+        let mut parity_hashes = vec![];
+
+        loop {
+            // Chunks must be filled, so byte offsets map to chunk indexes
+            let bytes_read = Self::read_chunk(&mut stream, &mut buf).await?;
             if bytes_read == 0 {
                 break
             }
 
             let chunk_slice = &buf[..bytes_read];
             let chunk_hash = blake3::hash(chunk_slice);
-            file_hasher.update(chunk_slice);
             chunk_hashes.push(chunk_hash);
 
             // Write the chunk to a file, if necessary. We first perform
@@ -289,7 +912,7 @@ impl Geode {
                 OpenOptions::new().read(true).write(true).create(true).open(&chunk_path).await?;
 
             let mut fs_buf = [0u8; MAX_CHUNK_SIZE];
-            let fs_bytes_read = chunk_fd.read(&mut fs_buf).await?;
+            let fs_bytes_read = Self::read_chunk(&mut chunk_fd, &mut fs_buf).await?;
             let fs_chunk_slice = &fs_buf[..fs_bytes_read];
             let fs_chunk_hash = blake3::hash(fs_chunk_slice);
 
@@ -303,6 +926,7 @@ impl Geode {
                 chunk_fd.set_len(0).await?;
                 chunk_fd.seek(SeekFrom::Start(0)).await?;
                 chunk_fd.write_all(chunk_slice).await?;
+                chunk_fd.flush().await?;
             } else {
                 debug!(
                     target: "geode::insert()",
@@ -311,25 +935,42 @@ impl Geode {
                 );
             }
 
+            size += bytes_read as u64;
+            if let Some(layout) = layout {
+                stripe.push(chunk_slice.to_vec());
+                if stripe.len() == layout.data_chunks {
+                    parity_hashes.extend(self.write_parity(&stripe, layout.parity_chunks).await?);
+                    stripe.clear();
+                }
+            }
+
             buf = [0u8; MAX_CHUNK_SIZE];
         }
 
-        // This hash is the file's chunks hashed in order.
-        let file_hash = file_hasher.finalize();
+        // The last stripe may hold fewer data chunks
+        if let Some(layout) = layout {
+            if !stripe.is_empty() {
+                parity_hashes.extend(self.write_parity(&stripe, layout.parity_chunks).await?);
+            }
+        }
+
+        // This hash is the Merkle root of the file's chunks in order, and
+        // the commitment to the parity chunks of erasure coded files.
+        let erasure = layout.map(|layout| ErasureMetadata { layout, size, parity_hashes });
+        let metadata = FileMetadata { chunk_hashes, erasure, erasure_commitment: None };
+        let file_hash = metadata.file_hash();
         let mut file_path = self.files_path.clone();
         file_path.push(file_hash.to_hex().as_str());
 
         // We always overwrite the metadata.
-        let mut file_fd = File::create(&file_path).await?;
-        for ch in &chunk_hashes {
-            file_fd.write(format!("{}\n", ch.to_hex().as_str()).as_bytes()).await?;
-        }
+        Self::write_metadata(&file_path, &metadata).await?;
 
-        Ok((file_hash, chunk_hashes))
+        Ok((file_hash, metadata.chunk_hashes))
     }
 
     /// Create and insert file metadata into Geode given a list of hashes.
-    /// Always overwrites any existing file.
+    /// Always overwrites any existing file. Returns an error if the chunk
+    /// hashes don't match the file hash.
     pub async fn insert_file(
         &self,
         file_hash: &blake3::Hash,
@@ -337,15 +978,18 @@ impl Geode {
     ) -> Result<()> {
         info!(target: "geode::insert_file()", "[Geode] Inserting file metadata");
 
-        let mut file_path = self.files_path.clone();
-        file_path.push(file_hash.to_hex().as_str());
-        let mut file_fd = File::create(&file_path).await?;
-
-        for ch in chunk_hashes {
-            file_fd.write(format!("{}\n", ch.to_hex().as_str()).as_bytes()).await?;
+        if &merkle_root(chunk_hashes) != file_hash {
+            return Err(Error::GeodeFileHashMismatch)
         }
 
-        Ok(())
+        let mut file_path = self.files_path.clone();
+        file_path.push(file_hash.to_hex().as_str());
+        let metadata = FileMetadata {
+            chunk_hashes: chunk_hashes.to_vec(),
+            erasure: None,
+            erasure_commitment: None,
+        };
+        Self::write_metadata(&file_path, &metadata).await
     }
 
     /// Create and insert a single chunk into Geode given a stream.
@@ -359,15 +1003,93 @@ impl Geode {
         let bytes_read = cursor.read(&mut chunk).await?;
         let chunk_slice = &chunk[..bytes_read];
         let chunk_hash = blake3::hash(chunk_slice);
-
-        let mut chunk_path = self.chunks_path.clone();
-        chunk_path.push(chunk_hash.to_hex().as_str());
-        let mut chunk_fd = File::create(&chunk_path).await?;
-        chunk_fd.write_all(chunk_slice).await?;
+        self.write_chunk(&chunk_hash, chunk_slice).await?;
 
         Ok(chunk_hash)
     }
 
+    /// Insert a single chunk of the file `file_hash` into Geode, verifying
+    /// it with its inclusion proof first. Unless the file metadata is already
+    /// known, the chunk is recorded in the file's download state, and once all
+    /// chunks are there the file metadata is written. Returns the download
+    /// progress, or `None` if the file metadata is known.
+    pub async fn insert_chunk_verified(
+        &self,
+        file_hash: &blake3::Hash,
+        proof: &ChunkProof,
+        chunk: &[u8],
+    ) -> Result<Option<Download>> {
+        info!(
+            target: "geode::insert_chunk_verified()",
+            "[Geode] Inserting chunk {} of file {}", proof.index, file_hash,
+        );
+
+        // Only the last chunk may be shorter than MAX_CHUNK_SIZE
+        let is_last = proof.index + 1 == proof.chunks;
+        if chunk.len() > MAX_CHUNK_SIZE || (!is_last && chunk.len() != MAX_CHUNK_SIZE) {
+            return Err(Error::GeodeInvalidProof)
+        }
+
+        let chunk_hash = blake3::hash(chunk);
+        if !proof.verify(&chunk_hash, file_hash) {
+            return Err(Error::GeodeInvalidProof)
+        }
+
+        self.write_chunk(&chunk_hash, chunk).await?;
+
+        let mut file_path = self.files_path.clone();
+        file_path.push(file_hash.to_hex().as_str());
+        if file_path.is_file() {
+            return Ok(None)
+        }
+
+        let mut download_path = self.downloads_path.clone();
+        download_path.push(file_hash.to_hex().as_str());
+
+        // The chunk count is bound to the file hash by the proof, so a
+        // stored state disagreeing with it is corrupted and started over.
+        let mut download = match Self::read_download(&download_path).await {
+            Ok(v) if v.len() as u64 == proof.chunks => v,
+            _ => {
+                let mut fd = File::create(&download_path).await?;
+                fd.write_all(format!("{}\n", proof.chunks).as_bytes()).await?;
+                fd.flush().await?;
+                Download(vec![None; proof.chunks as usize])
+            }
+        };
+
+        let mut fd = OpenOptions::new().append(true).open(&download_path).await?;
+        fd.write_all(format!("{} {}\n", proof.index, chunk_hash.to_hex().as_str()).as_bytes())
+            .await?;
+        fd.sync_all().await?;
+        download.0[proof.index as usize] = Some(chunk_hash);
+
+        if download.is_complete() {
+            // Erasure coded files are stored without their parity chunks,
+            // keeping the commitment the proofs were verified against.
+            let chunk_hashes: Vec<blake3::Hash> = download.0.iter().flatten().copied().collect();
+            let metadata =
+                FileMetadata { chunk_hashes, erasure: None, erasure_commitment: proof.erasure };
+            Self::write_metadata(&file_path, &metadata).await?;
+            fs::remove_file(&download_path).await?;
+        }
+
+        Ok(Some(download))
+    }
+
+    /// Fetch the download state of a file being downloaded chunk by chunk.
+    /// Returns `None` if there is no download in progress.
+    pub async fn get_download(&self, file_hash: &blake3::Hash) -> Result<Option<Download>> {
+        let mut download_path = self.downloads_path.clone();
+        download_path.push(file_hash.to_hex().as_str());
+
+        match Self::read_download(&download_path).await {
+            Ok(v) => Ok(Some(v)),
+            Err(Error::Io(std::io::ErrorKind::NotFound)) => Ok(None),
+            Err(_) => Err(Error::GeodeNeedsGc),
+        }
+    }
+
     /// Fetch file metadata from Geode. Returns [`ChunkedFile`] which gives a list
     /// of chunks and optionally file paths to the said chunks. Returns an error if
     /// the read failed in any way (could also be the file does not exist).
@@ -378,7 +1100,7 @@ impl Geode {
 
         // Try to read the file metadata. If it's corrupt, return an error signalling
         // that garbage collection needs to run.
-        let chunk_hashes = match Self::read_metadata(&file_path).await {
+        let metadata = match Self::read_metadata(&file_path).await {
             Ok(v) => v,
             Err(e) => {
                 return match e {
@@ -390,7 +1112,7 @@ impl Geode {
             }
         };
 
-        let mut chunked_file = ChunkedFile::new(&chunk_hashes);
+        let mut chunked_file = ChunkedFile::new(&metadata.chunk_hashes);
 
         // Iterate over chunks and find which chunks we have available locally.
         let mut buf = [0u8; MAX_CHUNK_SIZE];
@@ -405,7 +1127,7 @@ impl Geode {
 
             // Perform chunk consistency check
             let mut chunk_fd = File::open(&c_path).await?;
-            let bytes_read = chunk_fd.read(&mut buf).await?;
+            let bytes_read = Self::read_chunk(&mut chunk_fd, &mut buf).await?;
             let chunk_slice = &buf[..bytes_read];
             let hashed_chunk = blake3::hash(chunk_slice);
             if &hashed_chunk != chunk_hash {
@@ -418,6 +1140,13 @@ impl Geode {
             buf = [0u8; MAX_CHUNK_SIZE];
         }
 
+        // Rebuild missing chunks of erasure coded files
+        if let Some(erasure) = &metadata.erasure {
+            if !chunked_file.is_complete() {
+                self.repair(&mut chunked_file, erasure).await;
+            }
+        }
+
         Ok(chunked_file)
     }
 
@@ -436,7 +1165,7 @@ impl Geode {
         // Perform chunk consistency check
         let mut buf = [0u8; MAX_CHUNK_SIZE];
         let mut chunk_fd = File::open(&chunk_path).await?;
-        let bytes_read = chunk_fd.read(&mut buf).await?;
+        let bytes_read = Self::read_chunk(&mut chunk_fd, &mut buf).await?;
         let chunk_slice = &buf[..bytes_read];
         let hashed_chunk = blake3::hash(chunk_slice);
         if &hashed_chunk != chunk_hash {
@@ -446,4 +1175,206 @@ impl Geode {
 
         Ok(chunk_path)
     }
+
+    /// Fetch the chunk at `index` of a file from Geode, along with its
+    /// inclusion proof. Returns a `PathBuf` pointing to the chunk if found.
+    pub async fn get_chunk_with_proof(
+        &self,
+        file_hash: &blake3::Hash,
+        index: u64,
+    ) -> Result<(PathBuf, ChunkProof)> {
+        info!(
+            target: "geode::get_chunk_with_proof()",
+            "[Geode] Getting chunk {} of file {}", index, file_hash,
+        );
+        let mut file_path = self.files_path.clone();
+        file_path.push(file_hash.to_hex().as_str());
+
+        let metadata = match Self::read_metadata(&file_path).await {
+            Ok(v) => v,
+            Err(Error::Io(std::io::ErrorKind::NotFound)) => return Err(Error::GeodeFileNotFound),
+            Err(_) => return Err(Error::GeodeNeedsGc),
+        };
+        let chunk_hashes = &metadata.chunk_hashes;
+
+        let Some(mut proof) = ChunkProof::new(chunk_hashes, index as usize) else {
+            return Err(Error::GeodeChunkNotFound)
+        };
+        proof.erasure = metadata.erasure_commitment();
+
+        let chunk_path = self.get_chunk(&chunk_hashes[index as usize]).await?;
+        Ok((chunk_path, proof))
+    }
+
+    /// Read `len` bytes of a file starting at `offset`. Only the chunks
+    /// holding the range need to be available locally, and the range is
+    /// clamped to the end of the file.
+    pub async fn read_range(
+        &self,
+        file_hash: &blake3::Hash,
+        offset: u64,
+        len: u64,
+    ) -> Result<Vec<u8>> {
+        info!(
+            target: "geode::read_range()",
+            "[Geode] Reading {} bytes at {} from file {}", len, offset, file_hash,
+        );
+        let chunked_file = self.get(file_hash).await?;
+        let range = chunked_file.chunk_range(offset, len);
+
+        let mut data = vec![];
+        let mut buf = vec![0u8; MAX_CHUNK_SIZE];
+        for index in range.clone() {
+            let Some(ref chunk_path) = chunked_file.0[index].1 else {
+                return Err(Error::GeodeChunkNotFound)
+            };
+
+            let mut chunk_fd = File::open(chunk_path).await?;
+            let bytes_read = Self::read_chunk(&mut chunk_fd, &mut buf).await?;
+            data.extend_from_slice(&buf[..bytes_read]);
+        }
+
+        // Trim the data to the requested range
+        let chunk_start = (range.start * MAX_CHUNK_SIZE) as u64;
+        let skip = (offset.saturating_sub(chunk_start) as usize).min(data.len());
+        let take = (len as usize).min(data.len() - skip);
+        Ok(data[skip..skip + take].to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn geode_legacy_metadata() -> Result<()> {
+        smol::block_on(async {
+            let base_path = std::env::temp_dir().join("geode_legacy_metadata");
+            let _ = fs::remove_dir_all(&base_path).await;
+            let geode = Geode::new(&base_path).await?;
+
+            // Write a file in the legacy format, named by the BLAKE3 hash
+            // of its contents, without going through Geode::insert().
+            let data: Vec<u8> = (0..MAX_CHUNK_SIZE * 2 + 1).map(|i| i as u8).collect();
+            let mut chunk_hashes = vec![];
+            for chunk in data.chunks(MAX_CHUNK_SIZE) {
+                chunk_hashes.push(geode.insert_chunk(chunk).await?);
+            }
+            let legacy_hash = blake3::hash(&data);
+            let mut legacy_path = geode.files_path.clone();
+            legacy_path.push(legacy_hash.to_hex().as_str());
+            Geode::write_metadata(&legacy_path, &metadata_of(&chunk_hashes)).await?;
+
+            // Legacy metadata of a file whose chunks aren't available is kept
+            let missing_hash = blake3::hash(b"missing file");
+            let mut missing_path = geode.files_path.clone();
+            missing_path.push(missing_hash.to_hex().as_str());
+            Geode::write_metadata(&missing_path, &metadata_of(&[blake3::hash(b"missing")])).await?;
+
+            // Garbage collection rewrites it under the Merkle root
+            let (deleted_files, _) = geode.garbage_collect().await?;
+            assert!(deleted_files.is_empty());
+            let file_hash = merkle_root(&chunk_hashes);
+            assert!(!legacy_path.exists());
+            let chunked_file = geode.get(&file_hash).await?;
+            assert!(chunked_file.is_complete());
+            assert_eq!(chunked_file.iter().map(|(h, _)| *h).collect::<Vec<_>>(), chunk_hashes);
+            assert_eq!(geode.get(&missing_hash).await?.len(), 1);
+
+            // Opening the store migrates it as well
+            Geode::write_metadata(&legacy_path, &metadata_of(&chunk_hashes)).await?;
+            fs::remove_file(geode.files_path.join(file_hash.to_hex().as_str())).await?;
+            let geode = Geode::new(&base_path).await?;
+            assert!(!legacy_path.exists());
+            assert!(geode.get(&file_hash).await?.is_complete());
+
+            // Metadata whose chunks don't match its legacy hash is deleted
+            let bogus_hash = blake3::hash(b"bogus");
+            let mut bogus_path = geode.files_path.clone();
+            bogus_path.push(bogus_hash.to_hex().as_str());
+            Geode::write_metadata(&bogus_path, &metadata_of(&chunk_hashes)).await?;
+            let (deleted_files, _) = geode.garbage_collect().await?;
+            assert_eq!(deleted_files, HashSet::from([bogus_hash]));
+
+            let _ = fs::remove_dir_all(&base_path).await;
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn geode_erasure_commitment() -> Result<()> {
+        // Geode futures hold chunk buffers, so keep them off the test thread stack
+        smol::block_on(Box::pin(async {
+            let base_path = std::env::temp_dir().join("geode_erasure_commitment");
+            let _ = fs::remove_dir_all(&base_path).await;
+            let geode = Geode::new(&base_path.join("a")).await?;
+
+            let data: Vec<u8> = (0..MAX_CHUNK_SIZE * 3 + 1).map(|i| (i / 7) as u8).collect();
+            let layout = ErasureLayout { data_chunks: 2, parity_chunks: 1 };
+            let (file_hash, chunk_hashes) =
+                geode.insert_erasure_coded(Cursor::new(&data), layout).await?;
+            assert_ne!(file_hash, merkle_root(&chunk_hashes));
+
+            // Forging a parity chunk hash changes the file hash, so garbage
+            // collection removes the forged metadata.
+            let file_path = geode.files_path.join(file_hash.to_hex().as_str());
+            let mut metadata = Geode::read_metadata(&file_path).await?;
+            metadata.erasure.as_mut().unwrap().parity_hashes[0] = blake3::hash(b"forged");
+            assert_ne!(metadata.file_hash(), file_hash);
+            Geode::write_metadata(&file_path, &metadata).await?;
+            let (deleted_files, _) = geode.garbage_collect().await?;
+            assert_eq!(deleted_files, HashSet::from([file_hash]));
+
+            // Download the data chunks into another store with their proofs
+            let (file_hash, chunk_hashes) =
+                geode.insert_erasure_coded(Cursor::new(&data), layout).await?;
+            let other = Geode::new(&base_path.join("b")).await?;
+            for index in 0..chunk_hashes.len() as u64 {
+                let (chunk_path, proof) = geode.get_chunk_with_proof(&file_hash, index).await?;
+                assert_eq!(
+                    proof.erasure,
+                    Geode::read_metadata(&file_path).await?.erasure_commitment()
+                );
+                let chunk = fs::read(chunk_path).await?;
+
+                // A proof without the commitment doesn't verify
+                let mut bad_proof = proof.clone();
+                bad_proof.erasure = None;
+                assert!(other.insert_chunk_verified(&file_hash, &bad_proof, &chunk).await.is_err());
+
+                other.insert_chunk_verified(&file_hash, &proof, &chunk).await?;
+            }
+
+            // The downloaded copy keeps the commitment and survives garbage collection
+            let (deleted_files, _) = other.garbage_collect().await?;
+            assert!(deleted_files.is_empty());
+            assert_eq!(other.read_range(&file_hash, 0, u64::MAX).await?, data);
+            let (_, proof) = other.get_chunk_with_proof(&file_hash, 1).await?;
+            assert!(proof.verify(&chunk_hashes[1], &file_hash));
+
+            let _ = fs::remove_dir_all(&base_path).await;
+            Ok(())
+        }))
+    }
+
+    fn metadata_of(chunk_hashes: &[blake3::Hash]) -> FileMetadata {
+        FileMetadata {
+            chunk_hashes: chunk_hashes.to_vec(),
+            erasure: None,
+            erasure_commitment: None,
+        }
+    }
+
+    #[test]
+    fn geode_chunk_range() {
+        let size = MAX_CHUNK_SIZE as u64;
+        assert_eq!(chunk_range(0, 0, 4), 0..0);
+        assert_eq!(chunk_range(0, 1, 4), 0..1);
+        assert_eq!(chunk_range(0, size, 4), 0..1);
+        assert_eq!(chunk_range(size - 1, 2, 4), 0..2);
+        assert_eq!(chunk_range(size, size * 2, 4), 1..3);
+        // Clamped to the end of the file
+        assert_eq!(chunk_range(size * 3, u64::MAX, 4), 3..4);
+        assert_eq!(chunk_range(size * 8, 1, 4), 4..4);
+    }
 }
@@ -30,6 +30,9 @@ pub mod validator;
 #[cfg(feature = "geode")]
 pub mod geode;
 
+#[cfg(feature = "dht")]
+pub mod dht;
+
 #[cfg(feature = "event-graph")]
 pub mod event_graph;
 
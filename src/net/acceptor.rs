@@ -70,10 +70,14 @@ impl Acceptor {
         let listener = Listener::new(endpoint.clone(), datastore).await?;
 
         // Open socket
-        let ptlistener = listener.listen().await?;
+        let ptlistener = listener.listen(ex.clone()).await?;
+
+        if endpoint.scheme().ends_with("+noise") {
+            info!("[P2P] Noise listener endpoint: {}", listener.endpoint().await);
+        }
 
         #[cfg(feature = "p2p-tor")]
-        if endpoint.scheme() == "tor" {
+        if endpoint.scheme() == "tor" || endpoint.scheme() == "tor+noise" {
             let onion_addr = listener.endpoint().await;
             info!("[P2P] Adding {} to external_addrs", onion_addr);
             self.session
@@ -87,6 +91,21 @@ impl Acceptor {
                 .push(onion_addr);
         }
 
+        #[cfg(feature = "p2p-i2p")]
+        if endpoint.scheme() == "i2p" {
+            let i2p_addr = listener.endpoint().await;
+            info!("[P2P] Adding {} to external_addrs", i2p_addr);
+            self.session
+                .upgrade()
+                .unwrap()
+                .p2p()
+                .settings()
+                .write()
+                .await
+                .external_addrs
+                .push(i2p_addr);
+        }
+
         self.accept(ptlistener, ex);
         Ok(())
     }
@@ -225,6 +244,11 @@ impl Acceptor {
                 // In case a TLS handshake fails, we'll get this:
                 Err(e) if e.kind() == ErrorKind::UnexpectedEof => continue,
 
+                // In case a Noise handshake fails or times out, we'll get these:
+                Err(e) if e.kind() == ErrorKind::InvalidData || e.kind() == ErrorKind::TimedOut => {
+                    continue
+                }
+
                 // Handle ErrorKind::Other
                 Err(e) if e.kind() == ErrorKind::Other => {
                     if let Some(inner) = std::error::Error::source(&e) {
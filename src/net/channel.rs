@@ -17,10 +17,11 @@
  */
 
 use std::{
+    collections::HashMap,
     fmt,
     sync::{
         atomic::{AtomicBool, Ordering::SeqCst},
-        Arc,
+        Arc, Mutex,
     },
     time::UNIX_EPOCH,
 };
@@ -33,7 +34,7 @@ use rand::{rngs::OsRng, Rng};
 use smol::{
     io::{self, AsyncRead, AsyncReadExt, AsyncWriteExt, ReadHalf, WriteHalf},
     lock::{Mutex as AsyncMutex, OnceCell},
-    Executor,
+    Executor, Timer,
 };
 use url::Url;
 
@@ -43,7 +44,9 @@ use super::{
     message,
     message::{SerializedMessage, VersionMessage},
     message_publisher::{MessageSubscription, MessageSubsystem},
+    metrics::CommandMetrics,
     p2p::P2pPtr,
+    resource_manager::{ChannelResources, LimitsSnapshot, ResourceUsage, Verdict},
     session::{
         Session, SessionBitFlag, SessionWeakPtr, SESSION_ALL, SESSION_INBOUND, SESSION_REFINE,
     },
@@ -56,6 +59,9 @@ use crate::{
     Error, Result,
 };
 
+/// Maximum length of a message command name
+const MAX_COMMAND_LEN: u64 = 255;
+
 /// Atomic pointer to async channel
 pub type ChannelPtr = Arc<Channel>;
 
@@ -88,6 +94,12 @@ pub struct Channel {
     receive_task: StoppableTaskPtr,
     /// A boolean marking if this channel is stopped
     stopped: AtomicBool,
+    /// Resources used by the peer, checked against the configured limits
+    resources: ChannelResources,
+    /// Snapshot of the configured resource limits
+    limits: Mutex<Arc<LimitsSnapshot>>,
+    /// Metrics of the commands sent or received, by command
+    command_metrics: Mutex<HashMap<String, Arc<CommandMetrics>>>,
     /// Weak pointer to respective session
     pub(in crate::net) session: SessionWeakPtr,
     /// The version message of the node we are connected to.
@@ -118,6 +130,9 @@ impl Channel {
         let start_time = UNIX_EPOCH.elapsed().unwrap().as_secs();
         let info = ChannelInfo::new(resolve_addr, connect_addr.clone(), start_time);
 
+        let p2p = session.upgrade().unwrap().p2p();
+        let limits = Mutex::new(Arc::new(LimitsSnapshot::new(&p2p).await));
+
         Arc::new(Self {
             reader,
             writer,
@@ -125,6 +140,9 @@ impl Channel {
             stop_publisher: Publisher::new(),
             receive_task: StoppableTask::new(),
             stopped: AtomicBool::new(false),
+            resources: ChannelResources::new(),
+            limits,
+            command_metrics: Mutex::new(HashMap::new()),
             session,
             version: OnceCell::new(),
             info,
@@ -260,6 +278,27 @@ impl Channel {
 
         stream.flush().await?;
 
+        let metrics = self.command_metrics(&message.command);
+        metrics.messages_sent.inc();
+        metrics.bytes_sent.inc_by(written as u64);
+
+        // Hold the writer while going over our outbound bandwidth limits,
+        // so the next messages wait for the rate to catch up.
+        let limits = self.limits().await;
+        let protocol = limits.protocol(&message.command);
+        if let Some((protocol, delay)) =
+            self.resources.record_send(&limits.channel, protocol, written as u64)
+        {
+            dnetev!(self, ResourceThrottled, {
+                chan: self.info.clone(),
+                protocol,
+                resource: "bytes_out".to_string(),
+                delay: delay.as_millis() as u64,
+                time: NanoTimestamp::current_time(),
+            });
+            Timer::after(delay).await;
+        }
+
         Ok(())
     }
 
@@ -287,6 +326,10 @@ impl Channel {
 
         // First extract the length from the stream
         let cmd_len = VarInt::decode_async(stream).await?.0;
+        if cmd_len > MAX_COMMAND_LEN {
+            error!(target: "net::channel::read_command", "Error: Command length {} too large", cmd_len);
+            return Err(Error::MalformedPacket)
+        }
 
         // Then extract precisely `cmd_len` items from the stream.
         let mut take = stream.take(cmd_len);
@@ -380,8 +423,8 @@ impl Channel {
             });
 
             // Send result to our publishers
-            match self.message_subsystem.notify(&command, reader).await {
-                Ok(()) => {}
+            let bytes = match self.message_subsystem.notify(&command, reader).await {
+                Ok(bytes) => bytes,
                 Err(Error::MissingDispatcher) => {
                     // If we're getting messages without dispatchers, it's spam.
                     // We therefore ban this channel if:
@@ -412,10 +455,144 @@ impl Channel {
 
                         return Err(Error::ChannelStopped)
                     }
+
+                    continue
+                }
+                Err(Error::DecodeLimitExceeded(e)) => {
+                    // Oversized or deeply nested messages can only be sent
+                    // on purpose, so we treat them like spam. The remaining
+                    // message bytes can't be skipped, so the channel stops.
+                    warn!(
+                        target: "net::channel::main_receive_loop()",
+                        "Decoding limit exceeded for command={}, channel={:?}: {}",
+                        command, self, e,
+                    );
+
+                    if let BanPolicy::Strict = self.p2p().settings().read().await.ban_policy {
+                        self.ban().await;
+                    }
+
+                    return Err(Error::ChannelStopped)
                 }
                 Err(_) => unreachable!("You added a new error in notify()"),
+            };
+
+            // Only commands we have dispatchers for get this far, so peers
+            // can't make up new label values.
+            let metrics = self.command_metrics(&command);
+            metrics.messages_received.inc();
+            metrics.bytes_received.inc_by(bytes);
+
+            // Check the peer is within its resource limits
+            self.enforce_limits(&command, bytes).await?;
+        }
+    }
+
+    /// Account for a received message in the channel resources, and act
+    /// on the verdict. Peers exceeding their limits get throttled, and
+    /// disconnected if that happens too often. Peers that keep getting
+    /// disconnected are banned, if the `BanPolicy` is set to Strict.
+    async fn enforce_limits(&self, command: &str, bytes: u64) -> Result<()> {
+        let limits = self.limits().await;
+        let pending = self.message_subsystem.pending(command).await;
+
+        let verdict = self.resources.record_recv(
+            &limits.channel,
+            limits.protocol(command),
+            bytes,
+            pending,
+            limits.max_throttles,
+            limits.window,
+        );
+
+        match verdict {
+            Verdict::Allow => Ok(()),
+
+            Verdict::Throttle { protocol, resource, delay } => {
+                debug!(
+                    target: "net::channel::enforce_limits()",
+                    "Throttling channel {:?} for {:?}: {} {} limit exceeded",
+                    self, delay, protocol, resource,
+                );
+                dnetev!(self, ResourceThrottled, {
+                    chan: self.info.clone(),
+                    protocol,
+                    resource: resource.to_string(),
+                    delay: delay.as_millis() as u64,
+                    time: NanoTimestamp::current_time(),
+                });
+                Timer::after(delay).await;
+                Ok(())
             }
+
+            Verdict::Disconnect { protocol, resource } => {
+                warn!(
+                    target: "net::channel::enforce_limits()",
+                    "[P2P] Disconnecting channel {:?}: kept exceeding {} {} limit",
+                    self, protocol, resource,
+                );
+                dnetev!(self, ResourceDisconnected, {
+                    chan: self.info.clone(),
+                    protocol,
+                    resource: resource.to_string(),
+                    delay: 0,
+                    time: NanoTimestamp::current_time(),
+                });
+
+                let peer = match self.address().host_str() {
+                    Some(host) => host.to_string(),
+                    None => self.address().to_string(),
+                };
+                let p2p = self.p2p();
+                let manager = p2p.resource_manager();
+                if manager.record_disconnect(&peer, limits.max_disconnects, limits.window) &&
+                    limits.ban_policy == BanPolicy::Strict
+                {
+                    dnetev!(self, ResourceBanned, {
+                        chan: self.info.clone(),
+                        protocol,
+                        resource: resource.to_string(),
+                        delay: 0,
+                        time: NanoTimestamp::current_time(),
+                    });
+                    self.ban().await;
+                }
+
+                Err(Error::ChannelStopped)
+            }
+        }
+    }
+
+    /// Returns the snapshot of the resource limits, replacing it first if
+    /// they changed since it was taken.
+    async fn limits(&self) -> Arc<LimitsSnapshot> {
+        let p2p = self.p2p();
+        let limits = self.limits.lock().unwrap().clone();
+        if limits.is_current(p2p.resource_manager()) {
+            return limits
         }
+
+        let limits = Arc::new(LimitsSnapshot::new(&p2p).await);
+        *self.limits.lock().unwrap() = limits.clone();
+        limits
+    }
+
+    /// Returns the metrics of the given command, looking them up in the
+    /// network metrics only the first time the channel sees the command.
+    fn command_metrics(&self, command: &str) -> Arc<CommandMetrics> {
+        let mut cache = self.command_metrics.lock().unwrap();
+        if let Some(metrics) = cache.get(command) {
+            return metrics.clone()
+        }
+
+        let metrics = Arc::new(self.p2p().metrics().command(command));
+        cache.insert(command.to_string(), metrics.clone());
+        metrics
+    }
+
+    /// Returns the resources used by the peer, by protocol.
+    pub fn resource_usage(&self) -> Vec<ResourceUsage> {
+        self.resources.usage()
     }
 
     /// Ban a malicious peer and stop the channel.
@@ -435,7 +612,9 @@ impl Channel {
 
                 // An inbound Tor connection can't really be banned :)
                 #[cfg(feature = "p2p-tor")]
-                if (self.address().scheme() == "tor" || self.address().scheme() == "tor+tls") &&
+                if (self.address().scheme() == "tor" ||
+                    self.address().scheme() == "tor+tls" ||
+                    self.address().scheme() == "tor+noise") &&
                     self.p2p().hosts().is_local_host(self.address())
                 {
                     return
@@ -457,6 +636,7 @@ impl Channel {
         info!(target: "net::channel::ban()", "Blacklisting peer={}", peer);
         match self.p2p().hosts().move_host(&peer, last_seen, HostColor::Black) {
             Ok(()) => {
+                self.p2p().metrics().bans.inc();
                 info!(target: "net::channel::ban()", "Peer={} blacklisted successfully", peer);
             }
             Err(e) => {
@@ -500,6 +680,26 @@ impl Channel {
         self.version.get().unwrap().clone()
     }
 
+    /// Returns the protocol features advertised by the node this channel
+    /// is connected to. Should only be called after the version exchange
+    /// has been completed.
+    pub fn features(&self) -> Vec<(String, u32)> {
+        self.get_version().features.clone()
+    }
+
+    /// Check if the node this channel is connected to supports the given
+    /// protocol feature. Nodes advertising no features predate feature
+    /// negotiation, and are assumed to run all the version 0 protocols.
+    /// Should only be called after the version exchange has been completed.
+    pub fn supports_feature(&self, name: &str, version: u32) -> bool {
+        let peer_version = self.get_version();
+        if peer_version.features.is_empty() {
+            return version == 0
+        }
+
+        peer_version.features.iter().any(|(n, v)| n == name && *v == version)
+    }
+
     /// Returns the inner [`MessageSubsystem`] reference
     pub fn message_subsystem(&self) -> &MessageSubsystem {
         &self.message_subsystem
@@ -77,6 +77,8 @@ impl Connector {
                 endpoint.set_scheme("tor")?;
             } else if transports.contains(&"tor+tls".to_string()) && scheme == "tcp+tls" {
                 endpoint.set_scheme("tor+tls")?;
+            } else if transports.contains(&"tor+noise".to_string()) && scheme == "tcp+noise" {
+                endpoint.set_scheme("tor+noise")?;
             } else if transports.contains(&"nym".to_string()) && scheme == "tcp" {
                 endpoint.set_scheme("nym")?;
             } else if transports.contains(&"nym+tls".to_string()) && scheme == "tcp+tls" {
@@ -83,6 +83,20 @@ pub struct OutboundPeerDiscovery {
     pub state: &'static str,
 }
 
+#[derive(Clone, Debug)]
+pub struct ResourceInfo {
+    pub chan: ChannelInfo,
+    pub protocol: &'static str,
+    pub resource: String,
+    /// Delay applied to the channel in milliseconds, when throttled
+    pub delay: u64,
+    pub time: NanoTimestamp,
+}
+
+pub type ResourceThrottled = ResourceInfo;
+pub type ResourceDisconnected = ResourceInfo;
+pub type ResourceBanned = ResourceInfo;
+
 #[derive(Clone, Debug)]
 pub enum DnetEvent {
     SendMessage(MessageInfo),
@@ -94,4 +108,7 @@ pub enum DnetEvent {
     OutboundSlotConnected(OutboundSlotConnected),
     OutboundSlotDisconnected(OutboundSlotDisconnected),
     OutboundPeerDiscovery(OutboundPeerDiscovery),
+    ResourceThrottled(ResourceThrottled),
+    ResourceDisconnected(ResourceDisconnected),
+    ResourceBanned(ResourceBanned),
 }
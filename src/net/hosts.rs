@@ -16,6 +16,9 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
+use darkfi_serial::{
+    async_trait, deserialize_limited, serialize, SerialDecodable, SerialEncodable,
+};
 use log::{debug, error, info, trace, warn};
 use rand::{prelude::IteratorRandom, rngs::OsRng, Rng};
 use smol::lock::RwLock as AsyncRwLock;
@@ -33,18 +36,14 @@ use std::{
 use url::{Host, Url};
 
 use super::{
+    message::DEFAULT_DECODE_LIMITS,
     session::{SESSION_REFINE, SESSION_SEED},
     settings::Settings,
     ChannelPtr,
 };
 use crate::{
     system::{Publisher, PublisherPtr, Subscription},
-    util::{
-        file::{load_file, save_file},
-        most_frequent_or_any,
-        path::expand_path,
-        ringbuffer::RingBuffer,
-    },
+    util::{most_frequent_or_any, path::expand_path, ringbuffer::RingBuffer},
     Error, Result,
 };
 
@@ -83,8 +82,15 @@ use crate::{
 ///  or Connected. The state is `None` when the corresponding host has been removed from the
 ///  HostRegistry.
 ///
-///  TODO: Use HostState::Free `age` variable to implement a pruning logic that deletes peers from
-///  the registry once they have bypassed a certain age threshold.
+///  Hosts that have been `Free` for longer than `REGISTRY_FREE_MAX_AGE` are pruned from the
+///  registry, as are hostlist entries that are older than their list's maximum age or have
+///  failed too many connection attempts.
+///
+/// `NetGroup`: the network a host belongs to (an IPv4 /16, an IPv6 /32, Tor, I2P...). Bounded
+///  hostlists only keep a limited amount of hosts per group, so a single operator cannot fill
+///  our hostlists with addresses from the same network (eclipse attack). Tor and I2P are not
+///  capped: their addresses cost nothing to generate, so a cap wouldn't slow down an attacker,
+///  and it would shrink the hostlists of nodes only running on these networks.
 ///
 // An array containing all possible local host strings
 // TODO: This could perhaps be more exhaustive?
@@ -92,6 +98,22 @@ pub const LOCAL_HOST_STRS: [&str; 2] = ["localhost", "localhost.localdomain"];
 const WHITELIST_MAX_LEN: usize = 5000;
 const GREYLIST_MAX_LEN: usize = 2000;
 const DARKLIST_MAX_LEN: usize = 1000;
+/// Maximum amount of hosts from the same capped network group on a bounded hostlist.
+const NETGROUP_MAX_LEN: usize = 64;
+/// Whitelist entries not seen for 30 days are pruned.
+const WHITELIST_MAX_AGE: u64 = 30 * 86400;
+/// Greylist entries not seen for a week are pruned.
+const GREYLIST_MAX_AGE: u64 = 7 * 86400;
+/// Darklist entries not seen for a day are pruned.
+const DARKLIST_MAX_AGE: u64 = 86400;
+/// Hosts that failed this many consecutive connection attempts are pruned.
+const MAX_HOST_FAILURES: u32 = 5;
+/// Registry entries that have been `Free` for an hour are pruned.
+const REGISTRY_FREE_MAX_AGE: u64 = 3600;
+/// Magic bytes at the start of a binary hostlist file.
+const HOSTLIST_MAGIC: [u8; 4] = *b"DFHL";
+/// Current version of the binary hostlist format.
+const HOSTLIST_VERSION: u8 = 1;
 
 /// Atomic pointer to hosts object
 pub type HostsPtr = Arc<Hosts>;
@@ -337,11 +359,132 @@ impl TryFrom<usize> for HostColor {
     }
 }
 
+impl HostColor {
+    /// Maximum amount of entries on this hostlist.
+    /// Gold and Black list do not have a max size.
+    fn max_len(&self) -> Option<usize> {
+        match self {
+            HostColor::Grey => Some(GREYLIST_MAX_LEN),
+            HostColor::White => Some(WHITELIST_MAX_LEN),
+            HostColor::Dark => Some(DARKLIST_MAX_LEN),
+            HostColor::Gold | HostColor::Black => None,
+        }
+    }
+
+    /// Maximum age in seconds of the entries on this hostlist. Gold
+    /// entries are refreshed on every connection and Black entries are
+    /// kept for the duration of the program, so neither expire.
+    fn max_age(&self) -> Option<u64> {
+        match self {
+            HostColor::Grey => Some(GREYLIST_MAX_AGE),
+            HostColor::White => Some(WHITELIST_MAX_AGE),
+            HostColor::Dark => Some(DARKLIST_MAX_AGE),
+            HostColor::Gold | HostColor::Black => None,
+        }
+    }
+}
+
+/// The network a host belongs to. Used to bucket hosts on bounded
+/// hostlists, see `NETGROUP_MAX_LEN`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(in crate::net) enum NetGroup {
+    /// Loopback, private and otherwise non-global addresses, as well as
+    /// hosts without a host string (e.g. UNIX sockets). Never capped,
+    /// since these are only accepted on localnet.
+    Local,
+    /// An IPv4 /16 network.
+    Ipv4([u8; 2]),
+    /// An IPv6 /32 network.
+    Ipv6([u8; 4]),
+    /// Tor onion services. Never capped, see `NetGroup::is_capped()`.
+    Tor,
+    /// I2P eepsites. Never capped, see `NetGroup::is_capped()`.
+    I2p,
+    /// Domain names, grouped by their last two labels.
+    Domain(String),
+}
+
+impl NetGroup {
+    /// Find the network group of a given address.
+    pub(in crate::net) fn from_url(url: &Url) -> Self {
+        let Some(host) = url.host() else { return NetGroup::Local };
+
+        // Our transport schemes are not special, so IPv4 addresses are
+        // parsed as domains.
+        let host = match host {
+            Host::Domain(d) => match d.parse::<IpAddr>() {
+                Ok(IpAddr::V4(ip)) => Host::Ipv4(ip),
+                Ok(IpAddr::V6(ip)) => Host::Ipv6(ip),
+                Err(_) => Host::Domain(d),
+            },
+            host => host,
+        };
+
+        match host {
+            Host::Ipv4(ip) => {
+                if !ip.is_global() {
+                    return NetGroup::Local
+                }
+                let octets = ip.octets();
+                NetGroup::Ipv4([octets[0], octets[1]])
+            }
+            Host::Ipv6(ip) => {
+                // Treat IPv4-mapped addresses as the IPv4 address they map to.
+                if let Some(ip) = ip.to_ipv4_mapped() {
+                    if !ip.is_global() {
+                        return NetGroup::Local
+                    }
+                    let octets = ip.octets();
+                    return NetGroup::Ipv4([octets[0], octets[1]])
+                }
+                if !ip.is_global() {
+                    return NetGroup::Local
+                }
+                let octets = ip.octets();
+                NetGroup::Ipv6([octets[0], octets[1], octets[2], octets[3]])
+            }
+            Host::Domain(d) => {
+                let d = d.trim_end_matches('.').to_lowercase();
+                if LOCAL_HOST_STRS.contains(&d.as_str()) {
+                    return NetGroup::Local
+                }
+                if d.ends_with(".onion") {
+                    return NetGroup::Tor
+                }
+                if d.ends_with(".i2p") {
+                    return NetGroup::I2p
+                }
+                let labels: Vec<&str> = d.rsplitn(3, '.').take(2).collect();
+                NetGroup::Domain(labels.into_iter().rev().collect::<Vec<_>>().join("."))
+            }
+        }
+    }
+
+    /// Whether hosts of this group are limited to `NETGROUP_MAX_LEN` on
+    /// bounded hostlists. Overlay networks aren't, as a single group holds
+    /// all of their hosts, which are only bounded by the hostlist size.
+    pub(in crate::net) fn is_capped(&self) -> bool {
+        !matches!(self, NetGroup::Local | NetGroup::Tor | NetGroup::I2p)
+    }
+}
+
+/// A single hostlist entry as stored in the binary hostlist file.
+#[derive(Debug, Clone, SerialEncodable, SerialDecodable)]
+struct HostlistEntry {
+    color: u8,
+    addr: Url,
+    last_seen: u64,
+    failures: u32,
+}
+
 /// A Container for managing Grey, White, Gold and Black hostlists. Exposes
 /// a common interface for writing to and querying hostlists.
 // TODO: Benchmark hostlist operations when the hostlist is at max size.
 pub struct HostContainer {
     pub(in crate::net) hostlists: [RwLock<Vec<(Url, u64)>>; 5],
+    /// Consecutive connection failures per host. Reset once we manage
+    /// to connect to the host.
+    failures: RwLock<HashMap<Url, u32>>,
 }
 
 impl HostContainer {
@@ -354,7 +497,7 @@ impl HostContainer {
             RwLock::new(Vec::new()),
         ];
 
-        Self { hostlists }
+        Self { hostlists, failures: RwLock::new(HashMap::new()) }
     }
 
     /// Append host to a hostlist. Called when initalizing the hostlist in load_hosts().
@@ -428,6 +571,8 @@ impl HostContainer {
         // If transport mixing is enabled, then for example we're allowed to
         // use tor:// to connect to tcp:// and tor+tls:// to connect to tcp+tls://.
         // However, **do not** mix tor:// and tcp+tls://, nor tor+tls:// and tcp://.
+        // There is nothing to mix i2p:// with, as I2P routers only reach destinations
+        // inside the I2P network.
         macro_rules! mix_transport {
             ($a:expr, $b:expr) => {
                 if transports.contains(&$a.to_string()) && transport_mixing {
@@ -442,6 +587,7 @@ impl HostContainer {
 
         mix_transport!("tor", "tcp");
         mix_transport!("tor+tls", "tcp+tls");
+        mix_transport!("tor+noise", "tcp+noise");
         mix_transport!("nym", "tcp");
         mix_transport!("nym+tls", "tcp+tls");
 
@@ -682,36 +828,72 @@ impl HostContainer {
         list.reverse();
     }
 
-    /// Remove the last item on a hostlist if it reaches max size.
+    /// Record a failed connection attempt to a host. Returns the amount
+    /// of consecutive failures for this host.
+    pub(in crate::net) fn record_failure(&self, addr: &Url) -> u32 {
+        let mut failures = self.failures.write().unwrap();
+        let count = failures.entry(addr.clone()).or_insert(0);
+        *count = count.saturating_add(1);
+        *count
+    }
+
+    /// Reset the failure count of a host.
+    pub(in crate::net) fn clear_failures(&self, addr: &Url) {
+        self.failures.write().unwrap().remove(addr);
+    }
+
+    /// Get the amount of consecutive failed connection attempts to a host.
+    pub fn get_failures(&self, addr: &Url) -> u32 {
+        self.failures.read().unwrap().get(addr).copied().unwrap_or(0)
+    }
+
+    /// Evict entries from a hostlist until every capped network group is
+    /// within `NETGROUP_MAX_LEN` and the list is within its max size. The least
+    /// valuable entries are evicted first: those with the most failures,
+    /// then the ones that were seen least recently.
     fn resize(&self, color: HostColor) {
-        let list = self.hostlists[color.clone() as usize].read().unwrap();
-        let size = list.len();
-
-        // Immediately drop the read lock.
-        drop(list);
-
-        match color {
-            HostColor::Grey | HostColor::White | HostColor::Dark => {
-                let max_size = match color {
-                    HostColor::Grey => GREYLIST_MAX_LEN,
-                    HostColor::White => WHITELIST_MAX_LEN,
-                    HostColor::Dark => DARKLIST_MAX_LEN,
-                    _ => {
-                        unreachable!()
-                    }
-                };
-                if size == max_size {
-                    let mut list = self.hostlists[color.clone() as usize].write().unwrap();
-                    let last_entry = list.pop().unwrap();
-
-                    debug!(
-                        target: "net::hosts::resize()",
-                        "{:?}list reached max size. Removed {:?}", color, last_entry,
-                    );
-                }
+        let Some(max_len) = color.max_len() else { return };
+
+        let failures = self.failures.read().unwrap();
+        let mut list = self.hostlists[color.clone() as usize].write().unwrap();
+
+        // Returns the index of the worst entry among the given indexes.
+        let worst = |list: &[(Url, u64)], indexes: &mut dyn Iterator<Item = usize>| {
+            indexes.max_by_key(|&i| {
+                let (addr, last_seen) = &list[i];
+                (failures.get(addr).copied().unwrap_or(0), std::cmp::Reverse(*last_seen))
+            })
+        };
+
+        let mut groups: HashMap<NetGroup, usize> = HashMap::new();
+        for (addr, _) in list.iter() {
+            *groups.entry(NetGroup::from_url(addr)).or_insert(0) += 1;
+        }
+
+        for (group, count) in groups {
+            if !group.is_capped() {
+                continue
+            }
+
+            for _ in NETGROUP_MAX_LEN..count {
+                let mut indexes =
+                    (0..list.len()).filter(|&i| NetGroup::from_url(&list[i].0) == group);
+                let Some(index) = worst(&list, &mut indexes) else { break };
+                let entry = list.remove(index);
+                debug!(
+                    target: "net::hosts::resize()",
+                    "{:?}list reached max size for {:?}. Removed {:?}", color, group, entry,
+                );
             }
-            // Gold and Black list do not have a max size.
-            HostColor::Gold | HostColor::Black => (),
+        }
+
+        while list.len() > max_len {
+            let Some(index) = worst(&list, &mut (0..list.len())) else { break };
+            let entry = list.remove(index);
+            debug!(
+                target: "net::hosts::resize()",
+                "{:?}list reached max size. Removed {:?}", color, entry,
+            );
         }
     }
 
@@ -721,8 +903,8 @@ impl HostContainer {
         let now = UNIX_EPOCH.elapsed().unwrap().as_secs();
         let mut old_items = vec![];
 
-        let darklist = self.fetch_all(HostColor::Dark);
-        for (addr, last_seen) in darklist {
+        let hostlist = self.fetch_all(color.clone());
+        for (addr, last_seen) in hostlist {
             // Skip if last_seen comes from the future.
             //
             // We do this to avoid an overflow, which can happen if
@@ -744,7 +926,38 @@ impl HostContainer {
         }
     }
 
-    /// Load the hostlists from a file.
+    /// Prune all hostlists: delete entries older than their list's max
+    /// age, delete Grey and White entries that failed too many connection
+    /// attempts, and forget the failure counts of hosts we no longer know.
+    pub(in crate::net) fn prune(&self) {
+        for color in [HostColor::Grey, HostColor::White, HostColor::Dark] {
+            if let Some(max_age) = color.max_age() {
+                self.refresh(color.clone(), max_age);
+            }
+        }
+
+        let failing: Vec<Url> = self
+            .failures
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(_, count)| **count >= MAX_HOST_FAILURES)
+            .map(|(addr, _)| addr.clone())
+            .collect();
+
+        for addr in failing {
+            debug!(target: "net::hosts::prune()", "Removing failing host {}", addr);
+            self.remove_if_exists(HostColor::Grey, &addr);
+            self.remove_if_exists(HostColor::White, &addr);
+        }
+
+        let mut failures = self.failures.write().unwrap();
+        failures
+            .retain(|addr, _| (0..self.hostlists.len()).any(|color| self.contains(color, addr)));
+    }
+
+    /// Load the hostlists from a file. Hostlists saved in the legacy text
+    /// format are migrated, and written in the binary format on next save.
     pub(in crate::net) fn load_all(&self, path: &str) -> Result<()> {
         let path = expand_path(path)?;
 
@@ -756,16 +969,95 @@ impl HostContainer {
             File::create(path.clone())?;
         }
 
-        let contents = load_file(&path);
-        if let Err(e) = contents {
-            warn!(target: "net::hosts::load_hosts()", "Failed retrieving saved hosts: {}", e);
-            return Ok(())
+        let contents = match fs::read(&path) {
+            Ok(c) => c,
+            Err(e) => {
+                warn!(target: "net::hosts::load_hosts()", "Failed retrieving saved hosts: {}", e);
+                return Ok(())
+            }
+        };
+
+        let entries = if contents.starts_with(&HOSTLIST_MAGIC) {
+            match Self::decode_hostlist(&contents) {
+                Ok(entries) => entries,
+                Err(e) => {
+                    warn!(target: "net::hosts::load_hosts()", "Failed decoding saved hosts: {}", e);
+                    return Ok(())
+                }
+            }
+        } else {
+            if !contents.is_empty() {
+                info!(target: "net::hosts::load_hosts()", "Migrating legacy hostlist: {:?}", path);
+            }
+            Self::parse_legacy_hostlist(&String::from_utf8_lossy(&contents))
+        };
+
+        for entry in entries {
+            let color = match HostColor::try_from(entry.color as usize) {
+                Ok(HostColor::Black) | Err(_) => {
+                    debug!(target: "net::hosts::load_hosts()", "Malformed list name...");
+                    continue
+                }
+                Ok(color) => color,
+            };
+
+            if self.contains(color.clone() as usize, &entry.addr) {
+                continue
+            }
+
+            if entry.failures > 0 {
+                self.failures.write().unwrap().insert(entry.addr.clone(), entry.failures);
+            }
+
+            self.store(color as usize, entry.addr, entry.last_seen);
+        }
+
+        for color in [HostColor::Grey, HostColor::White, HostColor::Gold, HostColor::Dark] {
+            self.sort_by_last_seen(color.clone() as usize);
+            self.resize(color);
         }
 
-        for line in contents.unwrap().lines() {
+        // Delete entries that are stale or failing.
+        self.prune();
+
+        Ok(())
+    }
+
+    /// Decode a binary hostlist: the magic bytes and version, followed
+    /// by the serialized entries.
+    fn decode_hostlist(bytes: &[u8]) -> Result<Vec<HostlistEntry>> {
+        let version = bytes.get(HOSTLIST_MAGIC.len()).copied();
+        if version != Some(HOSTLIST_VERSION) {
+            return Err(Error::Custom(format!("Unsupported hostlist version {:?}", version)))
+        }
+
+        Ok(deserialize_limited(&bytes[HOSTLIST_MAGIC.len() + 1..], &DEFAULT_DECODE_LIMITS)?)
+    }
+
+    /// Parse a hostlist saved in the legacy format, a text file with one
+    /// tab-separated `list url last_seen` entry per line.
+    fn parse_legacy_hostlist(contents: &str) -> Vec<HostlistEntry> {
+        let mut entries = vec![];
+
+        for line in contents.lines() {
             let data: Vec<&str> = line.split('\t').collect();
+            if data.len() != 3 {
+                debug!(target: "net::hosts::load_hosts()", "Skipping malformed line {}", line);
+                continue
+            }
+
+            let color = match data[0] {
+                "gold" => HostColor::Gold,
+                "white" => HostColor::White,
+                "grey" => HostColor::Grey,
+                "dark" => HostColor::Dark,
+                _ => {
+                    debug!(target: "net::hosts::load_hosts()", "Malformed list name...");
+                    continue
+                }
+            };
 
-            let url = match Url::parse(data[1]) {
+            let addr = match Url::parse(data[1]) {
                 Ok(u) => u,
                 Err(e) => {
                     debug!(target: "net::hosts::load_hosts()", "Skipping malformed URL {}", e);
@@ -781,61 +1073,37 @@ impl HostContainer {
                 }
             };
 
-            match data[0] {
-                "gold" => {
-                    self.store(HostColor::Gold as usize, url, last_seen);
-                    self.sort_by_last_seen(HostColor::Gold as usize);
-                }
-                "white" => {
-                    self.store(HostColor::White as usize, url, last_seen);
-                    self.sort_by_last_seen(HostColor::White as usize);
-                    self.resize(HostColor::White);
-                }
-                "grey" => {
-                    self.store(HostColor::Grey as usize, url, last_seen);
-                    self.sort_by_last_seen(HostColor::Grey as usize);
-                    self.resize(HostColor::Grey);
-                }
-                "dark" => {
-                    self.store(HostColor::Dark as usize, url, last_seen);
-                    self.sort_by_last_seen(HostColor::Dark as usize);
-                    self.resize(HostColor::Dark);
-
-                    // Delete darklist entries that are older than one day.
-                    let day = 86400;
-                    self.refresh(HostColor::Dark, day);
-                }
-                _ => {
-                    debug!(target: "net::hosts::load_hosts()", "Malformed list name...");
-                }
-            }
+            entries.push(HostlistEntry { color: color as u8, addr, last_seen, failures: 0 });
         }
 
-        Ok(())
+        entries
     }
 
-    /// Save the hostlist to a file.
+    /// Save the hostlists to a file in the binary hostlist format.
     pub(in crate::net) fn save_all(&self, path: &str) -> Result<()> {
         let path = expand_path(path)?;
 
-        let mut tsv = String::new();
-        let mut hostlist: HashMap<String, Vec<(Url, u64)>> = HashMap::new();
-
-        hostlist.insert("dark".to_string(), self.fetch_all(HostColor::Dark));
-        hostlist.insert("grey".to_string(), self.fetch_all(HostColor::Grey));
-        hostlist.insert("white".to_string(), self.fetch_all(HostColor::White));
-        hostlist.insert("gold".to_string(), self.fetch_all(HostColor::Gold));
-
-        for (name, list) in hostlist {
-            for (url, last_seen) in list {
-                tsv.push_str(&format!("{}\t{}\t{}\n", name, url, last_seen));
+        let mut entries = vec![];
+        for color in [HostColor::Dark, HostColor::Grey, HostColor::White, HostColor::Gold] {
+            for (addr, last_seen) in self.fetch_all(color.clone()) {
+                let failures = self.get_failures(&addr);
+                entries.push(HostlistEntry {
+                    color: color.clone() as u8,
+                    addr,
+                    last_seen,
+                    failures,
+                });
             }
         }
 
-        if !tsv.is_empty() {
+        if !entries.is_empty() {
+            let mut bytes = HOSTLIST_MAGIC.to_vec();
+            bytes.push(HOSTLIST_VERSION);
+            bytes.extend_from_slice(&serialize(&entries));
+
             info!(target: "net::hosts::save_hosts()", "Saving hosts to: {:?}",
                   path);
-            if let Err(e) = save_file(&path, &tsv) {
+            if let Err(e) = fs::write(&path, bytes) {
                 error!(target: "net::hosts::save_hosts()", "Failed saving hosts: {}", e);
             }
         }
@@ -1033,6 +1301,25 @@ impl Hosts {
         debug!(target: "net::hosts::unregister()", "Unregistered: {}", &addr);
     }
 
+    /// Prune the hostlists, and delete hosts from the registry that have
+    /// been `Free` for longer than `REGISTRY_FREE_MAX_AGE`. A host that is
+    /// not in the registry is implicitly free, so this only frees memory.
+    pub(in crate::net) fn prune(&self) {
+        self.container.prune();
+
+        let now = UNIX_EPOCH.elapsed().unwrap().as_secs();
+        let mut registry = self.registry.lock().unwrap();
+        registry.retain(|addr, state| {
+            let HostState::Free(age) = state else { return true };
+            if now.saturating_sub(*age) <= REGISTRY_FREE_MAX_AGE {
+                return true
+            }
+
+            trace!(target: "net::hosts::prune()", "Removing {} from the registry", addr);
+            false
+        });
+    }
+
     /// Return the list of all connected channels, including seed and
     /// refinery connections.
     pub fn channels(&self) -> Vec<ChannelPtr> {
@@ -1310,7 +1597,7 @@ impl Hosts {
             match addr_.scheme() {
                 // Validate that the address is an actual onion.
                 #[cfg(feature = "p2p-tor")]
-                "tor" | "tor+tls" => {
+                "tor" | "tor+tls" | "tor+noise" => {
                     use std::str::FromStr;
                     if tor_hscrypto::pk::HsId::from_str(host_str).is_err() {
                         continue
@@ -1324,7 +1611,27 @@ impl Hosts {
                 #[cfg(feature = "p2p-nym")]
                 "nym" | "nym+tls" => continue, // <-- Temp skip
 
-                "tcp" | "tcp+tls" => {
+                // Validate that the address is an actual I2P destination hash.
+                #[cfg(feature = "p2p-i2p")]
+                "i2p" => {
+                    if !crate::net::transport::i2p::is_b32_address(host_str) {
+                        continue
+                    }
+                    trace!(
+                        target: "net::hosts::filter_addresses",
+                        "[I2P] Valid: {}", host_str,
+                    );
+                }
+
+                #[cfg(feature = "p2p-memory")]
+                "mem" => {
+                    trace!(
+                        target: "net::hosts::filter_addresses",
+                        "[Memory] Valid: {}", host_str,
+                    );
+                }
+
+                "tcp" | "tcp+tls" | "tcp+noise" => {
                     trace!(
                         target: "net::hosts::filter_addresses",
                         "[TCP] Valid: {}", host_str,
@@ -1450,6 +1757,9 @@ impl Hosts {
                 self.container.store_or_update(HostColor::White, addr.clone(), last_seen);
                 self.container.sort_by_last_seen(HostColor::White as usize);
                 self.container.resize(HostColor::White);
+
+                // The host passed the refinery, so forget about previous failures.
+                self.container.clear_failures(addr);
             }
 
             // Upgrade to gold. Remove from white or grey.
@@ -1459,6 +1769,9 @@ impl Hosts {
 
                 self.container.store_or_update(HostColor::Gold, addr.clone(), last_seen);
                 self.container.sort_by_last_seen(HostColor::Gold as usize);
+
+                // We managed to connect, so forget about previous failures.
+                self.container.clear_failures(addr);
             }
 
             // Move to black. Remove from all other lists.
@@ -1519,7 +1832,10 @@ impl Hosts {
     /// Make a best effort guess from the most frequently reported ipv6 auto address
     /// to set any unspecified ipv6 addrs: `external_addrs = ["tcp://[::]:1365"]`.
     fn patch_auto_addr(&self, ext_addr: &mut Url) -> Option<()> {
-        if ext_addr.scheme() != "tcp" && ext_addr.scheme() != "tcp+tls" {
+        if ext_addr.scheme() != "tcp" &&
+            ext_addr.scheme() != "tcp+tls" &&
+            ext_addr.scheme() != "tcp+noise"
+        {
             return None
         }
 
@@ -1738,6 +2054,178 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_netgroup() {
+        let groups = [
+            ("tcp://77.168.10.65:2222", NetGroup::Ipv4([77, 168])),
+            ("tcp+tls://77.168.200.1", NetGroup::Ipv4([77, 168])),
+            ("tcp://[2345:0425:2CA1:0000:0000:0567:5673:23b5]", NetGroup::Ipv6([35, 69, 4, 37])),
+            ("tcp://[::ffff:77.168.10.65]", NetGroup::Ipv4([77, 168])),
+            ("tcp://127.0.0.1:123", NetGroup::Local),
+            ("tcp://192.168.10.65", NetGroup::Local),
+            ("tcp+tls://[::1]", NetGroup::Local),
+            ("tcp://localhost:3921", NetGroup::Local),
+            ("unix:///tmp/darkfi.sock", NetGroup::Local),
+            (
+                "tor://eweiibe6tdjsdprb4px6rqrzzcsi22m4koia44kc5pcjr7nec2rlxyad.onion:2323",
+                NetGroup::Tor,
+            ),
+            ("i2p://foo.i2p", NetGroup::I2p),
+            ("tcp+tls://node1.dark.fi:26661", NetGroup::Domain("dark.fi".to_string())),
+            ("tcp+tls://dark.fi", NetGroup::Domain("dark.fi".to_string())),
+        ];
+
+        for (url, group) in groups {
+            assert_eq!(NetGroup::from_url(&Url::parse(url).unwrap()), group, "{}", url);
+        }
+    }
+
+    #[test]
+    fn test_resize() {
+        let settings = Settings { ..Default::default() };
+        let hosts = Hosts::new(Arc::new(AsyncRwLock::new(settings)));
+
+        // Fill the greylist with hosts from the same /16 network.
+        for i in 0..NETGROUP_MAX_LEN + 10 {
+            let url = Url::parse(&format!("tcp://77.168.{}.{}:123", i / 256, i % 256)).unwrap();
+            hosts.container.store(HostColor::Grey as usize, url, 1000 + i as u64);
+        }
+
+        // A failing host is evicted before older ones.
+        let failing = Url::parse("tcp://77.168.0.5:123").unwrap();
+        hosts.container.record_failure(&failing);
+
+        // Local hosts are never capped.
+        for i in 0..NETGROUP_MAX_LEN + 10 {
+            let url = Url::parse(&format!("tcp://127.0.0.1:{}", i + 1)).unwrap();
+            hosts.container.store(HostColor::Grey as usize, url, 1000);
+        }
+
+        hosts.container.sort_by_last_seen(HostColor::Grey as usize);
+        hosts.container.resize(HostColor::Grey);
+
+        let greylist = hosts.container.fetch_all(HostColor::Grey);
+        assert_eq!(greylist.len(), 2 * NETGROUP_MAX_LEN + 10);
+        assert!(!hosts.container.contains(HostColor::Grey as usize, &failing));
+
+        // The oldest remaining hosts were evicted.
+        let oldest = greylist
+            .iter()
+            .filter(|(url, _)| NetGroup::from_url(url) != NetGroup::Local)
+            .map(|(_, last_seen)| *last_seen)
+            .min()
+            .unwrap();
+        assert_eq!(oldest, 1000 + 10);
+
+        // Overlay hosts are only bounded by the hostlist size.
+        let hosts = Hosts::new(Arc::new(AsyncRwLock::new(Settings { ..Default::default() })));
+        for i in 0..NETGROUP_MAX_LEN + 10 {
+            let url = Url::parse(&format!("tor://node{}.onion:123", i)).unwrap();
+            hosts.container.store(HostColor::Grey as usize, url, 1000 + i as u64);
+        }
+        for i in 0..NETGROUP_MAX_LEN + 10 {
+            let url = Url::parse(&format!("i2p://node{}.i2p:123", i)).unwrap();
+            hosts.container.store(HostColor::Grey as usize, url, 1000 + i as u64);
+        }
+
+        hosts.container.resize(HostColor::Grey);
+        assert_eq!(hosts.container.fetch_all(HostColor::Grey).len(), 2 * (NETGROUP_MAX_LEN + 10));
+    }
+
+    #[test]
+    fn test_prune() {
+        let settings = Settings { ..Default::default() };
+        let hosts = Hosts::new(Arc::new(AsyncRwLock::new(settings)));
+        let now = UNIX_EPOCH.elapsed().unwrap().as_secs();
+
+        let stale = Url::parse("tcp://stale.host:123").unwrap();
+        let failing = Url::parse("tcp://failing.host:123").unwrap();
+        let healthy = Url::parse("tcp://healthy.host:123").unwrap();
+        let gold = Url::parse("tcp://gold.host:123").unwrap();
+
+        hosts.container.store(HostColor::Grey as usize, stale.clone(), now - GREYLIST_MAX_AGE - 1);
+        hosts.container.store(HostColor::White as usize, failing.clone(), now);
+        hosts.container.store(HostColor::White as usize, healthy.clone(), now);
+        hosts.container.store(HostColor::Gold as usize, gold.clone(), 0);
+
+        for _ in 0..MAX_HOST_FAILURES {
+            hosts.container.record_failure(&failing);
+        }
+        hosts.container.record_failure(&healthy);
+
+        // Free registry entries are pruned once they're old enough.
+        hosts
+            .try_register(stale.clone(), HostState::Free(now - REGISTRY_FREE_MAX_AGE - 1))
+            .unwrap();
+        hosts.try_register(healthy.clone(), HostState::Free(now)).unwrap();
+        hosts.try_register(gold.clone(), HostState::Connect).unwrap();
+
+        hosts.prune();
+
+        assert!(!hosts.container.contains(HostColor::Grey as usize, &stale));
+        assert!(!hosts.container.contains(HostColor::White as usize, &failing));
+        assert!(hosts.container.contains(HostColor::White as usize, &healthy));
+        assert!(hosts.container.contains(HostColor::Gold as usize, &gold));
+
+        assert_eq!(hosts.container.get_failures(&failing), 0);
+        assert_eq!(hosts.container.get_failures(&healthy), 1);
+
+        let registry = hosts.registry.lock().unwrap();
+        assert!(!registry.contains_key(&stale));
+        assert!(registry.contains_key(&healthy));
+        assert!(registry.contains_key(&gold));
+    }
+
+    #[test]
+    fn test_save_load() {
+        let now = UNIX_EPOCH.elapsed().unwrap().as_secs();
+        let path = std::env::temp_dir().join(format!("darkfi-hostlist-{}", OsRng.gen::<u64>()));
+        let path_str = path.to_str().unwrap();
+
+        // Write a hostlist in the legacy text format.
+        let legacy = format!(
+            "gold\ttcp://gold.host:123\t{now}\n\
+             white\ttcp://white.host:123\t{now}\n\
+             grey\ttcp://grey.host:123\t{now}\n\
+             dark\ttor://dark.onion:123\t{now}\n\
+             dark\ttor://old.onion:123\t1720000000\n\
+             malformed line\n"
+        );
+        fs::write(&path, legacy).unwrap();
+
+        let settings = Settings { ..Default::default() };
+        let hosts = Hosts::new(Arc::new(AsyncRwLock::new(settings.clone())));
+        hosts.container.load_all(path_str).unwrap();
+
+        let white = Url::parse("tcp://white.host:123").unwrap();
+        assert!(hosts
+            .container
+            .contains(HostColor::Gold as usize, &"tcp://gold.host:123".parse().unwrap()));
+        assert!(hosts.container.contains(HostColor::White as usize, &white));
+        assert!(hosts
+            .container
+            .contains(HostColor::Grey as usize, &"tcp://grey.host:123".parse().unwrap()));
+        assert_eq!(hosts.container.fetch_all(HostColor::Dark).len(), 1);
+
+        // Saving writes the binary format, preserving failure counts.
+        hosts.container.record_failure(&white);
+        hosts.container.save_all(path_str).unwrap();
+
+        let contents = fs::read(&path).unwrap();
+        assert!(contents.starts_with(&HOSTLIST_MAGIC));
+        assert_eq!(contents[HOSTLIST_MAGIC.len()], HOSTLIST_VERSION);
+
+        let hosts2 = Hosts::new(Arc::new(AsyncRwLock::new(settings)));
+        hosts2.container.load_all(path_str).unwrap();
+
+        for color in [HostColor::Grey, HostColor::White, HostColor::Gold, HostColor::Dark] {
+            assert_eq!(hosts.container.fetch_all(color.clone()), hosts2.container.fetch_all(color));
+        }
+        assert_eq!(hosts2.container.get_failures(&white), 1);
+
+        fs::remove_file(&path).unwrap();
+    }
+
     #[test]
     fn test_get_last() {
         smol::block_on(async {
@@ -16,15 +16,23 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
+pub use darkfi_serial::DecodeLimits;
 use darkfi_serial::{
     async_trait, serialize_async, AsyncDecodable, AsyncEncodable, SerialDecodable, SerialEncodable,
 };
 use std::net::Ipv6Addr;
 use url::{Host, Url};
 
+/// Default limits applied when decoding inbound messages:
+/// 1M elements per collection, 64MB per message and 32 nesting levels.
+pub const DEFAULT_DECODE_LIMITS: DecodeLimits =
+    DecodeLimits::new(1024 * 1024, 64 * 1024 * 1024, 32);
+
 /// Generic message template.
 pub trait Message: 'static + Send + Sync + AsyncDecodable + AsyncEncodable {
     const NAME: &'static str;
+    /// Limits applied when decoding this message from a peer.
+    const DECODE_LIMITS: DecodeLimits = DEFAULT_DECODE_LIMITS;
 }
 
 /// Generic serialized message template.
@@ -46,6 +54,12 @@ macro_rules! impl_p2p_message {
             const NAME: &'static str = $nm;
         }
     };
+    ($st:ty, $nm:expr, $limits:expr) => {
+        impl Message for $st {
+            const NAME: &'static str = $nm;
+            const DECODE_LIMITS: $crate::net::message::DecodeLimits = $limits;
+        }
+    };
 }
 
 /// Outbound keepalive message.
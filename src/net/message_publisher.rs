@@ -16,7 +16,12 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
-use std::{any::Any, collections::HashMap, sync::Arc, time::Duration};
+use std::{
+    any::Any,
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
 
 use async_trait::async_trait;
 use futures::stream::{FuturesUnordered, StreamExt};
@@ -26,7 +31,7 @@ use smol::{io::AsyncReadExt, lock::Mutex};
 
 use super::message::Message;
 use crate::{net::transport::PtStream, system::timeout::timeout, Error, Result};
-use darkfi_serial::{AsyncDecodable, VarInt};
+use darkfi_serial::{decode_async_limited, AsyncDecodable, LimitExceeded, VarInt};
 
 /// 64-bit identifier for message subscription.
 pub type MessageSubscriptionId = u64;
@@ -178,10 +183,17 @@ impl<M: Message> MessageSubscription<M> {
 /// Generic interface for the message dispatcher.
 #[async_trait]
 trait MessageDispatcherInterface: Send + Sync {
-    async fn trigger(&self, stream: &mut smol::io::ReadHalf<Box<dyn PtStream + 'static>>);
+    async fn trigger(
+        &self,
+        stream: &mut smol::io::ReadHalf<Box<dyn PtStream + 'static>>,
+    ) -> Result<u64>;
 
     async fn trigger_error(&self, err: Error);
 
+    async fn pending(&self) -> usize;
+
+    async fn has_subscribers(&self) -> bool;
+
     fn as_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync>;
 }
 
@@ -194,37 +206,57 @@ impl<M: Message> MessageDispatcherInterface for MessageDispatcher<M> {
     ///
     /// We extract the message length from the stream and use `take()`
     /// to allocate an appropiately sized buffer as a basic DDOS protection.
-    async fn trigger(&self, stream: &mut smol::io::ReadHalf<Box<dyn PtStream + 'static>>) {
-        match VarInt::decode_async(stream).await {
-            Ok(int) => {
-                // TODO: check the message length does not exceed some bound.
-                let len = int.0;
-                let mut take = stream.take(len);
-
-                // Deserialize stream into type, send down the pipes.
-                match M::decode_async(&mut take).await {
-                    Ok(payload) => {
-                        let message = Ok(Arc::new(payload));
-                        self._trigger_all(message).await
-                    }
-
-                    Err(err) => {
-                        error!(
-                            target: "net::message_publisher::trigger()",
-                            "Unable to decode data. Dropping...: {}",
-                            err,
-                        );
-                    }
-                }
-            }
+    /// The message is decoded within its `DECODE_LIMITS`. Exceeding them
+    /// leaves the stream in an unknown state, so the error is returned
+    /// for the channel to stop. Returns the length of the message payload.
+    async fn trigger(
+        &self,
+        stream: &mut smol::io::ReadHalf<Box<dyn PtStream + 'static>>,
+    ) -> Result<u64> {
+        let len = match VarInt::decode_async(stream).await {
+            Ok(int) => int.0,
             Err(err) => {
                 error!(
                     target: "net::message_publisher::trigger()",
                     "Unable to decode VarInt. Dropping...: {}",
                     err,
                 );
+                return Ok(0)
+            }
+        };
+
+        if let Err(err) = M::DECODE_LIMITS.check_bytes(len) {
+            error!(
+                target: "net::message_publisher::trigger()",
+                "Message {} of {} bytes is too large: {}",
+                M::NAME, len, err,
+            );
+            return Err(err.into())
+        }
+
+        let mut take = stream.take(len);
+
+        // Deserialize stream into type, send down the pipes.
+        match decode_async_limited::<M, _>(&mut take, &M::DECODE_LIMITS).await {
+            Ok(payload) => {
+                let message = Ok(Arc::new(payload));
+                self._trigger_all(message).await
+            }
+
+            Err(err) => {
+                error!(
+                    target: "net::message_publisher::trigger()",
+                    "Unable to decode data. Dropping...: {}",
+                    err,
+                );
+
+                if LimitExceeded::from_io(&err).is_some() {
+                    return Err(err.into())
+                }
             }
         }
+
+        Ok(len)
     }
 
     /// Internal function that sends an error message to all subscriber channels.
@@ -232,6 +264,17 @@ impl<M: Message> MessageDispatcherInterface for MessageDispatcher<M> {
         self._trigger_all(Err(err)).await;
     }
 
+    /// Returns the largest number of messages waiting to be received
+    /// by a subscriber.
+    async fn pending(&self) -> usize {
+        self.subs.lock().await.values().map(|sub| sub.len()).max().unwrap_or(0)
+    }
+
+    /// Returns whether anything is subscribed to the message.
+    async fn has_subscribers(&self) -> bool {
+        !self.subs.lock().await.is_empty()
+    }
+
     /// Converts to `Any` trait. Enables the dynamic modification of static types.
     fn as_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync> {
         self
@@ -283,18 +326,42 @@ impl MessageSubsystem {
     }
 
     /// Transmits a payload to a dispatcher.
-    /// Returns an error if the payload fails to transmit.
+    /// Returns the length of the payload, or an error if the payload
+    /// fails to transmit.
     pub async fn notify(
         &self,
         command: &str,
         reader: &mut smol::io::ReadHalf<Box<dyn PtStream + 'static>>,
-    ) -> Result<()> {
+    ) -> Result<u64> {
         let Some(dispatcher) = self.dispatchers.lock().await.get(command).cloned() else {
             return Err(Error::MissingDispatcher)
         };
 
-        dispatcher.trigger(reader).await;
-        Ok(())
+        dispatcher.trigger(reader).await
+    }
+
+    /// Returns the largest number of messages waiting to be received by
+    /// a subscriber of given command.
+    pub async fn pending(&self, command: &str) -> usize {
+        let Some(dispatcher) = self.dispatchers.lock().await.get(command).cloned() else {
+            return 0
+        };
+
+        dispatcher.pending().await
+    }
+
+    /// Returns the commands of the messages that have subscribers.
+    pub async fn subscribed_commands(&self) -> HashSet<&'static str> {
+        let dispatchers: Vec<_> =
+            self.dispatchers.lock().await.iter().map(|(c, d)| (*c, d.clone())).collect();
+
+        let mut commands = HashSet::new();
+        for (command, dispatcher) in dispatchers {
+            if dispatcher.has_subscribers().await {
+                commands.insert(command);
+            }
+        }
+        commands
     }
 
     /// Concurrently transmits an error message across dispatchers.
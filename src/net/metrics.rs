@@ -0,0 +1,135 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2025 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::sync::{Arc, LazyLock};
+
+use super::session::{
+    SessionBitFlag, SESSION_INBOUND, SESSION_MANUAL, SESSION_OUTBOUND, SESSION_REFINE, SESSION_SEED,
+};
+use crate::util::metrics::{registry, Counter, Family, Gauge};
+
+/// P2P metrics, registered in the global registry on first use
+static P2P_METRICS: LazyLock<P2pMetrics> = LazyLock::new(P2pMetrics::new);
+
+/// P2P metric families. Every series is labelled with the name of its
+/// network, as a process can run several P2P instances.
+struct P2pMetrics {
+    /// Connected channels, by network and session type
+    connections: Arc<Family<Gauge>>,
+    /// Messages sent, by network and command
+    messages_sent: Arc<Family<Counter>>,
+    /// Messages received, by network and command
+    messages_received: Arc<Family<Counter>>,
+    /// Bytes sent, by network and command
+    bytes_sent: Arc<Family<Counter>>,
+    /// Payload bytes received, by network and command
+    bytes_received: Arc<Family<Counter>>,
+    /// Peers we have banned, by network
+    bans: Arc<Family<Counter>>,
+}
+
+impl P2pMetrics {
+    fn new() -> Self {
+        let r = registry();
+        Self {
+            connections: r.gauge_family(
+                "darkfi_p2p_connections",
+                "Connected P2P channels",
+                &["network", "session"],
+            ),
+            messages_sent: r.counter_family(
+                "darkfi_p2p_messages_sent_total",
+                "P2P messages sent",
+                &["network", "command"],
+            ),
+            messages_received: r.counter_family(
+                "darkfi_p2p_messages_received_total",
+                "P2P messages received",
+                &["network", "command"],
+            ),
+            bytes_sent: r.counter_family(
+                "darkfi_p2p_bytes_sent_total",
+                "P2P bytes sent, including message framing",
+                &["network", "command"],
+            ),
+            bytes_received: r.counter_family(
+                "darkfi_p2p_bytes_received_total",
+                "P2P message payload bytes received",
+                &["network", "command"],
+            ),
+            bans: r.counter_family("darkfi_p2p_bans_total", "P2P peers banned", &["network"]),
+        }
+    }
+}
+
+/// Metrics of a single P2P network
+pub(super) struct NetworkMetrics {
+    /// Value of the `network` label
+    network: String,
+    /// Peers we have banned
+    pub bans: Arc<Counter>,
+}
+
+impl NetworkMetrics {
+    /// Create the metrics of the given network, registering the P2P
+    /// metrics so they get exported before any traffic.
+    pub fn new(network: &str) -> Self {
+        Self { network: network.to_string(), bans: P2P_METRICS.bans.with(&[network]) }
+    }
+
+    /// Returns the connected channels gauge of the given session type
+    pub fn connections(&self, type_id: SessionBitFlag) -> Arc<Gauge> {
+        P2P_METRICS.connections.with(&[&self.network, session_label(type_id)])
+    }
+
+    /// Returns the counters of the given command. Looking them up takes
+    /// a lock and allocates, so callers should keep them around.
+    pub fn command(&self, command: &str) -> CommandMetrics {
+        let labels = [self.network.as_str(), command];
+        CommandMetrics {
+            messages_sent: P2P_METRICS.messages_sent.with(&labels),
+            bytes_sent: P2P_METRICS.bytes_sent.with(&labels),
+            messages_received: P2P_METRICS.messages_received.with(&labels),
+            bytes_received: P2P_METRICS.bytes_received.with(&labels),
+        }
+    }
+}
+
+/// Counters of a single command on a P2P network
+pub(super) struct CommandMetrics {
+    /// Messages sent
+    pub messages_sent: Arc<Counter>,
+    /// Bytes sent, including message framing
+    pub bytes_sent: Arc<Counter>,
+    /// Messages received
+    pub messages_received: Arc<Counter>,
+    /// Payload bytes received
+    pub bytes_received: Arc<Counter>,
+}
+
+/// Returns the session label value for the given session type
+fn session_label(type_id: SessionBitFlag) -> &'static str {
+    match type_id {
+        SESSION_INBOUND => "inbound",
+        SESSION_OUTBOUND => "outbound",
+        SESSION_MANUAL => "manual",
+        SESSION_SEED => "seed",
+        SESSION_REFINE => "refine",
+        _ => "unknown",
+    }
+}
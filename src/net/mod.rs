@@ -124,6 +124,22 @@ pub mod connector;
 pub mod settings;
 pub use settings::{BanPolicy, Settings};
 
+/// Resource manager enforcing quotas on the message rates, bandwidth and
+/// pending subscriptions of every channel, and of the protocols running
+/// on it. Peers exceeding them get throttled, then disconnected, and
+/// finally banned.
+pub mod resource_manager;
+pub use resource_manager::ResourceLimits;
+
+/// P2P metrics, exported through the global metrics registry
+mod metrics;
+
+/// Network simulator running many P2P instances in a single process,
+/// with configurable latency, loss and partitions between them. Built
+/// on top of the in-memory transport, meant to be used in tests.
+#[cfg(feature = "p2p-memory")]
+pub mod simulator;
+
 /// Optional events based debug-notify subsystem. Off by default. Enabled in P2P instance,
 /// and then call `p2p.dnet_sub()` to start receiving events.
 #[macro_use]
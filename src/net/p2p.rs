@@ -32,7 +32,9 @@ use super::{
     dnet::DnetEvent,
     hosts::{Hosts, HostsPtr},
     message::{Message, SerializedMessage},
+    metrics::NetworkMetrics,
     protocol::{protocol_registry::ProtocolRegistry, register_default_protocols},
+    resource_manager::ResourceManager,
     session::{
         InboundSession, InboundSessionPtr, ManualSession, ManualSessionPtr, OutboundSession,
         OutboundSessionPtr, RefineSession, RefineSessionPtr, SeedSyncSession, SeedSyncSessionPtr,
@@ -59,6 +61,10 @@ pub struct P2p {
     hosts: HostsPtr,
     /// Protocol registry
     protocol_registry: ProtocolRegistry,
+    /// Resource manager tracking peers exceeding their limits
+    resource_manager: ResourceManager,
+    /// Metrics of this network
+    metrics: NetworkMetrics,
     /// P2P network settings
     settings: Arc<AsyncRwLock<Settings>>,
     /// Reference to configured [`ManualSession`]
@@ -99,6 +105,12 @@ impl P2p {
         // Register a CryptoProvider for rustls
         let _ = CryptoProvider::install_default(ring::default_provider());
 
+        // Point the I2P transport to the configured SAM bridge
+        #[cfg(feature = "p2p-i2p")]
+        super::transport::i2p::set_sam_bridge(settings.i2p_sam_bridge.clone());
+
+        let metrics = NetworkMetrics::new(&settings.network_name);
+
         // Wrap the Settings into an Arc<RwLock>
         let settings = Arc::new(AsyncRwLock::new(settings));
 
@@ -106,6 +118,8 @@ impl P2p {
             executor,
             hosts: Hosts::new(Arc::clone(&settings)),
             protocol_registry: ProtocolRegistry::new(),
+            resource_manager: ResourceManager::new(),
+            metrics,
             settings,
             session_manual: ManualSession::new(p2p.clone()),
             session_inbound: InboundSession::new(p2p.clone()),
@@ -127,6 +141,18 @@ impl P2p {
                self.settings.read().await.magic_bytes.0);
         info!(target: "net::p2p::start", "[P2P] Starting P2P subsystem");
 
+        // All protocols are registered by now, so catch limits configured
+        // for protocols that don't exist, likely due to a typo.
+        let features = self.protocol_registry.features().await;
+        for name in self.settings.read().await.protocol_limits.keys() {
+            if !features.iter().any(|(feature, _)| feature == name) {
+                warn!(
+                    target: "net::p2p::start",
+                    "[P2P] Ignoring protocol_limits of unknown protocol {}", name,
+                );
+            }
+        }
+
         // Start the inbound session
         if let Err(err) = self.session_inbound().start().await {
             error!(target: "net::p2p::start", "Failed to start inbound session!: {}", err);
@@ -245,6 +271,16 @@ impl P2p {
         &self.protocol_registry
     }
 
+    /// Return a reference to the internal resource manager
+    pub fn resource_manager(&self) -> &ResourceManager {
+        &self.resource_manager
+    }
+
+    /// Get pointer to the metrics of this network
+    pub(super) fn metrics(&self) -> &NetworkMetrics {
+        &self.metrics
+    }
+
     /// Get pointer to manual session
     pub fn session_manual(&self) -> ManualSessionPtr {
         self.session_manual.clone()
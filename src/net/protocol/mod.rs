@@ -18,6 +18,7 @@
 
 use super::{
     p2p::P2pPtr,
+    resource_manager::ResourceLimits,
     session::{SESSION_DEFAULT, SESSION_SEED},
 };
 
@@ -87,7 +88,27 @@ pub mod protocol_registry;
 /// Register the default network protocols for a p2p instance.
 pub async fn register_default_protocols(p2p: P2pPtr) {
     let registry = p2p.protocol_registry();
-    registry.register(SESSION_DEFAULT | SESSION_SEED, ProtocolPing::init).await;
-    registry.register(SESSION_DEFAULT, ProtocolAddress::init).await;
-    registry.register(SESSION_SEED, ProtocolSeed::init).await;
+
+    // Keepalives and address exchanges are infrequent, so anything
+    // above a few messages per second is spam.
+    let limits = ResourceLimits {
+        msg_rate: 1,
+        msg_burst: 10,
+        bytes_in_rate: 0,
+        bytes_out_rate: 0,
+        max_pending: 64,
+    };
+    registry
+        .register_with_limits(
+            SESSION_DEFAULT | SESSION_SEED,
+            "ProtocolPing",
+            0,
+            limits.clone(),
+            ProtocolPing::init,
+        )
+        .await;
+    registry
+        .register_with_limits(SESSION_DEFAULT, "ProtocolAddress", 0, limits, ProtocolAddress::init)
+        .await;
+    registry.register(SESSION_SEED, "ProtocolSeed", 0, ProtocolSeed::init).await;
 }
@@ -83,6 +83,20 @@ impl<M: Message + Clone, R: Message + Clone + Debug> ProtocolGenericHandler<M, R
         p2p: &P2pPtr,
         name: &'static str,
         session: SessionBitFlag,
+    ) -> ProtocolGenericHandlerPtr<M, R> {
+        Self::new_with_version(p2p, name, 0, session).await
+    }
+
+    /// Generate a new ProtocolGenericHandler for the provided P2P
+    /// instance, advertising its generic protocol with the given
+    /// feature version. Protocols introduced after feature negotiation
+    /// must use a version above 0, so they only run with peers that
+    /// support them.
+    pub async fn new_with_version(
+        p2p: &P2pPtr,
+        name: &'static str,
+        version: u32,
+        session: SessionBitFlag,
     ) -> ProtocolGenericHandlerPtr<M, R> {
         // Generate the message queue smol channel
         let (sender, receiver) = smol::channel::unbounded::<(u32, M)>();
@@ -99,7 +113,7 @@ impl<M: Message + Clone, R: Message + Clone + Debug> ProtocolGenericHandler<M, R
         // Attach a generic protocol to the P2P insstance
         let _handler = handler.clone();
         p2p.protocol_registry()
-            .register(session, move |channel, p2p| {
+            .register(session, name, version, move |channel, p2p| {
                 let handler = _handler.clone();
                 async move { ProtocolGeneric::init(channel, name, handler, p2p).await.unwrap() }
             })
@@ -16,6 +16,8 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
+use std::collections::{HashMap, HashSet};
+
 use log::debug;
 use smol::{
     future::{Boxed, Future},
@@ -23,15 +25,57 @@ use smol::{
 };
 
 use super::{
-    super::{channel::ChannelPtr, p2p::P2pPtr, session::SessionBitFlag},
+    super::{
+        channel::ChannelPtr, p2p::P2pPtr, resource_manager::ResourceLimits, session::SessionBitFlag,
+    },
     protocol_base::ProtocolBasePtr,
 };
 
 type Constructor = Box<dyn Fn(ChannelPtr, P2pPtr) -> Boxed<ProtocolBasePtr> + Send + Sync>;
 
+/// Protocol feature, as a `(name, version)` tuple advertised to peers
+/// in the handshake [`VersionMessage`](crate::net::message::VersionMessage).
+pub type Feature = (String, u32);
+
+/// Registered protocol constructor, along with the sessions it runs on,
+/// the feature it provides and its resource limits, if it has any.
+struct Registration {
+    session_flags: SessionBitFlag,
+    name: &'static str,
+    version: u32,
+    limits: Option<ResourceLimits>,
+    constructor: Constructor,
+}
+
+/// Protocol handling a message command, whose resource limits apply to it
+struct LimitsClaim {
+    /// Index of the protocol registration
+    index: usize,
+    /// Protocol name
+    protocol: &'static str,
+    /// Protocol resource limits, if it registered any
+    limits: Option<ResourceLimits>,
+}
+
+impl LimitsClaim {
+    /// Grab the protocol name and the limits applying to the command,
+    /// preferring the configured overrides to the registered limits.
+    fn resolve(
+        &self,
+        overrides: &HashMap<String, ResourceLimits>,
+    ) -> Option<(&'static str, ResourceLimits)> {
+        let limits = overrides.get(self.protocol).or(self.limits.as_ref())?;
+        Some((self.protocol, limits.clone()))
+    }
+}
+
 #[derive(Default)]
 pub struct ProtocolRegistry {
-    constructors: Mutex<Vec<(SessionBitFlag, Constructor)>>,
+    constructors: Mutex<Vec<Registration>>,
+    /// Protocols handling message commands, along with their resource limits
+    limits: Mutex<HashMap<&'static str, LimitsClaim>>,
+    /// Registrations whose message commands we already know
+    learned: Mutex<HashSet<usize>>,
 }
 
 impl ProtocolRegistry {
@@ -40,18 +84,145 @@ impl ProtocolRegistry {
         Self::default()
     }
 
-    /// `add_protocol()?`
-    pub async fn register<C, F>(&self, session_flags: SessionBitFlag, constructor: C)
-    where
+    /// Register a protocol for the given sessions. The protocol is
+    /// advertised to peers as the `(name, version)` feature, and only
+    /// runs on channels whose peer advertises the same feature.
+    ///
+    /// Version 0 is reserved for the protocols that existed before feature
+    /// negotiation, which is all that peers not advertising any features run.
+    /// New protocols, and new incompatible versions of existing ones, should
+    /// use a version above 0, so they never get started with such peers.
+    ///
+    /// The protocol has no resource limits of its own, unless an entry of
+    /// `Settings::protocol_limits` configures them. Use
+    /// [`Self::register_with_limits`] to register it with limits.
+    pub async fn register<C, F>(
+        &self,
+        session_flags: SessionBitFlag,
+        name: &'static str,
+        version: u32,
+        constructor: C,
+    ) where
+        C: 'static + Fn(ChannelPtr, P2pPtr) -> F + Send + Sync,
+        F: 'static + Future<Output = ProtocolBasePtr> + Send,
+    {
+        self.add(session_flags, name, version, None, constructor).await
+    }
+
+    /// Register a protocol like [`Self::register`], with given resource
+    /// limits. They get enforced on every channel, for the messages the
+    /// protocol subscribes to when constructed. Entries of
+    /// `Settings::protocol_limits` override them by protocol name.
+    pub async fn register_with_limits<C, F>(
+        &self,
+        session_flags: SessionBitFlag,
+        name: &'static str,
+        version: u32,
+        limits: ResourceLimits,
+        constructor: C,
+    ) where
+        C: 'static + Fn(ChannelPtr, P2pPtr) -> F + Send + Sync,
+        F: 'static + Future<Output = ProtocolBasePtr> + Send,
+    {
+        self.add(session_flags, name, version, Some(limits), constructor).await
+    }
+
+    async fn add<C, F>(
+        &self,
+        session_flags: SessionBitFlag,
+        name: &'static str,
+        version: u32,
+        limits: Option<ResourceLimits>,
+        constructor: C,
+    ) where
         C: 'static + Fn(ChannelPtr, P2pPtr) -> F + Send + Sync,
         F: 'static + Future<Output = ProtocolBasePtr> + Send,
     {
         let constructor =
             move |channel, p2p| Box::pin(constructor(channel, p2p)) as Boxed<ProtocolBasePtr>;
 
-        self.constructors.lock().await.push((session_flags, Box::new(constructor)));
+        self.constructors.lock().await.push(Registration {
+            session_flags,
+            name,
+            version,
+            limits,
+            constructor: Box::new(constructor),
+        });
+    }
+
+    /// Returns the features of all the registered protocols, sorted and
+    /// deduplicated. Protocols running on different sessions are all
+    /// included, since a protocol on one side of a channel can pair with
+    /// a different protocol on the other side.
+    pub async fn features(&self) -> Vec<Feature> {
+        let mut features: Vec<Feature> = self
+            .constructors
+            .lock()
+            .await
+            .iter()
+            .map(|reg| (reg.name.to_string(), reg.version))
+            .collect();
+        features.sort();
+        features.dedup();
+        features
     }
 
+    /// Grab the protocol name and resource limits applying to given message
+    /// command, if any, using the configured overrides.
+    pub async fn limits(
+        &self,
+        command: &str,
+        overrides: &HashMap<String, ResourceLimits>,
+    ) -> Option<(&'static str, ResourceLimits)> {
+        let limits = self.limits.lock().await;
+        limits.get(command).and_then(|claim| claim.resolve(overrides))
+    }
+
+    /// Grab the protocol names and resource limits of all the message
+    /// commands with limits, using the configured overrides.
+    pub async fn all_limits(
+        &self,
+        overrides: &HashMap<String, ResourceLimits>,
+    ) -> HashMap<&'static str, (&'static str, ResourceLimits)> {
+        let limits = self.limits.lock().await;
+        limits
+            .iter()
+            .filter_map(|(command, claim)| Some((*command, claim.resolve(overrides)?)))
+            .collect()
+    }
+
+    /// Record the message commands a protocol subscribed to when it got
+    /// constructed, so its limits apply to them. Commands subscribed to by
+    /// several protocols go to the one registered first.
+    async fn learn_commands(
+        &self,
+        index: usize,
+        protocol: &'static str,
+        limits: &Option<ResourceLimits>,
+        commands: impl Iterator<Item = &'static str>,
+    ) {
+        let mut claims = self.limits.lock().await;
+        for command in commands {
+            if claims.get(command).is_some_and(|claim| claim.index < index) {
+                continue
+            }
+
+            debug!(
+                target: "net::protocol_registry",
+                "{} handles {} messages", protocol, command,
+            );
+            claims.insert(command, LimitsClaim { index, protocol, limits: limits.clone() });
+        }
+        drop(claims);
+
+        self.learned.lock().await.insert(index);
+    }
+
+    /// Initialize the protocols registered for given session whose feature
+    /// is supported by the channel peer, without starting them. Must be
+    /// called once the peer version is known, and before acknowledging it,
+    /// so the protocols subscribe to their messages before the peer starts
+    /// sending them.
     pub async fn attach(
         &self,
         selector_id: SessionBitFlag,
@@ -60,8 +231,9 @@ impl ProtocolRegistry {
     ) -> Vec<ProtocolBasePtr> {
         let mut protocols = vec![];
 
-        for (session_flags, construct) in self.constructors.lock().await.iter() {
+        for (index, reg) in self.constructors.lock().await.iter().enumerate() {
             // Skip protocols that are not registered for this session
+            let session_flags = reg.session_flags;
             if selector_id & session_flags == 0 {
                 debug!(
                     target: "net::protocol_registry",
@@ -70,11 +242,69 @@ impl ProtocolRegistry {
                 continue
             }
 
-            let protocol = construct(channel.clone(), p2p.clone()).await;
+            // Skip protocols the peer doesn't run
+            if !channel.supports_feature(reg.name, reg.version) {
+                debug!(
+                    target: "net::protocol_registry",
+                    "Peer {} doesn't support {} v{}, skipping it",
+                    channel.address(), reg.name, reg.version,
+                );
+                continue
+            }
+
+            // The first time a protocol gets constructed, look at the
+            // messages it subscribes to, so we know what its limits apply to.
+            let msg_subsystem = channel.message_subsystem();
+            let learned = self.learned.lock().await.contains(&index);
+            let subscribed =
+                if learned { HashSet::new() } else { msg_subsystem.subscribed_commands().await };
+
+            let protocol = (reg.constructor)(channel.clone(), p2p.clone()).await;
             debug!(target: "net::protocol_registry", "Attached {}", protocol.name());
+
+            if !learned {
+                let commands = msg_subsystem.subscribed_commands().await;
+                let commands = commands.difference(&subscribed).copied();
+                self.learn_commands(index, reg.name, &reg.limits, commands).await;
+
+                // Have the channels pick up the limits of the new commands
+                p2p.resource_manager().reload_limits();
+            }
             protocols.push(protocol);
         }
 
         protocols
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_protocol_limits() {
+        smol::block_on(async {
+            let registry = ProtocolRegistry::new();
+            let limits = ResourceLimits { msg_rate: 1, ..ResourceLimits::unlimited() };
+            let mut overrides = HashMap::new();
+
+            registry
+                .learn_commands(0, "ProtocolA", &Some(limits.clone()), ["a", "b"].into_iter())
+                .await;
+            registry.learn_commands(1, "ProtocolB", &None, ["b", "c"].into_iter()).await;
+
+            // Commands handled by several protocols go to the one registered first
+            assert_eq!(registry.limits("a", &overrides).await, Some(("ProtocolA", limits.clone())));
+            assert_eq!(registry.limits("b", &overrides).await, Some(("ProtocolA", limits.clone())));
+
+            // Protocols registered without limits have none, unless configured
+            assert_eq!(registry.limits("c", &overrides).await, None);
+            assert_eq!(registry.all_limits(&overrides).await.len(), 2);
+
+            let configured = ResourceLimits { msg_rate: 2, ..ResourceLimits::unlimited() };
+            overrides.insert("ProtocolB".to_string(), configured.clone());
+            assert_eq!(registry.limits("c", &overrides).await, Some(("ProtocolB", configured)));
+            assert_eq!(registry.all_limits(&overrides).await.len(), 3);
+        });
+    }
+}
@@ -21,7 +21,10 @@ use futures::{
     pin_mut,
 };
 use log::{debug, error};
-use smol::{lock::RwLock as AsyncRwLock, Executor, Timer};
+use smol::{
+    lock::{Mutex, RwLock as AsyncRwLock},
+    Executor, Timer,
+};
 use std::{
     sync::Arc,
     time::{Duration, UNIX_EPOCH},
@@ -31,6 +34,8 @@ use super::super::{
     channel::ChannelPtr,
     message::{VerackMessage, VersionMessage},
     message_publisher::MessageSubscription,
+    protocol::protocol_base::ProtocolBasePtr,
+    session::SessionBitFlag,
     settings::Settings,
 };
 use crate::{Error, Result};
@@ -42,6 +47,11 @@ pub struct ProtocolVersion {
     version_sub: MessageSubscription<VersionMessage>,
     verack_sub: MessageSubscription<VerackMessage>,
     settings: Arc<AsyncRwLock<Settings>>,
+    /// Session whose registered protocols get attached once the peer
+    /// version is known
+    selector_id: Option<SessionBitFlag>,
+    /// Protocols attached during the handshake, waiting to be started
+    protocols: Mutex<Vec<ProtocolBasePtr>>,
 }
 
 impl ProtocolVersion {
@@ -50,6 +60,26 @@ impl ProtocolVersion {
     // TODO: This function takes settings as a param, however, it is also reachable through Channel.
     //       Maybe we want to navigate towards Settings through channel->session->p2p->settings
     pub async fn new(channel: ChannelPtr, settings: Arc<AsyncRwLock<Settings>>) -> Arc<Self> {
+        Self::create(channel, settings, None).await
+    }
+
+    /// Create a new version protocol which also attaches the protocols
+    /// registered for given session, restricted to the ones the peer
+    /// supports. Retrieve them with [`Self::take_protocols`] once the
+    /// handshake is done.
+    pub async fn with_protocols(
+        channel: ChannelPtr,
+        settings: Arc<AsyncRwLock<Settings>>,
+        selector_id: SessionBitFlag,
+    ) -> Arc<Self> {
+        Self::create(channel, settings, Some(selector_id)).await
+    }
+
+    async fn create(
+        channel: ChannelPtr,
+        settings: Arc<AsyncRwLock<Settings>>,
+        selector_id: Option<SessionBitFlag>,
+    ) -> Arc<Self> {
         // Creates a version subscription
         let version_sub =
             channel.subscribe_msg::<VersionMessage>().await.expect("Missing version dispatcher!");
@@ -58,7 +88,20 @@ impl ProtocolVersion {
         let verack_sub =
             channel.subscribe_msg::<VerackMessage>().await.expect("Missing verack dispatcher!");
 
-        Arc::new(Self { channel, version_sub, verack_sub, settings })
+        Arc::new(Self {
+            channel,
+            version_sub,
+            verack_sub,
+            settings,
+            selector_id,
+            protocols: Mutex::new(vec![]),
+        })
+    }
+
+    /// Take the protocols attached during the handshake. They are
+    /// initialized but not started.
+    pub async fn take_protocols(&self) -> Vec<ProtocolBasePtr> {
+        std::mem::take(&mut *self.protocols.lock().await)
     }
 
     /// Start version information exchange. Start the timer. Send version
@@ -155,6 +198,7 @@ impl ProtocolVersion {
         drop(settings);
 
         let external_addrs = self.channel.hosts().external_addrs().await;
+        let features = self.channel.p2p().protocol_registry().features().await;
 
         let version = VersionMessage {
             node_id,
@@ -163,10 +207,7 @@ impl ProtocolVersion {
             connect_recv_addr: self.channel.connect_addr().clone(),
             resolve_recv_addr: self.channel.resolve_addr().clone(),
             ext_send_addr: external_addrs,
-            /* NOTE: `features` is a list of enabled features in the
-            format Vec<(service, version)>. In the future, Protocols will
-            add their own data to this field when they are attached.*/
-            features: vec![],
+            features,
         };
         self.channel.send(&version).await?;
 
@@ -218,6 +259,18 @@ impl ProtocolVersion {
         }
         self.channel.set_version(version).await;
 
+        // Now that we know what the peer supports, initialize the matching
+        // protocols. They subscribe to their messages before the verack goes
+        // out, so nothing the peer sends once it's done is lost.
+        if let Some(selector_id) = self.selector_id {
+            let p2p = self.channel.p2p();
+            let protocols = p2p
+                .protocol_registry()
+                .attach(selector_id, self.channel.clone(), p2p.clone())
+                .await;
+            *self.protocols.lock().await = protocols;
+        }
+
         // Send verack
         let verack = VerackMessage { app_version: self.settings.read().await.app_version.clone() };
         self.channel.send(&verack).await?;
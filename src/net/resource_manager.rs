@@ -0,0 +1,574 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2025 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use super::{p2p::P2p, settings::BanPolicy};
+
+/// Pseudo protocol name used for the limits applied to a channel as a whole
+pub const CHANNEL_QUOTA: &str = "channel";
+
+/// Delay applied to a channel whose subscriptions have too many pending
+/// messages, before reading the next one.
+const PENDING_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Resource limits of a channel, or of the messages handled by a protocol.
+///
+/// Rates are per second. Peers can go above a rate for short periods, up
+/// to the configured burst, before getting throttled. Large messages put
+/// the peer in debt until the rate catches up. Setting a limit to zero
+/// disables it.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct ResourceLimits {
+    /// Messages per second a peer can send us
+    pub msg_rate: u32,
+    /// Messages a peer can send above `msg_rate` before getting throttled
+    pub msg_burst: u32,
+    /// Bytes per second a peer can send us
+    pub bytes_in_rate: u64,
+    /// Bytes per second we send to a peer
+    pub bytes_out_rate: u64,
+    /// Messages that can be waiting in the channel subscriptions to be
+    /// processed, before we stop reading from the peer
+    pub max_pending: usize,
+}
+
+/// The default limits are only applied to channels as a whole, through
+/// `Settings::channel_limits`. Protocols have no limits unless they get
+/// registered with some, or configured in `Settings::protocol_limits`.
+/// They are set well above the traffic of honest peers, so they only
+/// catch floods: a peer can send 5000 messages at once and 500 per second
+/// over time, and we stop reading from it while 8192 of its messages are
+/// waiting to be processed. Bandwidth isn't limited by default.
+impl Default for ResourceLimits {
+    fn default() -> Self {
+        Self {
+            msg_rate: 500,
+            msg_burst: 5000,
+            bytes_in_rate: 0,
+            bytes_out_rate: 0,
+            max_pending: 8192,
+        }
+    }
+}
+
+impl ResourceLimits {
+    /// Limits that never get exceeded
+    pub fn unlimited() -> Self {
+        Self { msg_rate: 0, msg_burst: 0, bytes_in_rate: 0, bytes_out_rate: 0, max_pending: 0 }
+    }
+}
+
+/// Resource whose limit got exceeded
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Resource {
+    Messages,
+    BytesIn,
+    BytesOut,
+    Pending,
+}
+
+impl fmt::Display for Resource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Self::Messages => "messages",
+            Self::BytesIn => "bytes_in",
+            Self::BytesOut => "bytes_out",
+            Self::Pending => "pending",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Decision taken after accounting for a message
+#[derive(Clone, Debug, PartialEq)]
+pub enum Verdict {
+    /// The peer is within its limits
+    Allow,
+    /// The peer exceeded a limit of given protocol, and the channel
+    /// should wait for the given duration before proceeding.
+    Throttle { protocol: &'static str, resource: Resource, delay: Duration },
+    /// The peer got throttled too many times, and should be disconnected.
+    Disconnect { protocol: &'static str, resource: Resource },
+}
+
+/// Token bucket refilling at a constant rate. The level can go negative,
+/// so messages larger than the bucket capacity still get through, but
+/// the peer has to wait for the debt to be paid.
+#[derive(Debug)]
+struct Bucket {
+    rate: f64,
+    capacity: f64,
+    level: f64,
+    last: Instant,
+}
+
+impl Bucket {
+    fn new(rate: f64, capacity: f64, now: Instant) -> Self {
+        Self { rate, capacity, level: capacity, last: now }
+    }
+
+    /// Take `amount` tokens out of the bucket, and return how long to wait
+    /// until it is out of debt. A zero rate never limits.
+    fn take(&mut self, amount: f64, now: Instant) -> Option<Duration> {
+        if self.rate == 0.0 {
+            return None
+        }
+
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+        self.last = now;
+        self.level = (self.level + elapsed * self.rate).min(self.capacity);
+        self.level -= amount;
+
+        if self.level >= 0.0 {
+            return None
+        }
+        Some(Duration::from_secs_f64(-self.level / self.rate))
+    }
+}
+
+/// Resources used by a channel under one protocol
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ResourceUsage {
+    /// Protocol name, or [`CHANNEL_QUOTA`] for the whole channel
+    pub protocol: &'static str,
+    /// Messages received
+    pub msgs_in: u64,
+    /// Bytes received
+    pub bytes_in: u64,
+    /// Bytes sent
+    pub bytes_out: u64,
+    /// Times the peer got throttled
+    pub throttles: u64,
+}
+
+/// Quota of a channel under one protocol
+#[derive(Debug)]
+struct Quota {
+    limits: ResourceLimits,
+    msgs: Bucket,
+    bytes_in: Bucket,
+    bytes_out: Bucket,
+    usage: ResourceUsage,
+}
+
+impl Quota {
+    fn new(protocol: &'static str, limits: &ResourceLimits, now: Instant) -> Self {
+        let msg_rate = limits.msg_rate as f64;
+        let bytes_in_rate = limits.bytes_in_rate as f64;
+        let bytes_out_rate = limits.bytes_out_rate as f64;
+        Self {
+            limits: limits.clone(),
+            msgs: Bucket::new(msg_rate, msg_rate + limits.msg_burst as f64, now),
+            bytes_in: Bucket::new(bytes_in_rate, bytes_in_rate, now),
+            bytes_out: Bucket::new(bytes_out_rate, bytes_out_rate, now),
+            usage: ResourceUsage { protocol, ..Default::default() },
+        }
+    }
+
+    /// Account for a received message, returning the exceeded resource
+    /// and the delay to apply, if any.
+    fn recv(&mut self, bytes: u64, pending: usize, now: Instant) -> Option<(Resource, Duration)> {
+        self.usage.msgs_in += 1;
+        self.usage.bytes_in += bytes;
+
+        let msgs_delay = self.msgs.take(1.0, now);
+        let bytes_delay = self.bytes_in.take(bytes as f64, now);
+        let pending_delay = if self.limits.max_pending != 0 && pending > self.limits.max_pending {
+            Some(PENDING_BACKOFF)
+        } else {
+            None
+        };
+
+        let exceeded = [
+            (Resource::Messages, msgs_delay),
+            (Resource::BytesIn, bytes_delay),
+            (Resource::Pending, pending_delay),
+        ]
+        .into_iter()
+        .filter_map(|(resource, delay)| delay.map(|d| (resource, d)))
+        .max_by_key(|(_, delay)| *delay);
+
+        if exceeded.is_some() {
+            self.usage.throttles += 1;
+        }
+        exceeded
+    }
+
+    /// Account for a sent message, returning the delay to apply, if any.
+    fn send(&mut self, bytes: u64, now: Instant) -> Option<Duration> {
+        self.usage.bytes_out += bytes;
+        self.bytes_out.take(bytes as f64, now)
+    }
+
+    /// Update the limits of the quota, in case the configuration changed.
+    fn update(&mut self, limits: &ResourceLimits, now: Instant) {
+        if &self.limits != limits {
+            let usage = self.usage.clone();
+            *self = Self::new(usage.protocol, limits, now);
+            self.usage = usage;
+        }
+    }
+}
+
+/// Resource accounting of a single channel.
+///
+/// Every channel has a quota covering all of its traffic, and a quota for
+/// each protocol with registered limits. Receiving messages over a quota
+/// throttles the peer, and getting throttled too often disconnects it.
+#[derive(Debug, Default)]
+pub struct ChannelResources {
+    /// Quotas by protocol name
+    quotas: Mutex<HashMap<&'static str, Quota>>,
+    /// Times the peer got throttled, within the configured window
+    throttles: Mutex<VecDeque<Instant>>,
+}
+
+impl ChannelResources {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Account for a received message of `bytes` length, while `pending`
+    /// messages are waiting in its subscriptions.
+    ///
+    /// `protocol` holds the name and limits of the protocol handling the
+    /// message, if it has any registered. The peer gets disconnected if
+    /// it got throttled more than `max_throttles` times within `window`.
+    pub fn record_recv(
+        &self,
+        channel_limits: &ResourceLimits,
+        protocol: Option<(&'static str, ResourceLimits)>,
+        bytes: u64,
+        pending: usize,
+        max_throttles: u32,
+        window: Duration,
+    ) -> Verdict {
+        let now = Instant::now();
+        let mut quotas = self.quotas.lock().unwrap();
+
+        let mut exceeded = vec![];
+        let mut apply = |name: &'static str, limits: &ResourceLimits| {
+            let quota = quotas.entry(name).or_insert_with(|| Quota::new(name, limits, now));
+            quota.update(limits, now);
+            if let Some((resource, delay)) = quota.recv(bytes, pending, now) {
+                exceeded.push((name, resource, delay));
+            }
+        };
+
+        apply(CHANNEL_QUOTA, channel_limits);
+        if let Some((name, limits)) = &protocol {
+            apply(name, limits);
+        }
+        drop(quotas);
+
+        // Backing off for pending messages is on us being slow to process
+        // them, so it doesn't count towards disconnecting the peer.
+        let misbehaved = exceeded.iter().any(|(_, resource, _)| *resource != Resource::Pending);
+
+        let Some((protocol, resource, delay)) =
+            exceeded.into_iter().max_by_key(|(_, _, delay)| *delay)
+        else {
+            return Verdict::Allow
+        };
+
+        if !misbehaved {
+            return Verdict::Throttle { protocol, resource, delay }
+        }
+
+        // Keep track of the throttles within the window
+        let mut throttles = self.throttles.lock().unwrap();
+        while throttles.front().is_some_and(|t| now.duration_since(*t) > window) {
+            throttles.pop_front();
+        }
+        throttles.push_back(now);
+
+        if throttles.len() > max_throttles as usize {
+            return Verdict::Disconnect { protocol, resource }
+        }
+
+        Verdict::Throttle { protocol, resource, delay }
+    }
+
+    /// Account for a sent message of `bytes` length. Returns the protocol
+    /// whose outbound rate got exceeded, along with how long to wait
+    /// before sending anything else.
+    pub fn record_send(
+        &self,
+        channel_limits: &ResourceLimits,
+        protocol: Option<(&'static str, ResourceLimits)>,
+        bytes: u64,
+    ) -> Option<(&'static str, Duration)> {
+        let now = Instant::now();
+        let mut quotas = self.quotas.lock().unwrap();
+
+        let mut delays = vec![];
+        let mut apply = |name: &'static str, limits: &ResourceLimits| {
+            let quota = quotas.entry(name).or_insert_with(|| Quota::new(name, limits, now));
+            quota.update(limits, now);
+            if let Some(delay) = quota.send(bytes, now) {
+                delays.push((name, delay));
+            }
+        };
+
+        apply(CHANNEL_QUOTA, channel_limits);
+        if let Some((name, limits)) = &protocol {
+            apply(name, limits);
+        }
+
+        delays.into_iter().max_by_key(|(_, delay)| *delay)
+    }
+
+    /// Returns the resources used by the channel, by protocol.
+    pub fn usage(&self) -> Vec<ResourceUsage> {
+        let quotas = self.quotas.lock().unwrap();
+        let mut usage: Vec<ResourceUsage> = quotas.values().map(|q| q.usage.clone()).collect();
+        usage.sort_by_key(|u| u.protocol);
+        usage
+    }
+}
+
+/// Resource limit settings used by a channel.
+///
+/// Channels take a snapshot of the P2P settings and the protocol registry
+/// limits when they get created, so enforcing the limits doesn't take
+/// their locks on every message. The snapshot gets replaced once
+/// [`ResourceManager::reload_limits`] signals they changed.
+#[derive(Debug)]
+pub struct LimitsSnapshot {
+    /// Generation of the limits the snapshot was taken at
+    generation: u64,
+    /// Resource limits applied to the channel as a whole
+    pub channel: ResourceLimits,
+    /// Protocol names and resource limits, by message command
+    pub protocols: HashMap<&'static str, (&'static str, ResourceLimits)>,
+    /// Number of times a peer can get throttled within `window`
+    pub max_throttles: u32,
+    /// Number of times a peer can get disconnected within `window`
+    pub max_disconnects: u32,
+    /// Window over which resource limit violations are counted
+    pub window: Duration,
+    /// Whether peers that keep exceeding their limits get banned
+    pub ban_policy: BanPolicy,
+}
+
+impl LimitsSnapshot {
+    pub async fn new(p2p: &P2p) -> Self {
+        // Grab the generation first, so changes made while we read the
+        // settings trigger another snapshot.
+        let generation = p2p.resource_manager().generation.load(Ordering::SeqCst);
+
+        let settings = p2p.settings();
+        let settings = settings.read().await;
+        let protocols = p2p.protocol_registry().all_limits(&settings.protocol_limits).await;
+
+        Self {
+            generation,
+            channel: settings.channel_limits.clone(),
+            protocols,
+            max_throttles: settings.resource_max_throttles,
+            max_disconnects: settings.resource_max_disconnects,
+            window: Duration::from_secs(settings.resource_window),
+            ban_policy: settings.ban_policy.clone(),
+        }
+    }
+
+    /// Returns `true` if the limits didn't change since the snapshot.
+    pub fn is_current(&self, manager: &ResourceManager) -> bool {
+        self.generation == manager.generation.load(Ordering::SeqCst)
+    }
+
+    /// Grab the protocol name and resource limits applying to given
+    /// message command, if any.
+    pub fn protocol(&self, command: &str) -> Option<(&'static str, ResourceLimits)> {
+        self.protocols.get(command).cloned()
+    }
+}
+
+/// P2P wide resource manager, keeping track of the peers that got
+/// disconnected for exceeding their limits, so repeat offenders get banned.
+#[derive(Debug, Default)]
+pub struct ResourceManager {
+    /// Disconnect times by peer host, within the configured window
+    offenses: Mutex<HashMap<String, VecDeque<Instant>>>,
+    /// Generation of the resource limits, bumped every time they change
+    generation: AtomicU64,
+}
+
+impl ResourceManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Signal the channels that the resource limits changed, so they
+    /// replace their [`LimitsSnapshot`]. Must be called after modifying
+    /// the resource limit fields of the P2P settings.
+    pub fn reload_limits(&self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Record a peer disconnect for exceeding its limits. Returns `true`
+    /// if the peer got disconnected more than `max_disconnects` times
+    /// within `window`, and should be banned.
+    pub fn record_disconnect(&self, peer: &str, max_disconnects: u32, window: Duration) -> bool {
+        let now = Instant::now();
+        let mut offenses = self.offenses.lock().unwrap();
+
+        // Drop the offenses that fell out of the window
+        offenses.retain(|_, times| {
+            while times.front().is_some_and(|t| now.duration_since(*t) > window) {
+                times.pop_front();
+            }
+            !times.is_empty()
+        });
+
+        let times = offenses.entry(peer.to_string()).or_default();
+        times.push_back(now);
+        if times.len() > max_disconnects as usize {
+            offenses.remove(peer);
+            return true
+        }
+
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOW: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn test_bucket_debt() {
+        let now = Instant::now();
+        let mut bucket = Bucket::new(10.0, 20.0, now);
+
+        // Burst goes through
+        for _ in 0..20 {
+            assert!(bucket.take(1.0, now).is_none());
+        }
+
+        // Going over puts us in debt
+        let delay = bucket.take(5.0, now).unwrap();
+        assert_eq!(delay, Duration::from_millis(500));
+
+        // The debt gets paid over time
+        assert!(bucket.take(1.0, now + Duration::from_secs(1)).is_none());
+
+        // Zero rate never limits
+        let mut bucket = Bucket::new(0.0, 0.0, now);
+        assert!(bucket.take(1e9, now).is_none());
+    }
+
+    #[test]
+    fn test_throttle_then_disconnect() {
+        let resources = ChannelResources::new();
+        let channel = ResourceLimits::unlimited();
+        let limits = ResourceLimits { msg_rate: 1, msg_burst: 1, ..ResourceLimits::unlimited() };
+        let protocol = Some(("ProtocolTest", limits));
+
+        for _ in 0..2 {
+            let verdict = resources.record_recv(&channel, protocol.clone(), 10, 0, 2, WINDOW);
+            assert_eq!(verdict, Verdict::Allow);
+        }
+
+        for _ in 0..2 {
+            let verdict = resources.record_recv(&channel, protocol.clone(), 10, 0, 2, WINDOW);
+            let Verdict::Throttle { protocol, resource, .. } = verdict else {
+                panic!("Expected throttle, got {:?}", verdict)
+            };
+            assert_eq!(protocol, "ProtocolTest");
+            assert_eq!(resource, Resource::Messages);
+        }
+
+        let verdict = resources.record_recv(&channel, protocol, 10, 0, 2, WINDOW);
+        assert_eq!(
+            verdict,
+            Verdict::Disconnect { protocol: "ProtocolTest", resource: Resource::Messages }
+        );
+
+        let usage = resources.usage();
+        assert_eq!(usage.len(), 2);
+        assert_eq!(usage[0].protocol, "ProtocolTest");
+        assert_eq!(usage[0].msgs_in, 5);
+        assert_eq!(usage[0].throttles, 3);
+        assert_eq!(usage[1].protocol, CHANNEL_QUOTA);
+        assert_eq!(usage[1].bytes_in, 50);
+        assert_eq!(usage[1].throttles, 0);
+    }
+
+    #[test]
+    fn test_pending_and_bytes() {
+        let resources = ChannelResources::new();
+        let channel = ResourceLimits {
+            bytes_in_rate: 100,
+            bytes_out_rate: 100,
+            max_pending: 4,
+            ..ResourceLimits::unlimited()
+        };
+
+        assert_eq!(resources.record_recv(&channel, None, 100, 4, 10, WINDOW), Verdict::Allow);
+
+        let verdict = resources.record_recv(&channel, None, 0, 5, 10, WINDOW);
+        let Verdict::Throttle { resource, delay, .. } = verdict else {
+            panic!("Expected throttle, got {:?}", verdict)
+        };
+        assert_eq!(resource, Resource::Pending);
+        assert_eq!(delay, PENDING_BACKOFF);
+
+        // Having too many pending messages is not the peer's fault,
+        // so it never gets it disconnected.
+        for _ in 0..10 {
+            let verdict = resources.record_recv(&channel, None, 0, 5, 0, WINDOW);
+            assert!(matches!(verdict, Verdict::Throttle { resource: Resource::Pending, .. }));
+        }
+
+        let verdict = resources.record_recv(&channel, None, 1000, 0, 10, WINDOW);
+        let Verdict::Throttle { resource, delay, .. } = verdict else {
+            panic!("Expected throttle, got {:?}", verdict)
+        };
+        assert_eq!(resource, Resource::BytesIn);
+        assert!(delay >= Duration::from_secs(9));
+
+        assert!(resources.record_send(&channel, None, 100).is_none());
+        let (protocol, _) = resources.record_send(&channel, None, 100).unwrap();
+        assert_eq!(protocol, CHANNEL_QUOTA);
+    }
+
+    #[test]
+    fn test_ban_repeat_offenders() {
+        let manager = ResourceManager::new();
+        assert!(!manager.record_disconnect("127.0.0.1", 2, WINDOW));
+        assert!(!manager.record_disconnect("127.0.0.1", 2, WINDOW));
+        assert!(!manager.record_disconnect("127.0.0.2", 2, WINDOW));
+        assert!(manager.record_disconnect("127.0.0.1", 2, WINDOW));
+
+        // The offenses got reset after the ban
+        assert!(!manager.record_disconnect("127.0.0.1", 2, WINDOW));
+    }
+}
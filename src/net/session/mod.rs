@@ -65,6 +65,7 @@ pub async fn remove_sub_on_stop(
     let addr = channel.address();
 
     stop_sub.receive().await;
+    p2p.metrics().connections(type_id).dec();
 
     debug!(
         target: "net::session::remove_sub_on_stop()",
@@ -119,23 +120,27 @@ pub trait Session: Sync {
     ) -> Result<()> {
         trace!(target: "net::session::register_channel()", "[START]");
 
-        // Protocols should all be initialized but not started.
-        // We do this so that the protocols can begin receiving and buffering
-        // messages while the handshake protocol is ongoing. They are currently
-        // in sleep mode.
+        // Perform the handshake protocol. Once the peer version is received,
+        // it initializes the protocols the peer supports, without starting
+        // them, so they can begin receiving and buffering messages while the
+        // handshake is finishing. They are currently in sleep mode.
         let p2p = self.p2p();
-        let protocols =
-            p2p.protocol_registry().attach(self.type_id(), channel.clone(), p2p.clone()).await;
-
-        // Perform the handshake protocol
-        let protocol_version = ProtocolVersion::new(channel.clone(), p2p.settings().clone()).await;
+        let protocol_version = ProtocolVersion::with_protocols(
+            channel.clone(),
+            p2p.settings().clone(),
+            self.type_id(),
+        )
+        .await;
         debug!(
             target: "net::session::register_channel()",
             "Performing handshake protocols {}", channel.clone().address(),
         );
 
-        let handshake_task =
-            self.perform_handshake_protocols(protocol_version, channel.clone(), executor.clone());
+        let handshake_task = self.perform_handshake_protocols(
+            protocol_version.clone(),
+            channel.clone(),
+            executor.clone(),
+        );
 
         // Switch on the channel
         channel.clone().start(executor.clone());
@@ -158,9 +163,10 @@ pub trait Session: Sync {
         debug!(target: "net::session::register_channel()", "Session handshake complete");
         debug!(target: "net::session::register_channel()", "Activating remaining protocols");
 
-        // Now start all the protocols. They are responsible for managing their own
-        // lifetimes and correctly selfdestructing when the channel ends.
-        for protocol in protocols {
+        // Now start all the protocols the peer supports. They are responsible for
+        // managing their own lifetimes and correctly selfdestructing when the
+        // channel ends.
+        for protocol in protocol_version.take_protocols().await {
             protocol.start(executor.clone()).await?;
         }
 
@@ -198,6 +204,7 @@ pub trait Session: Sync {
 
                 // Attempt to add channel to registry
                 self.p2p().hosts().register_channel(channel.clone()).await;
+                self.p2p().metrics().connections(self.type_id()).inc();
 
                 // Subscribe to stop, so we can remove from registry
                 executor
@@ -234,6 +234,9 @@ impl GreylistRefinery {
 
             sleep(greylist_refinery_interval).await;
 
+            // Delete stale and failing hosts.
+            hosts.prune();
+
             if hosts.container.is_empty(HostColor::Grey) {
                 debug!(target: "net::refinery",
                 "Greylist is empty! Cannot start refinery process");
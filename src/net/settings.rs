@@ -16,20 +16,23 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
+use std::collections::HashMap;
+
 use structopt::StructOpt;
 use url::Url;
 
+use super::resource_manager::ResourceLimits;
+
 type BlacklistEntry = (String, Vec<String>, Vec<u16>);
 
 /// Ban policies definitions.
 ///
 /// If the ban policy is set to `Relaxed` will not ban peers in case
-/// they send a message without a corresponding MessageDispatcher.
-/// This is useful for nodes that may not be subscribed to protocols,
-/// such as Lilith. For most uses this should be set to `Strict`.
-///
-/// TODO: this will be deprecated when we introduce the p2p resource
-/// mananger.
+/// they send a message without a corresponding MessageDispatcher, or
+/// keep exceeding their resource limits. Such peers still get
+/// disconnected. This is useful for nodes that may not be subscribed
+/// to protocols, such as Lilith. For most uses this should be set to
+/// `Strict`.
 #[derive(Clone, Debug, Default, PartialEq, serde::Deserialize, serde::Serialize)]
 #[serde(rename_all = "lowercase")]
 pub enum BanPolicy {
@@ -45,12 +48,17 @@ pub enum BanPolicy {
 pub struct Settings {
     /// Only used for debugging, compromises privacy when set
     pub node_id: String,
+    /// Name of the P2P network, used as the `network` label of its
+    /// metrics. Only read when the `P2p` instance gets created.
+    pub network_name: String,
     /// P2P accept addresses the instance listens on for inbound connections
     pub inbound_addrs: Vec<Url>,
     /// P2P external addresses the instance advertises so other peers can
     /// reach us and connect to us, as long as inbound addrs are configured
     pub external_addrs: Vec<Url>,
-    /// Peer nodes to manually connect to
+    /// Peer nodes to manually connect to. Peers on the Noise transports
+    /// can be pinned by their static public key, given as the URL fragment,
+    /// e.g. `tcp+noise://host:port#<pubkey>`.
     pub peers: Vec<Url>,
     /// Seed nodes to connect to for peer discovery and/or adversising our
     /// own external addresses
@@ -64,6 +72,8 @@ pub struct Settings {
     pub allowed_transports: Vec<String>,
     /// Allow transport mixing (e.g. Tor would be allowed to connect to `tcp://`)
     pub transport_mixing: bool,
+    /// Address of the SAM bridge of the I2P router used by `i2p://`
+    pub i2p_sam_bridge: Url,
     /// Outbound connection slots number, this many connections will be
     /// attempted. (This does not include manual connections)
     pub outbound_connections: usize,
@@ -107,6 +117,22 @@ pub struct Settings {
     /// Do not ban nodes that send messages without dispatchers if set
     /// to `Relaxed`. For most uses, should be set to `Strict`.
     pub ban_policy: BanPolicy,
+    /// Resource limits applied to every channel as a whole
+    pub channel_limits: ResourceLimits,
+    /// Resource limits of protocols, by protocol name. These override
+    /// the limits the protocols registered in the `ProtocolRegistry`,
+    /// and give limits to the protocols registered without any.
+    /// Channels only pick up runtime changes of the resource limit
+    /// settings after `ResourceManager::reload_limits()` gets called.
+    pub protocol_limits: HashMap<String, ResourceLimits>,
+    /// Number of times a peer can get throttled within `resource_window`
+    /// before getting disconnected
+    pub resource_max_throttles: u32,
+    /// Number of times a peer can get disconnected for exceeding its
+    /// resource limits within `resource_window` before getting banned
+    pub resource_max_disconnects: u32,
+    /// Window over which resource limit violations are counted (in seconds)
+    pub resource_window: u64,
 }
 
 impl Default for Settings {
@@ -116,6 +142,7 @@ impl Default for Settings {
 
         Self {
             node_id: String::new(),
+            network_name: "default".to_string(),
             inbound_addrs: vec![],
             external_addrs: vec![],
             magic_bytes: Default::default(),
@@ -124,6 +151,7 @@ impl Default for Settings {
             app_version,
             allowed_transports: vec!["tcp+tls".to_string()],
             transport_mixing: true,
+            i2p_sam_bridge: Url::parse("tcp://127.0.0.1:7656").unwrap(),
             outbound_connections: 8,
             inbound_connections: 8,
             outbound_connect_timeout: 15,
@@ -141,6 +169,11 @@ impl Default for Settings {
             time_with_no_connections: 30,
             blacklist: vec![],
             ban_policy: BanPolicy::Strict,
+            channel_limits: ResourceLimits::default(),
+            protocol_limits: HashMap::new(),
+            resource_max_throttles: 32,
+            resource_max_disconnects: 3,
+            resource_window: 60,
         }
     }
 }
@@ -188,7 +221,7 @@ pub struct SettingsOpt {
     #[structopt(long)]
     pub external_addrs: Vec<Url>,
 
-    /// Peer nodes to manually connect to
+    /// Peer nodes to manually connect to (`tcp+noise://host:port#<pubkey>` pins the peer key)
     #[serde(default)]
     #[structopt(long)]
     pub peers: Vec<Url>,
@@ -216,6 +249,10 @@ pub struct SettingsOpt {
     #[structopt(skip)]
     pub node_id: String,
 
+    /// Name of the P2P network, used as the `network` label of its metrics
+    #[structopt(skip)]
+    pub network_name: Option<String>,
+
     /// Preferred transports for outbound connections
     #[serde(default)]
     #[structopt(long = "transports")]
@@ -225,6 +262,10 @@ pub struct SettingsOpt {
     #[structopt(long)]
     pub transport_mixing: Option<bool>,
 
+    /// Address of the SAM bridge of the I2P router used by `i2p://`
+    #[structopt(long)]
+    pub i2p_sam_bridge: Option<Url>,
+
     /// If this is true, strictly follow the gold_connect_count and
     /// white_connect_percent settings. Otherwise, connect to greylist
     /// entries if we have no white or gold connections.
@@ -285,6 +326,29 @@ pub struct SettingsOpt {
     #[serde(default)]
     #[structopt(skip)]
     pub ban_policy: BanPolicy,
+
+    /// Resource limits applied to every channel as a whole
+    #[structopt(skip)]
+    pub channel_limits: Option<ResourceLimits>,
+
+    /// Resource limits of protocols, by protocol name
+    #[serde(default)]
+    #[structopt(skip)]
+    pub protocol_limits: HashMap<String, ResourceLimits>,
+
+    /// Number of times a peer can get throttled within `resource_window`
+    /// before getting disconnected
+    #[structopt(skip)]
+    pub resource_max_throttles: Option<u32>,
+
+    /// Number of times a peer can get disconnected for exceeding its
+    /// resource limits within `resource_window` before getting banned
+    #[structopt(skip)]
+    pub resource_max_disconnects: Option<u32>,
+
+    /// Window over which resource limit violations are counted, in seconds
+    #[structopt(skip)]
+    pub resource_window: Option<u64>,
 }
 
 impl From<SettingsOpt> for Settings {
@@ -293,6 +357,7 @@ impl From<SettingsOpt> for Settings {
 
         Self {
             node_id: opt.node_id,
+            network_name: opt.network_name.unwrap_or(def.network_name),
             inbound_addrs: opt.inbound,
             external_addrs: opt.external_addrs,
             magic_bytes: opt.magic_bytes,
@@ -301,6 +366,7 @@ impl From<SettingsOpt> for Settings {
             app_version: def.app_version,
             allowed_transports: opt.allowed_transports.unwrap_or(def.allowed_transports),
             transport_mixing: opt.transport_mixing.unwrap_or(def.transport_mixing),
+            i2p_sam_bridge: opt.i2p_sam_bridge.unwrap_or(def.i2p_sam_bridge),
             outbound_connections: opt.outbound_connections.unwrap_or(def.outbound_connections),
             inbound_connections: opt.inbound_connections.unwrap_or(def.inbound_connections),
             outbound_connect_timeout: opt
@@ -332,6 +398,15 @@ impl From<SettingsOpt> for Settings {
                 .unwrap_or(def.time_with_no_connections),
             blacklist: opt.blacklist,
             ban_policy: opt.ban_policy,
+            channel_limits: opt.channel_limits.unwrap_or(def.channel_limits),
+            protocol_limits: opt.protocol_limits,
+            resource_max_throttles: opt
+                .resource_max_throttles
+                .unwrap_or(def.resource_max_throttles),
+            resource_max_disconnects: opt
+                .resource_max_disconnects
+                .unwrap_or(def.resource_max_disconnects),
+            resource_window: opt.resource_window.unwrap_or(def.resource_window),
         }
     }
 }
@@ -0,0 +1,235 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2025 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+//! Network simulator running many [`P2p`] instances in a single process,
+//! connected with each other over the in-memory `mem://` transport.
+//!
+//! Every simulation gets its own [`MemoryNetwork`], whose link conditions
+//! can be changed at any point to add latency and loss, or to split the
+//! network into partitions. The choices of the simulator, e.g. which
+//! nodes to pick, and the draws of every link are derived from the
+//! simulation seed. The nodes themselves run on real time and their own
+//! RNGs though, so runs with the same seed aren't deterministic: reusing
+//! a seed recreates the same scenario, not the same sequence of events.
+
+use std::{
+    fs,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use log::info;
+use rand::{rngs::StdRng, seq::IteratorRandom, SeedableRng};
+use url::Url;
+
+use super::{
+    transport::memory::{LinkConditions, MemoryNetwork},
+    P2p, P2pPtr, Settings,
+};
+use crate::{system::ExecutorPtr, Result};
+
+/// Port the simulated nodes listen on
+const NODE_PORT: u16 = 26661;
+
+/// Counter used to give every simulation a distinct network
+static SIMULATIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// A simulated network of P2P nodes
+pub struct Simulator {
+    /// Seed of the simulation
+    seed: u64,
+    /// Memory network the nodes are connected to
+    network: Arc<MemoryNetwork>,
+    /// Executor the nodes run on
+    executor: ExecutorPtr,
+    /// Simulated nodes, by index
+    nodes: Vec<P2pPtr>,
+    /// RNG for the simulator choices, e.g. the network topology
+    rng: StdRng,
+    /// Directory holding the datastores of the nodes
+    datastore: PathBuf,
+}
+
+impl Simulator {
+    /// Create a new simulation with the given seed, running its nodes
+    /// on the given executor.
+    pub fn new(seed: u64, executor: ExecutorPtr) -> Self {
+        let id = SIMULATIONS.fetch_add(1, Ordering::SeqCst);
+        let network = MemoryNetwork::get(&format!("sim{}", id));
+        network.set_seed(seed);
+
+        let datastore = std::env::temp_dir().join(format!(
+            "darkfi_simulator_{}_{}",
+            std::process::id(),
+            network.name()
+        ));
+
+        info!(
+            target: "net::simulator",
+            "[P2P] Created simulation {} with seed {}", network.name(), seed,
+        );
+
+        Self { seed, network, executor, nodes: vec![], rng: StdRng::seed_from_u64(seed), datastore }
+    }
+
+    /// Return the seed of this simulation
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Return the memory network of this simulation
+    pub fn network(&self) -> &Arc<MemoryNetwork> {
+        &self.network
+    }
+
+    /// Return the seeded RNG of the simulator
+    pub fn rng(&mut self) -> &mut StdRng {
+        &mut self.rng
+    }
+
+    /// Return the simulated nodes
+    pub fn nodes(&self) -> &[P2pPtr] {
+        &self.nodes
+    }
+
+    /// Return the node at the given index
+    pub fn node(&self, index: usize) -> P2pPtr {
+        self.nodes[index].clone()
+    }
+
+    /// Return the name of the node at the given index on the memory network
+    pub fn node_name(&self, index: usize) -> String {
+        format!("node{}.{}", index, self.network.name())
+    }
+
+    /// Return the address the node at the given index listens on
+    pub fn addr(&self, index: usize) -> Url {
+        Url::parse(&format!("mem://{}:{}", self.node_name(index), NODE_PORT)).unwrap()
+    }
+
+    /// Find the index of the node owning the given address, which can
+    /// also be the address an inbound peer was seen from.
+    pub fn index_of(&self, addr: &Url) -> Option<usize> {
+        let host = addr.host_str()?;
+        (0..self.nodes.len()).find(|i| self.node_name(*i) == host)
+    }
+
+    /// Add a new node to the simulation. The given settings are used,
+    /// except for the addresses, transports and datastore of the node,
+    /// which are set up for the memory network.
+    pub async fn add_node(&mut self, mut settings: Settings) -> Result<P2pPtr> {
+        let index = self.nodes.len();
+        let name = self.node_name(index);
+        let addr = self.addr(index);
+        let datastore = self.datastore.join(format!("node{}", index)).to_string_lossy().to_string();
+
+        settings.inbound_addrs = vec![addr.clone()];
+        settings.external_addrs = vec![addr];
+        settings.allowed_transports = vec!["mem".to_string()];
+        settings.transport_mixing = false;
+        settings.localnet = false;
+        settings.p2p_datastore = Some(datastore.clone());
+        settings.hostlist = None;
+        if settings.node_id.is_empty() {
+            settings.node_id = name.clone();
+        }
+
+        self.network.register_node(&datastore, &name);
+
+        let p2p = P2p::new(settings, self.executor.clone()).await?;
+        self.nodes.push(p2p.clone());
+        Ok(p2p)
+    }
+
+    /// Add `n` nodes using the same settings
+    pub async fn add_nodes(&mut self, n: usize, settings: Settings) -> Result<Vec<P2pPtr>> {
+        let mut nodes = Vec::with_capacity(n);
+        for _ in 0..n {
+            nodes.push(self.add_node(settings.clone()).await?);
+        }
+        Ok(nodes)
+    }
+
+    /// Pick `n` distinct random node indexes, excluding `exclude`
+    pub fn random_nodes(&mut self, n: usize, exclude: &[usize]) -> Vec<usize> {
+        let candidates = (0..self.nodes.len()).filter(|i| !exclude.contains(i));
+        let mut picked = candidates.choose_multiple(&mut self.rng, n);
+        picked.sort_unstable();
+        picked
+    }
+
+    /// Start all the nodes of the simulation
+    pub async fn start(&self) -> Result<()> {
+        for node in &self.nodes {
+            node.clone().start().await?;
+        }
+        Ok(())
+    }
+
+    /// Stop all the nodes of the simulation
+    pub async fn stop(&self) {
+        for node in &self.nodes {
+            node.stop().await;
+        }
+    }
+
+    /// Set the conditions of links without specific ones
+    pub fn set_default_link(&self, conditions: LinkConditions) {
+        self.network.set_default_link(conditions);
+    }
+
+    /// Set the conditions of the link between nodes `a` and `b`
+    pub fn set_link(&self, a: usize, b: usize, conditions: LinkConditions) {
+        self.network.set_link(&self.node_name(a), &self.node_name(b), conditions);
+    }
+
+    /// Split the network into the given groups of node indexes. Nodes
+    /// left out of all groups still reach everyone.
+    pub fn partition(&self, groups: &[&[usize]]) {
+        let groups: Vec<Vec<String>> =
+            groups.iter().map(|group| group.iter().map(|i| self.node_name(*i)).collect()).collect();
+        self.network.partition(&groups);
+    }
+
+    /// Remove all partitions
+    pub fn heal(&self) {
+        self.network.heal();
+    }
+
+    /// Return the indexes of the nodes the given node is connected to,
+    /// excluding seed and refinery connections.
+    pub fn peers_of(&self, index: usize) -> Vec<usize> {
+        let mut peers: Vec<usize> = self.nodes[index]
+            .hosts()
+            .peers()
+            .iter()
+            .filter_map(|channel| self.index_of(channel.address()))
+            .collect();
+        peers.sort_unstable();
+        peers
+    }
+}
+
+impl Drop for Simulator {
+    fn drop(&mut self) {
+        MemoryNetwork::remove(self.network.name());
+        let _ = fs::remove_dir_all(&self.datastore);
+    }
+}
@@ -473,3 +473,142 @@ async fn p2p_test_real(ex: Arc<Executor<'static>>) {
         p2p.clone().stop().await;
     }
 }
+
+// cargo +nightly test --release --features=net,p2p-memory --lib p2p_simulated -- --include-ignored
+
+#[cfg(feature = "p2p-memory")]
+mod simulated {
+    use super::*;
+    use crate::net::{
+        session::SESSION_OUTBOUND, simulator::Simulator, transport::memory::LinkConditions,
+    };
+    use std::time::Duration;
+
+    // Seed of the simulations, change it to explore other scenarios
+    const SEED: u64 = 42;
+
+    // Number of nodes in the simulations, including the seed node.
+    // Each simulation takes around half a minute, so these tests are
+    // ignored by default. Run them with `cargo test -- --ignored`.
+    const N_SIM_NODES: usize = 40;
+
+    fn sim_settings() -> Settings {
+        Settings {
+            outbound_connections: 4,
+            inbound_connections: usize::MAX,
+            outbound_connect_timeout: 2,
+            channel_heartbeat_interval: 1,
+            outbound_peer_discovery_cooloff_time: 1,
+            outbound_peer_discovery_attempt_time: 1,
+            greylist_refinery_interval: 1,
+            ..Default::default()
+        }
+    }
+
+    /// Spawn a simulation with node 0 acting as the seed of all others
+    async fn spawn_simulation(ex: Arc<Executor<'static>>) -> Simulator {
+        let mut sim = Simulator::new(SEED, ex);
+        sim.set_default_link(LinkConditions {
+            latency: Duration::from_millis(20),
+            jitter: Duration::from_millis(10),
+            loss: 0.01,
+        });
+
+        let seed_settings = Settings { outbound_connections: 0, ..sim_settings() };
+        sim.add_node(seed_settings).await.unwrap();
+
+        let settings = Settings { seeds: vec![sim.addr(0)], ..sim_settings() };
+        sim.add_nodes(N_SIM_NODES - 1, settings).await.unwrap();
+
+        sim.start().await.unwrap();
+        sim
+    }
+
+    fn outbound_count(sim: &Simulator, index: usize) -> usize {
+        let peers = sim.node(index).hosts().peers();
+        peers.iter().filter(|c| c.session_type_id() & SESSION_OUTBOUND != 0).count()
+    }
+
+    /// Nodes keep losing and replacing outbound peers, so rather than
+    /// checking a single snapshot, poll `cond` every second until it
+    /// holds, for at most `secs` seconds.
+    async fn wait_for(secs: u64, cond: impl Fn() -> bool) {
+        for _ in 0..secs {
+            if cond() {
+                return
+            }
+            sleep(1).await;
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn p2p_simulated_test() {
+        test_body!(p2p_simulated_test_real);
+    }
+
+    async fn p2p_simulated_test_real(ex: Arc<Executor<'static>>) {
+        let sim = spawn_simulation(ex).await;
+
+        info!("Waiting for the simulated network to settle");
+        sleep(15).await;
+        wait_for(15, || {
+            (1..N_SIM_NODES).all(|i| {
+                outbound_count(&sim, i) > 0 &&
+                    !sim.node(i).hosts().container.is_empty(HostColor::Gold)
+            })
+        })
+        .await;
+
+        // The seed refinery whitelisted the nodes that reached it
+        assert!(!sim.node(0).hosts().container.is_empty(HostColor::White));
+
+        // Every node filled its outbound slots through peer discovery
+        for i in 1..N_SIM_NODES {
+            assert!(outbound_count(&sim, i) > 0, "node{} has no outbound peers", i);
+            assert!(!sim.node(i).hosts().container.is_empty(HostColor::Gold));
+        }
+
+        sim.stop().await;
+    }
+
+    #[test]
+    #[ignore]
+    fn p2p_simulated_partition() {
+        test_body!(p2p_simulated_partition_real);
+    }
+
+    async fn p2p_simulated_partition_real(ex: Arc<Executor<'static>>) {
+        let sim = spawn_simulation(ex).await;
+
+        info!("Waiting 15s for the simulated network to settle");
+        sleep(15).await;
+
+        let half = N_SIM_NODES / 2;
+        let left: Vec<usize> = (0..half).collect();
+        let right: Vec<usize> = (half..N_SIM_NODES).collect();
+        sim.partition(&[&left, &right]);
+
+        info!("Waiting for the partition to break the links");
+        sleep(10).await;
+        wait_for(10, || {
+            (0..N_SIM_NODES).all(|i| {
+                let side = if i < half { &left } else { &right };
+                sim.peers_of(i).iter().all(|peer| side.contains(peer))
+            })
+        })
+        .await;
+
+        // No connection is left between the two sides, and the nodes
+        // reconnected within their own side.
+        for i in 0..N_SIM_NODES {
+            let side = if i < half { &left } else { &right };
+            for peer in sim.peers_of(i) {
+                assert!(side.contains(&peer), "node{} still connected to node{}", i, peer);
+            }
+        }
+
+        sim.heal();
+        sim.stop().await;
+    }
+}
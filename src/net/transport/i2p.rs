@@ -0,0 +1,545 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2025 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+//! I2P transport, talking the SAMv3 protocol to a local I2P router.
+//!
+//! Every SAM session is bound to a control socket which has to stay
+//! open for as long as the session is used. Streams are then opened
+//! on new sockets to the SAM bridge with `STREAM CONNECT` and
+//! `STREAM ACCEPT`, after which the socket carries the raw stream.
+//!
+//! Outbound connections use a transient destination, so they can't be
+//! linked to our listener. The listener destination is kept in the P2P
+//! datastore, which gives us a stable `i2p://<b32>.b32.i2p:port`
+//! address across restarts.
+
+use std::{
+    collections::HashMap,
+    io::{self, ErrorKind},
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use log::{debug, info, warn};
+use rand::{rngs::OsRng, Rng};
+use sha2::{Digest, Sha256};
+use smol::{
+    fs,
+    io::{AsyncReadExt, AsyncWriteExt},
+    lock::{Mutex, OnceCell},
+    net::TcpStream,
+    Timer,
+};
+use url::Url;
+
+use super::{PtListener, PtStream};
+use crate::{
+    system::timeout::timeout,
+    util::{
+        encoding::{base32, base64},
+        path::expand_path,
+    },
+};
+
+/// Default address of the SAM bridge of a local I2P router
+const DEFAULT_SAM_BRIDGE: &str = "tcp://127.0.0.1:7656";
+
+/// Handshake announcing the SAM versions we speak
+const SAM_HELLO: &str = "HELLO VERSION MIN=3.1 MAX=3.3";
+
+/// Signature type of the destinations we create
+const SIGNATURE_TYPE: &str = "EdDSA_SHA512_Ed25519";
+
+/// File in the P2P datastore holding our private destination
+const DESTINATION_FILE: &str = "i2p_destination.key";
+
+/// Upper bound on the length of a line sent by the SAM bridge
+const MAX_LINE_LEN: usize = 16384;
+
+/// Time to wait before accepting again after the SAM bridge failed
+const ACCEPT_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Length of the base32-encoded hash in a `.b32.i2p` address
+const B32_LEN: usize = 52;
+
+/// Address of the SAM bridge, as configured in the P2P settings
+static SAM_BRIDGE: RwLock<Option<Url>> = RwLock::new(None);
+
+/// Transient session used for outbound connections
+static DIALER_SESSION: Mutex<Option<Arc<SamSession>>> = Mutex::new(None);
+
+/// Listener sessions, by datastore path. A destination can only be
+/// bound to a single session on the router, so they get shared.
+static LISTENER_SESSIONS: Mutex<Vec<(Option<String>, Arc<SamSession>)>> = Mutex::new(Vec::new());
+
+/// Set the address of the SAM bridge used by the I2P transport.
+pub fn set_sam_bridge(bridge: Url) {
+    *SAM_BRIDGE.write().unwrap() = Some(bridge);
+}
+
+/// Return the configured SAM bridge address
+fn sam_bridge() -> Url {
+    match SAM_BRIDGE.read().unwrap().as_ref() {
+        Some(bridge) => bridge.clone(),
+        None => Url::parse(DEFAULT_SAM_BRIDGE).unwrap(),
+    }
+}
+
+/// Check whether the given host is a valid `.b32.i2p` address
+pub(crate) fn is_b32_address(host: &str) -> bool {
+    let Some(hash) = host.strip_suffix(".b32.i2p") else { return false };
+    hash.len() == B32_LEN &&
+        hash.bytes().all(|c| c.is_ascii_lowercase() || (b'2'..=b'7').contains(&c))
+}
+
+/// Derive the `.b32.i2p` address of the given base64-encoded destination
+fn b32_address(destination: &str) -> io::Result<String> {
+    // I2P uses a base64 alphabet with `-` and `~` in place of `+` and `/`
+    let standard = destination.replace('-', "+").replace('~', "/");
+    let Some(bytes) = base64::decode(&standard) else {
+        return Err(io::Error::new(ErrorKind::InvalidData, "Invalid I2P destination"))
+    };
+
+    let hash = Sha256::digest(bytes);
+    Ok(format!("{}.b32.i2p", base32::encode(false, &hash).to_lowercase()))
+}
+
+/// A reply sent by the SAM bridge, e.g. `STREAM STATUS RESULT=OK`
+struct SamReply {
+    topic: String,
+    kind: String,
+    args: HashMap<String, String>,
+}
+
+impl SamReply {
+    /// Parse a reply line. Values can be quoted when they contain spaces.
+    fn parse(line: &str) -> Self {
+        let mut tokens = vec![];
+        let mut token = String::new();
+        let mut quoted = false;
+
+        for c in line.chars() {
+            match c {
+                '"' => quoted = !quoted,
+                ' ' if !quoted => {
+                    if !token.is_empty() {
+                        tokens.push(std::mem::take(&mut token));
+                    }
+                }
+                c => token.push(c),
+            }
+        }
+
+        if !token.is_empty() {
+            tokens.push(token);
+        }
+
+        let mut tokens = tokens.into_iter();
+        let topic = tokens.next().unwrap_or_default();
+        let kind = tokens.next().unwrap_or_default();
+
+        let mut args = HashMap::new();
+        for token in tokens {
+            match token.split_once('=') {
+                Some((k, v)) => args.insert(k.to_string(), v.to_string()),
+                None => args.insert(token, String::new()),
+            };
+        }
+
+        Self { topic, kind, args }
+    }
+
+    /// Make sure this is the expected reply and that it reports success
+    fn check(self, topic: &str, kind: &str) -> io::Result<Self> {
+        if self.topic != topic || self.kind != kind {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("Unexpected SAM reply: {} {}", self.topic, self.kind),
+            ))
+        }
+
+        let result = self.args.get("RESULT").map(String::as_str).unwrap_or("OK");
+        let kind = match result {
+            "OK" => return Ok(self),
+            "CANT_REACH_PEER" | "PEER_NOT_FOUND" => ErrorKind::ConnectionRefused,
+            "TIMEOUT" => ErrorKind::TimedOut,
+            "KEY_NOT_FOUND" => ErrorKind::NotFound,
+            "DUPLICATED_ID" | "DUPLICATED_DEST" => ErrorKind::AddrInUse,
+            "INVALID_ID" => ErrorKind::NotConnected,
+            "INVALID_KEY" => ErrorKind::InvalidInput,
+            _ => ErrorKind::Other,
+        };
+
+        let message = match self.args.get("MESSAGE") {
+            Some(message) => format!("SAM error {}: {}", result, message),
+            None => format!("SAM error {}", result),
+        };
+
+        Err(io::Error::new(kind, message))
+    }
+
+    /// Fetch a required argument of the reply
+    fn arg(&self, key: &str) -> io::Result<&str> {
+        match self.args.get(key) {
+            Some(v) => Ok(v),
+            None => {
+                Err(io::Error::new(ErrorKind::InvalidData, format!("SAM reply is missing {}", key)))
+            }
+        }
+    }
+}
+
+/// Read a single line from the SAM bridge. This is done a byte at a time,
+/// since the stream data follows right after the reply on the same socket.
+async fn read_line(stream: &mut TcpStream) -> io::Result<String> {
+    let mut line = vec![];
+    let mut byte = [0u8; 1];
+
+    loop {
+        stream.read_exact(&mut byte).await?;
+        match byte[0] {
+            b'\n' => break,
+            b => line.push(b),
+        }
+
+        if line.len() > MAX_LINE_LEN {
+            return Err(io::Error::new(ErrorKind::InvalidData, "SAM line too long"))
+        }
+    }
+
+    match String::from_utf8(line) {
+        Ok(line) => Ok(line.trim_end_matches('\r').to_string()),
+        Err(_) => Err(io::Error::new(ErrorKind::InvalidData, "SAM line is not UTF-8")),
+    }
+}
+
+/// Send a command to the SAM bridge and read back its reply
+async fn command(stream: &mut TcpStream, cmd: &str) -> io::Result<SamReply> {
+    stream.write_all(format!("{}\n", cmd).as_bytes()).await?;
+    let line = read_line(stream).await?;
+    Ok(SamReply::parse(&line))
+}
+
+/// Open a new socket to the SAM bridge and do the version handshake
+async fn connect_bridge() -> io::Result<TcpStream> {
+    let bridge = sam_bridge();
+    let sockaddr = bridge.socket_addrs(|| None)?;
+    let Some(sockaddr) = sockaddr.first() else {
+        return Err(io::Error::new(ErrorKind::NotFound, "SAM bridge address not resolved"))
+    };
+
+    let mut stream = TcpStream::connect(sockaddr).await?;
+    command(&mut stream, SAM_HELLO).await?.check("HELLO", "REPLY")?;
+    Ok(stream)
+}
+
+/// A SAM stream session, alive as long as its control socket is open
+struct SamSession {
+    /// Session ID, unique on the router
+    id: String,
+    /// Our public destination
+    destination: String,
+    /// Control socket, also used for naming lookups
+    control: Mutex<TcpStream>,
+}
+
+impl SamSession {
+    /// Create a new session using the given private destination, or a
+    /// transient one if none is given. Returns the session along with its
+    /// private destination.
+    async fn create(private: Option<&str>) -> io::Result<(Self, String)> {
+        let id = format!("darkfi-{:016x}", OsRng.gen::<u64>());
+        let mut control = connect_bridge().await?;
+
+        let cmd = match private {
+            Some(private) => {
+                format!("SESSION CREATE STYLE=STREAM ID={} DESTINATION={}", id, private)
+            }
+            None => format!(
+                "SESSION CREATE STYLE=STREAM ID={} DESTINATION=TRANSIENT SIGNATURE_TYPE={}",
+                id, SIGNATURE_TYPE,
+            ),
+        };
+
+        let reply = command(&mut control, &cmd).await?.check("SESSION", "STATUS")?;
+        let private = reply.arg("DESTINATION")?.to_string();
+
+        let reply = command(&mut control, "NAMING LOOKUP NAME=ME").await?;
+        let destination = reply.check("NAMING", "REPLY")?.arg("VALUE")?.to_string();
+
+        debug!(target: "net::i2p::SamSession::create", "Created SAM session {}", id);
+        Ok((Self { id, destination, control: Mutex::new(control) }, private))
+    }
+
+    /// Resolve an I2P hostname into its base64-encoded destination
+    async fn lookup(&self, name: &str) -> io::Result<String> {
+        let mut control = self.control.lock().await;
+        let reply = command(&mut control, &format!("NAMING LOOKUP NAME={}", name)).await?;
+        Ok(reply.check("NAMING", "REPLY")?.arg("VALUE")?.to_string())
+    }
+
+    /// Open a stream to the given destination and port
+    async fn connect(&self, host: &str, port: u16) -> io::Result<TcpStream> {
+        let destination = self.lookup(host).await?;
+
+        let mut stream = connect_bridge().await?;
+        let cmd = format!(
+            "STREAM CONNECT ID={} DESTINATION={} SILENT=false TO_PORT={}",
+            self.id, destination, port,
+        );
+        command(&mut stream, &cmd).await?.check("STREAM", "STATUS")?;
+
+        Ok(stream)
+    }
+
+    /// Wait for an inbound stream. Returns the stream along with the
+    /// destination of the peer and the ports it dialed from and to.
+    async fn accept(&self) -> io::Result<(TcpStream, String, u16, Option<u16>)> {
+        let mut stream = connect_bridge().await?;
+        let cmd = format!("STREAM ACCEPT ID={} SILENT=false", self.id);
+        command(&mut stream, &cmd).await?.check("STREAM", "STATUS")?;
+
+        // Once a peer connects, the bridge sends us a line with its
+        // destination, followed by the stream data.
+        let line = read_line(&mut stream).await?;
+        let mut parts = line.split(' ');
+        let destination = parts.next().unwrap_or_default().to_string();
+
+        let mut from_port = 0;
+        let mut to_port = None;
+        for part in parts {
+            match part.split_once('=') {
+                Some(("FROM_PORT", v)) => from_port = v.parse().unwrap_or(0),
+                Some(("TO_PORT", v)) => to_port = v.parse().ok(),
+                _ => {}
+            }
+        }
+
+        Ok((stream, destination, from_port, to_port))
+    }
+}
+
+/// Return the transient session for outbound connections, creating a
+/// new one if there is none yet, or if `stale` is the current one.
+async fn dialer_session(stale: Option<&Arc<SamSession>>) -> io::Result<Arc<SamSession>> {
+    let mut session = DIALER_SESSION.lock().await;
+
+    if let Some(s) = session.as_ref() {
+        if stale.is_none_or(|stale| !Arc::ptr_eq(s, stale)) {
+            return Ok(s.clone())
+        }
+    }
+
+    let (s, _) = SamSession::create(None).await?;
+    let s = Arc::new(s);
+    *session = Some(s.clone());
+    Ok(s)
+}
+
+/// Return the listener session of the given datastore, creating a new
+/// one if there is none yet, or if `stale` is the current one. Its
+/// destination is read from the datastore, or created and stored there
+/// if it doesn't exist yet. Without a datastore, a transient destination
+/// is used.
+async fn listener_session(
+    datastore: Option<String>,
+    stale: Option<&Arc<SamSession>>,
+) -> io::Result<Arc<SamSession>> {
+    let mut sessions = LISTENER_SESSIONS.lock().await;
+    if let Some(i) = sessions.iter().position(|(d, _)| d == &datastore) {
+        if stale.is_none_or(|stale| !Arc::ptr_eq(&sessions[i].1, stale)) {
+            return Ok(sessions[i].1.clone())
+        }
+
+        // Close the control socket of the stale session first, since
+        // the destination can't be bound to two sessions.
+        sessions.remove(i);
+    }
+
+    let session = match &datastore {
+        Some(datadir) => {
+            let datadir = expand_path(datadir)
+                .map_err(|e| io::Error::new(ErrorKind::InvalidInput, e.to_string()))?;
+            let path = datadir.join(DESTINATION_FILE);
+
+            if path.exists() {
+                let private = fs::read_to_string(&path).await?;
+                SamSession::create(Some(private.trim())).await?.0
+            } else {
+                let (session, private) = SamSession::create(None).await?;
+                fs::create_dir_all(&datadir).await?;
+                write_destination(&path, &private).await?;
+                info!(
+                    target: "net::i2p::listener_session",
+                    "[P2P] Generated new I2P destination in {}", path.display(),
+                );
+                session
+            }
+        }
+
+        None => {
+            warn!(
+                target: "net::i2p::listener_session",
+                "[P2P] No P2P datastore configured, using a transient I2P destination",
+            );
+            SamSession::create(None).await?.0
+        }
+    };
+
+    let session = Arc::new(session);
+    sessions.push((datastore, session.clone()));
+    Ok(session)
+}
+
+/// Write the private destination to the given path, readable by the
+/// owner only.
+async fn write_destination(path: &std::path::Path, private: &str) -> io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+
+    #[cfg(unix)]
+    {
+        use smol::fs::unix::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options.open(path).await?;
+    file.write_all(private.as_bytes()).await?;
+    file.flush().await
+}
+
+/// I2P Dialer implementation
+#[derive(Debug, Clone)]
+pub struct I2pDialer;
+
+impl I2pDialer {
+    /// Instantiate a new [`I2pDialer`] object
+    pub(crate) async fn new() -> io::Result<Self> {
+        Ok(Self)
+    }
+
+    /// Internal dial function
+    pub(crate) async fn do_dial(
+        &self,
+        host: &str,
+        port: u16,
+        conn_timeout: Option<Duration>,
+    ) -> io::Result<TcpStream> {
+        debug!(target: "net::i2p::do_dial", "Dialing {}:{} with I2P...", host, port);
+
+        let connect = async {
+            let session = dialer_session(None).await?;
+            match session.connect(host, port).await {
+                // The router forgot our session, e.g. because it restarted
+                Err(e) if e.kind() == ErrorKind::NotConnected => {
+                    let session = dialer_session(Some(&session)).await?;
+                    session.connect(host, port).await
+                }
+                ret => ret,
+            }
+        };
+
+        match conn_timeout {
+            Some(t) => match timeout(t, connect).await {
+                Ok(ret) => ret,
+                Err(_) => Err(ErrorKind::TimedOut.into()),
+            },
+            None => connect.await,
+        }
+    }
+}
+
+/// I2P Listener implementation
+#[derive(Debug, Clone)]
+pub struct I2pListener {
+    datastore: Option<String>,
+    pub endpoint: Arc<OnceCell<Url>>,
+}
+
+impl I2pListener {
+    /// Instantiate a new [`I2pListener`]
+    pub async fn new(datastore: Option<String>) -> io::Result<Self> {
+        Ok(Self { datastore, endpoint: Arc::new(OnceCell::new()) })
+    }
+
+    /// Internal listen function
+    pub(crate) async fn do_listen(&self, port: u16) -> io::Result<I2pListenerIntern> {
+        let session = listener_session(self.datastore.clone(), None).await?;
+        let b32 = b32_address(&session.destination)?;
+
+        info!(
+            target: "net::i2p::do_listen",
+            "[P2P] Established I2P listener on i2p://{}:{}", b32, port,
+        );
+
+        let endpoint = Url::parse(&format!("i2p://{}:{}", b32, port)).unwrap();
+        self.endpoint.set(endpoint).await.expect("fatal endpoint already set for I2pListener");
+
+        Ok(I2pListenerIntern {
+            port,
+            datastore: self.datastore.clone(),
+            session: Mutex::new(session),
+        })
+    }
+}
+
+/// Internal I2P Listener implementation, used with `PtListener`
+pub struct I2pListenerIntern {
+    port: u16,
+    datastore: Option<String>,
+    session: Mutex<Arc<SamSession>>,
+}
+
+#[async_trait]
+impl PtListener for I2pListenerIntern {
+    async fn next(&self) -> io::Result<(Box<dyn PtStream>, Url)> {
+        let session = self.session.lock().await.clone();
+
+        let (stream, destination, from_port, to_port) = match session.accept().await {
+            Ok(v) => v,
+            Err(e) => {
+                warn!(
+                    target: "net::i2p::PtListener::next",
+                    "[P2P] Failed accepting I2P stream: {}", e,
+                );
+
+                // The router forgot our session, e.g. because it restarted
+                if e.kind() == ErrorKind::NotConnected {
+                    if let Ok(s) = listener_session(self.datastore.clone(), Some(&session)).await {
+                        *self.session.lock().await = s;
+                    }
+                }
+
+                // Don't spin on the accept loop while the router is unavailable
+                Timer::after(ACCEPT_RETRY_DELAY).await;
+                return Err(io::Error::new(ErrorKind::ConnectionAborted, "Connection Aborted"))
+            }
+        };
+
+        // Validate port correctness. Old routers don't send the ports.
+        if to_port.is_some_and(|p| p != self.port) {
+            return Err(io::Error::new(ErrorKind::ConnectionAborted, "Connection Aborted"))
+        }
+
+        let Ok(b32) = b32_address(&destination) else {
+            return Err(io::Error::new(ErrorKind::ConnectionAborted, "Connection Aborted"))
+        };
+
+        Ok((Box::new(stream), Url::parse(&format!("i2p://{}:{}", b32, from_port)).unwrap()))
+    }
+}
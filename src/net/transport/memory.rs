@@ -0,0 +1,513 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2025 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+//! In-memory transport, used to run many nodes inside a single process.
+//!
+//! Endpoints look like `mem://<node>.<network>:<port>`. Every memory
+//! network is isolated from the others and holds the conditions of the
+//! links between its nodes: latency, jitter, chunk loss and partitions.
+//! Loss is modelled the way TCP experiences it, so a lost chunk is
+//! retransmitted and arrives late, rather than corrupting the stream.
+//! Every link draws its random decisions from its own RNG, derived from
+//! the network seed and the names of its nodes, so the draws of a link
+//! don't depend on the traffic of the others. Streams are still driven by
+//! real timers and the executor, so the interleaving of events, and with
+//! it the outcome of a simulation, isn't reproducible across runs.
+//!
+//! Dialers don't know which node they belong to, so a network identifies
+//! them by the P2P datastore path registered with [`MemoryNetwork::register_node`].
+//! Dialers of unregistered datastores are subject to the default link
+//! conditions and aren't affected by partitions.
+
+use std::{
+    collections::HashMap,
+    future::Future,
+    io::{self, ErrorKind},
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use futures::Stream;
+use log::debug;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use smol::{
+    channel::{self, Receiver, Sender},
+    io::{AsyncRead, AsyncWrite},
+    Timer,
+};
+use url::Url;
+
+use super::{PtListener, PtStream};
+
+/// Extra delay of a lost chunk, on top of the link latency
+const RETRANSMIT_DELAY: Duration = Duration::from_millis(200);
+
+/// Ports assigned to the dialing side of connections
+const EPHEMERAL_PORTS: std::ops::Range<u16> = 49152..65535;
+
+/// Registered memory networks
+static NETWORKS: Mutex<Vec<Arc<MemoryNetwork>>> = Mutex::new(Vec::new());
+
+/// Conditions of a link between two memory network nodes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LinkConditions {
+    /// One-way delay of every chunk of data
+    pub latency: Duration,
+    /// Upper bound of the random delay added to the latency
+    pub jitter: Duration,
+    /// Probability of a chunk getting lost and retransmitted, in `[0, 1]`
+    pub loss: f64,
+}
+
+impl Default for LinkConditions {
+    fn default() -> Self {
+        Self { latency: Duration::ZERO, jitter: Duration::ZERO, loss: 0.0 }
+    }
+}
+
+/// Mutable state of a [`MemoryNetwork`]
+struct NetworkState {
+    /// Listeners, by `node:port`, along with their unique ID
+    listeners: HashMap<String, (u64, Sender<(MemoryStream, Url)>)>,
+    /// Nodes, by the datastore path of their dialers
+    nodes: HashMap<String, String>,
+    /// Link conditions of node pairs, ordered by name
+    links: HashMap<(String, String), LinkConditions>,
+    /// Conditions of links not found in `links`
+    default_link: LinkConditions,
+    /// Partition group of nodes. Nodes in distinct groups can't reach
+    /// each other, while nodes without a group reach everyone.
+    partitions: HashMap<String, usize>,
+    /// Seed the link RNGs are derived from
+    seed: u64,
+    /// RNGs of the links that already drew a random decision
+    rngs: HashMap<(String, String), StdRng>,
+    /// Counter used for listener IDs and ephemeral ports
+    next_id: u64,
+}
+
+/// An isolated in-memory network
+pub struct MemoryNetwork {
+    name: String,
+    state: Mutex<NetworkState>,
+}
+
+impl MemoryNetwork {
+    /// Fetch the memory network with the given name, creating it if it
+    /// doesn't exist yet.
+    pub fn get(name: &str) -> Arc<Self> {
+        let mut networks = NETWORKS.lock().unwrap();
+        if let Some(network) = networks.iter().find(|n| n.name == name) {
+            return network.clone()
+        }
+
+        let state = NetworkState {
+            listeners: HashMap::new(),
+            nodes: HashMap::new(),
+            links: HashMap::new(),
+            default_link: LinkConditions::default(),
+            partitions: HashMap::new(),
+            seed: 0,
+            rngs: HashMap::new(),
+            next_id: 0,
+        };
+
+        let network = Arc::new(Self { name: name.to_string(), state: Mutex::new(state) });
+        networks.push(network.clone());
+        network
+    }
+
+    /// Fetch the memory network the given host belongs to
+    fn of_host(host: &str) -> Arc<Self> {
+        Self::get(host.split_once('.').map(|(_, network)| network).unwrap_or_default())
+    }
+
+    /// Remove the memory network with the given name
+    pub fn remove(name: &str) {
+        NETWORKS.lock().unwrap().retain(|n| n.name != name);
+    }
+
+    /// Return the name of this network
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Set the seed the link RNGs are derived from, restarting them
+    pub fn set_seed(&self, seed: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.seed = seed;
+        state.rngs.clear();
+    }
+
+    /// Identify the dialers using the given datastore path as `node`
+    pub fn register_node(&self, datastore: &str, node: &str) {
+        self.state.lock().unwrap().nodes.insert(datastore.to_string(), node.to_string());
+    }
+
+    /// Set the conditions of links without specific ones
+    pub fn set_default_link(&self, conditions: LinkConditions) {
+        self.state.lock().unwrap().default_link = conditions;
+    }
+
+    /// Set the conditions of the link between nodes `a` and `b`
+    pub fn set_link(&self, a: &str, b: &str, conditions: LinkConditions) {
+        self.state.lock().unwrap().links.insert(link_key(a, b), conditions);
+    }
+
+    /// Split the network into the given groups of nodes. Existing
+    /// connections between groups break on their next transmission.
+    pub fn partition(&self, groups: &[Vec<String>]) {
+        let mut state = self.state.lock().unwrap();
+        state.partitions.clear();
+        for (i, group) in groups.iter().enumerate() {
+            for node in group {
+                state.partitions.insert(node.clone(), i);
+            }
+        }
+    }
+
+    /// Remove all partitions
+    pub fn heal(&self) {
+        self.state.lock().unwrap().partitions.clear();
+    }
+
+    /// Check whether `a` can reach `b`
+    fn reachable(&self, a: &str, b: &str) -> bool {
+        let state = self.state.lock().unwrap();
+        match (state.partitions.get(a), state.partitions.get(b)) {
+            (Some(a), Some(b)) => a == b,
+            _ => true,
+        }
+    }
+
+    /// Draw the delay of a chunk sent from `a` to `b`
+    fn delay(&self, a: &str, b: &str) -> Duration {
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+        let key = link_key(a, b);
+        let conditions = state.links.get(&key).copied().unwrap_or(state.default_link);
+
+        let seed = link_seed(state.seed, &key);
+        let rng = state.rngs.entry(key).or_insert_with(|| StdRng::seed_from_u64(seed));
+
+        let mut delay = conditions.latency;
+        if !conditions.jitter.is_zero() {
+            delay += conditions.jitter.mul_f64(rng.gen::<f64>());
+        }
+
+        if conditions.loss > 0.0 && rng.gen_bool(conditions.loss.min(1.0)) {
+            delay += conditions.latency * 2 + RETRANSMIT_DELAY;
+        }
+
+        delay
+    }
+}
+
+/// Derive the RNG seed of a link from the network seed, hashing the
+/// link key with FNV-1a so it stays stable across builds.
+fn link_seed(seed: u64, key: &(String, String)) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325 ^ seed;
+    for byte in key.0.bytes().chain([0]).chain(key.1.bytes()) {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+/// Key of the link between two nodes, regardless of direction
+fn link_key(a: &str, b: &str) -> (String, String) {
+    if a <= b {
+        (a.to_string(), b.to_string())
+    } else {
+        (b.to_string(), a.to_string())
+    }
+}
+
+/// A chunk of data in transit
+struct Chunk {
+    deliver_at: Instant,
+    data: Vec<u8>,
+}
+
+/// One side of an in-memory connection
+pub struct MemoryStream {
+    network: Arc<MemoryNetwork>,
+    /// Node on our side
+    local: String,
+    /// Node on the other side
+    remote: String,
+    tx: Sender<Chunk>,
+    rx: Pin<Box<Receiver<Chunk>>>,
+    /// Chunk being read, along with the read position
+    pending: Option<(Chunk, usize)>,
+    /// Timer waiting for the pending chunk to arrive
+    timer: Option<Timer>,
+    /// Delivery time of the last sent chunk, keeping the stream ordered
+    last_sent: Instant,
+}
+
+impl MemoryStream {
+    /// Create both sides of a connection between `a` and `b`
+    fn pair(network: Arc<MemoryNetwork>, a: &str, b: &str) -> (Self, Self) {
+        let (a_tx, b_rx) = channel::unbounded();
+        let (b_tx, a_rx) = channel::unbounded();
+        let now = Instant::now();
+
+        let a_side = Self {
+            network: network.clone(),
+            local: a.to_string(),
+            remote: b.to_string(),
+            tx: a_tx,
+            rx: Box::pin(a_rx),
+            pending: None,
+            timer: None,
+            last_sent: now,
+        };
+
+        let b_side = Self {
+            network,
+            local: b.to_string(),
+            remote: a.to_string(),
+            tx: b_tx,
+            rx: Box::pin(b_rx),
+            pending: None,
+            timer: None,
+            last_sent: now,
+        };
+
+        (a_side, b_side)
+    }
+}
+
+impl AsyncRead for MemoryStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        loop {
+            if let Some((chunk, pos)) = &mut this.pending {
+                // Wait for the chunk to arrive
+                if Instant::now() < chunk.deliver_at {
+                    let timer = this.timer.get_or_insert_with(|| Timer::at(chunk.deliver_at));
+                    if Pin::new(timer).poll(cx).is_pending() {
+                        return Poll::Pending
+                    }
+                }
+                this.timer = None;
+
+                if !this.network.reachable(&this.remote, &this.local) {
+                    return Poll::Ready(Err(ErrorKind::ConnectionReset.into()))
+                }
+
+                let n = buf.len().min(chunk.data.len() - *pos);
+                buf[..n].copy_from_slice(&chunk.data[*pos..*pos + n]);
+                *pos += n;
+
+                if *pos == chunk.data.len() {
+                    this.pending = None;
+                }
+
+                return Poll::Ready(Ok(n))
+            }
+
+            match this.rx.as_mut().poll_next(cx) {
+                Poll::Ready(Some(chunk)) => this.pending = Some((chunk, 0)),
+                // The other side is gone
+                Poll::Ready(None) => return Poll::Ready(Ok(0)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl AsyncWrite for MemoryStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        if !this.network.reachable(&this.local, &this.remote) {
+            return Poll::Ready(Err(ErrorKind::ConnectionReset.into()))
+        }
+
+        // Chunks can't overtake each other, like on a TCP stream
+        let delay = this.network.delay(&this.local, &this.remote);
+        let deliver_at = (Instant::now() + delay).max(this.last_sent);
+        this.last_sent = deliver_at;
+
+        let chunk = Chunk { deliver_at, data: buf.to_vec() };
+        if this.tx.try_send(chunk).is_err() {
+            return Poll::Ready(Err(ErrorKind::BrokenPipe.into()))
+        }
+
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.tx.close();
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl PtStream for MemoryStream {}
+
+/// Memory Dialer implementation
+#[derive(Debug, Clone)]
+pub struct MemoryDialer {
+    datastore: Option<String>,
+}
+
+impl MemoryDialer {
+    /// Instantiate a new [`MemoryDialer`] for the node owning the given
+    /// datastore
+    pub(crate) async fn new(datastore: Option<String>) -> io::Result<Self> {
+        Ok(Self { datastore })
+    }
+
+    /// Internal dial function
+    pub(crate) async fn do_dial(
+        &self,
+        host: &str,
+        port: u16,
+        conn_timeout: Option<Duration>,
+    ) -> io::Result<MemoryStream> {
+        debug!(target: "net::memory::do_dial", "Dialing {}:{} in memory...", host, port);
+        let network = MemoryNetwork::of_host(host);
+
+        let local = {
+            let state = network.state.lock().unwrap();
+            let node = self.datastore.as_ref().and_then(|d| state.nodes.get(d));
+            node.cloned().unwrap_or_else(|| format!("anonymous.{}", network.name))
+        };
+
+        // Packets of the handshake get silently dropped between partitions
+        if !network.reachable(&local, host) {
+            if let Some(t) = conn_timeout {
+                Timer::after(t).await;
+            }
+            return Err(ErrorKind::TimedOut.into())
+        }
+
+        // Wait for the handshake to go through the link
+        let delay = network.delay(&local, host);
+        if let Some(t) = conn_timeout {
+            if delay > t {
+                Timer::after(t).await;
+                return Err(ErrorKind::TimedOut.into())
+            }
+        }
+        Timer::after(delay).await;
+
+        let Some(listener) =
+            network.state.lock().unwrap().listeners.get(&format!("{}:{}", host, port)).cloned()
+        else {
+            return Err(ErrorKind::ConnectionRefused.into())
+        };
+
+        // Like with TCP, the peer sees us on an ephemeral port
+        let port = {
+            let mut state = network.state.lock().unwrap();
+            state.next_id += 1;
+            EPHEMERAL_PORTS.start + (state.next_id % EPHEMERAL_PORTS.len() as u64) as u16
+        };
+
+        let (stream, peer_stream) = MemoryStream::pair(network, &local, host);
+        let peer_url = Url::parse(&format!("mem://{}:{}", local, port)).unwrap();
+
+        if listener.1.send((peer_stream, peer_url)).await.is_err() {
+            return Err(ErrorKind::ConnectionRefused.into())
+        }
+
+        Ok(stream)
+    }
+}
+
+/// Memory Listener implementation
+#[derive(Debug, Clone)]
+pub struct MemoryListener;
+
+impl MemoryListener {
+    /// Instantiate a new [`MemoryListener`] object
+    pub(crate) async fn new() -> io::Result<Self> {
+        Ok(Self {})
+    }
+
+    /// Internal listen function
+    pub(crate) async fn do_listen(
+        &self,
+        host: &str,
+        port: u16,
+    ) -> io::Result<MemoryListenerIntern> {
+        let network = MemoryNetwork::of_host(host);
+        let addr = format!("{}:{}", host, port);
+        let (tx, rx) = channel::unbounded();
+
+        let id = {
+            let mut state = network.state.lock().unwrap();
+            if state.listeners.contains_key(&addr) {
+                return Err(ErrorKind::AddrInUse.into())
+            }
+
+            state.next_id += 1;
+            let id = state.next_id;
+            state.listeners.insert(addr.clone(), (id, tx));
+            id
+        };
+
+        debug!(target: "net::memory::do_listen", "Listening on mem://{}", addr);
+        Ok(MemoryListenerIntern { network, addr, id, rx: Box::pin(rx) })
+    }
+}
+
+/// Internal Memory Listener implementation, used with `PtListener`
+pub struct MemoryListenerIntern {
+    network: Arc<MemoryNetwork>,
+    addr: String,
+    id: u64,
+    rx: Pin<Box<Receiver<(MemoryStream, Url)>>>,
+}
+
+impl Drop for MemoryListenerIntern {
+    fn drop(&mut self) {
+        let mut state = self.network.state.lock().unwrap();
+        if state.listeners.get(&self.addr).is_some_and(|(id, _)| *id == self.id) {
+            state.listeners.remove(&self.addr);
+        }
+    }
+}
+
+#[async_trait]
+impl PtListener for MemoryListenerIntern {
+    async fn next(&self) -> io::Result<(Box<dyn PtStream>, Url)> {
+        match self.rx.recv().await {
+            Ok((stream, url)) => Ok((Box::new(stream), url)),
+            Err(_) => Err(io::Error::new(ErrorKind::ConnectionAborted, "Connection Aborted")),
+        }
+    }
+}
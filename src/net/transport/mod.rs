@@ -16,11 +16,14 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
-use std::{io, time::Duration};
+use std::{io, sync::Arc, time::Duration};
 
 use async_trait::async_trait;
 use log::error;
-use smol::io::{AsyncRead, AsyncWrite};
+use smol::{
+    io::{AsyncRead, AsyncWrite},
+    Executor,
+};
 use url::Url;
 
 #[cfg(feature = "p2p-unix")]
@@ -29,6 +32,9 @@ use std::io::ErrorKind;
 /// TLS upgrade mechanism
 pub(crate) mod tls;
 
+/// Noise upgrade mechanism
+pub(crate) mod noise;
+
 /// SOCKS5 proxy client
 pub mod socks5;
 
@@ -43,10 +49,18 @@ pub(crate) mod tor;
 /// Nym transport
 pub(crate) mod nym;
 
+#[cfg(feature = "p2p-i2p")]
+/// I2P transport
+pub mod i2p;
+
 /// Unix socket transport
 #[cfg(feature = "p2p-unix")]
 pub(crate) mod unix;
 
+/// In-memory transport, used for network simulations
+#[cfg(feature = "p2p-memory")]
+pub mod memory;
+
 /// Dialer variants
 #[derive(Debug, Clone)]
 pub enum DialerVariant {
@@ -56,6 +70,9 @@ pub enum DialerVariant {
     /// TCP with TLS
     TcpTls(tcp::TcpDialer),
 
+    /// TCP with Noise
+    TcpNoise(tcp::TcpDialer, noise::NoiseUpgrade),
+
     #[cfg(feature = "p2p-tor")]
     /// Tor
     Tor(tor::TorDialer),
@@ -64,6 +81,10 @@ pub enum DialerVariant {
     /// Tor with TLS
     TorTls(tor::TorDialer),
 
+    #[cfg(feature = "p2p-tor")]
+    /// Tor with Noise
+    TorNoise(tor::TorDialer, noise::NoiseUpgrade),
+
     #[cfg(feature = "p2p-nym")]
     /// Nym
     Nym(nym::NymDialer),
@@ -72,10 +93,18 @@ pub enum DialerVariant {
     /// Nym with TLS
     NymTls(nym::NymDialer),
 
+    #[cfg(feature = "p2p-i2p")]
+    /// I2P
+    I2p(i2p::I2pDialer),
+
     /// Unix socket
     #[cfg(feature = "p2p-unix")]
     Unix(unix::UnixDialer),
 
+    /// In-memory
+    #[cfg(feature = "p2p-memory")]
+    Memory(memory::MemoryDialer),
+
     /// SOCKS5 proxy
     Socks5(socks5::Socks5Dialer),
 }
@@ -89,13 +118,28 @@ pub enum ListenerVariant {
     /// TCP with TLS
     TcpTls(tcp::TcpListener),
 
+    /// TCP with Noise
+    TcpNoise(tcp::TcpListener, noise::NoiseUpgrade),
+
     #[cfg(feature = "p2p-tor")]
     /// Tor
     Tor(tor::TorListener),
 
+    #[cfg(feature = "p2p-tor")]
+    /// Tor with Noise
+    TorNoise(tor::TorListener, noise::NoiseUpgrade),
+
+    #[cfg(feature = "p2p-i2p")]
+    /// I2P
+    I2p(i2p::I2pListener),
+
     /// Unix socket
     #[cfg(feature = "p2p-unix")]
     Unix(unix::UnixListener),
+
+    /// In-memory
+    #[cfg(feature = "p2p-memory")]
+    Memory(memory::MemoryListener),
 }
 
 /// A dialer that is able to transparently operate over arbitrary transports.
@@ -129,6 +173,8 @@ macro_rules! enforce_abspath {
 
 impl Dialer {
     /// Instantiate a new [`Dialer`] with the given [`Url`] and datastore path.
+    /// For the Noise transports, the URL fragment can hold the base58-encoded
+    /// static public key the peer has to authenticate with.
     pub async fn new(endpoint: Url, datastore: Option<String>) -> io::Result<Self> {
         match endpoint.scheme().to_lowercase().as_str() {
             "tcp" => {
@@ -147,6 +193,15 @@ impl Dialer {
                 Ok(Self { endpoint, variant })
             }
 
+            "tcp+noise" => {
+                // Build a TCP dialer wrapped with Noise
+                enforce_hostport!(endpoint);
+                let variant = tcp::TcpDialer::new(None).await?;
+                let upgrade = noise::NoiseUpgrade::new(datastore, endpoint.fragment()).await?;
+                let variant = DialerVariant::TcpNoise(variant, upgrade);
+                Ok(Self { endpoint, variant })
+            }
+
             #[cfg(feature = "p2p-tor")]
             "tor" => {
                 // Build a Tor dialer
@@ -165,6 +220,17 @@ impl Dialer {
                 Ok(Self { endpoint, variant })
             }
 
+            #[cfg(feature = "p2p-tor")]
+            "tor+noise" => {
+                // Build a Tor dialer wrapped with Noise
+                enforce_hostport!(endpoint);
+                let upgrade =
+                    noise::NoiseUpgrade::new(datastore.clone(), endpoint.fragment()).await?;
+                let variant = tor::TorDialer::new(datastore).await?;
+                let variant = DialerVariant::TorNoise(variant, upgrade);
+                Ok(Self { endpoint, variant })
+            }
+
             #[cfg(feature = "p2p-nym")]
             "nym" => {
                 // Build a Nym dialer
@@ -183,6 +249,15 @@ impl Dialer {
                 Ok(Self { endpoint, variant })
             }
 
+            #[cfg(feature = "p2p-i2p")]
+            "i2p" => {
+                // Build an I2P dialer
+                enforce_hostport!(endpoint);
+                let variant = i2p::I2pDialer::new().await?;
+                let variant = DialerVariant::I2p(variant);
+                Ok(Self { endpoint, variant })
+            }
+
             #[cfg(feature = "p2p-unix")]
             "unix" => {
                 // Build a Unix socket dialer
@@ -192,6 +267,15 @@ impl Dialer {
                 Ok(Self { endpoint, variant })
             }
 
+            #[cfg(feature = "p2p-memory")]
+            "mem" => {
+                // Build an in-memory dialer
+                enforce_hostport!(endpoint);
+                let variant = memory::MemoryDialer::new(datastore).await?;
+                let variant = DialerVariant::Memory(variant);
+                Ok(Self { endpoint, variant })
+            }
+
             "socks5" => {
                 // Build a SOCKS5 dialer
                 enforce_hostport!(endpoint);
@@ -229,6 +313,13 @@ impl Dialer {
                 Ok(Box::new(stream))
             }
 
+            DialerVariant::TcpNoise(dialer, upgrade) => {
+                let sockaddr = self.endpoint.socket_addrs(|| None)?;
+                let stream = dialer.do_dial(sockaddr[0], timeout).await?;
+                let stream = upgrade.upgrade_dialer(stream).await?;
+                Ok(Box::new(stream))
+            }
+
             #[cfg(feature = "p2p-tor")]
             DialerVariant::Tor(dialer) => {
                 let host = self.endpoint.host_str().unwrap();
@@ -247,6 +338,15 @@ impl Dialer {
                 Ok(Box::new(stream))
             }
 
+            #[cfg(feature = "p2p-tor")]
+            DialerVariant::TorNoise(dialer, upgrade) => {
+                let host = self.endpoint.host_str().unwrap();
+                let port = self.endpoint.port().unwrap();
+                let stream = dialer.do_dial(host, port, timeout).await?;
+                let stream = upgrade.upgrade_dialer(stream).await?;
+                Ok(Box::new(stream))
+            }
+
             #[cfg(feature = "p2p-nym")]
             DialerVariant::Nym(_dialer) => {
                 todo!();
@@ -257,6 +357,14 @@ impl Dialer {
                 todo!();
             }
 
+            #[cfg(feature = "p2p-i2p")]
+            DialerVariant::I2p(dialer) => {
+                let host = self.endpoint.host_str().unwrap();
+                let port = self.endpoint.port().unwrap();
+                let stream = dialer.do_dial(host, port, timeout).await?;
+                Ok(Box::new(stream))
+            }
+
             #[cfg(feature = "p2p-unix")]
             DialerVariant::Unix(dialer) => {
                 let path = match self.endpoint.to_file_path() {
@@ -267,6 +375,14 @@ impl Dialer {
                 Ok(Box::new(stream))
             }
 
+            #[cfg(feature = "p2p-memory")]
+            DialerVariant::Memory(dialer) => {
+                let host = self.endpoint.host_str().unwrap();
+                let port = self.endpoint.port().unwrap();
+                let stream = dialer.do_dial(host, port, timeout).await?;
+                Ok(Box::new(stream))
+            }
+
             DialerVariant::Socks5(dialer) => {
                 let stream = dialer.do_dial().await?;
                 Ok(Box::new(stream))
@@ -309,6 +425,15 @@ impl Listener {
                 Ok(Self { endpoint, variant })
             }
 
+            "tcp+noise" => {
+                // Build a TCP listener wrapped with Noise
+                enforce_hostport!(endpoint);
+                let variant = tcp::TcpListener::new(1024).await?;
+                let upgrade = noise::NoiseUpgrade::new(datastore, None).await?;
+                let variant = ListenerVariant::TcpNoise(variant, upgrade);
+                Ok(Self { endpoint, variant })
+            }
+
             #[cfg(feature = "p2p-tor")]
             "tor" => {
                 // Build a Tor Hidden Service listener
@@ -318,6 +443,25 @@ impl Listener {
                 Ok(Self { endpoint, variant })
             }
 
+            #[cfg(feature = "p2p-tor")]
+            "tor+noise" => {
+                // Build a Tor Hidden Service listener wrapped with Noise
+                enforce_hostport!(endpoint);
+                let upgrade = noise::NoiseUpgrade::new(datastore.clone(), None).await?;
+                let variant = tor::TorListener::new(datastore).await?;
+                let variant = ListenerVariant::TorNoise(variant, upgrade);
+                Ok(Self { endpoint, variant })
+            }
+
+            #[cfg(feature = "p2p-i2p")]
+            "i2p" => {
+                // Build an I2P listener on our destination
+                enforce_hostport!(endpoint);
+                let variant = i2p::I2pListener::new(datastore).await?;
+                let variant = ListenerVariant::I2p(variant);
+                Ok(Self { endpoint, variant })
+            }
+
             #[cfg(feature = "p2p-unix")]
             "unix" => {
                 enforce_abspath!(endpoint);
@@ -326,6 +470,15 @@ impl Listener {
                 Ok(Self { endpoint, variant })
             }
 
+            #[cfg(feature = "p2p-memory")]
+            "mem" => {
+                // Build an in-memory listener
+                enforce_hostport!(endpoint);
+                let variant = memory::MemoryListener::new().await?;
+                let variant = ListenerVariant::Memory(variant);
+                Ok(Self { endpoint, variant })
+            }
+
             x => {
                 error!("[P2P] Requested unsupported transport: {}", x);
                 Err(io::Error::from_raw_os_error(libc::ENETUNREACH))
@@ -334,8 +487,9 @@ impl Listener {
     }
 
     /// Listen on an instantiated [`Listener`].
-    /// This will open a socket and return the listener.
-    pub async fn listen(&self) -> io::Result<Box<dyn PtListener>> {
+    /// This will open a socket and return the listener. The executor is
+    /// used by transports that upgrade accepted streams in the background.
+    pub async fn listen(&self, ex: Arc<Executor<'_>>) -> io::Result<Box<dyn PtListener>> {
         match &self.variant {
             ListenerVariant::Tcp(listener) => {
                 let sockaddr = self.endpoint.socket_addrs(|| None)?;
@@ -351,6 +505,13 @@ impl Listener {
                 Ok(Box::new(l))
             }
 
+            ListenerVariant::TcpNoise(listener, upgrade) => {
+                let sockaddr = self.endpoint.socket_addrs(|| None)?;
+                let l = listener.do_listen(sockaddr[0]).await?;
+                let l = noise::NoiseListener::new(l, upgrade.clone(), "tcp+noise", ex);
+                Ok(Box::new(l))
+            }
+
             #[cfg(feature = "p2p-tor")]
             ListenerVariant::Tor(listener) => {
                 let port = self.endpoint.port().unwrap();
@@ -358,6 +519,21 @@ impl Listener {
                 Ok(Box::new(l))
             }
 
+            #[cfg(feature = "p2p-tor")]
+            ListenerVariant::TorNoise(listener, upgrade) => {
+                let port = self.endpoint.port().unwrap();
+                let l = listener.do_listen(port).await?;
+                let l = noise::NoiseListener::new(l, upgrade.clone(), "tor+noise", ex);
+                Ok(Box::new(l))
+            }
+
+            #[cfg(feature = "p2p-i2p")]
+            ListenerVariant::I2p(listener) => {
+                let port = self.endpoint.port().unwrap();
+                let l = listener.do_listen(port).await?;
+                Ok(Box::new(l))
+            }
+
             #[cfg(feature = "p2p-unix")]
             ListenerVariant::Unix(listener) => {
                 let path = match self.endpoint.to_file_path() {
@@ -367,13 +543,25 @@ impl Listener {
                 let l = listener.do_listen(&path).await?;
                 Ok(Box::new(l))
             }
+
+            #[cfg(feature = "p2p-memory")]
+            ListenerVariant::Memory(listener) => {
+                let host = self.endpoint.host_str().unwrap();
+                let port = self.endpoint.port().unwrap();
+                let l = listener.do_listen(host, port).await?;
+                Ok(Box::new(l))
+            }
         }
     }
 
     /// Should only be called after `listen()` in order to behave correctly.
+    /// For the Noise transports, our static public key is set as the URL
+    /// fragment, so peers dialing the endpoint can pin it.
     pub async fn endpoint(&self) -> Url {
         match &self.variant {
-            ListenerVariant::Tcp(listener) | ListenerVariant::TcpTls(listener) => {
+            ListenerVariant::Tcp(listener) |
+            ListenerVariant::TcpTls(listener) |
+            ListenerVariant::TcpNoise(listener, _) => {
                 let mut endpoint = self.endpoint.clone();
 
                 // Endpoint *must* always have a port set.
@@ -389,10 +577,23 @@ impl Listener {
                     }
                 }
 
+                if let ListenerVariant::TcpNoise(_, upgrade) = &self.variant {
+                    endpoint.set_fragment(Some(&upgrade.public_key()));
+                }
+
                 endpoint
             }
             #[cfg(feature = "p2p-tor")]
             ListenerVariant::Tor(listener) => listener.endpoint.get().unwrap().clone(),
+            #[cfg(feature = "p2p-tor")]
+            ListenerVariant::TorNoise(listener, upgrade) => {
+                let mut endpoint = listener.endpoint.get().unwrap().clone();
+                endpoint.set_scheme("tor+noise").unwrap();
+                endpoint.set_fragment(Some(&upgrade.public_key()));
+                endpoint
+            }
+            #[cfg(feature = "p2p-i2p")]
+            ListenerVariant::I2p(listener) => listener.endpoint.get().unwrap().clone(),
             #[allow(unreachable_patterns)]
             _ => self.endpoint.clone(),
         }
@@ -400,7 +601,13 @@ impl Listener {
 }
 
 /// Wrapper trait for async streams
-pub trait PtStream: AsyncRead + AsyncWrite + Unpin + Send {}
+pub trait PtStream: AsyncRead + AsyncWrite + Unpin + Send {
+    /// User ID of the process on the other end of the stream, for the
+    /// transports where the OS can tell us.
+    fn peer_uid(&self) -> Option<u32> {
+        None
+    }
+}
 
 impl PtStream for smol::net::TcpStream {}
 
@@ -413,7 +620,11 @@ impl PtStream for arti_client::DataStream {}
 impl PtStream for futures_rustls::TlsStream<arti_client::DataStream> {}
 
 #[cfg(feature = "p2p-unix")]
-impl PtStream for smol::net::unix::UnixStream {}
+impl PtStream for smol::net::unix::UnixStream {
+    fn peer_uid(&self) -> Option<u32> {
+        unix::peer_uid(self)
+    }
+}
 
 /// Wrapper trait for async listeners
 #[async_trait]
@@ -0,0 +1,603 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2025 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Noise protocol upgrade for P2P transports.
+//!
+//! Connections are encrypted and mutually authenticated with the
+//! `Noise_XX_25519_ChaChaPoly_BLAKE2s` handshake, using a long-lived
+//! static keypair kept in the P2P datastore. Unlike the throwaway
+//! certificates of `tcp+tls`, this gives every node a stable identity,
+//! so dialers can pin the public key they expect the peer to present.
+//! The key is given in the URL fragment, encoded in base58, e.g.
+//! `tcp+noise://example.com:26661#<pubkey>`.
+//!
+//! After the handshake, data is exchanged in frames prefixed by their
+//! big-endian `u16` length, as described in the Noise specification.
+
+use std::{
+    fmt, fs,
+    io::{self, ErrorKind},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use log::{debug, info, warn};
+use smol::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    lock::{Mutex, Semaphore},
+    Executor, Task,
+};
+use snow::{
+    params::DHChoice,
+    resolvers::{CryptoResolver, DefaultResolver},
+    Builder, HandshakeState, TransportState,
+};
+use url::Url;
+
+use super::{PtListener, PtStream};
+use crate::{system::timeout::timeout, util::path::expand_path};
+
+/// Noise protocol name
+const NOISE_PARAMS: &str = "Noise_XX_25519_ChaChaPoly_BLAKE2s";
+
+/// Prologue both handshake sides have to agree on
+const NOISE_PROLOGUE: &[u8] = b"darkfi-p2p";
+
+/// Maximum size of a Noise message, as defined by the specification
+const MAX_FRAME_LEN: usize = 65535;
+
+/// Size of the authentication tag appended to encrypted payloads
+const TAG_LEN: usize = 16;
+
+/// Maximum plaintext size carried by a single frame
+const MAX_PAYLOAD_LEN: usize = MAX_FRAME_LEN - TAG_LEN;
+
+/// Size of the Curve25519 keys
+const KEY_LEN: usize = 32;
+
+/// File in the P2P datastore holding the static secret key
+const KEY_FILE: &str = "noise_static.key";
+
+/// Time an inbound peer has to complete the handshake
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Maximum number of inbound handshakes running at the same time
+const MAX_PENDING_HANDSHAKES: usize = 64;
+
+/// Loaded static keypairs, by datastore path, so each of them is only
+/// read from disk (or generated) once.
+static NOISE_KEYPAIRS: Mutex<Vec<(Option<String>, Arc<NoiseKeypair>)>> = Mutex::new(Vec::new());
+
+/// Static Curve25519 keypair identifying this node
+pub struct NoiseKeypair {
+    secret: [u8; KEY_LEN],
+    public: [u8; KEY_LEN],
+}
+
+impl fmt::Debug for NoiseKeypair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "NoiseKeypair {{ public: {} }}", bs58::encode(&self.public).into_string())
+    }
+}
+
+impl NoiseKeypair {
+    /// Create a keypair from the given secret key
+    fn from_secret(secret: [u8; KEY_LEN]) -> Self {
+        let mut dh = DefaultResolver.resolve_dh(&DHChoice::Curve25519).unwrap();
+        dh.set(&secret);
+        let public = dh.pubkey().try_into().unwrap();
+        Self { secret, public }
+    }
+
+    /// Generate a new random keypair
+    fn generate() -> io::Result<Self> {
+        let keypair =
+            Builder::new(NOISE_PARAMS.parse().unwrap()).generate_keypair().map_err(noise_err)?;
+
+        Ok(Self::from_secret(keypair.private.try_into().unwrap()))
+    }
+
+    /// Read the static keypair from the given datastore, generating and
+    /// storing a new one if it doesn't exist yet. Without a datastore, an
+    /// ephemeral keypair is used for the lifetime of the process.
+    async fn load(datastore: Option<String>) -> io::Result<Arc<Self>> {
+        let mut keypairs = NOISE_KEYPAIRS.lock().await;
+        if let Some((_, keypair)) = keypairs.iter().find(|(d, _)| d == &datastore) {
+            return Ok(keypair.clone())
+        }
+
+        let keypair = match &datastore {
+            Some(datadir) => {
+                let datadir = expand_path(datadir)
+                    .map_err(|e| io::Error::new(ErrorKind::InvalidInput, e.to_string()))?;
+                let path = datadir.join(KEY_FILE);
+
+                if path.exists() {
+                    let encoded = fs::read_to_string(&path)?;
+                    let secret = decode_key(encoded.trim())?;
+                    Self::from_secret(secret)
+                } else {
+                    let keypair = Self::generate()?;
+                    fs::create_dir_all(&datadir)?;
+                    write_secret(&path, &keypair.secret)?;
+                    info!(
+                        target: "net::noise::load",
+                        "[P2P] Generated new Noise static key in {}", path.display(),
+                    );
+                    keypair
+                }
+            }
+
+            None => {
+                warn!(
+                    target: "net::noise::load",
+                    "[P2P] No P2P datastore configured, using an ephemeral Noise static key",
+                );
+                Self::generate()?
+            }
+        };
+
+        let keypair = Arc::new(keypair);
+        keypairs.push((datastore, keypair.clone()));
+        Ok(keypair)
+    }
+
+    /// Return the base58-encoded public key
+    pub fn public_key(&self) -> String {
+        bs58::encode(&self.public).into_string()
+    }
+}
+
+/// Write the base58-encoded secret key to the given path, readable by
+/// the owner only.
+fn write_secret(path: &std::path::Path, secret: &[u8; KEY_LEN]) -> io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options.open(path)?;
+    io::Write::write_all(&mut file, bs58::encode(secret).into_string().as_bytes())
+}
+
+/// Decode a base58-encoded Curve25519 key
+pub(crate) fn decode_key(encoded: &str) -> io::Result<[u8; KEY_LEN]> {
+    let Ok(bytes) = bs58::decode(encoded).into_vec() else {
+        return Err(io::Error::new(ErrorKind::InvalidInput, "Invalid Noise key encoding"))
+    };
+
+    bytes.try_into().map_err(|_| io::Error::new(ErrorKind::InvalidInput, "Invalid Noise key size"))
+}
+
+/// Noise upgrade mechanism, holding our static keypair and optionally
+/// the public key the remote peer is expected to have.
+#[derive(Debug, Clone)]
+pub struct NoiseUpgrade {
+    keypair: Arc<NoiseKeypair>,
+    pinned_key: Option<[u8; KEY_LEN]>,
+}
+
+impl NoiseUpgrade {
+    /// Instantiate a new [`NoiseUpgrade`] using the static keypair of the
+    /// given datastore. `pinned_key` is the base58-encoded public key the
+    /// remote peer must authenticate with, if any.
+    pub async fn new(datastore: Option<String>, pinned_key: Option<&str>) -> io::Result<Self> {
+        let keypair = NoiseKeypair::load(datastore).await?;
+        let pinned_key = match pinned_key {
+            Some(key) => Some(decode_key(key)?),
+            None => None,
+        };
+
+        Ok(Self { keypair, pinned_key })
+    }
+
+    /// Return our base58-encoded static public key
+    pub fn public_key(&self) -> String {
+        self.keypair.public_key()
+    }
+
+    fn builder(&self) -> Builder<'_> {
+        Builder::new(NOISE_PARAMS.parse().unwrap())
+            .local_private_key(&self.keypair.secret)
+            .prologue(NOISE_PROLOGUE)
+    }
+
+    /// Perform the handshake as the initiator. The pinned key, if any, is
+    /// verified before we reveal our own static key to the peer.
+    pub async fn upgrade_dialer<IO>(&self, mut stream: IO) -> io::Result<NoiseStream<IO>>
+    where
+        IO: AsyncRead + AsyncWrite + Unpin,
+    {
+        let mut handshake = self.builder().build_initiator().map_err(noise_err)?;
+
+        // -> e
+        write_handshake(&mut handshake, &mut stream).await?;
+        // <- e, ee, s, es
+        read_handshake(&mut handshake, &mut stream).await?;
+
+        let remote_key = remote_static(&handshake)?;
+        if let Some(pinned_key) = self.pinned_key {
+            if remote_key != pinned_key {
+                return Err(io::Error::new(
+                    ErrorKind::PermissionDenied,
+                    "Noise peer static key does not match the pinned key",
+                ))
+            }
+        }
+
+        // -> s, se
+        write_handshake(&mut handshake, &mut stream).await?;
+
+        let transport = handshake.into_transport_mode().map_err(noise_err)?;
+        Ok(NoiseStream::new(stream, transport, remote_key))
+    }
+
+    /// Perform the handshake as the responder
+    pub async fn upgrade_listener<IO>(&self, mut stream: IO) -> io::Result<NoiseStream<IO>>
+    where
+        IO: AsyncRead + AsyncWrite + Unpin,
+    {
+        let mut handshake = self.builder().build_responder().map_err(noise_err)?;
+
+        // -> e
+        read_handshake(&mut handshake, &mut stream).await?;
+        // <- e, ee, s, es
+        write_handshake(&mut handshake, &mut stream).await?;
+        // -> s, se
+        read_handshake(&mut handshake, &mut stream).await?;
+
+        let remote_key = remote_static(&handshake)?;
+        let transport = handshake.into_transport_mode().map_err(noise_err)?;
+        Ok(NoiseStream::new(stream, transport, remote_key))
+    }
+}
+
+/// Map a Noise error into an I/O error
+fn noise_err(e: snow::Error) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, e)
+}
+
+/// Return the static key the remote peer authenticated with
+fn remote_static(handshake: &HandshakeState) -> io::Result<[u8; KEY_LEN]> {
+    let Some(key) = handshake.get_remote_static() else {
+        return Err(io::Error::new(ErrorKind::InvalidData, "Missing Noise remote static key"))
+    };
+
+    key.try_into().map_err(|_| io::Error::new(ErrorKind::InvalidData, "Invalid Noise remote key"))
+}
+
+/// Write the next handshake message into the stream
+async fn write_handshake<IO>(handshake: &mut HandshakeState, stream: &mut IO) -> io::Result<()>
+where
+    IO: AsyncWrite + Unpin,
+{
+    let mut buf = vec![0u8; 2 + MAX_FRAME_LEN];
+    let len = handshake.write_message(&[], &mut buf[2..]).map_err(noise_err)?;
+    buf[..2].copy_from_slice(&(len as u16).to_be_bytes());
+    stream.write_all(&buf[..2 + len]).await?;
+    stream.flush().await
+}
+
+/// Read the next handshake message from the stream
+async fn read_handshake<IO>(handshake: &mut HandshakeState, stream: &mut IO) -> io::Result<()>
+where
+    IO: AsyncRead + Unpin,
+{
+    let mut len = [0u8; 2];
+    stream.read_exact(&mut len).await?;
+    let mut msg = vec![0u8; u16::from_be_bytes(len) as usize];
+    stream.read_exact(&mut msg).await?;
+
+    // Handshake messages carry no payload
+    let mut payload = vec![0u8; MAX_FRAME_LEN];
+    handshake.read_message(&msg, &mut payload).map_err(noise_err)?;
+    Ok(())
+}
+
+/// Encrypted stream, after a successful Noise handshake
+pub struct NoiseStream<IO> {
+    /// Underlying transport stream
+    inner: IO,
+    /// Noise transport cipher states
+    transport: TransportState,
+    /// Static public key of the remote peer
+    remote_key: [u8; KEY_LEN],
+    /// Buffer for the incoming frame, including its length prefix
+    read_buf: Vec<u8>,
+    /// Number of bytes of the incoming frame read so far
+    read_len: usize,
+    /// Decrypted payload of the last frame
+    plain_buf: Vec<u8>,
+    /// Number of bytes of the decrypted payload handed out so far
+    plain_pos: usize,
+    /// Encrypted outgoing frame, including its length prefix
+    write_buf: Vec<u8>,
+    /// Number of bytes of the outgoing frame written so far
+    write_pos: usize,
+}
+
+impl<IO> NoiseStream<IO> {
+    fn new(inner: IO, transport: TransportState, remote_key: [u8; KEY_LEN]) -> Self {
+        Self {
+            inner,
+            transport,
+            remote_key,
+            read_buf: vec![0u8; 2 + MAX_FRAME_LEN],
+            read_len: 0,
+            plain_buf: vec![],
+            plain_pos: 0,
+            write_buf: vec![],
+            write_pos: 0,
+        }
+    }
+
+    /// Return the base58-encoded static public key of the remote peer
+    pub fn remote_key(&self) -> String {
+        bs58::encode(&self.remote_key).into_string()
+    }
+}
+
+impl<IO: AsyncWrite + Unpin> NoiseStream<IO> {
+    /// Write out the pending outgoing frame, if any
+    fn poll_drain(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.write_pos < self.write_buf.len() {
+            let n =
+                match Pin::new(&mut self.inner).poll_write(cx, &self.write_buf[self.write_pos..]) {
+                    Poll::Ready(Ok(n)) => n,
+                    Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                    Poll::Pending => return Poll::Pending,
+                };
+
+            if n == 0 {
+                return Poll::Ready(Err(ErrorKind::WriteZero.into()))
+            }
+
+            self.write_pos += n;
+        }
+
+        self.write_buf.clear();
+        self.write_pos = 0;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<IO: AsyncRead + Unpin> AsyncRead for NoiseStream<IO> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0))
+        }
+
+        loop {
+            // Hand out what's left of the last decrypted frame
+            if this.plain_pos < this.plain_buf.len() {
+                let n = buf.len().min(this.plain_buf.len() - this.plain_pos);
+                buf[..n].copy_from_slice(&this.plain_buf[this.plain_pos..this.plain_pos + n]);
+                this.plain_pos += n;
+                return Poll::Ready(Ok(n))
+            }
+
+            // Read the length prefix first, then the rest of the frame
+            let frame_len = if this.read_len < 2 {
+                2
+            } else {
+                2 + u16::from_be_bytes([this.read_buf[0], this.read_buf[1]]) as usize
+            };
+
+            if this.read_len < frame_len {
+                let n = match Pin::new(&mut this.inner)
+                    .poll_read(cx, &mut this.read_buf[this.read_len..frame_len])
+                {
+                    Poll::Ready(Ok(n)) => n,
+                    Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                    Poll::Pending => return Poll::Pending,
+                };
+
+                if n == 0 {
+                    // Clean EOF only on a frame boundary
+                    if this.read_len == 0 {
+                        return Poll::Ready(Ok(0))
+                    }
+                    return Poll::Ready(Err(ErrorKind::UnexpectedEof.into()))
+                }
+
+                this.read_len += n;
+                continue
+            }
+
+            // The frame is complete, decrypt it
+            if frame_len == 2 {
+                return Poll::Ready(Err(io::Error::new(ErrorKind::InvalidData, "Empty Noise frame")))
+            }
+
+            this.plain_buf.resize(MAX_FRAME_LEN, 0);
+            let n = this
+                .transport
+                .read_message(&this.read_buf[2..frame_len], &mut this.plain_buf)
+                .map_err(noise_err)?;
+            this.plain_buf.truncate(n);
+            this.plain_pos = 0;
+            this.read_len = 0;
+        }
+    }
+}
+
+impl<IO: AsyncWrite + Unpin> AsyncWrite for NoiseStream<IO> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        // Only a single frame is buffered at a time
+        match this.poll_drain(cx) {
+            Poll::Ready(Ok(())) => {}
+            Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+            Poll::Pending => return Poll::Pending,
+        }
+
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0))
+        }
+
+        let n = buf.len().min(MAX_PAYLOAD_LEN);
+        this.write_buf.resize(2 + MAX_FRAME_LEN, 0);
+        let len =
+            this.transport.write_message(&buf[..n], &mut this.write_buf[2..]).map_err(noise_err)?;
+        this.write_buf[..2].copy_from_slice(&(len as u16).to_be_bytes());
+        this.write_buf.truncate(2 + len);
+
+        // The frame is accepted at this point, so whatever doesn't make it
+        // out now gets written on the next write or flush.
+        if let Poll::Ready(Err(e)) = this.poll_drain(cx) {
+            return Poll::Ready(Err(e))
+        }
+
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        match this.poll_drain(cx) {
+            Poll::Ready(Ok(())) => Pin::new(&mut this.inner).poll_flush(cx),
+            x => x,
+        }
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        match this.poll_drain(cx) {
+            Poll::Ready(Ok(())) => Pin::new(&mut this.inner).poll_close(cx),
+            x => x,
+        }
+    }
+}
+
+impl<IO: AsyncRead + AsyncWrite + Unpin + Send> PtStream for NoiseStream<IO> {}
+
+/// Result of an accepted and upgraded stream
+type Accepted = io::Result<(Box<dyn PtStream>, Url)>;
+
+/// Listener wrapper performing the Noise handshake on accepted streams.
+/// Every handshake runs in its own task, so a slow or stalling peer
+/// doesn't hold up the others, and finished streams are handed out in
+/// the order they complete.
+pub struct NoiseListener {
+    /// Boxed, as the receiver itself isn't `Unpin`
+    accepted: Box<smol::channel::Receiver<Accepted>>,
+    _accept_task: Task<()>,
+}
+
+impl NoiseListener {
+    /// Wrap the given listener, setting the URL scheme of the accepted
+    /// peers to the given one. Handshakes are spawned on the given
+    /// executor.
+    pub fn new<'a, L: PtListener + Sync + 'a>(
+        listener: L,
+        upgrade: NoiseUpgrade,
+        scheme: &'static str,
+        ex: Arc<Executor<'a>>,
+    ) -> Self {
+        let (sender, accepted) = smol::channel::bounded(MAX_PENDING_HANDSHAKES);
+        let accept_task =
+            ex.spawn(Self::accept_loop(listener, upgrade, scheme, sender, ex.clone()));
+        Self { accepted: Box::new(accepted), _accept_task: accept_task }
+    }
+
+    /// Accept raw streams and spawn a handshake task for each of them,
+    /// until the listener is dropped.
+    async fn accept_loop<'a, L: PtListener + Sync + 'a>(
+        listener: L,
+        upgrade: NoiseUpgrade,
+        scheme: &'static str,
+        sender: smol::channel::Sender<Accepted>,
+        ex: Arc<Executor<'a>>,
+    ) {
+        let pending = Arc::new(Semaphore::new(MAX_PENDING_HANDSHAKES));
+
+        loop {
+            // Stop accepting while too many handshakes are in flight
+            let permit = pending.acquire_arc().await;
+
+            let (stream, mut url) = match listener.next().await {
+                Ok(v) => v,
+                Err(e) => {
+                    // Let the caller deal with accept errors
+                    if sender.send(Err(e)).await.is_err() {
+                        return
+                    }
+                    continue
+                }
+            };
+
+            let upgrade = upgrade.clone();
+            let sender = sender.clone();
+            ex.spawn(async move {
+                // Don't let a stalling peer hold up its handshake slot
+                let stream =
+                    match timeout(HANDSHAKE_TIMEOUT, upgrade.upgrade_listener(stream)).await {
+                        Ok(Ok(v)) => v,
+                        Ok(Err(e)) => {
+                            debug!(
+                                target: "net::noise::NoiseListener::accept_loop",
+                                "[P2P] Noise handshake with {} failed: {}", url, e,
+                            );
+                            return
+                        }
+                        Err(_) => {
+                            debug!(
+                                target: "net::noise::NoiseListener::accept_loop",
+                                "[P2P] Noise handshake with {} timed out", url,
+                            );
+                            return
+                        }
+                    };
+                drop(permit);
+
+                url.set_scheme(scheme).unwrap();
+                let _ = sender.send(Ok((Box::new(stream), url))).await;
+            })
+            .detach();
+        }
+    }
+}
+
+#[async_trait]
+impl PtListener for NoiseListener {
+    async fn next(&self) -> io::Result<(Box<dyn PtStream>, Url)> {
+        match self.accepted.recv().await {
+            Ok(v) => v,
+            Err(_) => Err(ErrorKind::BrokenPipe.into()),
+        }
+    }
+}
@@ -18,6 +18,7 @@
 
 use std::{
     io,
+    os::fd::AsRawFd,
     path::{Path, PathBuf},
 };
 
@@ -86,3 +87,57 @@ impl PtListener for SmolUnixListener {
         Ok((Box::new(stream), url))
     }
 }
+
+/// Get the user ID of the process connected to the other end of a Unix
+/// socket, using `SO_PEERCRED` on Linux and `getpeereid(3)` on the BSDs.
+pub(crate) fn peer_uid(stream: &UnixStream) -> Option<u32> {
+    let fd = stream.as_raw_fd();
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        let mut cred: libc::ucred = unsafe { std::mem::zeroed() };
+        let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+        let ret = unsafe {
+            libc::getsockopt(
+                fd,
+                libc::SOL_SOCKET,
+                libc::SO_PEERCRED,
+                &mut cred as *mut libc::ucred as *mut libc::c_void,
+                &mut len,
+            )
+        };
+
+        (ret == 0).then_some(cred.uid)
+    }
+
+    #[cfg(any(
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "freebsd",
+        target_os = "openbsd",
+        target_os = "netbsd",
+        target_os = "dragonfly",
+    ))]
+    {
+        let mut uid = 0;
+        let mut gid = 0;
+        let ret = unsafe { libc::getpeereid(fd, &mut uid, &mut gid) };
+
+        (ret == 0).then_some(uid)
+    }
+
+    #[cfg(not(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "freebsd",
+        target_os = "openbsd",
+        target_os = "netbsd",
+        target_os = "dragonfly",
+    )))]
+    {
+        let _ = fd;
+        None
+    }
+}
@@ -0,0 +1,295 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2025 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Optional authentication and per-method authorization for the JSON-RPC
+//! server.
+//!
+//! Users are configured with a role, and every role comes with a list of
+//! methods it is allowed to call. Once any user is configured, requests
+//! from unauthenticated peers are refused. Depending on the transport,
+//! peers authenticate with:
+//!
+//! * `http+` schemes: an `Authorization` header on every request, either
+//!   `Bearer <secret>` or `Basic base64(<name>:<secret>)`.
+//! * `unix://`: the peer credentials of the socket, matched against the
+//!   users' `uid`.
+//! * Everything else: a challenge-response exchange at the beginning of
+//!   the connection. The client calls `auth.challenge` to get a nonce,
+//!   and replies with `auth.response` carrying its name and the hex
+//!   encoded [`challenge_proof()`] of that nonce.
+use std::{collections::HashMap, fmt};
+
+use rand::{rngs::OsRng, Rng};
+use tinyjson::JsonValue;
+
+use super::jsonrpc::{ErrorCode, JsonError, JsonRequest, JsonResponse, JsonResult};
+use crate::util::encoding::base64;
+
+/// Method returning a nonce for challenge-response authentication
+pub const AUTH_CHALLENGE_METHOD: &str = "auth.challenge";
+
+/// Method answering the challenge, authenticating the connection
+pub const AUTH_RESPONSE_METHOD: &str = "auth.response";
+
+/// A user allowed to authenticate to the JSON-RPC server
+#[derive(Clone, serde::Deserialize)]
+pub struct RpcUser {
+    /// Username
+    pub name: String,
+    /// Shared secret, used as the bearer token, the basic auth password,
+    /// and the challenge-response key
+    #[serde(default)]
+    pub secret: Option<String>,
+    /// Unix user ID, authenticating `unix://` peers running as that user
+    #[serde(default)]
+    pub uid: Option<u32>,
+    /// Role granted to the user
+    pub role: String,
+}
+
+// Keep the secret out of logs
+impl fmt::Debug for RpcUser {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RpcUser")
+            .field("name", &self.name)
+            .field("secret", &self.secret.as_ref().map(|_| "<redacted>"))
+            .field("uid", &self.uid)
+            .field("role", &self.role)
+            .finish()
+    }
+}
+
+/// JSON-RPC authentication settings
+#[derive(Clone, Debug, Default)]
+pub struct RpcAuth {
+    /// Users allowed to authenticate
+    pub users: Vec<RpcUser>,
+    /// Roles, and the methods they are allowed to call. An entry of `*`
+    /// allows any method, and an entry ending with `.*` allows all of the
+    /// methods in a namespace, e.g. `dnet.*`.
+    pub roles: HashMap<String, Vec<String>>,
+}
+
+impl RpcAuth {
+    /// Authentication is enabled as soon as any user is configured
+    pub fn is_enabled(&self) -> bool {
+        !self.users.is_empty()
+    }
+
+    /// Check if a peer authenticated with the given role may call `method`.
+    /// Always true when authentication is disabled.
+    pub fn is_authorized(&self, role: Option<&str>, method: &str) -> bool {
+        if !self.is_enabled() {
+            return true
+        }
+
+        let Some(allowed) = role.and_then(|role| self.roles.get(role)) else { return false };
+
+        allowed.iter().any(|entry| match entry.strip_suffix('*') {
+            Some("") => true,
+            Some(prefix) if prefix.ends_with('.') => method.starts_with(prefix),
+            _ => entry == method,
+        })
+    }
+
+    /// Authenticate the value of an HTTP `Authorization` header.
+    /// Returns the user's role on success.
+    pub fn authenticate_http(&self, authorization: &str) -> Option<String> {
+        let (scheme, credentials) = authorization.trim().split_once(' ')?;
+        let credentials = credentials.trim();
+
+        let user = if scheme.eq_ignore_ascii_case("bearer") {
+            self.users.iter().find(|u| secret_matches(u, credentials))?
+        } else if scheme.eq_ignore_ascii_case("basic") {
+            let credentials = String::from_utf8(base64::decode(credentials)?).ok()?;
+            let (name, secret) = credentials.split_once(':')?;
+            self.users.iter().find(|u| u.name == name && secret_matches(u, secret))?
+        } else {
+            return None
+        };
+
+        Some(user.role.clone())
+    }
+
+    /// Authenticate a Unix socket peer by its user ID.
+    /// Returns the user's role on success.
+    pub fn authenticate_uid(&self, uid: u32) -> Option<String> {
+        self.users.iter().find(|u| u.uid == Some(uid)).map(|u| u.role.clone())
+    }
+
+    /// Handle an `auth.challenge` or `auth.response` request, updating the
+    /// connection's [`AuthState`].
+    pub(super) fn handle_request(&self, req: &JsonRequest, state: &mut AuthState) -> JsonResult {
+        let Some(params) = req.params.get::<Vec<JsonValue>>() else {
+            return JsonError::new(ErrorCode::InvalidParams, None, req.id).into()
+        };
+
+        match req.method.as_str() {
+            AUTH_CHALLENGE_METHOD => {
+                if !params.is_empty() {
+                    return JsonError::new(ErrorCode::InvalidParams, None, req.id).into()
+                }
+
+                let nonce: [u8; 32] = OsRng.gen();
+                let nonce: String = nonce.iter().map(|b| format!("{:02x}", b)).collect();
+                state.challenge = Some(nonce.clone());
+
+                JsonResponse::new(JsonValue::String(nonce), req.id).into()
+            }
+
+            AUTH_RESPONSE_METHOD => {
+                if params.len() != 2 || !params[0].is_string() || !params[1].is_string() {
+                    return JsonError::new(ErrorCode::InvalidParams, None, req.id).into()
+                }
+
+                let name = params[0].get::<String>().unwrap();
+                let proof = params[1].get::<String>().unwrap();
+
+                // A challenge can only be answered once
+                let Some(nonce) = state.challenge.take() else {
+                    let msg = "no pending challenge".to_string();
+                    return JsonError::new(ErrorCode::Unauthorized, Some(msg), req.id).into()
+                };
+
+                let Ok(proof) = blake3::Hash::from_hex(proof) else {
+                    return JsonError::new(ErrorCode::InvalidParams, None, req.id).into()
+                };
+
+                let user = self.users.iter().find(|u| {
+                    &u.name == name &&
+                        u.secret.as_ref().is_some_and(|s| challenge_proof(s, &nonce) == proof)
+                });
+
+                match user {
+                    Some(user) => {
+                        state.role = Some(user.role.clone());
+                        JsonResponse::new(JsonValue::Boolean(true), req.id).into()
+                    }
+                    None => JsonError::new(ErrorCode::Unauthorized, None, req.id).into(),
+                }
+            }
+
+            _ => JsonError::new(ErrorCode::MethodNotFound, None, req.id).into(),
+        }
+    }
+}
+
+/// Per-connection authentication state
+#[derive(Default)]
+pub(super) struct AuthState {
+    /// Role the connection is authenticated with
+    pub role: Option<String>,
+    /// Nonce handed out by `auth.challenge`, waiting for `auth.response`
+    challenge: Option<String>,
+}
+
+impl AuthState {
+    /// Create a new state, already authenticated if `role` is set
+    pub(super) fn new(role: Option<String>) -> Self {
+        Self { role, challenge: None }
+    }
+}
+
+/// Compute the proof answering a challenge `nonce` with the given secret.
+pub fn challenge_proof(secret: &str, nonce: &str) -> blake3::Hash {
+    let key = blake3::hash(secret.as_bytes());
+    blake3::keyed_hash(key.as_bytes(), nonce.as_bytes())
+}
+
+/// Compare a user's secret with the given one. The comparison is done on
+/// the hashes, as [`blake3::Hash`] equality is constant-time.
+fn secret_matches(user: &RpcUser, secret: &str) -> bool {
+    user.secret
+        .as_ref()
+        .is_some_and(|s| blake3::hash(s.as_bytes()) == blake3::hash(secret.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn auth() -> RpcAuth {
+        RpcAuth {
+            users: vec![
+                RpcUser {
+                    name: "alice".to_string(),
+                    secret: Some("hunter2".to_string()),
+                    uid: None,
+                    role: "admin".to_string(),
+                },
+                RpcUser {
+                    name: "bob".to_string(),
+                    secret: None,
+                    uid: Some(1000),
+                    role: "reader".to_string(),
+                },
+            ],
+            roles: HashMap::from([
+                ("admin".to_string(), vec!["*".to_string()]),
+                ("reader".to_string(), vec!["ping".to_string(), "dnet.*".to_string()]),
+            ]),
+        }
+    }
+
+    #[test]
+    fn authorization() {
+        let auth = auth();
+        assert!(auth.is_authorized(Some("admin"), "tx.broadcast"));
+        assert!(auth.is_authorized(Some("reader"), "ping"));
+        assert!(auth.is_authorized(Some("reader"), "dnet.switch"));
+        assert!(!auth.is_authorized(Some("reader"), "dnetswitch"));
+        assert!(!auth.is_authorized(Some("reader"), "tx.broadcast"));
+        assert!(!auth.is_authorized(Some("unknown"), "ping"));
+        assert!(!auth.is_authorized(None, "ping"));
+
+        // Everything is allowed with authentication disabled
+        assert!(RpcAuth::default().is_authorized(None, "tx.broadcast"));
+    }
+
+    #[test]
+    fn authentication() {
+        let auth = auth();
+        assert_eq!(auth.authenticate_http("Bearer hunter2"), Some("admin".to_string()));
+        assert_eq!(auth.authenticate_http("Bearer hunter3"), None);
+
+        let basic = format!("Basic {}", base64::encode(b"alice:hunter2"));
+        assert_eq!(auth.authenticate_http(&basic), Some("admin".to_string()));
+        let basic = format!("Basic {}", base64::encode(b"bob:hunter2"));
+        assert_eq!(auth.authenticate_http(&basic), None);
+
+        assert_eq!(auth.authenticate_uid(1000), Some("reader".to_string()));
+        assert_eq!(auth.authenticate_uid(0), None);
+
+        // Challenge-response
+        let mut state = AuthState::default();
+        let req = JsonRequest::new(AUTH_CHALLENGE_METHOD, JsonValue::Array(vec![]));
+        let JsonResult::Response(rep) = auth.handle_request(&req, &mut state) else { panic!() };
+        let nonce = rep.result.get::<String>().unwrap();
+
+        let proof = challenge_proof("hunter2", nonce).to_hex().to_string();
+        let params = vec![JsonValue::String("alice".to_string()), JsonValue::String(proof)];
+        let req = JsonRequest::new(AUTH_RESPONSE_METHOD, JsonValue::Array(params.clone()));
+        assert!(matches!(auth.handle_request(&req, &mut state), JsonResult::Response(_)));
+        assert_eq!(state.role, Some("admin".to_string()));
+
+        // The challenge can't be replayed
+        let mut state = AuthState::default();
+        assert!(matches!(auth.handle_request(&req, &mut state), JsonResult::Error(_)));
+        assert_eq!(state.role, None);
+    }
+}
@@ -19,21 +19,37 @@
 use std::{io, time::Duration};
 
 use log::error;
-use smol::io::{AsyncReadExt, AsyncWriteExt, BufReader, ReadHalf, WriteHalf};
+use sha1::{Digest, Sha1};
+use smol::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader, ReadHalf, WriteHalf},
+    lock::Mutex,
+};
 
 use super::jsonrpc::*;
-use crate::net::transport::PtStream;
+use crate::{net::transport::PtStream, util::encoding::base64};
 
 pub(super) const INIT_BUF_SIZE: usize = 4096; // 4K
 pub(super) const MAX_BUF_SIZE: usize = 1024 * 1024 * 16; // 16M
 pub(super) const READ_TIMEOUT: Duration = Duration::from_secs(30);
 
+/// GUID the client key is concatenated with in the WebSocket handshake
+const WS_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// WebSocket frame opcodes
+pub(super) const WS_OPCODE_CONTINUATION: u8 = 0x0;
+pub(super) const WS_OPCODE_TEXT: u8 = 0x1;
+pub(super) const WS_OPCODE_BINARY: u8 = 0x2;
+pub(super) const WS_OPCODE_CLOSE: u8 = 0x8;
+pub(super) const WS_OPCODE_PING: u8 = 0x9;
+pub(super) const WS_OPCODE_PONG: u8 = 0xa;
+
 /// Internal read function that reads from the active stream into a buffer.
-/// Performs HTTP POST request parsing. Returns the request body length.
+/// Performs HTTP POST request parsing. Returns the request body length, and
+/// the value of the `Authorization` header if there was one.
 pub(super) async fn http_read_from_stream_request(
     reader: &mut BufReader<ReadHalf<Box<dyn PtStream>>>,
     buf: &mut Vec<u8>,
-) -> io::Result<usize> {
+) -> io::Result<(usize, Option<String>)> {
     let mut total_read = 0;
 
     // Intermediate buffer we use to read byte-by-byte.
@@ -75,6 +91,7 @@ pub(super) async fn http_read_from_stream_request(
     };
 
     let mut content_length: usize = 0;
+    let mut authorization = None;
     for header in headers {
         if header.name.to_lowercase() == "content-length" {
             let s = String::from_utf8_lossy(header.value);
@@ -83,6 +100,10 @@ pub(super) async fn http_read_from_stream_request(
                 Err(_) => return Err(io::ErrorKind::InvalidData.into()),
             };
         }
+
+        if header.name.to_lowercase() == "authorization" {
+            authorization = Some(String::from_utf8_lossy(header.value).to_string());
+        }
     }
 
     if content_length == 0 || content_length > MAX_BUF_SIZE {
@@ -95,7 +116,7 @@ pub(super) async fn http_read_from_stream_request(
     reader.read(buf).await?;
 
     assert!(buf.len() == content_length);
-    Ok(content_length)
+    Ok((content_length, authorization))
 }
 
 /// Internal read function that reads from the active stream into a buffer.
@@ -209,22 +230,44 @@ pub(super) async fn read_from_stream(
     Ok(total_read)
 }
 
+/// Serialize a JSON-RPC object into the string we write to the stream.
+fn stringify(object: &JsonResult) -> String {
+    match object {
+        JsonResult::Notification(v) => v.stringify().unwrap(),
+        JsonResult::Response(v) => v.stringify().unwrap(),
+        JsonResult::Error(v) => v.stringify().unwrap(),
+        JsonResult::Request(v) => v.stringify().unwrap(),
+        _ => unreachable!(),
+    }
+}
+
 /// Internal write function that writes a JSON-RPC object to the active stream.
 /// Sent as an HTTP response.
 pub(super) async fn http_write_to_stream(
     writer: &mut WriteHalf<Box<dyn PtStream>>,
     object: &JsonResult,
 ) -> io::Result<()> {
-    let (status_line, object_str) = match object {
-        JsonResult::Notification(v) => ("HTTP/1.1 200 OK", v.stringify().unwrap()),
-        JsonResult::Response(v) => ("HTTP/1.1 200 OK", v.stringify().unwrap()),
-        JsonResult::Error(v) => ("HTTP/1.1 400 Bad Request", v.stringify().unwrap()),
-        JsonResult::Request(v) => ("POST /json_rpc HTTP/1.1", v.stringify().unwrap()),
-        _ => unreachable!(),
+    let status_line = match object {
+        JsonResult::Error(_) => "HTTP/1.1 400 Bad Request",
+        JsonResult::Request(_) => "POST /json_rpc HTTP/1.1",
+        _ => "HTTP/1.1 200 OK",
     };
 
+    http_write_str_to_stream(writer, status_line, &[], &stringify(object)).await
+}
+
+/// Internal write function that writes a serialized JSON string to the
+/// active stream, prefixed with the given HTTP status or request line and
+/// any additional headers.
+pub(super) async fn http_write_str_to_stream(
+    writer: &mut WriteHalf<Box<dyn PtStream>>,
+    status_line: &str,
+    headers: &[(&str, &str)],
+    object_str: &str,
+) -> io::Result<()> {
     let length = object_str.len();
-    let data = format!("{status_line}\r\nContent-Length: {length}\r\nContent-Type: application/json\r\n\r\n{object_str}");
+    let headers: String = headers.iter().map(|(k, v)| format!("{k}: {v}\r\n")).collect();
+    let data = format!("{status_line}\r\n{headers}Content-Length: {length}\r\nContent-Type: application/json\r\n\r\n{object_str}");
 
     writer.write_all(data.as_bytes()).await?;
     writer.flush().await?;
@@ -237,14 +280,15 @@ pub(super) async fn write_to_stream(
     writer: &mut WriteHalf<Box<dyn PtStream>>,
     object: &JsonResult,
 ) -> io::Result<()> {
-    let object_str = match object {
-        JsonResult::Notification(v) => v.stringify().unwrap(),
-        JsonResult::Response(v) => v.stringify().unwrap(),
-        JsonResult::Error(v) => v.stringify().unwrap(),
-        JsonResult::Request(v) => v.stringify().unwrap(),
-        _ => unreachable!(),
-    };
+    write_str_to_stream(writer, &stringify(object)).await
+}
 
+/// Internal write function that writes a serialized JSON string to the
+/// active stream.
+pub(super) async fn write_str_to_stream(
+    writer: &mut WriteHalf<Box<dyn PtStream>>,
+    object_str: &str,
+) -> io::Result<()> {
     // As we're a line-based protocol, we append CRLF to the end of the JSON string.
     for i in [object_str.as_bytes(), b"\r\n"] {
         writer.write_all(i).await?
@@ -254,3 +298,242 @@ pub(super) async fn write_to_stream(
 
     Ok(())
 }
+
+/// Compute the `Sec-WebSocket-Accept` value for a client's `Sec-WebSocket-Key`.
+pub(super) fn ws_accept_key(key: &str) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(key.trim().as_bytes());
+    hasher.update(WS_GUID.as_bytes());
+    base64::encode(&hasher.finalize())
+}
+
+/// Internal function performing the server side of the WebSocket opening
+/// handshake (RFC 6455, section 4.2). Replies with `400 Bad Request` and
+/// returns an error if the client did not ask for a valid upgrade.
+pub(super) async fn ws_handshake_request(
+    reader: &mut BufReader<ReadHalf<Box<dyn PtStream>>>,
+    writer: &mut WriteHalf<Box<dyn PtStream>>,
+) -> io::Result<()> {
+    let mut buf = Vec::with_capacity(INIT_BUF_SIZE);
+
+    // Intermediate buffer we use to read byte-by-byte.
+    let mut tmpbuf = [0_u8];
+
+    loop {
+        if buf.len() >= INIT_BUF_SIZE * 4 {
+            return Err(io::ErrorKind::InvalidData.into())
+        }
+
+        match reader.read(&mut tmpbuf).await? {
+            0 if buf.is_empty() => return Err(io::ErrorKind::ConnectionAborted.into()),
+            0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+            _ => buf.push(tmpbuf[0]),
+        }
+
+        // The opening handshake has no body, so we're done with the headers.
+        if buf.ends_with(b"\r\n\r\n") {
+            break
+        }
+    }
+
+    let mut headers = [httparse::EMPTY_HEADER; 32];
+    let mut req = httparse::Request::new(&mut headers);
+    let parsed = req.parse(&buf).is_ok_and(|s| s.is_complete());
+
+    let mut upgrade = false;
+    let mut version = false;
+    let mut key = None;
+    for header in req.headers.iter() {
+        let value = String::from_utf8_lossy(header.value);
+        match header.name.to_lowercase().as_str() {
+            "upgrade" => upgrade = value.eq_ignore_ascii_case("websocket"),
+            "sec-websocket-version" => version = value.trim() == "13",
+            "sec-websocket-key" => key = Some(value.to_string()),
+            _ => {}
+        }
+    }
+
+    let key = match key {
+        Some(key) if parsed && req.method == Some("GET") && upgrade && version => key,
+        _ => {
+            error!("[RPC] Invalid WebSocket handshake request");
+            let resp = "HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\n\r\n";
+            writer.write_all(resp.as_bytes()).await?;
+            writer.flush().await?;
+            return Err(io::ErrorKind::InvalidData.into())
+        }
+    };
+
+    let accept = ws_accept_key(&key);
+    let resp = format!("HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {accept}\r\n\r\n");
+    writer.write_all(resp.as_bytes()).await?;
+    writer.flush().await?;
+
+    Ok(())
+}
+
+/// Internal function that reads a single WebSocket frame and appends its
+/// (unmasked) payload to the given buffer. Returns the frame opcode, and
+/// whether the FIN and MASK bits were set.
+pub(super) async fn ws_read_frame<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    buf: &mut Vec<u8>,
+) -> io::Result<(u8, bool, bool)> {
+    let mut header = [0_u8; 2];
+    if let Err(e) = reader.read_exact(&mut header).await {
+        if e.kind() == io::ErrorKind::UnexpectedEof {
+            return Err(io::ErrorKind::ConnectionAborted.into())
+        }
+        return Err(e)
+    }
+
+    let fin = header[0] & 0x80 != 0;
+    let opcode = header[0] & 0x0f;
+    let masked = header[1] & 0x80 != 0;
+
+    // We don't negotiate any extensions, so the RSV bits must be unset.
+    if header[0] & 0x70 != 0 {
+        return Err(io::ErrorKind::InvalidData.into())
+    }
+
+    let len = match header[1] & 0x7f {
+        126 => {
+            let mut len = [0_u8; 2];
+            reader.read_exact(&mut len).await?;
+            u16::from_be_bytes(len) as u64
+        }
+        127 => {
+            let mut len = [0_u8; 8];
+            reader.read_exact(&mut len).await?;
+            u64::from_be_bytes(len)
+        }
+        len => len as u64,
+    };
+
+    // Control frames can not be fragmented and carry at most 125 bytes.
+    if opcode & 0x08 != 0 && (!fin || len > 125) {
+        return Err(io::ErrorKind::InvalidData.into())
+    }
+
+    if buf.len() as u64 + len > MAX_BUF_SIZE as u64 {
+        return Err(io::ErrorKind::InvalidData.into())
+    }
+
+    let mut mask = [0_u8; 4];
+    if masked {
+        reader.read_exact(&mut mask).await?;
+    }
+
+    let offset = buf.len();
+    buf.resize(offset + len as usize, 0_u8);
+    reader.read_exact(&mut buf[offset..]).await?;
+
+    if masked {
+        for (i, byte) in buf[offset..].iter_mut().enumerate() {
+            *byte ^= mask[i % 4];
+        }
+    }
+
+    Ok((opcode, fin, masked))
+}
+
+/// Internal function that writes a single WebSocket frame with the given
+/// opcode and payload. Clients must pass a masking key, servers must not.
+pub(super) async fn ws_write_frame<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    opcode: u8,
+    payload: &[u8],
+    mask: Option<[u8; 4]>,
+) -> io::Result<()> {
+    let mask_bit = if mask.is_some() { 0x80 } else { 0x00 };
+
+    let mut frame = Vec::with_capacity(payload.len() + 14);
+    frame.push(0x80 | opcode);
+    match payload.len() {
+        len @ 0..=125 => frame.push(mask_bit | len as u8),
+        len @ 126..=0xffff => {
+            frame.push(mask_bit | 126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(mask_bit | 127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+
+    match mask {
+        Some(mask) => {
+            frame.extend_from_slice(&mask);
+            frame.extend(payload.iter().enumerate().map(|(i, byte)| byte ^ mask[i % 4]));
+        }
+        None => frame.extend_from_slice(payload),
+    }
+
+    writer.write_all(&frame).await?;
+    writer.flush().await?;
+
+    Ok(())
+}
+
+/// Internal read function that reads a complete WebSocket message from the
+/// active stream into a buffer. Control frames received in between are
+/// handled here: pings are answered using the given writer, and a close
+/// frame is echoed back before returning `ConnectionAborted`.
+pub(super) async fn ws_read_from_stream(
+    reader: &mut BufReader<ReadHalf<Box<dyn PtStream>>>,
+    writer: &Mutex<WriteHalf<Box<dyn PtStream>>>,
+    buf: &mut Vec<u8>,
+) -> io::Result<usize> {
+    buf.clear();
+    let mut started = false;
+
+    loop {
+        let mut payload = vec![];
+        let (opcode, fin, masked) = ws_read_frame(reader, &mut payload).await?;
+
+        // Clients must mask all frames they send to the server.
+        if !masked {
+            return Err(io::ErrorKind::InvalidData.into())
+        }
+
+        match opcode {
+            WS_OPCODE_TEXT | WS_OPCODE_BINARY if !started => started = true,
+            WS_OPCODE_CONTINUATION if started => {}
+
+            WS_OPCODE_PING => {
+                let mut writer = writer.lock().await;
+                ws_write_frame(&mut *writer, WS_OPCODE_PONG, &payload, None).await?;
+                continue
+            }
+
+            WS_OPCODE_PONG => continue,
+
+            WS_OPCODE_CLOSE => {
+                let mut writer = writer.lock().await;
+                let status = &payload[..payload.len().min(2)];
+                ws_write_frame(&mut *writer, WS_OPCODE_CLOSE, status, None).await?;
+                return Err(io::ErrorKind::ConnectionAborted.into())
+            }
+
+            _ => return Err(io::ErrorKind::InvalidData.into()),
+        }
+
+        if buf.len() + payload.len() > MAX_BUF_SIZE {
+            return Err(io::ErrorKind::InvalidData.into())
+        }
+        buf.extend_from_slice(&payload);
+
+        if fin {
+            return Ok(buf.len())
+        }
+    }
+}
+
+/// Internal write function that writes a JSON-RPC object to the active stream.
+/// Sent as a WebSocket text frame.
+pub(super) async fn ws_write_to_stream(
+    writer: &mut WriteHalf<Box<dyn PtStream>>,
+    object: &JsonResult,
+) -> io::Result<()> {
+    ws_write_frame(writer, WS_OPCODE_TEXT, stringify(object).as_bytes(), None).await
+}
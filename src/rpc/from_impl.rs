@@ -95,6 +95,19 @@ impl From<net::dnet::OutboundPeerDiscovery> for JsonValue {
     }
 }
 
+#[cfg(feature = "net")]
+impl From<net::dnet::ResourceInfo> for JsonValue {
+    fn from(info: net::dnet::ResourceInfo) -> JsonValue {
+        json_map([
+            ("chan", info.chan.into()),
+            ("protocol", JsonStr(info.protocol.to_string())),
+            ("resource", JsonStr(info.resource)),
+            ("delay", JsonNum(info.delay as f64)),
+            ("time", JsonStr(info.time.0.to_string())),
+        ])
+    }
+}
+
 #[cfg(feature = "net")]
 impl From<net::dnet::DnetEvent> for JsonValue {
     fn from(event: net::dnet::DnetEvent) -> JsonValue {
@@ -126,6 +139,15 @@ impl From<net::dnet::DnetEvent> for JsonValue {
             net::dnet::DnetEvent::OutboundPeerDiscovery(info) => {
                 json_map([("event", json_str("outbound_peer_discovery")), ("info", info.into())])
             }
+            net::dnet::DnetEvent::ResourceThrottled(info) => {
+                json_map([("event", json_str("resource_throttled")), ("info", info.into())])
+            }
+            net::dnet::DnetEvent::ResourceDisconnected(info) => {
+                json_map([("event", json_str("resource_disconnected")), ("info", info.into())])
+            }
+            net::dnet::DnetEvent::ResourceBanned(info) => {
+                json_map([("event", json_str("resource_banned")), ("info", info.into())])
+            }
         }
     }
 }
@@ -47,6 +47,8 @@ pub enum ErrorCode {
     IdMismatch,
     /// Invalid/Unexpected reply
     InvalidReply,
+    /// Caller is not authenticated or not allowed to call the method
+    Unauthorized,
     /// Reserved for implementation-defined server-errors.
     ServerError(i32),
 }
@@ -61,6 +63,7 @@ impl ErrorCode {
             Self::InternalError => -32603,
             Self::IdMismatch => -32360,
             Self::InvalidReply => -32361,
+            Self::Unauthorized => -32362,
             Self::ServerError(c) => c,
         }
     }
@@ -74,6 +77,7 @@ impl ErrorCode {
             Self::InternalError => "internal error".to_string(),
             Self::IdMismatch => "id mismatch".to_string(),
             Self::InvalidReply => "invalid reply".to_string(),
+            Self::Unauthorized => "unauthorized".to_string(),
             Self::ServerError(_) => "server error".to_string(),
         }
     }
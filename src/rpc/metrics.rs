@@ -0,0 +1,274 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2025 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{
+    io::{self, ErrorKind},
+    sync::{Arc, LazyLock},
+    time::Duration,
+};
+
+use log::{debug, error, info};
+use smol::io::{AsyncReadExt, AsyncWriteExt, BufReader};
+use url::Url;
+
+use super::{
+    common::READ_TIMEOUT,
+    jsonrpc::{ErrorCode, JsonResult},
+};
+use crate::{
+    net::transport::{Listener, PtStream},
+    system::{io_timeout, ExecutorPtr, StoppableTask, StoppableTaskPtr},
+    util::metrics::{registry, Counter, Family, Format, Histogram, DEFAULT_BUCKETS},
+    Error, Result,
+};
+
+/// Maximum size of the HTTP request headers we accept
+const MAX_REQUEST_SIZE: usize = 8192;
+
+/// JSON-RPC server metrics, registered in the global registry on first use
+pub(super) static RPC_METRICS: LazyLock<RpcMetrics> = LazyLock::new(RpcMetrics::new);
+
+pub(super) struct RpcMetrics {
+    /// Handled requests, by method
+    requests: Arc<Family<Counter>>,
+    /// Request handling time, by method
+    latency: Arc<Family<Histogram>>,
+}
+
+impl RpcMetrics {
+    fn new() -> Self {
+        let r = registry();
+        Self {
+            requests: r.counter_family(
+                "darkfi_rpc_requests_total",
+                "JSON-RPC requests handled",
+                &["method"],
+            ),
+            latency: r.histogram_family(
+                "darkfi_rpc_request_duration_seconds",
+                "JSON-RPC request handling time",
+                &["method"],
+                DEFAULT_BUCKETS,
+            ),
+        }
+    }
+
+    /// Record a handled request. Requests for methods we don't serve are
+    /// accounted as `unknown`, so clients can't make up new label values.
+    pub(super) fn record(&self, method: &str, reply: &JsonResult, elapsed: Duration) {
+        // Don't let unknown or unauthenticated callers grow the label set
+        let method = match reply {
+            JsonResult::Error(e)
+                if e.error.code == ErrorCode::MethodNotFound.code() ||
+                    e.error.code == ErrorCode::Unauthorized.code() =>
+            {
+                "unknown"
+            }
+            _ => method,
+        };
+
+        self.requests.with(&[method]).inc();
+        self.latency.with(&[method]).observe_duration(elapsed);
+    }
+}
+
+/// Start an HTTP server bound to the given listen URL, serving the global
+/// metrics registry on `GET /metrics` for Prometheus to scrape.
+///
+/// The listen URL takes the same network schemes as the JSON-RPC server,
+/// optionally prefixed with `http+`. The OpenMetrics format is served to
+/// clients asking for it in the `Accept` header.
+pub async fn listen_and_serve(listen: Url, ex: ExecutorPtr) -> Result<()> {
+    let mut listen_url = listen.clone();
+    if let Some(scheme) = listen.scheme().strip_prefix("http+") {
+        let url_str = listen.as_str().replace(listen.scheme(), scheme);
+        listen_url = url_str.parse()?;
+    }
+
+    let listener = Listener::new(listen_url, None).await?.listen(ex.clone()).await?;
+    info!(target: "rpc::metrics", "[METRICS] Serving metrics on {}", listen);
+
+    loop {
+        match listener.next().await {
+            Ok((stream, url)) => {
+                ex.spawn(async move {
+                    if let Err(e) = serve(stream).await {
+                        debug!(target: "rpc::metrics", "[METRICS] Failed serving {}: {}", url, e);
+                    }
+                })
+                .detach();
+            }
+
+            // As per accept(2) recommendation:
+            Err(e) if e.raw_os_error().is_some() => match e.raw_os_error().unwrap() {
+                libc::EAGAIN | libc::ECONNABORTED | libc::EPROTO | libc::EINTR => continue,
+                _ => {
+                    error!(
+                        target: "rpc::metrics::listen_and_serve()",
+                        "[METRICS] Server failed listening: {}", e,
+                    );
+                    return Err(e.into())
+                }
+            },
+
+            // In case a TLS handshake fails, we'll get this:
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => continue,
+
+            Err(e) => {
+                error!(
+                    target: "rpc::metrics::listen_and_serve()",
+                    "[METRICS] Unhandled listener.next() error: {}", e,
+                );
+                return Err(e.into())
+            }
+        }
+    }
+}
+
+/// Start [`listen_and_serve()`] in a background task, logging any error
+/// it exits with. The returned task can be used to stop the exporter.
+pub fn start_exporter(listen: Url, ex: ExecutorPtr) -> StoppableTaskPtr {
+    let task = StoppableTask::new();
+    task.clone().start(
+        listen_and_serve(listen, ex.clone()),
+        |res| async move {
+            match res {
+                Ok(()) | Err(Error::DetachedTaskStopped) => { /* Do nothing */ }
+                Err(e) => error!(target: "rpc::metrics", "[METRICS] Exporter failed: {}", e),
+            }
+        },
+        Error::DetachedTaskStopped,
+        ex,
+    );
+
+    task
+}
+
+/// Serve a single HTTP request on the given stream, then close it
+async fn serve(stream: Box<dyn PtStream>) -> io::Result<()> {
+    let (reader, mut writer) = smol::io::split(stream);
+    let mut reader = BufReader::new(reader);
+
+    // Read the request headers, the request body is of no interest to us.
+    let mut buf = vec![];
+    let mut byte = [0_u8];
+    while !buf.ends_with(b"\r\n\r\n") {
+        if buf.len() >= MAX_REQUEST_SIZE {
+            return Err(ErrorKind::InvalidData.into())
+        }
+
+        if io_timeout(READ_TIMEOUT, reader.read(&mut byte)).await? == 0 {
+            return Err(ErrorKind::UnexpectedEof.into())
+        }
+
+        buf.push(byte[0]);
+    }
+
+    let mut headers = [httparse::EMPTY_HEADER; 32];
+    let mut req = httparse::Request::new(&mut headers);
+    if req.parse(&buf).is_err() {
+        return Err(ErrorKind::InvalidData.into())
+    }
+
+    let path = req.path.unwrap_or_default();
+    let path = path.split('?').next().unwrap();
+
+    let (status, content_type, body) = match (req.method, path) {
+        (Some("GET"), "/metrics") => {
+            let openmetrics = req.headers.iter().any(|h| {
+                h.name.eq_ignore_ascii_case("accept") &&
+                    String::from_utf8_lossy(h.value).contains("application/openmetrics-text")
+            });
+
+            let format = if openmetrics { Format::OpenMetrics } else { Format::Prometheus };
+            ("200 OK", format.content_type(), registry().encode(format))
+        }
+        (Some("GET"), _) => ("404 Not Found", "text/plain", "Not Found\n".to_string()),
+        _ => ("405 Method Not Allowed", "text/plain", "Method Not Allowed\n".to_string()),
+    };
+
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body,
+    );
+
+    writer.write_all(response.as_bytes()).await?;
+    writer.flush().await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use smol::{
+        net::{TcpListener, TcpStream},
+        Executor,
+    };
+
+    async fn get(addr: &str, request: &str) -> io::Result<String> {
+        let mut stream = TcpStream::connect(addr).await?;
+        stream.write_all(request.as_bytes()).await?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await?;
+        Ok(response)
+    }
+
+    #[test]
+    fn serve_metrics() -> Result<()> {
+        let executor = Arc::new(Executor::new());
+
+        smol::block_on(executor.run(async {
+            // Find an available port
+            let listener = TcpListener::bind("127.0.0.1:0").await?;
+            let addr = listener.local_addr()?.to_string();
+            drop(listener);
+
+            registry().counter("test_exporter_requests_total", "Exporter test counter").inc_by(7);
+
+            let listen = Url::parse(&format!("http+tcp://{}", addr))?;
+            let task = start_exporter(listen, executor.clone());
+            crate::system::msleep(200).await;
+
+            let response = get(&addr, "GET /metrics HTTP/1.1\r\nHost: x\r\n\r\n").await?;
+            assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+            assert!(response.contains(Format::Prometheus.content_type()));
+            assert!(response.contains("\r\n\r\n# HELP"));
+            assert!(response.contains("\ntest_exporter_requests_total 7\n"));
+
+            let request =
+                "GET /metrics?x=1 HTTP/1.1\r\nAccept: application/openmetrics-text\r\n\r\n";
+            let response = get(&addr, request).await?;
+            assert!(response.contains(Format::OpenMetrics.content_type()));
+            assert!(response.contains("# TYPE test_exporter_requests counter\n"));
+            assert!(response.ends_with("# EOF\n"));
+
+            let response = get(&addr, "GET / HTTP/1.1\r\n\r\n").await?;
+            assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+
+            let response = get(&addr, "POST /metrics HTTP/1.1\r\n\r\n").await?;
+            assert!(response.starts_with("HTTP/1.1 405 Method Not Allowed\r\n"));
+
+            task.stop().await;
+            Ok(())
+        }))
+    }
+}
@@ -40,5 +40,14 @@ pub mod p2p_method;
 /// Json helper methods and types
 pub mod util;
 
+/// OpenRPC service description
+pub mod openrpc;
+
 /// JSON-RPC settings
 pub mod settings;
+
+/// JSON-RPC authentication and authorization
+pub mod auth;
+
+/// HTTP exporter for the metrics registry, and JSON-RPC server metrics
+pub mod metrics;
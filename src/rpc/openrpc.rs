@@ -0,0 +1,115 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2025 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! OpenRPC service description, served by the `rpc.discover` method.
+//!
+//! Daemons describe the methods they serve with a list of [`RpcMethod`]
+//! entries kept next to their [`RequestHandler`] implementation, and the
+//! server turns that list into an [OpenRPC](https://spec.open-rpc.org)
+//! document on request.
+//!
+//! [`RequestHandler`]: super::server::RequestHandler
+use std::collections::HashMap;
+
+use tinyjson::JsonValue;
+
+/// OpenRPC specification version the generated documents conform to
+pub const OPENRPC_VERSION: &str = "1.2.6";
+
+/// Name of the service discovery method
+pub const DISCOVER_METHOD: &str = "rpc.discover";
+
+/// Description of a single positional method parameter
+#[derive(Copy, Clone, Debug)]
+pub struct RpcParam {
+    /// Parameter name
+    pub name: &'static str,
+    /// JSON Schema type of the parameter
+    /// (`string`, `integer`, `number`, `boolean`, `array` or `object`)
+    pub schema: &'static str,
+}
+
+/// Metadata describing a single JSON-RPC method
+#[derive(Copy, Clone, Debug)]
+pub struct RpcMethod {
+    /// Method name, as matched in `handle_request()`
+    pub name: &'static str,
+    /// Short description of what the method does
+    pub summary: &'static str,
+    /// Positional parameters the method expects
+    pub params: &'static [RpcParam],
+    /// JSON Schema type of the method result
+    pub result: &'static str,
+}
+
+impl RpcMethod {
+    fn to_json(self) -> JsonValue {
+        let params = self
+            .params
+            .iter()
+            .map(|p| {
+                JsonValue::Object(HashMap::from([
+                    ("name".to_string(), JsonValue::String(p.name.to_string())),
+                    ("required".to_string(), JsonValue::Boolean(true)),
+                    ("schema".to_string(), schema(p.schema)),
+                ]))
+            })
+            .collect();
+
+        let result = JsonValue::Object(HashMap::from([
+            ("name".to_string(), JsonValue::String("result".to_string())),
+            ("schema".to_string(), schema(self.result)),
+        ]));
+
+        JsonValue::Object(HashMap::from([
+            ("name".to_string(), JsonValue::String(self.name.to_string())),
+            ("summary".to_string(), JsonValue::String(self.summary.to_string())),
+            ("paramStructure".to_string(), JsonValue::String("by-position".to_string())),
+            ("params".to_string(), JsonValue::Array(params)),
+            ("result".to_string(), result),
+        ]))
+    }
+}
+
+/// Metadata for the `ping` method provided by `RequestHandler::pong()`
+pub const PING_METHOD: RpcMethod =
+    RpcMethod { name: "ping", summary: "Replies with \"pong\"", params: &[], result: "string" };
+
+fn schema(type_: &str) -> JsonValue {
+    JsonValue::Object(HashMap::from([("type".to_string(), JsonValue::String(type_.to_string()))]))
+}
+
+/// Build an OpenRPC document describing the given methods.
+pub fn openrpc_document<'a>(
+    title: &str,
+    version: &str,
+    methods: impl IntoIterator<Item = &'a RpcMethod>,
+) -> JsonValue {
+    let info = JsonValue::Object(HashMap::from([
+        ("title".to_string(), JsonValue::String(title.to_string())),
+        ("version".to_string(), JsonValue::String(version.to_string())),
+    ]));
+
+    let methods = methods.into_iter().map(|m| m.to_json()).collect();
+
+    JsonValue::Object(HashMap::from([
+        ("openrpc".to_string(), JsonValue::String(OPENRPC_VERSION.to_string())),
+        ("info".to_string(), info),
+        ("methods".to_string(), JsonValue::Array(methods)),
+    ]))
+}
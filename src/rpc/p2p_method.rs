@@ -20,10 +20,19 @@ use async_trait::async_trait;
 
 use super::{
     jsonrpc::{JsonResponse, JsonResult},
+    openrpc::RpcMethod,
     util::*,
 };
 use crate::net;
 
+/// Metadata for the `p2p.get_info` method provided by [`HandlerP2p`]
+pub const P2P_GET_INFO_METHOD: RpcMethod = RpcMethod {
+    name: "p2p.get_info",
+    summary: "Returns the connected P2P channels and the outbound slots",
+    params: &[],
+    result: "object",
+};
+
 #[async_trait]
 pub trait HandlerP2p: Sync + Send {
     async fn p2p_get_info(&self, id: u16, _params: JsonValue) -> JsonResult {
@@ -16,11 +16,18 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
-use std::{collections::HashSet, io::ErrorKind, sync::Arc};
+use std::{
+    collections::HashSet,
+    io::{self, ErrorKind},
+    sync::{Arc, LazyLock},
+    time::Instant,
+};
 
 use async_trait::async_trait;
+use futures::future::join_all;
 use log::{debug, error, info};
 use smol::{
+    future::FutureExt,
     io::{BufReader, ReadHalf, WriteHalf},
     lock::{Mutex, MutexGuard},
 };
@@ -28,11 +35,15 @@ use tinyjson::JsonValue;
 use url::Url;
 
 use super::{
+    auth::{AuthState, AUTH_CHALLENGE_METHOD, AUTH_RESPONSE_METHOD},
     common::{
-        http_read_from_stream_request, http_write_to_stream, read_from_stream, write_to_stream,
-        INIT_BUF_SIZE,
+        http_read_from_stream_request, http_write_str_to_stream, http_write_to_stream,
+        read_from_stream, write_str_to_stream, write_to_stream, ws_handshake_request,
+        ws_read_from_stream, ws_write_frame, ws_write_to_stream, INIT_BUF_SIZE, WS_OPCODE_TEXT,
     },
     jsonrpc::*,
+    metrics::RPC_METRICS,
+    openrpc::{openrpc_document, RpcMethod, DISCOVER_METHOD},
     settings::RpcSettings,
 };
 use crate::{
@@ -52,6 +63,14 @@ pub trait RequestHandler<T>: Sync + Send {
 
     async fn connections_mut(&self) -> MutexGuard<'life0, HashSet<StoppableTaskPtr>>;
 
+    /// Metadata of the methods served by this handler, used to build the
+    /// OpenRPC document returned by `rpc.discover`. Handlers that don't
+    /// override it, such as the example and research daemons, return a
+    /// document with an empty method list.
+    fn methods(&self) -> Vec<RpcMethod> {
+        vec![]
+    }
+
     async fn connections(&self) -> Vec<StoppableTaskPtr> {
         self.connections_mut().await.iter().cloned().collect()
     }
@@ -77,7 +96,61 @@ pub trait RequestHandler<T>: Sync + Send {
     }
 }
 
+/// Auxiliary function to write a JSON-RPC object to the stream, framed
+/// for the transport the server is listening on.
+async fn write_result(
+    writer: &mut WriteHalf<Box<dyn PtStream>>,
+    settings: &RpcSettings,
+    object: &JsonResult,
+) -> io::Result<()> {
+    if settings.use_http() {
+        http_write_to_stream(writer, object).await
+    } else if settings.use_websocket() {
+        ws_write_to_stream(writer, object).await
+    } else {
+        write_to_stream(writer, object).await
+    }
+}
+
+/// Auxiliary function to pass a request to the [`RequestHandler`].
+/// Disabled methods, authorization of the caller's `role`, and
+/// `rpc.discover` are handled here.
+async fn dispatch<T>(
+    rh: &Arc<impl RequestHandler<T> + 'static>,
+    settings: &RpcSettings,
+    role: Option<&str>,
+    req: JsonRequest,
+) -> JsonResult {
+    let method = req.method.clone();
+    let start = Instant::now();
+
+    // Handle disabled RPC methods
+    let rep = if settings.is_method_disabled(&req.method) {
+        debug!(target: "rpc::server", "RPC method {} is disabled", req.method);
+        JsonError::new(ErrorCode::MethodNotFound, None, req.id).into()
+    } else if !settings.auth.is_authorized(role, &req.method) {
+        debug!(target: "rpc::server", "RPC method {} is not allowed for role {:?}", req.method, role);
+        JsonError::new(ErrorCode::Unauthorized, None, req.id).into()
+    } else if req.method == DISCOVER_METHOD {
+        // Only advertise the methods the caller is able to use
+        let methods = rh.methods();
+        let methods = methods.iter().filter(|m| {
+            !settings.is_method_disabled(&m.name.to_string()) &&
+                settings.auth.is_authorized(role, m.name)
+        });
+        let document = openrpc_document("DarkFi JSON-RPC", env!("CARGO_PKG_VERSION"), methods);
+        JsonResponse::new(document, req.id).into()
+    } else {
+        rh.handle_request(req).await
+    };
+
+    RPC_METRICS.record(&method, &rep, start.elapsed());
+
+    rep
+}
+
 /// Auxiliary function to handle a request in the background.
+#[allow(clippy::too_many_arguments)]
 async fn handle_request<T>(
     writer: Arc<Mutex<WriteHalf<Box<dyn PtStream>>>>,
     addr: Url,
@@ -85,15 +158,10 @@ async fn handle_request<T>(
     ex: Arc<smol::Executor<'_>>,
     tasks: Arc<Mutex<HashSet<Arc<StoppableTask>>>>,
     settings: RpcSettings,
+    role: Option<String>,
     req: JsonRequest,
 ) -> Result<()> {
-    // Handle disabled RPC methods
-    let rep = if settings.is_method_disabled(&req.method) {
-        debug!(target: "rpc::server", "RPC method {} is disabled", req.method);
-        JsonError::new(ErrorCode::MethodNotFound, None, req.id).into()
-    } else {
-        rh.handle_request(req).await
-    };
+    let rep = dispatch(&rh, &settings, role.as_deref(), req).await;
 
     match rep {
         JsonResult::Subscriber(subscriber) => {
@@ -120,17 +188,9 @@ async fn handle_request<T>(
 
                         let mut writer_lock = writer_.lock().await;
 
-                        #[allow(clippy::collapsible_else_if)]
-                        if settings.use_http() {
-                            if let Err(e) = http_write_to_stream(&mut writer_lock, &notification).await {
-                                subscription.unsubscribe().await;
-                                return Err(e.into())
-                            }
-                        } else {
-                            if let Err(e) = write_to_stream(&mut writer_lock, &notification).await {
-                                subscription.unsubscribe().await;
-                                return Err(e.into())
-                            }
+                        if let Err(e) = write_result(&mut writer_lock, &settings, &notification).await {
+                            subscription.unsubscribe().await;
+                            return Err(e.into())
                         }
 
                         drop(writer_lock);
@@ -155,11 +215,7 @@ async fn handle_request<T>(
             // Write the response
             debug!(target: "rpc::server", "{} <-- {}", addr, reply.stringify()?);
             let mut writer_lock = writer.lock().await;
-            if settings.use_http() {
-                http_write_to_stream(&mut writer_lock, &reply.into()).await?;
-            } else {
-                write_to_stream(&mut writer_lock, &reply.into()).await?;
-            }
+            write_result(&mut writer_lock, &settings, &reply.into()).await?;
             drop(writer_lock);
 
             let task = StoppableTask::new();
@@ -183,19 +239,10 @@ async fn handle_request<T>(
                         let notification = JsonResult::Notification(notification);
 
                         let mut writer_lock = writer_.lock().await;
-                        #[allow(clippy::collapsible_else_if)]
-                        if settings.use_http() {
-                            if let Err(e) = http_write_to_stream(&mut writer_lock, &notification).await {
-                                subscription.unsubscribe().await;
-                                drop(writer_lock);
-                                return Err(e.into())
-                            }
-                        } else {
-                            if let Err(e) = write_to_stream(&mut writer_lock, &notification).await {
-                                subscription.unsubscribe().await;
-                                drop(writer_lock);
-                                return Err(e.into())
-                            }
+                        if let Err(e) = write_result(&mut writer_lock, &settings, &notification).await {
+                            subscription.unsubscribe().await;
+                            drop(writer_lock);
+                            return Err(e.into())
                         }
                         drop(writer_lock);
                     }
@@ -222,22 +269,14 @@ async fn handle_request<T>(
         JsonResult::Response(ref v) => {
             debug!(target: "rpc::server", "{} <-- {}", addr, v.stringify()?);
             let mut writer_lock = writer.lock().await;
-            if settings.use_http() {
-                http_write_to_stream(&mut writer_lock, &rep).await?;
-            } else {
-                write_to_stream(&mut writer_lock, &rep).await?;
-            }
+            write_result(&mut writer_lock, &settings, &rep).await?;
             drop(writer_lock);
         }
 
         JsonResult::Error(ref v) => {
             debug!(target: "rpc::server", "{} <-- {}", addr, v.stringify()?);
             let mut writer_lock = writer.lock().await;
-            if settings.use_http() {
-                http_write_to_stream(&mut writer_lock, &rep).await?;
-            } else {
-                write_to_stream(&mut writer_lock, &rep).await?;
-            }
+            write_result(&mut writer_lock, &settings, &rep).await?;
             drop(writer_lock);
         }
     }
@@ -245,13 +284,94 @@ async fn handle_request<T>(
     Ok(())
 }
 
+/// Maximum number of requests accepted in a single JSON-RPC 2.0 batch
+pub const MAX_BATCH_LEN: usize = 100;
+
+/// Auxiliary function to handle a JSON-RPC 2.0 batch in the background.
+/// The requests are handled concurrently, and their replies are written
+/// back as a single array once all of them are done. Batches longer than
+/// [`MAX_BATCH_LEN`] are refused as a whole.
+async fn handle_batch<T>(
+    writer: Arc<Mutex<WriteHalf<Box<dyn PtStream>>>>,
+    addr: Url,
+    rh: Arc<impl RequestHandler<T> + 'static>,
+    settings: RpcSettings,
+    role: Option<String>,
+    batch: Vec<JsonValue>,
+) -> Result<()> {
+    // An empty or oversized batch is answered with a single error object
+    if batch.is_empty() || batch.len() > MAX_BATCH_LEN {
+        let msg = (!batch.is_empty())
+            .then(|| format!("batch exceeds the maximum of {} requests", MAX_BATCH_LEN));
+        let rep = JsonError::new(ErrorCode::InvalidRequest, msg, 0);
+        debug!(target: "rpc::server", "{} <-- {}", addr, rep.stringify()?);
+        let mut writer_lock = writer.lock().await;
+        write_result(&mut writer_lock, &settings, &rep.into()).await?;
+        drop(writer_lock);
+        return Ok(())
+    }
+
+    let (rh, settings_, role, addr_) = (&rh, &settings, role.as_deref(), &addr);
+    let replies = join_all(batch.iter().map(|val| async move {
+        let req = match JsonRequest::try_from(val) {
+            Ok(v) => v,
+            Err(e) => {
+                debug!(target: "rpc::server", "Invalid request in batch from {}: {}", addr_, e);
+                let id = match val {
+                    JsonValue::Object(map) => map.get("id").and_then(|v| v.get::<f64>()),
+                    _ => None,
+                };
+                let id = id.map_or(0, |id| *id as u16);
+                return JsonError::new(ErrorCode::InvalidRequest, None, id).into()
+            }
+        };
+
+        // Subscriptions need their own stream of notifications, which
+        // can't be multiplexed into a single batch reply.
+        let id = req.id;
+        match dispatch(rh, settings_, role, req).await {
+            JsonResult::Subscriber(_) | JsonResult::SubscriberWithReply(_, _) => {
+                let msg = "subscriptions are not supported in batch requests".to_string();
+                JsonError::new(ErrorCode::InvalidRequest, Some(msg), id).into()
+            }
+            rep => rep,
+        }
+    }))
+    .await;
+
+    let replies: Vec<JsonValue> = replies
+        .iter()
+        .map(|rep| match rep {
+            JsonResult::Response(v) => v.into(),
+            JsonResult::Error(v) => v.into(),
+            _ => unreachable!("Should never happen"),
+        })
+        .collect();
+
+    let replies = JsonValue::Array(replies).stringify()?;
+    debug!(target: "rpc::server", "{} <-- {}", addr, replies);
+
+    let mut writer_lock = writer.lock().await;
+    if settings.use_http() {
+        http_write_str_to_stream(&mut writer_lock, "HTTP/1.1 200 OK", &[], &replies).await?;
+    } else if settings.use_websocket() {
+        ws_write_frame(&mut *writer_lock, WS_OPCODE_TEXT, replies.as_bytes(), None).await?;
+    } else {
+        write_str_to_stream(&mut writer_lock, &replies).await?;
+    }
+    drop(writer_lock);
+
+    Ok(())
+}
+
 /// Accept function that should run inside a loop for accepting incoming
 /// JSON-RPC requests and passing them to the [`RequestHandler`].
-#[allow(clippy::type_complexity)]
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
 pub async fn accept<'a, T: 'a>(
     reader: Arc<Mutex<BufReader<ReadHalf<Box<dyn PtStream>>>>>,
     writer: Arc<Mutex<WriteHalf<Box<dyn PtStream>>>>,
     addr: Url,
+    peer_uid: Option<u32>,
     rh: Arc<impl RequestHandler<T> + 'static>,
     conn_limit: Option<usize>,
     settings: RpcSettings,
@@ -269,6 +389,17 @@ pub async fn accept<'a, T: 'a>(
         }
     }
 
+    // WebSocket connections start with an HTTP upgrade handshake
+    if settings.use_websocket() {
+        let mut reader_lock = reader.lock().await;
+        let mut writer_lock = writer.lock().await;
+        ws_handshake_request(&mut reader_lock, &mut writer_lock).await?;
+    }
+
+    // Unix socket peers can be authenticated by their credentials, others
+    // will have to authenticate through HTTP headers or a challenge.
+    let mut auth = AuthState::new(peer_uid.and_then(|uid| settings.auth.authenticate_uid(uid)));
+
     // We'll hold our background tasks here
     let tasks = Arc::new(Mutex::new(HashSet::new()));
 
@@ -277,7 +408,12 @@ pub async fn accept<'a, T: 'a>(
 
         let mut reader_lock = reader.lock().await;
         if settings.use_http() {
-            let _ = http_read_from_stream_request(&mut reader_lock, &mut buf).await?;
+            // Every HTTP request carries its own credentials
+            let (_, authorization) =
+                http_read_from_stream_request(&mut reader_lock, &mut buf).await?;
+            auth.role = authorization.and_then(|v| settings.auth.authenticate_http(&v));
+        } else if settings.use_websocket() {
+            let _ = ws_read_from_stream(&mut reader_lock, &writer, &mut buf).await?;
         } else {
             let _ = read_from_stream(&mut reader_lock, &mut buf).await?;
         }
@@ -306,20 +442,60 @@ pub async fn accept<'a, T: 'a>(
             }
         };
 
-        // Cast to JsonRequest
-        let req = match JsonRequest::try_from(&val) {
-            Ok(v) => v,
-            Err(e) => {
-                error!(
-                    target: "rpc::server::accept()",
-                    "[RPC SERVER] Failed casting JSON to a JsonRequest: {}", e,
-                );
-                return Err(e.into())
+        debug!(target: "rpc::server", "{} --> {}", addr, val.stringify()?);
+
+        // JSON-RPC 2.0 batches are sent as an array of request objects
+        let fut = match val {
+            JsonValue::Array(batch) => handle_batch(
+                writer.clone(),
+                addr.clone(),
+                rh.clone(),
+                settings.clone(),
+                auth.role.clone(),
+                batch,
+            )
+            .boxed(),
+
+            val => {
+                // Cast to JsonRequest
+                let req = match JsonRequest::try_from(&val) {
+                    Ok(v) => v,
+                    Err(e) => {
+                        error!(
+                            target: "rpc::server::accept()",
+                            "[RPC SERVER] Failed casting JSON to a JsonRequest: {}", e,
+                        );
+                        return Err(e.into())
+                    }
+                };
+
+                // The challenge-response exchange is handled right here, so
+                // the connection is authenticated before we read further.
+                if settings.auth.is_enabled() &&
+                    !settings.use_http() &&
+                    (req.method == AUTH_CHALLENGE_METHOD || req.method == AUTH_RESPONSE_METHOD)
+                {
+                    let rep = settings.auth.handle_request(&req, &mut auth);
+                    let mut writer_lock = writer.lock().await;
+                    write_result(&mut writer_lock, &settings, &rep).await?;
+                    drop(writer_lock);
+                    continue
+                }
+
+                handle_request(
+                    writer.clone(),
+                    addr.clone(),
+                    rh.clone(),
+                    ex.clone(),
+                    tasks.clone(),
+                    settings.clone(),
+                    auth.role.clone(),
+                    req,
+                )
+                .boxed()
             }
         };
 
-        debug!(target: "rpc::server", "{} --> {}", addr, val.stringify()?);
-
         // Create a new task to handle request in the background
         let task = StoppableTask::new();
 
@@ -329,15 +505,7 @@ pub async fn accept<'a, T: 'a>(
 
         // Detach the task
         task.clone().start(
-            handle_request(
-                writer.clone(),
-                addr.clone(),
-                rh.clone(),
-                ex.clone(),
-                tasks.clone(),
-                settings.clone(),
-                req,
-            ),
+            fut,
             move |_| async move {
                 debug!(
                     target: "rpc::server",
@@ -369,6 +537,7 @@ async fn run_accept_loop<'a, T: 'a>(
                 let rh_ = rh.clone();
                 info!(target: "rpc::server", "[RPC] Server accepted conn from {}", url);
 
+                let peer_uid = stream.peer_uid();
                 let (reader, writer) = smol::io::split(stream);
                 let reader = Arc::new(Mutex::new(BufReader::new(reader)));
                 let writer = Arc::new(Mutex::new(writer));
@@ -381,6 +550,7 @@ async fn run_accept_loop<'a, T: 'a>(
                         reader,
                         writer,
                         url.clone(),
+                        peer_uid,
                         rh.clone(),
                         conn_limit,
                         settings.clone(),
@@ -436,22 +606,31 @@ async fn run_accept_loop<'a, T: 'a>(
 /// given [`RequestHandler`] to handle incoming requests.
 ///
 /// The supported network schemes can be prefixed with `http+` to serve
-/// JSON-RPC over HTTP/1.1.
+/// JSON-RPC over HTTP/1.1, or with `ws+` to serve it over WebSocket.
+/// A plain `ws://` URL is shorthand for `ws+tcp://`.
 pub async fn listen_and_serve<'a, T: 'a>(
     settings: RpcSettings,
     rh: Arc<impl RequestHandler<T> + 'static>,
     conn_limit: Option<usize>,
     ex: Arc<smol::Executor<'a>>,
 ) -> Result<()> {
-    // Figure out if we're using HTTP and rewrite the URL accordingly.
+    // Figure out if we're using HTTP or WebSocket and rewrite the URL
+    // accordingly.
     let mut listen_url = settings.listen.clone();
-    if settings.listen.scheme().starts_with("http+") {
-        let scheme = settings.listen.scheme().strip_prefix("http+").unwrap();
-        let url_str = settings.listen.as_str().replace(settings.listen.scheme(), scheme);
+    let scheme = settings.listen.scheme();
+    let stripped = match scheme {
+        "ws" => Some("tcp"),
+        _ => scheme.strip_prefix("http+").or_else(|| scheme.strip_prefix("ws+")),
+    };
+    if let Some(stripped) = stripped {
+        let url_str = settings.listen.as_str().replacen(scheme, stripped, 1);
         listen_url = url_str.parse()?;
     }
 
-    let listener = Listener::new(listen_url, None).await?.listen().await?;
+    let listener = Listener::new(listen_url, None).await?.listen(ex.clone()).await?;
+
+    // Register the RPC metrics, so they get exported before any request
+    LazyLock::force(&RPC_METRICS);
 
     run_accept_loop(listener, rh, conn_limit, settings, ex.clone()).await
 }
@@ -459,11 +638,34 @@ pub async fn listen_and_serve<'a, T: 'a>(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{rpc::client::RpcClient, system::msleep};
-    use smol::{net::TcpListener, Executor};
+    use crate::{
+        rpc::{
+            auth::{RpcAuth, RpcUser},
+            client::RpcClient,
+            common::{ws_accept_key, ws_read_frame, WS_OPCODE_PING, WS_OPCODE_PONG},
+            openrpc::PING_METHOD,
+        },
+        system::msleep,
+    };
+    use smol::{
+        io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+        Executor,
+    };
+    use std::collections::HashMap;
 
     struct RpcServer {
         rpc_connections: Mutex<HashSet<StoppableTaskPtr>>,
+        subscriber: JsonSubscriber,
+    }
+
+    impl RpcServer {
+        fn new() -> Self {
+            Self {
+                rpc_connections: Mutex::new(HashSet::new()),
+                subscriber: JsonSubscriber::new("notify"),
+            }
+        }
     }
 
     #[async_trait]
@@ -471,13 +673,67 @@ mod tests {
         async fn handle_request(&self, req: JsonRequest) -> JsonResult {
             match req.method.as_str() {
                 "ping" => return self.pong(req.id, req.params).await,
-                _ => panic!(),
+                "subscribe" => return self.subscriber.clone().into(),
+                _ => JsonError::new(ErrorCode::MethodNotFound, None, req.id).into(),
             }
         }
 
         async fn connections_mut(&self) -> MutexGuard<'life0, HashSet<StoppableTaskPtr>> {
             self.rpc_connections.lock().await
         }
+
+        fn methods(&self) -> Vec<RpcMethod> {
+            vec![
+                PING_METHOD,
+                RpcMethod {
+                    name: "subscribe",
+                    summary: "Subscribe to notifications",
+                    params: &[],
+                    result: "object",
+                },
+            ]
+        }
+    }
+
+    /// Find a free local port and return a listen URL with the given scheme.
+    async fn local_url(scheme: &str) -> Result<Url> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let sockaddr = listener.local_addr()?;
+        Ok(Url::parse(&format!("{}://127.0.0.1:{}", scheme, sockaddr.port()))?)
+    }
+
+    /// Start an [`RpcServer`] with the given settings.
+    async fn start_server(
+        settings: RpcSettings,
+        ex: Arc<Executor<'static>>,
+    ) -> Result<(Arc<RpcServer>, RpcSettings)> {
+        let rpc_server = Arc::new(RpcServer::new());
+        let task = StoppableTask::new();
+        task.start(
+            listen_and_serve(settings.clone(), rpc_server.clone(), None, ex.clone()),
+            |_| async {},
+            Error::RpcServerStopped,
+            ex,
+        );
+
+        // Let the server spawn
+        msleep(500).await;
+
+        Ok((rpc_server, settings))
+    }
+
+    /// Send a masked WebSocket text frame, as clients are required to.
+    async fn ws_send(stream: &mut TcpStream, data: &str) -> Result<()> {
+        ws_write_frame(stream, WS_OPCODE_TEXT, data.as_bytes(), Some([1, 2, 3, 4])).await?;
+        Ok(())
+    }
+
+    /// Receive a WebSocket text frame and parse it as JSON.
+    async fn ws_recv(stream: &mut TcpStream) -> Result<JsonValue> {
+        let mut buf = vec![];
+        let (opcode, fin, masked) = ws_read_frame(stream, &mut buf).await?;
+        assert!(opcode == WS_OPCODE_TEXT && fin && !masked);
+        Ok(String::from_utf8(buf)?.parse()?)
     }
 
     #[test]
@@ -495,11 +751,11 @@ mod tests {
             let sockaddr = listener.local_addr()?;
             let settings = RpcSettings {
                 listen: Url::parse(&format!("tcp://127.0.0.1:{}", sockaddr.port()))?,
-                disabled_methods: vec![],
+                ..RpcSettings::default()
             };
             drop(listener);
 
-            let rpc_server = Arc::new(RpcServer { rpc_connections: Mutex::new(HashSet::new()) });
+            let rpc_server = Arc::new(RpcServer::new());
             let rpc_server_ = rpc_server.clone();
 
             let server_task = StoppableTask::new();
@@ -555,4 +811,227 @@ mod tests {
             Ok(())
         }))
     }
+
+    #[test]
+    fn batch_and_discover() -> Result<()> {
+        let executor = Arc::new(Executor::new());
+
+        smol::block_on(executor.run(async {
+            let (_rpc_server, settings) = start_server(
+                RpcSettings { listen: local_url("tcp").await?, ..RpcSettings::default() },
+                executor.clone(),
+            )
+            .await?;
+            let addr = settings.listen.socket_addrs(|| None)?[0];
+            let mut stream = smol::io::BufReader::new(TcpStream::connect(addr).await?);
+
+            let batch = concat!(
+                r#"[{"jsonrpc": "2.0", "method": "ping", "params": [], "id": 1},"#,
+                r#"{"jsonrpc": "2.0", "id": 2},"#,
+                r#"{"jsonrpc": "2.0", "method": "subscribe", "params": [], "id": 3},"#,
+                r#"{"jsonrpc": "2.0", "method": "rpc.discover", "params": [], "id": 4}]"#,
+                "\n",
+            );
+            stream.get_mut().write_all(batch.as_bytes()).await?;
+
+            let mut line = String::new();
+            stream.read_line(&mut line).await?;
+            let replies: JsonValue = line.trim().parse()?;
+            let replies = replies.get::<Vec<JsonValue>>().unwrap();
+            assert_eq!(replies.len(), 4);
+
+            let reply =
+                |id: u16| replies.iter().find(|r| r["id"] == JsonValue::Number(id.into())).unwrap();
+            assert_eq!(reply(1)["result"], JsonValue::String("pong".to_string()));
+            let code = JsonValue::Number(ErrorCode::InvalidRequest.code().into());
+            assert_eq!(reply(2)["error"]["code"], code);
+            assert_eq!(reply(3)["error"]["code"], code);
+
+            let document = &reply(4)["result"];
+            assert_eq!(document["openrpc"], JsonValue::String("1.2.6".to_string()));
+            let methods = document["methods"].get::<Vec<JsonValue>>().unwrap();
+            assert_eq!(methods.len(), 2);
+            assert_eq!(methods[0]["name"], JsonValue::String("ping".to_string()));
+            assert_eq!(
+                methods[0]["result"]["schema"]["type"],
+                JsonValue::String("string".to_string())
+            );
+
+            // An empty batch gets a single error object back
+            stream.get_mut().write_all(b"[]\n").await?;
+            line.clear();
+            stream.read_line(&mut line).await?;
+            let reply: JsonValue = line.trim().parse()?;
+            assert_eq!(reply["error"]["code"], code);
+
+            // So does an oversized one, without any of its requests handled
+            let ping = r#"{"jsonrpc": "2.0", "method": "ping", "params": [], "id": 1}"#;
+            let batch = format!("[{}]\n", vec![ping; MAX_BATCH_LEN + 1].join(","));
+            stream.get_mut().write_all(batch.as_bytes()).await?;
+            line.clear();
+            stream.read_line(&mut line).await?;
+            let reply: JsonValue = line.trim().parse()?;
+            assert_eq!(reply["error"]["code"], code);
+
+            Ok(())
+        }))
+    }
+
+    #[test]
+    fn websocket() -> Result<()> {
+        // Example handshake from RFC 6455, section 1.3
+        assert_eq!(ws_accept_key("dGhlIHNhbXBsZSBub25jZQ=="), "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+
+        let executor = Arc::new(Executor::new());
+
+        smol::block_on(executor.run(async {
+            let (rpc_server, settings) = start_server(
+                RpcSettings { listen: local_url("ws").await?, ..RpcSettings::default() },
+                executor.clone(),
+            )
+            .await?;
+            let addr = settings.listen.socket_addrs(|| None)?[0];
+            let mut stream = TcpStream::connect(addr).await?;
+
+            let handshake = format!(
+                "GET / HTTP/1.1\r\nHost: {}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+                 Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n",
+                addr,
+            );
+            stream.write_all(handshake.as_bytes()).await?;
+
+            let mut resp = vec![];
+            let mut byte = [0_u8];
+            while !resp.ends_with(b"\r\n\r\n") {
+                stream.read_exact(&mut byte).await?;
+                resp.push(byte[0]);
+            }
+            let resp = String::from_utf8(resp)?;
+            assert!(resp.starts_with("HTTP/1.1 101 Switching Protocols\r\n"));
+            assert!(resp.contains("Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n"));
+
+            // Plain request
+            ws_send(&mut stream, r#"{"jsonrpc": "2.0", "method": "ping", "params": [], "id": 1}"#)
+                .await?;
+            let reply = ws_recv(&mut stream).await?;
+            assert_eq!(reply["result"], JsonValue::String("pong".to_string()));
+
+            // Control frames are answered by the server
+            ws_write_frame(&mut stream, WS_OPCODE_PING, b"hi", Some([5, 6, 7, 8])).await?;
+            let mut buf = vec![];
+            let (opcode, _, _) = ws_read_frame(&mut stream, &mut buf).await?;
+            assert_eq!(opcode, WS_OPCODE_PONG);
+            assert_eq!(buf, b"hi");
+
+            // Notifications are pushed over the same connection
+            ws_send(
+                &mut stream,
+                r#"{"jsonrpc": "2.0", "method": "subscribe", "params": [], "id": 2}"#,
+            )
+            .await?;
+            msleep(500).await;
+            rpc_server.subscriber.notify(JsonValue::Array(vec![JsonValue::Boolean(true)])).await;
+            let notification = ws_recv(&mut stream).await?;
+            assert_eq!(notification["method"], JsonValue::String("notify".to_string()));
+            assert_eq!(notification["params"], JsonValue::Array(vec![JsonValue::Boolean(true)]));
+
+            Ok(())
+        }))
+    }
+
+    /// Check that a result failed with an `Unauthorized` JSON-RPC error
+    fn is_unauthorized<T>(res: Result<T>) -> bool {
+        matches!(res, Err(Error::JsonRpcError((c, _))) if c == ErrorCode::Unauthorized.code())
+    }
+
+    #[test]
+    fn authentication() -> Result<()> {
+        let user = |name: &str, secret: Option<&str>, uid: Option<u32>, role: &str| RpcUser {
+            name: name.to_string(),
+            secret: secret.map(|s| s.to_string()),
+            uid,
+            role: role.to_string(),
+        };
+
+        let auth = RpcAuth {
+            users: vec![
+                // Percent-encoded in the endpoint, and decoded by the client
+                user("alice", Some("hunter2 @:/%"), None, "admin"),
+                user("bob", Some("s3cret"), None, "reader"),
+            ],
+            roles: HashMap::from([
+                ("admin".to_string(), vec!["*".to_string()]),
+                ("reader".to_string(), vec!["ping".to_string()]),
+            ]),
+        };
+
+        let ping = || JsonRequest::new("ping", JsonValue::Array(vec![]));
+        let discover = || JsonRequest::new(DISCOVER_METHOD, JsonValue::Array(vec![]));
+
+        let executor = Arc::new(Executor::new());
+
+        smol::block_on(executor.run(async {
+            // Challenge-response over TCP, and Authorization headers over HTTP
+            for scheme in ["tcp", "http+tcp"] {
+                let settings = RpcSettings {
+                    listen: local_url(scheme).await?,
+                    auth: auth.clone(),
+                    ..RpcSettings::default()
+                };
+                let (_rpc_server, settings) = start_server(settings, executor.clone()).await?;
+
+                // Unauthenticated
+                let client = RpcClient::new(settings.listen.clone(), executor.clone()).await?;
+                assert!(is_unauthorized(client.request(ping()).await));
+                client.stop().await;
+
+                // Wrong secret
+                let mut endpoint = settings.listen.clone();
+                endpoint.set_username("bob").unwrap();
+                endpoint.set_password(Some("hunter2")).unwrap();
+                let res = match RpcClient::new(endpoint.clone(), executor.clone()).await {
+                    Ok(client) => client.request(ping()).await,
+                    Err(e) => Err(e),
+                };
+                assert!(is_unauthorized(res));
+
+                // Restricted role
+                endpoint.set_password(Some("s3cret")).unwrap();
+                let client = RpcClient::new(endpoint.clone(), executor.clone()).await?;
+                let rep = client.request(ping()).await?;
+                assert_eq!(rep, JsonValue::String("pong".to_string()));
+                assert!(is_unauthorized(client.request(discover()).await));
+                client.stop().await;
+
+                // Unrestricted role, with rpc.discover listing everything
+                endpoint.set_username("alice").unwrap();
+                endpoint.set_password(Some("hunter2 @:/%")).unwrap();
+                let client = RpcClient::new(endpoint, executor.clone()).await?;
+                let rep = client.request(discover()).await?;
+                assert_eq!(rep["methods"].get::<Vec<JsonValue>>().unwrap().len(), 2);
+                client.stop().await;
+            }
+
+            // Unix socket peer credentials
+            #[cfg(feature = "p2p-unix")]
+            {
+                let uid = unsafe { libc::getuid() };
+                let path = std::env::temp_dir().join(format!("darkfi-rpc-{}.sock", uid));
+                let listen = Url::parse(&format!("unix://{}", path.display()))?;
+
+                let mut auth = auth.clone();
+                auth.users.push(user("local", None, Some(uid), "reader"));
+                let settings = RpcSettings { listen, auth, ..RpcSettings::default() };
+                let (_rpc_server, settings) = start_server(settings, executor.clone()).await?;
+
+                let client = RpcClient::new(settings.listen.clone(), executor.clone()).await?;
+                let rep = client.request(ping()).await?;
+                assert_eq!(rep, JsonValue::String("pong".to_string()));
+                assert!(is_unauthorized(client.request(discover()).await));
+                client.stop().await;
+            }
+
+            Ok(())
+        }))
+    }
 }
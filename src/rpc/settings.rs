@@ -16,13 +16,18 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
+use std::collections::HashMap;
+
 use structopt::StructOpt;
 use url::Url;
 
+use super::auth::{RpcAuth, RpcUser};
+
 #[derive(Clone)]
 pub struct RpcSettings {
     pub listen: Url,
     pub disabled_methods: Vec<String>,
+    pub auth: RpcAuth,
 }
 
 impl RpcSettings {
@@ -32,11 +37,18 @@ impl RpcSettings {
     pub fn use_http(&self) -> bool {
         self.listen.scheme().starts_with("http+")
     }
+    pub fn use_websocket(&self) -> bool {
+        self.listen.scheme() == "ws" || self.listen.scheme().starts_with("ws+")
+    }
 }
 
 impl Default for RpcSettings {
     fn default() -> Self {
-        Self { listen: Url::parse("tcp://127.0.0.1:22222").unwrap(), disabled_methods: vec![] }
+        Self {
+            listen: Url::parse("tcp://127.0.0.1:22222").unwrap(),
+            disabled_methods: vec![],
+            auth: RpcAuth::default(),
+        }
     }
 }
 
@@ -52,6 +64,16 @@ pub struct RpcSettingsOpt {
     /// Disabled JSON-RPC methods
     #[structopt(long, use_delimiter = true)]
     pub rpc_disabled_methods: Option<Vec<String>>,
+
+    /// Users allowed to authenticate to the RPC server
+    #[serde(default)]
+    #[structopt(skip)]
+    pub rpc_users: Vec<RpcUser>,
+
+    /// Roles and the RPC methods they are allowed to call
+    #[serde(default)]
+    #[structopt(skip)]
+    pub rpc_roles: HashMap<String, Vec<String>>,
 }
 
 impl From<RpcSettingsOpt> for RpcSettings {
@@ -59,6 +81,7 @@ impl From<RpcSettingsOpt> for RpcSettings {
         Self {
             listen: opt.rpc_listen,
             disabled_methods: opt.rpc_disabled_methods.unwrap_or_default(),
+            auth: RpcAuth { users: opt.rpc_users, roles: opt.rpc_roles },
         }
     }
 }
@@ -18,6 +18,14 @@
 
 use std::collections::HashMap;
 
+use darkfi_serial::DecodeLimits;
+
+use super::common::MAX_BUF_SIZE;
+
+/// Limits applied when decoding serialized objects received in RPC
+/// requests. No object can be larger than the request carrying it.
+pub const RPC_DECODE_LIMITS: DecodeLimits = DecodeLimits::new(1024 * 1024, MAX_BUF_SIZE as u64, 32);
+
 pub use tinyjson::JsonValue::{
     self, Array as JsonArray, Number as JsonNum, Object as JsonObj, String as JsonStr,
 };
@@ -0,0 +1,188 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2025 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::{Arc, Mutex},
+};
+
+use sled_overlay::SledDbOverlay;
+
+use crate::Result;
+
+/// Atomic pointer to an [`AccessSet`], shared between all the runtimes
+/// executing the calls of a single transaction.
+pub type AccessSetPtr = Arc<Mutex<AccessSet>>;
+
+/// Record of the contract state keys a transaction read and wrote
+/// while being executed by the WASM runtime.
+///
+/// Accesses are recorded by the `db_*`, `merkle_*` and `sparse_merkle_*`
+/// host functions, so every state access a contract can perform is covered.
+/// The recorded writes hold the final value of each key, so they can be
+/// replayed over a different overlay, as long as the reads don't conflict
+/// with the writes already applied there.
+#[derive(Clone, Debug, Default)]
+pub struct AccessSet {
+    /// Keys read, grouped by their sled tree
+    pub reads: BTreeMap<Vec<u8>, BTreeSet<Vec<u8>>>,
+    /// Final values written to keys, grouped by their sled tree.
+    /// `None` marks a removed key.
+    pub writes: BTreeMap<Vec<u8>, BTreeMap<Vec<u8>, Option<Vec<u8>>>>,
+    /// Trees modified as a whole, like newly initialized contract trees
+    /// or zkas circuits trees. These writes can't be replayed.
+    pub tree_writes: BTreeSet<Vec<u8>>,
+}
+
+impl AccessSet {
+    /// Create a new empty [`AccessSet`] pointer.
+    pub fn new_ptr() -> AccessSetPtr {
+        Arc::new(Mutex::new(Self::default()))
+    }
+
+    /// Record a read of `key` in `tree`.
+    pub fn record_read(&mut self, tree: &[u8], key: &[u8]) {
+        self.reads.entry(tree.to_vec()).or_default().insert(key.to_vec());
+    }
+
+    /// Record a write of `value` to `key` in `tree`.
+    /// A `None` value records the removal of the key.
+    pub fn record_write(&mut self, tree: &[u8], key: &[u8], value: Option<&[u8]>) {
+        self.writes
+            .entry(tree.to_vec())
+            .or_default()
+            .insert(key.to_vec(), value.map(|v| v.to_vec()));
+    }
+
+    /// Record a write to `tree` as a whole.
+    pub fn record_tree_write(&mut self, tree: &[u8]) {
+        self.tree_writes.insert(tree.to_vec());
+    }
+
+    /// Check if the recorded writes can be replayed over another overlay.
+    pub fn is_replayable(&self) -> bool {
+        self.tree_writes.is_empty()
+    }
+
+    /// Check if any key read in this set was written in `other`.
+    /// Executing a transaction after the writes of `other` were applied
+    /// will only produce the same result as the recorded execution,
+    /// if this returns `false`.
+    pub fn conflicts_with(&self, other: &AccessSet) -> bool {
+        for (tree, keys) in &self.reads {
+            if other.tree_writes.contains(tree) {
+                return true
+            }
+
+            let Some(written) = other.writes.get(tree) else { continue };
+            if keys.iter().any(|key| written.contains_key(key)) {
+                return true
+            }
+        }
+
+        false
+    }
+
+    /// Merge `other` into this set. Writes of `other` override
+    /// the ones already recorded, as if they happened after them.
+    pub fn merge(&mut self, other: &AccessSet) {
+        for (tree, keys) in &other.reads {
+            self.reads.entry(tree.clone()).or_default().extend(keys.iter().cloned());
+        }
+
+        for (tree, keys) in &other.writes {
+            let written = self.writes.entry(tree.clone()).or_default();
+            for (key, value) in keys {
+                written.insert(key.clone(), value.clone());
+            }
+        }
+
+        self.tree_writes.extend(other.tree_writes.iter().cloned());
+    }
+
+    /// Apply the recorded writes to the provided overlay.
+    pub fn replay(&self, overlay: &mut SledDbOverlay) -> Result<()> {
+        for (tree, keys) in &self.writes {
+            overlay.open_tree(tree, false)?;
+            for (key, value) in keys {
+                match value {
+                    Some(value) => overlay.insert(tree, key, value)?,
+                    None => overlay.remove(tree, key)?,
+                };
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AccessSet;
+
+    #[test]
+    fn access_set_conflicts() {
+        let mut first = AccessSet::default();
+        first.record_read(b"nullifiers", b"n0");
+        first.record_write(b"nullifiers", b"n0", Some(b"tx0"));
+        first.record_write(b"coins", b"c0", Some(b"coin"));
+
+        // Disjoint keys in the same trees don't conflict
+        let mut second = AccessSet::default();
+        second.record_read(b"nullifiers", b"n1");
+        second.record_write(b"nullifiers", b"n1", Some(b"tx1"));
+        assert!(!second.conflicts_with(&first));
+        assert!(!first.conflicts_with(&second));
+
+        // Reading a key written before conflicts
+        let mut third = AccessSet::default();
+        third.record_read(b"nullifiers", b"n0");
+        assert!(third.conflicts_with(&first));
+
+        // Blind writes don't conflict
+        let mut fourth = AccessSet::default();
+        fourth.record_write(b"coins", b"c0", Some(b"other"));
+        assert!(!fourth.conflicts_with(&first));
+
+        // Reading from a tree written as a whole conflicts
+        let mut fifth = AccessSet::default();
+        fifth.record_tree_write(b"zkas");
+        assert!(!fifth.is_replayable());
+        let mut sixth = AccessSet::default();
+        sixth.record_read(b"zkas", b"circuit");
+        assert!(sixth.conflicts_with(&fifth));
+    }
+
+    #[test]
+    fn access_set_merge() {
+        let mut committed = AccessSet::default();
+        let mut first = AccessSet::default();
+        first.record_write(b"coins", b"c0", Some(b"first"));
+        first.record_write(b"coins", b"c1", Some(b"first"));
+        let mut second = AccessSet::default();
+        second.record_write(b"coins", b"c0", None);
+
+        committed.merge(&first);
+        committed.merge(&second);
+
+        let coins = &committed.writes[b"coins".as_slice()];
+        assert_eq!(coins[b"c0".as_slice()], None);
+        assert_eq!(coins[b"c1".as_slice()], Some(b"first".to_vec()));
+        assert!(committed.is_replayable());
+    }
+}
@@ -162,6 +162,9 @@ pub(crate) fn db_init(mut ctx: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, ptr_len: u
         }
     };
 
+    // Initializing a tree modifies the contract state trees registry
+    env.record_tree_write(&tree_handle);
+
     // Create the DbHandle
     let db_handle = DbHandle::new(read_cid, tree_handle);
     let mut db_handles = env.db_handles.borrow_mut();
@@ -455,6 +458,7 @@ pub(crate) fn db_set(mut ctx: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, ptr_len: u3
         );
         return darkfi_sdk::error::DB_SET_FAILED
     }
+    env.record_write(&db_handle.tree, &key, Some(&value));
 
     wasm::entrypoint::SUCCESS
 }
@@ -567,6 +571,7 @@ pub(crate) fn db_del(mut ctx: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, ptr_len: u3
         );
         return darkfi_sdk::error::DB_DEL_FAILED
     }
+    env.record_write(&db_handle.tree, &key, None);
 
     wasm::entrypoint::SUCCESS
 }
@@ -668,6 +673,7 @@ pub(crate) fn db_get(mut ctx: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, ptr_len: u3
     let db_handle = &db_handles[db_handle_index];
 
     // Retrieve data using the `key`
+    env.record_read(&db_handle.tree, &key);
     let ret =
         match env.blockchain.lock().unwrap().overlay.lock().unwrap().get(&db_handle.tree, &key) {
             Ok(v) => v,
@@ -805,6 +811,7 @@ pub(crate) fn db_contains_key(mut ctx: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, pt
     let db_handle = &db_handles[db_handle_index];
 
     // Lookup key parameter in the database
+    env.record_read(&db_handle.tree, &key);
     match env.blockchain.lock().unwrap().overlay.lock().unwrap().contains_key(&db_handle.tree, &key)
     {
         Ok(v) => i64::from(v), // <- 0=false, 1=true. Convert bool to i64.
@@ -902,6 +909,7 @@ pub(crate) fn zkas_db_set(mut ctx: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, ptr_le
     // Check if there is existing bincode and compare it. Return DB_SUCCESS if
     // they're the same. The assumption should be that VerifyingKey was generated
     // already so we can skip things after this guard.
+    env.record_tree_write(&db_handle.tree);
     match env
         .blockchain
         .lock()
@@ -178,6 +178,7 @@ pub(crate) fn merkle_add(mut ctx: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, len: u3
     let lock = env.blockchain.lock().unwrap();
     let mut overlay = lock.overlay.lock().unwrap();
     // Read the current tree
+    env.record_read(&db_info.tree, &tree_key);
     let ret = match overlay.get(&db_info.tree, &tree_key) {
         Ok(v) => v,
         Err(e) => {
@@ -257,6 +258,7 @@ pub(crate) fn merkle_add(mut ctx: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, len: u3
         );
         return darkfi_sdk::error::INTERNAL_ERROR
     }
+    env.record_write(&db_info.tree, &tree_key, Some(&tree_data));
 
     // Here we add the Merkle root to our set of roots
     // Since each update to the tree is atomic, we only need to add the last root.
@@ -287,6 +289,7 @@ pub(crate) fn merkle_add(mut ctx: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, len: u3
         );
         return darkfi_sdk::error::INTERNAL_ERROR
     }
+    env.record_write(&db_roots.tree, &latest_root_data, Some(&value_data));
 
     // Write a pointer to the latest known root
     debug!(
@@ -301,6 +304,7 @@ pub(crate) fn merkle_add(mut ctx: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, len: u3
         );
         return darkfi_sdk::error::INTERNAL_ERROR
     }
+    env.record_write(&db_info.tree, &root_key, Some(&latest_root_data));
 
     // Subtract used gas.
     // Here we count:
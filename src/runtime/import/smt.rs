@@ -33,12 +33,16 @@ use num_bigint::BigUint;
 use wasmer::{FunctionEnvMut, WasmPtr};
 
 use super::acl::acl_allow;
-use crate::runtime::vm_runtime::{ContractSection, Env};
+use crate::runtime::{
+    access::AccessSetPtr,
+    vm_runtime::{ContractSection, Env},
+};
 
 /// An SMT adapter for sled overlay storage. Compatible with the WasmDb SMT adapter
 pub struct SledStorage<'a> {
     overlay: &'a mut sled_overlay::SledDbOverlay,
     tree_key: &'a [u8],
+    access_set: Option<AccessSetPtr>,
 }
 
 impl StorageAdapter for SledStorage<'_> {
@@ -53,11 +57,21 @@ impl StorageAdapter for SledStorage<'_> {
             );
             return Err(ContractError::SmtPutFailed)
         }
+        if let Some(access_set) = &self.access_set {
+            access_set.lock().unwrap().record_write(
+                self.tree_key,
+                &key.to_bytes_le(),
+                Some(&value.to_repr()),
+            );
+        }
 
         Ok(())
     }
 
     fn get(&self, key: &BigUint) -> Option<pallas::Base> {
+        if let Some(access_set) = &self.access_set {
+            access_set.lock().unwrap().record_read(self.tree_key, &key.to_bytes_le());
+        }
         let value = match self.overlay.get(self.tree_key, &key.to_bytes_le()) {
             Ok(v) => v,
             Err(e) => {
@@ -86,6 +100,9 @@ impl StorageAdapter for SledStorage<'_> {
             );
             return Err(ContractError::SmtDelFailed)
         }
+        if let Some(access_set) = &self.access_set {
+            access_set.lock().unwrap().record_write(self.tree_key, &key.to_bytes_le(), None);
+        }
 
         Ok(())
     }
@@ -241,7 +258,11 @@ pub(crate) fn sparse_merkle_insert_batch(
     let hasher = PoseidonFp::new();
     let lock = env.blockchain.lock().unwrap();
     let mut overlay = lock.overlay.lock().unwrap();
-    let smt_store = SledStorage { overlay: &mut overlay, tree_key: &db_smt.tree };
+    let smt_store = SledStorage {
+        overlay: &mut overlay,
+        tree_key: &db_smt.tree,
+        access_set: env.access_set.clone(),
+    };
     let mut smt = SparseMerkleTree::<
         SMT_FP_DEPTH,
         { SMT_FP_DEPTH + 1 },
@@ -302,6 +323,7 @@ pub(crate) fn sparse_merkle_insert_batch(
     }
 
     // Retrieve snapshot root data set
+    env.record_read(&db_roots.tree, &latest_root_data);
     let root_value_data_set = match overlay.get(&db_roots.tree, &latest_root_data) {
         Ok(data) => data,
         Err(e) => {
@@ -350,6 +372,7 @@ pub(crate) fn sparse_merkle_insert_batch(
         );
         return darkfi_sdk::error::INTERNAL_ERROR
     }
+    env.record_write(&db_roots.tree, &latest_root_data, Some(&serialize(&root_value_data_set)));
 
     // Update the pointer to the latest known root
     debug!(
@@ -363,6 +386,7 @@ pub(crate) fn sparse_merkle_insert_batch(
         );
         return darkfi_sdk::error::INTERNAL_ERROR
     }
+    env.record_write(&db_info.tree, &root_key, Some(&latest_root_data));
 
     // Subtract used gas.
     // Here we count:
@@ -19,6 +19,9 @@
 /// Main WASM VM runtime implementation
 pub mod vm_runtime;
 
+/// Contract state access tracking
+pub mod access;
+
 /// VM memory access (read/write)
 pub(crate) mod memory;
 
@@ -34,7 +34,7 @@ use wasmer_middlewares::{
     Metering,
 };
 
-use super::{import, import::db::DbHandle, memory::MemoryManipulation};
+use super::{access::AccessSetPtr, import, import::db::DbHandle, memory::MemoryManipulation};
 use crate::{
     blockchain::{contract_store::SMART_CONTRACT_ZKAS_DB_NAME, BlockchainOverlayPtr},
     Error, Result,
@@ -105,6 +105,8 @@ pub struct Env {
     pub call_idx: u8,
     /// Parent `Instance`
     pub instance: Option<Arc<Instance>>,
+    /// Optional record of the contract state keys accessed
+    pub access_set: Option<AccessSetPtr>,
 }
 
 impl Env {
@@ -124,6 +126,28 @@ impl Env {
         self.memory.as_ref().unwrap()
     }
 
+    /// Record a read of `key` in `tree`, if access tracking is enabled
+    pub fn record_read(&self, tree: &[u8], key: &[u8]) {
+        if let Some(access_set) = &self.access_set {
+            access_set.lock().unwrap().record_read(tree, key);
+        }
+    }
+
+    /// Record a write to `key` in `tree`, if access tracking is enabled.
+    /// A `None` value records the removal of the key.
+    pub fn record_write(&self, tree: &[u8], key: &[u8], value: Option<&[u8]>) {
+        if let Some(access_set) = &self.access_set {
+            access_set.lock().unwrap().record_write(tree, key, value);
+        }
+    }
+
+    /// Record a write to `tree` as a whole, if access tracking is enabled
+    pub fn record_tree_write(&self, tree: &[u8]) {
+        if let Some(access_set) = &self.access_set {
+            access_set.lock().unwrap().record_tree_write(tree);
+        }
+    }
+
     /// Subtract given gas cost from remaining gas in the current runtime
     pub fn subtract_gas(&mut self, ctx: &mut impl AsStoreMut, gas: u64) {
         match get_remaining_points(ctx, self.instance.as_ref().unwrap()) {
@@ -206,6 +230,7 @@ impl Runtime {
                 tx_hash,
                 call_idx,
                 instance: None,
+                access_set: None,
             },
         );
 
@@ -546,6 +571,11 @@ impl Runtime {
         }
     }
 
+    /// Record all contract state accesses of this runtime into the given [`AccessSetPtr`].
+    pub fn track_access(&mut self, access_set: AccessSetPtr) {
+        self.ctx.as_mut(&mut self.store).access_set = Some(access_set);
+    }
+
     /// Calculate the remaining gas using wasm's concept
     /// of metering points.
     pub fn gas_used(&mut self) -> u64 {
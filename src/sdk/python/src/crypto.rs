@@ -16,13 +16,15 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
-use std::ops::Deref;
+use std::{ops::Deref, str::FromStr};
 
 use darkfi_sdk::{crypto, pasta::pallas};
 use pyo3::{
+    exceptions::PyValueError,
     prelude::{PyModule, PyModuleMethods},
-    pyfunction, wrap_pyfunction, Bound, PyResult, Python,
+    pyclass, pyfunction, pymethods, wrap_pyfunction, Bound, PyResult, Python,
 };
+use rand::rngs::OsRng;
 
 use super::pasta::{Ep, Fp, Fq};
 
@@ -66,9 +68,82 @@ pub fn pedersen_commitment_base(value: &Bound<Fp>, blind: &Bound<Fq>) -> Ep {
     ))
 }
 
+#[pyclass]
+#[derive(Copy, Clone)]
+/// Secret key used for signing transactions
+pub struct SecretKey(pub(crate) crypto::SecretKey);
+
+#[pymethods]
+impl SecretKey {
+    #[new]
+    fn new(v: &str) -> PyResult<Self> {
+        match crypto::SecretKey::from_str(v) {
+            Ok(v) => Ok(Self(v)),
+            Err(e) => Err(PyValueError::new_err(e.to_string())),
+        }
+    }
+
+    #[staticmethod]
+    fn random() -> Self {
+        Self(crypto::SecretKey::random(&mut OsRng))
+    }
+
+    #[staticmethod]
+    fn from_fp(v: &Bound<Fp>) -> Self {
+        Self(crypto::SecretKey::from(v.borrow().deref().0))
+    }
+
+    fn inner(&self) -> Fp {
+        Fp(self.0.inner())
+    }
+
+    fn public_key(&self) -> PublicKey {
+        PublicKey(crypto::PublicKey::from_secret(self.0))
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+}
+
+#[pyclass]
+#[derive(Copy, Clone)]
+/// Public key used for verifying transaction signatures
+pub struct PublicKey(pub(crate) crypto::PublicKey);
+
+#[pymethods]
+impl PublicKey {
+    #[new]
+    fn new(v: &str) -> PyResult<Self> {
+        match crypto::PublicKey::from_str(v) {
+            Ok(v) => Ok(Self(v)),
+            Err(e) => Err(PyValueError::new_err(e.to_string())),
+        }
+    }
+
+    #[staticmethod]
+    fn from_secret(secret: &Bound<SecretKey>) -> Self {
+        Self(crypto::PublicKey::from_secret(secret.borrow().0))
+    }
+
+    fn inner(&self) -> Ep {
+        Ep(self.0.inner())
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+
+    fn __eq__(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
 /// Wrapper function for creating this Python module.
 pub(crate) fn create_module(py: Python<'_>) -> PyResult<Bound<PyModule>> {
     let submod = PyModule::new_bound(py, "crypto")?;
+    submod.add_class::<SecretKey>()?;
+    submod.add_class::<PublicKey>()?;
     submod.add_function(wrap_pyfunction!(poseidon_hash, &submod)?)?;
     submod.add_function(wrap_pyfunction!(pedersen_commitment_u64, &submod)?)?;
     submod.add_function(wrap_pyfunction!(pedersen_commitment_base, &submod)?)?;
@@ -28,6 +28,12 @@ mod crypto;
 /// zkas definitions
 mod zkas;
 
+/// Transactions and contract calls
+mod tx;
+
+/// `Money` contract call parameters
+mod money;
+
 #[pyo3::prelude::pymodule]
 // We allow unexpected configs here since `pyo3::py_run!` macro
 // uses `#[cfg(feature = "gil-refs")]`.
@@ -52,5 +58,13 @@ fn darkfi_sdk(
     pyo3::py_run!(py, submodule, "import sys; sys.modules['darkfi_sdk.zkas'] = submodule");
     pyo3::types::PyModuleMethods::add_submodule(m, &submodule)?;
 
+    let submodule = tx::create_module(py)?;
+    pyo3::py_run!(py, submodule, "import sys; sys.modules['darkfi_sdk.tx'] = submodule");
+    pyo3::types::PyModuleMethods::add_submodule(m, &submodule)?;
+
+    let submodule = money::create_module(py)?;
+    pyo3::py_run!(py, submodule, "import sys; sys.modules['darkfi_sdk.money'] = submodule");
+    pyo3::types::PyModuleMethods::add_submodule(m, &submodule)?;
+
     Ok(())
 }
@@ -0,0 +1,246 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2025 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::ops::Deref;
+
+use darkfi_money_contract::{
+    model::{self, Coin, MoneyTransferParamsV1, Nullifier},
+    MoneyFunction,
+};
+use darkfi_sdk::crypto::{note::AeadEncryptedNote, MerkleNode};
+use darkfi_serial::{deserialize, serialize};
+use pyo3::{
+    exceptions::PyValueError,
+    prelude::{PyModule, PyModuleMethods},
+    pyclass, pymethods,
+    types::PyBytes,
+    Bound, PyResult, Python,
+};
+
+use super::{
+    crypto::PublicKey,
+    pasta::{Ep, Fp},
+};
+
+#[pyclass]
+#[derive(Clone)]
+/// An anonymous input of a `Money::Transfer` call
+pub struct Input(model::Input);
+
+#[pymethods]
+impl Input {
+    #[new]
+    fn new(
+        value_commit: &Bound<Ep>,
+        token_commit: &Bound<Fp>,
+        nullifier: &Bound<Fp>,
+        merkle_root: &Bound<Fp>,
+        user_data_enc: &Bound<Fp>,
+        signature_public: &Bound<PublicKey>,
+    ) -> Self {
+        Self(model::Input {
+            value_commit: value_commit.borrow().deref().0,
+            token_commit: token_commit.borrow().deref().0,
+            nullifier: Nullifier::from(nullifier.borrow().deref().0),
+            merkle_root: MerkleNode::from(merkle_root.borrow().deref().0),
+            user_data_enc: user_data_enc.borrow().deref().0,
+            signature_public: signature_public.borrow().deref().0,
+        })
+    }
+
+    fn value_commit(&self) -> Ep {
+        Ep(self.0.value_commit)
+    }
+
+    fn token_commit(&self) -> Fp {
+        Fp(self.0.token_commit)
+    }
+
+    fn nullifier(&self) -> Fp {
+        Fp(self.0.nullifier.inner())
+    }
+
+    fn merkle_root(&self) -> Fp {
+        Fp(self.0.merkle_root.inner())
+    }
+
+    fn user_data_enc(&self) -> Fp {
+        Fp(self.0.user_data_enc)
+    }
+
+    fn signature_public(&self) -> PublicKey {
+        PublicKey(self.0.signature_public)
+    }
+
+    fn __eq__(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+#[pyclass]
+#[derive(Clone)]
+/// An anonymous output of a `Money::Transfer` call
+pub struct Output(model::Output);
+
+#[pymethods]
+impl Output {
+    #[new]
+    fn new(
+        value_commit: &Bound<Ep>,
+        token_commit: &Bound<Fp>,
+        coin: &Bound<Fp>,
+        note_ciphertext: Vec<u8>,
+        note_ephem_public: &Bound<PublicKey>,
+    ) -> Self {
+        Self(model::Output {
+            value_commit: value_commit.borrow().deref().0,
+            token_commit: token_commit.borrow().deref().0,
+            coin: Coin::from(coin.borrow().deref().0),
+            note: AeadEncryptedNote {
+                ciphertext: note_ciphertext,
+                ephem_public: note_ephem_public.borrow().deref().0,
+            },
+        })
+    }
+
+    fn value_commit(&self) -> Ep {
+        Ep(self.0.value_commit)
+    }
+
+    fn token_commit(&self) -> Fp {
+        Fp(self.0.token_commit)
+    }
+
+    fn coin(&self) -> Fp {
+        Fp(self.0.coin.inner())
+    }
+
+    fn note_ciphertext<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new_bound(py, &self.0.note.ciphertext)
+    }
+
+    fn note_ephem_public(&self) -> PublicKey {
+        PublicKey(self.0.note.ephem_public)
+    }
+
+    fn __eq__(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+#[pyclass]
+#[derive(Clone)]
+/// Parameters of a `Money::Transfer` call
+pub struct TransferParams(MoneyTransferParamsV1);
+
+#[pymethods]
+impl TransferParams {
+    #[new]
+    fn new(inputs: Vec<Bound<Input>>, outputs: Vec<Bound<Output>>) -> Self {
+        let inputs = inputs.iter().map(|i| i.borrow().deref().0.clone()).collect();
+        let outputs = outputs.iter().map(|o| o.borrow().deref().0.clone()).collect();
+        Self(MoneyTransferParamsV1 { inputs, outputs })
+    }
+
+    #[staticmethod]
+    fn decode(bytes: Vec<u8>) -> PyResult<Self> {
+        match deserialize(&bytes) {
+            Ok(v) => Ok(Self(v)),
+            Err(e) => Err(PyValueError::new_err(e.to_string())),
+        }
+    }
+
+    fn encode<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new_bound(py, &serialize(&self.0))
+    }
+
+    /// Payload of the `ContractCall`, prefixed with the function byte
+    fn call_data<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        let mut data = vec![MoneyFunction::TransferV1 as u8];
+        data.extend(serialize(&self.0));
+        PyBytes::new_bound(py, &data)
+    }
+
+    fn inputs(&self) -> Vec<Input> {
+        self.0.inputs.iter().map(|i| Input(i.clone())).collect()
+    }
+
+    fn outputs(&self) -> Vec<Output> {
+        self.0.outputs.iter().map(|o| Output(o.clone())).collect()
+    }
+
+    fn __eq__(&self, other: &Self) -> bool {
+        self.0.inputs == other.0.inputs && self.0.outputs == other.0.outputs
+    }
+}
+
+/// Wrapper function for creating this Python module.
+pub(crate) fn create_module(py: Python<'_>) -> PyResult<Bound<PyModule>> {
+    let submod = PyModule::new_bound(py, "money")?;
+    submod.add_class::<Input>()?;
+    submod.add_class::<Output>()?;
+    submod.add_class::<TransferParams>()?;
+    Ok(submod)
+}
+
+#[cfg(test)]
+mod tests {
+    use darkfi_sdk::{
+        crypto::{pasta_prelude::*, Keypair},
+        pasta::pallas,
+    };
+    use pyo3::types::PyBytesMethods;
+    use rand::rngs::OsRng;
+
+    use super::*;
+
+    #[test]
+    fn transfer_params_encoding() -> PyResult<()> {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let fp = |v: u64| Bound::new(py, Fp(pallas::Base::from(v)));
+            let commit = Bound::new(py, Ep(pallas::Point::random(&mut OsRng)))?;
+            let public = Bound::new(py, PublicKey(Keypair::random(&mut OsRng).public))?;
+
+            let input = Input::new(&commit, &fp(1)?, &fp(2)?, &fp(3)?, &fp(4)?, &public);
+            let output = Output::new(&commit, &fp(5)?, &fp(6)?, vec![7; 32], &public);
+            let params = TransferParams::new(
+                vec![Bound::new(py, input.clone())?],
+                vec![Bound::new(py, output.clone())?],
+            );
+
+            let encoded = params.encode(py);
+            let decoded = TransferParams::decode(encoded.as_bytes().to_vec())?;
+            assert!(decoded.__eq__(&params));
+            assert!(decoded.inputs()[0].__eq__(&input));
+            assert!(decoded.outputs()[0].__eq__(&output));
+            assert_eq!(decoded.inputs()[0].nullifier(), Fp(pallas::Base::from(2)));
+            assert_eq!(decoded.outputs()[0].coin(), Fp(pallas::Base::from(6)));
+            assert_eq!(decoded.outputs()[0].note_ciphertext(py).as_bytes(), &[7; 32]);
+
+            // The call data is the function byte followed by the params
+            let data = params.call_data(py);
+            assert_eq!(data.as_bytes()[0], MoneyFunction::TransferV1 as u8);
+            assert_eq!(&data.as_bytes()[1..], encoded.as_bytes());
+
+            let truncated = &encoded.as_bytes()[..encoded.as_bytes().len() - 1];
+            assert!(TransferParams::decode(truncated.to_vec()).is_err());
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,505 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2025 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{collections::HashMap, ops::Deref, str::FromStr};
+
+use darkfi::{
+    tx::{self, ContractCallLeaf},
+    util::encoding::base64,
+};
+use darkfi_sdk::{
+    crypto::{
+        ContractId, DAO_CONTRACT_ID, DEPLOYOOOR_CONTRACT_ID, EXCHANGE_CONTRACT_ID,
+        MONEY_CONTRACT_ID,
+    },
+    dark_tree,
+    pasta::pallas,
+};
+use darkfi_serial::{deserialize, serialize};
+use pyo3::{
+    exceptions::PyValueError,
+    prelude::{PyModule, PyModuleMethods},
+    pyclass, pymethods,
+    types::PyBytes,
+    Bound, PyResult, Python,
+};
+
+use super::{
+    crypto::{PublicKey, SecretKey},
+    pasta::Fp,
+    zkas::{Proof, VerifyingKey},
+};
+
+#[pyclass]
+#[derive(Clone)]
+/// A call executing a contract with the given payload
+pub struct ContractCall(darkfi_sdk::tx::ContractCall);
+
+#[pymethods]
+impl ContractCall {
+    #[new]
+    fn new(contract_id: &str, data: Vec<u8>) -> PyResult<Self> {
+        let contract_id = match ContractId::from_str(contract_id) {
+            Ok(v) => v,
+            Err(e) => return Err(PyValueError::new_err(e.to_string())),
+        };
+        Ok(Self(darkfi_sdk::tx::ContractCall { contract_id, data }))
+    }
+
+    fn contract_id(&self) -> String {
+        self.0.contract_id.to_string()
+    }
+
+    fn data<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new_bound(py, &self.0.data)
+    }
+
+    fn __eq__(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+/// Build the leaf of a call tree from the Python objects
+fn call_leaf(call: &Bound<ContractCall>, proofs: Vec<Bound<Proof>>) -> ContractCallLeaf {
+    let call = call.borrow().deref().0.clone();
+    let proofs = proofs.iter().map(|p| p.borrow().deref().0.clone()).collect();
+    ContractCallLeaf { call, proofs }
+}
+
+/// Unwrap the Python call trees
+fn call_trees(children: Vec<Bound<DarkTree>>) -> Vec<dark_tree::DarkTree<ContractCallLeaf>> {
+    children.iter().map(|c| c.borrow().deref().0.clone()).collect()
+}
+
+#[pyclass]
+#[derive(Clone)]
+/// A tree of contract calls, where each call can depend on its children
+pub struct DarkTree(dark_tree::DarkTree<ContractCallLeaf>);
+
+#[pymethods]
+impl DarkTree {
+    #[new]
+    #[pyo3(signature = (call, proofs = vec![], children = vec![]))]
+    fn new(
+        call: &Bound<ContractCall>,
+        proofs: Vec<Bound<Proof>>,
+        children: Vec<Bound<DarkTree>>,
+    ) -> Self {
+        let leaf = call_leaf(call, proofs);
+        Self(dark_tree::DarkTree::new(leaf, call_trees(children), None, None))
+    }
+
+    fn append(&mut self, child: &Bound<DarkTree>) -> PyResult<()> {
+        let child = child.borrow().deref().0.clone();
+        match self.0.append(child) {
+            Ok(()) => Ok(()),
+            Err(e) => Err(PyValueError::new_err(e.to_string())),
+        }
+    }
+}
+
+#[pyclass]
+/// Builder creating a [`Transaction`] out of call trees
+pub struct TransactionBuilder(tx::TransactionBuilder);
+
+#[pymethods]
+impl TransactionBuilder {
+    #[new]
+    #[pyo3(signature = (call, proofs = vec![], children = vec![]))]
+    fn new(
+        call: &Bound<ContractCall>,
+        proofs: Vec<Bound<Proof>>,
+        children: Vec<Bound<DarkTree>>,
+    ) -> PyResult<Self> {
+        match tx::TransactionBuilder::new(call_leaf(call, proofs), call_trees(children)) {
+            Ok(v) => Ok(Self(v)),
+            Err(e) => Err(PyValueError::new_err(e.to_string())),
+        }
+    }
+
+    #[pyo3(signature = (call, proofs = vec![], children = vec![]))]
+    fn append(
+        &mut self,
+        call: &Bound<ContractCall>,
+        proofs: Vec<Bound<Proof>>,
+        children: Vec<Bound<DarkTree>>,
+    ) -> PyResult<()> {
+        match self.0.append(call_leaf(call, proofs), call_trees(children)) {
+            Ok(()) => Ok(()),
+            Err(e) => Err(PyValueError::new_err(e.to_string())),
+        }
+    }
+
+    fn set_expiry(&mut self, expiry: Option<u32>) {
+        self.0.set_expiry(expiry);
+    }
+
+    fn build(&mut self) -> PyResult<Transaction> {
+        match self.0.build() {
+            Ok(v) => Ok(Transaction(v)),
+            Err(e) => Err(PyValueError::new_err(e.to_string())),
+        }
+    }
+}
+
+#[pyclass]
+#[derive(Clone)]
+/// A DarkFi transaction, as accepted by darkfid's `tx.broadcast`
+pub struct Transaction(tx::Transaction);
+
+#[pymethods]
+impl Transaction {
+    #[staticmethod]
+    fn decode(bytes: Vec<u8>) -> PyResult<Self> {
+        match deserialize(&bytes) {
+            Ok(v) => Ok(Self(v)),
+            Err(e) => Err(PyValueError::new_err(e.to_string())),
+        }
+    }
+
+    fn encode<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new_bound(py, &serialize(&self.0))
+    }
+
+    #[staticmethod]
+    fn from_base64(v: &str) -> PyResult<Self> {
+        match base64::decode(v.trim()) {
+            Some(bytes) => Self::decode(bytes),
+            None => Err(PyValueError::new_err("Invalid base64 string")),
+        }
+    }
+
+    /// Encoding used by the darkfid JSON-RPC methods
+    fn to_base64(&self) -> String {
+        base64::encode(&serialize(&self.0))
+    }
+
+    fn hash(&self) -> String {
+        self.0.hash().to_string()
+    }
+
+    /// Returns the calls in order, along with their parent and children indexes
+    fn calls(&self) -> Vec<(ContractCall, Option<usize>, Vec<usize>)> {
+        self.0
+            .calls
+            .iter()
+            .map(|c| (ContractCall(c.data.clone()), c.parent_index, c.children_indexes.clone()))
+            .collect()
+    }
+
+    fn proofs(&self) -> Vec<Vec<Proof>> {
+        self.0.proofs.iter().map(|p| p.iter().map(|p| Proof(p.clone())).collect()).collect()
+    }
+
+    fn expiry(&self) -> Option<u32> {
+        self.0.expiry
+    }
+
+    fn is_expired(&self, block_height: u32) -> bool {
+        self.0.is_expired(block_height)
+    }
+
+    /// Sign the next call of the transaction with the given secret keys.
+    /// Must be called once for every call, in the order of `calls()`.
+    fn sign(&mut self, secret_keys: Vec<Bound<SecretKey>>) -> PyResult<()> {
+        if self.0.signatures.len() >= self.0.calls.len() {
+            return Err(PyValueError::new_err("All transaction calls are already signed"))
+        }
+
+        let secret_keys: Vec<_> = secret_keys.iter().map(|s| s.borrow().0).collect();
+        match self.0.create_sigs(&secret_keys) {
+            Ok(sigs) => {
+                self.0.signatures.push(sigs);
+                Ok(())
+            }
+            Err(e) => Err(PyValueError::new_err(e.to_string())),
+        }
+    }
+
+    /// Verify the signatures against the public keys of every call
+    fn verify_sigs(&self, pub_table: Vec<Vec<Bound<PublicKey>>>) -> bool {
+        if self.0.signatures.len() != pub_table.len() ||
+            self.0.signatures.iter().zip(pub_table.iter()).any(|(s, p)| s.len() != p.len())
+        {
+            return false
+        }
+
+        let pub_table: Vec<Vec<_>> =
+            pub_table.iter().map(|p| p.iter().map(|p| p.borrow().0).collect()).collect();
+        self.0.verify_sigs(pub_table).is_ok()
+    }
+
+    /// Verify the ZK proofs of every call. `verifying_keys` maps contract IDs
+    /// to their circuit namespaces, and `zkp_table` holds the namespace and
+    /// public inputs of each proof, per call.
+    fn verify_zkps(
+        &self,
+        verifying_keys: HashMap<String, HashMap<String, Bound<VerifyingKey>>>,
+        zkp_table: Vec<Vec<(String, Vec<Bound<Fp>>)>>,
+    ) -> PyResult<bool> {
+        let mut vks = HashMap::new();
+        for (contract_id, circuits) in verifying_keys {
+            let contract_id = match ContractId::from_str(&contract_id) {
+                Ok(v) => v,
+                Err(e) => return Err(PyValueError::new_err(e.to_string())),
+            };
+            let circuits: HashMap<_, _> =
+                circuits.into_iter().map(|(ns, vk)| (ns, vk.borrow().0.clone())).collect();
+            vks.insert(contract_id.to_bytes(), circuits);
+        }
+
+        if self.0.proofs.len() != zkp_table.len() ||
+            self.0.proofs.iter().zip(zkp_table.iter()).any(|(p, z)| p.len() != z.len())
+        {
+            return Ok(false)
+        }
+
+        let zkp_table: Vec<Vec<_>> = zkp_table
+            .into_iter()
+            .map(|call| {
+                call.into_iter()
+                    .map(|(ns, inputs)| {
+                        let inputs: Vec<pallas::Base> =
+                            inputs.iter().map(|i| i.borrow().deref().0).collect();
+                        (ns, inputs)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Ok(smol::block_on(self.0.verify_zkps(&vks, zkp_table)).is_ok())
+    }
+
+    fn __str__(&self) -> String {
+        format!("{:?}", self.0)
+    }
+}
+
+/// Wrapper function for creating this Python module.
+pub(crate) fn create_module(py: Python<'_>) -> PyResult<Bound<PyModule>> {
+    let submod = PyModule::new_bound(py, "tx")?;
+    submod.add_class::<ContractCall>()?;
+    submod.add_class::<DarkTree>()?;
+    submod.add_class::<TransactionBuilder>()?;
+    submod.add_class::<Transaction>()?;
+
+    submod.add("MONEY_CONTRACT_ID", MONEY_CONTRACT_ID.to_string())?;
+    submod.add("DAO_CONTRACT_ID", DAO_CONTRACT_ID.to_string())?;
+    submod.add("DEPLOYOOOR_CONTRACT_ID", DEPLOYOOOR_CONTRACT_ID.to_string())?;
+    submod.add("EXCHANGE_CONTRACT_ID", EXCHANGE_CONTRACT_ID.to_string())?;
+
+    Ok(submod)
+}
+
+#[cfg(test)]
+mod tests {
+    use darkfi::{
+        zk::{self, halo2::Value, Witness, ZkCircuit},
+        zkas,
+    };
+    use darkfi_sdk::crypto;
+    use pyo3::types::PyBytesMethods;
+    use rand::rngs::OsRng;
+
+    use super::*;
+
+    const SOURCE: &str = r#"k = 11;
+field = "pallas";
+
+constant "Sum" {}
+
+witness "Sum" {
+    Base a,
+    Base b,
+}
+
+circuit "Sum" {
+    c = base_add(a, b);
+    constrain_instance(c);
+}
+"#;
+
+    /// Create a proof of `a + b`, along with the circuit's verifying key
+    fn sum_proof(a: u64, b: u64) -> (Proof, VerifyingKey) {
+        let tokens = zkas::Lexer::new("sum.zk", SOURCE.chars()).lex().unwrap();
+        let (namespace, k, constants, witnesses, statements) =
+            zkas::Parser::new("sum.zk", SOURCE.chars(), tokens).parse().unwrap();
+        let mut analyzer =
+            zkas::Analyzer::new("sum.zk", SOURCE.chars(), constants, witnesses, statements);
+        analyzer.analyze_types().unwrap();
+        let bincode = zkas::Compiler::new(
+            "sum.zk",
+            SOURCE.chars(),
+            namespace,
+            k,
+            analyzer.constants,
+            analyzer.witnesses,
+            analyzer.statements,
+            analyzer.literals,
+            false,
+        )
+        .compile()
+        .unwrap();
+        let zkbin = zkas::ZkBinary::decode(&bincode).unwrap();
+
+        let witnesses = vec![
+            Witness::Base(Value::known(pallas::Base::from(a))),
+            Witness::Base(Value::known(pallas::Base::from(b))),
+        ];
+        let circuit = ZkCircuit::new(witnesses, &zkbin);
+        let vk = zk::VerifyingKey::build(zkbin.k, &circuit);
+        let pk = zk::ProvingKey::build(zkbin.k, &circuit);
+        let public_inputs = [pallas::Base::from(a + b)];
+        let proof = zk::Proof::create(&pk, &[circuit], &public_inputs, &mut OsRng).unwrap();
+
+        (Proof(proof), VerifyingKey(vk))
+    }
+
+    fn random_secret() -> SecretKey {
+        SecretKey(crypto::SecretKey::random(&mut OsRng))
+    }
+
+    /// Build a transaction with a `Money` call depending on a `DAO` call
+    fn transaction(py: Python<'_>, proofs: Vec<Bound<Proof>>) -> PyResult<Transaction> {
+        let money_call = ContractCall::new(&MONEY_CONTRACT_ID.to_string(), vec![0x03, 1])?;
+        let dao_call = ContractCall::new(&DAO_CONTRACT_ID.to_string(), vec![0x01, 2])?;
+        let child = DarkTree::new(&Bound::new(py, dao_call)?, vec![], vec![]);
+
+        let mut builder = TransactionBuilder::new(
+            &Bound::new(py, money_call)?,
+            proofs,
+            vec![Bound::new(py, child)?],
+        )?;
+        builder.set_expiry(Some(10));
+        builder.build()
+    }
+
+    #[test]
+    fn transaction_builder() -> PyResult<()> {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let tx = transaction(py, vec![])?;
+
+            // The child call is linked to its parent
+            let calls = tx.calls();
+            assert_eq!(calls.len(), 2);
+            let money = calls
+                .iter()
+                .position(|(c, _, _)| c.contract_id() == MONEY_CONTRACT_ID.to_string())
+                .unwrap();
+            let dao = 1 - money;
+            assert_eq!(calls[dao].0.contract_id(), DAO_CONTRACT_ID.to_string());
+            assert_eq!(calls[money].2, vec![dao]);
+            assert_eq!(calls[dao].1, Some(money));
+            assert_eq!(calls[money].0.data(py).as_bytes(), &[0x03, 1]);
+
+            assert_eq!(tx.expiry(), Some(10));
+            assert!(!tx.is_expired(10));
+            assert!(tx.is_expired(11));
+
+            assert!(ContractCall::new("invalid", vec![]).is_err());
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn transaction_signatures() -> PyResult<()> {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let mut tx = transaction(py, vec![])?;
+
+            let secrets = [random_secret(), random_secret()];
+            let publics: Vec<Bound<PublicKey>> = secrets
+                .iter()
+                .map(|s| Bound::new(py, PublicKey(crypto::PublicKey::from_secret(s.0))))
+                .collect::<PyResult<_>>()?;
+
+            // One signature per call, in order
+            tx.sign(vec![Bound::new(py, secrets[0])?])?;
+            tx.sign(vec![Bound::new(py, secrets[1])?])?;
+            assert!(tx.sign(vec![Bound::new(py, secrets[0])?]).is_err());
+
+            let table =
+                |a: usize, b: usize| vec![vec![publics[a].clone()], vec![publics[b].clone()]];
+            assert!(tx.verify_sigs(table(0, 1)));
+            assert!(!tx.verify_sigs(table(1, 0)));
+            assert!(!tx.verify_sigs(vec![vec![publics[0].clone()]]));
+            assert!(!tx.verify_sigs(vec![vec![], vec![publics[1].clone()]]));
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn transaction_proofs() -> PyResult<()> {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let (proof, vk) = sum_proof(2, 3);
+            let tx = transaction(py, vec![Bound::new(py, proof)?])?;
+
+            let money = tx
+                .calls()
+                .iter()
+                .position(|(c, _, _)| c.contract_id() == MONEY_CONTRACT_ID.to_string())
+                .unwrap();
+
+            let vk = Bound::new(py, vk)?;
+            let vks = |contract_id: String| {
+                HashMap::from([(contract_id, HashMap::from([("Sum".to_string(), vk.clone())]))])
+            };
+            let zkp_table = |sum: u64| -> PyResult<Vec<Vec<(String, Vec<Bound<Fp>>)>>> {
+                let mut table = vec![vec![], vec![]];
+                let inputs = vec![Bound::new(py, Fp(pallas::Base::from(sum)))?];
+                table[money].push(("Sum".to_string(), inputs));
+                Ok(table)
+            };
+
+            assert!(tx.verify_zkps(vks(MONEY_CONTRACT_ID.to_string()), zkp_table(5)?)?);
+            assert!(!tx.verify_zkps(vks(MONEY_CONTRACT_ID.to_string()), zkp_table(6)?)?);
+            assert!(!tx.verify_zkps(vks(DAO_CONTRACT_ID.to_string()), zkp_table(5)?)?);
+            assert!(!tx.verify_zkps(vks(MONEY_CONTRACT_ID.to_string()), vec![vec![], vec![]])?);
+            assert!(tx.verify_zkps(vks("invalid".to_string()), zkp_table(5)?).is_err());
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn transaction_encoding() -> PyResult<()> {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let mut tx = transaction(py, vec![])?;
+            tx.sign(vec![Bound::new(py, random_secret())?])?;
+
+            let decoded = Transaction::from_base64(&tx.to_base64())?;
+            assert_eq!(decoded.hash(), tx.hash());
+            assert_eq!(decoded.0, tx.0);
+
+            let decoded = Transaction::decode(tx.encode(py).as_bytes().to_vec())?;
+            assert_eq!(decoded.hash(), tx.hash());
+            assert_eq!(decoded.to_base64(), tx.to_base64());
+
+            // The hash commits to the calls
+            let mut other = transaction(py, vec![])?;
+            other.0.calls[0].data.data.push(0);
+            assert_ne!(other.hash(), tx.hash());
+
+            assert!(Transaction::from_base64("invalid base64").is_err());
+            assert!(Transaction::decode(vec![0xff]).is_err());
+            Ok(())
+        })
+    }
+}
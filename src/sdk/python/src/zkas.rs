@@ -25,7 +25,9 @@ use darkfi::{
 use darkfi_sdk::{crypto::MerkleNode, pasta::pallas};
 use pyo3::{
     prelude::{PyModule, PyModuleMethods},
-    pyclass, pymethods, Bound, PyResult, Python,
+    pyclass, pymethods,
+    types::PyBytes,
+    Bound, PyResult, Python,
 };
 use rand::rngs::OsRng;
 
@@ -227,7 +229,7 @@ impl ZkCircuit {
 
 #[pyclass]
 /// Verifying key for a zkVM proof
-pub struct VerifyingKey(zk::proof::VerifyingKey);
+pub struct VerifyingKey(pub(crate) zk::proof::VerifyingKey);
 
 #[pymethods]
 impl VerifyingKey {
@@ -256,8 +258,9 @@ impl ProvingKey {
 }
 
 #[pyclass]
+#[derive(Clone)]
 /// A zkVM proof
-pub struct Proof(zk::proof::Proof);
+pub struct Proof(pub(crate) zk::proof::Proof);
 
 #[pymethods]
 impl Proof {
@@ -319,6 +322,15 @@ impl Proof {
         Some(Self(proof))
     }
 
+    #[staticmethod]
+    fn decode(bytes: Vec<u8>) -> Self {
+        Self(zk::proof::Proof::new(bytes))
+    }
+
+    fn encode<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new_bound(py, self.0.as_ref())
+    }
+
     fn verify(&self, vk: &Bound<VerifyingKey>, instances: Vec<Bound<Fp>>) -> bool {
         let vk = vk.borrow().deref().0.clone();
         let instances: Vec<pallas::Base> = instances.iter().map(|i| i.borrow().deref().0).collect();
@@ -23,7 +23,10 @@ use syn::{
     Fields, FieldsNamed, FieldsUnnamed, Index, ItemEnum, ItemStruct, WhereClause, WherePredicate,
 };
 
-use super::{contains_initialize_with, contains_skip, discriminant_map, VariantParts};
+use super::{
+    contains_initialize_with, contains_skip, discriminant_map, fields_since, serial_attr,
+    VariantParts,
+};
 
 fn named_fields(
     cratename: &Ident,
@@ -116,7 +119,7 @@ pub fn async_enum_ser(input: &ItemEnum, cratename: Ident) -> syn::Result<TokenSt
     );
     let mut all_variants_idx_body = TokenStream::new();
     let mut fields_body = TokenStream::new();
-    let discriminants = discriminant_map(&input.variants);
+    let discriminants = discriminant_map(&input.variants)?;
 
     for variant in input.variants.iter() {
         let variant_ident = &variant.ident;
@@ -187,7 +190,7 @@ pub fn async_enum_de(input: &ItemEnum, cratename: Ident) -> syn::Result<TokenStr
 
     let init_method = contains_initialize_with(&input.attrs);
     let mut variant_arms = TokenStream::new();
-    let discriminants = discriminant_map(&input.variants);
+    let discriminants = discriminant_map(&input.variants)?;
 
     for variant in input.variants.iter() {
         let variant_ident = &variant.ident;
@@ -280,6 +283,16 @@ pub fn async_struct_ser(input: &ItemStruct, cratename: Ident) -> syn::Result<Tok
         Clone::clone,
     );
 
+    // Versioned structs encode their fields in a length-prefixed body,
+    // so decoders of older versions can skip the fields they don't know.
+    let version = serial_attr(&input.attrs, "version")?;
+    fields_since(&input.fields, version)?;
+    let writer = if version.is_some() {
+        quote! { &mut body }
+    } else {
+        quote! { s }
+    };
+
     let mut body = TokenStream::new();
 
     match &input.fields {
@@ -291,7 +304,7 @@ pub fn async_struct_ser(input: &ItemStruct, cratename: Ident) -> syn::Result<Tok
 
                 let field_name = field.ident.as_ref().unwrap();
                 let delta = quote! {
-                    len += self.#field_name.encode_async(s).await?;
+                    len += self.#field_name.encode_async(#writer).await?;
                 };
                 body.extend(delta);
 
@@ -311,7 +324,7 @@ pub fn async_struct_ser(input: &ItemStruct, cratename: Ident) -> syn::Result<Tok
                     span: Span::call_site(),
                 };
                 let delta = quote! {
-                    len += self.#field_idx.encode_async(s).await?;
+                    len += self.#field_idx.encode_async(#writer).await?;
                 };
                 body.extend(delta);
             }
@@ -319,6 +332,19 @@ pub fn async_struct_ser(input: &ItemStruct, cratename: Ident) -> syn::Result<Tok
         Fields::Unit => {}
     }
 
+    if let Some(version) = version {
+        body = quote! {
+            let mut body = vec![];
+            #body
+            len += #cratename::AsyncEncodable::encode_async(&#version, s).await?;
+            len += #cratename::AsyncEncodable::encode_async(
+                &#cratename::VarInt(body.len() as u64),
+                s,
+            ).await?;
+            #cratename::FutAsyncWriteExt::write_all(s, &body).await?;
+        };
+    }
+
     Ok(quote! {
     #[async_trait]
     impl #impl_generics #cratename::AsyncEncodable for #name #ty_generics #where_clause {
@@ -339,11 +365,29 @@ pub fn async_struct_de(input: &ItemStruct, cratename: Ident) -> syn::Result<Toke
         Clone::clone,
     );
 
+    let version = serial_attr(&input.attrs, "version")?;
+    let since = fields_since(&input.fields, version)?;
+
+    // Fields added after the decoded version get their default value
+    let decode_field = |since: u8| {
+        if since > 0 {
+            quote! {
+                if version >= #since {
+                    #cratename::AsyncDecodable::decode_async(d).await?
+                } else {
+                    Default::default()
+                }
+            }
+        } else {
+            quote! { #cratename::AsyncDecodable::decode_async(d).await? }
+        }
+    };
+
     let init_method = contains_initialize_with(&input.attrs);
     let return_value = match &input.fields {
         Fields::Named(fields) => {
             let mut body = TokenStream::new();
-            for field in &fields.named {
+            for (field, since) in fields.named.iter().zip(since) {
                 let field_name = field.ident.as_ref().unwrap();
 
                 let delta = if contains_skip(&field.attrs) {
@@ -359,8 +403,9 @@ pub fn async_struct_de(input: &ItemStruct, cratename: Ident) -> syn::Result<Toke
                         .unwrap(),
                     );
 
+                    let decode = decode_field(since);
                     quote! {
-                        #field_name: #cratename::AsyncDecodable::decode_async(d).await?,
+                        #field_name: #decode,
                     }
                 };
                 body.extend(delta);
@@ -369,11 +414,12 @@ pub fn async_struct_de(input: &ItemStruct, cratename: Ident) -> syn::Result<Toke
                 Self { #body }
             }
         }
-        Fields::Unnamed(fields) => {
+        Fields::Unnamed(_) => {
             let mut body = TokenStream::new();
-            for _ in 0..fields.unnamed.len() {
+            for since in since {
+                let decode = decode_field(since);
                 let delta = quote! {
-                    #cratename::AsyncDecodable::decode_async(d).await?,
+                    #decode,
                 };
                 body.extend(delta);
             }
@@ -388,6 +434,29 @@ pub fn async_struct_de(input: &ItemStruct, cratename: Ident) -> syn::Result<Toke
         }
     };
 
+    // Versioned structs decode their fields from the length-prefixed body,
+    // skipping any trailing fields added by newer versions.
+    let return_value = if version.is_some() {
+        quote! {{
+            #[allow(unused_variables)]
+            let version: u8 = #cratename::AsyncDecodable::decode_async(d).await?;
+            let body_len: #cratename::VarInt = #cratename::AsyncDecodable::decode_async(d).await?;
+            let mut body = #cratename::FutAsyncReadExt::take(d, body_len.0);
+            let d = &mut body;
+            let return_value = #return_value;
+            #cratename::FutAsyncReadExt::read_to_end(d, &mut vec![]).await?;
+            if d.limit() != 0 {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    "Versioned struct body is truncated",
+                ))
+            }
+            return_value
+        }}
+    } else {
+        return_value
+    };
+
     if let Some(method_ident) = init_method {
         Ok(quote! {
         #[async_trait]
@@ -21,7 +21,9 @@ use std::collections::HashMap;
 
 use proc_macro2::{Ident, TokenStream};
 use quote::quote;
-use syn::{punctuated::Punctuated, token::Comma, Attribute, Path, Variant, WherePredicate};
+use syn::{
+    punctuated::Punctuated, token::Comma, Attribute, Fields, LitInt, Path, Variant, WherePredicate,
+};
 
 mod sync_derive;
 pub use sync_derive::{enum_de, enum_ser, struct_de, struct_ser};
@@ -40,8 +42,12 @@ struct VariantParts {
 
 /// Calculates the discriminant that will be assigned by the compiler.
 /// See: <https://doc.rust-lang.org/reference/items/enumerations.html#assigning-discriminant-values>
-fn discriminant_map(variants: &Punctuated<Variant, Comma>) -> HashMap<Ident, TokenStream> {
+/// If the variants have `#[serial(tag = N)]` attributes, their tags are used instead.
+fn discriminant_map(
+    variants: &Punctuated<Variant, Comma>,
+) -> syn::Result<HashMap<Ident, TokenStream>> {
     let mut map = HashMap::new();
+    let mut tags = vec![];
 
     let mut next_discriminant_if_not_specified = quote! {0};
 
@@ -52,10 +58,34 @@ fn discriminant_map(variants: &Punctuated<Variant, Comma>) -> HashMap<Ident, Tok
             .map_or_else(|| quote! { #next_discriminant_if_not_specified }, |(_, e)| quote! { #e });
 
         next_discriminant_if_not_specified = quote! { #this_discriminant + 1 };
-        map.insert(variant.ident.clone(), this_discriminant);
+
+        match serial_attr(&variant.attrs, "tag")? {
+            Some(tag) => {
+                if tags.contains(&tag) {
+                    return Err(syn::Error::new_spanned(
+                        variant,
+                        format!("Duplicate serial tag: {}", tag),
+                    ))
+                }
+                tags.push(tag);
+                map.insert(variant.ident.clone(), quote! { #tag });
+            }
+            None => {
+                map.insert(variant.ident.clone(), this_discriminant);
+            }
+        }
+    }
+
+    // Tags can't be checked against the compiler assigned discriminants,
+    // so either all variants have one, or none of them.
+    if !tags.is_empty() && tags.len() != variants.len() {
+        return Err(syn::Error::new_spanned(
+            variants,
+            "Either all enum variants must have a serial tag, or none of them",
+        ))
     }
 
-    map
+    Ok(map)
 }
 
 pub fn contains_skip(attrs: &[Attribute]) -> bool {
@@ -76,3 +106,76 @@ pub fn contains_initialize_with(attrs: &[Attribute]) -> Option<Path> {
 
     None
 }
+
+/// Parse the value of a `#[serial(<key> = N)]` attribute, if it is set.
+///
+/// Supported keys are:
+/// * `version`: on structs, the current version of their layout
+/// * `since`: on struct fields, the version the field was added in
+/// * `tag`: on enum variants, the tag used to encode them
+pub fn serial_attr(attrs: &[Attribute], key: &str) -> syn::Result<Option<u8>> {
+    let mut res = None;
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("serial")) {
+        attr.parse_nested_meta(|meta| {
+            if !["version", "since", "tag"].iter().any(|k| meta.path.is_ident(k)) {
+                return Err(meta.error("Unsupported serial attribute"))
+            }
+
+            let value: LitInt = meta.value()?.parse()?;
+            if meta.path.is_ident(key) {
+                res = Some(value.base10_parse()?);
+            }
+
+            Ok(())
+        })?;
+    }
+
+    Ok(res)
+}
+
+/// Returns the version each of the given struct fields was added in,
+/// using their `#[serial(since = N)]` attributes. Fields without one
+/// are part of the initial version `0`.
+///
+/// Since decoders of older versions ignore any data following the fields
+/// they know about, fields added later must be trailing, in version order,
+/// and can't be newer than the struct `version`.
+pub fn fields_since(fields: &Fields, version: Option<u8>) -> syn::Result<Vec<u8>> {
+    let mut ret = Vec::with_capacity(fields.len());
+    let mut last = 0;
+
+    for field in fields.iter() {
+        let since = serial_attr(&field.attrs, "since")?.unwrap_or(0);
+        ret.push(since);
+
+        if contains_skip(&field.attrs) {
+            continue
+        }
+
+        if since > 0 {
+            let Some(version) = version else {
+                return Err(syn::Error::new_spanned(
+                    field,
+                    "`since` fields require a struct `#[serial(version = N)]` attribute",
+                ))
+            };
+
+            if since > version {
+                return Err(syn::Error::new_spanned(
+                    field,
+                    format!("Field `since` {} is newer than struct version {}", since, version),
+                ))
+            }
+        }
+
+        if since < last {
+            return Err(syn::Error::new_spanned(
+                field,
+                "Fields added in later versions must be trailing, in version order",
+            ))
+        }
+        last = since;
+    }
+
+    Ok(ret)
+}
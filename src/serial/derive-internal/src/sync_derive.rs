@@ -23,7 +23,10 @@ use syn::{
     Fields, FieldsNamed, FieldsUnnamed, Index, ItemEnum, ItemStruct, WhereClause, WherePredicate,
 };
 
-use super::{contains_initialize_with, contains_skip, discriminant_map, VariantParts};
+use super::{
+    contains_initialize_with, contains_skip, discriminant_map, fields_since, serial_attr,
+    VariantParts,
+};
 
 fn named_fields(
     cratename: &Ident,
@@ -116,7 +119,7 @@ pub fn enum_ser(input: &ItemEnum, cratename: Ident) -> syn::Result<TokenStream>
     );
     let mut all_variants_idx_body = TokenStream::new();
     let mut fields_body = TokenStream::new();
-    let discriminants = discriminant_map(&input.variants);
+    let discriminants = discriminant_map(&input.variants)?;
 
     for variant in input.variants.iter() {
         let variant_ident = &variant.ident;
@@ -186,7 +189,7 @@ pub fn enum_de(input: &ItemEnum, cratename: Ident) -> syn::Result<TokenStream> {
 
     let init_method = contains_initialize_with(&input.attrs);
     let mut variant_arms = TokenStream::new();
-    let discriminants = discriminant_map(&input.variants);
+    let discriminants = discriminant_map(&input.variants)?;
 
     for variant in input.variants.iter() {
         let variant_ident = &variant.ident;
@@ -278,6 +281,16 @@ pub fn struct_ser(input: &ItemStruct, cratename: Ident) -> syn::Result<TokenStre
         Clone::clone,
     );
 
+    // Versioned structs encode their fields in a length-prefixed body,
+    // so decoders of older versions can skip the fields they don't know.
+    let version = serial_attr(&input.attrs, "version")?;
+    fields_since(&input.fields, version)?;
+    let writer = if version.is_some() {
+        quote! { &mut body }
+    } else {
+        quote! { s }
+    };
+
     let mut body = TokenStream::new();
 
     match &input.fields {
@@ -289,7 +302,7 @@ pub fn struct_ser(input: &ItemStruct, cratename: Ident) -> syn::Result<TokenStre
 
                 let field_name = field.ident.as_ref().unwrap();
                 let delta = quote! {
-                    len += self.#field_name.encode(s)?;
+                    len += self.#field_name.encode(#writer)?;
                 };
                 body.extend(delta);
 
@@ -309,7 +322,7 @@ pub fn struct_ser(input: &ItemStruct, cratename: Ident) -> syn::Result<TokenStre
                     span: Span::call_site(),
                 };
                 let delta = quote! {
-                    len += self.#field_idx.encode(s)?;
+                    len += self.#field_idx.encode(#writer)?;
                 };
                 body.extend(delta);
             }
@@ -317,6 +330,16 @@ pub fn struct_ser(input: &ItemStruct, cratename: Ident) -> syn::Result<TokenStre
         Fields::Unit => {}
     }
 
+    if let Some(version) = version {
+        body = quote! {
+            let mut body = vec![];
+            #body
+            len += #cratename::Encodable::encode(&#version, s)?;
+            len += #cratename::Encodable::encode(&#cratename::VarInt(body.len() as u64), s)?;
+            s.write_all(&body)?;
+        };
+    }
+
     Ok(quote! {
         impl #impl_generics #cratename::Encodable for #name #ty_generics #where_clause {
             fn encode<S: std::io::Write>(&self, s: &mut S) -> ::core::result::Result<usize, std::io::Error> {
@@ -336,11 +359,25 @@ pub fn struct_de(input: &ItemStruct, cratename: Ident) -> syn::Result<TokenStrea
         Clone::clone,
     );
 
+    let version = serial_attr(&input.attrs, "version")?;
+    let since = fields_since(&input.fields, version)?;
+
+    // Fields added after the decoded version get their default value
+    let decode_field = |since: u8| {
+        if since > 0 {
+            quote! {
+                if version >= #since { #cratename::Decodable::decode(d)? } else { Default::default() }
+            }
+        } else {
+            quote! { #cratename::Decodable::decode(d)? }
+        }
+    };
+
     let init_method = contains_initialize_with(&input.attrs);
     let return_value = match &input.fields {
         Fields::Named(fields) => {
             let mut body = TokenStream::new();
-            for field in &fields.named {
+            for (field, since) in fields.named.iter().zip(since) {
                 let field_name = field.ident.as_ref().unwrap();
 
                 let delta = if contains_skip(&field.attrs) {
@@ -356,8 +393,9 @@ pub fn struct_de(input: &ItemStruct, cratename: Ident) -> syn::Result<TokenStrea
                         .unwrap(),
                     );
 
+                    let decode = decode_field(since);
                     quote! {
-                        #field_name: #cratename::Decodable::decode(d)?,
+                        #field_name: #decode,
                     }
                 };
                 body.extend(delta);
@@ -366,11 +404,12 @@ pub fn struct_de(input: &ItemStruct, cratename: Ident) -> syn::Result<TokenStrea
                 Self { #body }
             }
         }
-        Fields::Unnamed(fields) => {
+        Fields::Unnamed(_) => {
             let mut body = TokenStream::new();
-            for _ in 0..fields.unnamed.len() {
+            for since in since {
+                let decode = decode_field(since);
                 let delta = quote! {
-                    #cratename::Decodable::decode(d)?,
+                    #decode,
                 };
                 body.extend(delta);
             }
@@ -385,6 +424,29 @@ pub fn struct_de(input: &ItemStruct, cratename: Ident) -> syn::Result<TokenStrea
         }
     };
 
+    // Versioned structs decode their fields from the length-prefixed body,
+    // skipping any trailing fields added by newer versions.
+    let return_value = if version.is_some() {
+        quote! {{
+            #[allow(unused_variables)]
+            let version: u8 = #cratename::Decodable::decode(d)?;
+            let body_len: #cratename::VarInt = #cratename::Decodable::decode(d)?;
+            let mut body = std::io::Read::take(d, body_len.0);
+            let d = &mut body;
+            let return_value = #return_value;
+            std::io::Read::read_to_end(d, &mut vec![])?;
+            if d.limit() != 0 {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    "Versioned struct body is truncated",
+                ))
+            }
+            return_value
+        }}
+    } else {
+        return_value
+    };
+
     if let Some(method_ident) = init_method {
         Ok(quote! {
         impl #impl_generics #cratename::Decodable for #name #ty_generics #where_clause {
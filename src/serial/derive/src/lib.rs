@@ -28,7 +28,7 @@ use darkfi_derive_internal::{async_enum_de, async_enum_ser, async_struct_de, asy
 
 use darkfi_derive_internal::{enum_de, enum_ser, struct_de, struct_ser};
 
-#[proc_macro_derive(SerialEncodable, attributes(skip_serialize))]
+#[proc_macro_derive(SerialEncodable, attributes(skip_serialize, serial))]
 pub fn darkfi_serialize(input: TokenStream) -> TokenStream {
     let found_crate = crate_name("darkfi-serial").expect("darkfi-serial is found in Cargo.toml");
 
@@ -41,26 +41,28 @@ pub fn darkfi_serialize(input: TokenStream) -> TokenStream {
 
     let res: syn::Result<TokenStream2> = if let Ok(input) = syn::parse::<ItemStruct>(input.clone())
     {
-        let sync_tokens = struct_ser(&input, cratename.clone()).unwrap();
-        #[cfg(feature = "async")]
-        let async_tokens = async_struct_ser(&input, cratename).unwrap();
-        #[cfg(not(feature = "async"))]
-        let async_tokens = quote! {};
-
-        Ok(quote! {
-            #sync_tokens
-            #async_tokens
+        struct_ser(&input, cratename.clone()).and_then(|sync_tokens| {
+            #[cfg(feature = "async")]
+            let async_tokens = async_struct_ser(&input, cratename)?;
+            #[cfg(not(feature = "async"))]
+            let async_tokens = quote! {};
+
+            Ok(quote! {
+                #sync_tokens
+                #async_tokens
+            })
         })
     } else if let Ok(input) = syn::parse::<ItemEnum>(input.clone()) {
-        let sync_tokens = enum_ser(&input, cratename.clone()).unwrap();
-        #[cfg(feature = "async")]
-        let async_tokens = async_enum_ser(&input, cratename).unwrap();
-        #[cfg(not(feature = "async"))]
-        let async_tokens = quote! {};
-
-        Ok(quote! {
-            #sync_tokens
-            #async_tokens
+        enum_ser(&input, cratename.clone()).and_then(|sync_tokens| {
+            #[cfg(feature = "async")]
+            let async_tokens = async_enum_ser(&input, cratename)?;
+            #[cfg(not(feature = "async"))]
+            let async_tokens = quote! {};
+
+            Ok(quote! {
+                #sync_tokens
+                #async_tokens
+            })
         })
     } else if let Ok(_input) = syn::parse::<ItemUnion>(input) {
         todo!()
@@ -72,7 +74,7 @@ pub fn darkfi_serialize(input: TokenStream) -> TokenStream {
     TokenStream::from(res.unwrap_or_else(|err| err.to_compile_error()))
 }
 
-#[proc_macro_derive(SerialDecodable, attributes(skip_serialize))]
+#[proc_macro_derive(SerialDecodable, attributes(skip_serialize, serial))]
 pub fn darkfi_deserialize(input: TokenStream) -> TokenStream {
     let found_crate = crate_name("darkfi-serial").expect("darkfi-serial is found in Cargo.toml");
 
@@ -85,26 +87,28 @@ pub fn darkfi_deserialize(input: TokenStream) -> TokenStream {
 
     let res: syn::Result<TokenStream2> = if let Ok(input) = syn::parse::<ItemStruct>(input.clone())
     {
-        let sync_tokens = struct_de(&input, cratename.clone()).unwrap();
-        #[cfg(feature = "async")]
-        let async_tokens = async_struct_de(&input, cratename).unwrap();
-        #[cfg(not(feature = "async"))]
-        let async_tokens = quote! {};
-
-        Ok(quote! {
-            #sync_tokens
-            #async_tokens
+        struct_de(&input, cratename.clone()).and_then(|sync_tokens| {
+            #[cfg(feature = "async")]
+            let async_tokens = async_struct_de(&input, cratename)?;
+            #[cfg(not(feature = "async"))]
+            let async_tokens = quote! {};
+
+            Ok(quote! {
+                #sync_tokens
+                #async_tokens
+            })
         })
     } else if let Ok(input) = syn::parse::<ItemEnum>(input.clone()) {
-        let sync_tokens = enum_de(&input, cratename.clone()).unwrap();
-        #[cfg(feature = "async")]
-        let async_tokens = async_enum_de(&input, cratename).unwrap();
-        #[cfg(not(feature = "async"))]
-        let async_tokens = quote! {};
-
-        Ok(quote! {
-            #sync_tokens
-            #async_tokens
+        enum_de(&input, cratename.clone()).and_then(|sync_tokens| {
+            #[cfg(feature = "async")]
+            let async_tokens = async_enum_de(&input, cratename)?;
+            #[cfg(not(feature = "async"))]
+            let async_tokens = quote! {};
+
+            Ok(quote! {
+                #sync_tokens
+                #async_tokens
+            })
         })
     } else if let Ok(_input) = syn::parse::<ItemUnion>(input) {
         todo!()
@@ -27,7 +27,7 @@ pub use futures_lite::{
     AsyncWriteExt as FutAsyncWriteExt,
 };
 
-use crate::{endian, VarInt};
+use crate::{endian, enter_collection, with_limits_async, DecodeLimits, LimitedReader, VarInt};
 
 /// Data which can asynchronously be encoded in a consensus-consistent way.
 #[async_trait]
@@ -75,6 +75,36 @@ pub async fn deserialize_async<T: AsyncDecodable>(data: &[u8]) -> Result<T> {
     Ok(rv)
 }
 
+/// Asynchronously deserialize an object from a vector within the given
+/// [`DecodeLimits`], but do not error if the entire vector is not consumed.
+pub async fn deserialize_async_partial_limited<T: AsyncDecodable>(
+    data: &[u8],
+    limits: &DecodeLimits,
+) -> Result<(T, usize)> {
+    limits.check_bytes(data.len() as u64)?;
+    with_limits_async(*limits, deserialize_async_partial(data)).await
+}
+
+/// Asynchronously deserialize an object from a vector within the given
+/// [`DecodeLimits`]. Will error if said deserialization doesn't consume
+/// the entire vector.
+pub async fn deserialize_async_limited<T: AsyncDecodable>(
+    data: &[u8],
+    limits: &DecodeLimits,
+) -> Result<T> {
+    limits.check_bytes(data.len() as u64)?;
+    with_limits_async(*limits, deserialize_async(data)).await
+}
+
+/// Asynchronously decode an object from a reader within the given [`DecodeLimits`].
+pub async fn decode_async_limited<T: AsyncDecodable, D: AsyncRead + Unpin + Send>(
+    d: &mut D,
+    limits: &DecodeLimits,
+) -> Result<T> {
+    let mut reader = LimitedReader::new(d, limits);
+    with_limits_async(*limits, T::decode_async(&mut reader)).await
+}
+
 /// Extensions of `AsyncWrite` to encode data as per Bitcoin consensus.
 #[async_trait]
 pub trait AsyncWriteExt {
@@ -544,8 +574,9 @@ impl<T: AsyncDecodable + Send> AsyncDecodable for Vec<T> {
     #[inline]
     async fn decode_async<D: AsyncRead + Unpin + Send>(d: &mut D) -> Result<Self> {
         let len = VarInt::decode_async(d).await?.0;
+        let (_depth, prealloc) = enter_collection::<T>(len)?;
         let mut ret = Vec::new();
-        ret.try_reserve(len as usize).map_err(|_| std::io::ErrorKind::InvalidData)?;
+        ret.try_reserve(prealloc).map_err(|_| std::io::ErrorKind::InvalidData)?;
         for _ in 0..len {
             ret.push(AsyncDecodable::decode_async(d).await?);
         }
@@ -571,8 +602,9 @@ impl<T: AsyncDecodable + Send> AsyncDecodable for VecDeque<T> {
     #[inline]
     async fn decode_async<D: AsyncRead + Unpin + Send>(d: &mut D) -> Result<Self> {
         let len = VarInt::decode_async(d).await?.0;
+        let (_depth, prealloc) = enter_collection::<T>(len)?;
         let mut ret = VecDeque::new();
-        ret.try_reserve(len as usize).map_err(|_| std::io::ErrorKind::InvalidData)?;
+        ret.try_reserve(prealloc).map_err(|_| std::io::ErrorKind::InvalidData)?;
         for _ in 0..len {
             ret.push_back(AsyncDecodable::decode_async(d).await?);
         }
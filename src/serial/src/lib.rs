@@ -28,7 +28,8 @@ pub use darkfi_derive::{SerialDecodable, SerialEncodable};
 mod async_lib;
 #[cfg(feature = "async")]
 pub use async_lib::{
-    async_trait, deserialize_async, deserialize_async_partial, serialize_async, AsyncDecodable,
+    async_trait, decode_async_limited, deserialize_async, deserialize_async_limited,
+    deserialize_async_partial, deserialize_async_partial_limited, serialize_async, AsyncDecodable,
     AsyncEncodable, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, FutAsyncReadExt,
     FutAsyncWriteExt,
 };
@@ -36,6 +37,12 @@ pub use async_lib::{
 mod endian;
 mod types;
 
+mod limits;
+pub(crate) use limits::enter_collection;
+#[cfg(feature = "async")]
+pub use limits::with_limits_async;
+pub use limits::{with_limits, DecodeLimits, LimitExceeded, LimitedReader};
+
 /// Data which can be encoded in a consensus-consistent way.
 pub trait Encodable {
     /// Encode an object with a well-defined format.
@@ -80,6 +87,29 @@ pub fn deserialize<T: Decodable>(data: &[u8]) -> Result<T, Error> {
     Ok(rv)
 }
 
+/// Deserialize an object from a vector within the given [`DecodeLimits`],
+/// but do not error if the entire vector is not consumed.
+pub fn deserialize_partial_limited<T: Decodable>(
+    data: &[u8],
+    limits: &DecodeLimits,
+) -> Result<(T, usize), Error> {
+    limits.check_bytes(data.len() as u64)?;
+    with_limits(*limits, || deserialize_partial(data))
+}
+
+/// Deserialize an object from a vector within the given [`DecodeLimits`].
+/// Will error if said deserialization doesn't consume the entire vector.
+pub fn deserialize_limited<T: Decodable>(data: &[u8], limits: &DecodeLimits) -> Result<T, Error> {
+    limits.check_bytes(data.len() as u64)?;
+    with_limits(*limits, || deserialize(data))
+}
+
+/// Decode an object from a reader within the given [`DecodeLimits`].
+pub fn decode_limited<T: Decodable, R: Read>(r: R, limits: &DecodeLimits) -> Result<T, Error> {
+    let mut reader = LimitedReader::new(r, limits);
+    with_limits(*limits, || T::decode(&mut reader))
+}
+
 /// Extensions of `Write` to encode data as per Bitcoin consensus.
 pub trait WriteExt {
     /// Output a 128-bit unsigned int
@@ -481,8 +511,9 @@ impl<T: Decodable> Decodable for Vec<T> {
     #[inline]
     fn decode<D: Read>(d: &mut D) -> Result<Self, Error> {
         let len = VarInt::decode(d)?.0;
+        let (_depth, prealloc) = enter_collection::<T>(len)?;
         let mut ret = Vec::new();
-        ret.try_reserve(len as usize).map_err(|_| std::io::ErrorKind::InvalidData)?;
+        ret.try_reserve(prealloc).map_err(|_| std::io::ErrorKind::InvalidData)?;
         for _ in 0..len {
             ret.push(Decodable::decode(d)?);
         }
@@ -506,8 +537,9 @@ impl<T: Decodable> Decodable for VecDeque<T> {
     #[inline]
     fn decode<D: Read>(d: &mut D) -> Result<Self, Error> {
         let len = VarInt::decode(d)?.0;
+        let (_depth, prealloc) = enter_collection::<T>(len)?;
         let mut ret = VecDeque::new();
-        ret.try_reserve(len as usize).map_err(|_| std::io::ErrorKind::InvalidData)?;
+        ret.try_reserve(prealloc).map_err(|_| std::io::ErrorKind::InvalidData)?;
         for _ in 0..len {
             ret.push_back(Decodable::decode(d)?);
         }
@@ -1008,4 +1040,240 @@ mod tests {
         assert_eq!(ts1, ts1_n);
         assert_eq!(ts1_n, TestStruct1(baz));
     }
+
+    #[derive(Debug, PartialEq, SerialEncodable, SerialDecodable)]
+    #[serial(version = 0)]
+    struct VersionedStruct0 {
+        foo: u64,
+        bar: String,
+    }
+
+    #[derive(Debug, PartialEq, SerialEncodable, SerialDecodable)]
+    #[serial(version = 1)]
+    struct VersionedStruct1 {
+        foo: u64,
+        bar: String,
+        #[serial(since = 1)]
+        baz: Vec<u32>,
+    }
+
+    #[derive(Debug, PartialEq, SerialEncodable, SerialDecodable)]
+    #[serial(version = 2)]
+    struct VersionedStruct2 {
+        foo: u64,
+        bar: String,
+        #[serial(since = 1)]
+        baz: Vec<u32>,
+        #[serial(since = 2)]
+        qux: Option<bool>,
+    }
+
+    #[derive(Debug, PartialEq, SerialEncodable, SerialDecodable)]
+    #[serial(version = 1)]
+    struct VersionedTuple(u64, #[serial(since = 1)] String);
+
+    #[test]
+    fn derive_versioned_struct_layout() {
+        let v0 = VersionedStruct0 { foo: 1, bar: String::from("a") };
+        // Version tag, body length, and the body itself
+        let mut expected = vec![0x00, 0x0a];
+        expected.extend_from_slice(&serialize(&1u64));
+        expected.extend_from_slice(&serialize(&String::from("a")));
+        assert_eq!(serialize(&v0), expected);
+
+        let tuple = VersionedTuple(1, String::from("a"));
+        let mut expected = vec![0x01, 0x0a];
+        expected.extend_from_slice(&serialize(&1u64));
+        expected.extend_from_slice(&serialize(&String::from("a")));
+        assert_eq!(serialize(&tuple), expected);
+    }
+
+    #[test]
+    fn derive_versioned_struct_backward_compat() {
+        let foo = 42;
+        let bar = String::from("foobar");
+        let baz = vec![1, 2, 3];
+
+        // Newer decoders use defaults for the fields older encoders didn't know
+        let v0 = serialize(&VersionedStruct0 { foo, bar: bar.clone() });
+        assert_eq!(
+            deserialize::<VersionedStruct1>(&v0).unwrap(),
+            VersionedStruct1 { foo, bar: bar.clone(), baz: vec![] }
+        );
+        assert_eq!(
+            deserialize::<VersionedStruct2>(&v0).unwrap(),
+            VersionedStruct2 { foo, bar: bar.clone(), baz: vec![], qux: None }
+        );
+
+        let v1 = serialize(&VersionedStruct1 { foo, bar: bar.clone(), baz: baz.clone() });
+        assert_eq!(
+            deserialize::<VersionedStruct2>(&v1).unwrap(),
+            VersionedStruct2 { foo, bar: bar.clone(), baz, qux: None }
+        );
+
+        // A version 0 tuple only contains its first field
+        assert_eq!(
+            deserialize::<VersionedTuple>(&[0x00, 0x08, 7, 0, 0, 0, 0, 0, 0, 0]).unwrap(),
+            VersionedTuple(7, String::new())
+        );
+    }
+
+    #[test]
+    fn derive_versioned_struct_forward_compat() {
+        let foo = 42;
+        let bar = String::from("foobar");
+        let v2 = VersionedStruct2 { foo, bar: bar.clone(), baz: vec![1, 2, 3], qux: Some(true) };
+        let v2_s = serialize(&v2);
+
+        // Older decoders skip the fields they don't know, and consume the entire body
+        assert_eq!(
+            deserialize::<VersionedStruct0>(&v2_s).unwrap(),
+            VersionedStruct0 { foo, bar: bar.clone() }
+        );
+        assert_eq!(
+            deserialize::<VersionedStruct1>(&v2_s).unwrap(),
+            VersionedStruct1 { foo, bar: bar.clone(), baz: vec![1, 2, 3] }
+        );
+        assert_eq!(deserialize::<VersionedStruct2>(&v2_s).unwrap(), v2);
+
+        // Data following the versioned struct is decoded correctly
+        let v2_vec = serialize(&vec![v2, VersionedStruct2 { foo, bar, baz: vec![], qux: None }]);
+        let v0_vec = deserialize::<Vec<VersionedStruct0>>(&v2_vec).unwrap();
+        assert_eq!(v0_vec.len(), 2);
+        assert_eq!(v0_vec[0], v0_vec[1]);
+
+        // Truncated bodies fail
+        assert!(deserialize::<VersionedStruct0>(&v2_s[..v2_s.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn derive_versioned_struct_async() {
+        let v1 = VersionedStruct1 { foo: 42, bar: String::from("foobar"), baz: vec![1, 2, 3] };
+        let v1_s = futures_lite::future::block_on(serialize_async(&v1));
+        assert_eq!(v1_s, serialize(&v1));
+
+        let v0: VersionedStruct0 =
+            futures_lite::future::block_on(deserialize_async(&v1_s)).unwrap();
+        assert_eq!(v0, VersionedStruct0 { foo: v1.foo, bar: v1.bar.clone() });
+
+        let v2: VersionedStruct2 =
+            futures_lite::future::block_on(deserialize_async(&v1_s)).unwrap();
+        assert_eq!(v2, VersionedStruct2 { foo: v1.foo, bar: v1.bar, baz: v1.baz, qux: None });
+    }
+
+    #[derive(Debug, PartialEq, SerialEncodable, SerialDecodable)]
+    enum TaggedEnum0 {
+        #[serial(tag = 0x01)]
+        First,
+        #[serial(tag = 0x10)]
+        Second(u32),
+    }
+
+    #[derive(Debug, PartialEq, SerialEncodable, SerialDecodable)]
+    enum TaggedEnum1 {
+        #[serial(tag = 0x20)]
+        Third { foo: String },
+        #[serial(tag = 0x10)]
+        Second(u32),
+        #[serial(tag = 0x01)]
+        First,
+    }
+
+    #[test]
+    fn derive_tagged_enum() {
+        assert_eq!(serialize(&TaggedEnum0::First), [0x01]);
+        assert_eq!(serialize(&TaggedEnum0::Second(1)), [0x10, 1, 0, 0, 0]);
+        assert_eq!(serialize(&TaggedEnum1::Third { foo: String::new() }), [0x20, 0]);
+
+        // Tags stay stable when variants are reordered or added
+        let first = serialize(&TaggedEnum0::First);
+        let second = serialize(&TaggedEnum0::Second(42));
+        assert_eq!(deserialize::<TaggedEnum1>(&first).unwrap(), TaggedEnum1::First);
+        assert_eq!(deserialize::<TaggedEnum1>(&second).unwrap(), TaggedEnum1::Second(42));
+
+        let second = futures_lite::future::block_on(serialize_async(&TaggedEnum1::Second(42)));
+        let second: TaggedEnum0 =
+            futures_lite::future::block_on(deserialize_async(&second)).unwrap();
+        assert_eq!(second, TaggedEnum0::Second(42));
+
+        // Unknown tags fail
+        let third = serialize(&TaggedEnum1::Third { foo: String::from("foo") });
+        assert!(deserialize::<TaggedEnum0>(&third).is_err());
+    }
+
+    #[test]
+    fn limited_deserialize() {
+        let limits = DecodeLimits::new(4, 64, 2);
+        let limit = |e: Error| LimitExceeded::from_io(&e);
+
+        // Data within the limits decodes as usual
+        let data = serialize(&vec![vec![1u8, 2], vec![3, 4, 5, 6]]);
+        let v: Vec<Vec<u8>> = deserialize_limited(&data, &limits).unwrap();
+        assert_eq!(v, vec![vec![1, 2], vec![3, 4, 5, 6]]);
+
+        // A forged length prefix fails before allocating anything
+        let forged = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x0f];
+        let err = deserialize_limited::<Vec<u64>>(&forged, &limits).unwrap_err();
+        assert_eq!(limit(err), Some(LimitExceeded::Length(4)));
+        let err = deserialize_limited::<String>(&serialize(&"foobar"), &limits).unwrap_err();
+        assert_eq!(limit(err), Some(LimitExceeded::Length(4)));
+
+        // Nesting deeper than allowed fails
+        let data = serialize(&vec![vec![vec![1u8]]]);
+        let err = deserialize_limited::<Vec<Vec<Vec<u8>>>>(&data, &limits).unwrap_err();
+        assert_eq!(limit(err), Some(LimitExceeded::Depth(2)));
+
+        // Oversized input fails, both from slices and readers
+        let data = serialize(&[0u64; 9]);
+        let err = deserialize_limited::<[u64; 9]>(&data, &limits).unwrap_err();
+        assert_eq!(limit(err), Some(LimitExceeded::Bytes(64)));
+        let err = decode_limited::<[u64; 9], _>(Cursor::new(&data), &limits).unwrap_err();
+        assert_eq!(limit(err), Some(LimitExceeded::Bytes(64)));
+
+        // The context is left once decoding is done
+        let data = serialize(&vec![0u8; 16]);
+        assert!(deserialize::<Vec<u8>>(&data).is_ok());
+        assert!(limit(deserialize::<Vec<u8>>(&[0x01]).unwrap_err()).is_none());
+    }
+
+    #[test]
+    fn limited_deserialize_async() {
+        let limits = DecodeLimits::new(4, 64, 2);
+        let limit = |e: Error| LimitExceeded::from_io(&e);
+
+        let data = serialize(&vec![vec![1u8, 2], vec![3, 4, 5, 6]]);
+        let v: Vec<Vec<u8>> =
+            futures_lite::future::block_on(deserialize_async_limited(&data, &limits)).unwrap();
+        assert_eq!(v, vec![vec![1, 2], vec![3, 4, 5, 6]]);
+
+        let data = serialize(&vec![vec![vec![1u8]]]);
+        let err = futures_lite::future::block_on(deserialize_async_limited::<Vec<Vec<Vec<u8>>>>(
+            &data, &limits,
+        ))
+        .unwrap_err();
+        assert_eq!(limit(err), Some(LimitExceeded::Depth(2)));
+
+        let data = serialize(&vec![0u8; 5]);
+        let mut reader = futures_lite::io::Cursor::new(&data);
+        let err = futures_lite::future::block_on(decode_async_limited::<Vec<u8>, _>(
+            &mut reader,
+            &limits,
+        ))
+        .unwrap_err();
+        assert_eq!(limit(err), Some(LimitExceeded::Length(4)));
+
+        let data = serialize(&[0u64; 9]);
+        let mut reader = futures_lite::io::Cursor::new(&data);
+        let err = futures_lite::future::block_on(decode_async_limited::<[u64; 9], _>(
+            &mut reader,
+            &limits,
+        ))
+        .unwrap_err();
+        assert_eq!(limit(err), Some(LimitExceeded::Bytes(64)));
+
+        // The context is left once decoding is done
+        let data = serialize(&vec![0u8; 16]);
+        let v: Vec<u8> = futures_lite::future::block_on(deserialize_async(&data)).unwrap();
+        assert_eq!(v.len(), 16);
+    }
 }
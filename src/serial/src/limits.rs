@@ -0,0 +1,318 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2025 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Bounded decoding context.
+//!
+//! Collection decoders trust the `VarInt` length prefix they read, so
+//! decoding untrusted data can make us allocate huge buffers or recurse
+//! arbitrarily deep. [`DecodeLimits`] bounds the length of every decoded
+//! collection, the total amount of bytes consumed and the nesting depth
+//! of collections. The limits are installed in a thread-local context by
+//! the `*_limited` helpers, so the [`Decodable`](crate::Decodable) trait
+//! and existing implementations stay untouched. Outside of a context,
+//! decoding behaves as before.
+//!
+//! Violations are reported as an [`std::io::Error`] of kind
+//! [`ErrorKind::InvalidData`] wrapping a [`LimitExceeded`], which can be
+//! recovered with [`LimitExceeded::from_io`].
+
+use std::{
+    cell::Cell,
+    fmt,
+    io::{Error, ErrorKind, Read},
+    mem::size_of,
+};
+
+/// Maximum amount of bytes preallocated for a collection while decoding
+/// inside a bounded context. Larger collections grow as their elements
+/// are actually decoded.
+const MAX_PREALLOC_BYTES: usize = 1024 * 1024;
+
+/// Bounds applied while decoding untrusted data.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DecodeLimits {
+    /// Maximum number of elements of a single collection
+    pub max_len: u64,
+    /// Maximum total amount of bytes consumed
+    pub max_bytes: u64,
+    /// Maximum nesting depth of collections
+    pub max_depth: u32,
+}
+
+impl DecodeLimits {
+    /// Create a new set of decoding limits.
+    pub const fn new(max_len: u64, max_bytes: u64, max_depth: u32) -> Self {
+        Self { max_len, max_bytes, max_depth }
+    }
+
+    /// Limits that never trigger.
+    pub const fn unlimited() -> Self {
+        Self::new(u64::MAX, u64::MAX, u32::MAX)
+    }
+
+    /// Check that `len` bytes of input are within the `max_bytes` bound.
+    pub fn check_bytes(&self, len: u64) -> Result<(), Error> {
+        if len > self.max_bytes {
+            return Err(LimitExceeded::Bytes(self.max_bytes).into())
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for DecodeLimits {
+    /// 1M elements per collection, 16MB of input and 32 nesting levels.
+    fn default() -> Self {
+        Self::new(1024 * 1024, 16 * 1024 * 1024, 32)
+    }
+}
+
+/// Error returned when decoded data violates the active [`DecodeLimits`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LimitExceeded {
+    /// A collection length prefix exceeded `max_len`
+    Length(u64),
+    /// The input exceeded `max_bytes`
+    Bytes(u64),
+    /// Collections were nested deeper than `max_depth`
+    Depth(u32),
+}
+
+impl LimitExceeded {
+    /// Retrieve the [`LimitExceeded`] wrapped in an I/O error, if any.
+    pub fn from_io(err: &Error) -> Option<Self> {
+        err.get_ref()?.downcast_ref::<Self>().copied()
+    }
+}
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Length(max) => write!(f, "Collection length exceeds limit of {} elements", max),
+            Self::Bytes(max) => write!(f, "Data length exceeds limit of {} bytes", max),
+            Self::Depth(max) => write!(f, "Nesting depth exceeds limit of {} levels", max),
+        }
+    }
+}
+
+impl std::error::Error for LimitExceeded {}
+
+impl From<LimitExceeded> for Error {
+    fn from(err: LimitExceeded) -> Self {
+        Error::new(ErrorKind::InvalidData, err)
+    }
+}
+
+/// State of an active bounded decoding context.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct DecodeContext {
+    limits: DecodeLimits,
+    depth: u32,
+}
+
+impl DecodeContext {
+    pub(crate) fn new(limits: DecodeLimits) -> Self {
+        Self { limits, depth: 0 }
+    }
+}
+
+thread_local! {
+    static CONTEXT: Cell<Option<DecodeContext>> = const { Cell::new(None) };
+}
+
+/// Install `ctx` as the active context of this thread,
+/// returning the previously active one.
+pub(crate) fn swap_context(ctx: Option<DecodeContext>) -> Option<DecodeContext> {
+    CONTEXT.with(|c| c.replace(ctx))
+}
+
+/// Restores the previously active context when dropped,
+/// so nested and panicking decodes leave no state behind.
+struct ContextGuard(Option<DecodeContext>);
+
+impl Drop for ContextGuard {
+    fn drop(&mut self) {
+        swap_context(self.0);
+    }
+}
+
+/// Run `f` with `limits` as the active decoding context of this thread.
+pub fn with_limits<T>(limits: DecodeLimits, f: impl FnOnce() -> T) -> T {
+    let _guard = ContextGuard(swap_context(Some(DecodeContext::new(limits))));
+    f()
+}
+
+/// Leaves a collection nesting level when dropped.
+#[must_use]
+pub(crate) struct DepthGuard(bool);
+
+impl Drop for DepthGuard {
+    fn drop(&mut self) {
+        if !self.0 {
+            return
+        }
+
+        CONTEXT.with(|c| {
+            if let Some(mut ctx) = c.get() {
+                ctx.depth = ctx.depth.saturating_sub(1);
+                c.set(Some(ctx));
+            }
+        });
+    }
+}
+
+/// Enter the decoding of a collection of `len` elements, checking it
+/// against the active context. Returns a guard leaving the nesting
+/// level, and the amount of elements that may be preallocated.
+pub(crate) fn enter_collection<T>(len: u64) -> Result<(DepthGuard, usize), Error> {
+    CONTEXT.with(|c| {
+        let Some(mut ctx) = c.get() else { return Ok((DepthGuard(false), len as usize)) };
+
+        if len > ctx.limits.max_len {
+            return Err(LimitExceeded::Length(ctx.limits.max_len).into())
+        }
+
+        if ctx.depth >= ctx.limits.max_depth {
+            return Err(LimitExceeded::Depth(ctx.limits.max_depth).into())
+        }
+
+        ctx.depth += 1;
+        c.set(Some(ctx));
+
+        let prealloc = (MAX_PREALLOC_BYTES / size_of::<T>().max(1)).min(len as usize);
+        Ok((DepthGuard(true), prealloc))
+    })
+}
+
+/// Reader wrapper failing with [`LimitExceeded::Bytes`] once more than
+/// `max_bytes` are read from the inner reader.
+pub struct LimitedReader<R> {
+    inner: R,
+    remaining: u64,
+    max_bytes: u64,
+}
+
+impl<R> LimitedReader<R> {
+    /// Bound `inner` to the `max_bytes` of the given limits.
+    pub fn new(inner: R, limits: &DecodeLimits) -> Self {
+        Self { inner, remaining: limits.max_bytes, max_bytes: limits.max_bytes }
+    }
+
+    /// Unwrap the inner reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Compute how much of `buf` may be filled, or the limit error.
+    fn bound(&self, buf_len: usize) -> Result<usize, Error> {
+        if buf_len > 0 && self.remaining == 0 {
+            return Err(LimitExceeded::Bytes(self.max_bytes).into())
+        }
+
+        Ok((self.remaining.min(buf_len as u64)) as usize)
+    }
+}
+
+impl<R: Read> Read for LimitedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let len = self.bound(buf.len())?;
+        let n = self.inner.read(&mut buf[..len])?;
+        self.remaining -= n as u64;
+        Ok(n)
+    }
+}
+
+#[cfg(feature = "async")]
+mod async_limits {
+    use std::{
+        future::Future,
+        io::Error,
+        pin::Pin,
+        task::{Context, Poll},
+    };
+
+    use futures_lite::AsyncRead;
+
+    use super::{swap_context, ContextGuard, DecodeContext, DecodeLimits, LimitedReader};
+
+    /// Future polling its inner future with a bounded decoding context
+    /// installed, so the context follows the decoding task across threads.
+    struct Limited<F: Future> {
+        fut: Option<Pin<Box<F>>>,
+        ctx: Option<DecodeContext>,
+    }
+
+    impl<F: Future> Limited<F> {
+        /// Run `f` with this future's context installed, saving
+        /// back the updated context afterwards.
+        fn enter<T>(&mut self, f: impl FnOnce(&mut Option<Pin<Box<F>>>) -> T) -> T {
+            let guard = ContextGuard(swap_context(self.ctx));
+            let ret = f(&mut self.fut);
+            self.ctx = swap_context(guard.0);
+            std::mem::forget(guard);
+            ret
+        }
+    }
+
+    impl<F: Future> Future for Limited<F> {
+        type Output = F::Output;
+
+        fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+            let this = self.get_mut();
+            this.enter(|fut| fut.as_mut().expect("polled after completion").as_mut().poll(cx))
+        }
+    }
+
+    impl<F: Future> Drop for Limited<F> {
+        fn drop(&mut self) {
+            // Dropping a pending decode releases its nesting levels,
+            // which must happen inside its own context.
+            self.enter(|fut| drop(fut.take()));
+        }
+    }
+
+    /// Await `fut` with `limits` as its active decoding context.
+    pub async fn with_limits_async<F: Future>(limits: DecodeLimits, fut: F) -> F::Output {
+        Limited { fut: Some(Box::pin(fut)), ctx: Some(DecodeContext::new(limits)) }.await
+    }
+
+    impl<R: AsyncRead + Unpin> AsyncRead for LimitedReader<R> {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<Result<usize, Error>> {
+            let this = self.get_mut();
+            let len = match this.bound(buf.len()) {
+                Ok(len) => len,
+                Err(e) => return Poll::Ready(Err(e)),
+            };
+
+            match Pin::new(&mut this.inner).poll_read(cx, &mut buf[..len]) {
+                Poll::Ready(Ok(n)) => {
+                    this.remaining -= n as u64;
+                    Poll::Ready(Ok(n))
+                }
+                other => other,
+            }
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+pub use async_limits::with_limits_async;
@@ -29,7 +29,7 @@ use async_trait::async_trait;
 #[cfg(feature = "async")]
 use futures_lite::{AsyncRead, AsyncWrite};
 
-use crate::{Decodable, Encodable, VarInt};
+use crate::{enter_collection, Decodable, Encodable, VarInt};
 
 impl<T: Encodable> Encodable for HashSet<T> {
     fn encode<S: Write>(&self, s: &mut S) -> Result<usize> {
@@ -58,6 +58,7 @@ impl<T: AsyncEncodable + Sync> AsyncEncodable for HashSet<T> {
 impl<T: Decodable + std::cmp::Eq + std::hash::Hash> Decodable for HashSet<T> {
     fn decode<D: Read>(d: &mut D) -> Result<Self> {
         let len = VarInt::decode(d)?.0;
+        let (_depth, _) = enter_collection::<()>(len)?;
         let mut ret = HashSet::new();
         for _ in 0..len {
             let entry: T = Decodable::decode(d)?;
@@ -72,6 +73,7 @@ impl<T: Decodable + std::cmp::Eq + std::hash::Hash> Decodable for HashSet<T> {
 impl<T: AsyncDecodable + Send + std::cmp::Eq + std::hash::Hash> AsyncDecodable for HashSet<T> {
     async fn decode_async<D: AsyncRead + Unpin + Send>(d: &mut D) -> Result<Self> {
         let len = VarInt::decode_async(d).await?.0;
+        let (_depth, _) = enter_collection::<()>(len)?;
         let mut ret = HashSet::new();
         for _ in 0..len {
             let entry: T = AsyncDecodable::decode_async(d).await?;
@@ -110,6 +112,7 @@ impl<T: AsyncEncodable + Sync, U: AsyncEncodable + Sync> AsyncEncodable for BTre
 impl<T: Decodable + std::cmp::Ord, U: Decodable> Decodable for BTreeMap<T, U> {
     fn decode<D: Read>(d: &mut D) -> Result<Self> {
         let len = VarInt::decode(d)?.0;
+        let (_depth, _) = enter_collection::<()>(len)?;
         let mut ret = BTreeMap::new();
         for _ in 0..len {
             let key: T = Decodable::decode(d)?;
@@ -127,6 +130,7 @@ impl<T: AsyncDecodable + Send + std::cmp::Ord, U: AsyncDecodable + Send> AsyncDe
 {
     async fn decode_async<D: AsyncRead + Unpin + Send>(d: &mut D) -> Result<Self> {
         let len = VarInt::decode_async(d).await?.0;
+        let (_depth, _) = enter_collection::<()>(len)?;
         let mut ret = BTreeMap::new();
         for _ in 0..len {
             let key: T = AsyncDecodable::decode_async(d).await?;
@@ -164,6 +168,7 @@ impl<T: AsyncEncodable + Sync> AsyncEncodable for BTreeSet<T> {
 impl<T: Decodable + std::cmp::Ord> Decodable for BTreeSet<T> {
     fn decode<D: Read>(d: &mut D) -> Result<Self> {
         let len = VarInt::decode(d)?.0;
+        let (_depth, _) = enter_collection::<()>(len)?;
         let mut ret = BTreeSet::new();
         for _ in 0..len {
             let key: T = Decodable::decode(d)?;
@@ -178,6 +183,7 @@ impl<T: Decodable + std::cmp::Ord> Decodable for BTreeSet<T> {
 impl<T: AsyncDecodable + Send + std::cmp::Ord> AsyncDecodable for BTreeSet<T> {
     async fn decode_async<D: AsyncRead + Unpin + Send>(d: &mut D) -> Result<Self> {
         let len = VarInt::decode_async(d).await?.0;
+        let (_depth, _) = enter_collection::<()>(len)?;
         let mut ret = BTreeSet::new();
         for _ in 0..len {
             let key: T = AsyncDecodable::decode_async(d).await?;
@@ -216,6 +222,7 @@ impl<T: AsyncEncodable + Sync, U: AsyncEncodable + Sync> AsyncEncodable for Hash
 impl<T: Decodable + std::cmp::Eq + std::hash::Hash, U: Decodable> Decodable for HashMap<T, U> {
     fn decode<D: Read>(d: &mut D) -> Result<Self> {
         let len = VarInt::decode(d)?.0;
+        let (_depth, _) = enter_collection::<()>(len)?;
         let mut ret = HashMap::new();
         for _ in 0..len {
             let key: T = Decodable::decode(d)?;
@@ -233,6 +240,7 @@ impl<T: AsyncDecodable + Send + std::cmp::Eq + std::hash::Hash, U: AsyncDecodabl
 {
     async fn decode_async<D: AsyncRead + Unpin + Send>(d: &mut D) -> Result<Self> {
         let len = VarInt::decode_async(d).await?.0;
+        let (_depth, _) = enter_collection::<()>(len)?;
         let mut ret = HashMap::new();
         for _ in 0..len {
             let key: T = AsyncDecodable::decode_async(d).await?;
@@ -16,7 +16,10 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    io::{Error as IoError, ErrorKind, Read, Result as IoResult, Write},
+};
 
 use darkfi_sdk::{
     crypto::{
@@ -31,10 +34,13 @@ use darkfi_sdk::{
 };
 
 #[cfg(feature = "async-serial")]
-use darkfi_serial::async_trait;
+use darkfi_serial::{
+    async_trait, AsyncDecodable, AsyncEncodable, AsyncRead, AsyncWrite, FutAsyncReadExt,
+    FutAsyncWriteExt,
+};
 
-use darkfi_serial::{Encodable, SerialDecodable, SerialEncodable};
-use log::{debug, error};
+use darkfi_serial::{Decodable, Encodable};
+use log::{debug, error, warn};
 
 use crate::{
     error::TxVerifyFailed,
@@ -54,7 +60,11 @@ macro_rules! zip {
 /// along with corresponding ZK proofs and Schnorr signatures.
 ///
 /// `DarkLeaf` is used to map relations between contract calls in the transaction.
-#[derive(Clone, Default, Eq, PartialEq, SerialEncodable, SerialDecodable)]
+///
+/// Transactions without an expiry use the original encoding, so their hash
+/// and signatures are unchanged. Transactions with an expiry are prefixed
+/// with [`TX_VERSION_MARKER`] and a version byte.
+#[derive(Clone, Default, Eq, PartialEq)]
 pub struct Transaction {
     /// Calls executed in this transaction
     pub calls: Vec<DarkLeaf<ContractCall>>,
@@ -62,23 +72,158 @@ pub struct Transaction {
     pub proofs: Vec<Vec<Proof>>,
     /// Attached Schnorr signatures
     pub signatures: Vec<Vec<Signature>>,
+    /// Optional block height after which the transaction
+    /// can no longer be included in a block
+    pub expiry: Option<u32>,
 }
 // ANCHOR_END: transaction
 
+/// Prefix of versioned transaction encodings. Unversioned transactions
+/// start with the VarInt length of their calls, and since non-minimal
+/// VarInts are rejected by the decoder, this can never begin one.
+pub const TX_VERSION_MARKER: [u8; 3] = [0xfd, 0x00, 0x00];
+
+/// Transaction encoding version carrying an expiry height.
+pub const TX_VERSION_EXPIRY: u8 = 1;
+
+impl Encodable for Transaction {
+    fn encode<S: Write>(&self, s: &mut S) -> IoResult<usize> {
+        let mut len = 0;
+
+        if let Some(expiry) = self.expiry {
+            s.write_all(&TX_VERSION_MARKER)?;
+            len += TX_VERSION_MARKER.len();
+            len += TX_VERSION_EXPIRY.encode(s)?;
+            len += expiry.encode(s)?;
+        }
+
+        len += self.calls.encode(s)?;
+        len += self.proofs.encode(s)?;
+        len += self.signatures.encode(s)?;
+        Ok(len)
+    }
+}
+
+impl Decodable for Transaction {
+    fn decode<D: Read>(d: &mut D) -> IoResult<Self> {
+        // An unversioned transaction is at least three empty vectors long
+        let mut prefix = [0u8; 3];
+        d.read_exact(&mut prefix)?;
+
+        if prefix != TX_VERSION_MARKER {
+            let mut d = Read::chain(prefix.as_slice(), d);
+            return Ok(Self {
+                calls: Decodable::decode(&mut d)?,
+                proofs: Decodable::decode(&mut d)?,
+                signatures: Decodable::decode(&mut d)?,
+                expiry: None,
+            })
+        }
+
+        let version: u8 = Decodable::decode(d)?;
+        if version != TX_VERSION_EXPIRY {
+            return Err(IoError::new(ErrorKind::Other, "Unknown transaction version"))
+        }
+
+        Ok(Self {
+            expiry: Some(Decodable::decode(d)?),
+            calls: Decodable::decode(d)?,
+            proofs: Decodable::decode(d)?,
+            signatures: Decodable::decode(d)?,
+        })
+    }
+}
+
+#[cfg(feature = "async-serial")]
+#[async_trait]
+impl AsyncEncodable for Transaction {
+    async fn encode_async<S: AsyncWrite + Unpin + Send>(&self, s: &mut S) -> IoResult<usize> {
+        let mut len = 0;
+
+        if let Some(expiry) = self.expiry {
+            s.write_all(&TX_VERSION_MARKER).await?;
+            len += TX_VERSION_MARKER.len();
+            len += TX_VERSION_EXPIRY.encode_async(s).await?;
+            len += expiry.encode_async(s).await?;
+        }
+
+        len += self.calls.encode_async(s).await?;
+        len += self.proofs.encode_async(s).await?;
+        len += self.signatures.encode_async(s).await?;
+        Ok(len)
+    }
+}
+
+#[cfg(feature = "async-serial")]
+#[async_trait]
+impl AsyncDecodable for Transaction {
+    async fn decode_async<D: AsyncRead + Unpin + Send>(d: &mut D) -> IoResult<Self> {
+        // An unversioned transaction is at least three empty vectors long
+        let mut prefix = [0u8; 3];
+        d.read_exact(&mut prefix).await?;
+
+        if prefix != TX_VERSION_MARKER {
+            let mut d = FutAsyncReadExt::chain(prefix.as_slice(), d);
+            return Ok(Self {
+                calls: AsyncDecodable::decode_async(&mut d).await?,
+                proofs: AsyncDecodable::decode_async(&mut d).await?,
+                signatures: AsyncDecodable::decode_async(&mut d).await?,
+                expiry: None,
+            })
+        }
+
+        let version: u8 = AsyncDecodable::decode_async(d).await?;
+        if version != TX_VERSION_EXPIRY {
+            return Err(IoError::new(ErrorKind::Other, "Unknown transaction version"))
+        }
+
+        Ok(Self {
+            expiry: Some(AsyncDecodable::decode_async(d).await?),
+            calls: AsyncDecodable::decode_async(d).await?,
+            proofs: AsyncDecodable::decode_async(d).await?,
+            signatures: AsyncDecodable::decode_async(d).await?,
+        })
+    }
+}
+
 impl Transaction {
+    /// Check that the transaction carries exactly one proof for each
+    /// entry of the given public inputs table, and one set of proofs per
+    /// contract call. Transactions come from the network, so a mismatch
+    /// is a verification failure rather than a bug.
+    fn check_zkp_table(&self, zkp_table: &[Vec<(String, Vec<pallas::Base>)>]) -> Result<()> {
+        if self.calls.len() != self.proofs.len() || self.calls.len() != zkp_table.len() {
+            error!(
+                target: "tx::check_zkp_table",
+                "[TX] Transaction has {} calls, {} proof sets and {} public input sets",
+                self.calls.len(), self.proofs.len(), zkp_table.len(),
+            );
+            return Err(TxVerifyFailed::InvalidZkProof.into())
+        }
+
+        for (i, (proofs, pubvals)) in self.proofs.iter().zip(zkp_table).enumerate() {
+            if proofs.len() != pubvals.len() {
+                error!(
+                    target: "tx::check_zkp_table",
+                    "[TX] Call {} has {} proofs and {} public input sets",
+                    i, proofs.len(), pubvals.len(),
+                );
+                return Err(TxVerifyFailed::InvalidZkProof.into())
+            }
+        }
+
+        Ok(())
+    }
+
     /// Verify ZK proofs for the entire transaction.
     pub async fn verify_zkps(
         &self,
         verifying_keys: &HashMap<[u8; 32], HashMap<String, VerifyingKey>>,
         zkp_table: Vec<Vec<(String, Vec<pallas::Base>)>>,
     ) -> Result<()> {
-        // TODO: Are we sure we should assert here?
-        assert_eq!(self.calls.len(), self.proofs.len());
-        assert_eq!(self.calls.len(), zkp_table.len());
+        self.check_zkp_table(&zkp_table)?;
 
         for (call, (proofs, pubvals)) in zip!(self.calls, self.proofs, zkp_table) {
-            assert_eq!(proofs.len(), pubvals.len());
-
             let Some(contract_map) = verifying_keys.get(&call.data.contract_id.to_bytes()) else {
                 error!(
                     target: "tx::verify_zkps",
@@ -120,12 +265,59 @@ impl Transaction {
         Ok(())
     }
 
+    /// Append the ZK proofs of the entire transaction to provided [`ZkProofBatch`],
+    /// so they can be verified along with other transactions proofs. The provided
+    /// `index` identifies the transaction in case the batch verification fails.
+    pub fn batch_zkps(
+        &self,
+        index: usize,
+        verifying_keys: &HashMap<[u8; 32], HashMap<String, VerifyingKey>>,
+        zkp_table: Vec<Vec<(String, Vec<pallas::Base>)>>,
+        batch: &mut ZkProofBatch,
+    ) -> Result<()> {
+        self.check_zkp_table(&zkp_table)?;
+
+        for ((call, proofs), pubvals) in self.calls.iter().zip(self.proofs.iter()).zip(zkp_table) {
+            let contract_id = call.data.contract_id.to_bytes();
+            let Some(contract_map) = verifying_keys.get(&contract_id) else {
+                error!(
+                    target: "tx::batch_zkps",
+                    "[TX] Verifying keys not found for contract {}",
+                    call.data.contract_id,
+                );
+                return Err(TxVerifyFailed::InvalidZkProof.into())
+            };
+
+            for (proof, (zk_ns, public_vals)) in proofs.iter().zip(pubvals) {
+                if !contract_map.contains_key(&zk_ns) {
+                    error!(
+                        target: "tx::batch_zkps",
+                        "[TX] {}::{} circuit VK nonexistent",
+                        call.data.contract_id, zk_ns,
+                    );
+                    return Err(TxVerifyFailed::InvalidZkProof.into())
+                }
+
+                batch.proofs.entry((contract_id, zk_ns)).or_default().push((
+                    index,
+                    proof.clone(),
+                    public_vals,
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
     /// Verify Schnorr signatures for the entire transaction.
     pub fn verify_sigs(&self, pub_table: Vec<Vec<PublicKey>>) -> Result<()> {
         // Hash the transaction without the signatures
         let mut hasher = blake3::Hasher::new();
         self.calls.encode(&mut hasher)?;
         self.proofs.encode(&mut hasher)?;
+        if let Some(expiry) = self.expiry {
+            expiry.encode(&mut hasher)?;
+        }
         let data_hash = hasher.finalize();
 
         debug!(
@@ -164,6 +356,9 @@ impl Transaction {
         let mut hasher = blake3::Hasher::new();
         self.calls.encode(&mut hasher)?;
         self.proofs.encode(&mut hasher)?;
+        if let Some(expiry) = self.expiry {
+            expiry.encode(&mut hasher)?;
+        }
         let data_hash = hasher.finalize();
 
         debug!(
@@ -208,6 +403,15 @@ impl Transaction {
     pub fn is_single_call(&self) -> bool {
         self.calls.len() == 1 && !self.calls[0].data.data.is_empty()
     }
+
+    /// Returns true if the transaction has an expiry height and provided
+    /// block height is past it, meaning it can't be included in that block.
+    pub fn is_expired(&self, block_height: u32) -> bool {
+        match self.expiry {
+            Some(expiry) => block_height > expiry,
+            None => false,
+        }
+    }
 }
 
 // Avoid showing the proofs and sigs in the debug output since often they are very long.
@@ -225,6 +429,7 @@ impl std::fmt::Debug for Transaction {
             writeln!(f, "    children: {:?}", call.children_indexes)?;
             writeln!(f, "  }},")?;
         }
+        writeln!(f, "  expiry: {:?}", self.expiry)?;
         writeln!(f, "}}")
     }
 }
@@ -235,6 +440,59 @@ use crate::net::Message;
 #[cfg(feature = "net")]
 crate::impl_p2p_message!(Transaction, "tx");
 
+/// Auxiliary structure to verify the ZK proofs of multiple transactions
+/// using halo2 batch verification. Proofs are grouped by their
+/// `(contract_id, zkas_ns)` verifying key, since a batch must share it.
+#[derive(Default)]
+pub struct ZkProofBatch {
+    /// Proofs grouped by verifying key, along with their public inputs
+    /// and the index of the transaction they belong to
+    proofs: HashMap<([u8; 32], String), Vec<(usize, Proof, Vec<pallas::Base>)>>,
+}
+
+impl ZkProofBatch {
+    /// Verify all batched proofs. When a verifying key batch fails, its proofs
+    /// are verified one by one to find the erroneous ones. Returns the sorted
+    /// indexes of the transactions containing invalid proofs.
+    pub fn verify(
+        &self,
+        verifying_keys: &HashMap<[u8; 32], HashMap<String, VerifyingKey>>,
+    ) -> Vec<usize> {
+        let mut erroneous = vec![];
+        for ((contract_id, zk_ns), proofs) in &self.proofs {
+            // Verifying keys existence was checked when proofs were appended
+            let vk = &verifying_keys[contract_id][zk_ns];
+
+            debug!(
+                target: "tx::verify_zkps_batch",
+                "[TX] Batch verifying {} {} ZK proofs", proofs.len(), zk_ns,
+            );
+            if Proof::verify_batch(vk, proofs.iter().map(|(_, p, v)| (p, v.as_slice()))) {
+                continue
+            }
+
+            warn!(
+                target: "tx::verify_zkps_batch",
+                "[TX] Batch verification of {} ZK proofs failed, verifying them one by one", zk_ns,
+            );
+            for (index, proof, public_vals) in proofs {
+                if let Err(e) = proof.verify(vk, public_vals) {
+                    error!(
+                        target: "tx::verify_zkps_batch",
+                        "[TX] Failed verifying {} ZK proof of transaction {}: {:#?}",
+                        zk_ns, index, e
+                    );
+                    erroneous.push(*index);
+                }
+            }
+        }
+
+        erroneous.sort_unstable();
+        erroneous.dedup();
+        erroneous
+    }
+}
+
 /// Calls tree bounds definitions
 // TODO: increase min to 2 when fees are implement
 pub const MIN_TX_CALLS: usize = 1;
@@ -256,6 +514,8 @@ pub struct ContractCallLeaf {
 pub struct TransactionBuilder {
     /// Contract calls trees forest
     pub calls: DarkForest<ContractCallLeaf>,
+    /// Optional block height after which the transaction expires
+    pub expiry: Option<u32>,
 }
 
 // TODO: for now we build the trees manually, but we should
@@ -268,7 +528,7 @@ impl TransactionBuilder {
         children: Vec<DarkTree<ContractCallLeaf>>,
     ) -> DarkTreeResult<Self> {
         let calls = DarkForest::new(Some(MIN_TX_CALLS), Some(MAX_TX_CALLS));
-        let mut self_ = Self { calls };
+        let mut self_ = Self { calls, expiry: None };
         self_.append(data, children)?;
         Ok(self_)
     }
@@ -283,6 +543,13 @@ impl TransactionBuilder {
         self.calls.append(tree)
     }
 
+    /// Set the block height after which the transaction expires.
+    /// Must be called before [`TransactionBuilder::build`], since
+    /// the expiry is covered by the transaction signatures.
+    pub fn set_expiry(&mut self, expiry: Option<u32>) {
+        self.expiry = expiry;
+    }
+
     /// Builder builds the calls vector using the [`DarkForest`]
     /// and generates the corresponding [`Transaction`].
     pub fn build(&mut self) -> DarkTreeResult<Transaction> {
@@ -305,6 +572,64 @@ impl TransactionBuilder {
             proofs.push(leaf.data.proofs);
         }
 
-        Ok(Transaction { calls, proofs, signatures: vec![] })
+        Ok(Transaction { calls, proofs, signatures: vec![], expiry: self.expiry })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use darkfi_sdk::crypto::MONEY_CONTRACT_ID;
+    use darkfi_serial::{deserialize, serialize};
+
+    use super::*;
+
+    #[test]
+    fn tx_expiry_encoding() {
+        // Transactions without an expiry keep the unversioned encoding
+        let tx = Transaction::default();
+        assert_eq!(serialize(&tx), vec![0, 0, 0]);
+        assert_eq!(deserialize::<Transaction>(&[0, 0, 0]).unwrap(), tx);
+
+        let tx = Transaction { expiry: Some(42), ..Default::default() };
+        let bytes = serialize(&tx);
+        assert_eq!(bytes[..3], TX_VERSION_MARKER);
+        assert_eq!(bytes[3], TX_VERSION_EXPIRY);
+        assert_eq!(deserialize::<Transaction>(&bytes).unwrap(), tx);
+        assert_ne!(tx.hash(), Transaction::default().hash());
+
+        // Unknown versions are rejected
+        let mut bytes = bytes;
+        bytes[3] = TX_VERSION_EXPIRY + 1;
+        assert!(deserialize::<Transaction>(&bytes).is_err());
+    }
+
+    #[test]
+    fn tx_zkp_table_mismatch() {
+        let call = DarkLeaf {
+            data: ContractCall { contract_id: *MONEY_CONTRACT_ID, data: vec![] },
+            parent_index: None,
+            children_indexes: vec![],
+        };
+        let zkp_table = vec![vec![("Mint".to_string(), vec![pallas::Base::from(0)])]];
+        let vks = HashMap::new();
+
+        // A call without its proofs set is rejected instead of panicking
+        let tx = Transaction { calls: vec![call.clone()], ..Default::default() };
+        assert!(tx.check_zkp_table(&zkp_table).is_err());
+        let mut batch = ZkProofBatch::default();
+        assert!(tx.batch_zkps(0, &vks, zkp_table.clone(), &mut batch).is_err());
+        assert!(batch.proofs.is_empty());
+
+        // So is a missing public inputs set
+        let tx =
+            Transaction { calls: vec![call.clone()], proofs: vec![vec![]], ..Default::default() };
+        assert!(tx.check_zkp_table(&[]).is_err());
+
+        // And a proofs count not matching the public inputs sets
+        assert!(tx.check_zkp_table(&zkp_table).is_err());
+        assert!(tx.batch_zkps(0, &vks, zkp_table, &mut batch).is_err());
+
+        // Matching lengths pass the check
+        assert!(tx.check_zkp_table(&[vec![]]).is_ok());
     }
 }
@@ -0,0 +1,541 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2025 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Process-wide metrics registry.
+//!
+//! Subsystems register their counters, gauges and histograms once, in the
+//! global [`registry()`], and update them with plain atomic operations.
+//! The registry renders everything in the Prometheus text exposition
+//! format, or in OpenMetrics, and daemons can serve it over HTTP with
+//! [`crate::rpc::metrics::listen_and_serve()`].
+//!
+//! Metrics can be split by label values using a [`Family`]. Label values
+//! should come from a small, known set (session types, protocol commands,
+//! RPC methods), since every distinct value creates a new time series.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{
+        atomic::{AtomicI64, AtomicU64, Ordering::Relaxed},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+/// Default histogram buckets, in seconds
+pub const DEFAULT_BUCKETS: &[f64] =
+    &[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0];
+
+/// The global metrics registry
+static REGISTRY: Registry = Registry::new();
+
+/// Returns the global metrics registry
+pub fn registry() -> &'static Registry {
+    &REGISTRY
+}
+
+/// Text formats the registry can be rendered in
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Format {
+    /// Prometheus text exposition format, version 0.0.4
+    Prometheus,
+    /// OpenMetrics text format, version 1.0.0
+    OpenMetrics,
+}
+
+impl Format {
+    /// Returns the HTTP `Content-Type` of the format
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Prometheus => "text/plain; version=0.0.4; charset=utf-8",
+            Self::OpenMetrics => "application/openmetrics-text; version=1.0.0; charset=utf-8",
+        }
+    }
+}
+
+/// A single metric value, which can be rendered as a set of samples
+pub trait Metric: Send + Sync {
+    /// Metric type, as written in the `# TYPE` line
+    const TYPE: &'static str;
+
+    /// Append the samples of the metric to `out`. `labels` holds the
+    /// already rendered labels of the series, without braces.
+    fn encode(&self, name: &str, labels: &str, out: &mut String);
+}
+
+/// A monotonically increasing counter
+#[derive(Debug, Default)]
+pub struct Counter(AtomicU64);
+
+impl Counter {
+    /// Increment the counter by one
+    pub fn inc(&self) {
+        self.inc_by(1);
+    }
+
+    /// Increment the counter by `v`
+    pub fn inc_by(&self, v: u64) {
+        self.0.fetch_add(v, Relaxed);
+    }
+
+    /// Returns the current value of the counter
+    pub fn get(&self) -> u64 {
+        self.0.load(Relaxed)
+    }
+}
+
+impl Metric for Counter {
+    const TYPE: &'static str = "counter";
+
+    fn encode(&self, name: &str, labels: &str, out: &mut String) {
+        write_sample(out, name, "", labels, None, &self.get().to_string());
+    }
+}
+
+/// A value that can go up and down
+#[derive(Debug, Default)]
+pub struct Gauge(AtomicI64);
+
+impl Gauge {
+    /// Set the gauge to `v`
+    pub fn set(&self, v: i64) {
+        self.0.store(v, Relaxed);
+    }
+
+    /// Increment the gauge by one
+    pub fn inc(&self) {
+        self.add(1);
+    }
+
+    /// Decrement the gauge by one
+    pub fn dec(&self) {
+        self.add(-1);
+    }
+
+    /// Add `v` to the gauge
+    pub fn add(&self, v: i64) {
+        self.0.fetch_add(v, Relaxed);
+    }
+
+    /// Returns the current value of the gauge
+    pub fn get(&self) -> i64 {
+        self.0.load(Relaxed)
+    }
+}
+
+impl Metric for Gauge {
+    const TYPE: &'static str = "gauge";
+
+    fn encode(&self, name: &str, labels: &str, out: &mut String) {
+        write_sample(out, name, "", labels, None, &self.get().to_string());
+    }
+}
+
+/// Samples observations, like durations or sizes, into buckets
+#[derive(Debug)]
+pub struct Histogram {
+    /// Upper bounds of the buckets, in increasing order
+    bounds: Vec<f64>,
+    /// Observation count of each bucket, plus the `+Inf` one.
+    /// Counts are not cumulative, they get summed up when encoding.
+    buckets: Vec<AtomicU64>,
+    /// Sum of all observations, as `f64` bits
+    sum: AtomicU64,
+    /// Total number of observations
+    count: AtomicU64,
+}
+
+impl Histogram {
+    /// Create a new [`Histogram`] with the given bucket upper bounds
+    pub fn new(bounds: &[f64]) -> Self {
+        assert!(bounds.windows(2).all(|w| w[0] < w[1]), "Histogram buckets must be increasing");
+        let buckets = (0..=bounds.len()).map(|_| AtomicU64::new(0)).collect();
+        Self {
+            bounds: bounds.to_vec(),
+            buckets,
+            sum: AtomicU64::new(0f64.to_bits()),
+            count: AtomicU64::new(0),
+        }
+    }
+
+    /// Record an observation
+    pub fn observe(&self, v: f64) {
+        let idx = self.bounds.iter().position(|b| v <= *b).unwrap_or(self.bounds.len());
+        self.buckets[idx].fetch_add(1, Relaxed);
+
+        let mut sum = self.sum.load(Relaxed);
+        loop {
+            let new = (f64::from_bits(sum) + v).to_bits();
+            match self.sum.compare_exchange_weak(sum, new, Relaxed, Relaxed) {
+                Ok(_) => break,
+                Err(current) => sum = current,
+            }
+        }
+
+        self.count.fetch_add(1, Relaxed);
+    }
+
+    /// Record a duration, in seconds
+    pub fn observe_duration(&self, d: Duration) {
+        self.observe(d.as_secs_f64());
+    }
+
+    /// Returns the total number of observations
+    pub fn count(&self) -> u64 {
+        self.count.load(Relaxed)
+    }
+
+    /// Returns the sum of all observations
+    pub fn sum(&self) -> f64 {
+        f64::from_bits(self.sum.load(Relaxed))
+    }
+}
+
+impl Metric for Histogram {
+    const TYPE: &'static str = "histogram";
+
+    fn encode(&self, name: &str, labels: &str, out: &mut String) {
+        let mut cumulative = 0;
+        for (i, bucket) in self.buckets.iter().enumerate() {
+            cumulative += bucket.load(Relaxed);
+            let le = match self.bounds.get(i) {
+                Some(bound) => format_f64(*bound),
+                None => "+Inf".to_string(),
+            };
+            write_sample(out, name, "_bucket", labels, Some(&le), &cumulative.to_string());
+        }
+        write_sample(out, name, "_sum", labels, None, &format_f64(self.sum()));
+        write_sample(out, name, "_count", labels, None, &self.count().to_string());
+    }
+}
+
+/// A set of metrics of the same type, split by label values
+pub struct Family<M> {
+    /// Metric name
+    name: String,
+    /// Help text
+    help: String,
+    /// Label names
+    labels: Vec<String>,
+    /// Constructor for new series
+    new_metric: Box<dyn Fn() -> M + Send + Sync>,
+    /// Series of the family, by label values
+    series: Mutex<BTreeMap<Vec<String>, Arc<M>>>,
+}
+
+impl<M: Metric> Family<M> {
+    /// Returns the metric of the series with the given label values,
+    /// creating it if it does not exist yet.
+    pub fn with(&self, values: &[&str]) -> Arc<M> {
+        assert_eq!(values.len(), self.labels.len(), "Label count mismatch for {}", self.name);
+        let key: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+        let mut series = self.series.lock().unwrap();
+        series.entry(key).or_insert_with(|| Arc::new((self.new_metric)())).clone()
+    }
+
+    /// Remove the series with the given label values
+    pub fn remove(&self, values: &[&str]) {
+        let key: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+        self.series.lock().unwrap().remove(&key);
+    }
+}
+
+/// Type-erased metric family, as stored in the [`Registry`]
+trait Collector: Send + Sync {
+    fn name(&self) -> &str;
+    fn encode(&self, format: Format, out: &mut String);
+}
+
+impl<M: Metric> Collector for Family<M> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn encode(&self, format: Format, out: &mut String) {
+        // OpenMetrics names counter families without the `_total` suffix
+        // that the samples carry.
+        let family_name = match (format, M::TYPE) {
+            (Format::OpenMetrics, "counter") => self.name.strip_suffix("_total").unwrap(),
+            _ => &self.name,
+        };
+
+        let help = self.help.replace('\\', "\\\\").replace('\n', "\\n");
+        writeln!(out, "# HELP {} {}", family_name, help).unwrap();
+        writeln!(out, "# TYPE {} {}", family_name, M::TYPE).unwrap();
+
+        for (values, metric) in self.series.lock().unwrap().iter() {
+            let labels: Vec<String> = self
+                .labels
+                .iter()
+                .zip(values)
+                .map(|(k, v)| format!("{}=\"{}\"", k, escape_label_value(v)))
+                .collect();
+            metric.encode(&self.name, &labels.join(","), out);
+        }
+    }
+}
+
+/// A collection of metric families
+pub struct Registry {
+    families: Mutex<Vec<Arc<dyn Collector>>>,
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Registry {
+    /// Create a new, empty [`Registry`]. Most code should use the global
+    /// one, returned by [`registry()`].
+    pub const fn new() -> Self {
+        Self { families: Mutex::new(vec![]) }
+    }
+
+    /// Register a new [`Counter`]. Counter names must end in `_total`.
+    pub fn counter(&self, name: &str, help: &str) -> Arc<Counter> {
+        self.counter_family(name, help, &[]).with(&[])
+    }
+
+    /// Register a new family of [`Counter`]s with the given label names.
+    /// Counter names must end in `_total`.
+    pub fn counter_family(&self, name: &str, help: &str, labels: &[&str]) -> Arc<Family<Counter>> {
+        assert!(name.ends_with("_total"), "Counter name {} must end in _total", name);
+        self.register(name, help, labels, Counter::default)
+    }
+
+    /// Register a new [`Gauge`]
+    pub fn gauge(&self, name: &str, help: &str) -> Arc<Gauge> {
+        self.gauge_family(name, help, &[]).with(&[])
+    }
+
+    /// Register a new family of [`Gauge`]s with the given label names
+    pub fn gauge_family(&self, name: &str, help: &str, labels: &[&str]) -> Arc<Family<Gauge>> {
+        self.register(name, help, labels, Gauge::default)
+    }
+
+    /// Register a new [`Histogram`] with the given bucket upper bounds
+    pub fn histogram(&self, name: &str, help: &str, buckets: &[f64]) -> Arc<Histogram> {
+        self.histogram_family(name, help, &[], buckets).with(&[])
+    }
+
+    /// Register a new family of [`Histogram`]s with the given label names
+    /// and bucket upper bounds
+    pub fn histogram_family(
+        &self,
+        name: &str,
+        help: &str,
+        labels: &[&str],
+        buckets: &[f64],
+    ) -> Arc<Family<Histogram>> {
+        assert!(!labels.contains(&"le"), "Histogram {} can't use the le label", name);
+        let buckets = buckets.to_vec();
+        self.register(name, help, labels, move || Histogram::new(&buckets))
+    }
+
+    /// Render all registered metrics in the given text format
+    pub fn encode(&self, format: Format) -> String {
+        let mut out = String::new();
+        for family in self.families.lock().unwrap().iter() {
+            family.encode(format, &mut out);
+        }
+
+        if format == Format::OpenMetrics {
+            out.push_str("# EOF\n");
+        }
+
+        out
+    }
+
+    fn register<M: Metric + 'static>(
+        &self,
+        name: &str,
+        help: &str,
+        labels: &[&str],
+        new_metric: impl Fn() -> M + Send + Sync + 'static,
+    ) -> Arc<Family<M>> {
+        assert!(is_valid_name(name, true), "Invalid metric name {}", name);
+        for label in labels {
+            assert!(is_valid_name(label, false), "Invalid label name {} in {}", label, name);
+        }
+
+        let family = Arc::new(Family {
+            name: name.to_string(),
+            help: help.to_string(),
+            labels: labels.iter().map(|l| l.to_string()).collect(),
+            new_metric: Box::new(new_metric),
+            series: Mutex::new(BTreeMap::new()),
+        });
+
+        let mut families = self.families.lock().unwrap();
+        assert!(families.iter().all(|f| f.name() != name), "Metric {} already registered", name);
+        families.push(family.clone());
+
+        family
+    }
+}
+
+/// Check a metric or label name is valid. Only metric names can
+/// contain colons.
+fn is_valid_name(name: &str, allow_colon: bool) -> bool {
+    let valid = |c: char| c.is_ascii_alphanumeric() || c == '_' || (allow_colon && c == ':');
+    match name.chars().next() {
+        Some(c) if c.is_ascii_digit() => false,
+        Some(_) => name.chars().all(valid),
+        None => false,
+    }
+}
+
+/// Escape a label value as required by the text formats
+fn escape_label_value(v: &str) -> String {
+    v.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Format a float the way the text formats expect it
+fn format_f64(v: f64) -> String {
+    if v.is_nan() {
+        return "NaN".to_string()
+    }
+
+    if v.is_infinite() {
+        return if v > 0.0 { "+Inf".to_string() } else { "-Inf".to_string() }
+    }
+
+    v.to_string()
+}
+
+/// Append a single sample line to `out`
+fn write_sample(
+    out: &mut String,
+    name: &str,
+    suffix: &str,
+    labels: &str,
+    le: Option<&str>,
+    value: &str,
+) {
+    out.push_str(name);
+    out.push_str(suffix);
+
+    if !labels.is_empty() || le.is_some() {
+        out.push('{');
+        out.push_str(labels);
+        if let Some(le) = le {
+            if !labels.is_empty() {
+                out.push(',');
+            }
+            write!(out, "le=\"{}\"", le).unwrap();
+        }
+        out.push('}');
+    }
+
+    writeln!(out, " {}", value).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_counters_and_gauges() {
+        let registry = Registry::new();
+
+        let bans = registry.counter("test_bans_total", "Banned peers");
+        bans.inc();
+        bans.inc_by(2);
+
+        let messages = registry.counter_family("test_messages_total", "Messages", &["command"]);
+        messages.with(&["ping"]).inc();
+        messages.with(&["addr"]).inc_by(5);
+        messages.with(&["ping"]).inc();
+
+        let mempool = registry.gauge("test_mempool_size", "Pending transactions");
+        mempool.set(10);
+        mempool.dec();
+
+        let expected = "\
+# HELP test_bans_total Banned peers
+# TYPE test_bans_total counter
+test_bans_total 3
+# HELP test_messages_total Messages
+# TYPE test_messages_total counter
+test_messages_total{command=\"addr\"} 5
+test_messages_total{command=\"ping\"} 2
+# HELP test_mempool_size Pending transactions
+# TYPE test_mempool_size gauge
+test_mempool_size 9
+";
+        assert_eq!(registry.encode(Format::Prometheus), expected);
+
+        let openmetrics = registry.encode(Format::OpenMetrics);
+        assert!(openmetrics.contains("# TYPE test_bans counter\ntest_bans_total 3\n"));
+        assert!(openmetrics.ends_with("test_mempool_size 9\n# EOF\n"));
+    }
+
+    #[test]
+    fn encode_histograms() {
+        let registry = Registry::new();
+
+        let latency =
+            registry.histogram_family("test_latency_seconds", "Latency", &["method"], &[0.1, 1.0]);
+        let ping = latency.with(&["ping"]);
+        ping.observe(0.05);
+        ping.observe(0.5);
+        ping.observe_duration(Duration::from_secs(2));
+
+        assert_eq!(ping.count(), 3);
+        assert_eq!(ping.sum(), 2.55);
+
+        let expected = "\
+# HELP test_latency_seconds Latency
+# TYPE test_latency_seconds histogram
+test_latency_seconds_bucket{method=\"ping\",le=\"0.1\"} 1
+test_latency_seconds_bucket{method=\"ping\",le=\"1\"} 2
+test_latency_seconds_bucket{method=\"ping\",le=\"+Inf\"} 3
+test_latency_seconds_sum{method=\"ping\"} 2.55
+test_latency_seconds_count{method=\"ping\"} 3
+";
+        assert_eq!(registry.encode(Format::Prometheus), expected);
+    }
+
+    #[test]
+    fn escape_labels() {
+        let registry = Registry::new();
+
+        let family = registry.gauge_family("test_escape", "Help with \\ and\nnewline", &["v"]);
+        family.with(&["a\"b\\c\nd"]).set(1);
+        family.with(&["gone"]).set(2);
+        family.remove(&["gone"]);
+
+        let expected = "\
+# HELP test_escape Help with \\\\ and\\nnewline
+# TYPE test_escape gauge
+test_escape{v=\"a\\\"b\\\\c\\nd\"} 1
+";
+        assert_eq!(registry.encode(Format::Prometheus), expected);
+    }
+
+    #[test]
+    #[should_panic(expected = "already registered")]
+    fn duplicate_registration() {
+        let registry = Registry::new();
+        registry.gauge("test_gauge", "A gauge");
+        registry.gauge("test_gauge", "Another gauge");
+    }
+}
@@ -37,6 +37,9 @@ pub mod time;
 /// Ring Buffer implementation
 pub mod ringbuffer;
 
+/// Process-wide metrics registry
+pub mod metrics;
+
 /// Permuted Congruential Generator (PCG)
 /// This is an insecure PRNG used for simulations and tests.
 #[cfg(feature = "rand")]
@@ -0,0 +1,52 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2025 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::sync::{Arc, LazyLock};
+
+use crate::util::metrics::{registry, Gauge, Histogram, DEFAULT_BUCKETS};
+
+/// Validator metrics, registered in the global registry on first use
+pub(super) static VALIDATOR_METRICS: LazyLock<ValidatorMetrics> =
+    LazyLock::new(ValidatorMetrics::new);
+
+pub(super) struct ValidatorMetrics {
+    /// Time spent verifying valid blocks
+    pub block_verify_time: Arc<Histogram>,
+    /// Transactions in the pending txs store
+    pub mempool_size: Arc<Gauge>,
+    /// Consensus forks being tracked
+    pub forks: Arc<Gauge>,
+}
+
+impl ValidatorMetrics {
+    fn new() -> Self {
+        let r = registry();
+        Self {
+            block_verify_time: r.histogram(
+                "darkfi_validator_block_verify_duration_seconds",
+                "Time spent verifying valid blocks",
+                DEFAULT_BUCKETS,
+            ),
+            mempool_size: r.gauge(
+                "darkfi_validator_mempool_size",
+                "Transactions in the pending transactions store",
+            ),
+            forks: r.gauge("darkfi_validator_forks", "Consensus forks being tracked"),
+        }
+    }
+}
@@ -58,6 +58,10 @@ use fees::compute_fee;
 pub mod utils;
 use utils::{best_fork_index, block_rank, deploy_native_contracts};
 
+/// Validator metrics
+mod metrics;
+use metrics::VALIDATOR_METRICS;
+
 /// Configuration for initializing [`Validator`]
 #[derive(Clone)]
 pub struct ValidatorConfig {
@@ -126,6 +130,9 @@ impl Validator {
             verify_fees: config.verify_fees,
         });
 
+        // Register the validator metrics with their initial values
+        state.update_metrics().await;
+
         info!(target: "validator::new", "Finished initializing validator");
         Ok(state)
     }
@@ -238,6 +245,7 @@ impl Validator {
         if write {
             self.blockchain.add_pending_txs(&tx_vec)?;
             info!(target: "validator::append_tx", "Appended tx to pending txs store");
+            self.update_metrics().await;
         }
 
         Ok(())
@@ -314,6 +322,7 @@ impl Validator {
         }
         info!(target: "validator::purge_pending_txs", "Removing {} erroneous transactions...", removed_txs.len());
         self.blockchain.remove_pending_txs(&removed_txs)?;
+        self.update_metrics().await;
 
         Ok(())
     }
@@ -329,6 +338,8 @@ impl Validator {
         // Release append lock
         drop(append_lock);
 
+        self.update_metrics().await;
+
         result
     }
 
@@ -406,6 +417,8 @@ impl Validator {
         // Release append lock
         drop(append_lock);
 
+        self.update_metrics().await;
+
         Ok(confirmed_blocks)
     }
 
@@ -515,6 +528,8 @@ impl Validator {
         *self.consensus.forks.write().await =
             vec![Fork::new(self.blockchain.clone(), module).await?];
 
+        self.update_metrics().await;
+
         Ok(())
     }
 
@@ -803,8 +818,18 @@ impl Validator {
         // Release append lock
         drop(append_lock);
 
+        self.update_metrics().await;
+
         info!(target: "validator::reset_to_height", "Validator reset successfully!");
 
         Ok(())
     }
+
+    /// Auxiliary function to update the validator metrics with the current
+    /// consensus forks count and pending txs store size. Callers modifying
+    /// the pending txs store directly should call this once done.
+    pub async fn update_metrics(&self) {
+        VALIDATOR_METRICS.forks.set(self.consensus.forks.read().await.len() as i64);
+        VALIDATOR_METRICS.mempool_size.set(self.blockchain.transactions.pending.len() as i64);
+    }
 }
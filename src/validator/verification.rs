@@ -16,7 +16,7 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Instant};
 
 use darkfi_sdk::{
     blockchain::block_version,
@@ -36,11 +36,15 @@ use crate::{
         HeaderHash,
     },
     error::TxVerifyFailed,
-    runtime::vm_runtime::Runtime,
-    tx::{Transaction, MAX_TX_CALLS, MIN_TX_CALLS},
+    runtime::{
+        access::{AccessSet, AccessSetPtr},
+        vm_runtime::Runtime,
+    },
+    tx::{Transaction, ZkProofBatch, MAX_TX_CALLS, MIN_TX_CALLS},
     validator::{
         consensus::{Consensus, Fork, Proposal, GAS_LIMIT_UNPROPOSED_TXS},
         fees::{circuit_gas_use, compute_fee, GasData, PALLAS_SCHNORR_SIGNATURE_FEE},
+        metrics::VALIDATOR_METRICS,
         pow::PoWModule,
     },
     zk::VerifyingKey,
@@ -179,6 +183,7 @@ pub async fn verify_block(
     previous: &BlockInfo,
     verify_fees: bool,
 ) -> Result<()> {
+    let start = Instant::now();
     let block_hash = block.hash();
     debug!(target: "validator::verification::verify_block", "Validating block {}", block_hash);
 
@@ -198,7 +203,7 @@ pub async fn verify_block(
     // Verify transactions, exluding producer(last) one
     let mut tree = MerkleTree::new(1);
     let txs = &block.txs[..block.txs.len() - 1];
-    let e = verify_transactions(
+    let e = verify_transactions_parallel(
         overlay,
         block.header.height,
         module.target,
@@ -237,6 +242,7 @@ pub async fn verify_block(
 
     // Insert block
     overlay.lock().unwrap().add_block(block)?;
+    VALIDATOR_METRICS.block_verify_time.observe_duration(start.elapsed());
 
     debug!(target: "validator::verification::verify_block", "Block {} verified successfully", block_hash);
     Ok(())
@@ -544,6 +550,65 @@ pub async fn verify_transaction(
     let tx_hash = tx.hash();
     debug!(target: "validator::verification::verify_transaction", "Validating transaction {}", tx_hash);
 
+    let TxStateData { gas_data, zkp_table, sig_table, .. } = verify_transaction_state(
+        overlay,
+        verifying_block_height,
+        block_target,
+        tx,
+        verifying_keys,
+        verify_fee,
+        None,
+    )
+    .await?;
+    verify_transaction_sigs(tx, sig_table)?;
+
+    debug!(target: "validator::verification::verify_transaction", "Verifying ZK proofs for transaction {}", tx_hash);
+    if let Err(e) = tx.verify_zkps(verifying_keys, zkp_table).await {
+        error!(
+            target: "validator::verification::verify_transaction",
+            "[VALIDATOR] ZK proof verification for tx {} failed: {}", tx_hash, e,
+        );
+        return Err(TxVerifyFailed::InvalidZkProof.into())
+    }
+    debug!(target: "validator::verification::verify_transaction", "ZK proof verification successful");
+
+    // Append hash to merkle tree
+    append_tx_to_merkle_tree(tree, tx);
+
+    debug!(target: "validator::verification::verify_transaction", "The total gas used for transaction {}: {}", tx_hash, gas_data.total_gas_used());
+    debug!(target: "validator::verification::verify_transaction", "Transaction {} verified successfully", tx_hash);
+    Ok(gas_data)
+}
+
+/// Data produced by the state verification of a [`Transaction`].
+struct TxStateData {
+    /// Gas used by the transaction
+    gas_data: GasData,
+    /// Public inputs table of the transaction ZK proofs
+    zkp_table: Vec<Vec<(String, Vec<pallas::Base>)>>,
+    /// Gas charged for each ZK circuit whose verifying key was loaded
+    circuits_gas: Vec<([u8; 32], String, u64)>,
+    /// Public keys of the transaction signatures
+    sig_table: Vec<Vec<PublicKey>>,
+}
+
+/// Verify WASM execution for a given [`Transaction`], and apply it to the provided
+/// overlay. Signatures and ZK proofs are not verified here, instead the transaction
+/// signature and public inputs tables are returned along with its gas data, so the
+/// caller can verify them on their own or batched along with other transactions.
+/// If an [`AccessSetPtr`] is provided, all the contract state accesses of the
+/// transaction are recorded in it.
+async fn verify_transaction_state(
+    overlay: &BlockchainOverlayPtr,
+    verifying_block_height: u32,
+    block_target: u32,
+    tx: &Transaction,
+    verifying_keys: &mut HashMap<[u8; 32], HashMap<String, VerifyingKey>>,
+    verify_fee: bool,
+    access_set: Option<&AccessSetPtr>,
+) -> Result<TxStateData> {
+    let tx_hash = tx.hash();
+
     // Create a FeeData instance to hold the calculated fee data
     let mut gas_data = GasData::default();
 
@@ -558,6 +623,16 @@ pub async fn verify_transaction(
         dark_forest_leaf_vec_integrity_check(&tx.calls, Some(MIN_TX_CALLS), Some(MAX_TX_CALLS))?;
     }
 
+    // Verify transaction has not expired
+    if tx.is_expired(verifying_block_height) {
+        error!(
+            target: "validator::verification::verify_transaction",
+            "[VALIDATOR] Transaction {} expired at block height {}, verifying height: {}",
+            tx_hash, tx.expiry.unwrap(), verifying_block_height,
+        );
+        return Err(TxVerifyFailed::Expired(tx.expiry.unwrap()).into())
+    }
+
     // Table of public inputs used for ZK proof verification
     let mut zkp_table = vec![];
     // Table of public keys used for signature verification
@@ -615,6 +690,9 @@ pub async fn verify_transaction(
             tx_hash,
             idx as u8,
         )?;
+        if let Some(access_set) = access_set {
+            runtime.track_access(access_set.clone());
+        }
 
         debug!(target: "validator::verification::verify_transaction", "Executing \"metadata\" call");
         let metadata = runtime.metadata(&payload)?;
@@ -654,7 +732,7 @@ pub async fn verify_transaction(
                 overlay.lock().unwrap().contracts.get_zkas(&call.data.contract_id, zkas_ns)?;
 
             inner_vk_map.insert(zkas_ns.to_string(), vk);
-            circuits_to_verify.push(zkbin);
+            circuits_to_verify.push((call.data.contract_id.to_bytes(), zkbin));
         }
 
         zkp_table.push(zkp_pub);
@@ -691,6 +769,9 @@ pub async fn verify_transaction(
                 tx_hash,
                 idx as u8,
             )?;
+            if let Some(access_set) = access_set {
+                deploy_runtime.track_access(access_set.clone());
+            }
 
             deploy_runtime.deploy(&deploy_params.ix)?;
 
@@ -714,12 +795,14 @@ pub async fn verify_transaction(
     debug!(target: "validator::verification::verify_transaction", "The gas used for signature of transaction {}: {}", tx_hash, gas_data.signatures);
 
     // The ZK circuit fee is calculated using a function in validator/fees.rs
-    for zkbin in circuits_to_verify.iter() {
+    let mut circuits_gas = Vec::with_capacity(circuits_to_verify.len());
+    for (contract_id, zkbin) in circuits_to_verify.iter() {
         let zk_circuit_gas_used = circuit_gas_use(zkbin);
         debug!(target: "validator::verification::verify_transaction", "The gas used for ZK circuit in namespace {} of transaction {}: {}", zkbin.namespace, tx_hash, zk_circuit_gas_used);
 
         // Append the used zk circuit gas
         gas_data.zk_circuits += zk_circuit_gas_used;
+        circuits_gas.push((*contract_id, zkbin.namespace.clone(), zk_circuit_gas_used));
     }
 
     // Store the calculated total gas used to avoid recalculating it for subsequent uses
@@ -757,10 +840,9 @@ pub async fn verify_transaction(
     }
 
     // When we're done looping and executing over the tx's contract calls and
-    // (optionally) made sure that enough fee was paid, we now move on with
-    // verification. First we verify the transaction signatures and then we
-    // verify any accompanying ZK proofs.
-    debug!(target: "validator::verification::verify_transaction", "Verifying signatures for transaction {}", tx_hash);
+    // (optionally) made sure that enough fee was paid, we check that all the
+    // transaction signatures are present. The signatures themselves, along
+    // with any accompanying ZK proofs, are verified by the caller.
     if sig_table.len() != tx.signatures.len() {
         error!(
             target: "validator::verification::verify_transaction",
@@ -769,6 +851,14 @@ pub async fn verify_transaction(
         return Err(TxVerifyFailed::MissingSignatures.into())
     }
 
+    Ok(TxStateData { gas_data, zkp_table, circuits_gas, sig_table })
+}
+
+/// Verify the signatures of a given [`Transaction`], against the public keys
+/// table produced by its state verification.
+fn verify_transaction_sigs(tx: &Transaction, sig_table: Vec<Vec<PublicKey>>) -> Result<()> {
+    let tx_hash = tx.hash();
+    debug!(target: "validator::verification::verify_transaction", "Verifying signatures for transaction {}", tx_hash);
     if let Err(e) = tx.verify_sigs(sig_table) {
         error!(
             target: "validator::verification::verify_transaction",
@@ -778,22 +868,7 @@ pub async fn verify_transaction(
     }
     debug!(target: "validator::verification::verify_transaction", "Signature verification successful");
 
-    debug!(target: "validator::verification::verify_transaction", "Verifying ZK proofs for transaction {}", tx_hash);
-    if let Err(e) = tx.verify_zkps(verifying_keys, zkp_table).await {
-        error!(
-            target: "validator::verification::verify_transaction",
-            "[VALIDATOR] ZK proof verification for tx {} failed: {}", tx_hash, e,
-        );
-        return Err(TxVerifyFailed::InvalidZkProof.into())
-    }
-    debug!(target: "validator::verification::verify_transaction", "ZK proof verification successful");
-
-    // Append hash to merkle tree
-    append_tx_to_merkle_tree(tree, tx);
-
-    debug!(target: "validator::verification::verify_transaction", "The total gas used for transaction {}: {}", tx_hash, total_gas_used);
-    debug!(target: "validator::verification::verify_transaction", "Transaction {} verified successfully", tx_hash);
-    Ok(gas_data)
+    Ok(())
 }
 
 /// Apply given [`Transaction`] to the provided overlay.
@@ -876,6 +951,12 @@ async fn apply_transaction(
 /// If all transactions are valid, the function will return the total gas used and total
 /// paid fees from all the transactions. Additionally, their hash is appended to the provided
 /// Merkle tree.
+///
+/// ZK proofs of all the transactions are verified after their state transitions, in
+/// batches grouped by their verifying key. If a batch fails, its proofs are verified
+/// one by one to find the erroneous transactions. Since the transactions following them
+/// were verified on top of their state, the overlay and Merkle tree are then reverted
+/// and the rest of the transactions are verified again without them.
 pub async fn verify_transactions(
     overlay: &BlockchainOverlayPtr,
     verifying_block_height: u32,
@@ -889,9 +970,274 @@ pub async fn verify_transactions(
         return Ok((0, 0))
     }
 
+    // Keep the initial state, to revert to it if any ZK proofs are invalid
+    let initial_overlay = overlay.lock().unwrap().overlay.lock().unwrap().clone();
+    let initial_tree = tree.clone();
+
+    // Indexes of the transactions containing invalid ZK proofs
+    let mut invalid_zkps = vec![];
+
+    loop {
+        // Indexes of failed txs
+        let mut erroneous = vec![];
+
+        // Total gas accumulators
+        let mut total_gas_used = 0;
+        let mut total_gas_paid = 0;
+
+        // Map of ZK proof verifying keys for the current transaction batch
+        let mut vks: HashMap<[u8; 32], HashMap<String, VerifyingKey>> = HashMap::new();
+
+        // Initialize the map
+        for tx in txs {
+            for call in &tx.calls {
+                vks.insert(call.data.contract_id.to_bytes(), HashMap::new());
+            }
+        }
+
+        // ZK proofs of the verified transactions, to batch verify them
+        let mut zk_batch = ZkProofBatch::default();
+
+        // Iterate over transactions and attempt to verify them
+        for (index, tx) in txs.iter().enumerate() {
+            if invalid_zkps.contains(&index) {
+                continue
+            }
+
+            overlay.lock().unwrap().checkpoint();
+            let TxStateData { gas_data, zkp_table, sig_table, .. } = match verify_transaction_state(
+                overlay,
+                verifying_block_height,
+                block_target,
+                tx,
+                &mut vks,
+                verify_fees,
+                None,
+            )
+            .await
+            {
+                Ok(result) => result,
+                Err(e) => {
+                    warn!(target: "validator::verification::verify_transactions", "Transaction verification failed: {}", e);
+                    erroneous.push(index);
+                    overlay.lock().unwrap().revert_to_checkpoint()?;
+                    continue
+                }
+            };
+
+            if let Err(e) = verify_transaction_sigs(tx, sig_table) {
+                warn!(target: "validator::verification::verify_transactions", "Transaction verification failed: {}", e);
+                erroneous.push(index);
+                overlay.lock().unwrap().revert_to_checkpoint()?;
+                continue
+            }
+
+            // Store the gas used by the verified transaction
+            let tx_gas_used = gas_data.total_gas_used();
+
+            // Calculate current accumulated gas usage
+            let accumulated_gas_usage = total_gas_used + tx_gas_used;
+
+            // Check gas limit - if accumulated gas used exceeds it, break out of loop
+            if accumulated_gas_usage > GAS_LIMIT_UNPROPOSED_TXS {
+                warn!(target: "validator::verification::verify_transactions", "Transaction {} exceeds configured transaction gas limit: {} - {}", tx.hash(), accumulated_gas_usage, GAS_LIMIT_UNPROPOSED_TXS);
+                erroneous.push(index);
+                overlay.lock().unwrap().revert_to_checkpoint()?;
+                break
+            }
+
+            // Add the transaction ZK proofs to the batch
+            if let Err(e) = tx.batch_zkps(index, &vks, zkp_table, &mut zk_batch) {
+                warn!(target: "validator::verification::verify_transactions", "Transaction ZK proofs batching failed: {}", e);
+                erroneous.push(index);
+                overlay.lock().unwrap().revert_to_checkpoint()?;
+                continue
+            }
+
+            // Append hash to merkle tree
+            append_tx_to_merkle_tree(tree, tx);
+
+            // Update accumulated total gas
+            total_gas_used += tx_gas_used;
+            total_gas_paid += gas_data.paid;
+        }
+
+        // Verify all the batched ZK proofs
+        debug!(target: "validator::verification::verify_transactions", "Verifying ZK proofs for {} transactions", txs.len());
+        let failed = zk_batch.verify(&vks);
+        if failed.is_empty() {
+            // Return the failed txs in their original order
+            erroneous.extend(invalid_zkps);
+            if !erroneous.is_empty() {
+                erroneous.sort_unstable();
+                let erroneous_txs = erroneous.into_iter().map(|index| txs[index].clone()).collect();
+                return Err(TxVerifyFailed::ErroneousTxs(erroneous_txs).into())
+            }
+
+            return Ok((total_gas_used, total_gas_paid))
+        }
+
+        for index in failed {
+            warn!(target: "validator::verification::verify_transactions", "Transaction {} contains invalid ZK proofs", txs[index].hash());
+            invalid_zkps.push(index);
+        }
+
+        // Revert everything and verify the rest of the transactions again
+        debug!(target: "validator::verification::verify_transactions", "Reverting state to verify transactions without invalid ZK proofs");
+        *overlay.lock().unwrap().overlay.lock().unwrap() = initial_overlay.clone();
+        *tree = initial_tree.clone();
+    }
+}
+
+/// Outcome of a speculative [`Transaction`] verification, over its own overlay clone.
+struct SpeculativeTx {
+    /// State verification data, if the transaction was valid over the base overlay
+    data: Option<TxStateData>,
+    /// Whether the transaction ZK proofs are valid
+    zk_valid: bool,
+    /// Contract state accesses of the transaction
+    access_set: AccessSet,
+    /// Verifying keys loaded by the transaction
+    verifying_keys: HashMap<[u8; 32], HashMap<String, VerifyingKey>>,
+}
+
+/// Verify WASM execution, signatures, and ZK proofs for a given [`Transaction`]
+/// over a clone of the provided overlay, recording its contract state accesses.
+/// The provided overlay is not modified.
+async fn speculate_transaction(
+    overlay: &BlockchainOverlayPtr,
+    verifying_block_height: u32,
+    block_target: u32,
+    tx: &Transaction,
+    verify_fee: bool,
+) -> Result<SpeculativeTx> {
+    let overlay = overlay.lock().unwrap().full_clone()?;
+    let access_set = AccessSet::new_ptr();
+
+    // Each speculation uses its own map, so we know which keys it loaded
+    let mut verifying_keys = HashMap::new();
+    for call in &tx.calls {
+        verifying_keys.insert(call.data.contract_id.to_bytes(), HashMap::new());
+    }
+
+    let data = verify_transaction_state(
+        &overlay,
+        verifying_block_height,
+        block_target,
+        tx,
+        &mut verifying_keys,
+        verify_fee,
+        Some(&access_set),
+    )
+    .await;
+
+    // Signatures are verified here too, so they don't have to be verified again
+    let data = data.and_then(|data| {
+        verify_transaction_sigs(tx, data.sig_table.clone())?;
+        Ok(data)
+    });
+
+    let (data, zk_valid) = match data {
+        Ok(data) => {
+            let mut zk_batch = ZkProofBatch::default();
+            let zk_valid =
+                tx.batch_zkps(0, &verifying_keys, data.zkp_table.clone(), &mut zk_batch).is_ok() &&
+                    zk_batch.verify(&verifying_keys).is_empty();
+            (Some(data), zk_valid)
+        }
+        Err(e) => {
+            debug!(target: "validator::verification::speculate_transaction", "Speculative transaction verification failed: {}", e);
+            (None, false)
+        }
+    };
+
+    let access_set = access_set.lock().unwrap().clone();
+    Ok(SpeculativeTx { data, zk_valid, access_set, verifying_keys })
+}
+
+/// Verify a set of [`Transaction`] in parallel and apply them if all are valid.
+/// The outcome is the same as the one of [`verify_transactions`].
+///
+/// Each transaction is first verified speculatively over its own clone of the
+/// provided overlay, across all available threads, recording the contract state
+/// keys it read and wrote. Then, in the original order, the speculative writes
+/// of each transaction are replayed into the provided overlay, if none of the
+/// keys it read were written by the transactions applied before it. Otherwise,
+/// the transaction is re-verified over the provided overlay, like serial execution
+/// would do. Contract deployments are always verified serially, and since they
+/// can change the contracts code, all transactions after them are too. If any
+/// ZK proofs are invalid, the state is reverted and [`verify_transactions`] is
+/// used instead.
+///
+/// Transactions paying fees all append to the Money Merkle tree, so they conflict
+/// with each other and their state transitions are executed again serially. Their
+/// signatures and ZK proofs don't depend on the state though, so the speculative
+/// verification of them is kept, as long as the serial execution produced the same
+/// signature and public inputs tables.
+pub async fn verify_transactions_parallel(
+    overlay: &BlockchainOverlayPtr,
+    verifying_block_height: u32,
+    block_target: u32,
+    txs: &[Transaction],
+    tree: &mut MerkleTree,
+    verify_fees: bool,
+) -> Result<(u64, u64)> {
+    debug!(target: "validator::verification::verify_transactions_parallel", "Verifying {} transactions", txs.len());
+    if txs.is_empty() {
+        return Ok((0, 0))
+    }
+
+    // Keep the initial state, to revert to it if any ZK proofs are invalid
+    let initial_overlay = overlay.lock().unwrap().overlay.lock().unwrap().clone();
+    let initial_tree = tree.clone();
+
+    // Speculatively verify the transactions, splitting them across threads
+    let n_threads = match std::thread::available_parallelism() {
+        Ok(n) => n.get().min(txs.len()),
+        Err(_) => 1,
+    };
+    let mut speculations: Vec<Option<SpeculativeTx>> = txs.iter().map(|_| None).collect();
+    std::thread::scope(|scope| {
+        let mut handles = Vec::with_capacity(n_threads);
+        for thread in 0..n_threads {
+            handles.push(scope.spawn(move || {
+                let mut ret = vec![];
+                for index in (thread..txs.len()).step_by(n_threads) {
+                    let tx = &txs[index];
+                    if tx.calls.iter().any(|call| call.data.is_deployment()) {
+                        continue
+                    }
+
+                    match smol::block_on(speculate_transaction(
+                        overlay,
+                        verifying_block_height,
+                        block_target,
+                        tx,
+                        verify_fees,
+                    )) {
+                        Ok(speculation) => ret.push((index, speculation)),
+                        Err(e) => {
+                            warn!(target: "validator::verification::verify_transactions_parallel", "Speculative verification of transaction {} failed: {}", tx.hash(), e);
+                        }
+                    }
+                }
+                ret
+            }));
+        }
+
+        for handle in handles {
+            for (index, speculation) in handle.join().unwrap() {
+                speculations[index] = Some(speculation);
+            }
+        }
+    });
+
     // Tracker for failed txs
     let mut erroneous_txs = vec![];
 
+    // Indexes of speculatively applied txs with invalid ZK proofs
+    let mut invalid_zk_txs = vec![];
+
     // Total gas accumulators
     let mut total_gas_used = 0;
     let mut total_gas_paid = 0;
@@ -906,23 +1252,95 @@ pub async fn verify_transactions(
         }
     }
 
-    // Iterate over transactions and attempt to verify them
-    for tx in txs {
+    // ZK proofs of the serially verified transactions, to batch verify them
+    let mut zk_batch = ZkProofBatch::default();
+
+    // Contract state writes of all the applied transactions
+    let mut applied = AccessSet::default();
+
+    // Flag marking that a transaction which can't be replayed was applied
+    let mut serial_only = false;
+
+    // Iterate over transactions and merge their speculations, in order
+    for (index, tx) in txs.iter().enumerate() {
+        // Use the speculation, if it is still valid after the previous transactions
+        let speculation = speculations[index].take();
+        let replayable = speculation.as_ref().is_some_and(|speculation| {
+            !serial_only &&
+                speculation.data.is_some() &&
+                speculation.access_set.is_replayable() &&
+                !speculation.access_set.conflicts_with(&applied)
+        });
+
+        if replayable {
+            let speculation = speculation.unwrap();
+            let mut data = speculation.data.unwrap();
+
+            // Circuits are only charged to the first transaction loading their keys
+            for (contract_id, zkas_ns, gas) in &data.circuits_gas {
+                if vks[contract_id].contains_key(zkas_ns) {
+                    data.gas_data.zk_circuits -= gas;
+                }
+            }
+
+            // Store the gas used by the verified transaction
+            let tx_gas_used = data.gas_data.total_gas_used();
+
+            // Calculate current accumulated gas usage
+            let accumulated_gas_usage = total_gas_used + tx_gas_used;
+
+            // Check gas limit - if accumulated gas used exceeds it, break out of loop
+            if accumulated_gas_usage > GAS_LIMIT_UNPROPOSED_TXS {
+                warn!(target: "validator::verification::verify_transactions_parallel", "Transaction {} exceeds configured transaction gas limit: {} - {}", tx.hash(), accumulated_gas_usage, GAS_LIMIT_UNPROPOSED_TXS);
+                erroneous_txs.push(tx.clone());
+                break
+            }
+
+            // Keep the verifying keys loaded first
+            for (contract_id, keys) in speculation.verifying_keys {
+                let inner_vk_map = vks.get_mut(&contract_id).unwrap();
+                for (zkas_ns, vk) in keys {
+                    inner_vk_map.entry(zkas_ns).or_insert(vk);
+                }
+            }
+
+            // Apply the transaction state writes
+            let lock = overlay.lock().unwrap();
+            speculation.access_set.replay(&mut lock.overlay.lock().unwrap())?;
+            drop(lock);
+            applied.merge(&speculation.access_set);
+
+            if !speculation.zk_valid {
+                invalid_zk_txs.push(index);
+            }
+
+            // Append hash to merkle tree
+            append_tx_to_merkle_tree(tree, tx);
+
+            // Update accumulated total gas
+            total_gas_used += tx_gas_used;
+            total_gas_paid += data.gas_data.paid;
+            continue
+        }
+
+        // Execute the transaction over the merged state
+        debug!(target: "validator::verification::verify_transactions_parallel", "Verifying transaction {} serially", tx.hash());
+        let access_set = AccessSet::new_ptr();
         overlay.lock().unwrap().checkpoint();
-        let gas_data = match verify_transaction(
+        let TxStateData { gas_data, zkp_table, sig_table, .. } = match verify_transaction_state(
             overlay,
             verifying_block_height,
             block_target,
             tx,
-            tree,
             &mut vks,
             verify_fees,
+            Some(&access_set),
         )
         .await
         {
-            Ok(gas_values) => gas_values,
+            Ok(result) => result,
             Err(e) => {
-                warn!(target: "validator::verification::verify_transactions", "Transaction verification failed: {}", e);
+                warn!(target: "validator::verification::verify_transactions_parallel", "Transaction verification failed: {}", e);
                 erroneous_txs.push(tx.clone());
                 overlay.lock().unwrap().revert_to_checkpoint()?;
                 continue
@@ -937,17 +1355,78 @@ pub async fn verify_transactions(
 
         // Check gas limit - if accumulated gas used exceeds it, break out of loop
         if accumulated_gas_usage > GAS_LIMIT_UNPROPOSED_TXS {
-            warn!(target: "validator::verification::verify_transactions", "Transaction {} exceeds configured transaction gas limit: {} - {}", tx.hash(), accumulated_gas_usage, GAS_LIMIT_UNPROPOSED_TXS);
+            warn!(target: "validator::verification::verify_transactions_parallel", "Transaction {} exceeds configured transaction gas limit: {} - {}", tx.hash(), accumulated_gas_usage, GAS_LIMIT_UNPROPOSED_TXS);
             erroneous_txs.push(tx.clone());
             overlay.lock().unwrap().revert_to_checkpoint()?;
             break
         }
 
+        // Signatures and ZK proofs don't depend on the state, so if the execution
+        // produced the same tables as the speculation, their verification is reused.
+        // This way the conflicting transactions, like the ones paying fees, only
+        // execute their state transitions serially. After a transaction that can't
+        // be replayed, the verifying keys might have changed, so nothing is reused.
+        let verified = speculation
+            .filter(|_| !serial_only)
+            .and_then(|speculation| speculation.data.map(|data| (data, speculation.zk_valid)));
+        let sigs_verified = verified.as_ref().is_some_and(|(data, _)| data.sig_table == sig_table);
+        if !sigs_verified {
+            if let Err(e) = verify_transaction_sigs(tx, sig_table) {
+                warn!(target: "validator::verification::verify_transactions_parallel", "Transaction verification failed: {}", e);
+                erroneous_txs.push(tx.clone());
+                overlay.lock().unwrap().revert_to_checkpoint()?;
+                continue
+            }
+        }
+
+        let zkps_verified = verified
+            .as_ref()
+            .is_some_and(|(data, zk_valid)| *zk_valid && data.zkp_table == zkp_table);
+        if !zkps_verified {
+            // Add the transaction ZK proofs to the batch
+            if let Err(e) = tx.batch_zkps(index, &vks, zkp_table, &mut zk_batch) {
+                warn!(target: "validator::verification::verify_transactions_parallel", "Transaction ZK proofs batching failed: {}", e);
+                erroneous_txs.push(tx.clone());
+                overlay.lock().unwrap().revert_to_checkpoint()?;
+                continue
+            }
+        }
+
+        // Keep track of the transaction state writes
+        let access_set = access_set.lock().unwrap();
+        if !access_set.is_replayable() || tx.calls.iter().any(|call| call.data.is_deployment()) {
+            serial_only = true;
+        }
+        applied.merge(&access_set);
+
+        // Append hash to merkle tree
+        append_tx_to_merkle_tree(tree, tx);
+
         // Update accumulated total gas
         total_gas_used += tx_gas_used;
         total_gas_paid += gas_data.paid;
     }
 
+    // Verify all the batched ZK proofs
+    debug!(target: "validator::verification::verify_transactions_parallel", "Verifying ZK proofs for {} transactions", txs.len());
+    invalid_zk_txs.extend(zk_batch.verify(&vks));
+    if !invalid_zk_txs.is_empty() {
+        // The transactions after the invalid ones were verified on top of their
+        // state, so we revert everything and let the serial path handle them.
+        warn!(target: "validator::verification::verify_transactions_parallel", "Found {} transactions with invalid ZK proofs, verifying serially", invalid_zk_txs.len());
+        *overlay.lock().unwrap().overlay.lock().unwrap() = initial_overlay;
+        *tree = initial_tree;
+        return verify_transactions(
+            overlay,
+            verifying_block_height,
+            block_target,
+            txs,
+            tree,
+            verify_fees,
+        )
+        .await
+    }
+
     if !erroneous_txs.is_empty() {
         return Err(TxVerifyFailed::ErroneousTxs(erroneous_txs).into())
     }
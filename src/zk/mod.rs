@@ -31,6 +31,14 @@ pub mod gadget;
 pub mod proof;
 pub use proof::{Proof, ProvingKey, VerifyingKey};
 
+/// Gas cost of zk circuits
+pub mod gas;
+pub use gas::circuit_gas_use;
+
+/// Circuit cost profiling
+pub mod profile;
+pub use profile::{CircuitProfile, ProvingCost};
+
 /// Trace computation of intermediate values in circuit
 mod tracer;
 pub use tracer::DebugOpValue;
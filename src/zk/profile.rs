@@ -0,0 +1,470 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2025 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Layout and proving cost of zkas circuits.
+//!
+//! The circuit is synthesized into a recording [`Assignment`] which
+//! keeps track of the rows and columns every region touches. Regions
+//! are attributed to the statement namespace the VM was in when the
+//! region was entered.
+
+use std::{
+    collections::HashSet,
+    fs::read_to_string,
+    time::{Duration, Instant},
+};
+
+use darkfi_sdk::{
+    crypto::{constants::MERKLE_DEPTH_ORCHARD, pasta_prelude::*, smt::SMT_FP_DEPTH, MerkleNode},
+    pasta::pallas,
+};
+use halo2_proofs::{
+    circuit::Value,
+    plonk::{
+        self, Advice, Any, Assigned, Assignment, Circuit, Column, ConstraintSystem, Fixed,
+        FloorPlanner, Instance, Selector,
+    },
+};
+use rand::{rngs::OsRng, RngCore};
+
+use super::{circuit_gas_use, Proof, ProvingKey, Witness, ZkCircuit};
+use crate::{
+    zkas::{Opcode, VarType, ZkBinary},
+    Error::{CircuitKTooSmall, ZkasDecoderError},
+    Result,
+};
+
+/// Prefix of the namespace the VM enters for every statement
+const STATEMENT_NAMESPACE: &str = "zkvm statement ";
+
+/// Namespace the VM enters while executing the statement at `index`
+pub(super) fn statement_namespace(index: usize) -> String {
+    format!("{}{}", STATEMENT_NAMESPACE, index)
+}
+
+/// Layout cost of a single circuit statement
+#[derive(Clone, Debug)]
+pub struct StatementCost {
+    /// Opcode executed by the statement
+    pub opcode: Opcode,
+    /// Number of regions assigned by the statement
+    pub regions: usize,
+    /// Sum of the heights of the statement's regions. The floor planner
+    /// may place regions side by side, so these don't add up to the
+    /// rows of the circuit.
+    pub rows: usize,
+    /// Number of distinct advice and fixed columns used by the statement
+    pub columns: usize,
+}
+
+/// Layout cost of a zkas circuit
+#[derive(Clone, Debug)]
+pub struct CircuitProfile {
+    /// Cost of every statement, in order
+    pub statements: Vec<StatementCost>,
+    /// Sum of the heights of the regions loading constants, literals,
+    /// and witnesses
+    pub setup_rows: usize,
+    /// Rows used by the whole layout
+    pub rows: usize,
+    /// Rows halo2 reserves for blinding the witness polynomials
+    pub blinding_rows: usize,
+    /// Smallest `k` the circuit fits in
+    pub k: u32,
+    /// Gas used for verifying the circuit
+    pub gas: u64,
+}
+
+impl CircuitProfile {
+    /// Synthesize the circuit of the given zkas binary and measure its layout.
+    /// The declared `k` of the binary is not used.
+    pub fn new(zkbin: &ZkBinary) -> Result<Self> {
+        let circuit = ZkCircuit::new(super::empty_witnesses(zkbin)?, zkbin);
+
+        let mut cs = ConstraintSystem::default();
+        let config = ZkCircuit::configure_with_params(&mut cs, circuit.params());
+
+        let mut recorder = Recorder::default();
+        <ZkCircuit as Circuit<pallas::Base>>::FloorPlanner::synthesize(
+            &mut recorder,
+            &circuit,
+            config,
+            cs.constants().clone(),
+        )?;
+
+        let mut statements: Vec<StatementCost> = zkbin
+            .opcodes
+            .iter()
+            .map(|(opcode, _)| StatementCost { opcode: *opcode, regions: 0, rows: 0, columns: 0 })
+            .collect();
+
+        let mut columns = vec![HashSet::new(); statements.len()];
+        let mut setup_rows = 0;
+        for region in &recorder.regions {
+            let height = region.rows.map_or(0, |(start, end)| end - start + 1);
+            let Some(index) = region.statement else {
+                setup_rows += height;
+                continue
+            };
+
+            statements[index].regions += 1;
+            statements[index].rows += height;
+            columns[index].extend(region.columns.iter().copied());
+        }
+
+        for (statement, columns) in statements.iter_mut().zip(columns) {
+            statement.columns = columns.len();
+        }
+
+        // Assignments must leave the blinding rows and the last row free,
+        // and halo2 requires a minimum number of rows regardless.
+        let blinding_rows = cs.blinding_factors() + 1;
+        let needed = (recorder.rows + blinding_rows).max(cs.minimum_rows());
+        let k = needed.next_power_of_two().trailing_zeros();
+
+        Ok(Self {
+            statements,
+            setup_rows,
+            rows: recorder.rows,
+            blinding_rows,
+            k,
+            gas: circuit_gas_use(zkbin),
+        })
+    }
+
+    /// Check that a circuit declaring the given `k` fits in its rows.
+    pub fn check_k(&self, k: u32) -> Result<()> {
+        if k < self.k {
+            return Err(CircuitKTooSmall(k, self.k))
+        }
+
+        Ok(())
+    }
+}
+
+/// Proving cost of a zkas circuit at a given `k`
+#[derive(Clone, Debug)]
+pub struct ProvingCost {
+    /// Time spent generating the proving key
+    pub keygen: Duration,
+    /// Time spent creating the proof
+    pub proving: Duration,
+    /// Size of the proof in bytes
+    pub proof_size: usize,
+    /// Peak resident memory of the process in bytes, if available
+    pub peak_memory: Option<u64>,
+}
+
+impl ProvingCost {
+    /// Create a proof of the circuit with random witnesses and public inputs
+    /// and measure the cost. The resulting proof does not verify, as the
+    /// witnesses don't satisfy the circuit, but the work done is the same.
+    /// `k` must be at least [`CircuitProfile::k`].
+    pub fn measure(zkbin: &ZkBinary, k: u32) -> Result<Self> {
+        let circuit = ZkCircuit::new(random_witnesses(zkbin)?, zkbin);
+
+        let public_inputs: Vec<pallas::Base> = zkbin
+            .opcodes
+            .iter()
+            .filter(|(opcode, _)| *opcode == Opcode::ConstrainInstance)
+            .map(|_| pallas::Base::random(&mut OsRng))
+            .collect();
+
+        let now = Instant::now();
+        let proving_key = ProvingKey::build(k, &circuit);
+        let keygen = now.elapsed();
+
+        let now = Instant::now();
+        let proof = Proof::create(&proving_key, &[circuit], &public_inputs, &mut OsRng)?;
+        let proving = now.elapsed();
+
+        Ok(Self { keygen, proving, proof_size: proof.as_ref().len(), peak_memory: peak_memory() })
+    }
+}
+
+/// Generate random witnesses for a given decoded zkas binary
+fn random_witnesses(zkbin: &ZkBinary) -> Result<Vec<Witness>> {
+    let mut ret = Vec::with_capacity(zkbin.witnesses.len());
+
+    for witness in &zkbin.witnesses {
+        let point = || Value::known(pallas::Point::random(&mut OsRng));
+        match witness {
+            VarType::EcPoint => ret.push(Witness::EcPoint(point())),
+            VarType::EcNiPoint => ret.push(Witness::EcNiPoint(point())),
+            VarType::EcFixedPoint => ret.push(Witness::EcFixedPoint(point())),
+            VarType::Base => {
+                ret.push(Witness::Base(Value::known(pallas::Base::random(&mut OsRng))))
+            }
+            VarType::Scalar => {
+                ret.push(Witness::Scalar(Value::known(pallas::Scalar::random(&mut OsRng))))
+            }
+            VarType::MerklePath => {
+                let path: [MerkleNode; MERKLE_DEPTH_ORCHARD] =
+                    std::array::from_fn(|_| MerkleNode::from(pallas::Base::random(&mut OsRng)));
+                ret.push(Witness::MerklePath(Value::known(path)))
+            }
+            VarType::SparseMerklePath => {
+                let path: [pallas::Base; SMT_FP_DEPTH] =
+                    std::array::from_fn(|_| pallas::Base::random(&mut OsRng));
+                ret.push(Witness::SparseMerklePath(Value::known(path)))
+            }
+            VarType::Uint32 => ret.push(Witness::Uint32(Value::known(OsRng.next_u32()))),
+            VarType::Uint64 => ret.push(Witness::Uint64(Value::known(OsRng.next_u64()))),
+            x => return Err(ZkasDecoderError(format!("Unsupported witness type: {:?}", x))),
+        }
+    }
+
+    Ok(ret)
+}
+
+/// Peak resident memory of the process in bytes. Only available on Linux.
+fn peak_memory() -> Option<u64> {
+    let status = read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kib * 1024)
+}
+
+/// Region assigned during synthesis
+#[derive(Default)]
+struct Region {
+    /// Index of the statement the region belongs to, if any
+    statement: Option<usize>,
+    /// Advice and fixed columns the region assigned to
+    columns: HashSet<Column<Any>>,
+    /// First and last row the region assigned to
+    rows: Option<(usize, usize)>,
+}
+
+/// [`Assignment`] recording the layout of a circuit without
+/// evaluating any values
+#[derive(Default)]
+struct Recorder {
+    regions: Vec<Region>,
+    /// Region currently being assigned
+    current: Option<usize>,
+    namespaces: Vec<String>,
+    /// Number of rows used, up to the last assigned one
+    rows: usize,
+}
+
+impl Recorder {
+    /// Index of the statement found in the current namespace stack
+    fn statement(&self) -> Option<usize> {
+        self.namespaces.iter().find_map(|ns| ns.strip_prefix(STATEMENT_NAMESPACE)?.parse().ok())
+    }
+
+    fn touch(&mut self, column: Option<Column<Any>>, row: usize) {
+        self.rows = self.rows.max(row + 1);
+
+        let Some(current) = self.current else { return };
+        let region = &mut self.regions[current];
+        if let Some(column) = column {
+            region.columns.insert(column);
+        }
+        region.rows = match region.rows {
+            Some((start, end)) => Some((start.min(row), end.max(row))),
+            None => Some((row, row)),
+        };
+    }
+}
+
+impl Assignment<pallas::Base> for Recorder {
+    fn enter_region<NR, N>(&mut self, _name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        let statement = self.statement();
+        self.regions.push(Region { statement, ..Default::default() });
+        self.current = Some(self.regions.len() - 1);
+    }
+
+    fn exit_region(&mut self) {
+        self.current = None;
+    }
+
+    fn enable_selector<A, AR>(
+        &mut self,
+        _annotation: A,
+        _selector: &Selector,
+        row: usize,
+    ) -> std::result::Result<(), plonk::Error>
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.touch(None, row);
+        Ok(())
+    }
+
+    fn query_instance(
+        &self,
+        _column: Column<Instance>,
+        _row: usize,
+    ) -> std::result::Result<Value<pallas::Base>, plonk::Error> {
+        Ok(Value::unknown())
+    }
+
+    fn assign_advice<V, VR, A, AR>(
+        &mut self,
+        _annotation: A,
+        column: Column<Advice>,
+        row: usize,
+        _to: V,
+    ) -> std::result::Result<(), plonk::Error>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<pallas::Base>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.touch(Some(column.into()), row);
+        Ok(())
+    }
+
+    fn assign_fixed<V, VR, A, AR>(
+        &mut self,
+        _annotation: A,
+        column: Column<Fixed>,
+        row: usize,
+        _to: V,
+    ) -> std::result::Result<(), plonk::Error>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<pallas::Base>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.touch(Some(column.into()), row);
+        Ok(())
+    }
+
+    fn copy(
+        &mut self,
+        _left_column: Column<Any>,
+        _left_row: usize,
+        _right_column: Column<Any>,
+        _right_row: usize,
+    ) -> std::result::Result<(), plonk::Error> {
+        Ok(())
+    }
+
+    fn fill_from_row(
+        &mut self,
+        column: Column<Fixed>,
+        row: usize,
+        _to: Value<Assigned<pallas::Base>>,
+    ) -> std::result::Result<(), plonk::Error> {
+        // Table padding extends to the usable rows, whatever their number is
+        self.touch(Some(column.into()), row);
+        Ok(())
+    }
+
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        self.namespaces.push(name_fn().into());
+    }
+
+    fn pop_namespace(&mut self, _gadget_name: Option<String>) {
+        self.namespaces.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zkas::{Analyzer, Compiler, Lexer, Parser};
+    use halo2_proofs::dev::MockProver;
+
+    // The 64-bit range check loads a 2^10 row lookup table,
+    // so the circuit needs more than 2^10 rows.
+    const SOURCE: &str = r#"k = 11;
+field = "pallas";
+
+constant "Range" {}
+
+witness "Range" {
+    Base a,
+}
+
+circuit "Range" {
+    range_check(64, a);
+    constrain_instance(a);
+}
+"#;
+
+    fn compile(source: &str) -> Result<ZkBinary> {
+        let tokens = Lexer::new("range.zk", source.chars()).lex()?;
+        let (namespace, k, constants, witnesses, statements) =
+            Parser::new("range.zk", source.chars(), tokens).parse()?;
+
+        let mut analyzer =
+            Analyzer::new("range.zk", source.chars(), constants, witnesses, statements);
+        analyzer.analyze_types()?;
+
+        let bincode = Compiler::new(
+            "range.zk",
+            source.chars(),
+            namespace,
+            k,
+            analyzer.constants,
+            analyzer.witnesses,
+            analyzer.statements,
+            analyzer.literals,
+            false,
+        )
+        .compile()?;
+
+        ZkBinary::decode(&bincode)
+    }
+
+    #[test]
+    fn circuit_profile() -> Result<()> {
+        std::env::set_var("ZKAS_SILENT", "1");
+        let zkbin = compile(SOURCE)?;
+        let profile = CircuitProfile::new(&zkbin)?;
+
+        assert_eq!(profile.statements.len(), 2);
+        assert_eq!(profile.statements[0].opcode, Opcode::RangeCheck);
+        assert!(profile.statements[0].regions > 0);
+        // Constraining an instance doesn't assign any region
+        assert_eq!(profile.statements[1].opcode, Opcode::ConstrainInstance);
+        assert_eq!(profile.statements[1].regions, 0);
+        assert_eq!(profile.statements[1].rows, 0);
+
+        assert!(profile.rows > 1 << 10);
+        assert!(profile.rows + profile.blinding_rows <= 1 << 11);
+        assert_eq!(profile.k, 11);
+
+        // The circuit is satisfied at the smallest k, and doesn't fit below it
+        let a = pallas::Base::from(42);
+        let circuit = ZkCircuit::new(vec![Witness::Base(Value::known(a))], &zkbin);
+        MockProver::run(profile.k, &circuit, vec![vec![a]])?.assert_satisfied();
+        assert!(MockProver::run(profile.k - 1, &circuit, vec![vec![a]]).is_err());
+
+        assert!(profile.check_k(11).is_ok());
+        assert!(profile.check_k(12).is_ok());
+        assert!(matches!(profile.check_k(10), Err(CircuitKTooSmall(10, 11))));
+
+        Ok(())
+    }
+}
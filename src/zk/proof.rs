@@ -25,7 +25,7 @@ use darkfi_serial::{SerialDecodable, SerialEncodable};
 use halo2_proofs::{
     helpers::SerdeFormat,
     plonk,
-    plonk::{Circuit, SingleVerifier},
+    plonk::{BatchVerifier, Circuit, SingleVerifier},
     poly::commitment::Params,
     transcript::{Blake2bRead, Blake2bWrite},
 };
@@ -211,6 +211,21 @@ impl Proof {
         plonk::verify_proof(&vk.params, &vk.vk, strategy, &[&[instances]], &mut transcript)
     }
 
+    /// Verify a set of proofs sharing the same verifying key, using halo2 batch
+    /// verification. This is considerably cheaper than verifying each proof on
+    /// its own, but on failure it doesn't tell which of the proofs is invalid.
+    pub fn verify_batch<'a>(
+        vk: &VerifyingKey,
+        proofs: impl IntoIterator<Item = (&'a Proof, &'a [pallas::Base])>,
+    ) -> bool {
+        let mut batch = BatchVerifier::new();
+        for (proof, instances) in proofs {
+            batch.add_proof(vec![vec![instances.to_vec()]], proof.0.clone());
+        }
+
+        batch.finalize(&vk.params, &vk.vk)
+    }
+
     pub fn new(bytes: Vec<u8>) -> Self {
         Proof(bytes)
     }
@@ -67,6 +67,7 @@ use super::{
         smt,
         zero_cond::{ZeroCondChip, ZeroCondConfig},
     },
+    profile::statement_namespace,
     tracer::ZkTracer,
 };
 use crate::zkas::{
@@ -798,7 +799,9 @@ impl Circuit<pallas::Base> for ZkCircuit {
         self.tracer.clear();
         // TODO: Copy constraints
         // ANCHOR: opcode_begin
-        for opcode in &self.opcodes {
+        for (index, opcode) in self.opcodes.iter().enumerate() {
+            // Namespace each statement so the profiler can attribute regions
+            layouter.push_namespace(|| statement_namespace(index));
             match opcode.0 {
                 Opcode::EcAdd => {
                     trace!(target: "zk::vm", "Executing `EcAdd{:?}` opcode", opcode.1);
@@ -1269,6 +1272,7 @@ impl Circuit<pallas::Base> for ZkCircuit {
                     return Err(plonk::Error::Synthesis)
                 }
             }
+            layouter.pop_namespace(None);
         }
         self.tracer.assert_correct(self.opcodes.len());
 
@@ -16,17 +16,66 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
-use std::io::{self, Error, ErrorKind, Write};
+use std::{
+    cell::RefCell,
+    fmt,
+    io::{self, Error, ErrorKind, Write},
+};
+
+/// An error or warning emitted by the zkas toolchain, along with its
+/// position in the source. Lines and columns start at 1, and are 0
+/// when the position is unknown.
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    /// Toolchain stage that emitted the diagnostic
+    pub namespace: String,
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
+    /// Message as printed to stderr, pointing at the source
+    formatted: String,
+}
+
+impl Diagnostic {
+    /// Get the diagnostic of an error returned by the toolchain.
+    pub fn from_io(err: &Error) -> Option<&Self> {
+        err.get_ref()?.downcast_ref()
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.formatted)
+    }
+}
+
+impl std::error::Error for Diagnostic {}
 
 pub(super) struct ErrorEmitter {
     namespace: String,
     file: String,
     lines: Vec<String>,
+    warnings: RefCell<Vec<Diagnostic>>,
 }
 
 impl ErrorEmitter {
     pub fn new(namespace: &str, file: &str, lines: Vec<String>) -> Self {
-        Self { namespace: namespace.to_string(), file: file.to_string(), lines }
+        Self {
+            namespace: namespace.to_string(),
+            file: file.to_string(),
+            lines,
+            warnings: RefCell::new(vec![]),
+        }
+    }
+
+    fn diagnostic(&self, msg: &str, ln: usize, col: usize) -> Diagnostic {
+        Diagnostic {
+            namespace: self.namespace.clone(),
+            message: msg.to_string(),
+            line: ln,
+            column: col,
+            formatted: self.fmt(msg.to_string(), ln, col),
+        }
     }
 
     fn fmt(&self, msg: String, ln: usize, col: usize) -> String {
@@ -44,14 +93,20 @@ impl ErrorEmitter {
     }
 
     pub fn abort(&self, msg: &str, ln: usize, col: usize) -> Error {
-        let m = self.fmt(msg.to_string(), ln, col);
-        self.emit("error", &m);
-        Error::new(ErrorKind::Other, m)
+        let d = self.diagnostic(msg, ln, col);
+        self.emit("error", &d.formatted);
+        Error::new(ErrorKind::Other, d)
     }
 
     pub fn warn(&self, msg: &str, ln: usize, col: usize) {
-        let m = self.fmt(msg.to_string(), ln, col);
-        self.emit("warning", &m);
+        let d = self.diagnostic(msg, ln, col);
+        self.emit("warning", &d.formatted);
+        self.warnings.borrow_mut().push(d);
+    }
+
+    /// Return the warnings emitted so far.
+    pub fn warnings(&self) -> Vec<Diagnostic> {
+        self.warnings.borrow().clone()
     }
 
     pub fn emit(&self, typ: &str, msg: &str) {
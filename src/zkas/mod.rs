@@ -22,6 +22,7 @@
 
 /// Error emitter
 mod error;
+pub use error::Diagnostic;
 
 /// Constants
 pub mod constants;
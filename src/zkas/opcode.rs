@@ -101,6 +101,34 @@ pub enum Opcode {
 }
 
 impl Opcode {
+    /// All opcodes usable in zkas source, excluding [`Opcode::Noop`]
+    pub const ALL: [Self; 24] = [
+        Self::EcAdd,
+        Self::EcMul,
+        Self::EcMulBase,
+        Self::EcMulShort,
+        Self::EcMulVarBase,
+        Self::EcGetX,
+        Self::EcGetY,
+        Self::PoseidonHash,
+        Self::MerkleRoot,
+        Self::SparseMerkleRoot,
+        Self::BaseAdd,
+        Self::BaseMul,
+        Self::BaseSub,
+        Self::WitnessBase,
+        Self::RangeCheck,
+        Self::LessThanStrict,
+        Self::LessThanLoose,
+        Self::BoolCheck,
+        Self::CondSelect,
+        Self::ZeroCondSelect,
+        Self::ConstrainEqualBase,
+        Self::ConstrainEqualPoint,
+        Self::ConstrainInstance,
+        Self::DebugPrint,
+    ];
+
     pub fn from_name(n: &str) -> Option<Self> {
         match n {
             "ec_add" => Some(Self::EcAdd),
@@ -259,3 +287,21 @@ impl Opcode {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Opcode;
+
+    #[test]
+    fn opcode_all() {
+        // Every opcode with a binary representation must be listed
+        let opcodes: Vec<Opcode> = (0..=u8::MAX).filter_map(Opcode::from_repr).collect();
+        assert_eq!(opcodes.len(), Opcode::ALL.len());
+
+        for opcode in Opcode::ALL {
+            assert!(opcodes.contains(&opcode));
+            assert_eq!(Opcode::from_name(opcode.name()), Some(opcode));
+            assert_eq!(Opcode::from_repr(opcode as u8), Some(opcode));
+        }
+    }
+}
@@ -23,7 +23,7 @@ use std::{
 use super::{
     ast::{Arg, Constant, Literal, Statement, StatementType, Variable, Witness},
     constants::{ALLOWED_FIELDS, MAX_K, MAX_NS_LEN},
-    error::ErrorEmitter,
+    error::{Diagnostic, ErrorEmitter},
     lexer::{Token, TokenType},
     LitType, Opcode, VarType,
 };
@@ -102,6 +102,11 @@ impl Parser {
         Self { tokens, error }
     }
 
+    /// Return the warnings emitted while parsing.
+    pub fn warnings(&self) -> Vec<Diagnostic> {
+        self.error.warnings()
+    }
+
     pub fn parse(&self) -> Result<Parsed> {
         // We use these to keep state while parsing.
         let mut namespace = None;
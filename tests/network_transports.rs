@@ -16,19 +16,30 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
+use std::{cell::RefCell, collections::HashMap, rc::Rc, sync::Arc, time::Duration};
+
 use darkfi_serial::{AsyncDecodable, AsyncEncodable};
-use smol::{io, LocalExecutor};
+use smol::{
+    io::{self, AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    Executor, LocalExecutor, Timer,
+};
 use url::Url;
 
-use darkfi::net::transport::{Dialer, Listener};
+use darkfi::{
+    net::transport::{i2p, Dialer, Listener},
+    system::timeout::timeout,
+    util::encoding::{base32, base64},
+};
 
 #[test]
 fn tcp_transport() {
-    let executor = LocalExecutor::new();
+    let executor = Arc::new(Executor::new());
     let url = Url::parse("tcp://127.0.0.1:5432").unwrap();
 
     smol::block_on(executor.run(async {
-        let listener = Listener::new(url.clone(), None).await.unwrap().listen().await.unwrap();
+        let listener =
+            Listener::new(url.clone(), None).await.unwrap().listen(executor.clone()).await.unwrap();
         executor
             .spawn(async move {
                 let (stream, _) = listener.next().await.unwrap();
@@ -55,11 +66,12 @@ fn tcp_tls_transport() {
     use futures_rustls::rustls::crypto::{ring, CryptoProvider};
     let _ = CryptoProvider::install_default(ring::default_provider());
 
-    let executor = LocalExecutor::new();
+    let executor = Arc::new(Executor::new());
     let url = Url::parse("tcp+tls://127.0.0.1:5433").unwrap();
 
     smol::block_on(executor.run(async {
-        let listener = Listener::new(url.clone(), None).await.unwrap().listen().await.unwrap();
+        let listener =
+            Listener::new(url.clone(), None).await.unwrap().listen(executor.clone()).await.unwrap();
         executor
             .spawn(async move {
                 let (stream, _) = listener.next().await.unwrap();
@@ -82,7 +94,7 @@ fn tcp_tls_transport() {
 
 #[test]
 fn unix_transport() {
-    let executor = LocalExecutor::new();
+    let executor = Arc::new(Executor::new());
 
     let tmpdir = std::env::temp_dir();
     let url = Url::parse(&format!(
@@ -92,7 +104,8 @@ fn unix_transport() {
     .unwrap();
 
     smol::block_on(executor.run(async {
-        let listener = Listener::new(url.clone(), None).await.unwrap().listen().await.unwrap();
+        let listener =
+            Listener::new(url.clone(), None).await.unwrap().listen(executor.clone()).await.unwrap();
         executor
             .spawn(async move {
                 let (stream, _) = listener.next().await.unwrap();
@@ -112,3 +125,223 @@ fn unix_transport() {
         assert_eq!(buf, payload);
     }));
 }
+
+#[test]
+fn tcp_noise_transport() {
+    let executor = Arc::new(Executor::new());
+    let url = Url::parse("tcp+noise://127.0.0.1:5434").unwrap();
+
+    smol::block_on(executor.run(async {
+        let listener = Listener::new(url.clone(), None).await.unwrap();
+        let ptlistener = listener.listen(executor.clone()).await.unwrap();
+        executor
+            .spawn(async move {
+                loop {
+                    let Ok((stream, _)) = ptlistener.next().await else { continue };
+                    let (mut reader, mut writer) = smol::io::split(stream);
+                    let _ = io::copy(&mut reader, &mut writer).await;
+                }
+            })
+            .detach();
+
+        // The listener endpoint carries its static public key
+        let endpoint = listener.endpoint().await;
+        assert!(endpoint.fragment().is_some());
+
+        // A peer stalling its handshake must not hold up the others
+        let _stalled = TcpStream::connect("127.0.0.1:5434").await.unwrap();
+
+        let payload = "ohai noise";
+
+        let dialer = Dialer::new(endpoint, None).await.unwrap();
+        let mut client = timeout(Duration::from_secs(5), dialer.dial(None)).await.unwrap().unwrap();
+        payload.encode_async(&mut client).await.unwrap();
+
+        let buf: String = AsyncDecodable::decode_async(&mut client).await.unwrap();
+
+        assert_eq!(buf, payload);
+
+        // Dialing with a different pinned key must fail
+        let mut pinned = url.clone();
+        pinned.set_fragment(Some("11111111111111111111111111111111"));
+        let dialer = Dialer::new(pinned, None).await.unwrap();
+        assert!(dialer.dial(None).await.is_err());
+    }));
+}
+
+/// State of the mock SAM bridge
+#[derive(Default)]
+struct MockSam {
+    /// Public destinations of the sessions, by session ID
+    sessions: HashMap<String, String>,
+    /// Public destinations, by their `.b32.i2p` address
+    names: HashMap<String, String>,
+    /// Streams waiting in `STREAM ACCEPT`, by public destination
+    accepting: HashMap<String, Vec<TcpStream>>,
+}
+
+/// Length of the public part of the mock destinations
+const MOCK_DEST_LEN: usize = 516;
+
+fn mock_b32_address(destination: &str) -> String {
+    use sha2::{Digest, Sha256};
+    let bytes = base64::decode(&destination.replace('-', "+").replace('~', "/")).unwrap();
+    let hash = Sha256::digest(bytes);
+    format!("{}.b32.i2p", base32::encode(false, &hash).to_lowercase())
+}
+
+async fn mock_sam_read_line(stream: &mut TcpStream) -> String {
+    let mut line = vec![];
+    let mut byte = [0u8; 1];
+    while stream.read_exact(&mut byte).await.is_ok() && byte[0] != b'\n' {
+        line.push(byte[0]);
+    }
+    String::from_utf8(line).unwrap()
+}
+
+/// Serve a single connection to the mock SAM bridge. Destinations are
+/// random bytes, with the public part being a prefix of the private one.
+async fn mock_sam_serve(state: Rc<RefCell<MockSam>>, mut stream: TcpStream) {
+    let mut destination = String::new();
+
+    loop {
+        let line = mock_sam_read_line(&mut stream).await;
+        let mut tokens = line.split(' ');
+        let cmd = (tokens.next().unwrap_or_default(), tokens.next().unwrap_or_default());
+        let args: HashMap<&str, &str> = tokens.filter_map(|t| t.split_once('=')).collect();
+
+        let reply = match cmd {
+            ("HELLO", "VERSION") => "HELLO REPLY RESULT=OK VERSION=3.3".to_string(),
+
+            ("SESSION", "CREATE") => {
+                let private = match args["DESTINATION"] {
+                    "TRANSIENT" => {
+                        let bytes: Vec<u8> = (0..420).map(|_| rand::random()).collect();
+                        base64::encode(&bytes).replace('+', "-").replace('/', "~")
+                    }
+                    private => private.to_string(),
+                };
+
+                destination = private[..MOCK_DEST_LEN].to_string();
+                let mut state = state.borrow_mut();
+                state.sessions.insert(args["ID"].to_string(), destination.clone());
+                state.names.insert(mock_b32_address(&destination), destination.clone());
+                format!("SESSION STATUS RESULT=OK DESTINATION={}", private)
+            }
+
+            ("NAMING", "LOOKUP") => {
+                let value = match args["NAME"] {
+                    "ME" => Some(destination.clone()),
+                    name => state.borrow().names.get(name).cloned(),
+                };
+
+                match value {
+                    Some(v) => format!("NAMING REPLY RESULT=OK NAME={} VALUE={}", args["NAME"], v),
+                    None => format!("NAMING REPLY RESULT=KEY_NOT_FOUND NAME={}", args["NAME"]),
+                }
+            }
+
+            ("STREAM", "ACCEPT") => {
+                stream.write_all(b"STREAM STATUS RESULT=OK\n").await.unwrap();
+                let dest = state.borrow().sessions[args["ID"]].clone();
+                state.borrow_mut().accepting.entry(dest).or_default().push(stream);
+                return
+            }
+
+            ("STREAM", "CONNECT") => {
+                let peer = loop {
+                    let peer = state
+                        .borrow_mut()
+                        .accepting
+                        .get_mut(args["DESTINATION"])
+                        .and_then(|streams| streams.pop());
+                    match peer {
+                        Some(peer) => break peer,
+                        None => Timer::after(Duration::from_millis(10)).await,
+                    };
+                };
+
+                let from = state.borrow().sessions[args["ID"]].clone();
+                let header = format!("{} FROM_PORT=0 TO_PORT={}\n", from, args["TO_PORT"]);
+                let mut peer_ = peer.clone();
+                peer_.write_all(header.as_bytes()).await.unwrap();
+                stream.write_all(b"STREAM STATUS RESULT=OK\n").await.unwrap();
+
+                let mut stream_ = stream.clone();
+                let _ =
+                    smol::future::zip(io::copy(stream, &mut peer_), io::copy(peer, &mut stream_))
+                        .await;
+                return
+            }
+
+            _ => return,
+        };
+
+        stream.write_all(format!("{}\n", reply).as_bytes()).await.unwrap();
+    }
+}
+
+/// Start a mock SAM bridge on its own thread and return its address
+fn mock_sam_bridge() -> Url {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = Url::parse(&format!("tcp://{}", listener.local_addr().unwrap())).unwrap();
+
+    std::thread::spawn(move || {
+        let executor = LocalExecutor::new();
+        let state = Rc::new(RefCell::new(MockSam::default()));
+
+        smol::block_on(executor.run(async {
+            let listener = TcpListener::try_from(listener).unwrap();
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                executor.spawn(mock_sam_serve(state.clone(), stream)).detach();
+            }
+        }))
+    });
+
+    url
+}
+
+#[test]
+fn i2p_transport() {
+    let executor = Arc::new(Executor::new());
+    i2p::set_sam_bridge(mock_sam_bridge());
+
+    let datastore = std::env::temp_dir().join("darkfi_i2p_transport");
+    let _ = std::fs::remove_dir_all(&datastore);
+    let datastore = datastore.to_str().unwrap().to_string();
+
+    smol::block_on(executor.run(async {
+        let url = Url::parse("i2p://127.0.0.1:5435").unwrap();
+        let listener = Listener::new(url, Some(datastore.clone())).await.unwrap();
+        let ptlistener = listener.listen(executor.clone()).await.unwrap();
+        executor
+            .spawn(async move {
+                let (stream, _) = ptlistener.next().await.unwrap();
+                let (mut reader, mut writer) = smol::io::split(stream);
+                io::copy(&mut reader, &mut writer).await.unwrap();
+            })
+            .detach();
+
+        // The listener endpoint is our destination, which has to be
+        // stored in the datastore.
+        let endpoint = listener.endpoint().await;
+        assert_eq!(endpoint.port(), Some(5435));
+
+        let stored = std::fs::read_to_string(format!("{}/i2p_destination.key", datastore)).unwrap();
+        let b32 = mock_b32_address(&stored[..MOCK_DEST_LEN]);
+        assert_eq!(endpoint.host_str(), Some(b32.as_str()));
+
+        let payload = "ohai i2p";
+
+        let dialer = Dialer::new(endpoint, None).await.unwrap();
+        let mut client = dialer.dial(None).await.unwrap();
+        payload.encode_async(&mut client).await.unwrap();
+
+        let buf: String = AsyncDecodable::decode_async(&mut client).await.unwrap();
+
+        assert_eq!(buf, payload);
+    }));
+
+    let _ = std::fs::remove_dir_all(&datastore);
+}
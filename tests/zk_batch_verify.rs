@@ -0,0 +1,131 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2025 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use halo2_proofs::{
+    arithmetic::Field,
+    circuit::Value,
+    pasta::{pallas, Fp},
+};
+use rand::rngs::OsRng;
+use std::collections::HashMap;
+
+use darkfi::{
+    tx::{Transaction, ZkProofBatch},
+    zk::{
+        proof::{ProvingKey, VerifyingKey},
+        vm::ZkCircuit,
+        vm_heap::{empty_witnesses, Witness},
+        Proof,
+    },
+    zkas::ZkBinary,
+    Result,
+};
+use darkfi_sdk::{crypto::contract_id::MONEY_CONTRACT_ID, dark_tree::DarkLeaf, tx::ContractCall};
+
+#[test]
+fn zk_batch_verify() -> Result<()> {
+    const PROOFS: usize = 5;
+
+    let bincode = include_bytes!("../proof/arithmetic.zk.bin");
+    let zkbin = ZkBinary::decode(bincode)?;
+
+    let circuit = ZkCircuit::new(empty_witnesses(&zkbin)?, &zkbin);
+    let proving_key = ProvingKey::build(zkbin.k, &circuit);
+    let verifying_key = VerifyingKey::build(zkbin.k, &circuit);
+
+    // Create a set of valid proofs
+    let mut proofs = Vec::with_capacity(PROOFS);
+    for _ in 0..PROOFS {
+        let a = pallas::Base::random(&mut OsRng);
+        let b = pallas::Base::random(&mut OsRng);
+        let prover_witnesses = vec![Witness::Base(Value::known(a)), Witness::Base(Value::known(b))];
+        let public_inputs: Vec<Fp> = vec![a + b, a * b, a - b];
+
+        let circuit = ZkCircuit::new(prover_witnesses, &zkbin);
+        let proof = Proof::create(&proving_key, &[circuit], &public_inputs, &mut OsRng)?;
+        proof.verify(&verifying_key, &public_inputs)?;
+        proofs.push((proof, public_inputs));
+    }
+
+    // The whole set must pass batch verification
+    assert!(Proof::verify_batch(&verifying_key, proofs.iter().map(|(p, i)| (p, i.as_slice()))));
+
+    // Tampering with a single public input must fail the whole batch
+    proofs[2].1[0] += pallas::Base::ONE;
+    assert!(!Proof::verify_batch(&verifying_key, proofs.iter().map(|(p, i)| (p, i.as_slice()))));
+
+    // While single verification pinpoints the bad proof
+    for (i, (proof, public_inputs)) in proofs.iter().enumerate() {
+        assert_eq!(proof.verify(&verifying_key, public_inputs).is_ok(), i != 2);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn zk_batch_verify_fallback() -> Result<()> {
+    const TXS: usize = 4;
+    const BAD_TX: usize = 1;
+
+    let bincode = include_bytes!("../proof/arithmetic.zk.bin");
+    let zkbin = ZkBinary::decode(bincode)?;
+
+    let circuit = ZkCircuit::new(empty_witnesses(&zkbin)?, &zkbin);
+    let proving_key = ProvingKey::build(zkbin.k, &circuit);
+    let verifying_key = VerifyingKey::build(zkbin.k, &circuit);
+
+    let mut verifying_keys = HashMap::new();
+    verifying_keys.insert(
+        MONEY_CONTRACT_ID.to_bytes(),
+        HashMap::from([(zkbin.namespace.clone(), verifying_key)]),
+    );
+
+    // Batch a single-proof transaction per index, one of them with
+    // a tampered public input
+    let mut batch = ZkProofBatch::default();
+    for index in 0..TXS {
+        let a = pallas::Base::random(&mut OsRng);
+        let b = pallas::Base::random(&mut OsRng);
+        let prover_witnesses = vec![Witness::Base(Value::known(a)), Witness::Base(Value::known(b))];
+        let mut public_inputs: Vec<Fp> = vec![a + b, a * b, a - b];
+
+        let circuit = ZkCircuit::new(prover_witnesses, &zkbin);
+        let proof = Proof::create(&proving_key, &[circuit], &public_inputs, &mut OsRng)?;
+
+        if index == BAD_TX {
+            public_inputs[0] += pallas::Base::ONE;
+        }
+
+        let tx = Transaction {
+            calls: vec![DarkLeaf {
+                data: ContractCall { contract_id: *MONEY_CONTRACT_ID, data: vec![] },
+                parent_index: None,
+                children_indexes: vec![],
+            }],
+            proofs: vec![vec![proof]],
+            ..Default::default()
+        };
+        let zkp_table = vec![vec![(zkbin.namespace.clone(), public_inputs)]];
+        tx.batch_zkps(index, &verifying_keys, zkp_table, &mut batch)?;
+    }
+
+    // The batch fails, and the fallback pinpoints the bad transaction
+    assert_eq!(batch.verify(&verifying_keys), vec![BAD_TX]);
+
+    Ok(())
+}